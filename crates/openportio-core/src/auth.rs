@@ -1,12 +1,40 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-#[derive(Debug, Clone)]
+/// Default clock-skew allowance applied to `exp`, `nbf` and `iat`.
+pub const DEFAULT_JWT_LEEWAY_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Default)]
 pub struct JwtValidationConfig {
     pub secret: String,
     pub expected_issuer: Option<String>,
     pub expected_audience: Option<String>,
+    pub time: JwtTimeValidation,
+}
+
+/// How the time-based claims are checked. `exp` is always required; `nbf` and `iat` are only
+/// checked when present in the token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JwtTimeValidation {
+    /// Seconds of clock skew tolerated between the issuer and this server.
+    pub leeway_secs: u64,
+    /// Reject tokens whose `nbf` is later than now plus the leeway.
+    pub validate_nbf: bool,
+    /// Reject tokens whose `iat` is later than now plus the leeway.
+    pub validate_iat: bool,
+}
+
+impl Default for JwtTimeValidation {
+    fn default() -> Self {
+        Self {
+            leeway_secs: DEFAULT_JWT_LEEWAY_SECS,
+            validate_nbf: true,
+            validate_iat: false,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,18 +43,32 @@ pub struct AuthPrincipal {
     pub issuer: Option<String>,
     pub audience: Vec<String>,
     pub scopes: Vec<String>,
+    /// `jti` claim, used for revocation checks.
+    #[serde(default)]
+    pub token_id: Option<String>,
+    /// `tenant_id` (or `tid`) claim, used for tenant resolution.
+    #[serde(default)]
+    pub tenant_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JwtClaims {
     pub sub: String,
     pub exp: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iat: Option<usize>,
     #[serde(default)]
     pub iss: Option<String>,
     #[serde(default)]
     pub aud: Option<AudienceClaim>,
     #[serde(default)]
     pub scope: Option<String>,
+    #[serde(default)]
+    pub jti: Option<String>,
+    #[serde(default, alias = "tid", skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,6 +91,12 @@ impl AudienceClaim {
 pub enum AuthError {
     #[error("invalid token: {0}")]
     InvalidToken(String),
+    #[error("token has expired")]
+    Expired,
+    #[error("token is not valid yet (nbf)")]
+    NotYetValid,
+    #[error("token was issued in the future (iat)")]
+    IssuedInFuture,
     #[error("issuer mismatch")]
     IssuerMismatch,
     #[error("audience mismatch")]
@@ -58,14 +106,26 @@ pub enum AuthError {
 pub fn validate_bearer_jwt(
     token: &str,
     cfg: &JwtValidationConfig,
+) -> Result<AuthPrincipal, AuthError> {
+    validate_bearer_jwt_at(token, cfg, SystemTime::now())
+}
+
+/// Like [`validate_bearer_jwt`], checking the time claims against `now` instead of the system
+/// clock.
+pub fn validate_bearer_jwt_at(
+    token: &str,
+    cfg: &JwtValidationConfig,
+    now: SystemTime,
 ) -> Result<AuthPrincipal, AuthError> {
     let decoding_key = DecodingKey::from_secret(cfg.secret.as_bytes());
-    validate_bearer_jwt_with_key(
+    validate_bearer_jwt_with_key_at(
         token,
         &decoding_key,
         Algorithm::HS256,
         cfg.expected_issuer.as_deref(),
         cfg.expected_audience.as_deref(),
+        cfg.time,
+        now,
     )
 }
 
@@ -75,9 +135,31 @@ pub fn validate_bearer_jwt_with_key(
     algorithm: Algorithm,
     expected_issuer: Option<&str>,
     expected_audience: Option<&str>,
+) -> Result<AuthPrincipal, AuthError> {
+    validate_bearer_jwt_with_key_at(
+        token,
+        decoding_key,
+        algorithm,
+        expected_issuer,
+        expected_audience,
+        JwtTimeValidation::default(),
+        SystemTime::now(),
+    )
+}
+
+pub fn validate_bearer_jwt_with_key_at(
+    token: &str,
+    decoding_key: &DecodingKey,
+    algorithm: Algorithm,
+    expected_issuer: Option<&str>,
+    expected_audience: Option<&str>,
+    time: JwtTimeValidation,
+    now: SystemTime,
 ) -> Result<AuthPrincipal, AuthError> {
     let mut validation = Validation::new(algorithm);
-    validation.validate_exp = true;
+    // Time claims are checked below against `now` so callers can supply their own clock.
+    validation.validate_exp = false;
+    validation.validate_nbf = false;
     validation.validate_aud = false;
     validation
         .required_spec_claims
@@ -87,6 +169,7 @@ pub fn validate_bearer_jwt_with_key(
         .map_err(|err| AuthError::InvalidToken(err.to_string()))?;
 
     let claims = token_data.claims;
+    check_time_claims(&claims, time, now)?;
     if let Some(expected) = expected_issuer {
         if claims.iss.as_deref() != Some(expected) {
             return Err(AuthError::IssuerMismatch);
@@ -112,11 +195,37 @@ pub fn validate_bearer_jwt_with_key(
         issuer: claims.iss,
         audience,
         scopes,
+        token_id: claims.jti,
+        tenant_id: claims.tenant_id,
     })
 }
 
+fn check_time_claims(
+    claims: &JwtClaims,
+    time: JwtTimeValidation,
+    now: SystemTime,
+) -> Result<(), AuthError> {
+    let now = now
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let skewed_now = now.saturating_add(time.leeway_secs);
+    if (claims.exp as u64).saturating_add(time.leeway_secs) < now {
+        return Err(AuthError::Expired);
+    }
+    if time.validate_nbf && claims.nbf.is_some_and(|nbf| nbf as u64 > skewed_now) {
+        return Err(AuthError::NotYetValid);
+    }
+    if time.validate_iat && claims.iat.is_some_and(|iat| iat as u64 > skewed_now) {
+        return Err(AuthError::IssuedInFuture);
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use super::*;
     use jsonwebtoken::{encode, EncodingKey, Header};
 
@@ -133,9 +242,13 @@ mod tests {
         JwtClaims {
             sub: "user-1".to_string(),
             exp: 4_102_444_800,
+            nbf: None,
+            iat: None,
             iss: Some("https://issuer.local".to_string()),
             aud: Some(AudienceClaim::One("openportio-api".to_string())),
             scope: Some("read:notes write:notes".to_string()),
+            jti: None,
+            tenant_id: None,
         }
     }
 
@@ -147,6 +260,7 @@ mod tests {
             secret: secret.to_string(),
             expected_issuer: Some("https://issuer.local".to_string()),
             expected_audience: Some("openportio-api".to_string()),
+            ..Default::default()
         };
 
         let principal = validate_bearer_jwt(&token, &cfg).expect("token should validate");
@@ -156,6 +270,41 @@ mod tests {
         assert!(principal.scopes.iter().any(|scope| scope == "read:notes"));
     }
 
+    #[test]
+    fn maps_tenant_claim_and_tid_alias() {
+        let secret = "dev-secret";
+        let cfg = JwtValidationConfig {
+            secret: secret.to_string(),
+            expected_issuer: None,
+            expected_audience: None,
+            ..Default::default()
+        };
+        let mut claims = base_claims();
+        claims.tenant_id = Some("acme".to_string());
+        let principal = validate_bearer_jwt(&issue_token(secret, &claims), &cfg)
+            .expect("token should validate");
+        assert_eq!(principal.tenant_id.as_deref(), Some("acme"));
+
+        #[derive(Serialize)]
+        struct TidClaims {
+            sub: &'static str,
+            exp: usize,
+            tid: &'static str,
+        }
+        let token = encode(
+            &Header::new(Algorithm::HS256),
+            &TidClaims {
+                sub: "user-1",
+                exp: 4_102_444_800,
+                tid: "globex",
+            },
+            &EncodingKey::from_secret(secret.as_bytes()),
+        )
+        .expect("token should encode");
+        let principal = validate_bearer_jwt(&token, &cfg).expect("token should validate");
+        assert_eq!(principal.tenant_id.as_deref(), Some("globex"));
+    }
+
     #[test]
     fn rejects_issuer_mismatch() {
         let secret = "dev-secret";
@@ -164,6 +313,7 @@ mod tests {
             secret: secret.to_string(),
             expected_issuer: Some("https://other-issuer.local".to_string()),
             expected_audience: None,
+            ..Default::default()
         };
 
         let err = validate_bearer_jwt(&token, &cfg).expect_err("issuer mismatch should fail");
@@ -178,9 +328,62 @@ mod tests {
             secret: secret.to_string(),
             expected_issuer: None,
             expected_audience: Some("other-aud".to_string()),
+            ..Default::default()
         };
 
         let err = validate_bearer_jwt(&token, &cfg).expect_err("audience mismatch should fail");
         assert!(matches!(err, AuthError::AudienceMismatch));
     }
+
+    #[test]
+    fn expiry_is_checked_against_the_supplied_time_with_leeway() {
+        let secret = "dev-secret";
+        let cfg = JwtValidationConfig {
+            secret: secret.to_string(),
+            expected_issuer: None,
+            expected_audience: None,
+            ..Default::default()
+        };
+        let mut claims = base_claims();
+        claims.exp = 1_000;
+        let token = issue_token(secret, &claims);
+
+        validate_bearer_jwt_at(&token, &cfg, UNIX_EPOCH + Duration::from_secs(1_060))
+            .expect("token is within the expiry leeway");
+        let err = validate_bearer_jwt_at(&token, &cfg, UNIX_EPOCH + Duration::from_secs(1_061))
+            .expect_err("token past exp plus leeway should fail");
+        assert!(matches!(err, AuthError::Expired));
+        assert!(validate_bearer_jwt(&token, &cfg).is_err());
+    }
+
+    #[test]
+    fn nbf_and_iat_tolerate_configured_skew() {
+        let secret = "dev-secret";
+        let mut cfg = JwtValidationConfig {
+            secret: secret.to_string(),
+            time: JwtTimeValidation {
+                leeway_secs: 30,
+                validate_nbf: true,
+                validate_iat: true,
+            },
+            ..Default::default()
+        };
+        let mut claims = base_claims();
+        claims.nbf = Some(1_000);
+        claims.iat = Some(1_000);
+        let token = issue_token(secret, &claims);
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+
+        validate_bearer_jwt_at(&token, &cfg, at(970)).expect("issuer clock 30s ahead is tolerated");
+        let err = validate_bearer_jwt_at(&token, &cfg, at(969)).expect_err("nbf beyond leeway");
+        assert!(matches!(err, AuthError::NotYetValid));
+
+        cfg.time.validate_nbf = false;
+        let err = validate_bearer_jwt_at(&token, &cfg, at(969)).expect_err("iat beyond leeway");
+        assert!(matches!(err, AuthError::IssuedInFuture));
+        assert_eq!(err.to_string(), "token was issued in the future (iat)");
+
+        cfg.time.validate_iat = false;
+        validate_bearer_jwt_at(&token, &cfg, at(0)).expect("time checks disabled");
+    }
 }
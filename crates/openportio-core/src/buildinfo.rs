@@ -0,0 +1,189 @@
+use std::{
+    env,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+pub const GIT_SHA_ENV: &str = "OPENPORTIO_BUILD_GIT_SHA";
+pub const TIMESTAMP_ENV: &str = "OPENPORTIO_BUILD_TIMESTAMP";
+pub const RUSTC_VERSION_ENV: &str = "OPENPORTIO_BUILD_RUSTC_VERSION";
+pub const FEATURES_ENV: &str = "OPENPORTIO_BUILD_FEATURES";
+
+/// What was built and from where, captured at compile time by [`crate::openportio_buildinfo!`].
+/// Fields a build script did not provide (see [`emit`]) stay `None`/empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildInfo {
+    pub name: String,
+    pub version: String,
+    pub git_sha: Option<String>,
+    /// RFC 3339 UTC timestamp; honours `SOURCE_DATE_EPOCH` for reproducible builds.
+    pub build_timestamp: Option<String>,
+    pub rustc_version: Option<String>,
+    pub features: Vec<String>,
+}
+
+impl BuildInfo {
+    /// Used by the macro; `features` is the comma-separated list written by [`emit`].
+    pub fn from_parts(
+        name: &str,
+        version: &str,
+        git_sha: Option<&str>,
+        build_timestamp: Option<&str>,
+        rustc_version: Option<&str>,
+        features: Option<&str>,
+    ) -> Self {
+        let non_empty = |value: Option<&str>| {
+            value
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        Self {
+            name: name.to_string(),
+            version: version.to_string(),
+            git_sha: non_empty(git_sha),
+            build_timestamp: non_empty(build_timestamp),
+            rustc_version: non_empty(rustc_version),
+            features: features
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|feature| !feature.is_empty())
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+
+    /// First 12 characters of the git SHA, for log lines and banners.
+    pub fn short_sha(&self) -> Option<&str> {
+        self.git_sha
+            .as_deref()
+            .map(|sha| sha.get(..12).unwrap_or(sha))
+    }
+}
+
+/// Captures the calling crate's [`BuildInfo`]. Call [`emit`] from that crate's `build.rs`
+/// (with `openportio-core` as a build dependency) to fill in git, timestamp, rustc, and features.
+#[macro_export]
+macro_rules! openportio_buildinfo {
+    () => {
+        $crate::buildinfo::BuildInfo::from_parts(
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION"),
+            option_env!("OPENPORTIO_BUILD_GIT_SHA"),
+            option_env!("OPENPORTIO_BUILD_TIMESTAMP"),
+            option_env!("OPENPORTIO_BUILD_RUSTC_VERSION"),
+            option_env!("OPENPORTIO_BUILD_FEATURES"),
+        )
+    };
+}
+
+#[macro_export]
+macro_rules! meld_buildinfo {
+    () => {
+        $crate::openportio_buildinfo!()
+    };
+}
+
+#[macro_export]
+macro_rules! alloy_buildinfo {
+    () => {
+        $crate::openportio_buildinfo!()
+    };
+}
+
+/// Build-script half of [`crate::openportio_buildinfo!`]: prints the `cargo:rustc-env` lines the
+/// macro reads. Missing tools (no git checkout, no `$RUSTC`) leave the matching field empty.
+pub fn emit() {
+    if let Some(sha) = command_output("git", &["rev-parse", "HEAD"]) {
+        println!("cargo:rustc-env={GIT_SHA_ENV}={sha}");
+    }
+    if let Some(git_dir) = command_output("git", &["rev-parse", "--absolute-git-dir"]) {
+        println!("cargo:rerun-if-changed={git_dir}/HEAD");
+        println!("cargo:rerun-if-changed={git_dir}/refs");
+    }
+
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    let epoch = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default()
+        });
+    println!("cargo:rustc-env={TIMESTAMP_ENV}={}", rfc3339(epoch));
+
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    if let Some(version) = command_output(&rustc, &["--version"]) {
+        println!("cargo:rustc-env={RUSTC_VERSION_ENV}={version}");
+    }
+
+    let mut features: Vec<_> = env::vars()
+        .filter_map(|(key, _)| {
+            key.strip_prefix("CARGO_FEATURE_")
+                .map(|feature| feature.to_ascii_lowercase().replace('_', "-"))
+        })
+        .collect();
+    features.sort_unstable();
+    println!("cargo:rustc-env={FEATURES_ENV}={}", features.join(","));
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    Some(stdout.trim().to_string()).filter(|value| !value.is_empty())
+}
+
+fn rfc3339(epoch: u64) -> String {
+    let days = (epoch / 86_400) as i64;
+    let seconds = epoch % 86_400;
+    // Howard Hinnant's civil_from_days.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        seconds / 3_600,
+        seconds % 3_600 / 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn macro_captures_package_metadata_and_parts_are_normalized() {
+        let info = crate::meld_buildinfo!();
+        assert_eq!(info.name, "openportio-core");
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+
+        let info = BuildInfo::from_parts(
+            "notes",
+            "1.2.3",
+            Some("0123456789abcdef0123"),
+            Some(""),
+            Some("rustc 1.80.0"),
+            Some("opa, ,metrics"),
+        );
+        assert_eq!(info.short_sha(), Some("0123456789ab"));
+        assert_eq!(info.build_timestamp, None);
+        assert_eq!(info.features, ["opa", "metrics"]);
+        assert_eq!(rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc3339(1_709_210_096), "2024-02-29T12:34:56Z");
+    }
+}
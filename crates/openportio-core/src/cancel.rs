@@ -0,0 +1,65 @@
+use std::future::Future;
+
+pub use tokio_util::sync::CancellationToken;
+
+use crate::{OpenportioError, OpenportioResult};
+
+/// Runs `future` until it completes or `token` is cancelled, whichever happens first; on
+/// cancellation the future is dropped and [`OpenportioError::Cancelled`] returned. In handlers
+/// pass `ctx.cancellation()` so work stops once the client disconnects.
+pub async fn select_with_cancel<F: Future>(
+    token: &CancellationToken,
+    future: F,
+) -> OpenportioResult<F::Output> {
+    tokio::select! {
+        biased;
+        _ = token.cancelled() => Err(OpenportioError::Cancelled),
+        output = future => Ok(output),
+    }
+}
+
+/// [`select_with_cancel`] for futures that already return an [`OpenportioResult`].
+pub async fn try_select_with_cancel<T, F>(
+    token: &CancellationToken,
+    future: F,
+) -> OpenportioResult<T>
+where
+    F: Future<Output = OpenportioResult<T>>,
+{
+    select_with_cancel(token, future).await?
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn cancellation_wins_over_pending_work() {
+        let token = CancellationToken::new();
+        assert_eq!(select_with_cancel(&token, async { 7 }).await.unwrap(), 7);
+
+        let child = token.child_token();
+        let canceller = tokio::spawn({
+            let token = token.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                token.cancel();
+            }
+        });
+        let slow = try_select_with_cancel(&child, async {
+            tokio::time::sleep(Duration::from_secs(30)).await;
+            Ok(())
+        });
+        assert!(matches!(slow.await, Err(OpenportioError::Cancelled)));
+        canceller.await.unwrap();
+
+        let failed: OpenportioResult<()> =
+            try_select_with_cancel(&CancellationToken::new(), async {
+                Err(OpenportioError::Unavailable("down".to_string()))
+            })
+            .await;
+        assert!(matches!(failed, Err(OpenportioError::Unavailable(_))));
+    }
+}
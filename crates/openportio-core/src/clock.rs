@@ -0,0 +1,151 @@
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use tokio::sync::watch;
+
+pub type Sleep = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+/// Time source for framework code that measures or waits: rate limit windows, JWKS refresh,
+/// token expiry and SSE heartbeats. Register a [`TestClock`] as a dependency to drive them
+/// from tests without sleeping.
+pub trait Clock: fmt::Debug + Send + Sync + 'static {
+    /// Monotonic time, for intervals and windows.
+    fn now(&self) -> Instant;
+
+    /// Wall-clock time, for token expiry.
+    fn system_time(&self) -> SystemTime;
+
+    fn sleep(&self, duration: Duration) -> Sleep;
+
+    fn unix_seconds(&self) -> u64 {
+        self.system_time()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default()
+    }
+}
+
+pub type SharedClock = Arc<dyn Clock>;
+
+pub fn system_clock() -> SharedClock {
+    Arc::new(SystemClock)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// A clock that only moves when [`TestClock::advance`] is called. Clones share the same time,
+/// and pending [`Clock::sleep`] futures complete once the clock is advanced past their deadline.
+#[derive(Clone)]
+pub struct TestClock {
+    instant: Instant,
+    system_time: SystemTime,
+    elapsed: Arc<watch::Sender<Duration>>,
+}
+
+impl Default for TestClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestClock {
+    /// Starts at the current time.
+    pub fn new() -> Self {
+        Self::at(SystemTime::now())
+    }
+
+    /// Starts with `system_time` as the wall-clock time.
+    pub fn at(system_time: SystemTime) -> Self {
+        Self {
+            instant: Instant::now(),
+            system_time,
+            elapsed: Arc::new(watch::channel(Duration::ZERO).0),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.elapsed.send_modify(|elapsed| *elapsed += duration);
+    }
+
+    /// Total time advanced since creation.
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.borrow()
+    }
+
+    pub fn shared(&self) -> SharedClock {
+        Arc::new(self.clone())
+    }
+}
+
+impl Clock for TestClock {
+    fn now(&self) -> Instant {
+        self.instant + self.elapsed()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        self.system_time + self.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        let mut elapsed = self.elapsed.subscribe();
+        let deadline = *elapsed.borrow_and_update() + duration;
+        Box::pin(async move {
+            while *elapsed.borrow_and_update() < deadline {
+                if elapsed.changed().await.is_err() {
+                    std::future::pending::<()>().await;
+                }
+            }
+        })
+    }
+}
+
+impl fmt::Debug for TestClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TestClock")
+            .field("system_time", &self.system_time())
+            .field("elapsed", &self.elapsed())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_clock_moves_only_when_advanced_and_wakes_sleepers() {
+        let clock = TestClock::at(UNIX_EPOCH + Duration::from_secs(1_000));
+        let started = clock.now();
+        assert_eq!(clock.unix_seconds(), 1_000);
+
+        let sleeper = tokio::spawn(clock.sleep(Duration::from_secs(30)));
+        clock.advance(Duration::from_secs(29));
+        tokio::task::yield_now().await;
+        assert!(!sleeper.is_finished());
+
+        clock.advance(Duration::from_secs(1));
+        sleeper.await.unwrap();
+        assert_eq!(clock.now() - started, Duration::from_secs(30));
+        assert_eq!(clock.shared().unix_seconds(), 1_030);
+    }
+}
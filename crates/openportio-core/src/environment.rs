@@ -0,0 +1,143 @@
+use std::{convert::Infallible, fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+const ENV_VAR: &str = "OPENPORTIO_ENV";
+
+/// Where the service runs. Drives defaults that differ between a laptop and production:
+/// docs endpoints, error detail in responses, and the default log filter.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum Environment {
+    #[default]
+    Local,
+    Dev,
+    Staging,
+    Production,
+    /// Any other name, kept verbatim (`test`, `qa`, `eu-canary`, ...).
+    Custom(String),
+}
+
+impl Environment {
+    /// Reads `OPENPORTIO_ENV`; unset means [`Environment::Local`].
+    pub fn from_env() -> Self {
+        std::env::var(ENV_VAR)
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+            .map(|value| value.parse().unwrap_or_default())
+            .unwrap_or_default()
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Local => "local",
+            Self::Dev => "dev",
+            Self::Staging => "staging",
+            Self::Production => "production",
+            Self::Custom(name) => name,
+        }
+    }
+
+    pub fn is_production(&self) -> bool {
+        matches!(self, Self::Production)
+    }
+
+    /// `local`, `dev`, and the custom name `test`.
+    pub fn is_local(&self) -> bool {
+        match self {
+            Self::Local | Self::Dev => true,
+            Self::Custom(name) => name.eq_ignore_ascii_case("test"),
+            Self::Staging | Self::Production => false,
+        }
+    }
+
+    /// Whether internal error details (such as panic messages) may be sent to clients.
+    pub fn exposes_error_details(&self) -> bool {
+        self.is_local()
+    }
+
+    /// Whether `/docs` and `/openapi.json` are served unless the builder says otherwise.
+    pub fn serves_docs_by_default(&self) -> bool {
+        !self.is_production()
+    }
+
+    /// `tracing` filter used when `RUST_LOG` is unset.
+    pub fn default_log_filter(&self) -> &'static str {
+        if self.is_local() {
+            "debug"
+        } else {
+            "info"
+        }
+    }
+}
+
+impl FromStr for Environment {
+    type Err = Infallible;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        Ok(match value.to_ascii_lowercase().as_str() {
+            "local" => Self::Local,
+            "dev" | "development" => Self::Dev,
+            "staging" | "stage" => Self::Staging,
+            "prod" | "production" => Self::Production,
+            _ => Self::Custom(value.to_string()),
+        })
+    }
+}
+
+impl From<&str> for Environment {
+    fn from(value: &str) -> Self {
+        value.parse().unwrap_or_default()
+    }
+}
+
+impl From<String> for Environment {
+    fn from(value: String) -> Self {
+        value.as_str().into()
+    }
+}
+
+impl From<Environment> for String {
+    fn from(value: Environment) -> Self {
+        value.as_str().to_string()
+    }
+}
+
+impl fmt::Display for Environment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_parse_to_variants_and_unknown_names_stay_custom() {
+        assert_eq!(Environment::from("Development"), Environment::Dev);
+        assert_eq!(Environment::from("prod"), Environment::Production);
+        assert_eq!(Environment::from(" staging "), Environment::Staging);
+        assert_eq!(
+            Environment::from("eu-canary"),
+            Environment::Custom("eu-canary".to_string())
+        );
+        assert_eq!(Environment::Production.to_string(), "production");
+        assert_eq!(
+            serde_json::to_value(Environment::Dev).unwrap(),
+            serde_json::json!("dev")
+        );
+    }
+
+    #[test]
+    fn behavior_switches_follow_the_environment() {
+        assert!(Environment::Local.exposes_error_details());
+        assert!(Environment::from("test").is_local());
+        assert!(!Environment::Staging.exposes_error_details());
+        assert!(Environment::Staging.serves_docs_by_default());
+        assert!(!Environment::Production.serves_docs_by_default());
+        assert_eq!(Environment::Dev.default_log_filter(), "debug");
+        assert_eq!(Environment::Production.default_log_filter(), "info");
+    }
+}
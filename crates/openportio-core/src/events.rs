@@ -0,0 +1,191 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+const DEFAULT_TOPIC_CAPACITY: usize = 256;
+
+/// Names an event-bus topic. Implement it for an application enum (`Topic::Notes`) or use
+/// string topics directly.
+pub trait EventTopic {
+    fn topic_name(&self) -> Cow<'static, str>;
+}
+
+impl EventTopic for &'static str {
+    fn topic_name(&self) -> Cow<'static, str> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl EventTopic for String {
+    fn topic_name(&self) -> Cow<'static, str> {
+        Cow::Owned(self.clone())
+    }
+}
+
+/// A published event; `data` is the payload serialized to JSON at publish time.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BusEvent {
+    /// Monotonic per bus, usable as an SSE `id`.
+    pub id: u64,
+    pub topic: String,
+    pub kind: String,
+    pub data: serde_json::Value,
+}
+
+/// In-process publish/subscribe over per-topic broadcast channels. Slow subscribers lose the
+/// oldest events once `capacity` are queued; publishing never blocks.
+#[derive(Clone)]
+pub struct EventBus {
+    inner: Arc<EventBusInner>,
+}
+
+struct EventBusInner {
+    channels: RwLock<HashMap<String, broadcast::Sender<BusEvent>>>,
+    capacity: usize,
+    next_id: AtomicU64,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_TOPIC_CAPACITY)
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Events buffered per topic for each subscriber.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(EventBusInner {
+                channels: RwLock::default(),
+                capacity: capacity.max(1),
+                next_id: AtomicU64::new(1),
+            }),
+        }
+    }
+
+    /// Serializes `payload` and delivers it to the topic's current subscribers, returning how
+    /// many received it.
+    pub fn publish<T>(
+        &self,
+        topic: impl EventTopic,
+        kind: impl Into<String>,
+        payload: &T,
+    ) -> Result<usize, serde_json::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        let topic = topic.topic_name();
+        let event = BusEvent {
+            id: self.inner.next_id.fetch_add(1, Ordering::Relaxed),
+            topic: topic.to_string(),
+            kind: kind.into(),
+            data: serde_json::to_value(payload)?,
+        };
+        let channels = self
+            .inner
+            .channels
+            .read()
+            .unwrap_or_else(|err| err.into_inner());
+        Ok(channels
+            .get(topic.as_ref())
+            .and_then(|sender| sender.send(event).ok())
+            .unwrap_or_default())
+    }
+
+    pub fn subscribe(&self, topic: impl EventTopic) -> broadcast::Receiver<BusEvent> {
+        let topic = topic.topic_name();
+        if let Some(sender) = self
+            .inner
+            .channels
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .get(topic.as_ref())
+        {
+            return sender.subscribe();
+        }
+        self.inner
+            .channels
+            .write()
+            .unwrap_or_else(|err| err.into_inner())
+            .entry(topic.into_owned())
+            .or_insert_with(|| broadcast::channel(self.inner.capacity).0)
+            .subscribe()
+    }
+
+    pub fn subscriber_count(&self, topic: impl EventTopic) -> usize {
+        self.inner
+            .channels
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .get(topic.topic_name().as_ref())
+            .map(broadcast::Sender::receiver_count)
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let channels = self
+            .inner
+            .channels
+            .read()
+            .unwrap_or_else(|err| err.into_inner());
+        let mut topics: Vec<_> = channels.keys().collect();
+        topics.sort_unstable();
+        f.debug_struct("EventBus")
+            .field("topics", &topics)
+            .field("capacity", &self.inner.capacity)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    enum Topic {
+        Notes,
+    }
+
+    impl EventTopic for Topic {
+        fn topic_name(&self) -> Cow<'static, str> {
+            match self {
+                Self::Notes => Cow::Borrowed("notes"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn events_reach_subscribers_of_their_topic_only() {
+        let bus = EventBus::new();
+        assert_eq!(bus.publish(Topic::Notes, "created", &"dropped").unwrap(), 0);
+
+        let mut notes = bus.subscribe(Topic::Notes);
+        let mut audit = bus.subscribe("audit");
+        assert_eq!(bus.subscriber_count("notes"), 1);
+
+        let delivered = bus
+            .publish(Topic::Notes, "created", &serde_json::json!({"id": 7}))
+            .unwrap();
+        assert_eq!(delivered, 1);
+        let event = notes.recv().await.unwrap();
+        assert_eq!(event.topic, "notes");
+        assert_eq!(event.kind, "created");
+        assert_eq!(event.data["id"], 7);
+        assert!(event.id > 1);
+        assert!(audit.try_recv().is_err());
+    }
+}
@@ -0,0 +1,204 @@
+use std::{collections::HashMap, env, fmt, str::FromStr, sync::Arc};
+
+const ENV_FLAG_PREFIX: &str = "OPENPORTIO_FLAG_";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlagRule {
+    Off,
+    On,
+    /// Enabled for the given percentage (0-100) of subjects, bucketed by a stable hash.
+    Percentage(u8),
+}
+
+impl FlagRule {
+    pub fn percentage(percent: u8) -> Self {
+        Self::Percentage(percent.min(100))
+    }
+
+    pub fn evaluate(&self, flag: &str, subject: Option<&str>) -> bool {
+        match *self {
+            Self::Off => false,
+            Self::On => true,
+            Self::Percentage(percent) if percent >= 100 => true,
+            Self::Percentage(0) => false,
+            Self::Percentage(percent) => {
+                subject.is_some_and(|subject| rollout_bucket(flag, subject) < percent)
+            }
+        }
+    }
+}
+
+impl FromStr for FlagRule {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let value = raw.trim().to_ascii_lowercase();
+        match value.as_str() {
+            "true" | "on" | "1" | "enabled" => return Ok(Self::On),
+            "false" | "off" | "0" | "disabled" => return Ok(Self::Off),
+            _ => {}
+        }
+        value
+            .strip_suffix('%')
+            .and_then(|percent| percent.trim().parse::<u8>().ok())
+            .filter(|percent| *percent <= 100)
+            .map(Self::Percentage)
+            .ok_or_else(|| format!("unsupported flag rule `{raw}`"))
+    }
+}
+
+pub trait FlagProvider: Send + Sync {
+    fn rule(&self, flag: &str) -> Option<FlagRule>;
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StaticFlagProvider {
+    rules: HashMap<String, FlagRule>,
+}
+
+impl StaticFlagProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, flag: impl Into<String>, rule: FlagRule) -> Self {
+        self.rules.insert(flag.into(), rule);
+        self
+    }
+}
+
+impl FlagProvider for StaticFlagProvider {
+    fn rule(&self, flag: &str) -> Option<FlagRule> {
+        self.rules.get(flag).copied()
+    }
+}
+
+/// Reads `OPENPORTIO_FLAG_<NAME>` on every evaluation, where
+/// `<NAME>` is the flag name upper-cased with non-alphanumeric characters replaced by `_`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EnvFlagProvider;
+
+impl FlagProvider for EnvFlagProvider {
+    fn rule(&self, flag: &str) -> Option<FlagRule> {
+        let suffix = env_flag_suffix(flag);
+        env::var(format!("{ENV_FLAG_PREFIX}{suffix}"))
+            .ok()
+            .and_then(|raw| raw.parse::<FlagRule>().ok())
+    }
+}
+
+#[derive(Clone)]
+pub struct FeatureFlags {
+    provider: Arc<dyn FlagProvider>,
+}
+
+impl FeatureFlags {
+    pub fn new(provider: impl FlagProvider + 'static) -> Self {
+        Self {
+            provider: Arc::new(provider),
+        }
+    }
+
+    pub fn from_provider(provider: Arc<dyn FlagProvider>) -> Self {
+        Self { provider }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(EnvFlagProvider)
+    }
+
+    pub fn rule(&self, flag: &str) -> FlagRule {
+        self.provider.rule(flag).unwrap_or(FlagRule::Off)
+    }
+
+    /// Evaluates a flag without a subject; percentage rollouts are only on at 100%.
+    pub fn is_enabled(&self, flag: &str) -> bool {
+        self.rule(flag).evaluate(flag, None)
+    }
+
+    pub fn is_enabled_for(&self, flag: &str, subject: &str) -> bool {
+        self.rule(flag).evaluate(flag, Some(subject))
+    }
+}
+
+impl Default for FeatureFlags {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
+
+impl fmt::Debug for FeatureFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FeatureFlags").finish_non_exhaustive()
+    }
+}
+
+fn env_flag_suffix(flag: &str) -> String {
+    flag.chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() {
+                ch.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+// FNV-1a keeps buckets stable across releases and processes, unlike `DefaultHasher`.
+fn rollout_bucket(flag: &str, subject: &str) -> u8 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in flag.bytes().chain([b':']).chain(subject.bytes()) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    (hash % 100) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_boolean_and_percentage_rules() {
+        assert_eq!("on".parse::<FlagRule>(), Ok(FlagRule::On));
+        assert_eq!(" False ".parse::<FlagRule>(), Ok(FlagRule::Off));
+        assert_eq!("25%".parse::<FlagRule>(), Ok(FlagRule::Percentage(25)));
+        assert!("150%".parse::<FlagRule>().is_err());
+        assert!("sometimes".parse::<FlagRule>().is_err());
+    }
+
+    #[test]
+    fn percentage_rollout_is_stable_per_subject() {
+        let flags =
+            FeatureFlags::new(StaticFlagProvider::new().with("beta", FlagRule::percentage(30)));
+
+        let enabled = (0..1_000)
+            .filter(|id| flags.is_enabled_for("beta", &format!("user-{id}")))
+            .count();
+        assert!((200..400).contains(&enabled), "got {enabled} of 1000");
+
+        for id in 0..50 {
+            let subject = format!("user-{id}");
+            assert_eq!(
+                flags.is_enabled_for("beta", &subject),
+                flags.is_enabled_for("beta", &subject)
+            );
+        }
+        assert!(!flags.is_enabled("beta"));
+    }
+
+    #[test]
+    fn unknown_flags_are_off() {
+        let flags = FeatureFlags::new(StaticFlagProvider::new().with("known", FlagRule::On));
+        assert!(flags.is_enabled("known"));
+        assert!(!flags.is_enabled("unknown"));
+        assert!(!flags.is_enabled_for("unknown", "user-1"));
+    }
+
+    #[test]
+    fn env_flag_names_are_normalized() {
+        assert_eq!(env_flag_suffix("new_notes_api"), "NEW_NOTES_API");
+        assert_eq!(env_flag_suffix("checkout.v2-ui"), "CHECKOUT_V2_UI");
+    }
+}
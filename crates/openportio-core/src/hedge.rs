@@ -0,0 +1,214 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    future::{poll_fn, Future},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::Poll,
+    time::Duration,
+};
+
+use tokio::time::{Instant, Sleep};
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 2;
+const DEFAULT_PERCENTILE: f64 = 0.95;
+const DEFAULT_INITIAL_DELAY: Duration = Duration::from_millis(50);
+/// Latencies kept per policy, and how many are needed before the percentile replaces the
+/// initial delay.
+const LATENCY_WINDOW: usize = 1000;
+const MIN_SAMPLES: usize = 20;
+
+#[derive(Debug, Clone, Copy)]
+enum HedgeDelay {
+    Fixed(Duration),
+    Percentile { quantile: f64, initial: Duration },
+}
+
+/// When [`hedge`] starts backup attempts. The default waits for the 95th percentile of the
+/// latencies this policy has observed (50ms until 20 calls have finished), with one backup.
+///
+/// Clones share the observed latencies, so keep one policy per downstream: percentiles of a
+/// fast cache and a slow search service mixed together help neither.
+#[derive(Clone)]
+pub struct HedgePolicy {
+    delay: HedgeDelay,
+    max_attempts: u32,
+    latencies: Arc<Mutex<VecDeque<Duration>>>,
+}
+
+impl Default for HedgePolicy {
+    fn default() -> Self {
+        Self::percentile(DEFAULT_PERCENTILE, DEFAULT_INITIAL_DELAY)
+    }
+}
+
+impl HedgePolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Backups start `delay` after the previous attempt.
+    pub fn fixed(delay: Duration) -> Self {
+        Self::with_delay(HedgeDelay::Fixed(delay))
+    }
+
+    /// Backups start once the previous attempt has been running for the `quantile` (for example
+    /// `0.95`) of observed successful latencies; `initial` is used until enough calls finished.
+    pub fn percentile(quantile: f64, initial: Duration) -> Self {
+        Self::with_delay(HedgeDelay::Percentile {
+            quantile: quantile.clamp(0.0, 1.0),
+            initial,
+        })
+    }
+
+    fn with_delay(delay: HedgeDelay) -> Self {
+        Self {
+            delay,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            latencies: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+
+    /// Total attempts including the first call; 1 disables hedging.
+    pub fn with_max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// How long an attempt may run before the next one starts.
+    pub fn delay(&self) -> Duration {
+        match self.delay {
+            HedgeDelay::Fixed(delay) => delay,
+            HedgeDelay::Percentile { quantile, initial } => {
+                let latencies = self.lock();
+                if latencies.len() < MIN_SAMPLES {
+                    return initial;
+                }
+                let mut sorted: Vec<_> = latencies.iter().copied().collect();
+                sorted.sort_unstable();
+                let index = ((sorted.len() - 1) as f64 * quantile).round() as usize;
+                sorted[index]
+            }
+        }
+    }
+
+    /// Feeds the percentile; [`hedge`] records the latency of every winning attempt.
+    pub fn record(&self, latency: Duration) {
+        let mut latencies = self.lock();
+        if latencies.len() == LATENCY_WINDOW {
+            latencies.pop_front();
+        }
+        latencies.push_back(latency);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<Duration>> {
+        self.latencies.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl fmt::Debug for HedgePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HedgePolicy")
+            .field("delay", &self.delay)
+            .field("max_attempts", &self.max_attempts)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Runs `operation` and, while it is slower than [`HedgePolicy::delay`], starts up to
+/// `max_attempts - 1` backup attempts; the first success wins and the others are dropped
+/// (cancelled). A failed attempt starts the next one immediately; when every attempt fails,
+/// the last error is returned. Only hedge idempotent reads: every attempt may reach the
+/// downstream.
+pub async fn hedge<T, E, F, Fut>(policy: &HedgePolicy, mut operation: F) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let delay = policy.delay();
+    let mut attempts: Vec<(Instant, Pin<Box<Fut>>)> = vec![(Instant::now(), Box::pin(operation()))];
+    let mut started = 1;
+    let mut timer: Pin<Box<Sleep>> = Box::pin(tokio::time::sleep(delay));
+    let mut last_error = None;
+
+    poll_fn(|cx| loop {
+        let mut index = 0;
+        while index < attempts.len() {
+            let Poll::Ready(result) = attempts[index].1.as_mut().poll(cx) else {
+                index += 1;
+                continue;
+            };
+            let (attempt_started, _) = attempts.swap_remove(index);
+            match result {
+                Ok(value) => {
+                    policy.record(attempt_started.elapsed());
+                    return Poll::Ready(Ok(value));
+                }
+                Err(err) => last_error = Some(err),
+            }
+        }
+
+        let can_start = started < policy.max_attempts;
+        if attempts.is_empty() && !can_start {
+            return Poll::Ready(Err(last_error.take().expect("a failed attempt")));
+        }
+        if !can_start {
+            return Poll::Pending;
+        }
+        if !attempts.is_empty() && timer.as_mut().poll(cx).is_pending() {
+            return Poll::Pending;
+        }
+        started += 1;
+        attempts.push((Instant::now(), Box::pin(operation())));
+        timer.as_mut().reset(Instant::now() + delay);
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn slow_attempts_are_hedged_and_the_first_success_wins() {
+        let policy = HedgePolicy::fixed(Duration::from_millis(20)).with_max_attempts(3);
+        let calls = AtomicU32::new(0);
+        let started = Instant::now();
+        let winner = hedge(&policy, || {
+            let call = calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                let latency = if call == 0 { 1000 } else { 5 };
+                tokio::time::sleep(Duration::from_millis(latency)).await;
+                Ok::<_, ()>(call)
+            }
+        })
+        .await;
+        assert_eq!(winner, Ok(1));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert!(started.elapsed() < Duration::from_millis(500));
+
+        let calls = AtomicU32::new(0);
+        let failed = hedge(&policy, || {
+            let call = calls.fetch_add(1, Ordering::SeqCst);
+            async move { Err::<(), _>(call) }
+        })
+        .await;
+        assert_eq!(failed, Err(2), "failures start the next attempt at once");
+    }
+
+    #[test]
+    fn percentile_delay_follows_observed_latencies() {
+        let policy = HedgePolicy::percentile(0.9, Duration::from_millis(50));
+        assert_eq!(policy.delay(), Duration::from_millis(50));
+        for ms in 1..=100 {
+            policy.clone().record(Duration::from_millis(ms));
+        }
+        assert_eq!(policy.delay(), Duration::from_millis(90));
+    }
+}
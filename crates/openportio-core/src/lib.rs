@@ -1,8 +1,32 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use thiserror::Error;
 
 pub mod auth;
+pub mod buildinfo;
+pub mod cancel;
+pub mod clock;
+pub mod environment;
+pub mod events;
+pub mod flags;
+pub mod hedge;
+pub mod outbox;
+pub mod query;
+pub mod retry;
+pub mod services;
+pub mod startup;
+
+pub use buildinfo::BuildInfo;
+pub use cancel::{select_with_cancel, try_select_with_cancel, CancellationToken};
+pub use clock::{Clock, SharedClock, SystemClock, TestClock};
+pub use environment::Environment;
+pub use events::{BusEvent, EventBus, EventTopic};
+pub use hedge::{hedge, HedgePolicy};
+pub use outbox::{Outbox, OutboxRelay, OutboxStore};
+pub use query::{Filter, ListQuery, Page, Paginated, QuerySpec, Queryable, Sort};
+pub use retry::{with_retry, with_retry_by, Classify, RetryError, RetryPolicy, Retryability};
+pub use services::ServiceRegistry;
+pub use startup::AppStateBuilder;
 
 pub type OpenportioResult<T> = Result<T, OpenportioError>;
 pub type MeldResult<T> = OpenportioResult<T>;
@@ -11,16 +35,29 @@ pub type AlloyResult<T> = OpenportioResult<T>;
 #[derive(Debug, Clone)]
 pub struct AppConfig {
     pub service_name: String,
-    pub environment: String,
+    pub environment: Environment,
 }
 
 impl AppConfig {
     pub fn local(service_name: impl Into<String>) -> Self {
         Self {
             service_name: service_name.into(),
-            environment: "local".to_string(),
+            environment: Environment::Local,
         }
     }
+
+    /// Like [`Self::local`], with the environment read from `OPENPORTIO_ENV`.
+    pub fn from_env(service_name: impl Into<String>) -> Self {
+        Self {
+            service_name: service_name.into(),
+            environment: Environment::from_env(),
+        }
+    }
+
+    pub fn with_environment(mut self, environment: impl Into<Environment>) -> Self {
+        self.environment = environment.into();
+        self
+    }
 }
 
 #[derive(Debug, Error)]
@@ -29,6 +66,17 @@ pub enum OpenportioError {
     Validation(String),
     #[error("internal error: {0}")]
     Internal(String),
+    /// A dependency is temporarily down; retrying may succeed.
+    #[error("unavailable: {0}")]
+    Unavailable(String),
+    #[error("throttled: {message}")]
+    Throttled {
+        message: String,
+        retry_after: Option<Duration>,
+    },
+    /// The caller went away (client disconnect, shutdown) before the work finished.
+    #[error("cancelled")]
+    Cancelled,
 }
 
 pub type MeldError = OpenportioError;
@@ -38,8 +86,25 @@ pub trait GreetingEngine: Send + Sync {
     fn greet(&self, name: &str) -> OpenportioResult<String>;
 }
 
+/// Metric labels as `(key, value)` pairs, e.g. `&[("route", "/health"), ("status", "200")]`.
+pub type MetricLabels<'a> = &'a [(&'a str, &'a str)];
+
 pub trait MetricsSink: Send + Sync {
     fn incr_counter(&self, name: &str);
+
+    /// Sinks without label support keep working: the default drops the labels.
+    fn incr_counter_with(&self, name: &str, labels: MetricLabels<'_>) {
+        let _ = labels;
+        self.incr_counter(name);
+    }
+
+    fn observe_histogram(&self, name: &str, value: f64, labels: MetricLabels<'_>) {
+        let _ = (name, value, labels);
+    }
+
+    fn set_gauge(&self, name: &str, value: f64, labels: MetricLabels<'_>) {
+        let _ = (name, value, labels);
+    }
 }
 
 #[derive(Debug, Default)]
@@ -78,6 +143,11 @@ pub struct AppState {
     pub config: AppConfig,
     pub greeter: Arc<dyn GreetingEngine>,
     pub metrics: Arc<dyn MetricsSink>,
+    pub flags: flags::FeatureFlags,
+    pub startup: Arc<startup::StartupReport>,
+    /// Domain services by type. `greeter` is registered here too, as `Arc<dyn GreetingEngine>`.
+    pub services: ServiceRegistry,
+    pub events: events::EventBus,
 }
 
 impl AppState {
@@ -87,23 +157,62 @@ impl AppState {
         metrics: Arc<dyn MetricsSink>,
     ) -> Self {
         Self {
+            services: ServiceRegistry::new().with(greeter.clone()),
             config,
             greeter,
             metrics,
+            flags: flags::FeatureFlags::from_env(),
+            startup: Arc::default(),
+            events: events::EventBus::new(),
         }
     }
 
     pub fn local(service_name: impl Into<String>) -> Self {
-        Self {
-            config: AppConfig::local(service_name),
-            greeter: Arc::new(StaticGreetingEngine::new("Hello")),
-            metrics: Arc::new(NoopMetrics),
-        }
+        Self::new(
+            AppConfig::local(service_name),
+            Arc::new(StaticGreetingEngine::new("Hello")),
+            Arc::new(NoopMetrics),
+        )
+    }
+
+    /// Registers a domain service, resolvable with [`Self::service`] and, in the server crate,
+    /// through `Depends<T>` / `GrpcContext::depends`.
+    pub fn with_service<T>(mut self, service: T) -> Self
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.services.insert(service);
+        self
+    }
+
+    pub fn service<T>(&self) -> Option<T>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.services.get::<T>()
+    }
+
+    pub fn with_flags(mut self, flags: flags::FeatureFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    pub fn with_event_bus(mut self, events: events::EventBus) -> Self {
+        self.events = events;
+        self
+    }
+
+    pub fn with_startup_report(mut self, report: startup::StartupReport) -> Self {
+        self.startup = Arc::new(report);
+        self
     }
 
     pub fn greet(&self, name: &str) -> OpenportioResult<String> {
         self.metrics.incr_counter("greet.requests");
-        self.greeter.greet(name)
+        match self.service::<Arc<dyn GreetingEngine>>() {
+            Some(greeter) => greeter.greet(name),
+            None => self.greeter.greet(name),
+        }
     }
 }
 
@@ -118,6 +227,24 @@ mod tests {
         assert!(matches!(result, Err(OpenportioError::Validation(_))));
     }
 
+    #[test]
+    fn labeled_counters_forward_to_unlabeled_sinks() {
+        #[derive(Default)]
+        struct CountingSink(std::sync::Mutex<Vec<String>>);
+
+        impl MetricsSink for CountingSink {
+            fn incr_counter(&self, name: &str) {
+                self.0.lock().unwrap().push(name.to_string());
+            }
+        }
+
+        let sink = CountingSink::default();
+        sink.incr_counter_with("http.requests", &[("route", "/health")]);
+        sink.observe_histogram("http.request.duration_seconds", 0.1, &[]);
+        sink.set_gauge("http.in_flight", 1.0, &[]);
+        assert_eq!(*sink.0.lock().unwrap(), ["http.requests"]);
+    }
+
     #[test]
     fn greet_returns_message() {
         let state = AppState::local("openportio-test");
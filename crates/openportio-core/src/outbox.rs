@@ -0,0 +1,326 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{events::EventTopic, EventBus, OpenportioError, OpenportioResult};
+
+const DEFAULT_BATCH_SIZE: usize = 100;
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+pub type OutboxFuture<'a, T> = Pin<Box<dyn Future<Output = OpenportioResult<T>> + Send + 'a>>;
+
+/// An event waiting to be published; `data` is the payload serialized at enqueue time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutboxEvent {
+    pub topic: String,
+    pub kind: String,
+    pub data: serde_json::Value,
+}
+
+impl OutboxEvent {
+    pub fn new<T>(
+        topic: impl EventTopic,
+        kind: impl Into<String>,
+        payload: &T,
+    ) -> Result<Self, serde_json::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        Ok(Self {
+            topic: topic.topic_name().into_owned(),
+            kind: kind.into(),
+            data: serde_json::to_value(payload)?,
+        })
+    }
+}
+
+/// A stored [`OutboxEvent`]. `id` orders delivery; `attempts` counts failed publishes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutboxRecord {
+    pub id: u64,
+    pub event: OutboxEvent,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+}
+
+/// Durable queue behind an [`Outbox`]. A database-backed store should insert in the same
+/// transaction as the state change the event describes; that is what makes the pattern
+/// reliable. See `docs/fastapi-like-builder.md` for a Postgres schema.
+pub trait OutboxStore: Send + Sync + 'static {
+    fn enqueue(&self, event: OutboxEvent) -> OutboxFuture<'_, u64>;
+
+    /// Up to `limit` unpublished records, oldest first.
+    fn pending(&self, limit: usize) -> OutboxFuture<'_, Vec<OutboxRecord>>;
+
+    fn mark_published(&self, id: u64) -> OutboxFuture<'_, ()>;
+
+    fn mark_failed(&self, id: u64, error: String) -> OutboxFuture<'_, ()>;
+}
+
+/// Single-process store; records are lost on restart, so use it for tests and prototypes.
+#[derive(Debug, Default)]
+pub struct InMemoryOutboxStore {
+    state: Mutex<InMemoryOutboxState>,
+}
+
+#[derive(Debug, Default)]
+struct InMemoryOutboxState {
+    next_id: u64,
+    records: BTreeMap<u64, OutboxRecord>,
+}
+
+impl InMemoryOutboxStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, InMemoryOutboxState> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl OutboxStore for InMemoryOutboxStore {
+    fn enqueue(&self, event: OutboxEvent) -> OutboxFuture<'_, u64> {
+        let mut state = self.lock();
+        state.next_id += 1;
+        let id = state.next_id;
+        state.records.insert(
+            id,
+            OutboxRecord {
+                id,
+                event,
+                attempts: 0,
+                last_error: None,
+            },
+        );
+        Box::pin(async move { Ok(id) })
+    }
+
+    fn pending(&self, limit: usize) -> OutboxFuture<'_, Vec<OutboxRecord>> {
+        let records = self.lock().records.values().take(limit).cloned().collect();
+        Box::pin(async move { Ok(records) })
+    }
+
+    fn mark_published(&self, id: u64) -> OutboxFuture<'_, ()> {
+        self.lock().records.remove(&id);
+        Box::pin(async { Ok(()) })
+    }
+
+    fn mark_failed(&self, id: u64, error: String) -> OutboxFuture<'_, ()> {
+        if let Some(record) = self.lock().records.get_mut(&id) {
+            record.attempts = record.attempts.saturating_add(1);
+            record.last_error = Some(error);
+        }
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Handle handlers use to enqueue events; cheap to clone.
+#[derive(Clone)]
+pub struct Outbox {
+    store: Arc<dyn OutboxStore>,
+}
+
+impl Outbox {
+    pub fn new(store: impl OutboxStore) -> Self {
+        Self::from_arc(Arc::new(store))
+    }
+
+    pub fn from_arc(store: Arc<dyn OutboxStore>) -> Self {
+        Self { store }
+    }
+
+    pub fn in_memory() -> Self {
+        Self::new(InMemoryOutboxStore::new())
+    }
+
+    pub fn store(&self) -> &Arc<dyn OutboxStore> {
+        &self.store
+    }
+
+    /// Stores the event for the relay to publish; returns its record id.
+    pub async fn enqueue<T>(
+        &self,
+        topic: impl EventTopic,
+        kind: impl Into<String>,
+        payload: &T,
+    ) -> OpenportioResult<u64>
+    where
+        T: Serialize + ?Sized,
+    {
+        let event = OutboxEvent::new(topic, kind, payload)
+            .map_err(|err| OpenportioError::Validation(format!("outbox payload: {err}")))?;
+        self.store.enqueue(event).await
+    }
+}
+
+impl fmt::Debug for Outbox {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Outbox").finish_non_exhaustive()
+    }
+}
+
+/// Where the relay delivers events: the in-process [`EventBus`] or an external broker.
+pub trait OutboxPublisher: Send + Sync + 'static {
+    fn publish<'a>(&'a self, record: &'a OutboxRecord) -> OutboxFuture<'a, ()>;
+}
+
+impl OutboxPublisher for EventBus {
+    fn publish<'a>(&'a self, record: &'a OutboxRecord) -> OutboxFuture<'a, ()> {
+        let event = &record.event;
+        let result = EventBus::publish(self, event.topic.clone(), event.kind.clone(), &event.data)
+            .map(|_| ())
+            .map_err(|err| OpenportioError::Internal(err.to_string()));
+        Box::pin(async move { result })
+    }
+}
+
+/// Moves records from an [`OutboxStore`] to an [`OutboxPublisher`]. A record is marked
+/// published only after the publisher accepts it, so a crash in between publishes it again:
+/// delivery is at-least-once and consumers should dedupe on the record id. A failed publish
+/// ends the batch, keeping delivery in order; it is retried on the next poll.
+#[derive(Clone)]
+pub struct OutboxRelay {
+    outbox: Outbox,
+    publisher: Arc<dyn OutboxPublisher>,
+    batch_size: usize,
+    poll_interval: Duration,
+}
+
+impl OutboxRelay {
+    pub fn new(outbox: Outbox, publisher: impl OutboxPublisher) -> Self {
+        Self {
+            outbox,
+            publisher: Arc::new(publisher),
+            batch_size: DEFAULT_BATCH_SIZE,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        }
+    }
+
+    /// Records fetched per poll (default 100).
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Pause between polls that found nothing to do or hit an error (default 1 second).
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn outbox(&self) -> &Outbox {
+        &self.outbox
+    }
+
+    /// Publishes one batch; returns how many records were published.
+    pub async fn relay_once(&self) -> OpenportioResult<usize> {
+        let store = self.outbox.store();
+        let mut published = 0;
+        for record in store.pending(self.batch_size).await? {
+            if let Err(err) = self.publisher.publish(&record).await {
+                tracing::warn!(
+                    id = record.id,
+                    topic = %record.event.topic,
+                    attempts = record.attempts + 1,
+                    error = %err,
+                    "outbox publish failed"
+                );
+                store.mark_failed(record.id, err.to_string()).await?;
+                break;
+            }
+            store.mark_published(record.id).await?;
+            published += 1;
+        }
+        Ok(published)
+    }
+
+    /// Relays until the task is dropped; full batches are followed immediately by the next.
+    pub async fn run(self) {
+        loop {
+            match self.relay_once().await {
+                Ok(published) if published == self.batch_size => continue,
+                Ok(_) => {}
+                Err(err) => tracing::warn!(error = %err, "outbox relay poll failed"),
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+}
+
+impl fmt::Debug for OutboxRelay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OutboxRelay")
+            .field("batch_size", &self.batch_size)
+            .field("poll_interval", &self.poll_interval)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::*;
+
+    struct Flaky {
+        bus: EventBus,
+        fail_next: AtomicBool,
+    }
+
+    impl OutboxPublisher for Flaky {
+        fn publish<'a>(&'a self, record: &'a OutboxRecord) -> OutboxFuture<'a, ()> {
+            if self.fail_next.swap(false, Ordering::SeqCst) {
+                return Box::pin(async { Err(OpenportioError::Unavailable("broker down".into())) });
+            }
+            OutboxPublisher::publish(&self.bus, record)
+        }
+    }
+
+    #[tokio::test]
+    async fn relay_publishes_in_order_and_retries_failed_records() {
+        let store = Arc::new(InMemoryOutboxStore::new());
+        let outbox = Outbox::from_arc(store.clone());
+        let bus = EventBus::new();
+        let mut notes = bus.subscribe("notes");
+        let relay = OutboxRelay::new(
+            outbox.clone(),
+            Flaky {
+                bus: bus.clone(),
+                fail_next: AtomicBool::new(true),
+            },
+        );
+
+        assert_eq!(outbox.enqueue("notes", "created", &1).await.unwrap(), 1);
+        assert_eq!(outbox.enqueue("notes", "created", &2).await.unwrap(), 2);
+
+        assert_eq!(relay.relay_once().await.unwrap(), 0);
+        let pending = store.pending(10).await.unwrap();
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].attempts, 1);
+        assert_eq!(
+            pending[0].last_error.as_deref(),
+            Some("unavailable: broker down")
+        );
+
+        assert_eq!(relay.relay_once().await.unwrap(), 2);
+        assert!(store.is_empty());
+        assert_eq!(notes.recv().await.unwrap().data, 1);
+        assert_eq!(notes.recv().await.unwrap().data, 2);
+    }
+}
@@ -0,0 +1,693 @@
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+use serde::Serialize;
+use thiserror::Error;
+
+/// Which fields a list endpoint lets clients sort and filter by, and its page size bounds.
+/// Anything outside the whitelist is rejected, so field names can be used as column names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuerySpec {
+    pub sortable: &'static [&'static str],
+    pub filterable: &'static [&'static str],
+    pub default_page_size: u32,
+    pub max_page_size: u32,
+    /// Used when the request has no `sort`, e.g. `"-created_at"`.
+    pub default_sort: Option<&'static str>,
+}
+
+impl QuerySpec {
+    pub const fn new(
+        sortable: &'static [&'static str],
+        filterable: &'static [&'static str],
+    ) -> Self {
+        Self {
+            sortable,
+            filterable,
+            default_page_size: 20,
+            max_page_size: 100,
+            default_sort: None,
+        }
+    }
+
+    pub const fn with_page_size(mut self, default: u32, max: u32) -> Self {
+        self.default_page_size = default;
+        self.max_page_size = max;
+        self
+    }
+
+    pub const fn with_default_sort(mut self, sort: &'static str) -> Self {
+        self.default_sort = Some(sort);
+        self
+    }
+
+    /// Parses decoded query pairs:
+    ///
+    /// - `page` (1-based) and `per_page`, capped at `max_page_size`
+    /// - `sort=-created_at,name`: comma-separated fields, `-` for descending
+    /// - `status=active` or `age[gte]=18` for filterable fields; operators are `eq`, `ne`, `lt`,
+    ///   `lte`, `gt`, `gte`, `contains` and `in` (comma-separated values)
+    ///
+    /// Other plain parameters are ignored; bracketed ones must name a filterable field.
+    pub fn parse<K, V>(
+        &self,
+        pairs: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<ListQuery, QueryError>
+    where
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let mut page = Page::new(1, self.default_page_size);
+        let mut sort = None;
+        let mut filter = Filter::default();
+        for (key, value) in pairs {
+            let (key, value) = (key.as_ref(), value.as_ref().trim());
+            match key {
+                "page" => page.number = parse_positive(key, value)?,
+                "per_page" => page.size = parse_positive(key, value)?,
+                "sort" => sort = Some(self.parse_sort(value)?),
+                _ => {
+                    if let Some(condition) = self.parse_condition(key, value)? {
+                        filter.conditions.push(condition);
+                    }
+                }
+            }
+        }
+        if page.size > self.max_page_size {
+            return Err(QueryError::PageSizeTooLarge {
+                max: self.max_page_size,
+            });
+        }
+        let sort = match (sort, self.default_sort) {
+            (Some(sort), _) => sort,
+            (None, Some(default)) => self.parse_sort(default)?,
+            (None, None) => Sort::default(),
+        };
+        Ok(ListQuery { page, sort, filter })
+    }
+
+    fn parse_sort(&self, raw: &str) -> Result<Sort, QueryError> {
+        let mut keys = Vec::new();
+        for field in raw
+            .split(',')
+            .map(str::trim)
+            .filter(|field| !field.is_empty())
+        {
+            let (field, direction) = match field.strip_prefix('-') {
+                Some(field) => (field, SortDirection::Desc),
+                None => (field.strip_prefix('+').unwrap_or(field), SortDirection::Asc),
+            };
+            if !self.sortable.contains(&field) {
+                return Err(QueryError::UnknownSortField(field.to_string()));
+            }
+            keys.push(SortKey {
+                field: field.to_string(),
+                direction,
+            });
+        }
+        Ok(Sort { keys })
+    }
+
+    fn parse_condition(&self, key: &str, value: &str) -> Result<Option<Condition>, QueryError> {
+        let (field, op) = match key.split_once('[') {
+            Some((field, op)) => {
+                let op = op.strip_suffix(']').unwrap_or(op);
+                let op = op.parse().map_err(|_| QueryError::UnsupportedOperator {
+                    field: field.to_string(),
+                    op: op.to_string(),
+                })?;
+                if !self.filterable.contains(&field) {
+                    return Err(QueryError::UnknownFilterField(field.to_string()));
+                }
+                (field, op)
+            }
+            None if self.filterable.contains(&key) => (key, FilterOp::Eq),
+            None => return Ok(None),
+        };
+        Ok(Some(Condition {
+            field: field.to_string(),
+            op,
+            value: value.to_string(),
+        }))
+    }
+}
+
+fn parse_positive(key: &str, value: &str) -> Result<u32, QueryError> {
+    value
+        .parse()
+        .ok()
+        .filter(|value| *value > 0)
+        .ok_or_else(|| QueryError::InvalidNumber {
+            param: key.to_string(),
+            value: value.to_string(),
+        })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum QueryError {
+    #[error("`{param}` must be a positive integer, got `{value}`")]
+    InvalidNumber { param: String, value: String },
+    #[error("`per_page` must be at most {max}")]
+    PageSizeTooLarge { max: u32 },
+    #[error("cannot sort by `{0}`")]
+    UnknownSortField(String),
+    #[error("cannot filter by `{0}`")]
+    UnknownFilterField(String),
+    #[error("unsupported filter operator `{op}` for `{field}`")]
+    UnsupportedOperator { field: String, op: String },
+}
+
+impl QueryError {
+    /// The query parameter at fault, for error locations.
+    pub fn param(&self) -> &str {
+        match self {
+            Self::InvalidNumber { param, .. } => param,
+            Self::PageSizeTooLarge { .. } => "per_page",
+            Self::UnknownSortField(_) => "sort",
+            Self::UnknownFilterField(field) | Self::UnsupportedOperator { field, .. } => field,
+        }
+    }
+}
+
+/// A parsed list request: one page, an ordering and a conjunction of filter conditions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListQuery {
+    pub page: Page,
+    pub sort: Sort,
+    pub filter: Filter,
+}
+
+impl ListQuery {
+    /// Filters, sorts and slices `items` in memory.
+    pub fn apply<T: Queryable>(&self, items: impl IntoIterator<Item = T>) -> Paginated<T> {
+        let mut items: Vec<T> = items
+            .into_iter()
+            .filter(|item| self.filter.matches(item))
+            .collect();
+        items.sort_by(|a, b| self.sort.compare(a, b));
+        let total = items.len() as u64;
+        let items = items
+            .into_iter()
+            .skip(self.page.offset() as usize)
+            .take(self.page.limit() as usize)
+            .collect();
+        Paginated {
+            items,
+            total,
+            page: self.page.number,
+            per_page: self.page.size,
+        }
+    }
+
+    /// `WHERE`, `ORDER BY`, `LIMIT` and `OFFSET` clauses with bound parameters. Field names are
+    /// whitelisted by the [`QuerySpec`] and used as column names; values are only ever bound.
+    pub fn to_sql(&self, placeholders: Placeholders) -> SqlClauses {
+        let mut params = Vec::new();
+        let mut bind = |value: String| {
+            params.push(value);
+            placeholders.nth(params.len())
+        };
+        let conditions: Vec<String> = self
+            .filter
+            .conditions
+            .iter()
+            .map(|condition| {
+                let field = &condition.field;
+                match condition.op {
+                    FilterOp::In => {
+                        let values: Vec<String> = condition
+                            .values()
+                            .map(|value| bind(value.to_string()))
+                            .collect();
+                        if values.is_empty() {
+                            "1 = 0".to_string()
+                        } else {
+                            format!("{field} IN ({})", values.join(", "))
+                        }
+                    }
+                    FilterOp::Contains => format!(
+                        "{field} LIKE {} ESCAPE '\\'",
+                        bind(format!("%{}%", escape_like(&condition.value)))
+                    ),
+                    op => format!("{field} {} {}", op.sql(), bind(condition.value.clone())),
+                }
+            })
+            .collect();
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        let order_by = if self.sort.keys.is_empty() {
+            String::new()
+        } else {
+            let keys: Vec<String> = self
+                .sort
+                .keys
+                .iter()
+                .map(|key| format!("{} {}", key.field, key.direction.sql()))
+                .collect();
+            format!("ORDER BY {}", keys.join(", "))
+        };
+        SqlClauses {
+            where_clause,
+            order_by,
+            limit: self.page.limit(),
+            offset: self.page.offset(),
+            params,
+        }
+    }
+}
+
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// 1-based page number and page size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Page {
+    pub number: u32,
+    pub size: u32,
+}
+
+impl Page {
+    pub fn new(number: u32, size: u32) -> Self {
+        Self {
+            number: number.max(1),
+            size,
+        }
+    }
+
+    pub fn offset(&self) -> u64 {
+        u64::from(self.number.saturating_sub(1)) * u64::from(self.size)
+    }
+
+    pub fn limit(&self) -> u32 {
+        self.size
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+impl SortDirection {
+    fn sql(self) -> &'static str {
+        match self {
+            Self::Asc => "ASC",
+            Self::Desc => "DESC",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortKey {
+    pub field: String,
+    pub direction: SortDirection,
+}
+
+/// Keys in priority order; later keys break ties.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Sort {
+    pub keys: Vec<SortKey>,
+}
+
+impl Sort {
+    /// Missing fields sort first.
+    pub fn compare<T: Queryable>(&self, a: &T, b: &T) -> Ordering {
+        self.keys
+            .iter()
+            .map(|key| {
+                let ordering = match (a.field(&key.field), b.field(&key.field)) {
+                    (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+                    (a, b) => a.is_some().cmp(&b.is_some()),
+                };
+                match key.direction {
+                    SortDirection::Asc => ordering,
+                    SortDirection::Desc => ordering.reverse(),
+                }
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterOp {
+    Eq,
+    Ne,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+    Contains,
+    In,
+}
+
+impl FilterOp {
+    fn sql(self) -> &'static str {
+        match self {
+            Self::Eq => "=",
+            Self::Ne => "<>",
+            Self::Lt => "<",
+            Self::Lte => "<=",
+            Self::Gt => ">",
+            Self::Gte => ">=",
+            Self::Contains => "LIKE",
+            Self::In => "IN",
+        }
+    }
+}
+
+impl FromStr for FilterOp {
+    type Err = ();
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw {
+            "eq" => Ok(Self::Eq),
+            "ne" => Ok(Self::Ne),
+            "lt" => Ok(Self::Lt),
+            "lte" => Ok(Self::Lte),
+            "gt" => Ok(Self::Gt),
+            "gte" => Ok(Self::Gte),
+            "contains" => Ok(Self::Contains),
+            "in" => Ok(Self::In),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    pub field: String,
+    pub op: FilterOp,
+    /// Raw value; comma-separated for [`FilterOp::In`].
+    pub value: String,
+}
+
+impl Condition {
+    pub fn values(&self) -> impl Iterator<Item = &str> {
+        self.value
+            .split(',')
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    }
+
+    /// The value is parsed as the field's type; items without the field, or values that do not
+    /// parse, never match.
+    pub fn matches<T: Queryable>(&self, item: &T) -> bool {
+        let Some(actual) = item.field(&self.field) else {
+            return false;
+        };
+        match self.op {
+            FilterOp::Contains => match &actual {
+                FieldValue::Str(actual) => actual.contains(&self.value),
+                _ => false,
+            },
+            FilterOp::In => self
+                .values()
+                .any(|value| actual.compare_raw(value) == Some(Ordering::Equal)),
+            op => actual
+                .compare_raw(&self.value)
+                .is_some_and(|ordering| match op {
+                    FilterOp::Eq => ordering.is_eq(),
+                    FilterOp::Ne => ordering.is_ne(),
+                    FilterOp::Lt => ordering.is_lt(),
+                    FilterOp::Lte => ordering.is_le(),
+                    FilterOp::Gt => ordering.is_gt(),
+                    FilterOp::Gte => ordering.is_ge(),
+                    FilterOp::Contains | FilterOp::In => unreachable!(),
+                }),
+        }
+    }
+}
+
+/// All conditions must hold.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Filter {
+    pub conditions: Vec<Condition>,
+}
+
+impl Filter {
+    pub fn matches<T: Queryable>(&self, item: &T) -> bool {
+        self.conditions
+            .iter()
+            .all(|condition| condition.matches(item))
+    }
+}
+
+/// A field value exposed for in-memory filtering and sorting.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum FieldValue {
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+impl FieldValue {
+    fn compare_raw(&self, raw: &str) -> Option<Ordering> {
+        match self {
+            Self::Str(value) => Some(value.as_str().cmp(raw)),
+            Self::Int(value) => raw.parse::<i64>().ok().map(|raw| value.cmp(&raw)),
+            Self::Float(value) => raw
+                .parse::<f64>()
+                .ok()
+                .and_then(|raw| value.partial_cmp(&raw)),
+            Self::Bool(value) => raw.parse::<bool>().ok().map(|raw| value.cmp(&raw)),
+        }
+    }
+}
+
+impl From<&str> for FieldValue {
+    fn from(value: &str) -> Self {
+        Self::Str(value.to_string())
+    }
+}
+
+impl From<String> for FieldValue {
+    fn from(value: String) -> Self {
+        Self::Str(value)
+    }
+}
+
+impl From<i64> for FieldValue {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
+
+impl From<f64> for FieldValue {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<bool> for FieldValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+/// Field lookup for [`ListQuery::apply`]; return `None` for unknown fields.
+pub trait Queryable {
+    fn field(&self, name: &str) -> Option<FieldValue>;
+}
+
+impl<T: Queryable> Queryable for &T {
+    fn field(&self, name: &str) -> Option<FieldValue> {
+        (**self).field(name)
+    }
+}
+
+/// One page of results with the total count before paging.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Paginated<T> {
+    pub items: Vec<T>,
+    pub total: u64,
+    pub page: u32,
+    pub per_page: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placeholders {
+    /// `$1`, `$2`, ... (PostgreSQL)
+    Dollar,
+    /// `?` (MySQL, SQLite)
+    Question,
+}
+
+impl Placeholders {
+    fn nth(self, n: usize) -> String {
+        match self {
+            Self::Dollar => format!("${n}"),
+            Self::Question => "?".to_string(),
+        }
+    }
+}
+
+/// SQL fragments from [`ListQuery::to_sql`]; `where_clause` and `order_by` are empty when there
+/// is nothing to filter or sort by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqlClauses {
+    pub where_clause: String,
+    pub order_by: String,
+    pub limit: u32,
+    pub offset: u64,
+    /// Bound values in placeholder order.
+    pub params: Vec<String>,
+}
+
+impl fmt::Display for SqlClauses {
+    /// `WHERE ... ORDER BY ... LIMIT n OFFSET m`, ready to append to a `SELECT`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for clause in [&self.where_clause, &self.order_by] {
+            if !clause.is_empty() {
+                write!(f, "{clause} ")?;
+            }
+        }
+        write!(f, "LIMIT {} OFFSET {}", self.limit, self.offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTES: QuerySpec = QuerySpec::new(&["title", "stars"], &["status", "stars", "title"])
+        .with_default_sort("-stars");
+
+    struct Note {
+        title: &'static str,
+        status: &'static str,
+        stars: i64,
+    }
+
+    impl Queryable for Note {
+        fn field(&self, name: &str) -> Option<FieldValue> {
+            match name {
+                "title" => Some(self.title.into()),
+                "status" => Some(self.status.into()),
+                "stars" => Some(self.stars.into()),
+                _ => None,
+            }
+        }
+    }
+
+    fn parse(query: &[(&str, &str)]) -> Result<ListQuery, QueryError> {
+        NOTES.parse(query.iter().copied())
+    }
+
+    #[test]
+    fn parses_pages_whitelisted_sorts_and_filters() {
+        let query = parse(&[
+            ("page", "3"),
+            ("per_page", "10"),
+            ("sort", "-stars,title"),
+            ("status", "open"),
+            ("stars[gte]", "2"),
+            ("unrelated", "ignored"),
+        ])
+        .unwrap();
+        assert_eq!(query.page, Page::new(3, 10));
+        assert_eq!(query.page.offset(), 20);
+        assert_eq!(query.sort.keys.len(), 2);
+        assert_eq!(query.sort.keys[0].direction, SortDirection::Desc);
+        assert_eq!(query.filter.conditions.len(), 2);
+        assert_eq!(query.filter.conditions[1].op, FilterOp::Gte);
+
+        let defaults = parse(&[]).unwrap();
+        assert_eq!(defaults.page, Page::new(1, 20));
+        assert_eq!(defaults.sort.keys[0].field, "stars");
+
+        assert_eq!(
+            parse(&[("sort", "password")]),
+            Err(QueryError::UnknownSortField("password".to_string()))
+        );
+        assert_eq!(parse(&[("owner[eq]", "me")]).unwrap_err().param(), "owner");
+        assert!(matches!(
+            parse(&[("stars[like]", "1")]),
+            Err(QueryError::UnsupportedOperator { .. })
+        ));
+        assert_eq!(
+            parse(&[("per_page", "500")]),
+            Err(QueryError::PageSizeTooLarge { max: 100 })
+        );
+        assert!(matches!(
+            parse(&[("page", "0")]),
+            Err(QueryError::InvalidNumber { .. })
+        ));
+    }
+
+    #[test]
+    fn applies_filters_sorting_and_paging_in_memory() {
+        let notes = [
+            Note {
+                title: "b",
+                status: "open",
+                stars: 3,
+            },
+            Note {
+                title: "a",
+                status: "open",
+                stars: 3,
+            },
+            Note {
+                title: "c",
+                status: "closed",
+                stars: 9,
+            },
+            Note {
+                title: "d",
+                status: "open",
+                stars: 1,
+            },
+        ];
+        let query = parse(&[
+            ("status[in]", "open,archived"),
+            ("stars[gt]", "1"),
+            ("sort", "-stars,title"),
+            ("per_page", "1"),
+            ("page", "2"),
+        ])
+        .unwrap();
+        let page = query.apply(&notes);
+        assert_eq!(page.total, 2);
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.items[0].title, "b");
+
+        let contains = parse(&[("title[contains]", "c"), ("stars", "nine")]).unwrap();
+        assert_eq!(contains.apply(&notes).total, 0);
+    }
+
+    #[test]
+    fn renders_sql_with_bound_values() {
+        let query = parse(&[
+            ("status[in]", "open, archived"),
+            ("title[contains]", "50%_off"),
+            ("stars[lt]", "5"),
+            ("sort", "title"),
+            ("page", "2"),
+        ])
+        .unwrap();
+        let sql = query.to_sql(Placeholders::Dollar);
+        assert_eq!(
+            sql.to_string(),
+            "WHERE status IN ($1, $2) AND title LIKE $3 ESCAPE '\\' AND stars < $4 \
+             ORDER BY title ASC LIMIT 20 OFFSET 20"
+        );
+        assert_eq!(sql.params, ["open", "archived", "%50\\%\\_off%", "5"]);
+
+        let sql = parse(&[("status", "open")])
+            .unwrap()
+            .to_sql(Placeholders::Question);
+        assert_eq!(
+            sql.to_string(),
+            "WHERE status = ? ORDER BY stars DESC LIMIT 20 OFFSET 0"
+        );
+    }
+}
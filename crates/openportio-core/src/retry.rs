@@ -0,0 +1,378 @@
+use std::{
+    collections::hash_map::RandomState,
+    fmt,
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    io,
+    time::Duration,
+};
+
+use tokio::sync::watch;
+
+use crate::OpenportioError;
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(10);
+const DEFAULT_MULTIPLIER: f64 = 2.0;
+const DEFAULT_JITTER: f64 = 0.2;
+
+/// Whether repeating a failed operation can succeed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Retryability {
+    /// Transient: timeouts, dropped connections, unavailable upstreams.
+    Retryable,
+    /// Retrying returns the same error (bad input, missing permission, bugs).
+    NonRetryable,
+    /// The callee asked us to slow down; `retry_after` overrides the computed backoff.
+    Throttled { retry_after: Option<Duration> },
+}
+
+impl Retryability {
+    pub fn is_retryable(self) -> bool {
+        !matches!(self, Self::NonRetryable)
+    }
+}
+
+/// Errors that know their [`Retryability`]; used by [`with_retry`].
+pub trait Classify {
+    fn retryability(&self) -> Retryability;
+}
+
+impl Classify for OpenportioError {
+    fn retryability(&self) -> Retryability {
+        match self {
+            Self::Validation(_) | Self::Internal(_) | Self::Cancelled => Retryability::NonRetryable,
+            Self::Unavailable(_) => Retryability::Retryable,
+            Self::Throttled { retry_after, .. } => Retryability::Throttled {
+                retry_after: *retry_after,
+            },
+        }
+    }
+}
+
+impl Classify for io::Error {
+    fn retryability(&self) -> Retryability {
+        match self.kind() {
+            io::ErrorKind::TimedOut
+            | io::ErrorKind::Interrupted
+            | io::ErrorKind::WouldBlock
+            | io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::UnexpectedEof => Retryability::Retryable,
+            _ => Retryability::NonRetryable,
+        }
+    }
+}
+
+/// Attempts and exponential backoff for [`with_retry`]. Defaults: 3 attempts, 100ms doubling
+/// up to 10s, each delay shortened by up to 20% at random.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    jitter: f64,
+    cancel: Option<watch::Receiver<bool>>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            multiplier: DEFAULT_MULTIPLIER,
+            jitter: DEFAULT_JITTER,
+            cancel: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Total attempts including the first call; at least 1.
+    pub fn with_max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    pub fn with_initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    pub fn with_max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    /// Fraction (0..=1) of each delay that is randomly removed; 0 disables jitter.
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Stops retrying once `cancel` reads `true` (for example the server's shutdown signal),
+    /// including in the middle of a backoff sleep.
+    pub fn with_cancellation(mut self, cancel: watch::Receiver<bool>) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Un-jittered delay after failed attempt number `attempt` (1-based).
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let delay = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        Duration::from_secs_f64(delay.min(self.max_backoff.as_secs_f64()))
+    }
+
+    fn delay(&self, attempt: u32, retryability: Retryability) -> Duration {
+        if let Retryability::Throttled {
+            retry_after: Some(retry_after),
+        } = retryability
+        {
+            return retry_after;
+        }
+        let backoff = self.backoff(attempt);
+        if self.jitter == 0.0 {
+            return backoff;
+        }
+        let sample = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+        backoff.mul_f64(1.0 - self.jitter * sample)
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|cancel| *cancel.borrow())
+    }
+
+    async fn sleep(&self, delay: Duration) -> bool {
+        let Some(mut cancel) = self.cancel.clone() else {
+            tokio::time::sleep(delay).await;
+            return true;
+        };
+        // A dropped sender disables the cancel branch rather than cancelling.
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => true,
+            Ok(_) = cancel.wait_for(|cancelled| *cancelled) => false,
+        }
+    }
+}
+
+/// Why [`with_retry`] gave up; every variant keeps the last error when there was one.
+#[derive(Debug)]
+pub enum RetryError<E> {
+    NonRetryable(E),
+    Exhausted { attempts: u32, last: E },
+    Cancelled { attempts: u32, last: Option<E> },
+}
+
+impl<E> RetryError<E> {
+    pub fn last_error(&self) -> Option<&E> {
+        match self {
+            Self::NonRetryable(err) | Self::Exhausted { last: err, .. } => Some(err),
+            Self::Cancelled { last, .. } => last.as_ref(),
+        }
+    }
+
+    pub fn into_last_error(self) -> Option<E> {
+        match self {
+            Self::NonRetryable(err) | Self::Exhausted { last: err, .. } => Some(err),
+            Self::Cancelled { last, .. } => last,
+        }
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for RetryError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NonRetryable(err) => write!(f, "non-retryable error: {err}"),
+            Self::Exhausted { attempts, last } => {
+                write!(f, "gave up after {attempts} attempts: {last}")
+            }
+            Self::Cancelled {
+                attempts,
+                last: Some(last),
+            } => write!(f, "cancelled after {attempts} attempts: {last}"),
+            Self::Cancelled {
+                attempts,
+                last: None,
+            } => {
+                write!(f, "cancelled after {attempts} attempts")
+            }
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for RetryError<E> {}
+
+/// Runs `operation` until it succeeds, fails with a non-retryable error, runs out of attempts,
+/// or the policy's cancellation fires.
+pub async fn with_retry<T, E, F, Fut>(
+    policy: &RetryPolicy,
+    operation: F,
+) -> Result<T, RetryError<E>>
+where
+    E: Classify,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    with_retry_by(policy, E::retryability, operation).await
+}
+
+/// [`with_retry`] for error types without a [`Classify`] impl, such as `tonic::Status`.
+pub async fn with_retry_by<T, E, C, F, Fut>(
+    policy: &RetryPolicy,
+    classify: C,
+    mut operation: F,
+) -> Result<T, RetryError<E>>
+where
+    C: Fn(&E) -> Retryability,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempts = 0;
+    let mut last = None;
+    loop {
+        if policy.is_cancelled() {
+            return Err(RetryError::Cancelled { attempts, last });
+        }
+        attempts += 1;
+        let err = match operation().await {
+            Ok(value) => return Ok(value),
+            Err(err) => err,
+        };
+        let retryability = classify(&err);
+        if !retryability.is_retryable() {
+            return Err(RetryError::NonRetryable(err));
+        }
+        if attempts >= policy.max_attempts {
+            return Err(RetryError::Exhausted {
+                attempts,
+                last: err,
+            });
+        }
+        let delay = policy.delay(attempts, retryability);
+        last = Some(err);
+        if !policy.sleep(delay).await {
+            return Err(RetryError::Cancelled { attempts, last });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    fn fast() -> RetryPolicy {
+        RetryPolicy::new()
+            .with_initial_backoff(Duration::from_millis(1))
+            .with_max_attempts(4)
+    }
+
+    #[tokio::test]
+    async fn retries_transient_errors_and_stops_on_permanent_ones() {
+        let calls = AtomicU32::new(0);
+        let value = with_retry(&fast(), || async {
+            match calls.fetch_add(1, Ordering::SeqCst) {
+                0 => Err(OpenportioError::Unavailable("db down".to_string())),
+                1 => Err(OpenportioError::Throttled {
+                    message: "slow down".to_string(),
+                    retry_after: Some(Duration::from_millis(1)),
+                }),
+                _ => Ok("done"),
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!((value, calls.load(Ordering::SeqCst)), ("done", 3));
+
+        let calls = AtomicU32::new(0);
+        let err = with_retry(&fast(), || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err::<(), _>(OpenportioError::Validation("bad".to_string()))
+        })
+        .await
+        .unwrap_err();
+        assert!(matches!(err, RetryError::NonRetryable(_)));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let err = with_retry(&fast(), || async {
+            Err::<(), _>(io::Error::from(io::ErrorKind::TimedOut))
+        })
+        .await
+        .unwrap_err();
+        assert!(matches!(err, RetryError::Exhausted { attempts: 4, .. }));
+        assert_eq!(err.to_string(), "gave up after 4 attempts: timed out");
+    }
+
+    #[test]
+    fn backoff_grows_exponentially_up_to_the_cap_and_jitter_only_shortens() {
+        let policy = RetryPolicy::new().with_max_backoff(Duration::from_millis(500));
+        let delays: Vec<_> = (1..=5).map(|attempt| policy.backoff(attempt)).collect();
+        assert_eq!(delays, [100, 200, 400, 500, 500].map(Duration::from_millis));
+        for _ in 0..32 {
+            let delay = policy.delay(2, Retryability::Retryable);
+            assert!(delay <= Duration::from_millis(200) && delay >= Duration::from_millis(160));
+        }
+        let throttled = Retryability::Throttled {
+            retry_after: Some(Duration::from_secs(3)),
+        };
+        assert_eq!(policy.delay(1, throttled), Duration::from_secs(3));
+    }
+
+    #[tokio::test]
+    async fn cancellation_interrupts_the_backoff_sleep() {
+        let (cancel_tx, cancel_rx) = watch::channel(false);
+        let policy = RetryPolicy::new()
+            .with_initial_backoff(Duration::from_secs(60))
+            .with_cancellation(cancel_rx);
+        let calls = Arc::new(AtomicU32::new(0));
+        let task = tokio::spawn({
+            let calls = Arc::clone(&calls);
+            async move {
+                with_retry(&policy, || {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    async { Err::<(), _>(OpenportioError::Unavailable("down".to_string())) }
+                })
+                .await
+            }
+        });
+        while calls.load(Ordering::SeqCst) == 0 {
+            tokio::task::yield_now().await;
+        }
+        cancel_tx.send(true).unwrap();
+        let err = tokio::time::timeout(Duration::from_secs(1), task)
+            .await
+            .expect("cancellation should wake the sleep")
+            .unwrap()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            RetryError::Cancelled {
+                attempts: 1,
+                last: Some(_)
+            }
+        ));
+    }
+}
@@ -0,0 +1,107 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt,
+    sync::Arc,
+};
+
+type Entry = (&'static str, Arc<dyn Any + Send + Sync>);
+
+/// Typed application services (repositories, clients, engines) keyed by their type, so domain
+/// code can hang new services off [`crate::AppState`] without adding fields to it. Cloning is
+/// cheap; values are cloned out on lookup, so register `Arc<dyn Trait>` or cheap handles.
+#[derive(Clone, Default)]
+pub struct ServiceRegistry {
+    services: Arc<HashMap<TypeId, Entry>>,
+}
+
+impl ServiceRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `value`, replacing any earlier service of the same type.
+    pub fn insert<T>(&mut self, value: T)
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.services).insert(
+            TypeId::of::<T>(),
+            (std::any::type_name::<T>(), Arc::new(value)),
+        );
+    }
+
+    pub fn with<T>(mut self, value: T) -> Self
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.insert(value);
+        self
+    }
+
+    /// Copies every service of `other` into `self`, replacing same-typed entries.
+    pub fn extend(&mut self, other: &ServiceRegistry) {
+        let services = Arc::make_mut(&mut self.services);
+        for (type_id, entry) in other.services.iter() {
+            services.insert(*type_id, entry.clone());
+        }
+    }
+
+    pub fn get<T>(&self) -> Option<T>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.services
+            .get(&TypeId::of::<T>())
+            .and_then(|(_, value)| value.downcast_ref::<T>())
+            .cloned()
+    }
+
+    pub fn contains<T: 'static>(&self) -> bool {
+        self.services.contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn len(&self) -> usize {
+        self.services.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.services.is_empty()
+    }
+
+    /// Registered type names, sorted, for diagnostics.
+    pub fn type_names(&self) -> Vec<&'static str> {
+        let mut names: Vec<_> = self.services.values().map(|(name, _)| *name).collect();
+        names.sort_unstable();
+        names
+    }
+}
+
+impl fmt::Debug for ServiceRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServiceRegistry")
+            .field("services", &self.type_names())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Repo(&'static str);
+
+    #[test]
+    fn services_are_typed_and_clones_do_not_see_later_inserts() {
+        let registry = ServiceRegistry::new().with(Repo("postgres")).with(42_u32);
+        let snapshot = registry.clone();
+        let registry = registry.with(Repo("sqlite"));
+
+        assert_eq!(registry.get::<Repo>(), Some(Repo("sqlite")));
+        assert_eq!(snapshot.get::<Repo>(), Some(Repo("postgres")));
+        assert_eq!(registry.get::<u32>(), Some(42));
+        assert_eq!(registry.get::<String>(), None);
+        assert_eq!(registry.len(), 2);
+    }
+}
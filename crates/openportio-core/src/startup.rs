@@ -0,0 +1,248 @@
+use std::{future::Future, pin::Pin, sync::Arc, time::Duration};
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::{
+    flags::FeatureFlags, AppConfig, AppState, GreetingEngine, MetricsSink, NoopMetrics,
+    ServiceRegistry, StaticGreetingEngine,
+};
+
+const DEFAULT_VALIDATOR_TIMEOUT: Duration = Duration::from_secs(5);
+
+type ValidatorFuture = Pin<Box<dyn Future<Output = Result<(), String>> + Send>>;
+type Validator = Box<dyn FnOnce() -> ValidatorFuture + Send>;
+
+/// What a failing startup validator does to initialization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailurePolicy {
+    /// Abort `AppStateBuilder::init` with a [`StartupError`].
+    FailFast,
+    /// Start, but report the app as not ready.
+    NotReady,
+    /// Start and stay ready, reporting the dependency as degraded.
+    Degrade,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "status", content = "message")]
+pub enum CheckOutcome {
+    Ok,
+    Failed(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StartupCheck {
+    pub name: String,
+    pub policy: FailurePolicy,
+    #[serde(flatten)]
+    pub outcome: CheckOutcome,
+    pub duration_ms: u64,
+}
+
+impl StartupCheck {
+    pub fn is_ok(&self) -> bool {
+        self.outcome == CheckOutcome::Ok
+    }
+}
+
+/// Results of the startup validators, kept on [`AppState::startup`] for readiness probes.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct StartupReport {
+    pub checks: Vec<StartupCheck>,
+}
+
+impl StartupReport {
+    /// False when a `NotReady` (or, in a report from a failed init, `FailFast`) check failed.
+    pub fn is_ready(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|check| check.is_ok() || check.policy == FailurePolicy::Degrade)
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        self.checks
+            .iter()
+            .any(|check| !check.is_ok() && check.policy == FailurePolicy::Degrade)
+    }
+
+    /// `ready`, `degraded` or `not_ready`.
+    pub fn status(&self) -> &'static str {
+        if !self.is_ready() {
+            "not_ready"
+        } else if self.is_degraded() {
+            "degraded"
+        } else {
+            "ready"
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("startup check `{check}` failed: {message}")]
+pub struct StartupError {
+    pub check: String,
+    pub message: String,
+    pub report: StartupReport,
+}
+
+/// Async counterpart of [`AppState::new`]: runs startup validators (database reachable? JWKS
+/// reachable?) in registration order before handing out the state.
+pub struct AppStateBuilder {
+    config: AppConfig,
+    greeter: Arc<dyn GreetingEngine>,
+    metrics: Arc<dyn MetricsSink>,
+    flags: Option<FeatureFlags>,
+    services: ServiceRegistry,
+    validators: Vec<(String, FailurePolicy, Validator)>,
+    validator_timeout: Duration,
+}
+
+impl AppStateBuilder {
+    pub fn new(config: AppConfig) -> Self {
+        Self {
+            config,
+            greeter: Arc::new(StaticGreetingEngine::new("Hello")),
+            metrics: Arc::new(NoopMetrics),
+            flags: None,
+            services: ServiceRegistry::new(),
+            validators: Vec::new(),
+            validator_timeout: DEFAULT_VALIDATOR_TIMEOUT,
+        }
+    }
+
+    pub fn with_greeter(mut self, greeter: Arc<dyn GreetingEngine>) -> Self {
+        self.greeter = greeter;
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: Arc<dyn MetricsSink>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    pub fn with_flags(mut self, flags: FeatureFlags) -> Self {
+        self.flags = Some(flags);
+        self
+    }
+
+    /// Registers a domain service on the built state's [`ServiceRegistry`].
+    pub fn with_service<T>(mut self, service: T) -> Self
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.services.insert(service);
+        self
+    }
+
+    /// Upper bound for each validator; a validator that runs longer fails. Defaults to 5s.
+    pub fn with_validator_timeout(mut self, timeout: Duration) -> Self {
+        self.validator_timeout = timeout;
+        self
+    }
+
+    pub fn validate<F, Fut, E>(
+        mut self,
+        name: impl Into<String>,
+        policy: FailurePolicy,
+        check: F,
+    ) -> Self
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: std::fmt::Display,
+    {
+        self.validators.push((
+            name.into(),
+            policy,
+            Box::new(move || Box::pin(async move { check().await.map_err(|err| err.to_string()) })),
+        ));
+        self
+    }
+
+    /// Runs every validator, stopping at the first failing `FailFast` one.
+    pub async fn init(self) -> Result<AppState, StartupError> {
+        let mut report = StartupReport::default();
+        for (name, policy, validator) in self.validators {
+            let started = tokio::time::Instant::now();
+            let outcome = match tokio::time::timeout(self.validator_timeout, validator()).await {
+                Ok(Ok(())) => CheckOutcome::Ok,
+                Ok(Err(message)) => CheckOutcome::Failed(message),
+                Err(_) => CheckOutcome::Failed(format!(
+                    "timed out after {}ms",
+                    self.validator_timeout.as_millis()
+                )),
+            };
+            report.checks.push(StartupCheck {
+                name: name.clone(),
+                policy,
+                outcome: outcome.clone(),
+                duration_ms: started.elapsed().as_millis() as u64,
+            });
+            if let (CheckOutcome::Failed(message), FailurePolicy::FailFast) = (outcome, policy) {
+                return Err(StartupError {
+                    check: name,
+                    message,
+                    report,
+                });
+            }
+        }
+
+        let mut state = AppState::new(self.config, self.greeter, self.metrics);
+        state.services.extend(&self.services);
+        let state = match self.flags {
+            Some(flags) => state.with_flags(flags),
+            None => state,
+        };
+        Ok(state.with_startup_report(report))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn policies_decide_between_abort_degrade_and_not_ready() {
+        let state = AppStateBuilder::new(AppConfig::local("startup-test"))
+            .validate("database", FailurePolicy::FailFast, || async {
+                Ok::<_, String>(())
+            })
+            .validate("cache", FailurePolicy::Degrade, || async {
+                Err::<(), _>("connection refused")
+            })
+            .init()
+            .await
+            .expect("degraded dependencies do not abort startup");
+        assert_eq!(state.startup.status(), "degraded");
+        assert!(state.startup.checks[0].is_ok());
+
+        let state = AppStateBuilder::new(AppConfig::local("startup-test"))
+            .with_validator_timeout(Duration::from_millis(10))
+            .validate("jwks", FailurePolicy::NotReady, || async {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                Ok::<_, String>(())
+            })
+            .init()
+            .await
+            .expect("not-ready dependencies do not abort startup");
+        assert_eq!(state.startup.status(), "not_ready");
+
+        let result = AppStateBuilder::new(AppConfig::local("startup-test"))
+            .validate("database", FailurePolicy::FailFast, || async {
+                Err::<(), _>("unreachable")
+            })
+            .validate("never-run", FailurePolicy::FailFast, || async {
+                Ok::<_, String>(())
+            })
+            .init()
+            .await;
+        let Err(err) = result else {
+            panic!("fail-fast dependency aborts startup");
+        };
+        assert_eq!(err.check, "database");
+        assert_eq!(err.report.checks.len(), 1);
+        assert!(!err.report.is_ready());
+    }
+}
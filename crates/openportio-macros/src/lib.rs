@@ -1,19 +1,37 @@
 use proc_macro::TokenStream;
 use proc_macro2::Span;
 use proc_macro_crate::{crate_name, FoundCrate};
-use quote::quote;
+use quote::{format_ident, quote, quote_spanned};
+use syn::meta::ParseNestedMeta;
 use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
 use syn::{
-    parse::Parse, parse_macro_input, parse_quote, Attribute, Error, FnArg, GenericArgument, Ident,
-    Item, ItemEnum, ItemFn, ItemStruct, LitStr, Pat, PatTupleStruct, PathArguments, PathSegment,
-    Token, Type,
+    parse::Parse, parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Error, Fields,
+    FnArg, GenericArgument, Ident, Item, ItemEnum, ItemFn, ItemStruct, LitStr, Pat, PatTupleStruct,
+    PathArguments, PathSegment, Token, Type,
 };
 
 struct RouteArgs {
     method: RouteMethod,
     path: LitStr,
     auto_validate: bool,
+    feature: Option<LitStr>,
+    tags: Vec<LitStr>,
+    operation_id: Option<LitStr>,
+    deprecated: bool,
+    hidden: bool,
+    protected: bool,
+    scopes: Vec<LitStr>,
+}
+
+impl RouteArgs {
+    fn has_openapi_flags(&self) -> bool {
+        !self.tags.is_empty()
+            || self.operation_id.is_some()
+            || self.deprecated
+            || self.hidden
+            || self.protected
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -25,6 +43,19 @@ enum RouteMethod {
     Delete,
 }
 
+impl RouteMethod {
+    fn ident(self) -> Ident {
+        let name = match self {
+            Self::Get => "get",
+            Self::Post => "post",
+            Self::Put => "put",
+            Self::Patch => "patch",
+            Self::Delete => "delete",
+        };
+        Ident::new(name, Span::call_site())
+    }
+}
+
 #[derive(Clone, Copy)]
 enum ExtractorKind {
     Json,
@@ -85,11 +116,42 @@ impl Parse for RouteArgs {
             .map_err(|_| Error::new(input.span(), "route path must be a string literal"))?;
 
         let mut auto_validate = false;
+        let mut feature = None;
+        let mut tags = Vec::new();
+        let mut operation_id = None;
+        let mut deprecated = false;
+        let mut hidden = false;
+        let mut protected = false;
+        let mut scopes = Vec::new();
         while !input.is_empty() {
             input.parse::<Token![,]>()?;
             let flag: Ident = input.parse()?;
             match flag.to_string().as_str() {
                 "auto_validate" => auto_validate = true,
+                "deprecated" => deprecated = true,
+                "hidden" => hidden = true,
+                "protected" => protected = true,
+                "scope" => {
+                    scopes.push(parse_non_empty_str(input, "scope")?);
+                    protected = true;
+                }
+                "tag" => tags.push(parse_non_empty_str(input, "tag")?),
+                "operation_id" => {
+                    if operation_id.is_some() {
+                        return Err(Error::new(flag.span(), "duplicate `operation_id`"));
+                    }
+                    operation_id = Some(parse_non_empty_str(input, "operation_id")?);
+                }
+                "feature" => {
+                    input.parse::<Token![=]>()?;
+                    let name: LitStr = input.parse().map_err(|_| {
+                        Error::new(input.span(), "feature name must be a string literal")
+                    })?;
+                    if name.value().trim().is_empty() {
+                        return Err(Error::new(name.span(), "feature name must not be empty"));
+                    }
+                    feature = Some(name);
+                }
                 _ => return Err(Error::new(flag.span(), format!("unknown flag `{}`", flag))),
             }
         }
@@ -98,10 +160,31 @@ impl Parse for RouteArgs {
             method,
             path,
             auto_validate,
+            feature,
+            tags,
+            operation_id,
+            deprecated,
+            hidden,
+            protected,
+            scopes,
         })
     }
 }
 
+fn parse_non_empty_str(input: syn::parse::ParseStream<'_>, flag: &str) -> syn::Result<LitStr> {
+    input.parse::<Token![=]>()?;
+    let value: LitStr = input
+        .parse()
+        .map_err(|_| Error::new(input.span(), format!("`{flag}` must be a string literal")))?;
+    if value.value().trim().is_empty() {
+        return Err(Error::new(
+            value.span(),
+            format!("`{flag}` must not be empty"),
+        ));
+    }
+    Ok(value)
+}
+
 #[proc_macro_attribute]
 pub fn route(args: TokenStream, item: TokenStream) -> TokenStream {
     let parsed = parse_macro_input!(args as RouteArgs);
@@ -112,14 +195,108 @@ pub fn route(args: TokenStream, item: TokenStream) -> TokenStream {
             Ok(path) => path,
             Err(err) => return err.to_compile_error().into(),
         };
+        apply_path_param_checks(&mut item_fn, &parsed.path, &server_crate);
         if let Err(err) = apply_auto_validate(&mut item_fn, &server_crate) {
             return err.to_compile_error().into();
         }
     }
 
-    let _ = (parsed.method, parsed.path);
+    let feature_marker = match &parsed.feature {
+        Some(feature) => {
+            let server_crate = match resolve_openportio_server_path() {
+                Ok(path) => path,
+                Err(err) => return err.to_compile_error().into(),
+            };
+            Some(apply_feature_gate(&mut item_fn, feature, &server_crate))
+        }
+        None => None,
+    };
+
+    let openapi_path = if parsed.has_openapi_flags() {
+        let server_crate = match resolve_openportio_server_path() {
+            Ok(path) => path,
+            Err(err) => return err.to_compile_error().into(),
+        };
+        Some(openapi_path_item(&parsed, &item_fn, &server_crate))
+    } else {
+        None
+    };
+
+    let dependencies = match injected_extractor_types(&item_fn) {
+        types if types.is_empty() => None,
+        types => {
+            let server_crate = match resolve_openportio_server_path() {
+                Ok(path) => path,
+                Err(err) => return err.to_compile_error().into(),
+            };
+            Some(route_dependency_registration(
+                &parsed,
+                &types,
+                &server_crate,
+            ))
+        }
+    };
+
+    TokenStream::from(quote! {
+        #feature_marker
+        #openapi_path
+        #dependencies
+        #item_fn
+    })
+}
 
-    TokenStream::from(quote! { #item_fn })
+/// Argument types of a non-generic handler whose last path segment is `Depends`, `Named` or
+/// `Lazy`. Types that only share the name resolve to no keys through the fallback in `di`.
+fn injected_extractor_types(item_fn: &ItemFn) -> Vec<&Type> {
+    if !item_fn.sig.generics.params.is_empty() {
+        return Vec::new();
+    }
+    item_fn
+        .sig
+        .inputs
+        .iter()
+        .filter_map(|input| match input {
+            FnArg::Typed(arg) => Some(arg.ty.as_ref()),
+            FnArg::Receiver(_) => None,
+        })
+        .filter(|ty| {
+            let Type::Path(type_path) = ty else {
+                return false;
+            };
+            type_path.path.segments.last().is_some_and(|segment| {
+                matches!(
+                    segment.ident.to_string().as_str(),
+                    "Depends" | "Named" | "Lazy"
+                )
+            })
+        })
+        .collect()
+}
+
+/// Submits the handler's injected extractors to the `di` registry, so the builder's dependency
+/// graph fails `build_app()` when one has no provider instead of answering `500` per request.
+fn route_dependency_registration(
+    args: &RouteArgs,
+    types: &[&Type],
+    server_crate: &syn::Path,
+) -> proc_macro2::TokenStream {
+    let route = format!(
+        "{} {}",
+        args.method.ident().to_string().to_ascii_uppercase(),
+        args.path.value()
+    );
+    quote! {
+        #server_crate::inventory::submit! {
+            #server_crate::di::RouteDependencies {
+                route: #route,
+                extractors: &[#({
+                    #[allow(unused_imports)]
+                    use #server_crate::di::ExtractorDependencyFallback as _;
+                    #server_crate::di::ExtractorDependency::<#types>::keys
+                }),*],
+            }
+        }
+    }
 }
 
 #[proc_macro_attribute]
@@ -140,20 +317,395 @@ pub fn dto(args: TokenStream, item: TokenStream) -> TokenStream {
     };
 
     let apply_result = match &mut item {
-        Item::Struct(ItemStruct { attrs, .. }) => ensure_dto_derives(attrs, &server_crate),
-        Item::Enum(ItemEnum { attrs, .. }) => ensure_dto_derives(attrs, &server_crate),
+        Item::Struct(item_struct) => ensure_dto_derives(&mut item_struct.attrs, &server_crate)
+            .map(|()| path_param_fields_impl(item_struct, &server_crate)),
+        Item::Enum(ItemEnum { attrs, .. }) => {
+            ensure_dto_derives(attrs, &server_crate).map(|()| None)
+        }
         _ => Err(Error::new(
             item.span(),
             "`#[dto]` can only be used on structs or enums",
         )),
     };
 
+    let registration = match &item {
+        Item::Struct(item_struct) => {
+            dto_schema_registration(&item_struct.ident, &item_struct.generics, &server_crate)
+        }
+        Item::Enum(item_enum) => {
+            dto_schema_registration(&item_enum.ident, &item_enum.generics, &server_crate)
+        }
+        _ => None,
+    };
+
     match apply_result {
-        Ok(()) => TokenStream::from(quote!(#item)),
+        Ok(path_fields) => TokenStream::from(quote! {
+            #item
+            #path_fields
+            #registration
+        }),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Submits the schema of a non-generic `#[dto]` type to the `api_docs` registry through
+/// `inventory`, so served OpenAPI documents include it without a `components(schemas(...))`
+/// entry. Generic types have no single schema to register.
+fn dto_schema_registration(
+    ident: &syn::Ident,
+    generics: &syn::Generics,
+    server_crate: &syn::Path,
+) -> Option<proc_macro2::TokenStream> {
+    if !generics.params.is_empty() {
+        return None;
+    }
+    Some(quote! {
+        #server_crate::inventory::submit! {
+            #server_crate::api_docs::DtoSchema::of::<#ident>()
+        }
+    })
+}
+
+/// Implements `grpc::GrpcService` for the self type of an `impl <Trait> for <Service>` block of
+/// a tonic-generated service trait, so `OpenportioServer::with_grpc(service)` can mount it behind
+/// the auth interceptor with the builder's `GrpcMessageConfig` applied. The server type defaults
+/// to `<Trait>Server` next to the trait path; `server = path::to::FooServer` overrides it and
+/// `descriptor = path::to::FILE_DESCRIPTOR_SET` registers the descriptor set for reflection and
+/// `describe()`.
+#[proc_macro_attribute]
+pub fn grpc_service(args: TokenStream, item: TokenStream) -> TokenStream {
+    let mut server = None;
+    let mut descriptor = None;
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("server") {
+            server = Some(meta.value()?.parse::<syn::Path>()?);
+            Ok(())
+        } else if meta.path.is_ident("descriptor") {
+            descriptor = Some(meta.value()?.parse::<syn::Path>()?);
+            Ok(())
+        } else {
+            Err(meta.error("unknown argument; use `server = ...` or `descriptor = ...`"))
+        }
+    });
+    parse_macro_input!(args with parser);
+    let item_impl = parse_macro_input!(item as syn::ItemImpl);
+
+    match grpc_service_impl(&item_impl, server, descriptor) {
+        Ok(service_impl) => TokenStream::from(quote! {
+            #item_impl
+            #service_impl
+        }),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn grpc_service_impl(
+    item_impl: &syn::ItemImpl,
+    server: Option<syn::Path>,
+    descriptor: Option<syn::Path>,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let Some((_, trait_path, _)) = &item_impl.trait_ else {
+        return Err(Error::new(
+            item_impl.span(),
+            "`#[grpc_service]` must be applied to `impl <GeneratedTrait> for <Service>`",
+        ));
+    };
+    let server = match server {
+        Some(server) => server,
+        None => {
+            let mut server = trait_path.clone();
+            let last = server
+                .segments
+                .last_mut()
+                .ok_or_else(|| Error::new(trait_path.span(), "empty trait path"))?;
+            last.ident = format_ident!("{}Server", last.ident);
+            last.arguments = PathArguments::None;
+            server
+        }
+    };
+    let server_crate = resolve_openportio_server_path()?;
+    let self_ty = &item_impl.self_ty;
+    let (impl_generics, _, where_clause) = item_impl.generics.split_for_impl();
+    let descriptor = match descriptor {
+        Some(descriptor) => quote!(::core::option::Option::Some(#descriptor)),
+        None => quote!(::core::option::Option::None),
+    };
+    Ok(quote! {
+        impl #impl_generics #server_crate::grpc::GrpcService for #self_ty #where_clause {
+            type Server = #server<#self_ty>;
+
+            const DESCRIPTOR_SET: ::core::option::Option<&'static [u8]> = #descriptor;
+
+            fn into_server(self) -> Self::Server {
+                #server::new(self)
+            }
+
+            fn configure_server(
+                server: Self::Server,
+                config: &#server_crate::grpc_limits::GrpcMessageConfig,
+            ) -> Self::Server {
+                let server = server
+                    .max_decoding_message_size(config.max_decoding_message_size)
+                    .max_encoding_message_size(config.max_encoding_message_size);
+                let server = config
+                    .accept_compression
+                    .iter()
+                    .fold(server, |server, encoding| server.accept_compressed(encoding.encoding()));
+                config
+                    .send_compression
+                    .iter()
+                    .fold(server, |server, encoding| server.send_compressed(encoding.encoding()))
+            }
+        }
+    })
+}
+
+/// Implements `RequestValidation` from `#[validate_with(path::to::rule)]` hooks. Field rules take
+/// `&FieldType`, struct rules take `&Self`; both return `api::ValidationRuleResult`. A type that
+/// also derives `validator::Validate` (it has `#[validate(...)]` attributes) runs those rules
+/// first. Every rule runs and all violations are reported together.
+#[proc_macro_derive(RequestValidation, attributes(validate_with))]
+pub fn derive_request_validation(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+    let server_crate = match resolve_openportio_server_path() {
+        Ok(path) => path,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    match request_validation_impl(&input, &server_crate) {
+        Ok(tokens) => tokens.into(),
         Err(err) => err.to_compile_error().into(),
     }
 }
 
+fn request_validation_impl(
+    input: &DeriveInput,
+    server_crate: &syn::Path,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let mut checks = Vec::new();
+    if uses_validator(input) {
+        checks.push(quote! {
+            if let ::core::result::Result::Err(err) =
+                #server_crate::validator::Validate::validate(self)
+            {
+                issues.extend(#server_crate::api::validation_issues(&err, source));
+            }
+        });
+    }
+    if let Data::Struct(data) = &input.data {
+        for (idx, field) in data.fields.iter().enumerate() {
+            let rules = validate_with_paths(&field.attrs)?;
+            if rules.is_empty() {
+                continue;
+            }
+            let (member, name) = match &field.ident {
+                Some(ident) => {
+                    let name = serde_attrs(&field.attrs)?
+                        .rename
+                        .unwrap_or_else(|| ident.to_string().trim_start_matches("r#").to_string());
+                    (quote!(#ident), name)
+                }
+                None => {
+                    let index = syn::Index::from(idx);
+                    (quote!(#index), idx.to_string())
+                }
+            };
+            checks.extend(rules.into_iter().map(|rule| {
+                quote! {
+                    if let ::core::result::Result::Err(err) = #rule(&self.#member) {
+                        issues.push(err.into_issue(source, ::core::option::Option::Some(#name)));
+                    }
+                }
+            }));
+        }
+    } else if let Data::Enum(data) = &input.data {
+        if let Some(attr) = data
+            .variants
+            .iter()
+            .flat_map(|variant| &variant.attrs)
+            .find(|attr| attr.path().is_ident("validate_with"))
+        {
+            return Err(Error::new(
+                attr.span(),
+                "`#[validate_with(...)]` is not supported on enum variants; put it on the enum",
+            ));
+        }
+    }
+    checks.extend(validate_with_paths(&input.attrs)?.into_iter().map(|rule| {
+        quote! {
+            if let ::core::result::Result::Err(err) = #rule(self) {
+                issues.push(err.into_issue(source, ::core::option::Option::None));
+            }
+        }
+    }));
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #server_crate::api::RequestValidation for #ident #ty_generics #where_clause {
+            fn validate_request(
+                &self,
+                source: &'static str,
+            ) -> ::core::result::Result<(), #server_crate::api::ApiError> {
+                let mut issues: ::std::vec::Vec<#server_crate::api::ApiValidationIssue> =
+                    ::std::vec::Vec::new();
+                #(#checks)*
+                if issues.is_empty() {
+                    ::core::result::Result::Ok(())
+                } else {
+                    ::core::result::Result::Err(#server_crate::api::validation_issues_error(issues))
+                }
+            }
+        }
+    })
+}
+
+/// `#[validate(...)]` is the helper attribute of `validator::Validate`'s derive, so its presence
+/// on the type or a field means the type implements `Validate`.
+fn uses_validator(input: &DeriveInput) -> bool {
+    let is_validate = |attr: &Attribute| attr.path().is_ident("validate");
+    let field_attrs: Vec<&Attribute> = match &input.data {
+        Data::Struct(data) => data.fields.iter().flat_map(|field| &field.attrs).collect(),
+        _ => Vec::new(),
+    };
+    input.attrs.iter().any(is_validate) || field_attrs.into_iter().any(is_validate)
+}
+
+/// Implements `api::FromPathParams` for a struct with named fields, so it can be extracted with
+/// `TypedPath<T>`. Each field is parsed from the route parameter of the same name (or
+/// `#[path_param(rename = "...")]`) with `FromStr`; `Option` fields may be absent. Every parse
+/// failure is reported as a `validation_error` issue at `["path", field]`.
+#[proc_macro_derive(TypedPath, attributes(path_param))]
+pub fn derive_typed_path(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+    let server_crate = match resolve_openportio_server_path() {
+        Ok(path) => path,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    match typed_path_impl(&input, &server_crate) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn typed_path_impl(
+    input: &DeriveInput,
+    server_crate: &syn::Path,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new(
+                    input.ident.span(),
+                    "`#[derive(TypedPath)]` needs named fields; use `TypedPath<T>` with a scalar for a single parameter",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                input.ident.span(),
+                "`#[derive(TypedPath)]` only supports structs",
+            ))
+        }
+    };
+
+    let mut parses = Vec::new();
+    let mut required = Vec::new();
+    let mut members = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named field");
+        let name = path_param_rename(&field.attrs)?
+            .unwrap_or_else(|| ident.to_string().trim_start_matches("r#").to_string());
+        let parse = match option_inner_type(&field.ty) {
+            Some(inner) => quote_spanned! {field.ty.span()=>
+                let #ident = params.parse_optional::<#inner>(#name, &mut issues);
+            },
+            None => {
+                required.push(ident);
+                let ty = &field.ty;
+                quote_spanned! {field.ty.span()=>
+                    let #ident = params.parse::<#ty>(#name, &mut issues);
+                }
+            }
+        };
+        parses.push(parse);
+        members.push(ident);
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #server_crate::api::FromPathParams for #ident #ty_generics #where_clause {
+            fn from_path_params(
+                params: &#server_crate::api::PathParams,
+            ) -> ::core::result::Result<Self, ::std::vec::Vec<#server_crate::api::ApiValidationIssue>> {
+                let mut issues: ::std::vec::Vec<#server_crate::api::ApiValidationIssue> =
+                    ::std::vec::Vec::new();
+                #(#parses)*
+                match (#(#required,)*) {
+                    (#(::core::option::Option::Some(#required),)*) if issues.is_empty() => {
+                        ::core::result::Result::Ok(Self { #(#members),* })
+                    }
+                    _ => ::core::result::Result::Err(issues),
+                }
+            }
+        }
+    })
+}
+
+fn path_param_rename(attrs: &[Attribute]) -> syn::Result<Option<String>> {
+    let mut rename = None;
+    for attr in attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("path_param"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else {
+                Err(meta.error("expected `rename = \"...\"`"))
+            }
+        })?;
+    }
+    Ok(rename)
+}
+
+fn option_inner_type(ty: &Type) -> Option<&Type> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(inner) => Some(inner),
+        _ => None,
+    }
+}
+
+fn validate_with_paths(attrs: &[Attribute]) -> syn::Result<Vec<syn::Path>> {
+    let mut paths = Vec::new();
+    for attr in attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("validate_with"))
+    {
+        let parsed = attr.parse_args_with(Punctuated::<syn::Path, Token![,]>::parse_terminated)?;
+        if parsed.is_empty() {
+            return Err(Error::new(
+                attr.span(),
+                "`#[validate_with(...)]` expects at least one rule function path",
+            ));
+        }
+        paths.extend(parsed);
+    }
+    Ok(paths)
+}
+
 fn resolve_openportio_server_path() -> syn::Result<syn::Path> {
     let found = crate_name("openportio-server").or_else(|_| crate_name("alloy-server"));
     match found {
@@ -172,9 +724,10 @@ fn resolve_openportio_server_path() -> syn::Result<syn::Path> {
 }
 
 fn ensure_dto_derives(attrs: &mut Vec<Attribute>, server_crate: &syn::Path) -> syn::Result<()> {
-    let required: [syn::Path; 3] = [
+    let required: [syn::Path; 4] = [
         parse_quote!(#server_crate::serde::Deserialize),
         parse_quote!(#server_crate::validator::Validate),
+        parse_quote!(#server_crate::RequestValidation),
         parse_quote!(#server_crate::utoipa::ToSchema),
     ];
     let mut existing_last_segments = std::collections::BTreeSet::new();
@@ -242,6 +795,286 @@ fn apply_auto_validate(item_fn: &mut ItemFn, server_crate: &syn::Path) -> syn::R
     }
 }
 
+fn apply_feature_gate(
+    item_fn: &mut ItemFn,
+    feature: &LitStr,
+    server_crate: &syn::Path,
+) -> proc_macro2::TokenStream {
+    let marker = format_ident!("__OpenportioFeature_{}", item_fn.sig.ident);
+    let gate: FnArg = parse_quote!(_: #server_crate::flags::FeatureGate<#marker>);
+    item_fn.sig.inputs.insert(0, gate);
+
+    quote! {
+        #[doc(hidden)]
+        #[allow(non_camel_case_types)]
+        struct #marker;
+
+        impl #server_crate::flags::FeatureName for #marker {
+            const NAME: &'static str = #feature;
+        }
+    }
+}
+
+/// Emits the `__path_{handler}` struct `#[openapi(paths(handler))]` expects. `utoipa::path` is
+/// applied to an argument-less stand-in so it never infers parameters or bodies from the real
+/// extractors, and so `deprecated` marks only the operation, not the handler function.
+fn openapi_path_item(
+    args: &RouteArgs,
+    item_fn: &ItemFn,
+    server_crate: &syn::Path,
+) -> proc_macro2::TokenStream {
+    let handler = &item_fn.sig.ident;
+    let vis = &item_fn.vis;
+    let path_struct = format_ident!("__path_{}", handler);
+    let method = args.method.ident();
+    let path = LitStr::new(&openapi_path(&args.path.value()), args.path.span());
+    let operation_id = args.operation_id.iter();
+    let tags = &args.tags;
+    let params = route_placeholders(&args.path.value());
+    let docs = item_fn
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"));
+    let deprecated = args.deprecated.then(|| quote!(#[deprecated]));
+    let scopes = &args.scopes;
+    let security = args
+        .protected
+        .then(|| quote!(security(("bearerAuth" = [#(#scopes),*])),));
+    let extensions = args
+        .hidden
+        .then(|| quote!(extensions(("x-openportio-hidden" = json!(true))),));
+
+    quote! {
+        #[doc(hidden)]
+        #[allow(non_camel_case_types)]
+        #[derive(Clone)]
+        #vis struct #path_struct;
+
+        const _: () = {
+            use #server_crate::utoipa;
+
+            #[utoipa::path(
+                #method,
+                path = #path,
+                impl_for = #path_struct,
+                #(operation_id = #operation_id,)*
+                tags = [#(#tags),*],
+                params(#((#params, Path)),*),
+                #security
+                #extensions
+                responses((status = 200, description = "Successful response"))
+            )]
+            #(#docs)*
+            #deprecated
+            #[allow(dead_code)]
+            fn #handler() {}
+        };
+    }
+}
+
+/// `/notes/:id` and `/files/*rest` in OpenAPI form: `/notes/{id}`, `/files/{rest}`.
+fn openapi_path(path: &str) -> String {
+    path.split('/')
+        .map(|segment| {
+            match segment
+                .strip_prefix(':')
+                .or_else(|| segment.strip_prefix('*'))
+            {
+                Some(name) => format!("{{{name}}}"),
+                None => segment.replace("{*", "{"),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// Placeholders use axum's `:name` / `*name` syntax; `{name}` is accepted for OpenAPI-style paths.
+fn route_placeholders(path: &str) -> Vec<String> {
+    path.split('/')
+        .filter_map(|segment| {
+            let name = segment
+                .strip_prefix(':')
+                .or_else(|| segment.strip_prefix('*'))
+                .or_else(|| {
+                    segment
+                        .strip_prefix('{')
+                        .and_then(|rest| rest.strip_suffix('}'))
+                        .map(|name| name.trim_start_matches('*'))
+                })?;
+            (!name.is_empty()).then(|| name.to_string())
+        })
+        .collect()
+}
+
+/// Prepends const assertions that every route placeholder has a field in the `Path<T>` struct and
+/// that every required field is a placeholder. Types without `#[dto]` resolve to no field list
+/// and pass; generic handlers are skipped because nested items cannot name their parameters.
+fn apply_path_param_checks(item_fn: &mut ItemFn, route_path: &LitStr, server_crate: &syn::Path) {
+    if !item_fn.sig.generics.params.is_empty() {
+        return;
+    }
+    let route = route_path.value();
+    let placeholders = route_placeholders(&route);
+    let mut checks = Vec::new();
+
+    for input in &item_fn.sig.inputs {
+        let FnArg::Typed(arg) = input else {
+            continue;
+        };
+        let Ok(Some((ExtractorKind::Path, _, inner_ty))) = extract_rewrite_target(&arg.ty) else {
+            continue;
+        };
+        if matches!(inner_ty, Type::Tuple(_)) {
+            continue;
+        }
+
+        let type_name = quote!(#inner_ty).to_string().replace(' ', "");
+        let contains = placeholders.iter().map(|name| {
+            let message = escape_format_braces(&format!(
+                "route `{route}`: placeholder `{name}` has no matching field in `{type_name}`"
+            ));
+            quote_spanned! {inner_ty.span()=>
+                ::core::assert!(
+                    #server_crate::api::path_params_contain(
+                        #server_crate::api::PathParamCheck::<#inner_ty>::FIELDS,
+                        #name,
+                    ),
+                    #message
+                );
+            }
+        });
+        let cover_message = escape_format_braces(&format!(
+            "route `{route}`: `{type_name}` has required fields that are not placeholders in the route"
+        ));
+        let check: syn::Stmt = parse_quote! {
+            const _: () = {
+                #[allow(unused_imports)]
+                use #server_crate::api::PathParamFieldsFallback as _;
+                #(#contains)*
+                ::core::assert!(
+                    #server_crate::api::path_params_cover(
+                        #server_crate::api::PathParamCheck::<#inner_ty>::REQUIRED,
+                        &[#(#placeholders),*],
+                    ),
+                    #cover_message
+                );
+            };
+        };
+        checks.push(check);
+    }
+
+    item_fn.block.stmts.splice(0..0, checks);
+}
+
+fn escape_format_braces(message: &str) -> String {
+    message.replace('{', "{{").replace('}', "}}")
+}
+
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    aliases: Vec<String>,
+    optional: bool,
+    skip: bool,
+    unsupported: bool,
+}
+
+fn serde_attrs(attrs: &[Attribute]) -> syn::Result<SerdeAttrs> {
+    let mut parsed = SerdeAttrs::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                if meta.input.peek(Token![=]) {
+                    parsed.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else {
+                    meta.parse_nested_meta(|inner| {
+                        let value = inner.value()?.parse::<LitStr>()?.value();
+                        if inner.path.is_ident("deserialize") {
+                            parsed.rename = Some(value);
+                        }
+                        Ok(())
+                    })?;
+                }
+            } else if meta.path.is_ident("alias") {
+                parsed
+                    .aliases
+                    .push(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("default") {
+                parsed.optional = true;
+                skip_meta_value(&meta)?;
+            } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_deserializing") {
+                parsed.skip = true;
+            } else if meta.path.is_ident("flatten") || meta.path.is_ident("rename_all") {
+                parsed.unsupported = true;
+                skip_meta_value(&meta)?;
+            } else {
+                skip_meta_value(&meta)?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(parsed)
+}
+
+fn skip_meta_value(meta: &ParseNestedMeta<'_>) -> syn::Result<()> {
+    if meta.input.peek(Token![=]) {
+        meta.value()?.parse::<syn::Expr>()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        meta.parse_nested_meta(|inner| skip_meta_value(&inner))?;
+    }
+    Ok(())
+}
+
+fn is_option_type(ty: &Type) -> bool {
+    matches!(ty, Type::Path(type_path)
+        if type_path.path.segments.last().is_some_and(|segment| segment.ident == "Option"))
+}
+
+/// Emits `PathParamFields` for named-field structs. Structs using serde features the check cannot
+/// model (`flatten`, `rename_all`, unparsable attributes) get no impl and are not checked.
+fn path_param_fields_impl(
+    item_struct: &ItemStruct,
+    server_crate: &syn::Path,
+) -> Option<proc_macro2::TokenStream> {
+    let Fields::Named(fields) = &item_struct.fields else {
+        return None;
+    };
+    let container = serde_attrs(&item_struct.attrs).ok()?;
+    if container.unsupported {
+        return None;
+    }
+
+    let mut accepted = Vec::new();
+    let mut required = Vec::new();
+    for field in &fields.named {
+        let attrs = serde_attrs(&field.attrs).ok()?;
+        if attrs.unsupported {
+            return None;
+        }
+        if attrs.skip {
+            continue;
+        }
+        let name = attrs.rename.clone().unwrap_or_else(|| {
+            let ident = field.ident.as_ref().expect("named field").to_string();
+            ident.trim_start_matches("r#").to_string()
+        });
+        if !(container.optional || attrs.optional || is_option_type(&field.ty)) {
+            required.push(name.clone());
+        }
+        accepted.push(name);
+        accepted.extend(attrs.aliases);
+    }
+
+    let ident = &item_struct.ident;
+    let (impl_generics, ty_generics, where_clause) = item_struct.generics.split_for_impl();
+    Some(quote! {
+        impl #impl_generics #server_crate::api::PathParamFields for #ident #ty_generics #where_clause {
+            const FIELDS: &'static [&'static str] = &[#(#accepted),*];
+            const REQUIRED: &'static [&'static str] = &[#(#required),*];
+        }
+    })
+}
+
 fn maybe_rewrite_typed_arg(arg: &mut syn::PatType, server_crate: &syn::Path) -> syn::Result<()> {
     let (kind, original_segment, inner_ty) = match extract_rewrite_target(&arg.ty)? {
         Some(values) => values,
@@ -385,8 +1218,38 @@ fn extract_single_generic_type(arguments: &PathArguments) -> syn::Result<Type> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use quote::ToTokens;
     use syn::{parse_quote, parse_str};
 
+    #[test]
+    fn collects_injected_extractor_types_of_non_generic_handlers() {
+        let item_fn: ItemFn = parse_quote! {
+            async fn report(
+                Depends(service): Depends<Service>,
+                store: di::Named<Store, Reporting>,
+                audit: Lazy<Audit>,
+                Json(body): Json<Body>,
+            ) {}
+        };
+        let types: Vec<String> = injected_extractor_types(&item_fn)
+            .into_iter()
+            .map(|ty| ty.to_token_stream().to_string())
+            .collect();
+        assert_eq!(
+            types,
+            [
+                "Depends < Service >",
+                "di :: Named < Store , Reporting >",
+                "Lazy < Audit >"
+            ]
+        );
+
+        let generic: ItemFn = parse_quote! {
+            async fn report<S>(store: Lazy<S>) {}
+        };
+        assert!(injected_extractor_types(&generic).is_empty());
+    }
+
     #[test]
     fn parses_method_path_and_auto_validate_flag() {
         let parsed = parse_str::<RouteArgs>(r#"post, "/notes", auto_validate"#)
@@ -415,6 +1278,159 @@ mod tests {
         assert!(err.to_string().contains("unsupported method"));
     }
 
+    #[test]
+    fn parses_feature_flag_alongside_auto_validate() {
+        let parsed =
+            parse_str::<RouteArgs>(r#"get, "/v2/notes", auto_validate, feature = "new_notes_api""#)
+                .expect("route args should parse");
+
+        assert!(parsed.auto_validate);
+        assert_eq!(
+            parsed.feature.map(|feature| feature.value()).as_deref(),
+            Some("new_notes_api")
+        );
+    }
+
+    #[test]
+    fn rejects_non_string_feature_name() {
+        let err = match parse_str::<RouteArgs>(r#"get, "/notes", feature = new_notes_api"#) {
+            Ok(_) => panic!("non-string feature must fail"),
+            Err(err) => err,
+        };
+        assert!(err.to_string().contains("string literal"));
+    }
+
+    #[test]
+    fn feature_gate_prepends_gate_extractor() {
+        let mut item_fn: ItemFn = parse_quote! {
+            async fn list_notes(Query(q): Query<ListQuery>) {}
+        };
+        let server_crate: syn::Path = parse_quote!(::openportio_server);
+        let feature: LitStr = parse_quote!("new_notes_api");
+        let marker = apply_feature_gate(&mut item_fn, &feature, &server_crate).to_string();
+
+        assert_eq!(item_fn.sig.inputs.len(), 2);
+        let first = item_fn
+            .sig
+            .inputs
+            .iter()
+            .next()
+            .expect("gate arg should exist");
+        assert_eq!(arg_type_ident(first), Some("FeatureGate".to_string()));
+        assert!(marker.contains("__OpenportioFeature_list_notes"));
+        assert!(marker.contains("\"new_notes_api\""));
+    }
+
+    #[test]
+    fn route_placeholders_support_colon_wildcard_and_brace_syntax() {
+        assert_eq!(
+            route_placeholders("/orgs/:org_id/notes/{note_id}/*rest"),
+            vec!["org_id", "note_id", "rest"]
+        );
+        assert!(route_placeholders("/notes").is_empty());
+    }
+
+    #[test]
+    fn path_param_checks_assert_placeholders_against_path_struct() {
+        let mut item_fn: ItemFn = parse_quote! {
+            async fn get_note(Path(path): Path<NotePath>, Query(q): Query<ListQuery>) {}
+        };
+        let server_crate: syn::Path = parse_quote!(::openportio_server);
+        let route: LitStr = parse_quote!("/notes/:id");
+        apply_path_param_checks(&mut item_fn, &route, &server_crate);
+
+        assert_eq!(item_fn.block.stmts.len(), 1);
+        let rendered = item_fn.block.stmts[0].to_token_stream().to_string();
+        assert!(rendered.contains("PathParamCheck :: < NotePath > :: FIELDS"));
+        assert!(rendered.contains("placeholder `id` has no matching field in `NotePath`"));
+        assert!(rendered.contains("path_params_cover"));
+
+        let mut tuple_fn: ItemFn = parse_quote! {
+            async fn get_pair(Path((a, b)): Path<(String, u32)>) {}
+        };
+        apply_path_param_checks(&mut tuple_fn, &parse_quote!("/:a/:b"), &server_crate);
+        assert!(tuple_fn.block.stmts.is_empty());
+    }
+
+    #[test]
+    fn dto_path_fields_follow_serde_rename_default_and_skip() {
+        let item: ItemStruct = parse_quote! {
+            struct NotePath {
+                #[serde(rename = "noteId")]
+                note_id: String,
+                #[serde(default)]
+                page: u32,
+                version: Option<u32>,
+                #[serde(skip)]
+                cached: bool,
+            }
+        };
+        let server_crate: syn::Path = parse_quote!(::openportio_server);
+        let rendered = path_param_fields_impl(&item, &server_crate)
+            .expect("named struct should get an impl")
+            .to_string();
+        assert!(rendered.contains(
+            "const FIELDS : & 'static [& 'static str] = & [\"noteId\" , \"page\" , \"version\"]"
+        ));
+        assert!(rendered.contains("const REQUIRED : & 'static [& 'static str] = & [\"noteId\"]"));
+
+        let flattened: ItemStruct = parse_quote! {
+            struct Outer {
+                #[serde(flatten)]
+                inner: Inner,
+            }
+        };
+        assert!(path_param_fields_impl(&flattened, &server_crate).is_none());
+    }
+
+    #[test]
+    fn parses_openapi_tag_operation_id_and_deprecated_flags() {
+        let parsed = parse_str::<RouteArgs>(
+            r#"get, "/notes/:id", tag = "notes", tag = "public", operation_id = "getNote", deprecated"#,
+        )
+        .expect("route args should parse");
+        assert!(parsed.has_openapi_flags());
+        assert_eq!(
+            parsed.tags.iter().map(LitStr::value).collect::<Vec<_>>(),
+            ["notes", "public"]
+        );
+        assert_eq!(
+            parsed.operation_id.map(|id| id.value()).as_deref(),
+            Some("getNote")
+        );
+        assert!(parsed.deprecated);
+        assert_eq!(
+            openapi_path("/notes/:id/files/*rest"),
+            "/notes/{id}/files/{rest}"
+        );
+
+        let err = match parse_str::<RouteArgs>(r#"get, "/notes", operation_id = """#) {
+            Ok(_) => panic!("empty operation id must fail"),
+            Err(err) => err,
+        };
+        assert!(err.to_string().contains("`operation_id` must not be empty"));
+    }
+
+    #[test]
+    fn openapi_flags_emit_a_path_struct_for_the_handler() {
+        let args = parse_str::<RouteArgs>(r#"delete, "/notes/:id", tag = "notes", deprecated"#)
+            .expect("route args should parse");
+        let item_fn: ItemFn = parse_quote! {
+            /// Deletes a note.
+            pub async fn delete_note(Path(id): Path<i64>) {}
+        };
+        let server_crate: syn::Path = parse_quote!(::openportio_server);
+        let tokens = openapi_path_item(&args, &item_fn, &server_crate).to_string();
+
+        assert!(tokens.contains("pub struct __path_delete_note"));
+        assert!(tokens.contains("impl_for = __path_delete_note"));
+        assert!(tokens.contains("path = \"/notes/{id}\""));
+        assert!(tokens.contains("params ((\"id\" , Path))"));
+        assert!(tokens.contains("# [deprecated]"));
+        assert!(tokens.contains("Deletes a note."));
+        assert!(!tokens.contains("Path < i64 >"));
+    }
+
     #[test]
     fn rejects_unknown_flag() {
         let err = match parse_str::<RouteArgs>(r#"post, "/notes", unknown_flag"#) {
@@ -543,7 +1559,7 @@ mod tests {
     }
 
     #[test]
-    fn dto_injects_deserialize_validate_and_schema_derives() {
+    fn dto_injects_deserialize_validation_and_schema_derives() {
         let mut item: ItemStruct = parse_quote! {
             struct Payload {
                 #[validate(length(min = 1))]
@@ -556,6 +1572,7 @@ mod tests {
         let rendered = quote!(#item).to_string();
         assert!(rendered.contains(":: openportio_server :: serde :: Deserialize"));
         assert!(rendered.contains(":: openportio_server :: validator :: Validate"));
+        assert!(rendered.contains(":: openportio_server :: RequestValidation"));
         assert!(rendered.contains(":: openportio_server :: utoipa :: ToSchema"));
     }
 
@@ -578,6 +1595,56 @@ mod tests {
         assert!(rendered.contains(":: openportio_server :: utoipa :: ToSchema"));
     }
 
+    #[test]
+    fn request_validation_derive_calls_field_and_struct_rules() {
+        let input: DeriveInput = parse_quote! {
+            #[validate_with(rules::window)]
+            struct Booking {
+                #[serde(rename = "roomName")]
+                #[validate_with(rules::not_blank, rules::ascii)]
+                room: String,
+                start: i64,
+            }
+        };
+        let server_crate: syn::Path = parse_quote!(::openportio_server);
+        let rendered = request_validation_impl(&input, &server_crate)
+            .expect("derive should expand")
+            .to_string();
+
+        assert!(rendered.contains("rules :: not_blank (& self . room)"));
+        assert!(rendered.contains("rules :: ascii (& self . room)"));
+        assert!(rendered.contains("Some (\"roomName\")"));
+        assert!(rendered.contains("rules :: window (self)"));
+        assert!(!rendered.contains("self . start"));
+        assert!(!rendered.contains("Validate :: validate"));
+
+        let input: DeriveInput = parse_quote! {
+            struct Room {
+                #[validate(length(min = 3))]
+                name: String,
+            }
+        };
+        let rendered = request_validation_impl(&input, &server_crate)
+            .expect("derive should expand")
+            .to_string();
+        assert!(
+            rendered.contains(":: openportio_server :: validator :: Validate :: validate (self)")
+        );
+    }
+
+    #[test]
+    fn request_validation_derive_rejects_variant_rules() {
+        let input: DeriveInput = parse_quote! {
+            enum Choice {
+                #[validate_with(rules::never)]
+                A,
+            }
+        };
+        let err = request_validation_impl(&input, &parse_quote!(::openportio_server))
+            .expect_err("variant rules must fail");
+        assert!(err.to_string().contains("enum variants"));
+    }
+
     fn arg_type_ident(arg: &FnArg) -> Option<String> {
         let FnArg::Typed(arg) = arg else {
             return None;
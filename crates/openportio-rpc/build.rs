@@ -1,4 +1,9 @@
-use std::path::PathBuf;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const PROTO_ROOT: &str = "proto";
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let protoc = protoc_bin_vendored::protoc_bin_path()?;
@@ -6,14 +11,35 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let out_dir = PathBuf::from(std::env::var("OUT_DIR")?);
     let descriptor_path = out_dir.join("openportio_descriptor.bin");
 
+    let mut protos = Vec::new();
+    collect_protos(Path::new(PROTO_ROOT), &mut protos)?;
+    protos.sort();
+
+    // One module file per package plus `openportio_packages.rs`, which nests them by package path.
     tonic_build::configure()
         .build_client(true)
         .build_server(true)
         .file_descriptor_set_path(descriptor_path)
-        .compile_protos(&["proto/service.proto"], &["proto"])?;
+        .include_file("openportio_packages.rs")
+        .compile_protos(&protos, &[PROTO_ROOT])?;
+
+    println!("cargo:rerun-if-changed={PROTO_ROOT}");
+    for proto in &protos {
+        println!("cargo:rerun-if-changed={}", proto.display());
+    }
 
-    println!("cargo:rerun-if-changed=proto/service.proto");
-    println!("cargo:rerun-if-changed=proto");
+    Ok(())
+}
 
+fn collect_protos(dir: &Path, protos: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            println!("cargo:rerun-if-changed={}", path.display());
+            collect_protos(&path, protos)?;
+        } else if path.extension().is_some_and(|ext| ext == "proto") {
+            protos.push(path);
+        }
+    }
     Ok(())
 }
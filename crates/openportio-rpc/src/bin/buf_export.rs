@@ -0,0 +1,305 @@
+//! Arranges the proto sources into a Buf Schema Registry module: one directory per package
+//! (`openportio/v1/service.proto`), imports rewritten to match, plus `buf.yaml`,
+//! `buf.gen.yaml` and a module README. The export is then compiled with the vendored `protoc`
+//! and compared with the sources, so the BSR copy can never drift from the descriptor set the
+//! crate builds.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use prost::Message;
+use prost_types::{FileDescriptorProto, FileDescriptorSet};
+
+#[derive(Debug, Clone)]
+struct Config {
+    proto_root: PathBuf,
+    out: PathBuf,
+    module: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            proto_root: PathBuf::from("crates/openportio-rpc/proto"),
+            out: PathBuf::from("target/buf/openportio"),
+            module: "buf.build/openportio/openportio".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ProtoFile {
+    /// Path relative to the proto root, as used by `import`.
+    source: String,
+    /// Path inside the module, derived from the package.
+    target: String,
+    package: String,
+    contents: String,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let config = parse_args(std::env::args().skip(1))?;
+    let files = collect(&config.proto_root)?;
+    let renames: BTreeMap<String, String> = files
+        .iter()
+        .map(|file| (file.source.clone(), file.target.clone()))
+        .collect();
+
+    prepare_out_dir(&config.out)?;
+    for file in &files {
+        let path = config.out.join(&file.target);
+        fs::create_dir_all(path.parent().expect("target has a package directory"))?;
+        fs::write(path, rewrite_imports(&file.contents, &renames))?;
+    }
+    fs::write(config.out.join("buf.yaml"), buf_yaml(&config))?;
+    fs::write(config.out.join("buf.gen.yaml"), BUF_GEN_YAML)?;
+    fs::write(config.out.join("README.md"), module_readme(&config, &files))?;
+
+    verify(&config, &files, &renames)?;
+    println!(
+        "buf-export: wrote {} files for {} to {}",
+        files.len(),
+        config.module,
+        config.out.display()
+    );
+    Ok(())
+}
+
+fn parse_args(
+    args: impl IntoIterator<Item = String>,
+) -> Result<Config, Box<dyn std::error::Error>> {
+    let mut cfg = Config::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| args.next().ok_or(format!("missing value for {flag}"));
+        match arg.as_str() {
+            "--proto-root" => cfg.proto_root = PathBuf::from(value("--proto-root")?),
+            "--out" => cfg.out = PathBuf::from(value("--out")?),
+            "--module" => cfg.module = value("--module")?,
+            "--help" | "-h" => {
+                println!(
+                    "Usage: buf-export [--proto-root <dir>] [--out <dir>] [--module <buf.build/owner/name>]"
+                );
+                std::process::exit(0);
+            }
+            other => return Err(format!("unknown argument `{other}`").into()),
+        }
+    }
+    Ok(cfg)
+}
+
+fn collect(root: &Path) -> Result<Vec<ProtoFile>, Box<dyn std::error::Error>> {
+    let mut paths = Vec::new();
+    collect_paths(root, &mut paths)?;
+    paths.sort();
+
+    let mut files = Vec::new();
+    let mut targets: BTreeMap<String, String> = BTreeMap::new();
+    for path in paths {
+        let source = relative(root, &path);
+        let contents = fs::read_to_string(&path)?;
+        let package = package_of(&contents)
+            .ok_or_else(|| format!("{source}: BSR modules need a `package` in every file"))?;
+        let file_name = path
+            .file_name()
+            .expect("proto paths have a file name")
+            .to_string_lossy();
+        let target = format!("{}/{file_name}", package.replace('.', "/"));
+        if let Some(other) = targets.insert(target.clone(), source.clone()) {
+            return Err(format!("{other} and {source} would both be exported as {target}").into());
+        }
+        files.push(ProtoFile {
+            source,
+            target,
+            package,
+            contents,
+        });
+    }
+    if files.is_empty() {
+        return Err(format!("no .proto files under {}", root.display()).into());
+    }
+    Ok(files)
+}
+
+fn collect_paths(dir: &Path, paths: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_paths(&path, paths)?;
+        } else if path.extension().is_some_and(|ext| ext == "proto") {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn relative(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn package_of(contents: &str) -> Option<String> {
+    contents.lines().find_map(|line| {
+        let line = line.split("//").next().unwrap_or_default().trim();
+        let name = line.strip_prefix("package")?.trim().strip_suffix(';')?;
+        Some(name.trim().to_string())
+    })
+}
+
+/// Points `import "..."` statements at the exported paths; unknown imports (well-known types)
+/// are left alone.
+fn rewrite_imports(contents: &str, renames: &BTreeMap<String, String>) -> String {
+    let mut out = String::with_capacity(contents.len());
+    for line in contents.split_inclusive('\n') {
+        let rewritten = line.trim_start().starts_with("import").then(|| {
+            let start = line.find('"')? + 1;
+            let end = start + line[start..].find('"')?;
+            let target = renames.get(&line[start..end])?;
+            Some(format!("{}{target}{}", &line[..start], &line[end..]))
+        });
+        match rewritten.flatten() {
+            Some(line) => out.push_str(&line),
+            None => out.push_str(line),
+        }
+    }
+    out
+}
+
+fn prepare_out_dir(out: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    if out.exists() {
+        let is_empty = fs::read_dir(out)?.next().is_none();
+        if !is_empty && !out.join("buf.yaml").exists() {
+            return Err(format!(
+                "{} exists and is not a previous export; pick another --out",
+                out.display()
+            )
+            .into());
+        }
+        fs::remove_dir_all(out)?;
+    }
+    fs::create_dir_all(out)?;
+    Ok(())
+}
+
+fn buf_yaml(config: &Config) -> String {
+    format!(
+        "# Generated by `buf-export` from {}; edit the sources, not this module.\n\
+         version: v2\n\
+         modules:\n\
+         \x20 - path: .\n\
+         \x20   name: {}\n\
+         lint:\n\
+         \x20 use:\n\
+         \x20   - STANDARD\n\
+         \x20 except:\n\
+         \x20   # Naming rules the exported contracts may predate; renaming would break clients.\n\
+         \x20   - SERVICE_SUFFIX\n\
+         \x20   - RPC_REQUEST_STANDARD_NAME\n\
+         \x20   - RPC_RESPONSE_STANDARD_NAME\n\
+         \x20   - RPC_REQUEST_RESPONSE_UNIQUE\n\
+         breaking:\n\
+         \x20 use:\n\
+         \x20   - FILE\n",
+        config.proto_root.display(),
+        config.module
+    )
+}
+
+const BUF_GEN_YAML: &str = "\
+# Rust bindings matching openportio-rpc (prost + tonic); swap plugins for other languages.
+version: v2
+plugins:
+  - remote: buf.build/community/neoeinstein-prost
+    out: gen/rust
+  - remote: buf.build/community/neoeinstein-tonic
+    out: gen/rust
+";
+
+fn module_readme(config: &Config, files: &[ProtoFile]) -> String {
+    let mut packages: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for file in files {
+        packages
+            .entry(file.package.as_str())
+            .or_default()
+            .push(file.target.as_str());
+    }
+    let mut readme = format!(
+        "# {}\n\nGenerated by `buf-export` from `{}`. Change the sources there; this module is \
+         rebuilt and checked against the crate's descriptor set on every export.\n\n\
+         | Package | Files |\n|---|---|\n",
+        config.module,
+        config.proto_root.display()
+    );
+    for (package, files) in packages {
+        readme.push_str(&format!("| `{package}` | `{}` |\n", files.join("`, `")));
+    }
+    readme
+}
+
+/// Compiles sources and export and requires identical descriptors once file names are mapped.
+fn verify(
+    config: &Config,
+    files: &[ProtoFile],
+    renames: &BTreeMap<String, String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let sources: Vec<_> = files.iter().map(|file| file.source.as_str()).collect();
+    let targets: Vec<_> = files.iter().map(|file| file.target.as_str()).collect();
+    let mut expected = compile(&config.proto_root, &sources)?;
+    let actual = compile(&config.out, &targets)?;
+
+    for file in &mut expected {
+        let rename = |name: &mut String| {
+            if let Some(target) = renames.get(name.as_str()) {
+                *name = target.clone();
+            }
+        };
+        if let Some(name) = file.name.as_mut() {
+            rename(name);
+        }
+        file.dependency.iter_mut().for_each(rename);
+    }
+    let by_name = |files: Vec<FileDescriptorProto>| -> BTreeMap<String, FileDescriptorProto> {
+        files
+            .into_iter()
+            .map(|file| (file.name().to_string(), file))
+            .collect()
+    };
+    let (expected, actual) = (by_name(expected), by_name(actual));
+    for (name, file) in &expected {
+        if actual.get(name) != Some(file) {
+            return Err(format!(
+                "exported {name} does not match its source descriptor; report this as a buf-export bug"
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+fn compile(
+    root: &Path,
+    files: &[&str],
+) -> Result<Vec<FileDescriptorProto>, Box<dyn std::error::Error>> {
+    let protoc = protoc_bin_vendored::protoc_bin_path()?;
+    let tmp = tempfile::NamedTempFile::new()?;
+    let output = Command::new(protoc)
+        .arg(format!("--descriptor_set_out={}", tmp.path().display()))
+        .arg("-I")
+        .arg(root)
+        .args(files)
+        .output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("protoc failed for {}: {stderr}", root.display()).into());
+    }
+    let bytes = fs::read(tmp.path())?;
+    Ok(FileDescriptorSet::decode(bytes.as_slice())?.file)
+}
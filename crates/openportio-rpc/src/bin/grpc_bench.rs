@@ -0,0 +1,306 @@
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use openportio_core::AppState;
+use openportio_rpc::{
+    build_hello_response, build_hello_stream, Greeter, GreeterClient, GreeterServer, HelloRequest,
+    HelloResponse, HelloStreamRequest,
+};
+use serde_json::{json, Value};
+use tokio::{net::TcpListener, task::JoinSet};
+use tonic::{
+    transport::{server::TcpIncoming, Channel, Server},
+    Request, Response, Status,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Unary,
+    Streaming,
+}
+
+impl Mode {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Unary => "unary",
+            Self::Streaming => "streaming",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Config {
+    /// `--addr http://host:port`: benchmark a running server instead of an in-process Greeter.
+    addr: Option<String>,
+    concurrency: usize,
+    /// Calls per mode, spread across the workers.
+    requests: usize,
+    /// Greetings requested per `SayHelloStream` call.
+    stream_count: u32,
+    warmup: usize,
+    modes: Vec<Mode>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            addr: None,
+            concurrency: 16,
+            requests: 5_000,
+            stream_count: 10,
+            warmup: 100,
+            modes: vec![Mode::Unary, Mode::Streaming],
+        }
+    }
+}
+
+/// The built-in Greeter, backed by a local `AppState` like the default server.
+struct BenchGreeter {
+    state: AppState,
+}
+
+#[tonic::async_trait]
+impl Greeter for BenchGreeter {
+    async fn say_hello(
+        &self,
+        request: Request<HelloRequest>,
+    ) -> Result<Response<HelloResponse>, Status> {
+        build_hello_response(&self.state, request.into_inner())
+            .map(Response::new)
+            .map_err(|err| Status::invalid_argument(err.to_string()))
+    }
+
+    type SayHelloStreamStream = tonic::codegen::BoxStream<HelloResponse>;
+
+    async fn say_hello_stream(
+        &self,
+        request: Request<HelloStreamRequest>,
+    ) -> Result<Response<Self::SayHelloStreamStream>, Status> {
+        let responses = build_hello_stream(&self.state, request.into_inner())
+            .map_err(|err| Status::invalid_argument(err.to_string()))?;
+        Ok(Response::new(Box::pin(tonic::codegen::tokio_stream::iter(
+            responses.into_iter().map(Ok),
+        ))))
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let config = parse_args(std::env::args().skip(1))?;
+    let target = match &config.addr {
+        Some(addr) => addr.clone(),
+        None => format!("http://{}", spawn_local_greeter().await?),
+    };
+    let channel = Channel::from_shared(target.clone())?.connect().await?;
+    let client = GreeterClient::new(channel);
+
+    let mut results = Vec::new();
+    for mode in &config.modes {
+        run_mode(client.clone(), *mode, &config, config.warmup).await;
+        results.push(run_mode(client.clone(), *mode, &config, config.requests).await);
+    }
+
+    let report = json!({
+        "target": target,
+        "in_process": config.addr.is_none(),
+        "concurrency": config.concurrency,
+        "requests_per_mode": config.requests,
+        "stream_count": config.stream_count,
+        "results": results,
+    });
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
+
+fn parse_args(
+    args: impl IntoIterator<Item = String>,
+) -> Result<Config, Box<dyn std::error::Error>> {
+    let mut cfg = Config::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--addr" => {
+                cfg.addr = Some(args.next().ok_or("missing value for --addr")?);
+            }
+            "--concurrency" => {
+                let value = args.next().ok_or("missing value for --concurrency")?;
+                cfg.concurrency = value.parse()?;
+                if cfg.concurrency == 0 {
+                    return Err("--concurrency must be at least 1".into());
+                }
+            }
+            "--requests" => {
+                let value = args.next().ok_or("missing value for --requests")?;
+                cfg.requests = value.parse()?;
+            }
+            "--stream-count" => {
+                let value = args.next().ok_or("missing value for --stream-count")?;
+                cfg.stream_count = value.parse()?;
+            }
+            "--warmup" => {
+                let value = args.next().ok_or("missing value for --warmup")?;
+                cfg.warmup = value.parse()?;
+            }
+            "--mode" => {
+                let value = args.next().ok_or("missing value for --mode")?;
+                cfg.modes = match value.as_str() {
+                    "unary" => vec![Mode::Unary],
+                    "streaming" => vec![Mode::Streaming],
+                    "all" => vec![Mode::Unary, Mode::Streaming],
+                    other => {
+                        return Err(format!(
+                            "unknown mode `{other}` (expected unary, streaming or all)"
+                        )
+                        .into())
+                    }
+                };
+            }
+            "--help" | "-h" => {
+                println!(
+                    "usage: grpc_bench [--addr URL] [--mode unary|streaming|all] \
+                     [--concurrency N] [--requests N] [--stream-count N] [--warmup N]"
+                );
+                std::process::exit(0);
+            }
+            other => return Err(format!("unknown argument: {other}").into()),
+        }
+    }
+
+    Ok(cfg)
+}
+
+async fn spawn_local_greeter() -> Result<SocketAddr, Box<dyn std::error::Error>> {
+    let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+    let addr = listener.local_addr()?;
+    let incoming =
+        TcpIncoming::from_listener(listener, true, None).map_err(|err| err.to_string())?;
+    let service = GreeterServer::new(BenchGreeter {
+        state: AppState::local("grpc-bench"),
+    });
+    tokio::spawn(
+        Server::builder()
+            .add_service(service)
+            .serve_with_incoming(incoming),
+    );
+    Ok(addr)
+}
+
+/// Runs `requests` calls of `mode` across `config.concurrency` workers and summarizes them.
+async fn run_mode(
+    client: GreeterClient<Channel>,
+    mode: Mode,
+    config: &Config,
+    requests: usize,
+) -> Value {
+    let next = Arc::new(AtomicUsize::new(0));
+    let mut workers = JoinSet::new();
+    let started = Instant::now();
+    for _ in 0..config.concurrency {
+        let mut client = client.clone();
+        let next = next.clone();
+        let stream_count = config.stream_count;
+        workers.spawn(async move {
+            let mut latencies = Vec::new();
+            let mut errors = 0usize;
+            let mut messages = 0usize;
+            while next.fetch_add(1, Ordering::Relaxed) < requests {
+                let call_started = Instant::now();
+                match call(&mut client, mode, stream_count).await {
+                    Ok(received) => {
+                        latencies.push(call_started.elapsed());
+                        messages += received;
+                    }
+                    Err(_) => errors += 1,
+                }
+            }
+            (latencies, errors, messages)
+        });
+    }
+
+    let mut latencies = Vec::with_capacity(requests);
+    let mut errors = 0;
+    let mut messages = 0;
+    while let Some(joined) = workers.join_next().await {
+        let (worker_latencies, worker_errors, worker_messages) =
+            joined.expect("benchmark worker panicked");
+        latencies.extend(worker_latencies);
+        errors += worker_errors;
+        messages += worker_messages;
+    }
+    summarize(mode, latencies, errors, messages, started.elapsed())
+}
+
+/// One call; returns how many greetings were received.
+async fn call(
+    client: &mut GreeterClient<Channel>,
+    mode: Mode,
+    stream_count: u32,
+) -> Result<usize, Status> {
+    match mode {
+        Mode::Unary => {
+            client
+                .say_hello(HelloRequest {
+                    name: "bench".to_string(),
+                })
+                .await?;
+            Ok(1)
+        }
+        Mode::Streaming => {
+            let mut stream = client
+                .say_hello_stream(HelloStreamRequest {
+                    name: "bench".to_string(),
+                    count: stream_count,
+                })
+                .await?
+                .into_inner();
+            let mut received = 0;
+            while stream.message().await?.is_some() {
+                received += 1;
+            }
+            Ok(received)
+        }
+    }
+}
+
+fn summarize(
+    mode: Mode,
+    mut latencies: Vec<Duration>,
+    errors: usize,
+    messages: usize,
+    elapsed: Duration,
+) -> Value {
+    latencies.sort_unstable();
+    let secs = elapsed.as_secs_f64().max(f64::EPSILON);
+    let millis = |latency: Duration| latency.as_secs_f64() * 1_000.0;
+    json!({
+        "mode": mode.as_str(),
+        "requests": latencies.len(),
+        "errors": errors,
+        "messages": messages,
+        "duration_ms": millis(elapsed),
+        "rps": latencies.len() as f64 / secs,
+        "messages_per_sec": messages as f64 / secs,
+        "latency_ms": {
+            "p50": millis(percentile(&latencies, 50.0)),
+            "p95": millis(percentile(&latencies, 95.0)),
+            "p99": millis(percentile(&latencies, 99.0)),
+            "max": millis(latencies.last().copied().unwrap_or_default()),
+        },
+    })
+}
+
+/// Nearest-rank percentile of an ascending slice; zero when empty.
+fn percentile(sorted: &[Duration], pct: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
@@ -1,7 +1,7 @@
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, BTreeSet, HashMap},
     fs,
-    path::PathBuf,
+    path::{Path, PathBuf},
     process::Command,
 };
 
@@ -9,16 +9,46 @@ use prost::Message;
 use prost_types::{
     field_descriptor_proto::{Label, Type},
     DescriptorProto, EnumDescriptorProto, FieldDescriptorProto, FileDescriptorProto,
-    FileDescriptorSet, MethodDescriptorProto, ServiceDescriptorProto,
+    FileDescriptorSet, MethodDescriptorProto, ServiceDescriptorProto, SourceCodeInfo,
 };
 use serde_json::{json, Value};
 
+/// How the `FileDescriptorSet` is produced. `Auto` prefers the pure-Rust `protox` parser when
+/// built with the `protox` feature and falls back to the vendored `protoc`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DescriptorCompiler {
+    Auto,
+    Protox,
+    Protoc,
+}
+
+impl std::str::FromStr for DescriptorCompiler {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw {
+            "auto" => Ok(Self::Auto),
+            "protox" => Ok(Self::Protox),
+            "protoc" => Ok(Self::Protoc),
+            other => Err(format!(
+                "unknown compiler `{other}` (expected auto, protox or protoc)"
+            )),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 struct Config {
     proto: PathBuf,
     includes: Vec<PathBuf>,
     out_markdown: PathBuf,
     out_openapi: PathBuf,
+    /// `--out-jsonschema <dir>`: also write one JSON Schema file per message.
+    out_jsonschema: Option<PathBuf>,
+    compiler: DescriptorCompiler,
+    /// `--changelog old.bin new.bin`: diff two descriptor sets instead of generating docs.
+    changelog: Option<(PathBuf, PathBuf)>,
+    out_changelog: PathBuf,
 }
 
 impl Default for Config {
@@ -28,6 +58,10 @@ impl Default for Config {
             includes: vec![PathBuf::from("crates/openportio-rpc/proto")],
             out_markdown: PathBuf::from("docs/generated/grpc-contracts.md"),
             out_openapi: PathBuf::from("docs/generated/grpc-openapi-bridge.json"),
+            out_jsonschema: None,
+            compiler: DescriptorCompiler::Auto,
+            changelog: None,
+            out_changelog: PathBuf::from("docs/generated/grpc-changelog.md"),
         }
     }
 }
@@ -38,10 +72,27 @@ struct DescriptorIndex {
     messages: BTreeMap<String, DescriptorProto>,
     enums: BTreeMap<String, EnumDescriptorProto>,
     services: BTreeMap<String, ServiceDescriptorProto>,
+    /// Leading proto comments keyed by element: `pkg.Message`, `pkg.Message.field`,
+    /// `pkg.Enum`, `pkg.Enum.VALUE`, `pkg.Service`, `pkg.Service.Method`.
+    comments: BTreeMap<String, String>,
 }
 
+// `FileDescriptorProto` / `DescriptorProto` / `EnumDescriptorProto` / `ServiceDescriptorProto`
+// field numbers used in `SourceCodeInfo` location paths.
+const FILE_MESSAGE: i32 = 4;
+const FILE_ENUM: i32 = 5;
+const FILE_SERVICE: i32 = 6;
+const MESSAGE_FIELD: i32 = 2;
+const MESSAGE_NESTED: i32 = 3;
+const MESSAGE_ENUM: i32 = 4;
+const ENUM_VALUE: i32 = 2;
+const SERVICE_METHOD: i32 = 2;
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let config = parse_args(std::env::args().skip(1))?;
+    if let Some((old, new)) = &config.changelog {
+        return write_changelog(old, new, &config.out_changelog);
+    }
     let descriptor = compile_descriptor_set(&config)?;
     let index = build_index(&descriptor);
 
@@ -61,6 +112,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         serde_json::to_string_pretty(&openapi)? + "\n",
     )?;
 
+    if let Some(dir) = &config.out_jsonschema {
+        fs::create_dir_all(dir)?;
+        for (name, schema) in build_json_schemas(&index) {
+            fs::write(
+                dir.join(format!("{name}.schema.json")),
+                serde_json::to_string_pretty(&schema)? + "\n",
+            )?;
+        }
+    }
+
     Ok(())
 }
 
@@ -93,10 +154,32 @@ fn parse_args(
                 let value = args.next().ok_or("missing value for --out-openapi")?;
                 cfg.out_openapi = PathBuf::from(value);
             }
+            "--out-jsonschema" => {
+                let value = args.next().ok_or("missing value for --out-jsonschema")?;
+                cfg.out_jsonschema = Some(PathBuf::from(value));
+            }
+            "--compiler" => {
+                let value = args.next().ok_or("missing value for --compiler")?;
+                cfg.compiler = value.parse()?;
+            }
+            "--changelog" => {
+                let old = args
+                    .next()
+                    .ok_or("missing old descriptor set for --changelog")?;
+                let new = args
+                    .next()
+                    .ok_or("missing new descriptor set for --changelog")?;
+                cfg.changelog = Some((PathBuf::from(old), PathBuf::from(new)));
+            }
+            "--out" => {
+                let value = args.next().ok_or("missing value for --out")?;
+                cfg.out_changelog = PathBuf::from(value);
+            }
             "--help" | "-h" => {
                 println!(
-                    "grpc-docgen [--proto <path>] [--include <path>]... [--out-md <path>] [--out-openapi <path>]"
+                    "grpc-docgen [--proto <path>] [--include <path>]... [--out-md <path>] [--out-openapi <path>] [--out-jsonschema <dir>] [--compiler auto|protox|protoc]"
                 );
+                println!("grpc-docgen --changelog <old.bin> <new.bin> [--out <path>]");
                 std::process::exit(0);
             }
             other => return Err(format!("unknown argument: {other}").into()),
@@ -115,6 +198,30 @@ fn parse_args(
 fn compile_descriptor_set(
     config: &Config,
 ) -> Result<FileDescriptorSet, Box<dyn std::error::Error>> {
+    match config.compiler {
+        DescriptorCompiler::Protoc => compile_with_protoc(config),
+        DescriptorCompiler::Protox => compile_with_protox(config),
+        DescriptorCompiler::Auto if cfg!(feature = "protox") => compile_with_protox(config)
+            .or_else(|err| {
+                eprintln!("grpc-docgen: protox failed ({err}); falling back to protoc");
+                compile_with_protoc(config)
+            }),
+        DescriptorCompiler::Auto => compile_with_protoc(config),
+    }
+}
+
+#[cfg(feature = "protox")]
+fn compile_with_protox(config: &Config) -> Result<FileDescriptorSet, Box<dyn std::error::Error>> {
+    // Imports and source info are included, matching the protoc invocation below.
+    Ok(protox::compile([&config.proto], &config.includes)?)
+}
+
+#[cfg(not(feature = "protox"))]
+fn compile_with_protox(_config: &Config) -> Result<FileDescriptorSet, Box<dyn std::error::Error>> {
+    Err("grpc-docgen was built without the `protox` feature; rebuild with `--features protox` or use `--compiler protoc`".into())
+}
+
+fn compile_with_protoc(config: &Config) -> Result<FileDescriptorSet, Box<dyn std::error::Error>> {
     let protoc = protoc_bin_vendored::protoc_bin_path()?;
     let tmp = tempfile::NamedTempFile::new()?;
 
@@ -143,25 +250,51 @@ fn build_index(descriptor_set: &FileDescriptorSet) -> DescriptorIndex {
     let mut messages = BTreeMap::new();
     let mut enums = BTreeMap::new();
     let mut services = BTreeMap::new();
+    let mut comments = BTreeMap::new();
 
     for file in &descriptor_set.file {
         let package = file.package.clone().unwrap_or_default();
         packages.insert(package.clone(), file.clone());
+        let file_comments = FileComments::new(file.source_code_info.as_ref());
 
-        for message in &file.message_type {
-            collect_message_and_nested(&package, message, &mut messages, &mut enums);
+        for (idx, message) in file.message_type.iter().enumerate() {
+            collect_message_and_nested(
+                &package,
+                message,
+                &[FILE_MESSAGE, idx as i32],
+                &file_comments,
+                &mut messages,
+                &mut enums,
+                &mut comments,
+            );
         }
 
-        for en in &file.enum_type {
+        for (idx, en) in file.enum_type.iter().enumerate() {
             let enum_name = qualify(&package, en.name.as_deref().unwrap_or("UnknownEnum"));
+            collect_enum_comments(
+                &enum_name,
+                en,
+                &[FILE_ENUM, idx as i32],
+                &file_comments,
+                &mut comments,
+            );
             enums.insert(enum_name, en.clone());
         }
 
-        for service in &file.service {
+        for (idx, service) in file.service.iter().enumerate() {
             let full = qualify(
                 &package,
                 service.name.as_deref().unwrap_or("UnknownService"),
             );
+            let path = [FILE_SERVICE, idx as i32];
+            file_comments.collect(&full, &path, &mut comments);
+            for (method_idx, method) in service.method.iter().enumerate() {
+                file_comments.collect(
+                    &qualify(&full, method.name.as_deref().unwrap_or("UnknownMethod")),
+                    &[&path[..], &[SERVICE_METHOD, method_idx as i32]].concat(),
+                    &mut comments,
+                );
+            }
             services.insert(full, service.clone());
         }
     }
@@ -171,26 +304,116 @@ fn build_index(descriptor_set: &FileDescriptorSet) -> DescriptorIndex {
         messages,
         enums,
         services,
+        comments,
+    }
+}
+
+/// Leading comments of one file, keyed by `SourceCodeInfo` location path. Empty when the
+/// descriptor set was built without `--include_source_info`.
+struct FileComments(HashMap<Vec<i32>, String>);
+
+impl FileComments {
+    fn new(info: Option<&SourceCodeInfo>) -> Self {
+        Self(
+            info.map(|info| {
+                info.location
+                    .iter()
+                    .filter_map(|location| {
+                        let comment = clean_comment(location.leading_comments.as_deref()?);
+                        (!comment.is_empty()).then(|| (location.path.clone(), comment))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        )
+    }
+
+    fn collect(&self, key: &str, path: &[i32], comments: &mut BTreeMap<String, String>) {
+        if let Some(comment) = self.0.get(path) {
+            comments.insert(key.to_string(), comment.clone());
+        }
     }
 }
 
+/// Trims the comment markers' leading space and surrounding blank lines, keeping line breaks.
+fn clean_comment(raw: &str) -> String {
+    let lines: Vec<&str> = raw.lines().map(str::trim).collect();
+    lines.join("\n").trim().to_string()
+}
+
 fn collect_message_and_nested(
     prefix: &str,
     message: &DescriptorProto,
+    path: &[i32],
+    file_comments: &FileComments,
     messages: &mut BTreeMap<String, DescriptorProto>,
     enums: &mut BTreeMap<String, EnumDescriptorProto>,
+    comments: &mut BTreeMap<String, String>,
 ) {
     let name = message.name.as_deref().unwrap_or("UnknownMessage");
     let full = qualify(prefix, name);
     messages.insert(full.clone(), message.clone());
+    file_comments.collect(&full, path, comments);
+
+    for (idx, field) in message.field.iter().enumerate() {
+        file_comments.collect(
+            &qualify(&full, field.name.as_deref().unwrap_or("unknown_field")),
+            &[path, &[MESSAGE_FIELD, idx as i32]].concat(),
+            comments,
+        );
+    }
 
-    for en in &message.enum_type {
+    for (idx, en) in message.enum_type.iter().enumerate() {
         let enum_name = qualify(&full, en.name.as_deref().unwrap_or("UnknownEnum"));
+        collect_enum_comments(
+            &enum_name,
+            en,
+            &[path, &[MESSAGE_ENUM, idx as i32]].concat(),
+            file_comments,
+            comments,
+        );
         enums.insert(enum_name, en.clone());
     }
 
-    for nested in &message.nested_type {
-        collect_message_and_nested(&full, nested, messages, enums);
+    for (idx, nested) in message.nested_type.iter().enumerate() {
+        collect_message_and_nested(
+            &full,
+            nested,
+            &[path, &[MESSAGE_NESTED, idx as i32]].concat(),
+            file_comments,
+            messages,
+            enums,
+            comments,
+        );
+    }
+}
+
+fn collect_enum_comments(
+    full: &str,
+    en: &EnumDescriptorProto,
+    path: &[i32],
+    file_comments: &FileComments,
+    comments: &mut BTreeMap<String, String>,
+) {
+    file_comments.collect(full, path, comments);
+    for (idx, value) in en.value.iter().enumerate() {
+        file_comments.collect(
+            &qualify(full, value.name.as_deref().unwrap_or("UNKNOWN")),
+            &[path, &[ENUM_VALUE, idx as i32]].concat(),
+            comments,
+        );
+    }
+}
+
+impl DescriptorIndex {
+    fn comment(&self, key: &str) -> Option<&str> {
+        self.comments.get(key).map(String::as_str)
+    }
+
+    /// The comment folded onto one line, for list items.
+    fn inline_comment(&self, key: &str) -> Option<String> {
+        self.comment(key)
+            .map(|comment| comment.split_whitespace().collect::<Vec<_>>().join(" "))
     }
 }
 
@@ -213,7 +436,9 @@ fn build_openapi_bridge(index: &DescriptorIndex) -> Value {
         if is_map_entry(message) {
             continue;
         }
-        schemas.insert(name.clone(), message_schema(name, message, index));
+        let mut schema = message_schema(name, message, index);
+        schema["example"] = message_example(name, index, &mut Vec::new());
+        schemas.insert(name.clone(), schema);
     }
 
     for (name, en) in &index.enums {
@@ -222,16 +447,29 @@ fn build_openapi_bridge(index: &DescriptorIndex) -> Value {
             .iter()
             .filter_map(|v| v.name.as_ref().map(|n| Value::String(n.clone())))
             .collect();
-        schemas.insert(
-            name.clone(),
-            json!({
-                "type": "string",
-                "enum": values,
-            }),
-        );
+        let mut schema = json!({
+            "type": "string",
+            "enum": values,
+        });
+        if let Some(comment) = index.comment(name) {
+            schema["description"] = json!(comment);
+        }
+        let value_comments: BTreeMap<&str, &str> = en
+            .value
+            .iter()
+            .filter_map(|v| {
+                let value = v.name.as_deref()?;
+                Some((value, index.comment(&qualify(name, value))?))
+            })
+            .collect();
+        if !value_comments.is_empty() {
+            schema["x-openportio-enum-descriptions"] = json!(value_comments);
+        }
+        schemas.insert(name.clone(), schema);
     }
 
     let mut paths = BTreeMap::<String, Value>::new();
+    let mut tags = Vec::<Value>::new();
 
     for (service_full_name, service) in &index.services {
         let mut service_parts: Vec<&str> = service_full_name.rsplitn(2, '.').collect();
@@ -241,23 +479,40 @@ fn build_openapi_bridge(index: &DescriptorIndex) -> Value {
             [name] => (String::new(), (*name).to_string()),
             _ => (String::new(), service_full_name.clone()),
         };
+        let mut tag = json!({ "name": service_full_name });
+        if let Some(comment) = index.comment(service_full_name) {
+            tag["description"] = json!(comment);
+        }
+        tags.push(tag);
 
         for method in &service.method {
             let path = grpc_path(&package, &service_name, method);
             let input = normalize_type_name(method.input_type.as_deref().unwrap_or(""));
             let output = normalize_type_name(method.output_type.as_deref().unwrap_or(""));
             let method_name = method.name.as_deref().unwrap_or("UnknownMethod");
+            let input_example = message_example(&input, index, &mut Vec::new());
+            let output_example = message_example(&output, index, &mut Vec::new());
+            let description = index
+                .comment(&qualify(service_full_name, method_name))
+                .unwrap_or("Swagger-compatible bridge for gRPC method contract discovery.");
             paths.insert(
                 path,
                 json!({
                     "post": {
+                        "tags": [service_full_name],
                         "summary": format!("{service_name}.{method_name}"),
-                        "description": "Swagger-compatible bridge for gRPC method contract discovery.",
+                        "description": description,
                         "requestBody": {
                             "required": true,
                             "content": {
                                 "application/grpc+proto": {
-                                    "schema": {"$ref": format!("#/components/schemas/{input}")}
+                                    "schema": {"$ref": format!("#/components/schemas/{input}")},
+                                    "examples": {
+                                        "default": {
+                                            "summary": format!("Example {input}"),
+                                            "value": input_example
+                                        }
+                                    }
                                 }
                             }
                         },
@@ -266,7 +521,13 @@ fn build_openapi_bridge(index: &DescriptorIndex) -> Value {
                                 "description": "gRPC success response payload shape",
                                 "content": {
                                     "application/grpc+proto": {
-                                        "schema": {"$ref": format!("#/components/schemas/{output}")}
+                                        "schema": {"$ref": format!("#/components/schemas/{output}")},
+                                        "examples": {
+                                            "default": {
+                                                "summary": format!("Example {output}"),
+                                                "value": output_example
+                                            }
+                                        }
                                     }
                                 }
                             }
@@ -291,6 +552,7 @@ fn build_openapi_bridge(index: &DescriptorIndex) -> Value {
             "version": "0.1.0",
             "description": "Swagger-compatible contract view generated from protobuf descriptors."
         },
+        "tags": tags,
         "paths": paths,
         "components": {
             "schemas": schemas
@@ -342,7 +604,11 @@ fn message_schema(name: &str, message: &DescriptorProto, index: &DescriptorIndex
             oneof_map.entry(group).or_default().push(field_name.clone());
         }
 
-        props.insert(field_name, field_schema(field, index));
+        let mut schema = field_schema(field, index);
+        if let Some(comment) = index.comment(&qualify(name, &field_name)) {
+            describe_schema(&mut schema, comment);
+        }
+        props.insert(field_name, schema);
     }
 
     let mut schema = json!({
@@ -362,10 +628,22 @@ fn message_schema(name: &str, message: &DescriptorProto, index: &DescriptorIndex
         schema["x-openportio-map-entry"] = json!(true);
     }
 
+    if let Some(comment) = index.comment(name) {
+        schema["description"] = json!(comment);
+    }
+
     schema["x-openportio-message"] = json!(name);
     schema
 }
 
+/// OpenAPI 3.0 ignores siblings of `$ref`, so references are wrapped in `allOf` first.
+fn describe_schema(schema: &mut Value, description: &str) {
+    if schema.get("$ref").is_some() {
+        *schema = json!({ "allOf": [schema.take()] });
+    }
+    schema["description"] = json!(description);
+}
+
 fn field_schema(field: &FieldDescriptorProto, index: &DescriptorIndex) -> Value {
     let field_type = Type::try_from(field.r#type.unwrap_or_default()).unwrap_or(Type::String);
     let repeated = field.label == Some(Label::Repeated as i32);
@@ -441,6 +719,145 @@ fn base_schema(field: &FieldDescriptorProto, index: &DescriptorIndex) -> Value {
     }
 }
 
+/// Sample payload for `name`, keyed like the schema properties: one element per repeated field, one entry per map, the
+/// first member of each oneof, and the first meaningful value of each enum. Recursive messages
+/// stop at `{}`.
+fn message_example(name: &str, index: &DescriptorIndex, stack: &mut Vec<String>) -> Value {
+    if let Some(example) = well_known_example(name) {
+        return example;
+    }
+    let Some(message) = index.messages.get(name) else {
+        return json!({});
+    };
+    if stack.iter().any(|seen| seen == name) {
+        return json!({});
+    }
+    stack.push(name.to_string());
+
+    let mut example = serde_json::Map::new();
+    let mut filled_oneofs = Vec::<i32>::new();
+    for field in &message.field {
+        if let Some(group) = field.oneof_index.filter(|_| !field.proto3_optional()) {
+            if filled_oneofs.contains(&group) {
+                continue;
+            }
+            filled_oneofs.push(group);
+        }
+        let field_name = field
+            .name
+            .clone()
+            .unwrap_or_else(|| "unknown_field".to_string());
+        example.insert(field_name, field_example(field, index, stack));
+    }
+
+    stack.pop();
+    Value::Object(example)
+}
+
+fn field_example(
+    field: &FieldDescriptorProto,
+    index: &DescriptorIndex,
+    stack: &mut Vec<String>,
+) -> Value {
+    let field_type = Type::try_from(field.r#type.unwrap_or_default()).unwrap_or(Type::String);
+    let repeated = field.label == Some(Label::Repeated as i32);
+
+    if repeated && field_type == Type::Message {
+        let message_name = normalize_type_name(field.type_name.as_deref().unwrap_or(""));
+        if let Some(entry) = index
+            .messages
+            .get(&message_name)
+            .filter(|msg| is_map_entry(msg))
+        {
+            let key = entry
+                .field
+                .iter()
+                .find(|f| f.name.as_deref() == Some("key"))
+                .map(|key| match scalar_example(key, index, stack) {
+                    Value::String(key) => key,
+                    other => other.to_string(),
+                })
+                .unwrap_or_else(|| "key".to_string());
+            let value = entry
+                .field
+                .iter()
+                .find(|f| f.name.as_deref() == Some("value"))
+                .map(|value| scalar_example(value, index, stack))
+                .unwrap_or_else(|| json!({}));
+            return json!({ key: value });
+        }
+    }
+
+    let example = scalar_example(field, index, stack);
+    if repeated {
+        json!([example])
+    } else {
+        example
+    }
+}
+
+fn scalar_example(
+    field: &FieldDescriptorProto,
+    index: &DescriptorIndex,
+    stack: &mut Vec<String>,
+) -> Value {
+    let field_type = Type::try_from(field.r#type.unwrap_or_default()).unwrap_or(Type::String);
+    match field_type {
+        Type::Double | Type::Float => json!(1.5),
+        Type::Int64
+        | Type::Sint64
+        | Type::Sfixed64
+        | Type::Uint64
+        | Type::Fixed64
+        | Type::Int32
+        | Type::Sint32
+        | Type::Sfixed32
+        | Type::Uint32
+        | Type::Fixed32 => json!(1),
+        Type::Bool => json!(true),
+        Type::String => json!(field.name.as_deref().unwrap_or("string")),
+        // base64("example")
+        Type::Bytes => json!("ZXhhbXBsZQ=="),
+        Type::Enum => {
+            let enum_name = normalize_type_name(field.type_name.as_deref().unwrap_or(""));
+            let values: Vec<&str> = index
+                .enums
+                .get(&enum_name)
+                .map(|en| en.value.iter().filter_map(|v| v.name.as_deref()).collect())
+                .unwrap_or_default();
+            values
+                .iter()
+                .find(|value| !value.ends_with("_UNSPECIFIED"))
+                .or(values.first())
+                .map(|value| json!(value))
+                .unwrap_or_else(|| json!("string"))
+        }
+        Type::Message => {
+            let message_name = normalize_type_name(field.type_name.as_deref().unwrap_or(""));
+            message_example(&message_name, index, stack)
+        }
+        _ => json!({}),
+    }
+}
+
+fn well_known_example(name: &str) -> Option<Value> {
+    Some(match name {
+        "google.protobuf.Timestamp" => json!("2024-01-01T00:00:00Z"),
+        "google.protobuf.Duration" => json!("1.5s"),
+        "google.protobuf.Empty" => json!({}),
+        "google.protobuf.FieldMask" => json!("field.path"),
+        "google.protobuf.StringValue" => json!("string"),
+        "google.protobuf.BoolValue" => json!(true),
+        "google.protobuf.DoubleValue" | "google.protobuf.FloatValue" => json!(1.5),
+        "google.protobuf.Int32Value"
+        | "google.protobuf.UInt32Value"
+        | "google.protobuf.Int64Value"
+        | "google.protobuf.UInt64Value" => json!(1),
+        "google.protobuf.Struct" | "google.protobuf.Value" => json!({}),
+        _ => return None,
+    })
+}
+
 fn is_map_entry(message: &DescriptorProto) -> bool {
     message
         .options
@@ -449,6 +866,230 @@ fn is_map_entry(message: &DescriptorProto) -> bool {
         .unwrap_or(false)
 }
 
+const JSON_SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Standalone draft 2020-12 schemas keyed by message name (map entries excluded). Each one
+/// carries the messages it references under `$defs`; enums are inlined like in the bridge.
+fn build_json_schemas(index: &DescriptorIndex) -> BTreeMap<String, Value> {
+    index
+        .messages
+        .iter()
+        .filter(|(_, message)| !is_map_entry(message))
+        .map(|(name, _)| (name.clone(), json_schema_document(name, index)))
+        .collect()
+}
+
+fn json_schema_document(root: &str, index: &DescriptorIndex) -> Value {
+    let mut pending = Vec::new();
+    let schema = message_json_schema(root, root, index, &mut pending);
+    let mut defs = BTreeMap::<String, Value>::new();
+    while let Some(name) = pending.pop() {
+        if name == root || defs.contains_key(&name) {
+            continue;
+        }
+        let def = message_json_schema(&name, root, index, &mut pending);
+        defs.insert(name, def);
+    }
+
+    let mut document = json!({
+        "$schema": JSON_SCHEMA_DIALECT,
+        "$id": format!("{root}.schema.json"),
+        "title": root,
+    });
+    if let (Some(document), Value::Object(schema)) = (document.as_object_mut(), schema) {
+        document.extend(schema);
+    }
+    if !defs.is_empty() {
+        document["$defs"] = json!(defs);
+    }
+    document
+}
+
+/// Proto JSON shape of one message. Each oneof becomes a `oneOf` of "only this member" plus
+/// "none of them"; several oneofs are combined under `allOf`. Referenced messages are pushed
+/// to `pending` for the document's `$defs`.
+fn message_json_schema(
+    name: &str,
+    root: &str,
+    index: &DescriptorIndex,
+    pending: &mut Vec<String>,
+) -> Value {
+    if let Some(schema) = well_known_json_schema(name) {
+        return schema;
+    }
+    let Some(message) = index.messages.get(name) else {
+        return json!({ "type": "object" });
+    };
+
+    let mut props = BTreeMap::<String, Value>::new();
+    let mut required = Vec::<String>::new();
+    let mut oneofs = BTreeMap::<i32, Vec<String>>::new();
+    for field in &message.field {
+        let field_name = field
+            .name
+            .clone()
+            .unwrap_or_else(|| "unknown_field".to_string());
+        if field.label == Some(Label::Required as i32) {
+            required.push(field_name.clone());
+        }
+        if let Some(group) = field.oneof_index.filter(|_| !field.proto3_optional()) {
+            oneofs.entry(group).or_default().push(field_name.clone());
+        }
+        let mut schema = field_json_schema(field, root, index, pending);
+        if let Some(comment) = index.comment(&qualify(name, &field_name)) {
+            schema["description"] = json!(comment);
+        }
+        props.insert(field_name, schema);
+    }
+
+    let mut schema = json!({
+        "type": "object",
+        "properties": props,
+    });
+    if !required.is_empty() {
+        schema["required"] = json!(required);
+    }
+    let mut groups: Vec<Value> = oneofs
+        .values()
+        .map(|members| {
+            let mut choices: Vec<Value> = members
+                .iter()
+                .map(|member| json!({ "required": [member] }))
+                .collect();
+            choices.push(json!({ "not": { "anyOf": choices.clone() } }));
+            json!({ "oneOf": choices })
+        })
+        .collect();
+    match groups.len() {
+        0 => {}
+        1 => schema["oneOf"] = groups.remove(0)["oneOf"].take(),
+        _ => schema["allOf"] = json!(groups),
+    }
+    if let Some(comment) = index.comment(name) {
+        schema["description"] = json!(comment);
+    }
+    schema
+}
+
+fn field_json_schema(
+    field: &FieldDescriptorProto,
+    root: &str,
+    index: &DescriptorIndex,
+    pending: &mut Vec<String>,
+) -> Value {
+    let repeated = field.label == Some(Label::Repeated as i32);
+    let entry = field
+        .type_name
+        .as_deref()
+        .and_then(|type_name| index.messages.get(&normalize_type_name(type_name)))
+        .filter(|message| repeated && is_map_entry(message));
+    if let Some(entry) = entry {
+        let entry_field = |name: &str| entry.field.iter().find(|f| f.name.as_deref() == Some(name));
+        let mut schema = json!({
+            "type": "object",
+            "additionalProperties": entry_field("value")
+                .map(|value| base_json_schema(value, root, index, pending))
+                .unwrap_or_else(|| json!({})),
+        });
+        let key_type =
+            entry_field("key").and_then(|key| Type::try_from(key.r#type.unwrap_or_default()).ok());
+        match key_type {
+            Some(Type::String) | None => {}
+            Some(Type::Bool) => schema["propertyNames"] = json!({ "enum": ["true", "false"] }),
+            Some(_) => schema["propertyNames"] = json!({ "pattern": "^-?[0-9]+$" }),
+        }
+        return schema;
+    }
+
+    let base = base_json_schema(field, root, index, pending);
+    if repeated {
+        json!({ "type": "array", "items": base })
+    } else {
+        base
+    }
+}
+
+fn base_json_schema(
+    field: &FieldDescriptorProto,
+    root: &str,
+    index: &DescriptorIndex,
+    pending: &mut Vec<String>,
+) -> Value {
+    let field_type = Type::try_from(field.r#type.unwrap_or_default()).unwrap_or(Type::String);
+    match field_type {
+        Type::Double | Type::Float => json!({ "type": "number" }),
+        Type::Int32 | Type::Sint32 | Type::Sfixed32 => {
+            json!({ "type": "integer", "minimum": i32::MIN, "maximum": i32::MAX })
+        }
+        Type::Uint32 | Type::Fixed32 => {
+            json!({ "type": "integer", "minimum": 0, "maximum": u32::MAX })
+        }
+        Type::Int64 | Type::Sint64 | Type::Sfixed64 => {
+            json!({ "type": "integer", "format": "int64" })
+        }
+        Type::Uint64 | Type::Fixed64 => {
+            json!({ "type": "integer", "format": "uint64", "minimum": 0 })
+        }
+        Type::Bool => json!({ "type": "boolean" }),
+        Type::String => json!({ "type": "string" }),
+        Type::Bytes => json!({ "type": "string", "contentEncoding": "base64" }),
+        Type::Enum => {
+            let enum_name = normalize_type_name(field.type_name.as_deref().unwrap_or(""));
+            let mut schema = json!({ "type": "string" });
+            if let Some(en) = index.enums.get(&enum_name) {
+                let values: Vec<String> = en.value.iter().filter_map(|v| v.name.clone()).collect();
+                schema["enum"] = json!(values);
+            }
+            if let Some(comment) = index.comment(&enum_name) {
+                schema["description"] = json!(comment);
+            }
+            schema
+        }
+        Type::Message => {
+            let type_name = normalize_type_name(field.type_name.as_deref().unwrap_or(""));
+            if let Some(schema) = well_known_json_schema(&type_name) {
+                return schema;
+            }
+            let reference = if type_name == root {
+                "#".to_string()
+            } else {
+                format!("#/$defs/{type_name}")
+            };
+            pending.push(type_name);
+            json!({ "$ref": reference })
+        }
+        _ => json!({}),
+    }
+}
+
+/// Canonical proto JSON forms of the well-known types.
+fn well_known_json_schema(name: &str) -> Option<Value> {
+    Some(match name {
+        "google.protobuf.Timestamp" => json!({ "type": "string", "format": "date-time" }),
+        "google.protobuf.Duration" => {
+            json!({ "type": "string", "pattern": "^-?[0-9]+(\\.[0-9]{1,9})?s$" })
+        }
+        "google.protobuf.Empty" => json!({ "type": "object", "maxProperties": 0 }),
+        "google.protobuf.FieldMask" => json!({ "type": "string" }),
+        "google.protobuf.StringValue" => json!({ "type": ["string", "null"] }),
+        "google.protobuf.BytesValue" => {
+            json!({ "type": ["string", "null"], "contentEncoding": "base64" })
+        }
+        "google.protobuf.BoolValue" => json!({ "type": ["boolean", "null"] }),
+        "google.protobuf.DoubleValue" | "google.protobuf.FloatValue" => {
+            json!({ "type": ["number", "null"] })
+        }
+        "google.protobuf.Int32Value"
+        | "google.protobuf.UInt32Value"
+        | "google.protobuf.Int64Value"
+        | "google.protobuf.UInt64Value" => json!({ "type": ["integer", "null"] }),
+        "google.protobuf.Struct" => json!({ "type": "object" }),
+        "google.protobuf.ListValue" => json!({ "type": "array" }),
+        "google.protobuf.Value" => json!({}),
+        _ => return None,
+    })
+}
+
 fn build_markdown(index: &DescriptorIndex) -> String {
     let mut lines = vec![
         "# gRPC Contract Documentation".to_string(),
@@ -471,6 +1112,7 @@ fn build_markdown(index: &DescriptorIndex) -> String {
     for (service_name, service) in &index.services {
         lines.push(format!("### `{service_name}`"));
         lines.push(String::new());
+        push_comment(&mut lines, index.comment(service_name));
         for method in &service.method {
             let method_name = method.name.as_deref().unwrap_or("UnknownMethod");
             let input = normalize_type_name(method.input_type.as_deref().unwrap_or(""));
@@ -481,7 +1123,8 @@ fn build_markdown(index: &DescriptorIndex) -> String {
                 method.server_streaming.unwrap_or(false)
             );
             lines.push(format!(
-                "- `{method_name}`: `{input}` -> `{output}` ({stream})"
+                "- `{method_name}`: `{input}` -> `{output}` ({stream}){}",
+                inline_suffix(index.inline_comment(&qualify(service_name, method_name)))
             ));
         }
         lines.push(String::new());
@@ -495,6 +1138,7 @@ fn build_markdown(index: &DescriptorIndex) -> String {
         }
         lines.push(format!("### `{message_name}`"));
         lines.push(String::new());
+        push_comment(&mut lines, index.comment(message_name));
 
         if message.field.is_empty() {
             lines.push("- (no fields)".to_string());
@@ -507,7 +1151,8 @@ fn build_markdown(index: &DescriptorIndex) -> String {
             let repeated = field.label == Some(Label::Repeated as i32);
             let repeated_flag = if repeated { ", repeated" } else { "" };
             lines.push(format!(
-                "- `{name}` (`{type_name}`, field #{number}{repeated_flag})"
+                "- `{name}` (`{type_name}`, field #{number}{repeated_flag}){}",
+                inline_suffix(index.inline_comment(&qualify(message_name, name)))
             ));
         }
 
@@ -533,7 +1178,16 @@ fn build_markdown(index: &DescriptorIndex) -> String {
     lines.push(String::new());
     for (enum_name, en) in &index.enums {
         let values: Vec<String> = en.value.iter().filter_map(|v| v.name.clone()).collect();
-        lines.push(format!("- `{enum_name}`: {}", values.join(", ")));
+        let comment = index
+            .inline_comment(enum_name)
+            .map(|comment| format!(" — {comment}"))
+            .unwrap_or_default();
+        lines.push(format!("- `{enum_name}`: {}{comment}", values.join(", ")));
+        for value in &values {
+            if let Some(comment) = index.inline_comment(&qualify(enum_name, value)) {
+                lines.push(format!("  - `{value}`: {comment}"));
+            }
+        }
     }
     lines.push(String::new());
 
@@ -554,6 +1208,19 @@ fn build_markdown(index: &DescriptorIndex) -> String {
     lines.join("\n")
 }
 
+fn push_comment(lines: &mut Vec<String>, comment: Option<&str>) {
+    if let Some(comment) = comment {
+        lines.push(comment.to_string());
+        lines.push(String::new());
+    }
+}
+
+fn inline_suffix(comment: Option<String>) -> String {
+    comment
+        .map(|comment| format!(": {comment}"))
+        .unwrap_or_default()
+}
+
 fn readable_field_type(field: &FieldDescriptorProto) -> String {
     let field_type = Type::try_from(field.r#type.unwrap_or_default()).unwrap_or(Type::String);
     match field_type {
@@ -576,3 +1243,295 @@ fn readable_field_type(field: &FieldDescriptorProto) -> String {
         Type::Sint64 => "sint64".to_string(),
     }
 }
+
+fn write_changelog(old: &Path, new: &Path, out: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let read = |path: &Path| -> Result<DescriptorIndex, Box<dyn std::error::Error>> {
+        let bytes =
+            fs::read(path).map_err(|err| format!("cannot read {}: {err}", path.display()))?;
+        Ok(build_index(&FileDescriptorSet::decode(bytes.as_slice())?))
+    };
+    let diff = diff_indexes(&read(old)?, &read(new)?);
+    let markdown = build_changelog_markdown(&diff, old, new);
+
+    if let Some(parent) = out.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(out, markdown)?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ContractChange {
+    breaking: bool,
+    description: String,
+}
+
+impl ContractChange {
+    fn added(description: String) -> Self {
+        Self {
+            breaking: false,
+            description,
+        }
+    }
+
+    fn breaking(description: String) -> Self {
+        Self {
+            breaking: true,
+            description,
+        }
+    }
+}
+
+/// Changes keyed by fully-qualified service, message, or enum name.
+#[derive(Debug, Default)]
+struct ContractDiff {
+    services: BTreeMap<String, Vec<ContractChange>>,
+    messages: BTreeMap<String, Vec<ContractChange>>,
+    enums: BTreeMap<String, Vec<ContractChange>>,
+}
+
+impl ContractDiff {
+    fn changes(&self) -> impl Iterator<Item = &ContractChange> {
+        self.services
+            .values()
+            .chain(self.messages.values())
+            .chain(self.enums.values())
+            .flatten()
+    }
+}
+
+fn diff_indexes(old: &DescriptorIndex, new: &DescriptorIndex) -> ContractDiff {
+    let messages = |index: &DescriptorIndex| -> BTreeMap<String, DescriptorProto> {
+        index
+            .messages
+            .iter()
+            .filter(|(_, message)| !is_map_entry(message))
+            .map(|(name, message)| (name.clone(), message.clone()))
+            .collect()
+    };
+    ContractDiff {
+        services: diff_entries(&old.services, &new.services, "service", diff_service),
+        messages: diff_entries(&messages(old), &messages(new), "message", diff_message),
+        enums: diff_entries(&old.enums, &new.enums, "enum", diff_enum),
+    }
+}
+
+fn diff_entries<T>(
+    old: &BTreeMap<String, T>,
+    new: &BTreeMap<String, T>,
+    kind: &str,
+    diff: impl Fn(&T, &T) -> Vec<ContractChange>,
+) -> BTreeMap<String, Vec<ContractChange>> {
+    let names: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let changes = match (old.get(name), new.get(name)) {
+                (None, Some(_)) => vec![ContractChange::added(format!("Added {kind}"))],
+                (Some(_), None) => vec![ContractChange::breaking(format!("Removed {kind}"))],
+                (Some(old), Some(new)) => diff(old, new),
+                (None, None) => Vec::new(),
+            };
+            (!changes.is_empty()).then(|| (name.clone(), changes))
+        })
+        .collect()
+}
+
+fn diff_service(old: &ServiceDescriptorProto, new: &ServiceDescriptorProto) -> Vec<ContractChange> {
+    let by_name = |service: &ServiceDescriptorProto| -> BTreeMap<String, MethodDescriptorProto> {
+        service
+            .method
+            .iter()
+            .map(|method| (method.name.clone().unwrap_or_default(), method.clone()))
+            .collect()
+    };
+    let (old, new) = (by_name(old), by_name(new));
+    let names: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    let mut changes = Vec::new();
+    for name in names {
+        match (old.get(name), new.get(name)) {
+            (None, Some(method)) => changes.push(ContractChange::added(format!(
+                "Added method `{name}`: {}",
+                method_signature(method)
+            ))),
+            (Some(_), None) => {
+                changes.push(ContractChange::breaking(format!("Removed method `{name}`")))
+            }
+            (Some(old), Some(new)) if method_signature(old) != method_signature(new) => changes
+                .push(ContractChange::breaking(format!(
+                    "Changed method `{name}`: {} -> {}",
+                    method_signature(old),
+                    method_signature(new)
+                ))),
+            _ => {}
+        }
+    }
+    changes
+}
+
+fn method_signature(method: &MethodDescriptorProto) -> String {
+    let stream = |streaming: Option<bool>| {
+        if streaming.unwrap_or(false) {
+            "stream "
+        } else {
+            ""
+        }
+    };
+    format!(
+        "`{}{}` -> `{}{}`",
+        stream(method.client_streaming),
+        normalize_type_name(method.input_type.as_deref().unwrap_or("")),
+        stream(method.server_streaming),
+        normalize_type_name(method.output_type.as_deref().unwrap_or(""))
+    )
+}
+
+/// Fields are matched by number, which is what the wire format depends on.
+fn diff_message(old: &DescriptorProto, new: &DescriptorProto) -> Vec<ContractChange> {
+    let by_number = |message: &DescriptorProto| -> BTreeMap<i32, FieldDescriptorProto> {
+        message
+            .field
+            .iter()
+            .map(|field| (field.number.unwrap_or_default(), field.clone()))
+            .collect()
+    };
+    let (old, new) = (by_number(old), by_number(new));
+    let numbers: BTreeSet<&i32> = old.keys().chain(new.keys()).collect();
+    let mut changes = Vec::new();
+    for number in numbers {
+        match (old.get(number), new.get(number)) {
+            (None, Some(field)) => changes.push(ContractChange::added(format!(
+                "Added field {}",
+                describe_field(field)
+            ))),
+            (Some(field), None) => changes.push(ContractChange::breaking(format!(
+                "Removed field {}",
+                describe_field(field)
+            ))),
+            (Some(old), Some(new)) => {
+                let (old_name, new_name) = (field_name(old), field_name(new));
+                if old_name != new_name {
+                    changes.push(ContractChange::breaking(format!(
+                        "Renamed field #{number} `{old_name}` -> `{new_name}` (breaks JSON clients)"
+                    )));
+                }
+                let (old_type, new_type) = (field_type_label(old), field_type_label(new));
+                if old_type != new_type {
+                    changes.push(ContractChange::breaking(format!(
+                        "Changed type of field `{new_name}` (#{number}): `{old_type}` -> `{new_type}`"
+                    )));
+                }
+            }
+            (None, None) => {}
+        }
+    }
+    changes
+}
+
+fn field_name(field: &FieldDescriptorProto) -> &str {
+    field.name.as_deref().unwrap_or("unknown_field")
+}
+
+fn field_type_label(field: &FieldDescriptorProto) -> String {
+    let type_name = readable_field_type(field);
+    if field.label == Some(Label::Repeated as i32) {
+        format!("repeated {type_name}")
+    } else {
+        type_name
+    }
+}
+
+fn describe_field(field: &FieldDescriptorProto) -> String {
+    format!(
+        "`{}` (`{}`, field #{})",
+        field_name(field),
+        field_type_label(field),
+        field.number.unwrap_or_default()
+    )
+}
+
+fn diff_enum(old: &EnumDescriptorProto, new: &EnumDescriptorProto) -> Vec<ContractChange> {
+    let by_number = |en: &EnumDescriptorProto| -> BTreeMap<i32, String> {
+        en.value
+            .iter()
+            .map(|value| {
+                (
+                    value.number.unwrap_or_default(),
+                    value.name.clone().unwrap_or_default(),
+                )
+            })
+            .collect()
+    };
+    let (old, new) = (by_number(old), by_number(new));
+    let numbers: BTreeSet<&i32> = old.keys().chain(new.keys()).collect();
+    let mut changes = Vec::new();
+    for number in numbers {
+        match (old.get(number), new.get(number)) {
+            (None, Some(name)) => changes.push(ContractChange::added(format!(
+                "Added value `{name}` = {number}"
+            ))),
+            (Some(name), None) => changes.push(ContractChange::breaking(format!(
+                "Removed value `{name}` = {number}"
+            ))),
+            (Some(old), Some(new)) if old != new => changes.push(ContractChange::breaking(
+                format!("Renamed value {number}: `{old}` -> `{new}`"),
+            )),
+            _ => {}
+        }
+    }
+    changes
+}
+
+fn build_changelog_markdown(diff: &ContractDiff, old: &Path, new: &Path) -> String {
+    let total = diff.changes().count();
+    let breaking = diff.changes().filter(|change| change.breaking).count();
+    let file_name = |path: &Path| {
+        path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string())
+    };
+    let mut lines = vec![
+        "# gRPC Contract Changelog".to_string(),
+        String::new(),
+        format!(
+            "Generated from descriptor sets `{}` -> `{}`.",
+            file_name(old),
+            file_name(new)
+        ),
+        String::new(),
+    ];
+    if total == 0 {
+        lines.push("No contract changes.".to_string());
+        lines.push(String::new());
+        return lines.join("\n");
+    }
+    lines.push(format!("{total} change(s), {breaking} breaking."));
+    lines.push(String::new());
+
+    for (title, section) in [
+        ("Services", &diff.services),
+        ("Messages", &diff.messages),
+        ("Enums", &diff.enums),
+    ] {
+        if section.is_empty() {
+            continue;
+        }
+        lines.push(format!("## {title}"));
+        lines.push(String::new());
+        for (name, changes) in section {
+            lines.push(format!("### `{name}`"));
+            lines.push(String::new());
+            for change in changes {
+                let marker = if change.breaking {
+                    "**Breaking:** "
+                } else {
+                    ""
+                };
+                lines.push(format!("- {marker}{}", change.description));
+            }
+            lines.push(String::new());
+        }
+    }
+
+    lines.join("\n")
+}
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use prost::Message;
+use tonic::{Code, Status};
+
+const BAD_REQUEST_TYPE_URL: &str = "type.googleapis.com/google.rpc.BadRequest";
+const ERROR_INFO_TYPE_URL: &str = "type.googleapis.com/google.rpc.ErrorInfo";
+
+/// `google.rpc.Status`, carried in the `grpc-status-details-bin` trailer.
+#[derive(Clone, PartialEq, Message)]
+pub struct RpcStatus {
+    #[prost(int32, tag = "1")]
+    pub code: i32,
+    #[prost(string, tag = "2")]
+    pub message: String,
+    #[prost(message, repeated, tag = "3")]
+    pub details: Vec<prost_types::Any>,
+}
+
+/// `google.rpc.BadRequest`: which request fields were invalid and why.
+#[derive(Clone, PartialEq, Eq, Message)]
+pub struct BadRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub field_violations: Vec<FieldViolation>,
+}
+
+#[derive(Clone, PartialEq, Eq, Message)]
+pub struct FieldViolation {
+    /// Dotted path to the field, e.g. `address.zip`.
+    #[prost(string, tag = "1")]
+    pub field: String,
+    #[prost(string, tag = "2")]
+    pub description: String,
+}
+
+/// `google.rpc.ErrorInfo`: a machine-readable `UPPER_SNAKE_CASE` reason within `domain`, plus
+/// string metadata such as limits.
+#[derive(Clone, PartialEq, Eq, Message)]
+pub struct ErrorInfo {
+    #[prost(string, tag = "1")]
+    pub reason: String,
+    #[prost(string, tag = "2")]
+    pub domain: String,
+    #[prost(map = "string, string", tag = "3")]
+    pub metadata: HashMap<String, String>,
+}
+
+/// The `google.rpc` rich error details the framework attaches to a `Status`, readable by any gRPC
+/// client that understands the standard error model (`grpcurl`, `grpc-status` helpers in Go,
+/// Java and Python, `tonic-types`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ErrorDetails {
+    pub bad_request: Option<BadRequest>,
+    pub error_info: Option<ErrorInfo>,
+}
+
+impl ErrorDetails {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_field_violation(
+        mut self,
+        field: impl Into<String>,
+        description: impl Into<String>,
+    ) -> Self {
+        self.bad_request
+            .get_or_insert_with(BadRequest::default)
+            .field_violations
+            .push(FieldViolation {
+                field: field.into(),
+                description: description.into(),
+            });
+        self
+    }
+
+    pub fn with_error_info(
+        mut self,
+        reason: impl Into<String>,
+        domain: impl Into<String>,
+        metadata: HashMap<String, String>,
+    ) -> Self {
+        self.error_info = Some(ErrorInfo {
+            reason: reason.into(),
+            domain: domain.into(),
+            metadata,
+        });
+        self
+    }
+
+    /// A status with these details encoded as `google.rpc.Status`.
+    pub fn into_status(self, code: Code, message: impl Into<String>) -> Status {
+        let message = message.into();
+        let mut details = Vec::new();
+        if let Some(bad_request) = &self.bad_request {
+            details.push(prost_types::Any {
+                type_url: BAD_REQUEST_TYPE_URL.to_string(),
+                value: bad_request.encode_to_vec(),
+            });
+        }
+        if let Some(error_info) = &self.error_info {
+            details.push(prost_types::Any {
+                type_url: ERROR_INFO_TYPE_URL.to_string(),
+                value: error_info.encode_to_vec(),
+            });
+        }
+        let status = RpcStatus {
+            code: code as i32,
+            message: message.clone(),
+            details,
+        };
+        Status::with_details(code, message, status.encode_to_vec().into())
+    }
+
+    /// Decodes the details of a received status; `None` when it carries no `google.rpc.Status`.
+    /// Detail types other than `BadRequest` and `ErrorInfo` are skipped.
+    pub fn from_status(status: &Status) -> Option<Self> {
+        if status.details().is_empty() {
+            return None;
+        }
+        let decoded = RpcStatus::decode(status.details()).ok()?;
+        let mut details = Self::default();
+        for any in decoded.details {
+            match any.type_url.as_str() {
+                BAD_REQUEST_TYPE_URL => {
+                    details.bad_request = BadRequest::decode(any.value.as_slice()).ok();
+                }
+                ERROR_INFO_TYPE_URL => {
+                    details.error_info = ErrorInfo::decode(any.value.as_slice()).ok();
+                }
+                _ => {}
+            }
+        }
+        Some(details)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn details_round_trip_through_the_status_trailer() {
+        let status = ErrorDetails::new()
+            .with_field_violation("title", "must not be empty")
+            .with_field_violation("tags.0", "too long")
+            .with_error_info(
+                "VALIDATION_ERROR",
+                "notes.example",
+                HashMap::from([("limit".to_string(), "3".to_string())]),
+            )
+            .into_status(Code::InvalidArgument, "request validation failed");
+
+        let decoded = RpcStatus::decode(status.details()).unwrap();
+        assert_eq!(decoded.code, Code::InvalidArgument as i32);
+        assert_eq!(decoded.message, "request validation failed");
+        assert_eq!(decoded.details[0].type_url, BAD_REQUEST_TYPE_URL);
+
+        let details = ErrorDetails::from_status(&status).unwrap();
+        let violations = details.bad_request.unwrap().field_violations;
+        assert_eq!(violations.len(), 2);
+        assert_eq!(violations[1].field, "tags.0");
+        let info = details.error_info.unwrap();
+        assert_eq!(info.reason, "VALIDATION_ERROR");
+        assert_eq!(info.metadata["limit"], "3");
+
+        assert_eq!(ErrorDetails::from_status(&Status::internal("plain")), None);
+    }
+}
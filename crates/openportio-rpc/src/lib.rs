@@ -1,14 +1,31 @@
-use openportio_core::{AppState, OpenportioResult};
+use std::{convert::Infallible, time::Duration};
 
-pub mod proto {
-    tonic::include_proto!("openportio.v1");
-}
+use openportio_core::{retry::Retryability, AppState, OpenportioResult};
+use prost::Message;
+use tonic::{
+    body::BoxBody,
+    codegen::{http, Service},
+    server::NamedService,
+    service::Routes,
+};
+
+pub mod error_details;
+
+// Every package under `proto/` becomes a nested module, e.g. `openportio.common.v1` is
+// `openportio::common::v1`.
+include!(concat!(env!("OUT_DIR"), "/openportio_packages.rs"));
+
+pub use openportio::v1 as proto;
 
 pub const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("openportio_descriptor");
 
+pub use error_details::ErrorDetails;
 pub use proto::greeter_client::GreeterClient;
 pub use proto::greeter_server::{Greeter, GreeterServer};
-pub use proto::{HelloRequest, HelloResponse};
+pub use proto::{HelloRequest, HelloResponse, HelloStreamRequest};
+
+/// Upper bound on the greetings a single `SayHelloStream` call yields.
+pub const MAX_HELLO_STREAM_COUNT: u32 = 1000;
 
 pub fn build_hello_response(
     state: &AppState,
@@ -18,6 +35,17 @@ pub fn build_hello_response(
     Ok(HelloResponse { message })
 }
 
+/// The greetings `SayHelloStream` yields: `count` copies (at least one, at most
+/// [`MAX_HELLO_STREAM_COUNT`]) of the unary response.
+pub fn build_hello_stream(
+    state: &AppState,
+    request: HelloStreamRequest,
+) -> OpenportioResult<Vec<HelloResponse>> {
+    let count = request.count.clamp(1, MAX_HELLO_STREAM_COUNT) as usize;
+    let response = build_hello_response(state, HelloRequest { name: request.name })?;
+    Ok(vec![response; count])
+}
+
 pub fn grpc_contract_docs_markdown() -> &'static str {
     include_str!("../generated/grpc-contracts.md")
 }
@@ -25,3 +53,209 @@ pub fn grpc_contract_docs_markdown() -> &'static str {
 pub fn grpc_contract_openapi_bridge_json() -> &'static str {
     include_str!("../generated/grpc-openapi-bridge.json")
 }
+
+/// Classifies a gRPC client error for `openportio_core::retry::with_retry_by`: `UNAVAILABLE`,
+/// `DEADLINE_EXCEEDED`, and `ABORTED` are retryable, `RESOURCE_EXHAUSTED` is throttled (honouring
+/// a `retry-after` seconds metadata entry), everything else is final.
+pub fn status_retryability(status: &tonic::Status) -> Retryability {
+    match status.code() {
+        tonic::Code::Unavailable | tonic::Code::DeadlineExceeded | tonic::Code::Aborted => {
+            Retryability::Retryable
+        }
+        tonic::Code::ResourceExhausted => Retryability::Throttled {
+            retry_after: status
+                .metadata()
+                .get("retry-after")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse().ok())
+                .map(Duration::from_secs),
+        },
+        _ => Retryability::NonRetryable,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceSummary {
+    /// Fully qualified name, e.g. `openportio.v1.Greeter`.
+    pub name: String,
+    pub methods: Vec<String>,
+}
+
+pub fn describe_services(descriptor_set: &[u8]) -> Result<Vec<ServiceSummary>, prost::DecodeError> {
+    let set = prost_types::FileDescriptorSet::decode(descriptor_set)?;
+    Ok(set
+        .file
+        .iter()
+        .flat_map(|file| {
+            let package = file.package();
+            file.service.iter().map(move |service| ServiceSummary {
+                name: if package.is_empty() {
+                    service.name().to_string()
+                } else {
+                    format!("{package}.{}", service.name())
+                },
+                methods: service
+                    .method
+                    .iter()
+                    .map(|method| method.name().to_string())
+                    .collect(),
+            })
+        })
+        .collect())
+}
+
+/// Declarative set of generated tonic services plus the descriptor sets that describe them.
+#[derive(Debug, Clone, Default)]
+pub struct ServiceRegistry {
+    routes: Option<Routes>,
+    service_names: Vec<String>,
+    descriptor_sets: Vec<&'static [u8]>,
+}
+
+impl ServiceRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Panics when a service with the same `NamedService::NAME` is already registered.
+    pub fn register<S>(mut self, service: S) -> Self
+    where
+        S: Service<http::Request<BoxBody>, Response = http::Response<BoxBody>, Error = Infallible>
+            + NamedService
+            + Clone
+            + Send
+            + 'static,
+        S::Future: Send + 'static,
+    {
+        assert!(
+            !self.service_names.iter().any(|name| name == S::NAME),
+            "grpc service `{}` is already registered",
+            S::NAME
+        );
+        self.routes = Some(match self.routes.take() {
+            Some(routes) => routes.add_service(service),
+            None => Routes::new(service),
+        });
+        self.service_names.push(S::NAME.to_string());
+        self
+    }
+
+    /// Adds an encoded `FileDescriptorSet` used for reflection and service catalogs.
+    pub fn with_descriptor_set(mut self, descriptor_set: &'static [u8]) -> Self {
+        if !self
+            .descriptor_sets
+            .iter()
+            .any(|existing| std::ptr::eq(*existing, descriptor_set))
+        {
+            self.descriptor_sets.push(descriptor_set);
+        }
+        self
+    }
+
+    pub fn service_names(&self) -> &[String] {
+        &self.service_names
+    }
+
+    pub fn descriptor_sets(&self) -> &[&'static [u8]] {
+        &self.descriptor_sets
+    }
+
+    /// Services and methods declared by the registered descriptor sets.
+    pub fn catalog(&self) -> Vec<ServiceSummary> {
+        self.descriptor_sets
+            .iter()
+            .flat_map(|set| describe_services(set).unwrap_or_default())
+            .collect()
+    }
+
+    pub fn into_routes(self) -> Routes {
+        self.routes.unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grpc_statuses_are_classified_for_retries() {
+        let mut throttled = tonic::Status::resource_exhausted("quota");
+        throttled
+            .metadata_mut()
+            .insert("retry-after", "7".parse().unwrap());
+        assert_eq!(
+            status_retryability(&throttled),
+            Retryability::Throttled {
+                retry_after: Some(Duration::from_secs(7))
+            }
+        );
+        assert_eq!(
+            status_retryability(&tonic::Status::unavailable("down")),
+            Retryability::Retryable
+        );
+        assert_eq!(
+            status_retryability(&tonic::Status::invalid_argument("bad")),
+            Retryability::NonRetryable
+        );
+    }
+
+    #[test]
+    fn every_proto_package_is_compiled_into_the_descriptor_set() {
+        let set = prost_types::FileDescriptorSet::decode(FILE_DESCRIPTOR_SET).expect("decode");
+        let packages = set
+            .file
+            .iter()
+            .map(|file| file.package().to_string())
+            .collect::<Vec<_>>();
+        assert!(packages.contains(&"openportio.v1".to_string()));
+        assert!(packages.contains(&"openportio.common.v1".to_string()));
+
+        let page = openportio::common::v1::PageRequest {
+            page_size: 10,
+            page_token: String::new(),
+        };
+        assert_eq!(page.page_size, 10);
+    }
+
+    #[test]
+    fn registry_tracks_services_and_catalog() {
+        #[derive(Clone)]
+        struct Greeter;
+
+        #[tonic::async_trait]
+        impl proto::greeter_server::Greeter for Greeter {
+            async fn say_hello(
+                &self,
+                request: tonic::Request<HelloRequest>,
+            ) -> Result<tonic::Response<HelloResponse>, tonic::Status> {
+                Ok(tonic::Response::new(HelloResponse {
+                    message: request.into_inner().name,
+                }))
+            }
+
+            type SayHelloStreamStream = tonic::codegen::BoxStream<HelloResponse>;
+
+            async fn say_hello_stream(
+                &self,
+                _request: tonic::Request<HelloStreamRequest>,
+            ) -> Result<tonic::Response<Self::SayHelloStreamStream>, tonic::Status> {
+                Err(tonic::Status::unimplemented("not used"))
+            }
+        }
+
+        let registry = ServiceRegistry::new()
+            .with_descriptor_set(FILE_DESCRIPTOR_SET)
+            .with_descriptor_set(FILE_DESCRIPTOR_SET)
+            .register(GreeterServer::new(Greeter));
+
+        assert_eq!(registry.service_names(), ["openportio.v1.Greeter"]);
+        assert_eq!(registry.descriptor_sets().len(), 1);
+        assert_eq!(
+            registry.catalog(),
+            vec![ServiceSummary {
+                name: "openportio.v1.Greeter".to_string(),
+                methods: vec!["SayHello".to_string(), "SayHelloStream".to_string()],
+            }]
+        );
+    }
+}
@@ -0,0 +1,65 @@
+use std::{path::Path, process::Command};
+
+fn export(proto_root: &Path, out: &Path) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_buf-export"))
+        .arg("--proto-root")
+        .arg(proto_root)
+        .arg("--out")
+        .arg(out)
+        .arg("--module")
+        .arg("buf.build/acme/contracts")
+        .output()
+        .expect("run buf-export")
+}
+
+#[test]
+fn buf_export_lays_out_packages_and_rewrites_imports() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let out = tmp.path().join("module");
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+
+    let output = export(&fixtures, &out);
+    assert!(
+        output.status.success(),
+        "buf-export failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let complex = std::fs::read_to_string(out.join("fixture/docs/v1/complex.proto"))
+        .expect("complex.proto moved under its package");
+    assert!(complex.contains("import \"fixture/common/v1/common.proto\";"));
+    assert!(out.join("fixture/common/v1/common.proto").exists());
+
+    let buf_yaml = std::fs::read_to_string(out.join("buf.yaml")).expect("buf.yaml");
+    assert!(buf_yaml.contains("version: v2"));
+    assert!(buf_yaml.contains("name: buf.build/acme/contracts"));
+    assert!(out.join("buf.gen.yaml").exists());
+    let readme = std::fs::read_to_string(out.join("README.md")).expect("README.md");
+    assert!(readme.contains("| `fixture.docs.v1` | `fixture/docs/v1/complex.proto` |"));
+
+    // Re-exporting replaces the previous export, but never an unrelated directory.
+    assert!(export(&fixtures, &out).status.success());
+    let unrelated = tmp.path().join("unrelated");
+    std::fs::create_dir_all(&unrelated).unwrap();
+    std::fs::write(unrelated.join("notes.txt"), "keep me").unwrap();
+    assert!(!export(&fixtures, &unrelated).status.success());
+    assert!(unrelated.join("notes.txt").exists());
+}
+
+#[test]
+fn buf_export_accepts_the_crate_protos() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let proto_root = Path::new(env!("CARGO_MANIFEST_DIR")).join("proto");
+
+    let output = export(&proto_root, tmp.path());
+    assert!(
+        output.status.success(),
+        "buf-export failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(tmp.path().join("openportio/v1/service.proto").exists());
+    assert!(tmp
+        .path()
+        .join("openportio/common/v1/pagination.proto")
+        .exists());
+}
@@ -1,5 +1,8 @@
 use openportio_core::AppState;
-use openportio_rpc::{build_hello_response, Greeter, GreeterServer, HelloRequest, HelloResponse};
+use openportio_rpc::{
+    build_hello_response, build_hello_stream, Greeter, GreeterServer, HelloRequest, HelloResponse,
+    HelloStreamRequest, MAX_HELLO_STREAM_COUNT,
+};
 use tonic::{Request, Response, Status};
 
 #[derive(Default)]
@@ -16,6 +19,15 @@ impl Greeter for TestGreeter {
             message: format!("hi {}", req.name),
         }))
     }
+
+    type SayHelloStreamStream = tonic::codegen::BoxStream<HelloResponse>;
+
+    async fn say_hello_stream(
+        &self,
+        _request: Request<HelloStreamRequest>,
+    ) -> Result<Response<Self::SayHelloStreamStream>, Status> {
+        Err(Status::unimplemented("not used"))
+    }
 }
 
 #[test]
@@ -39,3 +51,23 @@ fn build_hello_response_matches_domain_behavior() {
 
     assert_eq!(result.message, "Hello, Rust!");
 }
+
+#[test]
+fn build_hello_stream_repeats_the_greeting_within_bounds() {
+    let state = AppState::local("rpc-contract-test");
+    let stream = |count| {
+        build_hello_stream(
+            &state,
+            HelloStreamRequest {
+                name: "Rust".to_string(),
+                count,
+            },
+        )
+        .expect("stream should be built")
+    };
+
+    assert_eq!(stream(0).len(), 1);
+    assert_eq!(stream(3).len(), 3);
+    assert_eq!(stream(u32::MAX).len(), MAX_HELLO_STREAM_COUNT as usize);
+    assert!(stream(2).iter().all(|r| r.message == "Hello, Rust!"));
+}
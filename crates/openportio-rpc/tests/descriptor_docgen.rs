@@ -57,4 +57,303 @@ fn descriptor_docgen_supports_complex_protobuf_features() {
         json.contains("fixture.common.v1.CommonMeta"),
         "openapi bridge should reference imported message schemas"
     );
+
+    let bridge: serde_json::Value = serde_json::from_str(&json).expect("bridge is json");
+    let request_example = serde_json::json!({
+        "meta": {"trace_id": "trace_id", "tags": {"key": "value"}},
+        "fields": ["fields"],
+        "id": "id",
+        "labels": {"key": {"value": "value", "scope": "INTERNAL"}}
+    });
+    assert_eq!(
+        bridge["components"]["schemas"]["fixture.docs.v1.GetDocRequest"]["example"],
+        request_example,
+        "examples should cover nested messages, maps, repeated fields, oneofs and enums"
+    );
+    let operation = &bridge["paths"]["/fixture.docs.v1.DocsService/GetDoc"]["post"];
+    assert_eq!(
+        operation["requestBody"]["content"]["application/grpc+proto"]["examples"]["default"]
+            ["value"],
+        request_example
+    );
+    assert_eq!(
+        operation["responses"]["200"]["content"]["application/grpc+proto"]["examples"]["default"]
+            ["value"],
+        serde_json::json!({"results": [{"id": "id", "status": "READY"}]})
+    );
+}
+
+#[test]
+fn descriptor_docgen_documents_proto_comments() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let out_md = tmp.path().join("grpc-contracts.md");
+    let out_openapi = tmp.path().join("grpc-openapi-bridge.json");
+    let fixture_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+
+    let status = Command::new(env!("CARGO_BIN_EXE_grpc-docgen"))
+        .arg("--proto")
+        .arg(fixture_dir.join("complex.proto"))
+        .arg("--include")
+        .arg(&fixture_dir)
+        .arg("--out-md")
+        .arg(&out_md)
+        .arg("--out-openapi")
+        .arg(&out_openapi)
+        .status()
+        .expect("run grpc-docgen");
+    assert!(status.success());
+
+    let md = std::fs::read_to_string(&out_md).expect("read markdown");
+    for expected in [
+        "### `fixture.docs.v1.DocsService`\n\nServes rendered documents.\n",
+        "(client_streaming=false, server_streaming=false): Fetches one document by id or slug.",
+        "### `fixture.docs.v1.GetDocRequest`\n\nSelects a document and the fields to return.\n",
+        "- `fields` (`string`, field #2, repeated): Field mask; empty returns every field.",
+        "- `fixture.docs.v1.GetDocResponse.Status`: STATUS_UNSPECIFIED, READY, FAILED — Rendering state.",
+        "  - `READY`: Rendered and cached.",
+    ] {
+        assert!(md.contains(expected), "missing `{expected}` in:\n{md}");
+    }
+
+    let bridge: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&out_openapi).expect("read bridge"))
+            .expect("bridge is json");
+    let schemas = &bridge["components"]["schemas"];
+    let request = &schemas["fixture.docs.v1.GetDocRequest"];
+    assert_eq!(
+        request["description"],
+        "Selects a document and the fields to return."
+    );
+    assert_eq!(
+        request["properties"]["fields"]["description"],
+        "Field mask; empty returns\nevery field."
+    );
+    assert_eq!(
+        request["properties"]["meta"],
+        serde_json::json!({
+            "allOf": [{"$ref": "#/components/schemas/fixture.common.v1.CommonMeta"}],
+            "description": "Caller metadata."
+        })
+    );
+    let status = &schemas["fixture.docs.v1.GetDocResponse.Status"];
+    assert_eq!(status["description"], "Rendering state.");
+    assert_eq!(
+        status["x-openportio-enum-descriptions"],
+        serde_json::json!({"READY": "Rendered and cached."})
+    );
+    assert_eq!(
+        bridge["paths"]["/fixture.docs.v1.DocsService/GetDoc"]["post"]["description"],
+        "Fetches one document by id or slug."
+    );
+    assert_eq!(
+        bridge["tags"],
+        serde_json::json!([{"name": "fixture.docs.v1.DocsService", "description": "Serves rendered documents."}])
+    );
+}
+
+#[test]
+fn descriptor_docgen_writes_standalone_json_schemas() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let fixture_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let out_dir = tmp.path().join("schemas");
+
+    let status = Command::new(env!("CARGO_BIN_EXE_grpc-docgen"))
+        .arg("--proto")
+        .arg(fixture_dir.join("complex.proto"))
+        .arg("--include")
+        .arg(&fixture_dir)
+        .arg("--out-md")
+        .arg(tmp.path().join("contracts.md"))
+        .arg("--out-openapi")
+        .arg(tmp.path().join("bridge.json"))
+        .arg("--out-jsonschema")
+        .arg(&out_dir)
+        .status()
+        .expect("run grpc-docgen");
+    assert!(status.success(), "grpc-docgen must write json schemas");
+
+    let read = |name: &str| -> serde_json::Value {
+        let raw = std::fs::read_to_string(out_dir.join(format!("{name}.schema.json")))
+            .expect("read json schema");
+        serde_json::from_str(&raw).expect("schema is json")
+    };
+    let schema = read("fixture.docs.v1.GetDocRequest");
+    assert_eq!(
+        schema["$schema"],
+        "https://json-schema.org/draft/2020-12/schema"
+    );
+    assert_eq!(schema["$id"], "fixture.docs.v1.GetDocRequest.schema.json");
+    assert_eq!(
+        schema["properties"]["meta"]["$ref"],
+        "#/$defs/fixture.common.v1.CommonMeta"
+    );
+    assert_eq!(
+        schema["$defs"]["fixture.common.v1.CommonMeta"]["properties"]["tags"],
+        serde_json::json!({"type": "object", "additionalProperties": {"type": "string"}})
+    );
+    assert_eq!(
+        schema["properties"]["labels"]["additionalProperties"]["$ref"],
+        "#/$defs/fixture.docs.v1.GetDocRequest.NestedInfo"
+    );
+    assert_eq!(
+        schema["$defs"]["fixture.docs.v1.GetDocRequest.NestedInfo"]["properties"]["scope"]["enum"],
+        serde_json::json!(["SCOPE_UNSPECIFIED", "INTERNAL", "EXTERNAL"])
+    );
+    assert_eq!(
+        schema["oneOf"],
+        serde_json::json!([
+            {"required": ["id"]},
+            {"required": ["slug"]},
+            {"not": {"anyOf": [{"required": ["id"]}, {"required": ["slug"]}]}}
+        ]),
+        "a oneof allows at most one member"
+    );
+
+    let response = read("fixture.docs.v1.GetDocResponse");
+    assert_eq!(
+        response["$defs"]["fixture.docs.v1.GetDocResponse.Result"]["properties"]["status"]
+            ["description"],
+        "Rendering state."
+    );
+    assert!(
+        !out_dir
+            .join("fixture.docs.v1.GetDocRequest.LabelsEntry.schema.json")
+            .exists(),
+        "map entries are not standalone messages"
+    );
+}
+
+#[test]
+fn descriptor_docgen_compiler_selection() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let fixture_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let run = |compiler: &str| {
+        let out_md = tmp.path().join(format!("{compiler}.md"));
+        let output = Command::new(env!("CARGO_BIN_EXE_grpc-docgen"))
+            .arg("--proto")
+            .arg(fixture_dir.join("complex.proto"))
+            .arg("--include")
+            .arg(&fixture_dir)
+            .arg("--out-md")
+            .arg(&out_md)
+            .arg("--out-openapi")
+            .arg(tmp.path().join(format!("{compiler}.json")))
+            .arg("--compiler")
+            .arg(compiler)
+            .output()
+            .expect("run grpc-docgen");
+        (output, out_md)
+    };
+
+    let (protoc, protoc_md) = run("protoc");
+    assert!(protoc.status.success(), "explicit protoc must succeed");
+
+    let (protox, protox_md) = run("protox");
+    if cfg!(feature = "protox") {
+        assert!(protox.status.success(), "protox must succeed");
+        assert_eq!(
+            std::fs::read_to_string(protox_md).expect("read protox markdown"),
+            std::fs::read_to_string(protoc_md).expect("read protoc markdown"),
+            "both compilers must produce the same contract docs"
+        );
+    } else {
+        assert!(!protox.status.success());
+        assert!(String::from_utf8_lossy(&protox.stderr).contains("--features protox"));
+    }
+
+    let (unknown, _) = run("javac");
+    assert!(!unknown.status.success());
+}
+
+#[test]
+fn descriptor_docgen_writes_changelog_between_descriptor_sets() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let compile = |name: &str, proto: &str| {
+        let dir = tmp.path().join(name);
+        std::fs::create_dir_all(&dir).expect("proto dir");
+        std::fs::write(dir.join("notes.proto"), proto).expect("write proto");
+        let out = tmp.path().join(format!("{name}.bin"));
+        let status = Command::new(protoc_bin_vendored::protoc_bin_path().expect("protoc"))
+            .arg(format!("--descriptor_set_out={}", out.display()))
+            .arg("-I")
+            .arg(&dir)
+            .arg(dir.join("notes.proto"))
+            .status()
+            .expect("run protoc");
+        assert!(status.success(), "protoc must compile the {name} fixture");
+        out
+    };
+    let old = compile(
+        "old",
+        r#"syntax = "proto3";
+package notes.v1;
+enum Visibility { PRIVATE = 0; PUBLIC = 1; }
+message Note { string id = 1; int32 rank = 2; string body = 3; }
+message GetNoteRequest { string id = 1; }
+service Notes {
+  rpc GetNote(GetNoteRequest) returns (Note);
+  rpc DeleteNote(GetNoteRequest) returns (Note);
+}
+"#,
+    );
+    let new = compile(
+        "new",
+        r#"syntax = "proto3";
+package notes.v1;
+enum Visibility { PRIVATE = 0; PUBLIC = 1; SHARED = 2; }
+message Note { string id = 1; string rank = 2; repeated string tags = 4; }
+message GetNoteRequest { string id = 1; }
+message ListNotesRequest {}
+service Notes {
+  rpc GetNote(GetNoteRequest) returns (Note);
+  rpc ListNotes(ListNotesRequest) returns (stream Note);
+}
+"#,
+    );
+
+    let out = tmp.path().join("generated/grpc-changelog.md");
+    let output = Command::new(env!("CARGO_BIN_EXE_grpc-docgen"))
+        .arg("--changelog")
+        .arg(&old)
+        .arg(&new)
+        .arg("--out")
+        .arg(&out)
+        .output()
+        .expect("run grpc-docgen");
+    assert!(
+        output.status.success(),
+        "changelog must succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let md = std::fs::read_to_string(&out).expect("read changelog");
+    for expected in [
+        "Generated from descriptor sets `old.bin` -> `new.bin`.",
+        "7 change(s), 3 breaking.",
+        "### `notes.v1.Notes`",
+        "- Added method `ListNotes`: `notes.v1.ListNotesRequest` -> `stream notes.v1.Note`",
+        "- **Breaking:** Removed method `DeleteNote`",
+        "### `notes.v1.Note`",
+        "- **Breaking:** Changed type of field `rank` (#2): `int32` -> `string`",
+        "- **Breaking:** Removed field `body` (`string`, field #3)",
+        "- Added field `tags` (`repeated string`, field #4)",
+        "### `notes.v1.ListNotesRequest`\n\n- Added message",
+        "### `notes.v1.Visibility`\n\n- Added value `SHARED` = 2",
+    ] {
+        assert!(md.contains(expected), "missing `{expected}` in:\n{md}");
+    }
+
+    let unchanged = tmp.path().join("unchanged.md");
+    let status = Command::new(env!("CARGO_BIN_EXE_grpc-docgen"))
+        .arg("--changelog")
+        .arg(&new)
+        .arg(&new)
+        .arg("--out")
+        .arg(&unchanged)
+        .status()
+        .expect("run grpc-docgen");
+    assert!(status.success());
+    assert!(std::fs::read_to_string(unchanged)
+        .expect("read changelog")
+        .contains("No contract changes."));
 }
@@ -0,0 +1,4 @@
+fn main() {
+    // Fills in git, timestamp, rustc and features for `openportio_buildinfo!()` in this crate.
+    openportio_core::buildinfo::emit();
+}
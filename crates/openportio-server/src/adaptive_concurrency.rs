@@ -0,0 +1,419 @@
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::{from_fn_with_state, Next},
+    response::Response,
+    Router,
+};
+use openportio_core::MetricsSink;
+use tokio::time::Instant;
+
+use crate::middleware::{is_grpc_request, overloaded_response, HEALTH_PATHS};
+
+const DEFAULT_INITIAL_LIMIT: usize = 20;
+const DEFAULT_MIN_LIMIT: usize = 1;
+const DEFAULT_MAX_LIMIT: usize = 1000;
+const DEFAULT_LATENCY_THRESHOLD: Duration = Duration::from_secs(1);
+const DEFAULT_BACKOFF_RATIO: f64 = 0.9;
+const DEFAULT_SMOOTHING: f64 = 0.2;
+const DEFAULT_TOLERANCE: f64 = 1.5;
+/// Samples averaged into the baseline and recent latency of the gradient algorithm.
+const LONG_WINDOW: f64 = 600.0;
+const SHORT_WINDOW: f64 = 10.0;
+/// gRPC UNAVAILABLE and DEADLINE_EXCEEDED, reported in the response head of trailers-only
+/// responses.
+const GRPC_OVERLOAD_CODES: &[&str] = &["14", "4"];
+
+/// How the in-flight ceiling follows observed latency.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LimitAlgorithm {
+    /// Additive increase, multiplicative decrease: the limit grows by one per call that finished
+    /// within `latency_threshold` while at least half the limit was in use, and is multiplied by
+    /// `backoff_ratio` whenever a call was slower, timed out, or answered 503/504.
+    Aimd {
+        latency_threshold: Duration,
+        backoff_ratio: f64,
+    },
+    /// Compares recent latency with a long-running baseline: the limit shrinks as latency climbs
+    /// past `tolerance` times the baseline and grows by a small queue allowance otherwise.
+    /// `smoothing` is how much of each new estimate is applied per call.
+    Gradient { tolerance: f64, smoothing: f64 },
+}
+
+impl FromStr for LimitAlgorithm {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "aimd" => Ok(AdaptiveConcurrency::aimd(DEFAULT_LATENCY_THRESHOLD).algorithm),
+            "gradient" => Ok(AdaptiveConcurrency::gradient().algorithm),
+            other => Err(format!(
+                "unsupported adaptive concurrency algorithm `{other}`"
+            )),
+        }
+    }
+}
+
+/// Settings for [`with_adaptive_concurrency`]. Calls beyond the current limit are shed with
+/// `503` + `Retry-After` (gRPC `UNAVAILABLE`); health probes are never counted or shed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdaptiveConcurrency {
+    pub algorithm: LimitAlgorithm,
+    pub initial_limit: usize,
+    pub min_limit: usize,
+    pub max_limit: usize,
+    pub retry_after_seconds: u64,
+}
+
+impl AdaptiveConcurrency {
+    pub fn aimd(latency_threshold: Duration) -> Self {
+        Self::new(LimitAlgorithm::Aimd {
+            latency_threshold,
+            backoff_ratio: DEFAULT_BACKOFF_RATIO,
+        })
+    }
+
+    pub fn gradient() -> Self {
+        Self::new(LimitAlgorithm::Gradient {
+            tolerance: DEFAULT_TOLERANCE,
+            smoothing: DEFAULT_SMOOTHING,
+        })
+    }
+
+    fn new(algorithm: LimitAlgorithm) -> Self {
+        Self {
+            algorithm,
+            initial_limit: DEFAULT_INITIAL_LIMIT,
+            min_limit: DEFAULT_MIN_LIMIT,
+            max_limit: DEFAULT_MAX_LIMIT,
+            retry_after_seconds: 1,
+        }
+    }
+
+    /// Bounds for the limit and its starting point; `initial` is clamped into `min..=max`.
+    pub fn with_limits(mut self, min: usize, initial: usize, max: usize) -> Self {
+        self.min_limit = min.max(1);
+        self.max_limit = max.max(self.min_limit);
+        self.initial_limit = initial.clamp(self.min_limit, self.max_limit);
+        self
+    }
+
+    pub fn with_retry_after(mut self, seconds: u64) -> Self {
+        self.retry_after_seconds = seconds;
+        self
+    }
+
+    /// `OPENPORTIO_ADAPTIVE_CONCURRENCY` (`aimd` or `gradient`) enables the limiter;
+    /// `OPENPORTIO_ADAPTIVE_CONCURRENCY_LATENCY_MS` sets the AIMD threshold and
+    /// `OPENPORTIO_ADAPTIVE_CONCURRENCY_MIN` / `_MAX` bound the limit.
+    pub(crate) fn from_env() -> Result<Option<Self>, String> {
+        let Some(algorithm) = crate::env::setting::<LimitAlgorithm>("ADAPTIVE_CONCURRENCY")? else {
+            return Ok(None);
+        };
+        let config = match algorithm {
+            LimitAlgorithm::Aimd { .. } => Self::aimd(
+                crate::env::setting("ADAPTIVE_CONCURRENCY_LATENCY_MS")?
+                    .map(Duration::from_millis)
+                    .unwrap_or(DEFAULT_LATENCY_THRESHOLD),
+            ),
+            LimitAlgorithm::Gradient { .. } => Self::gradient(),
+        };
+        let min = crate::env::setting("ADAPTIVE_CONCURRENCY_MIN")?.unwrap_or(config.min_limit);
+        let max = crate::env::setting("ADAPTIVE_CONCURRENCY_MAX")?.unwrap_or(config.max_limit);
+        let initial = config.initial_limit;
+        Ok(Some(config.with_limits(min, initial, max)))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self.algorithm {
+            LimitAlgorithm::Aimd { .. } => "aimd",
+            LimitAlgorithm::Gradient { .. } => "gradient",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LimiterState {
+    limit: f64,
+    in_flight: usize,
+    /// Exponential moving averages of call latency in seconds (gradient only).
+    long_rtt: Option<f64>,
+    short_rtt: Option<f64>,
+}
+
+impl LimiterState {
+    fn new(config: &AdaptiveConcurrency) -> Self {
+        Self {
+            limit: config.initial_limit as f64,
+            in_flight: 0,
+            long_rtt: None,
+            short_rtt: None,
+        }
+    }
+
+    fn effective_limit(&self) -> usize {
+        self.limit as usize
+    }
+
+    /// Updates the limit after a call that took `rtt`, with `in_flight` still counting it; `overloaded` marks calls that timed out or answered 503/504.
+    fn sample(&mut self, config: &AdaptiveConcurrency, rtt: Duration, overloaded: bool) {
+        let limit = match config.algorithm {
+            LimitAlgorithm::Aimd {
+                latency_threshold,
+                backoff_ratio,
+            } => {
+                if overloaded || rtt > latency_threshold {
+                    self.limit * backoff_ratio
+                } else if self.in_flight * 2 >= self.effective_limit() {
+                    self.limit + 1.0
+                } else {
+                    self.limit
+                }
+            }
+            LimitAlgorithm::Gradient {
+                tolerance,
+                smoothing,
+            } => {
+                let rtt = rtt.as_secs_f64();
+                let long = ema(self.long_rtt, rtt, LONG_WINDOW);
+                let short = ema(self.short_rtt, rtt, SHORT_WINDOW);
+                self.long_rtt = Some(long);
+                self.short_rtt = Some(short);
+                // Idle capacity says nothing about how much more the server could take.
+                if self.in_flight * 2 < self.effective_limit() && !overloaded {
+                    return;
+                }
+                let gradient = if short > 0.0 {
+                    (tolerance * long / short).clamp(0.5, 1.0)
+                } else {
+                    1.0
+                };
+                let estimate = self.limit * gradient + self.limit.sqrt();
+                self.limit * (1.0 - smoothing) + estimate * smoothing
+            }
+        };
+        self.limit = limit.clamp(config.min_limit as f64, config.max_limit as f64);
+    }
+}
+
+fn ema(average: Option<f64>, sample: f64, window: f64) -> f64 {
+    match average {
+        Some(average) => average + (sample - average) / window,
+        None => sample,
+    }
+}
+
+#[derive(Clone)]
+struct AdaptiveLimiter {
+    config: Arc<AdaptiveConcurrency>,
+    state: Arc<Mutex<LimiterState>>,
+    metrics: Arc<dyn MetricsSink>,
+}
+
+impl AdaptiveLimiter {
+    fn lock(&self) -> std::sync::MutexGuard<'_, LimiterState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn try_acquire(&self) -> Option<InFlight> {
+        let mut state = self.lock();
+        if state.in_flight >= state.effective_limit() {
+            return None;
+        }
+        state.in_flight += 1;
+        Some(InFlight {
+            limiter: self.clone(),
+            started: Instant::now(),
+            overloaded: None,
+        })
+    }
+}
+
+/// One admitted call. Dropping it before `finish` (the request timed out or the client went
+/// away) counts as an overloaded sample.
+struct InFlight {
+    limiter: AdaptiveLimiter,
+    started: Instant,
+    overloaded: Option<bool>,
+}
+
+impl InFlight {
+    fn finish(mut self, overloaded: bool) {
+        self.overloaded = Some(overloaded);
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let limiter = &self.limiter;
+        let limit = {
+            let mut state = limiter.lock();
+            state.sample(
+                &limiter.config,
+                self.started.elapsed(),
+                self.overloaded.unwrap_or(true),
+            );
+            state.in_flight -= 1;
+            state.effective_limit()
+        };
+        limiter
+            .metrics
+            .set_gauge("adaptive_concurrency.limit", limit as f64, &[]);
+    }
+}
+
+/// Caps in-flight REST and gRPC calls at a limit that adapts to observed latency (see
+/// [`LimitAlgorithm`]), unlike the fixed `max_in_flight_requests`. Reports the current limit
+/// as the `adaptive_concurrency.limit` gauge and shed calls as `adaptive_concurrency.dropped`
+/// (label `protocol`). Latency is measured until the response head, so streaming bodies are
+/// not included.
+pub fn with_adaptive_concurrency(
+    router: Router,
+    config: AdaptiveConcurrency,
+    metrics: Arc<dyn MetricsSink>,
+) -> Router {
+    metrics.set_gauge(
+        "adaptive_concurrency.limit",
+        config.initial_limit as f64,
+        &[],
+    );
+    let limiter = AdaptiveLimiter {
+        state: Arc::new(Mutex::new(LimiterState::new(&config))),
+        config: Arc::new(config),
+        metrics,
+    };
+    router.layer(from_fn_with_state(limiter, adaptive_concurrency_middleware))
+}
+
+async fn adaptive_concurrency_middleware(
+    State(limiter): State<AdaptiveLimiter>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if HEALTH_PATHS.contains(&req.uri().path()) {
+        return next.run(req).await;
+    }
+    let is_grpc = is_grpc_request(req.headers());
+    let Some(in_flight) = limiter.try_acquire() else {
+        let protocol = if is_grpc { "grpc" } else { "rest" };
+        tracing::warn!(
+            path = %req.uri().path(),
+            protocol,
+            "shedding request: adaptive concurrency limit reached"
+        );
+        limiter
+            .metrics
+            .incr_counter_with("adaptive_concurrency.dropped", &[("protocol", protocol)]);
+        if is_grpc {
+            return tonic::Status::unavailable("server is overloaded; retry later")
+                .into_http()
+                .map(Body::new);
+        }
+        return overloaded_response(limiter.config.retry_after_seconds);
+    };
+
+    let response = next.run(req).await;
+    let overloaded = matches!(
+        response.status(),
+        StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+    ) || response
+        .headers()
+        .get("grpc-status")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|code| GRPC_OVERLOAD_CODES.contains(&code));
+    in_flight.finish(overloaded);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use openportio_test::RecordingMetrics;
+    use tower::util::ServiceExt;
+
+    fn run(state: &mut LimiterState, config: &AdaptiveConcurrency, in_flight: usize, ms: u64) {
+        state.in_flight = in_flight;
+        state.sample(config, Duration::from_millis(ms), false);
+    }
+
+    #[test]
+    fn aimd_grows_while_busy_and_backs_off_when_slow() {
+        let config = AdaptiveConcurrency::aimd(Duration::from_millis(100)).with_limits(2, 10, 12);
+        let mut state = LimiterState::new(&config);
+
+        run(&mut state, &config, 2, 10);
+        assert_eq!(state.effective_limit(), 10, "mostly idle: no growth");
+        for _ in 0..5 {
+            run(&mut state, &config, 8, 10);
+        }
+        assert_eq!(state.effective_limit(), 12, "capped at max");
+        run(&mut state, &config, 8, 150);
+        assert_eq!(state.effective_limit(), 10);
+        state.sample(&config, Duration::from_millis(1), true);
+        assert_eq!(state.effective_limit(), 9);
+    }
+
+    #[test]
+    fn gradient_shrinks_as_latency_climbs_above_baseline() {
+        let config = AdaptiveConcurrency::gradient().with_limits(1, 20, 100);
+        let mut state = LimiterState::new(&config);
+
+        for _ in 0..20 {
+            run(&mut state, &config, 20, 10);
+        }
+        let steady = state.effective_limit();
+        assert!(steady > 20, "steady latency leaves room to grow: {steady}");
+        for _ in 0..20 {
+            run(&mut state, &config, steady, 100);
+        }
+        assert!(
+            state.effective_limit() < steady,
+            "{} should be below {steady}",
+            state.effective_limit()
+        );
+    }
+
+    #[tokio::test]
+    async fn calls_beyond_the_limit_are_shed() {
+        let metrics = Arc::new(RecordingMetrics::default());
+        let app = with_adaptive_concurrency(
+            Router::new()
+                .route(
+                    "/slow",
+                    get(|| async {
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        "done"
+                    }),
+                )
+                .route("/health", get(|| async { "ok" })),
+            AdaptiveConcurrency::aimd(Duration::from_secs(5)).with_limits(1, 1, 1),
+            metrics.clone(),
+        );
+        let call = |uri: &'static str| {
+            app.clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        };
+
+        let slow = tokio::spawn(call("/slow"));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let shed = call("/slow").await.unwrap();
+        assert_eq!(shed.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(shed.headers()["retry-after"], "1");
+        assert_eq!(call("/health").await.unwrap().status(), StatusCode::OK);
+        assert_eq!(slow.await.unwrap().unwrap().status(), StatusCode::OK);
+        assert_eq!(call("/slow").await.unwrap().status(), StatusCode::OK);
+
+        assert_eq!(
+            metrics.counters(),
+            vec!["adaptive_concurrency.dropped{protocol=rest}".to_string()]
+        );
+    }
+}
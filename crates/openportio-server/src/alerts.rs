@@ -0,0 +1,504 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, UNIX_EPOCH},
+};
+
+use axum::{
+    extract::{Request, State},
+    middleware::{from_fn, from_fn_with_state, Next},
+    response::Response,
+    Router,
+};
+use serde::Serialize;
+
+use crate::{
+    di::{clock_from_extensions, resolve_from_extensions},
+    middleware::is_grpc_request,
+    slow_requests::{capture_matched_route, MatchedRoute},
+};
+
+const UNMATCHED_ROUTE: &str = "unmatched";
+/// Each window is tracked as this many sub-buckets, so it rolls instead of resetting.
+const WINDOW_BUCKETS: u32 = 10;
+const DEFAULT_WINDOW: Duration = Duration::from_secs(60);
+const DEFAULT_MIN_REQUESTS: u64 = 20;
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+/// gRPC codes that mean the server failed rather than the caller: UNKNOWN, DEADLINE_EXCEEDED,
+/// INTERNAL, UNAVAILABLE, DATA_LOSS.
+const GRPC_SERVER_ERROR_CODES: &[&str] = &["2", "4", "13", "14", "15"];
+
+/// Destination for error-rate alerts. Register one with `OpenportioServer::with_alert_sink`;
+/// without one, alerts go to [`TracingAlertSink`].
+pub trait AlertSink: Send + Sync + 'static {
+    fn send(&self, alert: &Alert);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    /// Share of failed calls (HTTP 4xx/5xx, non-OK gRPC status) crossed `error_rate`.
+    ErrorRate,
+    /// Share of server failures (HTTP 5xx, gRPC INTERNAL/UNAVAILABLE/...) crossed
+    /// `server_error_rate`.
+    ServerErrorRate,
+}
+
+impl AlertKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::ErrorRate => "error_rate",
+            Self::ServerErrorRate => "server_error_rate",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Alert {
+    pub kind: AlertKind,
+    pub protocol: &'static str,
+    /// REST route template (`/users/:id`, `unmatched` for 404s) or gRPC method path.
+    pub route: String,
+    pub window_secs: u64,
+    pub requests: u64,
+    pub failures: u64,
+    pub rate: f64,
+    pub threshold: f64,
+    /// Wall-clock time in Unix milliseconds, from the registered `SharedClock`.
+    pub timestamp_ms: u64,
+}
+
+/// Thresholds for [`with_error_rate_alerts`]. Rates are fractions (`0.05` = 5%) over a rolling
+/// `window` per route; routes with fewer than `min_requests` calls in the window never alert.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertRules {
+    pub window: Duration,
+    pub min_requests: u64,
+    pub error_rate: Option<f64>,
+    pub server_error_rate: Option<f64>,
+    /// Quiet period per route and kind after an alert fires. Defaults to `window`.
+    pub cooldown: Option<Duration>,
+}
+
+impl Default for AlertRules {
+    fn default() -> Self {
+        Self {
+            window: DEFAULT_WINDOW,
+            min_requests: DEFAULT_MIN_REQUESTS,
+            error_rate: None,
+            server_error_rate: None,
+            cooldown: None,
+        }
+    }
+}
+
+impl AlertRules {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_error_rate(mut self, threshold: f64) -> Self {
+        self.error_rate = Some(threshold);
+        self
+    }
+
+    pub fn with_server_error_rate(mut self, threshold: f64) -> Self {
+        self.server_error_rate = Some(threshold);
+        self
+    }
+
+    pub fn with_min_requests(mut self, min_requests: u64) -> Self {
+        self.min_requests = min_requests;
+        self
+    }
+
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = Some(cooldown);
+        self
+    }
+
+    /// `OPENPORTIO_ALERT_ERROR_RATE` / `OPENPORTIO_ALERT_SERVER_ERROR_RATE` enable the rules;
+    /// `OPENPORTIO_ALERT_WINDOW_SECS` and `OPENPORTIO_ALERT_MIN_REQUESTS` tune them.
+    pub(crate) fn from_env() -> Result<Option<Self>, String> {
+        let error_rate = crate::env::setting("ALERT_ERROR_RATE")?;
+        let server_error_rate = crate::env::setting("ALERT_SERVER_ERROR_RATE")?;
+        if error_rate.is_none() && server_error_rate.is_none() {
+            return Ok(None);
+        }
+        let defaults = Self::default();
+        Ok(Some(Self {
+            window: crate::env::setting("ALERT_WINDOW_SECS")?
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.window),
+            min_requests: crate::env::setting("ALERT_MIN_REQUESTS")?
+                .unwrap_or(defaults.min_requests),
+            error_rate,
+            server_error_rate,
+            cooldown: None,
+        }))
+    }
+
+    fn cooldown(&self) -> Duration {
+        self.cooldown.unwrap_or(self.window)
+    }
+}
+
+/// Cloneable handle to the configured [`AlertSink`], resolved like any other dependency.
+#[derive(Clone)]
+pub struct AlertNotifier(Arc<dyn AlertSink>);
+
+impl AlertNotifier {
+    pub fn new(sink: impl AlertSink) -> Self {
+        Self(Arc::new(sink))
+    }
+
+    pub fn send(&self, alert: &Alert) {
+        self.0.send(alert);
+    }
+
+    fn from_extensions(extensions: &http::Extensions) -> Self {
+        resolve_from_extensions(extensions).unwrap_or_default()
+    }
+}
+
+impl Default for AlertNotifier {
+    fn default() -> Self {
+        Self::new(TracingAlertSink)
+    }
+}
+
+/// Logs each alert as a `tracing::error!` event with target `alerts`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TracingAlertSink;
+
+impl AlertSink for TracingAlertSink {
+    fn send(&self, alert: &Alert) {
+        tracing::error!(
+            target: "alerts",
+            kind = alert.kind.as_str(),
+            protocol = alert.protocol,
+            route = %alert.route,
+            window_secs = alert.window_secs,
+            requests = alert.requests,
+            failures = alert.failures,
+            rate = alert.rate,
+            threshold = alert.threshold,
+            "error rate threshold breached"
+        );
+    }
+}
+
+/// POSTs each alert as JSON to `url` (a Slack/Teams relay, Alertmanager adapter, ...) from a
+/// blocking task, so a slow receiver never delays responses. Failed deliveries are logged.
+#[derive(Debug, Clone)]
+pub struct WebhookAlertSink {
+    url: String,
+    client: ureq::Agent,
+}
+
+impl WebhookAlertSink {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            client: ureq::AgentBuilder::new()
+                .timeout_connect(WEBHOOK_TIMEOUT)
+                .timeout_read(WEBHOOK_TIMEOUT)
+                .build(),
+        }
+    }
+
+    fn post(&self, body: String) {
+        let result = self
+            .client
+            .post(&self.url)
+            .set("content-type", "application/json")
+            .send_string(&body);
+        if let Err(err) = result {
+            tracing::warn!(url = %self.url, error = %err, "failed to deliver alert webhook");
+        }
+    }
+}
+
+impl AlertSink for WebhookAlertSink {
+    fn send(&self, alert: &Alert) {
+        let Ok(body) = serde_json::to_string(alert) else {
+            return;
+        };
+        let sink = self.clone();
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn_blocking(move || sink.post(body));
+            }
+            Err(_) => sink.post(body),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    started: Instant,
+    requests: u64,
+    errors: u64,
+    server_errors: u64,
+}
+
+#[derive(Debug, Default)]
+struct RouteWindow {
+    buckets: VecDeque<Bucket>,
+    last_alert: HashMap<AlertKind, Instant>,
+}
+
+impl RouteWindow {
+    fn record(&mut self, now: Instant, window: Duration, outcome: Outcome) {
+        while self
+            .buckets
+            .front()
+            .is_some_and(|bucket| now.saturating_duration_since(bucket.started) >= window)
+        {
+            self.buckets.pop_front();
+        }
+        let slice = window / WINDOW_BUCKETS;
+        let bucket = match self.buckets.back_mut() {
+            Some(bucket) if now.saturating_duration_since(bucket.started) < slice => bucket,
+            _ => {
+                self.buckets.push_back(Bucket {
+                    started: now,
+                    requests: 0,
+                    errors: 0,
+                    server_errors: 0,
+                });
+                self.buckets.back_mut().expect("bucket was just pushed")
+            }
+        };
+        bucket.requests += 1;
+        bucket.errors += u64::from(outcome != Outcome::Ok);
+        bucket.server_errors += u64::from(outcome == Outcome::ServerError);
+    }
+
+    fn totals(&self) -> (u64, u64, u64) {
+        self.buckets.iter().fold((0, 0, 0), |acc, bucket| {
+            (
+                acc.0 + bucket.requests,
+                acc.1 + bucket.errors,
+                acc.2 + bucket.server_errors,
+            )
+        })
+    }
+
+    /// Marks `kind` as alerted unless it already fired within `cooldown`.
+    fn arm(&mut self, kind: AlertKind, now: Instant, cooldown: Duration) -> bool {
+        match self.last_alert.get(&kind) {
+            Some(last) if now.saturating_duration_since(*last) < cooldown => false,
+            _ => {
+                self.last_alert.insert(kind, now);
+                true
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Ok,
+    ClientError,
+    ServerError,
+}
+
+#[derive(Clone)]
+struct ErrorRateMonitor {
+    rules: Arc<AlertRules>,
+    routes: Arc<Mutex<HashMap<(&'static str, String), RouteWindow>>>,
+}
+
+/// Tracks a rolling per-route error rate for REST and gRPC calls and sends an [`Alert`] to the
+/// registered [`AlertNotifier`] when a threshold in `rules` is crossed. Each route and kind
+/// alerts at most once per cooldown. gRPC failures are read from trailers-only responses, so
+/// errors raised mid-stream are not counted.
+pub fn with_error_rate_alerts(router: Router, rules: AlertRules) -> Router {
+    // `route_layer` panics on a router without routes.
+    let router = if router.has_routes() {
+        router.route_layer(from_fn(capture_matched_route))
+    } else {
+        router
+    };
+    router.layer(from_fn_with_state(
+        ErrorRateMonitor {
+            rules: Arc::new(rules),
+            routes: Arc::default(),
+        },
+        error_rate_middleware,
+    ))
+}
+
+async fn error_rate_middleware(
+    State(monitor): State<ErrorRateMonitor>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let is_grpc = is_grpc_request(req.headers());
+    let path = req.uri().path().to_string();
+    let clock = clock_from_extensions(req.extensions());
+    let notifier = AlertNotifier::from_extensions(req.extensions());
+
+    let response = next.run(req).await;
+    let (protocol, route, outcome) = if is_grpc {
+        let outcome = match response
+            .headers()
+            .get("grpc-status")
+            .and_then(|value| value.to_str().ok())
+        {
+            None | Some("0") => Outcome::Ok,
+            Some(code) if GRPC_SERVER_ERROR_CODES.contains(&code) => Outcome::ServerError,
+            Some(_) => Outcome::ClientError,
+        };
+        ("grpc", path, outcome)
+    } else {
+        let route = response
+            .extensions()
+            .get::<MatchedRoute>()
+            .map(|MatchedRoute(route)| route.clone())
+            .unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+        let status = response.status();
+        let outcome = if status.is_server_error() {
+            Outcome::ServerError
+        } else if status.is_client_error() {
+            Outcome::ClientError
+        } else {
+            Outcome::Ok
+        };
+        ("rest", route, outcome)
+    };
+
+    let alerts = monitor.record(protocol, route, outcome, clock.now(), clock.system_time());
+    for alert in &alerts {
+        notifier.send(alert);
+    }
+    response
+}
+
+impl ErrorRateMonitor {
+    fn record(
+        &self,
+        protocol: &'static str,
+        route: String,
+        outcome: Outcome,
+        now: Instant,
+        wall_clock: std::time::SystemTime,
+    ) -> Vec<Alert> {
+        let rules = &self.rules;
+        let Ok(mut routes) = self.routes.lock() else {
+            return Vec::new();
+        };
+        let window = routes.entry((protocol, route.clone())).or_default();
+        window.record(now, rules.window, outcome);
+        let (requests, errors, server_errors) = window.totals();
+        if requests < rules.min_requests.max(1) {
+            return Vec::new();
+        }
+
+        let timestamp_ms = wall_clock
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or_default();
+        [
+            (AlertKind::ErrorRate, rules.error_rate, errors),
+            (
+                AlertKind::ServerErrorRate,
+                rules.server_error_rate,
+                server_errors,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(kind, threshold, failures)| {
+            let threshold = threshold?;
+            let rate = failures as f64 / requests as f64;
+            (rate >= threshold && window.arm(kind, now, rules.cooldown())).then(|| Alert {
+                kind,
+                protocol,
+                route: route.clone(),
+                window_secs: rules.window.as_secs(),
+                requests,
+                failures,
+                rate,
+                threshold,
+                timestamp_ms,
+            })
+        })
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::di::DependencyOverrides;
+    use axum::{body::Body, http::StatusCode, routing::get};
+    use openportio_core::clock::{SharedClock, TestClock};
+    use tower::ServiceExt;
+
+    #[derive(Clone, Default)]
+    struct RecordingSink(Arc<Mutex<Vec<Alert>>>);
+
+    impl AlertSink for RecordingSink {
+        fn send(&self, alert: &Alert) {
+            self.0.lock().unwrap().push(alert.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn breaching_routes_alert_once_per_cooldown_and_windows_roll() {
+        let sink = RecordingSink::default();
+        let clock = Arc::new(TestClock::at(UNIX_EPOCH + Duration::from_secs(100)));
+        let overrides = DependencyOverrides::default()
+            .with(AlertNotifier::new(sink.clone()))
+            .with::<SharedClock>(clock.clone());
+        let app = with_error_rate_alerts(
+            Router::new().route("/ok", get(|| async { "ok" })).route(
+                "/orders/:id",
+                get(|| async { StatusCode::INTERNAL_SERVER_ERROR }),
+            ),
+            AlertRules::new(Duration::from_secs(10))
+                .with_server_error_rate(0.5)
+                .with_min_requests(4),
+        )
+        .layer(axum::Extension(overrides));
+        let call = |uri: &'static str| {
+            app.clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        };
+
+        for id in 0..3 {
+            call(["/orders/1", "/orders/2", "/orders/3"][id])
+                .await
+                .unwrap();
+            call("/ok").await.unwrap();
+        }
+        assert!(sink.0.lock().unwrap().is_empty(), "below min_requests");
+
+        call("/orders/4").await.unwrap();
+        call("/orders/5").await.unwrap();
+        {
+            let alerts = sink.0.lock().unwrap();
+            assert_eq!(alerts.len(), 1, "cooldown suppresses the repeat");
+            assert_eq!(alerts[0].kind, AlertKind::ServerErrorRate);
+            assert_eq!(alerts[0].route, "/orders/:id");
+            assert_eq!(alerts[0].requests, 4);
+            assert_eq!(alerts[0].failures, 4);
+            assert_eq!(alerts[0].timestamp_ms, 100_000);
+        }
+
+        clock.advance(Duration::from_secs(11));
+        for _ in 0..4 {
+            call("/ok").await.unwrap();
+        }
+        call("/orders/6").await.unwrap();
+        assert_eq!(
+            sink.0.lock().unwrap().len(),
+            1,
+            "old failures left the window"
+        );
+    }
+}
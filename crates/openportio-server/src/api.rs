@@ -1,14 +1,24 @@
+use std::{collections::HashMap, fmt, marker::PhantomData, ops::Deref, str::FromStr, sync::Arc};
+
 use axum::{
-    extract::{FromRequest, FromRequestParts, Path, Query},
-    http::{request::Parts, StatusCode},
-    response::IntoResponse,
+    body::Body,
+    extract::{FromRequest, FromRequestParts, Path, Query, RawPathParams, Request, State},
+    http::{header, request::Parts, Extensions, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
     Json,
 };
-use openportio_core::OpenportioError;
+use openportio_core::{
+    query::{ListQuery, QuerySpec},
+    OpenportioError,
+};
+use openportio_rpc::ErrorDetails;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::{json, Value};
 use tonic::Status;
-use validator::{Validate, ValidationErrors};
+use validator::ValidationErrors;
+
+use crate::{body_stream::buffer_within, trace_context::TraceContext};
 
 #[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema, PartialEq, Eq)]
 pub struct ApiValidationIssue {
@@ -63,60 +73,147 @@ impl ApiErrorResponse {
 
 pub type ApiError = (StatusCode, Json<ApiErrorResponse>);
 
-pub trait RequestValidation {
-    fn validate_request(&self, source: &'static str) -> Result<(), ApiError>;
+/// Success body written by [`Enveloped`]. Use `body = Envelope<T>` in `#[utoipa::path]`
+/// responses to document it; errors keep the plain [`ApiErrorResponse`] shape.
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema, PartialEq)]
+pub struct Envelope<T> {
+    pub data: T,
+    pub meta: EnvelopeMeta,
 }
 
-impl<T> RequestValidation for T
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema, PartialEq, Eq)]
+pub struct EnvelopeMeta {
+    /// The `x-request-id` of the request, as set or propagated by the shared middleware.
+    pub request_id: Option<String>,
+    /// Time from receiving the request to building the response.
+    pub duration_ms: Option<u64>,
+}
+
+impl EnvelopeMeta {
+    /// Metadata for the request being served on this task.
+    pub fn current() -> Self {
+        let context = TraceContext::current();
+        Self {
+            request_id: context
+                .as_ref()
+                .and_then(|context| context.request_id().map(str::to_string)),
+            duration_ms: context
+                .and_then(|context| context.elapsed())
+                .map(|elapsed| elapsed.as_millis() as u64),
+        }
+    }
+}
+
+/// Opt-in responder wrapping a JSON payload as `{ "data": ..., "meta": { request_id,
+/// duration_ms } }`. Combine with a status as `(StatusCode::CREATED, Enveloped(value))`.
+#[derive(Debug, Clone)]
+pub struct Enveloped<T>(pub T);
+
+impl<T> IntoResponse for Enveloped<T>
 where
-    T: Validate,
+    T: Serialize,
 {
-    fn validate_request(&self, source: &'static str) -> Result<(), ApiError> {
-        self.validate()
-            .map_err(|err| validation_error_with_source(err, source))
+    fn into_response(self) -> Response {
+        Json(Envelope {
+            data: self.0,
+            meta: EnvelopeMeta::current(),
+        })
+        .into_response()
     }
 }
 
+/// Validation run by the `Validated*` extractors. `#[derive(RequestValidation)]` implements it,
+/// running `validator::Validate` first when the type also uses `#[validate(...)]` rules; `#[dto]`
+/// adds the derive.
+pub trait RequestValidation {
+    fn validate_request(&self, source: &'static str) -> Result<(), ApiError>;
+}
+
+/// Violation returned by a `#[validate_with(...)]` rule of `#[derive(RequestValidation)]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationRuleError {
+    pub issue_type: String,
+    pub msg: String,
+    pub field: Option<String>,
+}
+
+pub type ValidationRuleResult = Result<(), ValidationRuleError>;
+
+impl ValidationRuleError {
+    pub fn new(issue_type: impl Into<String>, msg: impl Into<String>) -> Self {
+        Self {
+            issue_type: issue_type.into(),
+            msg: msg.into(),
+            field: None,
+        }
+    }
+
+    /// Points the issue at `field`; lets struct-level (cross-field) rules blame a field.
+    pub fn at(mut self, field: impl Into<String>) -> Self {
+        self.field = Some(field.into());
+        self
+    }
+
+    /// `loc` is `[source, field]`, falling back to `default_field` and then to `[source]`.
+    pub fn into_issue(self, source: &str, default_field: Option<&str>) -> ApiValidationIssue {
+        let mut loc = vec![source.to_string()];
+        loc.extend(self.field.or_else(|| default_field.map(str::to_string)));
+        ApiValidationIssue {
+            loc,
+            msg: self.msg,
+            issue_type: self.issue_type,
+        }
+    }
+}
+
+/// `400 validation_error` carrying `issues`, with `details` grouping messages by field like
+/// [`validation_error_with_source`].
+pub fn validation_issues_error(issues: Vec<ApiValidationIssue>) -> ApiError {
+    let mut fields = serde_json::Map::new();
+    for issue in &issues {
+        if let Some(field) = issue.loc.get(1) {
+            let messages = fields.entry(field.clone()).or_insert_with(|| json!([]));
+            if let Value::Array(messages) = messages {
+                messages.push(json!(issue.msg));
+            }
+        }
+    }
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ApiErrorResponse::validation(
+            "request validation failed",
+            Some(issues),
+            Some(Value::Object(fields)),
+        )),
+    )
+}
+
 pub fn validation_error(err: ValidationErrors) -> ApiError {
     validation_error_with_source(err, "request")
 }
 
 pub fn validation_error_with_source(err: ValidationErrors, source: &'static str) -> ApiError {
-    let mut fields = serde_json::Map::new();
-    let mut detail = Vec::new();
+    validation_issues_error(validation_issues(&err, source))
+}
+
+/// One issue per `validator` field error, located at `[source, field]`.
+pub fn validation_issues(err: &ValidationErrors, source: &'static str) -> Vec<ApiValidationIssue> {
+    let mut issues = Vec::new();
     for (field, errors) in err.field_errors() {
-        let messages: Vec<String> = errors
-            .iter()
-            .map(|e| {
-                e.message
-                    .clone()
-                    .map(|m| m.to_string())
-                    .unwrap_or_else(|| e.code.to_string())
-            })
-            .collect();
         for error in errors {
             let msg = error
                 .message
                 .clone()
                 .map(|m| m.to_string())
                 .unwrap_or_else(|| error.code.to_string());
-            detail.push(ApiValidationIssue {
+            issues.push(ApiValidationIssue {
                 loc: vec![source.to_string(), field.to_string()],
                 msg,
                 issue_type: error.code.to_string(),
             });
         }
-        fields.insert(field.to_string(), json!(messages));
     }
-
-    (
-        StatusCode::BAD_REQUEST,
-        Json(ApiErrorResponse::validation(
-            "request validation failed",
-            Some(detail),
-            Some(Value::Object(fields)),
-        )),
-    )
+    issues
 }
 
 pub fn bad_request(message: impl Into<String>) -> ApiError {
@@ -126,6 +223,202 @@ pub fn bad_request(message: impl Into<String>) -> ApiError {
     )
 }
 
+const MAX_REJECTION_BODY_BYTES: usize = 16 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectionKind {
+    /// JSON body could not be read or deserialized.
+    Body,
+    Query,
+    Path,
+    Parts,
+    MissingExtension,
+    MethodNotAllowed,
+    /// Other plain-text `4xx` rejection produced by an axum extractor.
+    Other,
+}
+
+/// A request rejected before reaching handler code, passed to the [`RejectionHandler`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestRejection {
+    pub kind: RejectionKind,
+    /// Status axum's rejection would have used.
+    pub status: StatusCode,
+    pub message: String,
+}
+
+impl RequestRejection {
+    pub fn new(kind: RejectionKind, status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            status,
+            message: message.into(),
+        }
+    }
+
+    /// The response used when no [`RejectionHandler`] is registered.
+    pub fn default_response(&self) -> ApiError {
+        let message = &self.message;
+        match self.kind {
+            RejectionKind::Body => bad_request(format!("invalid json body: {message}")),
+            RejectionKind::Query => bad_request(format!("invalid query: {message}")),
+            RejectionKind::Path => bad_request(format!("invalid path: {message}")),
+            RejectionKind::Parts => bad_request(format!("invalid request parts: {message}")),
+            RejectionKind::MissingExtension => {
+                tracing::error!(error = %message, "request extension missing");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiErrorResponse::internal_server_error()),
+                )
+            }
+            RejectionKind::MethodNotAllowed => (
+                StatusCode::METHOD_NOT_ALLOWED,
+                Json(ApiErrorResponse {
+                    code: "method_not_allowed".to_string(),
+                    message: "method not allowed".to_string(),
+                    detail: None,
+                    details: None,
+                }),
+            ),
+            RejectionKind::Other => (self.status, Json(ApiErrorResponse::bad_request(message))),
+        }
+    }
+}
+
+/// Application-wide mapping from extractor rejections to error responses, registered with
+/// `OpenportioServer::with_rejection_handler(...)` and resolved from DI by the `Validated*`
+/// extractors.
+#[derive(Clone)]
+pub struct RejectionHandler(Arc<dyn Fn(&RequestRejection) -> ApiError + Send + Sync>);
+
+impl RejectionHandler {
+    pub fn new<F>(handler: F) -> Self
+    where
+        F: Fn(&RequestRejection) -> ApiError + Send + Sync + 'static,
+    {
+        Self(Arc::new(handler))
+    }
+
+    pub fn handle(&self, rejection: &RequestRejection) -> ApiError {
+        (self.0)(rejection)
+    }
+}
+
+impl fmt::Debug for RejectionHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RejectionHandler").finish_non_exhaustive()
+    }
+}
+
+pub(crate) fn reject(handler: Option<&RejectionHandler>, rejection: RequestRejection) -> ApiError {
+    match handler {
+        Some(handler) => handler.handle(&rejection),
+        None => rejection.default_response(),
+    }
+}
+
+pub(crate) fn rejection_handler(extensions: &Extensions) -> Option<RejectionHandler> {
+    crate::di::resolve_from_extensions(extensions)
+}
+
+/// Response extension marking a rejection produced by the framework rather than handler code.
+/// [`rejection_middleware`] rewrites marked responses; [`method_not_allowed`] sets it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameworkRejection(pub RejectionKind);
+
+/// Method-not-allowed fallback marked as a [`FrameworkRejection`]; install it with
+/// `Router::method_not_allowed_fallback`. axum still adds the `Allow` header.
+pub async fn method_not_allowed() -> Response {
+    let mut response = StatusCode::METHOD_NOT_ALLOWED.into_response();
+    response
+        .extensions_mut()
+        .insert(FrameworkRejection(RejectionKind::MethodNotAllowed));
+    response
+}
+
+/// Body texts of axum's own extractor rejections (bare `Json`, `Query`, `Path`, `Extension`,
+/// body buffering), which are fixed by axum and followed by `: <cause>` where there is one.
+const AXUM_REJECTIONS: &[(&str, RejectionKind)] = &[
+    (
+        "Failed to deserialize the JSON body into the target type",
+        RejectionKind::Body,
+    ),
+    (
+        "Failed to parse the request body as JSON",
+        RejectionKind::Body,
+    ),
+    (
+        "Expected request with `Content-Type: application/json`",
+        RejectionKind::Body,
+    ),
+    ("Failed to buffer the request body", RejectionKind::Body),
+    (
+        "Request body didn't contain valid UTF-8",
+        RejectionKind::Body,
+    ),
+    ("Failed to deserialize query string", RejectionKind::Query),
+    ("Invalid URL", RejectionKind::Path),
+    ("Missing request extension", RejectionKind::MissingExtension),
+];
+
+fn axum_rejection_kind(message: &str) -> Option<RejectionKind> {
+    AXUM_REJECTIONS
+        .iter()
+        .find(|(text, _)| message.starts_with(text))
+        .map(|(_, kind)| *kind)
+}
+
+/// Routes rejections produced outside handler code through `handler`: responses marked with
+/// [`FrameworkRejection`] and axum's own plain-text extractor rejections. Everything else,
+/// including plain-text errors returned by handlers, passes through untouched.
+pub async fn rejection_middleware(
+    State(handler): State<RejectionHandler>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let response = next.run(req).await;
+    let status = response.status();
+    if let Some(FrameworkRejection(kind)) = response.extensions().get::<FrameworkRejection>() {
+        let rejection = RequestRejection::new(*kind, status, status.as_str());
+        return handler.handle(&rejection).into_response();
+    }
+    if !matches!(
+        status,
+        StatusCode::BAD_REQUEST
+            | StatusCode::UNSUPPORTED_MEDIA_TYPE
+            | StatusCode::UNPROCESSABLE_ENTITY
+            | StatusCode::INTERNAL_SERVER_ERROR
+    ) {
+        return response;
+    }
+    let is_plain_text = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/plain"));
+    if !is_plain_text {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let bytes = match buffer_within(body, MAX_REJECTION_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(body) => return Response::from_parts(parts, body),
+    };
+    let message = String::from_utf8_lossy(&bytes).into_owned();
+    match axum_rejection_kind(&message) {
+        Some(kind)
+            if status != StatusCode::INTERNAL_SERVER_ERROR
+                || kind == RejectionKind::MissingExtension =>
+        {
+            handler
+                .handle(&RequestRejection::new(kind, status, message))
+                .into_response()
+        }
+        _ => Response::from_parts(parts, Body::from(bytes)),
+    }
+}
+
 pub fn map_domain_error_to_rest(err: OpenportioError) -> ApiError {
     match err {
         OpenportioError::Validation(message) => {
@@ -150,19 +443,107 @@ pub fn map_domain_error_to_rest(err: OpenportioError) -> ApiError {
                 Json(ApiErrorResponse::internal_server_error()),
             )
         }
+        OpenportioError::Unavailable(message) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ApiErrorResponse {
+                code: "service_unavailable".to_string(),
+                message,
+                detail: None,
+                details: None,
+            }),
+        ),
+        OpenportioError::Throttled {
+            message,
+            retry_after,
+        } => (
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(ApiErrorResponse {
+                code: "rate_limited".to_string(),
+                message,
+                detail: None,
+                details: retry_after.map(|retry_after| {
+                    serde_json::json!({ "retry_after_seconds": retry_after.as_secs().max(1) })
+                }),
+            }),
+        ),
+        // Nobody reads this response; 499 keeps it out of the 5xx error rate in access logs.
+        OpenportioError::Cancelled => (
+            CLIENT_CLOSED_REQUEST,
+            Json(ApiErrorResponse {
+                code: "client_closed_request".to_string(),
+                message: "request was cancelled".to_string(),
+                detail: None,
+                details: None,
+            }),
+        ),
     }
 }
 
+/// Non-standard status (nginx) for requests abandoned by the client.
+pub const CLIENT_CLOSED_REQUEST: StatusCode = match StatusCode::from_u16(499) {
+    Ok(status) => status,
+    Err(_) => panic!("499 is a valid status code"),
+};
+
 pub fn map_domain_error_to_grpc(err: OpenportioError) -> Status {
     match err {
-        OpenportioError::Validation(message) => Status::invalid_argument(message),
         OpenportioError::Internal(message) => {
             tracing::error!(error = %message, "internal domain error surfaced in gRPC handler");
-            Status::internal("internal server error")
+            api_error_to_grpc((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiErrorResponse::internal_server_error()),
+            ))
         }
+        other => api_error_to_grpc(map_domain_error_to_rest(other)),
     }
 }
 
+/// `ErrorInfo.domain` of the details attached by [`api_error_to_grpc`].
+pub const GRPC_ERROR_DOMAIN: &str = "openportio";
+
+/// Converts a REST-shaped error into a gRPC status with `google.rpc` rich error details: each
+/// `ApiValidationIssue` becomes a `BadRequest` field violation (`loc` after the source, dotted),
+/// and `ErrorInfo` carries the upper-cased `code` as its reason plus the scalar `details` values
+/// as metadata. Decode them with `openportio_rpc::ErrorDetails::from_status`.
+pub fn api_error_to_grpc((status, Json(body)): ApiError) -> Status {
+    let code = match status {
+        StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => tonic::Code::InvalidArgument,
+        StatusCode::UNAUTHORIZED => tonic::Code::Unauthenticated,
+        StatusCode::FORBIDDEN => tonic::Code::PermissionDenied,
+        StatusCode::NOT_FOUND => tonic::Code::NotFound,
+        StatusCode::CONFLICT => tonic::Code::AlreadyExists,
+        StatusCode::TOO_MANY_REQUESTS => tonic::Code::ResourceExhausted,
+        StatusCode::SERVICE_UNAVAILABLE => tonic::Code::Unavailable,
+        StatusCode::GATEWAY_TIMEOUT | StatusCode::REQUEST_TIMEOUT => tonic::Code::DeadlineExceeded,
+        CLIENT_CLOSED_REQUEST => tonic::Code::Cancelled,
+        _ => tonic::Code::Internal,
+    };
+    let mut details = ErrorDetails::new();
+    for issue in body.detail.iter().flatten() {
+        let field = issue.loc.get(1..).unwrap_or_default().join(".");
+        if !field.is_empty() {
+            details = details.with_field_violation(field, issue.msg.clone());
+        }
+    }
+    let metadata = match &body.details {
+        Some(Value::Object(fields)) => fields
+            .iter()
+            .filter_map(|(key, value)| {
+                let value = match value {
+                    Value::String(value) => value.clone(),
+                    Value::Number(_) | Value::Bool(_) => value.to_string(),
+                    _ => return None,
+                };
+                Some((key.clone(), value))
+            })
+            .collect(),
+        _ => HashMap::new(),
+    };
+    details
+        .with_error_info(body.code.to_uppercase(), GRPC_ERROR_DOMAIN, metadata)
+        .into_status(code, body.message)
+}
+
 #[derive(Debug, Clone)]
 pub struct ValidatedJson<T>(pub T);
 
@@ -175,15 +556,128 @@ where
     type Rejection = ApiError;
 
     async fn from_request(req: axum::extract::Request, state: &S) -> Result<Self, Self::Rejection> {
-        let Json(value) = Json::<T>::from_request(req, state)
-            .await
-            .map_err(|err| bad_request(format!("invalid json body: {err}")))?;
+        let handler = rejection_handler(req.extensions());
+        let Json(value) = Json::<T>::from_request(req, state).await.map_err(|err| {
+            reject(
+                handler.as_ref(),
+                RequestRejection::new(RejectionKind::Body, err.status(), err.body_text()),
+            )
+        })?;
 
         value.validate_request("body")?;
         Ok(Self(value))
     }
 }
 
+/// Validation that needs I/O (uniqueness checks, remote verification), run by
+/// [`ValidatedJsonAsync`] after the synchronous [`RequestValidation`] rules. Return
+/// [`validation_issues_error`] for issues so they merge with the synchronous ones; any other
+/// error (for example a `503` when the remote check is down) is returned unchanged.
+#[axum::async_trait]
+pub trait AsyncRequestValidation: RequestValidation + Send + Sync {
+    async fn validate_request_async(
+        &self,
+        source: &'static str,
+        deps: &ValidationDeps,
+    ) -> Result<(), ApiError>;
+}
+
+/// Dependencies visible to [`AsyncRequestValidation`]: the request's overrides,
+/// `DependencyOverride<T>` extensions and service registry, resolved like `Depends<T>` without
+/// the state fallback.
+#[derive(Debug, Clone, Default)]
+pub struct ValidationDeps {
+    extensions: Extensions,
+}
+
+impl ValidationDeps {
+    pub fn from_extensions(extensions: &Extensions) -> Self {
+        Self {
+            extensions: extensions.clone(),
+        }
+    }
+
+    pub fn get<T>(&self) -> Option<T>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        crate::di::resolve_from_extensions(&self.extensions)
+    }
+
+    /// Like [`Self::get`], failing with a `500` when `T` is not registered.
+    pub fn require<T>(&self) -> Result<T, ApiError>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.get().ok_or_else(|| {
+            tracing::error!(
+                dependency = std::any::type_name::<T>(),
+                "async validator dependency is not registered"
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiErrorResponse::internal_server_error()),
+            )
+        })
+    }
+}
+
+/// Runs both validation stages over `value` and reports their issues as one
+/// `400 validation_error`.
+pub async fn validate_request_with_deps<T>(
+    value: &T,
+    source: &'static str,
+    deps: &ValidationDeps,
+) -> Result<(), ApiError>
+where
+    T: AsyncRequestValidation,
+{
+    let sync = value.validate_request(source);
+    let async_result = value.validate_request_async(source, deps).await;
+    let mut issues = Vec::new();
+    for result in [sync, async_result] {
+        match result {
+            Ok(()) => {}
+            Err((StatusCode::BAD_REQUEST, Json(body))) if body.code == "validation_error" => {
+                issues.extend(body.detail.unwrap_or_default());
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(validation_issues_error(issues))
+    }
+}
+
+/// [`ValidatedJson`] for bodies implementing [`AsyncRequestValidation`].
+#[derive(Debug, Clone)]
+pub struct ValidatedJsonAsync<T>(pub T);
+
+#[axum::async_trait]
+impl<T, S> FromRequest<S> for ValidatedJsonAsync<T>
+where
+    T: DeserializeOwned + AsyncRequestValidation,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: axum::extract::Request, state: &S) -> Result<Self, Self::Rejection> {
+        let handler = rejection_handler(req.extensions());
+        let deps = ValidationDeps::from_extensions(req.extensions());
+        let Json(value) = Json::<T>::from_request(req, state).await.map_err(|err| {
+            reject(
+                handler.as_ref(),
+                RequestRejection::new(RejectionKind::Body, err.status(), err.body_text()),
+            )
+        })?;
+
+        validate_request_with_deps(&value, "body", &deps).await?;
+        Ok(Self(value))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ValidatedQuery<T>(pub T);
 
@@ -198,12 +692,76 @@ where
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
         let Query(value) = Query::<T>::from_request_parts(parts, state)
             .await
-            .map_err(|err| bad_request(format!("invalid query: {err}")))?;
+            .map_err(|err| {
+                reject(
+                    rejection_handler(&parts.extensions).as_ref(),
+                    RequestRejection::new(RejectionKind::Query, err.status(), err.body_text()),
+                )
+            })?;
         value.validate_request("query")?;
         Ok(Self(value))
     }
 }
 
+/// A listable resource: the fields [`ListParams`] lets clients sort and filter by.
+pub trait ListResource {
+    const QUERY: QuerySpec;
+}
+
+/// `page`, `per_page`, `sort` and filter parameters parsed against `T::QUERY` (see
+/// [`QuerySpec::parse`]). Unknown fields, operators and oversized pages are answered with
+/// `400 validation_error` located at the offending parameter.
+#[derive(Debug, Clone)]
+pub struct ListParams<T> {
+    query: ListQuery,
+    _resource: PhantomData<fn() -> T>,
+}
+
+impl<T> ListParams<T> {
+    pub fn into_inner(self) -> ListQuery {
+        self.query
+    }
+}
+
+impl<T> Deref for ListParams<T> {
+    type Target = ListQuery;
+
+    fn deref(&self) -> &Self::Target {
+        &self.query
+    }
+}
+
+#[axum::async_trait]
+impl<T, S> FromRequestParts<S> for ListParams<T>
+where
+    T: ListResource,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(pairs) = Query::<Vec<(String, String)>>::from_request_parts(parts, state)
+            .await
+            .map_err(|err| {
+                reject(
+                    rejection_handler(&parts.extensions).as_ref(),
+                    RequestRejection::new(RejectionKind::Query, err.status(), err.body_text()),
+                )
+            })?;
+        let query = T::QUERY.parse(pairs).map_err(|err| {
+            validation_issues_error(vec![ApiValidationIssue {
+                loc: vec!["query".to_string(), err.param().to_string()],
+                msg: err.to_string(),
+                issue_type: "invalid_query".to_string(),
+            }])
+        })?;
+        Ok(Self {
+            query,
+            _resource: PhantomData,
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ValidatedPath<T>(pub T);
 
@@ -218,12 +776,251 @@ where
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
         let Path(value) = Path::<T>::from_request_parts(parts, state)
             .await
-            .map_err(|err| bad_request(format!("invalid path: {err}")))?;
+            .map_err(|err| {
+                reject(
+                    rejection_handler(&parts.extensions).as_ref(),
+                    RequestRejection::new(RejectionKind::Path, err.status(), err.body_text()),
+                )
+            })?;
         value.validate_request("path")?;
         Ok(Self(value))
     }
 }
 
+/// Path parameters parsed field by field with `FromStr` (see [`FromPathParams`]). A value that
+/// fails to parse is answered with `400 validation_error` located at `["path", field]` rather
+/// than axum's plain-text rejection:
+///
+/// ```ignore
+/// #[derive(TypedPath)]
+/// struct NotePath {
+///     owner: String,
+///     id: u64,
+/// }
+///
+/// async fn get_note(TypedPath(path): TypedPath<NotePath>) { /* ... */ }
+/// async fn get_user(TypedPath(id): TypedPath<u64>) { /* `/users/:id` */ }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TypedPath<T>(pub T);
+
+#[axum::async_trait]
+impl<T, S> FromRequestParts<S> for TypedPath<T>
+where
+    T: FromPathParams + Send,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let raw = RawPathParams::from_request_parts(parts, state)
+            .await
+            .map_err(|err| {
+                reject(
+                    rejection_handler(&parts.extensions).as_ref(),
+                    RequestRejection::new(RejectionKind::Path, err.status(), err.body_text()),
+                )
+            })?;
+        let params = PathParams(
+            raw.iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        );
+        T::from_path_params(&params)
+            .map(Self)
+            .map_err(validation_issues_error)
+    }
+}
+
+/// Percent-decoded path parameters of the matched route, in template order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PathParams(Vec<(String, String)>);
+
+impl PathParams {
+    pub fn new(params: impl IntoIterator<Item = (String, String)>) -> Self {
+        Self(params.into_iter().collect())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Parses the required parameter `name`, recording a `missing` or `invalid_path` issue
+    /// when it is absent or does not parse.
+    pub fn parse<T>(&self, name: &str, issues: &mut Vec<ApiValidationIssue>) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        let parsed = self.parse_optional(name, issues);
+        if parsed.is_none() && self.get(name).is_none() {
+            issues.push(path_issue(name, "missing", "path parameter is missing"));
+        }
+        parsed
+    }
+
+    /// Like [`PathParams::parse`], but an absent parameter is `None` without an issue.
+    pub fn parse_optional<T>(&self, name: &str, issues: &mut Vec<ApiValidationIssue>) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        let raw = self.get(name)?;
+        match raw.parse() {
+            Ok(value) => Some(value),
+            Err(err) => {
+                issues.push(path_issue(
+                    name,
+                    "invalid_path",
+                    format!("invalid value `{raw}`: {err}"),
+                ));
+                None
+            }
+        }
+    }
+}
+
+fn path_issue(name: &str, issue_type: &str, msg: impl Into<String>) -> ApiValidationIssue {
+    ApiValidationIssue {
+        loc: vec!["path".to_string(), name.to_string()],
+        msg: msg.into(),
+        issue_type: issue_type.to_string(),
+    }
+}
+
+/// Builds a value from [`PathParams`] for [`TypedPath`]; derive it with
+/// `#[derive(TypedPath)]` on a struct whose fields are `FromStr` (or `Option` of one). Scalars
+/// implement it by parsing the route's single parameter. Return every issue, not just the first.
+pub trait FromPathParams: Sized {
+    fn from_path_params(params: &PathParams) -> Result<Self, Vec<ApiValidationIssue>>;
+}
+
+macro_rules! scalar_path_params {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl FromPathParams for $ty {
+                fn from_path_params(params: &PathParams) -> Result<Self, Vec<ApiValidationIssue>> {
+                    let mut issues = Vec::new();
+                    let Some((name, _)) = params.iter().next() else {
+                        return Err(vec![ApiValidationIssue {
+                            loc: vec!["path".to_string()],
+                            msg: "route has no path parameter".to_string(),
+                            issue_type: "missing".to_string(),
+                        }]);
+                    };
+                    params.parse(name, &mut issues).ok_or(issues)
+                }
+            }
+        )*
+    };
+}
+
+scalar_path_params!(
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64,
+    bool,
+    char,
+    String,
+    uuid::Uuid,
+);
+
+/// Field names a `#[dto]` struct accepts as path parameters, used by `#[route(..., auto_validate)]`
+/// to check `Path<T>` against the route template at compile time.
+#[doc(hidden)]
+pub trait PathParamFields {
+    const FIELDS: &'static [&'static str];
+    /// Fields without `Option`, `#[serde(default)]`, or `#[serde(skip)]`.
+    const REQUIRED: &'static [&'static str];
+}
+
+/// Resolves to the `PathParamFields` of `T` when implemented and to `None` through
+/// [`PathParamFieldsFallback`] otherwise, so non-`#[dto]` path types skip the check.
+#[doc(hidden)]
+pub struct PathParamCheck<T>(std::marker::PhantomData<T>);
+
+impl<T: PathParamFields> PathParamCheck<T> {
+    pub const FIELDS: Option<&'static [&'static str]> = Some(T::FIELDS);
+    pub const REQUIRED: Option<&'static [&'static str]> = Some(T::REQUIRED);
+}
+
+#[doc(hidden)]
+pub trait PathParamFieldsFallback {
+    const FIELDS: Option<&'static [&'static str]> = None;
+    const REQUIRED: Option<&'static [&'static str]> = None;
+}
+
+impl<T> PathParamFieldsFallback for PathParamCheck<T> {}
+
+#[doc(hidden)]
+pub const fn path_params_contain(fields: Option<&[&str]>, name: &str) -> bool {
+    match fields {
+        Some(fields) => str_slice_contains(fields, name),
+        None => true,
+    }
+}
+
+#[doc(hidden)]
+pub const fn path_params_cover(required: Option<&[&str]>, placeholders: &[&str]) -> bool {
+    let Some(required) = required else {
+        return true;
+    };
+    let mut idx = 0;
+    while idx < required.len() {
+        if !str_slice_contains(placeholders, required[idx]) {
+            return false;
+        }
+        idx += 1;
+    }
+    true
+}
+
+const fn str_slice_contains(haystack: &[&str], needle: &str) -> bool {
+    let mut idx = 0;
+    while idx < haystack.len() {
+        if const_str_eq(haystack[idx], needle) {
+            return true;
+        }
+        idx += 1;
+    }
+    false
+}
+
+const fn const_str_eq(left: &str, right: &str) -> bool {
+    let (left, right) = (left.as_bytes(), right.as_bytes());
+    if left.len() != right.len() {
+        return false;
+    }
+    let mut idx = 0;
+    while idx < left.len() {
+        if left[idx] != right[idx] {
+            return false;
+        }
+        idx += 1;
+    }
+    true
+}
+
 #[derive(Debug, Clone)]
 pub struct ValidatedParts<T>(pub T);
 
@@ -237,9 +1034,16 @@ where
     type Rejection = ApiError;
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        let value = T::from_request_parts(parts, state)
-            .await
-            .map_err(|err| bad_request(format!("invalid request parts: {err}")))?;
+        let value = T::from_request_parts(parts, state).await.map_err(|err| {
+            reject(
+                rejection_handler(&parts.extensions).as_ref(),
+                RequestRejection::new(
+                    RejectionKind::Parts,
+                    StatusCode::BAD_REQUEST,
+                    err.to_string(),
+                ),
+            )
+        })?;
         value.validate_request("parts")?;
         Ok(Self(value))
     }
@@ -263,12 +1067,118 @@ mod tests {
     use tower::util::ServiceExt;
     use validator::Validate;
 
-    #[derive(Debug, serde::Deserialize, Validate)]
+    #[derive(Debug, serde::Deserialize, Validate, crate::RequestValidation)]
     struct BodyDto {
         #[validate(length(min = 3))]
         name: String,
     }
 
+    #[derive(Debug, Serialize, utoipa::ToSchema)]
+    struct EnvelopedItem {
+        id: u32,
+    }
+
+    #[utoipa::path(
+        get,
+        path = "/items/{id}",
+        responses(
+            (status = 200, body = Envelope<EnvelopedItem>),
+            (status = 404, body = ApiErrorResponse)
+        )
+    )]
+    #[allow(dead_code)]
+    async fn get_enveloped_item(
+        axum::extract::Path(id): axum::extract::Path<u32>,
+    ) -> Result<Enveloped<EnvelopedItem>, ApiError> {
+        if id == 0 {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(ApiErrorResponse::bad_request("missing")),
+            ));
+        }
+        Ok(Enveloped(EnvelopedItem { id }))
+    }
+
+    #[tokio::test]
+    async fn enveloped_wraps_success_and_leaves_errors_alone() {
+        let app = crate::middleware::apply_shared_middleware(
+            Router::new().route("/items/:id", axum::routing::get(get_enveloped_item)),
+            &crate::middleware::MiddlewareConfig::default(),
+        );
+        let get = |uri: &str| {
+            Request::builder()
+                .uri(uri)
+                .header("x-request-id", "req-7")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(get("/items/3")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value =
+            serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap())
+                .unwrap();
+        assert_eq!(body["data"]["id"], 3);
+        assert_eq!(body["meta"]["request_id"], "req-7");
+        assert!(body["meta"]["duration_ms"].is_u64());
+
+        let response = app.oneshot(get("/items/0")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body: Value =
+            serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap())
+                .unwrap();
+        assert_eq!(body["code"], "bad_request");
+        assert!(body.get("data").is_none());
+
+        #[derive(utoipa::OpenApi)]
+        #[openapi(paths(get_enveloped_item))]
+        struct EnvelopeDoc;
+        let doc = serde_json::to_value(<EnvelopeDoc as utoipa::OpenApi>::openapi()).unwrap();
+        let envelope = &doc["components"]["schemas"]["Envelope_EnvelopedItem"]["properties"];
+        assert!(envelope["data"].is_object());
+        assert!(envelope["meta"].is_object());
+    }
+
+    struct Note;
+
+    impl ListResource for Note {
+        const QUERY: QuerySpec = QuerySpec::new(&["title"], &["status"]).with_page_size(10, 50);
+    }
+
+    async fn list_notes(params: ListParams<Note>) -> String {
+        let sql = params.to_sql(openportio_core::query::Placeholders::Dollar);
+        format!("{sql} {:?}", sql.params)
+    }
+
+    #[tokio::test]
+    async fn list_params_parse_against_the_resource_spec() {
+        let app = Router::new().route("/notes", axum::routing::get(list_notes));
+        let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+        let response = app
+            .clone()
+            .oneshot(get("/notes?status%5Bin%5D=open,draft&sort=-title&page=2"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(
+            body,
+            r#"WHERE status IN ($1, $2) ORDER BY title DESC LIMIT 10 OFFSET 10 ["open", "draft"]"#
+        );
+
+        let response = app.oneshot(get("/notes?sort=secret")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: ApiErrorResponse =
+            serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap())
+                .unwrap();
+        assert_eq!(body.code, "validation_error");
+        let issue = &body.detail.unwrap()[0];
+        assert_eq!(issue.loc, ["query", "sort"]);
+        assert_eq!(issue.msg, "cannot sort by `secret`");
+        assert_eq!(issue.issue_type, "invalid_query");
+    }
+
     #[test]
     fn validation_error_includes_fastapi_like_detail_shape() {
         let dto = BodyDto {
@@ -285,6 +1195,29 @@ mod tests {
             .any(|issue| issue.loc == vec!["body".to_string(), "name".to_string()]));
     }
 
+    #[test]
+    fn grpc_statuses_carry_error_info_with_rest_details_as_metadata() {
+        let status = map_domain_error_to_grpc(OpenportioError::Throttled {
+            message: "slow down".to_string(),
+            retry_after: Some(std::time::Duration::from_secs(4)),
+        });
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+        let details = ErrorDetails::from_status(&status).unwrap();
+        assert_eq!(details.bad_request, None);
+        let info = details.error_info.unwrap();
+        assert_eq!(info.reason, "RATE_LIMITED");
+        assert_eq!(info.metadata["retry_after_seconds"], "4");
+
+        let status = map_domain_error_to_grpc(OpenportioError::Internal("db exploded".into()));
+        assert_eq!(status.message(), "internal server error");
+        let info = ErrorDetails::from_status(&status)
+            .unwrap()
+            .error_info
+            .unwrap();
+        assert_eq!(info.reason, "INTERNAL_ERROR");
+        assert!(info.metadata.is_empty());
+    }
+
     #[test]
     fn internal_domain_errors_are_sanitized_for_rest_clients() {
         let (status, Json(body)) =
@@ -302,7 +1235,42 @@ mod tests {
         assert_eq!(status.message(), "internal server error");
     }
 
-    #[derive(Debug, serde::Deserialize, Validate)]
+    #[test]
+    fn transient_domain_errors_map_to_retryable_statuses() {
+        let throttled = || OpenportioError::Throttled {
+            message: "quota exceeded".to_string(),
+            retry_after: Some(std::time::Duration::from_secs(30)),
+        };
+        let (status, Json(body)) = map_domain_error_to_rest(throttled());
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(body.details.unwrap()["retry_after_seconds"], 30);
+        assert_eq!(
+            map_domain_error_to_grpc(throttled()).code(),
+            tonic::Code::ResourceExhausted
+        );
+
+        let unavailable = || OpenportioError::Unavailable("replica lagging".to_string());
+        let (status, Json(body)) = map_domain_error_to_rest(unavailable());
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body.code, "service_unavailable");
+        assert_eq!(
+            map_domain_error_to_grpc(unavailable()).code(),
+            tonic::Code::Unavailable
+        );
+    }
+
+    #[test]
+    fn cancelled_work_maps_to_client_closed_request() {
+        let (status, Json(body)) = map_domain_error_to_rest(OpenportioError::Cancelled);
+        assert_eq!(status.as_u16(), 499);
+        assert_eq!(body.code, "client_closed_request");
+        assert_eq!(
+            map_domain_error_to_grpc(OpenportioError::Cancelled).code(),
+            tonic::Code::Cancelled
+        );
+    }
+
+    #[derive(Debug, serde::Deserialize, Validate, crate::RequestValidation)]
     struct DerivedValidationDto {
         #[validate(length(min = 3))]
         name: String,
@@ -401,4 +1369,89 @@ mod tests {
         let parsed: ApiErrorResponse = serde_json::from_slice(&body).expect("api error json");
         assert_eq!(parsed.code, "validation_error");
     }
+
+    #[derive(Clone)]
+    struct TakenNames(Arc<Vec<&'static str>>);
+
+    #[derive(Debug, serde::Deserialize, Validate, crate::RequestValidation)]
+    struct SignupDto {
+        #[validate(length(min = 3))]
+        name: String,
+        #[validate(email)]
+        email: String,
+    }
+
+    #[axum::async_trait]
+    impl AsyncRequestValidation for SignupDto {
+        async fn validate_request_async(
+            &self,
+            source: &'static str,
+            deps: &ValidationDeps,
+        ) -> Result<(), ApiError> {
+            let TakenNames(taken) = deps.require::<TakenNames>()?;
+            if taken.contains(&self.email.as_str()) {
+                return Err(validation_issues_error(vec![ValidationRuleError::new(
+                    "unique",
+                    "email is already registered",
+                )
+                .at("email")
+                .into_issue(source, None)]));
+            }
+            Ok(())
+        }
+    }
+
+    async fn signup_handler(ValidatedJsonAsync(dto): ValidatedJsonAsync<SignupDto>) -> String {
+        dto.name
+    }
+
+    #[tokio::test]
+    async fn validated_json_async_merges_sync_and_async_issues() {
+        let app = crate::di::with_dependency(
+            Router::new().route("/signup", post(signup_handler)),
+            TakenNames(Arc::new(vec!["taken@example.com"])),
+        );
+        let post_json = |body: &'static str| {
+            Request::builder()
+                .method("POST")
+                .uri("/signup")
+                .header("content-type", "application/json")
+                .body(Body::from(body))
+                .expect("request should build")
+        };
+
+        let response = app
+            .clone()
+            .oneshot(post_json(r#"{"name":"ferris","email":"new@example.com"}"#))
+            .await
+            .expect("request should complete");
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .oneshot(post_json(r#"{"name":"fe","email":"taken@example.com"}"#))
+            .await
+            .expect("request should complete");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("body bytes");
+        let parsed: ApiErrorResponse = serde_json::from_slice(&body).expect("api error json");
+        let locs: Vec<_> = parsed
+            .detail
+            .unwrap()
+            .into_iter()
+            .map(|issue| (issue.loc.join("."), issue.issue_type))
+            .collect();
+        assert_eq!(
+            locs,
+            vec![
+                ("body.name".to_string(), "length".to_string()),
+                ("body.email".to_string(), "unique".to_string()),
+            ]
+        );
+        assert_eq!(
+            parsed.details.unwrap()["email"],
+            json!(["email is already registered"])
+        );
+    }
 }
@@ -0,0 +1,202 @@
+use std::borrow::Cow;
+
+use utoipa::{
+    openapi::{
+        security::{HttpAuthScheme, HttpBuilder, SecurityRequirement, SecurityScheme},
+        OpenApi, PathItem, RefOr, Schema,
+    },
+    ToSchema,
+};
+
+use crate::versioning::operations_mut;
+
+/// Security scheme name used by `#[route(.., protected)]`, `NestedRouter::with_bearer_auth`
+/// and the built-in `/protected/*` routes.
+pub const BEARER_AUTH_SCHEME: &str = "bearerAuth";
+/// Operation extension set by `#[route(.., hidden)]` and `NestedRouter::with_hidden_path`;
+/// [`public_openapi`] drops operations carrying it.
+pub const HIDDEN_EXTENSION: &str = "x-openportio-hidden";
+
+/// The document as served at `/openapi.json`: hidden operations removed, and the
+/// `bearerAuth` scheme (HTTP bearer, JWT) registered when any operation requires it, so the
+/// Swagger UI "Authorize" button works.
+pub fn public_openapi(mut doc: OpenApi) -> OpenApi {
+    doc.paths.paths.retain(|_, item| {
+        remove_hidden_operations(item);
+        operations_mut(item).next().is_some()
+    });
+    let uses_bearer_auth = doc
+        .paths
+        .paths
+        .values_mut()
+        .flat_map(operations_mut)
+        .filter_map(|operation| operation.security.as_ref())
+        .chain(doc.security.as_ref())
+        .flatten()
+        .any(requires_bearer_auth);
+    if uses_bearer_auth {
+        register_bearer_auth_scheme(&mut doc);
+    }
+    doc
+}
+
+/// Requires `bearerAuth` on every operation that declares no security of its own.
+pub fn require_bearer_auth(mut doc: OpenApi) -> OpenApi {
+    for operation in doc.paths.paths.values_mut().flat_map(operations_mut) {
+        operation
+            .security
+            .get_or_insert_with(|| vec![bearer_auth_requirement(Vec::<String>::new())]);
+    }
+    register_bearer_auth_scheme(&mut doc);
+    doc
+}
+
+/// Marks every operation under `path` (OpenAPI form, `/notes/{id}`) as hidden.
+pub fn hide_path(mut doc: OpenApi, path: &str) -> OpenApi {
+    if let Some(item) = doc.paths.paths.get_mut(path) {
+        for operation in operations_mut(item) {
+            operation
+                .extensions
+                .get_or_insert_with(Default::default)
+                .insert(HIDDEN_EXTENSION.to_string(), serde_json::Value::Bool(true));
+        }
+    }
+    doc
+}
+
+pub fn bearer_auth_requirement<I, S>(scopes: I) -> SecurityRequirement
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    SecurityRequirement::new(BEARER_AUTH_SCHEME, scopes)
+}
+
+pub(crate) fn register_bearer_auth_scheme(doc: &mut OpenApi) {
+    let components = doc.components.get_or_insert_with(Default::default);
+    if !components.security_schemes.contains_key(BEARER_AUTH_SCHEME) {
+        components.add_security_scheme(
+            BEARER_AUTH_SCHEME,
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        );
+    }
+}
+
+/// Submitted to `inventory` by `#[dto]` for every non-generic type.
+#[doc(hidden)]
+pub struct DtoSchema {
+    name: fn() -> Cow<'static, str>,
+    schema: fn() -> RefOr<Schema>,
+    schemas: fn(&mut Vec<(String, RefOr<Schema>)>),
+}
+
+impl DtoSchema {
+    pub const fn of<T: ToSchema>() -> Self {
+        Self {
+            name: T::name,
+            schema: T::schema,
+            schemas: T::schemas,
+        }
+    }
+}
+
+inventory::collect!(DtoSchema);
+
+/// Schemas of every non-generic `#[dto]` type linked into the binary, and of the types they
+/// reference, sorted by name.
+pub fn registered_dto_schemas() -> Vec<(String, RefOr<Schema>)> {
+    let mut schemas = Vec::new();
+    for dto in inventory::iter::<DtoSchema> {
+        schemas.push(((dto.name)().into_owned(), (dto.schema)()));
+        (dto.schemas)(&mut schemas);
+    }
+    schemas.sort_by(|a, b| a.0.cmp(&b.0));
+    schemas.dedup_by(|a, b| a.0 == b.0);
+    schemas
+}
+
+/// Adds the [`registered_dto_schemas`] missing from `doc`'s components, so `#[dto]` types need
+/// no `components(schemas(...))` entry; schemas the document already declares win. The
+/// built-in `/openapi.json` and every nested router's document get it when served.
+pub fn with_dto_schemas(mut doc: OpenApi) -> OpenApi {
+    let registered = registered_dto_schemas();
+    if registered.is_empty() {
+        return doc;
+    }
+    let components = doc.components.get_or_insert_with(Default::default);
+    for (name, schema) in registered {
+        components.schemas.entry(name).or_insert(schema);
+    }
+    doc
+}
+
+fn remove_hidden_operations(item: &mut PathItem) {
+    for operation in [
+        &mut item.get,
+        &mut item.put,
+        &mut item.post,
+        &mut item.delete,
+        &mut item.options,
+        &mut item.head,
+        &mut item.patch,
+        &mut item.trace,
+    ] {
+        let hidden = operation
+            .as_ref()
+            .and_then(|operation| operation.extensions.as_ref())
+            .and_then(|extensions| extensions.get(HIDDEN_EXTENSION))
+            .is_some_and(|value| value == &serde_json::Value::Bool(true));
+        if hidden {
+            *operation = None;
+        }
+    }
+}
+
+fn requires_bearer_auth(requirement: &SecurityRequirement) -> bool {
+    serde_json::to_value(requirement)
+        .ok()
+        .and_then(|value| value.get(BEARER_AUTH_SCHEME).cloned())
+        .is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utoipa::openapi::{path::OperationBuilder, HttpMethod, PathItem, PathsBuilder};
+
+    fn doc() -> OpenApi {
+        let paths = PathsBuilder::new()
+            .path(
+                "/notes",
+                PathItem::new(HttpMethod::Get, OperationBuilder::new().build()),
+            )
+            .path(
+                "/notes/{id}",
+                PathItem::new(HttpMethod::Delete, OperationBuilder::new().build()),
+            )
+            .build();
+        OpenApi::new(utoipa::openapi::Info::new("notes", "1"), paths)
+    }
+
+    #[test]
+    fn hidden_paths_are_dropped_and_bearer_auth_is_registered_once() {
+        let doc = public_openapi(require_bearer_auth(hide_path(doc(), "/notes/{id}")));
+        let json = serde_json::to_value(&doc).unwrap();
+
+        assert!(json["paths"].get("/notes/{id}").is_none());
+        assert_eq!(
+            json["paths"]["/notes"]["get"]["security"],
+            serde_json::json!([{ "bearerAuth": [] }])
+        );
+        assert_eq!(
+            json["components"]["securitySchemes"]["bearerAuth"],
+            serde_json::json!({ "type": "http", "scheme": "bearer", "bearerFormat": "JWT" })
+        );
+        assert!(public_openapi(self::doc()).components.is_none());
+    }
+}
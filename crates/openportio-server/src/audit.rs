@@ -0,0 +1,321 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::UNIX_EPOCH,
+};
+
+use axum::{
+    extract::{OriginalUri, Request},
+    middleware::Next,
+    response::Response,
+};
+use openportio_core::auth::AuthPrincipal;
+use serde::Serialize;
+
+use crate::{
+    client_ip::ClientIp,
+    context::RequestContext,
+    di::{clock_from_extensions, resolve_from_extensions},
+    middleware::is_grpc_request,
+};
+
+/// Destination for security-relevant events. Register one with
+/// `OpenportioServer::with_audit_log`; without one, events go to [`TracingAuditLog`].
+pub trait AuditLog: Send + Sync + 'static {
+    fn record(&self, event: &AuditEvent);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEventKind {
+    /// A bearer token (or mTLS peer identity) was accepted.
+    LoginSucceeded,
+    /// Auth was required and the credentials were missing, invalid or revoked.
+    LoginFailed,
+    /// An authenticated caller lacked the scope or permission a route requires.
+    ScopeDenied,
+    /// A caller was blocked, or turned away while blocked, after repeated failed logins.
+    LoginBlocked,
+    /// A request reached one of the `/internal/*` admin routes.
+    AdminAccess,
+    /// Runtime configuration was changed through an admin route (maintenance mode).
+    ConfigReload,
+}
+
+impl AuditEventKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::LoginSucceeded => "login_succeeded",
+            Self::LoginFailed => "login_failed",
+            Self::ScopeDenied => "scope_denied",
+            Self::LoginBlocked => "login_blocked",
+            Self::AdminAccess => "admin_access",
+            Self::ConfigReload => "config_reload",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuditEvent {
+    pub kind: AuditEventKind,
+    /// Wall-clock time in Unix milliseconds, from the registered `SharedClock`.
+    pub timestamp_ms: u64,
+    pub protocol: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub principal: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_ip: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl AuditEvent {
+    /// An event stamped with the request's clock, principal, path, client IP and request id.
+    pub(crate) fn from_extensions(kind: AuditEventKind, extensions: &http::Extensions) -> Self {
+        let timestamp_ms = clock_from_extensions(extensions)
+            .system_time()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or_default();
+        Self {
+            kind,
+            timestamp_ms,
+            protocol: "rest",
+            principal: extensions
+                .get::<AuthPrincipal>()
+                .map(|principal| principal.subject.clone()),
+            method: None,
+            path: extensions
+                .get::<OriginalUri>()
+                .map(|OriginalUri(uri)| uri.path().to_string()),
+            status: None,
+            client_ip: extensions
+                .get::<ClientIp>()
+                .map(|ClientIp(ip)| ip.to_string()),
+            request_id: extensions
+                .get::<RequestContext>()
+                .and_then(|context| context.request_id().map(str::to_string)),
+            reason: None,
+        }
+    }
+
+    /// Like [`Self::from_extensions`], with the method, path and protocol of `req`.
+    pub(crate) fn from_request(kind: AuditEventKind, req: &Request) -> Self {
+        let mut event = Self::from_extensions(kind, req.extensions());
+        event.protocol = if is_grpc_request(req.headers()) {
+            "grpc"
+        } else {
+            "rest"
+        };
+        event.method = Some(req.method().to_string());
+        event.path = Some(req.uri().path().to_string());
+        event
+    }
+
+    pub(crate) fn with_protocol(mut self, protocol: &'static str) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
+    pub(crate) fn with_principal(mut self, subject: impl Into<String>) -> Self {
+        self.principal = Some(subject.into());
+        self
+    }
+
+    pub(crate) fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+
+    /// Sends the event to the `AuditLogger` registered for the request.
+    pub(crate) fn emit(self, extensions: &http::Extensions) {
+        AuditLogger::from_extensions(extensions).record(&self);
+    }
+}
+
+/// Cloneable handle to the configured [`AuditLog`], resolved like any other dependency.
+#[derive(Clone)]
+pub struct AuditLogger(Arc<dyn AuditLog>);
+
+impl AuditLogger {
+    pub fn new(log: impl AuditLog) -> Self {
+        Self(Arc::new(log))
+    }
+
+    pub fn record(&self, event: &AuditEvent) {
+        self.0.record(event);
+    }
+
+    pub(crate) fn from_extensions(extensions: &http::Extensions) -> Self {
+        resolve_from_extensions(extensions).unwrap_or_default()
+    }
+}
+
+impl Default for AuditLogger {
+    fn default() -> Self {
+        Self::new(TracingAuditLog)
+    }
+}
+
+/// Emits each event as a `tracing` event with target `audit`: failures and denials at `warn`,
+/// the rest at `info`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TracingAuditLog;
+
+impl AuditLog for TracingAuditLog {
+    fn record(&self, event: &AuditEvent) {
+        macro_rules! audit_event {
+            ($level:ident) => {
+                tracing::$level!(
+                    target: "audit",
+                    kind = event.kind.as_str(),
+                    timestamp_ms = event.timestamp_ms,
+                    protocol = event.protocol,
+                    principal = event.principal.as_deref().unwrap_or("-"),
+                    method = event.method.as_deref().unwrap_or("-"),
+                    path = event.path.as_deref().unwrap_or("-"),
+                    status = event.status.unwrap_or_default(),
+                    client_ip = event.client_ip.as_deref().unwrap_or("-"),
+                    request_id = event.request_id.as_deref().unwrap_or("-"),
+                    reason = event.reason.as_deref().unwrap_or("-"),
+                    "audit event"
+                )
+            };
+        }
+        match event.kind {
+            AuditEventKind::LoginFailed
+            | AuditEventKind::ScopeDenied
+            | AuditEventKind::LoginBlocked => audit_event!(warn),
+            _ => audit_event!(info),
+        }
+    }
+}
+
+/// Appends each event as one JSON object per line, for shipping to a SIEM.
+#[derive(Debug)]
+pub struct JsonlAuditLog {
+    file: Mutex<File>,
+}
+
+impl JsonlAuditLog {
+    /// Opens `path` for appending, creating it when missing.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl AuditLog for JsonlAuditLog {
+    fn record(&self, event: &AuditEvent) {
+        let Ok(mut line) = serde_json::to_vec(event) else {
+            return;
+        };
+        line.push(b'\n');
+        let Ok(mut file) = self.file.lock() else {
+            tracing::warn!("audit log file lock poisoned; dropping event");
+            return;
+        };
+        if let Err(err) = file.write_all(&line) {
+            tracing::warn!(error = %err, "failed to write audit event");
+        }
+    }
+}
+
+/// Records an `admin_access` event, with the response status, for every admin route call.
+pub(crate) async fn admin_access_middleware(req: Request, next: Next) -> Response {
+    let logger = AuditLogger::from_extensions(req.extensions());
+    let mut event = AuditEvent::from_request(AuditEventKind::AdminAccess, &req);
+    let response = next.run(req).await;
+    event.status = Some(response.status().as_u16());
+    logger.record(&event);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::di::DependencyOverrides;
+    use axum::{body::Body, middleware::from_fn, routing::get, Router};
+    use openportio_core::clock::{SharedClock, TestClock};
+    use std::{sync::Arc, time::Duration};
+    use tower::ServiceExt;
+
+    #[derive(Clone, Default)]
+    struct RecordingAuditLog(Arc<Mutex<Vec<AuditEvent>>>);
+
+    impl AuditLog for RecordingAuditLog {
+        fn record(&self, event: &AuditEvent) {
+            self.0.lock().unwrap().push(event.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn admin_access_is_recorded_with_status_and_clock_time() {
+        let log = RecordingAuditLog::default();
+        let clock = TestClock::at(UNIX_EPOCH + Duration::from_millis(1_500));
+        let overrides = DependencyOverrides::default()
+            .with(AuditLogger::new(log.clone()))
+            .with::<SharedClock>(Arc::new(clock));
+        let app = Router::new()
+            .route("/internal/routes", get(|| async { "[]" }))
+            .layer(from_fn(admin_access_middleware))
+            .layer(axum::Extension(overrides));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/internal/routes")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+
+        let events = log.0.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, AuditEventKind::AdminAccess);
+        assert_eq!(events[0].method.as_deref(), Some("GET"));
+        assert_eq!(events[0].path.as_deref(), Some("/internal/routes"));
+        assert_eq!(events[0].status, Some(200));
+        assert_eq!(events[0].protocol, "rest");
+        assert_eq!(events[0].timestamp_ms, 1_500);
+    }
+
+    #[test]
+    fn jsonl_sink_appends_one_object_per_line() {
+        let path =
+            std::env::temp_dir().join(format!("openportio-audit-{}.jsonl", uuid::Uuid::new_v4()));
+        let sink = JsonlAuditLog::open(&path).unwrap();
+        let event = AuditEvent::from_extensions(AuditEventKind::LoginFailed, &Default::default())
+            .with_protocol("grpc")
+            .with_reason("token has been revoked");
+        sink.record(&event);
+        sink.record(&event.clone().with_principal("svc-a"));
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["kind"], "login_failed");
+        assert_eq!(lines[0]["protocol"], "grpc");
+        assert_eq!(lines[0]["reason"], "token has been revoked");
+        assert!(lines[0].get("principal").is_none());
+        assert_eq!(lines[1]["principal"], "svc-a");
+        std::fs::remove_file(path).unwrap();
+    }
+}
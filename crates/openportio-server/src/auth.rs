@@ -1,13 +1,14 @@
 use std::{
     collections::HashMap,
-    env,
     io::Read,
+    path::Path,
     str::FromStr,
     sync::{Arc, Mutex, RwLock},
     time::{Duration, Instant},
 };
 
 use axum::{
+    body::Body,
     extract::{Request, State},
     http::{header, HeaderMap, HeaderValue, StatusCode},
     middleware::Next,
@@ -15,12 +16,30 @@ use axum::{
     Json,
 };
 use jsonwebtoken::{decode_header, jwk::JwkSet, Algorithm, DecodingKey};
-use openportio_core::auth::{
-    validate_bearer_jwt, validate_bearer_jwt_with_key, AuthPrincipal, JwtValidationConfig,
+use openportio_core::{
+    auth::{
+        validate_bearer_jwt_at, validate_bearer_jwt_with_key_at, AuthPrincipal, JwtTimeValidation,
+        JwtValidationConfig,
+    },
+    clock::{system_clock, Clock, SharedClock},
 };
 use tonic::Status;
 
-use crate::api::ApiErrorResponse;
+use crate::{
+    api::ApiErrorResponse,
+    audit::{AuditEvent, AuditEventKind},
+    brute_force::AttemptGuard,
+    context::RequestContext,
+    di,
+    middleware::{is_grpc_request, HEALTH_PATHS},
+    quota,
+    revocation::{RedisRevocationStore, RevocationList, TokenRevocationStore},
+    tls::PeerIdentity,
+};
+use x509_parser::{pem::parse_x509_pem, prelude::FromDer, x509::SubjectPublicKeyInfo};
+
+/// Scope a token must carry to call the admin router (`with_admin_routes`).
+pub const ADMIN_SCOPE: &str = "openportio:admin";
 
 const DEFAULT_JWKS_REFRESH_SECS: u64 = 300;
 const DEFAULT_JWKS_CONNECT_TIMEOUT_SECS: u64 = 2;
@@ -62,6 +81,7 @@ impl JwksProvider {
     fn decoding_key_for_token(
         &self,
         token: &str,
+        clock: &dyn Clock,
     ) -> Result<(DecodingKey, Algorithm), AuthRejection> {
         let header = decode_header(token)
             .map_err(|err| AuthRejection::InvalidToken(format!("invalid token header: {err}")))?;
@@ -77,7 +97,7 @@ impl JwksProvider {
             )));
         }
 
-        self.refresh_if_needed()?;
+        self.refresh_if_needed(clock.now())?;
         if let Some(key) = self.cached_key(&kid)? {
             return Ok((key, algorithm));
         }
@@ -92,8 +112,8 @@ impl JwksProvider {
         Ok((key, algorithm))
     }
 
-    fn refresh_if_needed(&self) -> Result<(), AuthRejection> {
-        if !self.should_refresh()? {
+    fn refresh_if_needed(&self, now: Instant) -> Result<(), AuthRejection> {
+        if !self.should_refresh(now)? {
             return Ok(());
         }
 
@@ -103,11 +123,11 @@ impl JwksProvider {
             .map_err(|_| AuthRejection::Misconfigured("jwks refresh lock poisoned".to_string()))?;
 
         // Double-check after obtaining refresh lock to prevent thundering herd refreshes.
-        if !self.should_refresh()? {
+        if !self.should_refresh(now)? {
             return Ok(());
         }
 
-        match self.refresh_keys() {
+        match self.refresh_keys(now) {
             Ok(()) => Ok(()),
             Err(err) => {
                 if self.has_cached_keys()? {
@@ -120,12 +140,11 @@ impl JwksProvider {
         }
     }
 
-    fn should_refresh(&self) -> Result<bool, AuthRejection> {
+    fn should_refresh(&self, now: Instant) -> Result<bool, AuthRejection> {
         let guard = self
             .state
             .read()
             .map_err(|_| AuthRejection::Misconfigured("jwks cache lock poisoned".to_string()))?;
-        let now = Instant::now();
         let should_refresh = match guard.last_refresh {
             Some(last) => now.duration_since(last) >= self.refresh_interval,
             None => true,
@@ -149,7 +168,7 @@ impl JwksProvider {
         Ok(guard.keys.get(kid).cloned())
     }
 
-    fn refresh_keys(&self) -> Result<(), AuthRejection> {
+    fn refresh_keys(&self, now: Instant) -> Result<(), AuthRejection> {
         let jwk_set = self.fetch_jwks()?;
         let mut keys = HashMap::new();
 
@@ -178,7 +197,7 @@ impl JwksProvider {
             .write()
             .map_err(|_| AuthRejection::Misconfigured("jwks cache lock poisoned".to_string()))?;
         guard.keys = keys;
-        guard.last_refresh = Some(Instant::now());
+        guard.last_refresh = Some(now);
         Ok(())
     }
 
@@ -237,6 +256,134 @@ impl JwksProvider {
     }
 }
 
+/// Public key for tokens from an issuer without a JWKS endpoint, such as internally minted
+/// service tokens. Accepts `PUBLIC KEY` (SPKI) PEMs with RSA, P-256, P-384 or Ed25519 keys and
+/// PKCS#1 `RSA PUBLIC KEY` PEMs.
+#[derive(Debug, Clone)]
+pub struct StaticPublicKey {
+    key: DecodingKey,
+    algorithm: Algorithm,
+}
+
+impl StaticPublicKey {
+    /// Without an `algorithm`, RSA keys verify RS256, P-256 ES256, P-384 ES384 and Ed25519
+    /// EdDSA. Only tokens signed with that one algorithm are accepted.
+    pub fn from_pem(pem: &[u8], algorithm: Option<Algorithm>) -> Result<Self, AuthRejection> {
+        let invalid =
+            |reason: String| AuthRejection::Misconfigured(format!("public key: {reason}"));
+        let (_, pem) = parse_x509_pem(pem).map_err(|err| invalid(format!("invalid PEM: {err}")))?;
+        let (family, key) = if pem.label == "RSA PUBLIC KEY" {
+            (KeyFamily::Rsa, DecodingKey::from_rsa_der(&pem.contents))
+        } else if pem.label == "PUBLIC KEY" {
+            let (_, spki) = SubjectPublicKeyInfo::from_der(&pem.contents)
+                .map_err(|err| invalid(format!("invalid SubjectPublicKeyInfo: {err}")))?;
+            let bits = spki.subject_public_key.data.as_ref();
+            let curve = spki
+                .algorithm
+                .parameters
+                .as_ref()
+                .and_then(|params| params.as_oid().ok())
+                .map(|oid| oid.to_id_string());
+            match (
+                spki.algorithm.algorithm.to_id_string().as_str(),
+                curve.as_deref(),
+            ) {
+                (OID_RSA, _) => (KeyFamily::Rsa, DecodingKey::from_rsa_der(bits)),
+                (OID_EC, Some(OID_P256)) => (KeyFamily::P256, DecodingKey::from_ec_der(bits)),
+                (OID_EC, Some(OID_P384)) => (KeyFamily::P384, DecodingKey::from_ec_der(bits)),
+                (OID_ED25519, _) => (KeyFamily::Ed25519, DecodingKey::from_ed_der(bits)),
+                (oid, curve) => {
+                    return Err(invalid(format!(
+                        "unsupported key type {oid}{}",
+                        curve.map(|curve| format!(" ({curve})")).unwrap_or_default()
+                    )))
+                }
+            }
+        } else {
+            return Err(invalid(format!(
+                "expected a PUBLIC KEY or RSA PUBLIC KEY PEM, found {}",
+                pem.label
+            )));
+        };
+
+        let algorithm = algorithm.unwrap_or(family.default_algorithm());
+        if !family.supports(algorithm) {
+            return Err(invalid(format!(
+                "{algorithm:?} cannot be used with {} keys",
+                family.name()
+            )));
+        }
+        Ok(Self { key, algorithm })
+    }
+
+    pub fn from_pem_file(
+        path: impl AsRef<Path>,
+        algorithm: Option<Algorithm>,
+    ) -> Result<Self, AuthRejection> {
+        let path = path.as_ref();
+        let pem = std::fs::read(path).map_err(|err| {
+            AuthRejection::Misconfigured(format!(
+                "public key: cannot read {}: {err}",
+                path.display()
+            ))
+        })?;
+        Self::from_pem(&pem, algorithm)
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+}
+
+const OID_RSA: &str = "1.2.840.113549.1.1.1";
+const OID_EC: &str = "1.2.840.10045.2.1";
+const OID_P256: &str = "1.2.840.10045.3.1.7";
+const OID_P384: &str = "1.3.132.0.34";
+const OID_ED25519: &str = "1.3.101.112";
+
+#[derive(Debug, Clone, Copy)]
+enum KeyFamily {
+    Rsa,
+    P256,
+    P384,
+    Ed25519,
+}
+
+impl KeyFamily {
+    fn default_algorithm(self) -> Algorithm {
+        match self {
+            Self::Rsa => Algorithm::RS256,
+            Self::P256 => Algorithm::ES256,
+            Self::P384 => Algorithm::ES384,
+            Self::Ed25519 => Algorithm::EdDSA,
+        }
+    }
+
+    fn supports(self, algorithm: Algorithm) -> bool {
+        match self {
+            Self::Rsa => matches!(
+                algorithm,
+                Algorithm::RS256
+                    | Algorithm::RS384
+                    | Algorithm::RS512
+                    | Algorithm::PS256
+                    | Algorithm::PS384
+                    | Algorithm::PS512
+            ),
+            Self::P256 | Self::P384 | Self::Ed25519 => algorithm == self.default_algorithm(),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Rsa => "RSA",
+            Self::P256 => "P-256",
+            Self::P384 => "P-384",
+            Self::Ed25519 => "Ed25519",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AuthRuntimeConfig {
     pub enabled: bool,
@@ -244,9 +391,15 @@ pub struct AuthRuntimeConfig {
     pub jwks_url: Option<String>,
     pub jwks_refresh_secs: u64,
     pub jwks_allowed_algorithms: Vec<Algorithm>,
+    /// Verifies asymmetrically signed tokens when the issuer does not publish a JWKS.
+    pub public_key: Option<StaticPublicKey>,
     pub expected_issuer: Option<String>,
     pub expected_audience: Option<String>,
+    /// Leeway and `nbf`/`iat` checks applied to both shared-secret and JWKS tokens.
+    pub jwt_time_validation: JwtTimeValidation,
     jwks_provider: Option<Arc<JwksProvider>>,
+    revocation: Option<RevocationList>,
+    clock: Option<SharedClock>,
 }
 
 impl Default for AuthRuntimeConfig {
@@ -257,60 +410,65 @@ impl Default for AuthRuntimeConfig {
             jwks_url: None,
             jwks_refresh_secs: DEFAULT_JWKS_REFRESH_SECS,
             jwks_allowed_algorithms: default_jwks_algorithms(),
+            public_key: None,
             expected_issuer: None,
             expected_audience: None,
+            jwt_time_validation: JwtTimeValidation::default(),
             jwks_provider: None,
+            revocation: None,
+            clock: None,
         }
     }
 }
 
 impl AuthRuntimeConfig {
-    pub fn from_env() -> Self {
-        let enabled = read_env_bool_with_aliases(&[
-            "OPENPORTIO_AUTH_ENABLED",
-            "MELD_AUTH_ENABLED",
-            "ALLOY_AUTH_ENABLED",
-        ])
-        .unwrap_or(false);
-        let jwt_secret = read_env_string_with_aliases(&[
-            "OPENPORTIO_AUTH_JWT_SECRET",
-            "MELD_AUTH_JWT_SECRET",
-            "ALLOY_AUTH_JWT_SECRET",
-        ]);
-        let jwks_url = read_env_string_with_aliases(&[
-            "OPENPORTIO_AUTH_JWKS_URL",
-            "MELD_AUTH_JWKS_URL",
-            "ALLOY_AUTH_JWKS_URL",
-        ]);
-        let jwks_refresh_secs = read_env_u64_with_aliases(&[
-            "OPENPORTIO_AUTH_JWKS_REFRESH_SECS",
-            "MELD_AUTH_JWKS_REFRESH_SECS",
-            "ALLOY_AUTH_JWKS_REFRESH_SECS",
-        ])
-        .unwrap_or(DEFAULT_JWKS_REFRESH_SECS);
-        let jwks_allowed_algorithms = read_env_algorithms_with_aliases(&[
-            "OPENPORTIO_AUTH_JWKS_ALGORITHMS",
-            "MELD_AUTH_JWKS_ALGORITHMS",
-            "ALLOY_AUTH_JWKS_ALGORITHMS",
-        ])
-        .unwrap_or_else(default_jwks_algorithms);
+    /// Reads the `OPENPORTIO_AUTH_*` settings and fetches the JWKS once when a URL is set.
+    ///
+    /// Fails when a setting is present but cannot be used — a non-numeric refresh interval,
+    /// an unknown algorithm, an unreadable public key or a bad revocation Redis URL — rather
+    /// than quietly starting without it.
+    pub fn from_env() -> Result<Self, String> {
+        let cfg = Self::from_env_without_prefetch()?;
+        if let Some(provider) = cfg.jwks_provider.as_ref() {
+            if let Err(err) = provider.refresh_keys(Instant::now()) {
+                tracing::warn!(
+                    error = ?err,
+                    "initial jwks fetch failed; runtime will retry during authentication"
+                );
+            }
+        }
 
-        let cfg = Self {
+        Ok(cfg)
+    }
+
+    /// Reads the same settings as [`Self::from_env`] without the initial JWKS fetch.
+    pub(crate) fn from_env_without_prefetch() -> Result<Self, String> {
+        let enabled = crate::env::setting::<bool>("AUTH_ENABLED")?.unwrap_or(false);
+        let jwt_secret = crate::env::var("AUTH_JWT_SECRET")?;
+        let jwks_url = crate::env::var("AUTH_JWKS_URL")?;
+        let jwks_refresh_secs = crate::env::setting::<u64>("AUTH_JWKS_REFRESH_SECS")?
+            .unwrap_or(DEFAULT_JWKS_REFRESH_SECS);
+        let jwks_allowed_algorithms =
+            read_env_algorithms("AUTH_JWKS_ALGORITHMS")?.unwrap_or_else(default_jwks_algorithms);
+        let revocation = crate::env::var("AUTH_REVOCATION_REDIS_URL")?
+            .map(|url| {
+                RedisRevocationStore::new(&url)
+                    .map(RevocationList::new)
+                    .map_err(|err| {
+                        format!("`OPENPORTIO_AUTH_REVOCATION_REDIS_URL` is invalid: {err}")
+                    })
+            })
+            .transpose()?;
+
+        Ok(Self {
             enabled,
             jwt_secret,
             jwks_url: jwks_url.clone(),
             jwks_refresh_secs,
             jwks_allowed_algorithms: jwks_allowed_algorithms.clone(),
-            expected_issuer: read_env_string_with_aliases(&[
-                "OPENPORTIO_AUTH_ISSUER",
-                "MELD_AUTH_ISSUER",
-                "ALLOY_AUTH_ISSUER",
-            ]),
-            expected_audience: read_env_string_with_aliases(&[
-                "OPENPORTIO_AUTH_AUDIENCE",
-                "MELD_AUTH_AUDIENCE",
-                "ALLOY_AUTH_AUDIENCE",
-            ]),
+            expected_issuer: crate::env::var("AUTH_ISSUER")?,
+            expected_audience: crate::env::var("AUTH_AUDIENCE")?,
+            jwt_time_validation: jwt_time_validation_from_env()?,
             jwks_provider: jwks_url.map(|url| {
                 Arc::new(JwksProvider::new(
                     url,
@@ -318,24 +476,85 @@ impl AuthRuntimeConfig {
                     jwks_allowed_algorithms,
                 ))
             }),
-        };
+            public_key: public_key_from_env()?,
+            revocation,
+            clock: None,
+        })
+    }
 
-        if let Some(provider) = cfg.jwks_provider.as_ref() {
-            if let Err(err) = provider.refresh_keys() {
-                tracing::warn!(
-                    error = ?err,
-                    "initial jwks fetch failed; runtime will retry during authentication"
-                );
+    /// Unwraps a loaded config for callers that cannot return the error, rejecting every
+    /// token instead of running unauthenticated. `OpenportioServer::run` reports the same
+    /// error before serving.
+    pub(crate) fn or_reject_all(loaded: Result<Self, String>) -> Self {
+        loaded.unwrap_or_else(|err| {
+            tracing::error!(error = %err, "invalid auth settings; rejecting every token");
+            Self {
+                enabled: true,
+                ..Self::default()
             }
+        })
+    }
+
+    /// `disabled`, `jwks`, `public_key`, `shared_secret`, or `unconfigured` (enabled without a
+    /// key source).
+    pub fn mode(&self) -> &'static str {
+        match (
+            self.enabled,
+            &self.jwks_provider,
+            &self.public_key,
+            &self.jwt_secret,
+        ) {
+            (false, _, _, _) => "disabled",
+            (true, Some(_), _, _) => "jwks",
+            (true, None, Some(_), _) => "public_key",
+            (true, None, None, Some(_)) => "shared_secret",
+            (true, None, None, None) => "unconfigured",
         }
+    }
+
+    /// Verifies tokens with `key` instead of the shared secret; a JWKS url still takes precedence.
+    pub fn with_public_key(mut self, key: StaticPublicKey) -> Self {
+        self.public_key = Some(key);
+        self
+    }
+
+    /// Rejects tokens whose `jti` is in `store`. Tokens without a `jti` cannot be revoked.
+    pub fn with_revocation_store(mut self, store: impl TokenRevocationStore) -> Self {
+        self.revocation = Some(RevocationList::new(store));
+        self
+    }
+
+    pub fn with_revocation_list(mut self, list: RevocationList) -> Self {
+        self.revocation = Some(list);
+        self
+    }
+
+    pub fn revocation_list(&self) -> Option<&RevocationList> {
+        self.revocation.as_ref()
+    }
+
+    /// Time source for token expiry and JWKS refresh. Without one, requests use the `SharedClock`
+    /// dependency when registered, else the system clock.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
 
-        cfg
+    pub(crate) fn clock_for(&self, extensions: &http::Extensions) -> SharedClock {
+        self.clock
+            .clone()
+            .unwrap_or_else(|| di::clock_from_extensions(extensions))
+    }
+
+    fn clock(&self) -> SharedClock {
+        self.clock.clone().unwrap_or_else(system_clock)
     }
 
     fn jwt_validation_config(&self) -> Result<JwtValidationConfig, AuthRejection> {
         let secret = self.jwt_secret.clone().ok_or_else(|| {
             AuthRejection::Misconfigured(
-                "OPENPORTIO_AUTH_JWT_SECRET is missing (or configure OPENPORTIO_AUTH_JWKS_URL)"
+                "OPENPORTIO_AUTH_JWT_SECRET is missing (or configure OPENPORTIO_AUTH_JWKS_URL or \
+                 OPENPORTIO_AUTH_PUBLIC_KEY_PATH)"
                     .to_string(),
             )
         })?;
@@ -344,12 +563,35 @@ impl AuthRuntimeConfig {
             secret,
             expected_issuer: self.expected_issuer.clone(),
             expected_audience: self.expected_audience.clone(),
+            time: self.jwt_time_validation,
         })
     }
 
-    pub fn authenticate_authorization_value_str(
+    pub async fn authenticate_authorization_value_str(
+        &self,
+        auth_value: &str,
+    ) -> Result<AuthPrincipal, AuthRejection> {
+        self.authenticate_with_revocation(auth_value, self.revocation.as_ref(), &*self.clock())
+            .await
+    }
+
+    /// Like [`Self::authenticate_authorization_value_str`], but checks `revocation` instead of the
+    /// configured store. Used when the deny-list is resolved per request from DI overrides.
+    pub(crate) async fn authenticate_with_revocation(
+        &self,
+        auth_value: &str,
+        revocation: Option<&RevocationList>,
+        clock: &dyn Clock,
+    ) -> Result<AuthPrincipal, AuthRejection> {
+        let principal = self.verify_bearer(auth_value, clock)?;
+        ensure_not_revoked(&principal, revocation).await?;
+        Ok(principal)
+    }
+
+    fn verify_bearer(
         &self,
         auth_value: &str,
+        clock: &dyn Clock,
     ) -> Result<AuthPrincipal, AuthRejection> {
         if !self.enabled {
             return Ok(AuthPrincipal {
@@ -357,31 +599,61 @@ impl AuthRuntimeConfig {
                 issuer: None,
                 audience: vec![],
                 scopes: vec![],
+                token_id: None,
+                tenant_id: None,
             });
         }
 
         let token = parse_bearer_token(auth_value)?;
 
-        if let Some(provider) = &self.jwks_provider {
-            let (decoding_key, algorithm) = provider.decoding_key_for_token(token)?;
-            return validate_bearer_jwt_with_key(
+        let principal = if let Some(provider) = &self.jwks_provider {
+            let (decoding_key, algorithm) = provider.decoding_key_for_token(token, clock)?;
+            validate_bearer_jwt_with_key_at(
                 token,
                 &decoding_key,
                 algorithm,
                 self.expected_issuer.as_deref(),
                 self.expected_audience.as_deref(),
+                self.jwt_time_validation,
+                clock.system_time(),
             )
-            .map_err(|err| AuthRejection::InvalidToken(err.to_string()));
-        }
+            .map_err(|err| AuthRejection::InvalidToken(err.to_string()))?
+        } else if let Some(public_key) = &self.public_key {
+            validate_bearer_jwt_with_key_at(
+                token,
+                &public_key.key,
+                public_key.algorithm,
+                self.expected_issuer.as_deref(),
+                self.expected_audience.as_deref(),
+                self.jwt_time_validation,
+                clock.system_time(),
+            )
+            .map_err(|err| AuthRejection::InvalidToken(err.to_string()))?
+        } else {
+            let validation_cfg = self.jwt_validation_config()?;
+            validate_bearer_jwt_at(token, &validation_cfg, clock.system_time())
+                .map_err(|err| AuthRejection::InvalidToken(err.to_string()))?
+        };
+        Ok(principal)
+    }
 
-        let validation_cfg = self.jwt_validation_config()?;
-        validate_bearer_jwt(token, &validation_cfg)
-            .map_err(|err| AuthRejection::InvalidToken(err.to_string()))
+    pub async fn authenticate_header_value(
+        &self,
+        auth_value: Option<&HeaderValue>,
+    ) -> Result<AuthPrincipal, AuthRejection> {
+        self.authenticate_header_value_with_revocation(
+            auth_value,
+            self.revocation.as_ref(),
+            &*self.clock(),
+        )
+        .await
     }
 
-    pub fn authenticate_header_value(
+    async fn authenticate_header_value_with_revocation(
         &self,
         auth_value: Option<&HeaderValue>,
+        revocation: Option<&RevocationList>,
+        clock: &dyn Clock,
     ) -> Result<AuthPrincipal, AuthRejection> {
         if !self.enabled {
             return Ok(AuthPrincipal {
@@ -389,6 +661,8 @@ impl AuthRuntimeConfig {
                 issuer: None,
                 audience: vec![],
                 scopes: vec![],
+                token_id: None,
+                tenant_id: None,
             });
         }
 
@@ -399,14 +673,16 @@ impl AuthRuntimeConfig {
                 AuthRejection::InvalidToken("authorization header is invalid".to_string())
             })?;
 
-        self.authenticate_authorization_value_str(value)
+        self.authenticate_with_revocation(value, revocation, clock)
+            .await
     }
 
-    pub fn authenticate_headers(
+    pub async fn authenticate_headers(
         &self,
         headers: &HeaderMap,
     ) -> Result<AuthPrincipal, AuthRejection> {
         self.authenticate_header_value(headers.get(header::AUTHORIZATION))
+            .await
     }
 }
 
@@ -418,6 +694,13 @@ pub enum AuthRejection {
 }
 
 impl AuthRejection {
+    pub(crate) fn reason(&self) -> &str {
+        match self {
+            Self::MissingAuthorization => "missing bearer token",
+            Self::InvalidToken(message) | Self::Misconfigured(message) => message,
+        }
+    }
+
     pub fn into_rest_response(self) -> Response {
         match self {
             Self::MissingAuthorization => (
@@ -467,12 +750,188 @@ pub async fn rest_auth_middleware(
     mut req: Request,
     next: Next,
 ) -> Response {
-    match cfg.authenticate_headers(req.headers()) {
-        Ok(principal) => {
+    match authenticate_guarded(&cfg, &mut req).await {
+        Ok(()) if cfg.enabled => quota::charge_verified_principal(req, next).await,
+        Ok(()) => next.run(req).await,
+        Err(GuardedRejection::Blocked(response)) => response,
+        Err(GuardedRejection::Auth(rejection)) => rejection.into_rest_response(),
+    }
+}
+
+/// Router-wide variant of [`rest_auth_middleware`] installed by `OpenportioServer::require_auth`:
+/// health probes pass through and gRPC calls are rejected with a gRPC status.
+pub(crate) async fn required_auth_middleware(
+    State(cfg): State<AuthRuntimeConfig>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    if HEALTH_PATHS.contains(&req.uri().path()) {
+        return next.run(req).await;
+    }
+    match authenticate_guarded(&cfg, &mut req).await {
+        Ok(()) => next.run(req).await,
+        Err(GuardedRejection::Blocked(response)) => response,
+        Err(GuardedRejection::Auth(rejection)) if is_grpc_request(req.headers()) => {
+            rejection.into_grpc_status().into_http().map(Body::new)
+        }
+        Err(GuardedRejection::Auth(rejection)) => rejection.into_rest_response(),
+    }
+}
+
+/// Guards the admin router: the caller authenticates against `cfg` even when the rest of the
+/// app is public, and needs [`ADMIN_SCOPE`] (`403 insufficient_scope` otherwise). A principal
+/// already verified by `require_auth` is reused.
+pub(crate) async fn admin_auth_middleware(
+    State(cfg): State<AuthRuntimeConfig>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    if req.extensions().get::<AuthPrincipal>().is_none() {
+        match authenticate_guarded(&cfg, &mut req).await {
+            Ok(()) => {}
+            Err(GuardedRejection::Blocked(response)) => return response,
+            Err(GuardedRejection::Auth(rejection)) => return rejection.into_rest_response(),
+        }
+    }
+    let is_admin = req
+        .extensions()
+        .get::<AuthPrincipal>()
+        .is_some_and(|principal| principal.scopes.iter().any(|scope| scope == ADMIN_SCOPE));
+    if !is_admin {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(ApiErrorResponse {
+                code: "insufficient_scope".to_string(),
+                message: format!("admin routes require the `{ADMIN_SCOPE}` scope"),
+                detail: None,
+                details: None,
+            }),
+        )
+            .into_response();
+    }
+    next.run(req).await
+}
+
+enum GuardedRejection {
+    Auth(AuthRejection),
+    Blocked(Response),
+}
+
+/// [`authenticate_request`] behind the registered `BruteForceProtection`, if any: blocked
+/// callers are turned away before their token is checked, and invalid tokens are counted and
+/// delayed.
+async fn authenticate_guarded(
+    cfg: &AuthRuntimeConfig,
+    req: &mut Request,
+) -> Result<(), GuardedRejection> {
+    let guard = cfg
+        .enabled
+        .then(|| AttemptGuard::for_request(req))
+        .flatten();
+    if let Some(response) = guard
+        .as_ref()
+        .and_then(|guard| guard.blocked_response(req.extensions()))
+    {
+        return Err(GuardedRejection::Blocked(response));
+    }
+    let result = authenticate_request(cfg, req).await;
+    let delay = guard
+        .as_ref()
+        .map(|guard| guard.settle(&result, req.extensions()))
+        .unwrap_or_default();
+    if !delay.is_zero() {
+        tokio::time::sleep(delay).await;
+    }
+    result.map_err(GuardedRejection::Auth)
+}
+
+async fn authenticate_request(
+    cfg: &AuthRuntimeConfig,
+    req: &mut Request,
+) -> Result<(), AuthRejection> {
+    let result = verify_request(cfg, req).await;
+    if cfg.enabled {
+        let event = match &result {
+            Ok(()) => AuditEvent::from_request(AuditEventKind::LoginSucceeded, req),
+            Err(rejection) => AuditEvent::from_request(AuditEventKind::LoginFailed, req)
+                .with_reason(rejection.reason()),
+        };
+        event.emit(req.extensions());
+    }
+    result
+}
+
+async fn verify_request(cfg: &AuthRuntimeConfig, req: &mut Request) -> Result<(), AuthRejection> {
+    // A verified mTLS workload identity stands in for a bearer token.
+    if cfg.enabled && !req.headers().contains_key(header::AUTHORIZATION) {
+        if let Some(principal) = PeerIdentity::principal_from_extensions(req.extensions()) {
+            CallPrincipal::record(req.extensions(), &principal);
             req.extensions_mut().insert(principal);
-            next.run(req).await
+            return Ok(());
         }
-        Err(rejection) => rejection.into_rest_response(),
+    }
+    let revocation = cfg
+        .revocation
+        .clone()
+        .or_else(|| RevocationList::from_extensions(req.extensions()));
+    let clock = cfg.clock_for(req.extensions());
+    let principal = cfg
+        .authenticate_header_value_with_revocation(
+            req.headers().get(header::AUTHORIZATION),
+            revocation.as_ref(),
+            &*clock,
+        )
+        .await?;
+    CallPrincipal::record(req.extensions(), &principal);
+    req.extensions_mut().insert(principal);
+    Ok(())
+}
+
+/// Subject of the authenticated caller, filled in by the auth middleware and
+/// `GrpcAuthInterceptor` so layers running outside them (request logs, the slow request
+/// detector) can report it after the response.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CallPrincipal(Arc<Mutex<Option<String>>>);
+
+impl CallPrincipal {
+    /// The slot already in `extensions`, or a new one inserted there.
+    pub(crate) fn attach(extensions: &mut http::Extensions) -> Self {
+        extensions.get_or_insert_default::<Self>().clone()
+    }
+
+    /// Also fills in the request's `RequestContext`.
+    pub(crate) fn record(extensions: &http::Extensions, principal: &AuthPrincipal) {
+        if let Some(context) = extensions.get::<RequestContext>() {
+            context.set_principal(principal);
+        }
+        if let Some(slot) = extensions.get::<Self>() {
+            if let Ok(mut guard) = slot.0.lock() {
+                *guard = Some(principal.subject.clone());
+            }
+        }
+    }
+
+    pub(crate) fn get(&self) -> Option<String> {
+        self.0.lock().ok().and_then(|guard| guard.clone())
+    }
+}
+
+// Store failures reject the request: an unreachable deny-list must not let revoked tokens through.
+async fn ensure_not_revoked(
+    principal: &AuthPrincipal,
+    revocation: Option<&RevocationList>,
+) -> Result<(), AuthRejection> {
+    let (Some(list), Some(jti)) = (revocation, principal.token_id.as_deref()) else {
+        return Ok(());
+    };
+    match list.is_revoked(jti).await {
+        Ok(false) => Ok(()),
+        Ok(true) => Err(AuthRejection::InvalidToken(
+            "token has been revoked".to_string(),
+        )),
+        Err(err) => Err(AuthRejection::Misconfigured(format!(
+            "token revocation check failed: {err}"
+        ))),
     }
 }
 
@@ -500,6 +959,37 @@ fn default_jwks_algorithms() -> Vec<Algorithm> {
     ]
 }
 
+fn jwt_time_validation_from_env() -> Result<JwtTimeValidation, String> {
+    let defaults = JwtTimeValidation::default();
+    Ok(JwtTimeValidation {
+        leeway_secs: crate::env::setting::<u64>("AUTH_JWT_LEEWAY_SECS")?
+            .unwrap_or(defaults.leeway_secs),
+        validate_nbf: crate::env::setting::<bool>("AUTH_JWT_VALIDATE_NBF")?
+            .unwrap_or(defaults.validate_nbf),
+        validate_iat: crate::env::setting::<bool>("AUTH_JWT_VALIDATE_IAT")?
+            .unwrap_or(defaults.validate_iat),
+    })
+}
+
+fn public_key_from_env() -> Result<Option<StaticPublicKey>, String> {
+    let algorithm = match crate::env::var("AUTH_PUBLIC_KEY_ALGORITHM")? {
+        Some(raw) => Some(Algorithm::from_str(raw.trim()).map_err(|_| {
+            format!("`OPENPORTIO_AUTH_PUBLIC_KEY_ALGORITHM` has an invalid value `{raw}`")
+        })?),
+        None => None,
+    };
+    let key = if let Some(pem) = crate::env::var("AUTH_PUBLIC_KEY_PEM")? {
+        // Single-line env files often escape the PEM line breaks.
+        StaticPublicKey::from_pem(pem.replace("\\n", "\n").as_bytes(), algorithm)
+    } else if let Some(path) = crate::env::var("AUTH_PUBLIC_KEY_PATH")? {
+        StaticPublicKey::from_pem_file(path, algorithm)
+    } else {
+        return Ok(None);
+    };
+    key.map(Some)
+        .map_err(|err| format!("invalid auth public key: {}", err.reason()))
+}
+
 fn parse_jwks_algorithm(raw: &str) -> Option<Algorithm> {
     match raw.trim().to_ascii_uppercase().as_str() {
         "RS256" => Some(Algorithm::RS256),
@@ -511,29 +1001,10 @@ fn parse_jwks_algorithm(raw: &str) -> Option<Algorithm> {
     }
 }
 
-fn read_env_bool(name: &str) -> Option<bool> {
-    env::var(name)
-        .ok()
-        .and_then(|raw| bool::from_str(raw.trim()).ok())
-}
-
-fn read_env_bool_with_aliases(names: &[&str]) -> Option<bool> {
-    names.iter().find_map(|name| read_env_bool(name))
-}
-
-fn read_env_string_with_aliases(names: &[&str]) -> Option<String> {
-    names.iter().find_map(|name| env::var(name).ok())
-}
-
-fn read_env_u64_with_aliases(names: &[&str]) -> Option<u64> {
-    names
-        .iter()
-        .find_map(|name| env::var(name).ok())
-        .and_then(|raw| raw.trim().parse::<u64>().ok())
-}
-
-fn read_env_algorithms_with_aliases(names: &[&str]) -> Option<Vec<Algorithm>> {
-    let raw = names.iter().find_map(|name| env::var(name).ok())?;
+fn read_env_algorithms(suffix: &str) -> Result<Option<Vec<Algorithm>>, String> {
+    let Some((name, raw)) = crate::env::lookup(suffix)? else {
+        return Ok(None);
+    };
     let mut parsed = Vec::new();
 
     for entry in raw.split(',') {
@@ -554,37 +1025,25 @@ fn read_env_algorithms_with_aliases(names: &[&str]) -> Option<Vec<Algorithm>> {
     }
 
     if parsed.is_empty() {
-        tracing::warn!(
-            "OPENPORTIO_AUTH_JWKS_ALGORITHMS was set but contained no supported entries"
-        );
-        None
-    } else {
-        Some(parsed)
+        return Err(format!("`{name}` lists no supported algorithms"));
     }
+    Ok(Some(parsed))
 }
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-    use jsonwebtoken::{
-        encode,
-        jwk::{Jwk, JwkSet},
-        EncodingKey, Header,
-    };
-    use openportio_core::auth::{AudienceClaim, JwtClaims};
-    use std::{
-        io::{Read, Write},
-        net::TcpListener,
-        sync::{
-            atomic::{AtomicUsize, Ordering},
-            mpsc, LazyLock, Mutex,
-        },
-        thread,
-    };
+    use std::env;
 
-    static ENV_LOCK: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
+    use super::*;
+    use crate::env::ENV_LOCK;
+    use openportio_core::TestClock;
+    use openportio_test::{Failure, FakeIdp};
+    use std::thread;
 
-    const TEST_RSA_PRIVATE_KEY_DER: &[u8] = include_bytes!("../tests/fixtures/private_rsa_key.der");
+    const EC_P256_PRIVATE_KEY: &[u8] = include_bytes!("../tests/fixtures/ec_p256_private.der");
+    const EC_P256_PUBLIC_PEM: &[u8] = include_bytes!("../tests/fixtures/ec_p256_public.pem");
+    const ED25519_PRIVATE_KEY: &[u8] = include_bytes!("../tests/fixtures/ed25519_private.der");
+    const ED25519_PUBLIC_PEM: &[u8] = include_bytes!("../tests/fixtures/ed25519_public.pem");
 
     #[test]
     fn from_env_supports_meld_compatibility_aliases() {
@@ -598,8 +1057,11 @@ mod tests {
         env::set_var("MELD_AUTH_JWKS_ALGORITHMS", "RS256,ES256");
         env::set_var("MELD_AUTH_ISSUER", "https://issuer.legacy");
         env::set_var("MELD_AUTH_AUDIENCE", "legacy-audience");
+        // Added after the rename, so the legacy names are not read.
+        env::set_var("MELD_AUTH_JWT_LEEWAY_SECS", "5");
+        env::set_var("MELD_AUTH_JWT_VALIDATE_IAT", "true");
 
-        let cfg = AuthRuntimeConfig::from_env();
+        let cfg = AuthRuntimeConfig::from_env().expect("auth settings parse");
         assert!(cfg.enabled);
         assert_eq!(cfg.jwt_secret.as_deref(), Some("legacy-secret"));
         assert_eq!(cfg.jwks_url.as_deref(), Some("https://legacy.example/jwks"));
@@ -611,14 +1073,48 @@ mod tests {
             Some("https://issuer.legacy")
         );
         assert_eq!(cfg.expected_audience.as_deref(), Some("legacy-audience"));
+        assert_eq!(cfg.jwt_time_validation, JwtTimeValidation::default());
 
         clear_auth_env();
     }
 
-    #[test]
-    fn jwks_mode_validates_rs256_token() {
-        let jwks_body = build_jwks_json("rsa-key-1");
-        let (jwks_url, _payload, _request_count, shutdown_tx) = spawn_jwks_server(jwks_body);
+    #[tokio::test]
+    async fn jwks_tokens_from_a_skewed_issuer_clock_honour_the_leeway() {
+        let idp = FakeIdp::spawn();
+        let mut cfg = AuthRuntimeConfig {
+            enabled: true,
+            jwks_url: Some(idp.jwks_url()),
+            jwks_provider: Some(Arc::new(JwksProvider::new(
+                idp.jwks_url(),
+                300,
+                default_jwks_algorithms(),
+            ))),
+            ..AuthRuntimeConfig::default()
+        };
+        let token = idp.token().not_before_in(Duration::from_secs(30)).mint();
+        let header = format!("Bearer {token}");
+
+        cfg.authenticate_authorization_value_str(&header)
+            .await
+            .expect("nbf within the default leeway should validate");
+
+        cfg.jwt_time_validation.leeway_secs = 5;
+        let err = cfg
+            .authenticate_authorization_value_str(&header)
+            .await
+            .expect_err("nbf beyond the leeway should fail");
+        assert_eq!(err.reason(), "token is not valid yet (nbf)");
+
+        cfg.jwt_time_validation.validate_nbf = false;
+        cfg.authenticate_authorization_value_str(&header)
+            .await
+            .expect("nbf is ignored when its validation is off");
+    }
+
+    #[tokio::test]
+    async fn jwks_mode_validates_rs256_token() {
+        let idp = FakeIdp::spawn();
+        let jwks_url = idp.jwks_url();
 
         let cfg = AuthRuntimeConfig {
             enabled: true,
@@ -626,6 +1122,7 @@ mod tests {
             jwks_url: Some(jwks_url.clone()),
             jwks_refresh_secs: 300,
             jwks_allowed_algorithms: default_jwks_algorithms(),
+            public_key: None,
             expected_issuer: Some("https://issuer.local".to_string()),
             expected_audience: Some("openportio-api".to_string()),
             jwks_provider: Some(Arc::new(JwksProvider::new(
@@ -633,24 +1130,27 @@ mod tests {
                 300,
                 default_jwks_algorithms(),
             ))),
+            revocation: None,
+            clock: None,
+            jwt_time_validation: JwtTimeValidation::default(),
         };
 
-        let token = build_rs256_token("rsa-key-1", "https://issuer.local", "openportio-api");
+        let token = build_rs256_token(&idp, &idp.active_kid());
         let principal = cfg
             .authenticate_authorization_value_str(&format!("Bearer {token}"))
+            .await
             .expect("jwks token should validate");
 
         assert_eq!(principal.subject, "user-1");
         assert_eq!(principal.issuer.as_deref(), Some("https://issuer.local"));
         assert!(principal.audience.iter().any(|aud| aud == "openportio-api"));
-
-        let _ = shutdown_tx.send(());
     }
 
-    #[test]
-    fn jwks_refresh_failure_uses_cached_keys() {
-        let jwks_body = build_jwks_json("rsa-key-1");
-        let (jwks_url, payload, _request_count, shutdown_tx) = spawn_jwks_server(jwks_body);
+    #[tokio::test]
+    async fn jwks_refresh_failure_uses_cached_keys() {
+        let idp = FakeIdp::spawn();
+        let clock = TestClock::new();
+        let jwks_url = idp.jwks_url();
 
         let provider = Arc::new(JwksProvider::new(
             jwks_url.clone(),
@@ -663,34 +1163,79 @@ mod tests {
             jwks_url: Some(jwks_url),
             jwks_refresh_secs: 1,
             jwks_allowed_algorithms: default_jwks_algorithms(),
+            public_key: None,
             expected_issuer: Some("https://issuer.local".to_string()),
             expected_audience: Some("openportio-api".to_string()),
             jwks_provider: Some(Arc::clone(&provider)),
+            revocation: None,
+            clock: Some(clock.shared()),
+            jwt_time_validation: JwtTimeValidation::default(),
         };
 
-        let token = build_rs256_token("rsa-key-1", "https://issuer.local", "openportio-api");
+        let token = build_rs256_token(&idp, &idp.active_kid());
         cfg.authenticate_authorization_value_str(&format!("Bearer {token}"))
+            .await
             .expect("initial validation should work");
 
-        {
-            let mut guard = payload.lock().expect("payload lock");
-            *guard = "{ invalid-json".to_string();
-        }
+        idp.fail_with(Failure::Malformed);
 
-        std::thread::sleep(Duration::from_millis(1100));
+        clock.advance(Duration::from_secs(1));
 
         let principal = cfg
             .authenticate_authorization_value_str(&format!("Bearer {token}"))
+            .await
             .expect("cached key should survive jwks refresh failure");
         assert_eq!(principal.subject, "user-1");
+    }
+
+    #[tokio::test]
+    async fn jwks_upstream_outage_keeps_cached_keys_until_rotation_is_fetched() {
+        let idp = FakeIdp::spawn();
+        let clock = TestClock::new();
+        let jwks_url = idp.jwks_url();
+        let cfg = AuthRuntimeConfig {
+            enabled: true,
+            jwt_secret: None,
+            jwks_url: Some(jwks_url.clone()),
+            jwks_refresh_secs: 1,
+            jwks_allowed_algorithms: default_jwks_algorithms(),
+            public_key: None,
+            expected_issuer: Some("https://issuer.local".to_string()),
+            expected_audience: Some("openportio-api".to_string()),
+            jwks_provider: Some(Arc::new(JwksProvider::new(
+                jwks_url,
+                1,
+                default_jwks_algorithms(),
+            ))),
+            revocation: None,
+            clock: Some(clock.shared()),
+            jwt_time_validation: JwtTimeValidation::default(),
+        };
+
+        let old_token = build_rs256_token(&idp, &idp.active_kid());
+        cfg.authenticate_authorization_value_str(&format!("Bearer {old_token}"))
+            .await
+            .expect("initial validation should work");
 
-        let _ = shutdown_tx.send(());
+        let new_kid = idp.rotate_key();
+        let new_token = build_rs256_token(&idp, &new_kid);
+        idp.fail_next(Failure::Status(503), 1);
+        clock.advance(Duration::from_secs(1));
+
+        cfg.authenticate_authorization_value_str(&format!("Bearer {old_token}"))
+            .await
+            .expect("cached key should survive a 503 from the jwks endpoint");
+        let principal = cfg
+            .authenticate_authorization_value_str(&format!("Bearer {new_token}"))
+            .await
+            .expect("rotated key should be fetched once the endpoint recovers");
+        assert_eq!(principal.subject, "user-1");
     }
 
-    #[test]
-    fn jwks_rejects_unknown_kid() {
-        let jwks_body = build_jwks_json("rsa-key-1");
-        let (jwks_url, _payload, _request_count, shutdown_tx) = spawn_jwks_server(jwks_body);
+    #[tokio::test]
+    async fn jwks_rejects_unknown_kid() {
+        let idp = FakeIdp::spawn();
+        let jwks_url = idp.jwks_url();
 
         let cfg = AuthRuntimeConfig {
             enabled: true,
@@ -698,6 +1243,7 @@ mod tests {
             jwks_url: Some(jwks_url.clone()),
             jwks_refresh_secs: 300,
             jwks_allowed_algorithms: default_jwks_algorithms(),
+            public_key: None,
             expected_issuer: Some("https://issuer.local".to_string()),
             expected_audience: Some("openportio-api".to_string()),
             jwks_provider: Some(Arc::new(JwksProvider::new(
@@ -705,11 +1251,15 @@ mod tests {
                 300,
                 default_jwks_algorithms(),
             ))),
+            revocation: None,
+            clock: None,
+            jwt_time_validation: JwtTimeValidation::default(),
         };
 
-        let token = build_rs256_token("unknown-key", "https://issuer.local", "openportio-api");
+        let token = build_rs256_token(&idp, "unknown-key");
         let err = cfg
             .authenticate_authorization_value_str(&format!("Bearer {token}"))
+            .await
             .expect_err("unknown kid must fail");
 
         match err {
@@ -718,14 +1268,12 @@ mod tests {
             }
             other => panic!("unexpected error: {other:?}"),
         }
-
-        let _ = shutdown_tx.send(());
     }
 
-    #[test]
-    fn jwks_unknown_kid_does_not_force_immediate_refresh() {
-        let jwks_body = build_jwks_json("rsa-key-1");
-        let (jwks_url, _payload, request_count, shutdown_tx) = spawn_jwks_server(jwks_body);
+    #[tokio::test]
+    async fn jwks_unknown_kid_does_not_force_immediate_refresh() {
+        let idp = FakeIdp::spawn();
+        let jwks_url = idp.jwks_url();
 
         let cfg = AuthRuntimeConfig {
             enabled: true,
@@ -733,6 +1281,7 @@ mod tests {
             jwks_url: Some(jwks_url.clone()),
             jwks_refresh_secs: 300,
             jwks_allowed_algorithms: default_jwks_algorithms(),
+            public_key: None,
             expected_issuer: Some("https://issuer.local".to_string()),
             expected_audience: Some("openportio-api".to_string()),
             jwks_provider: Some(Arc::new(JwksProvider::new(
@@ -740,33 +1289,36 @@ mod tests {
                 300,
                 default_jwks_algorithms(),
             ))),
+            revocation: None,
+            clock: None,
+            jwt_time_validation: JwtTimeValidation::default(),
         };
 
-        let known_token = build_rs256_token("rsa-key-1", "https://issuer.local", "openportio-api");
+        let known_token = build_rs256_token(&idp, &idp.active_kid());
         cfg.authenticate_authorization_value_str(&format!("Bearer {known_token}"))
+            .await
             .expect("known kid should validate");
-        let before_unknown = request_count.load(Ordering::SeqCst);
+        let before_unknown = idp.jwks_requests();
 
-        let unknown_token =
-            build_rs256_token("unknown-key", "https://issuer.local", "openportio-api");
+        let unknown_token = build_rs256_token(&idp, "unknown-key");
         let err = cfg
             .authenticate_authorization_value_str(&format!("Bearer {unknown_token}"))
+            .await
             .expect_err("unknown kid must fail");
         assert!(matches!(err, AuthRejection::InvalidToken(_)));
 
-        let after_unknown = request_count.load(Ordering::SeqCst);
+        let after_unknown = idp.jwks_requests();
         assert_eq!(
             before_unknown, after_unknown,
             "unknown kid must not trigger an out-of-interval jwks refresh"
         );
-
-        let _ = shutdown_tx.send(());
     }
 
     #[test]
     fn jwks_concurrent_refresh_performs_single_fetch() {
-        let jwks_body = build_jwks_json("rsa-key-1");
-        let (jwks_url, _payload, request_count, shutdown_tx) = spawn_jwks_server(jwks_body);
+        let idp = FakeIdp::spawn();
+        let clock = TestClock::new();
+        let jwks_url = idp.jwks_url();
 
         let cfg = Arc::new(AuthRuntimeConfig {
             enabled: true,
@@ -774,6 +1326,7 @@ mod tests {
             jwks_url: Some(jwks_url.clone()),
             jwks_refresh_secs: 1,
             jwks_allowed_algorithms: default_jwks_algorithms(),
+            public_key: None,
             expected_issuer: Some("https://issuer.local".to_string()),
             expected_audience: Some("openportio-api".to_string()),
             jwks_provider: Some(Arc::new(JwksProvider::new(
@@ -781,22 +1334,31 @@ mod tests {
                 1,
                 default_jwks_algorithms(),
             ))),
+            revocation: None,
+            clock: Some(clock.shared()),
+            jwt_time_validation: JwtTimeValidation::default(),
         });
 
-        let token = build_rs256_token("rsa-key-1", "https://issuer.local", "openportio-api");
+        let token = build_rs256_token(&idp, &idp.active_kid());
         let auth_header = format!("Bearer {token}");
-        cfg.authenticate_authorization_value_str(&auth_header)
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .expect("test runtime")
+            .block_on(cfg.authenticate_authorization_value_str(&auth_header))
             .expect("initial call should warm cache");
-        let before = request_count.load(Ordering::SeqCst);
+        let before = idp.jwks_requests();
 
-        std::thread::sleep(Duration::from_millis(1_100));
+        clock.advance(Duration::from_secs(1));
 
         let mut workers = Vec::new();
         for _ in 0..8 {
             let cfg = Arc::clone(&cfg);
             let auth_header = auth_header.clone();
             workers.push(thread::spawn(move || {
-                cfg.authenticate_authorization_value_str(&auth_header)
+                tokio::runtime::Builder::new_current_thread()
+                    .build()
+                    .expect("worker runtime")
+                    .block_on(cfg.authenticate_authorization_value_str(&auth_header))
                     .expect("concurrent auth call should succeed");
             }));
         }
@@ -805,18 +1367,17 @@ mod tests {
             worker.join().expect("worker thread should join");
         }
 
-        let after = request_count.load(Ordering::SeqCst);
+        let after = idp.jwks_requests();
         assert_eq!(
             after,
             before + 1,
             "only one refresh fetch should occur per refresh interval under concurrency"
         );
-
-        let _ = shutdown_tx.send(());
     }
 
-    #[test]
-    fn jwks_unreachable_endpoint_returns_misconfigured() {
+    #[tokio::test]
+    async fn jwks_unreachable_endpoint_returns_misconfigured() {
+        let idp = FakeIdp::spawn();
         // Port 9 is traditionally discard service and is expected to be closed in local tests.
         let jwks_url = "http://127.0.0.1:9/jwks".to_string();
         let cfg = AuthRuntimeConfig {
@@ -825,6 +1386,7 @@ mod tests {
             jwks_url: Some(jwks_url.clone()),
             jwks_refresh_secs: 300,
             jwks_allowed_algorithms: default_jwks_algorithms(),
+            public_key: None,
             expected_issuer: Some("https://issuer.local".to_string()),
             expected_audience: Some("openportio-api".to_string()),
             jwks_provider: Some(Arc::new(JwksProvider::new(
@@ -832,11 +1394,15 @@ mod tests {
                 300,
                 default_jwks_algorithms(),
             ))),
+            revocation: None,
+            clock: None,
+            jwt_time_validation: JwtTimeValidation::default(),
         };
 
-        let token = build_rs256_token("rsa-key-1", "https://issuer.local", "openportio-api");
+        let token = build_rs256_token(&idp, &idp.active_kid());
         let err = cfg
             .authenticate_authorization_value_str(&format!("Bearer {token}"))
+            .await
             .expect_err("unreachable jwks endpoint should fail");
 
         match err {
@@ -847,16 +1413,18 @@ mod tests {
         }
     }
 
-    #[test]
-    fn jwks_malformed_payload_without_cache_returns_misconfigured() {
-        let (jwks_url, _payload, _request_count, shutdown_tx) =
-            spawn_jwks_server("{ invalid-json".to_string());
+    #[tokio::test]
+    async fn jwks_malformed_payload_without_cache_returns_misconfigured() {
+        let idp = FakeIdp::spawn();
+        idp.fail_with(Failure::Malformed);
+        let jwks_url = idp.jwks_url();
         let cfg = AuthRuntimeConfig {
             enabled: true,
             jwt_secret: None,
             jwks_url: Some(jwks_url.clone()),
             jwks_refresh_secs: 300,
             jwks_allowed_algorithms: default_jwks_algorithms(),
+            public_key: None,
             expected_issuer: Some("https://issuer.local".to_string()),
             expected_audience: Some("openportio-api".to_string()),
             jwks_provider: Some(Arc::new(JwksProvider::new(
@@ -864,11 +1432,15 @@ mod tests {
                 300,
                 default_jwks_algorithms(),
             ))),
+            revocation: None,
+            clock: None,
+            jwt_time_validation: JwtTimeValidation::default(),
         };
 
-        let token = build_rs256_token("rsa-key-1", "https://issuer.local", "openportio-api");
+        let token = build_rs256_token(&idp, &idp.active_kid());
         let err = cfg
             .authenticate_authorization_value_str(&format!("Bearer {token}"))
+            .await
             .expect_err("malformed jwks payload should fail");
 
         match err {
@@ -877,20 +1449,20 @@ mod tests {
             }
             other => panic!("unexpected error: {other:?}"),
         }
-
-        let _ = shutdown_tx.send(());
     }
 
-    #[test]
-    fn jwks_rejects_oversized_payload() {
-        let oversized = "a".repeat(MAX_JWKS_RESPONSE_BYTES + 16);
-        let (jwks_url, _payload, _request_count, shutdown_tx) = spawn_jwks_server(oversized);
+    #[tokio::test]
+    async fn jwks_rejects_oversized_payload() {
+        let idp = FakeIdp::spawn();
+        idp.fail_with(Failure::Payload("a".repeat(MAX_JWKS_RESPONSE_BYTES + 16)));
+        let jwks_url = idp.jwks_url();
         let cfg = AuthRuntimeConfig {
             enabled: true,
             jwt_secret: None,
             jwks_url: Some(jwks_url.clone()),
             jwks_refresh_secs: 300,
             jwks_allowed_algorithms: default_jwks_algorithms(),
+            public_key: None,
             expected_issuer: Some("https://issuer.local".to_string()),
             expected_audience: Some("openportio-api".to_string()),
             jwks_provider: Some(Arc::new(JwksProvider::new(
@@ -898,11 +1470,15 @@ mod tests {
                 300,
                 default_jwks_algorithms(),
             ))),
+            revocation: None,
+            clock: None,
+            jwt_time_validation: JwtTimeValidation::default(),
         };
 
-        let token = build_rs256_token("rsa-key-1", "https://issuer.local", "openportio-api");
+        let token = build_rs256_token(&idp, &idp.active_kid());
         let err = cfg
             .authenticate_authorization_value_str(&format!("Bearer {token}"))
+            .await
             .expect_err("oversized jwks payload should fail");
 
         match err {
@@ -914,85 +1490,257 @@ mod tests {
             }
             other => panic!("unexpected error: {other:?}"),
         }
-
-        let _ = shutdown_tx.send(());
     }
 
-    fn build_rs256_token(kid: &str, issuer: &str, audience: &str) -> String {
-        let mut header = Header::new(Algorithm::RS256);
-        header.kid = Some(kid.to_string());
-        let claims = JwtClaims {
-            sub: "user-1".to_string(),
-            exp: 4_102_444_800,
-            iss: Some(issuer.to_string()),
-            aud: Some(AudienceClaim::One(audience.to_string())),
-            scope: Some("read:notes".to_string()),
+    #[tokio::test]
+    async fn static_public_keys_verify_es256_and_eddsa_tokens() {
+        use jsonwebtoken::{encode, EncodingKey, Header};
+
+        let mint = |alg: Algorithm, key: EncodingKey, issuer: &str| {
+            let claims = serde_json::json!({
+                "sub": "svc-billing",
+                "exp": 4_102_444_800_u64,
+                "iss": issuer,
+                "aud": "openportio-api",
+            });
+            format!(
+                "Bearer {}",
+                encode(&Header::new(alg), &claims, &key).expect("token should encode")
+            )
         };
-        let encoding_key = EncodingKey::from_rsa_der(TEST_RSA_PRIVATE_KEY_DER);
-        encode(&header, &claims, &encoding_key).expect("token should encode")
-    }
-
-    fn build_jwks_json(kid: &str) -> String {
-        let encoding_key = EncodingKey::from_rsa_der(TEST_RSA_PRIVATE_KEY_DER);
-        let mut jwk = Jwk::from_encoding_key(&encoding_key, Algorithm::RS256)
-            .expect("jwk should be generated");
-        jwk.common.key_id = Some(kid.to_string());
-        serde_json::to_string(&JwkSet { keys: vec![jwk] }).expect("jwks should serialize")
-    }
-
-    fn spawn_jwks_server(
-        initial_payload: String,
-    ) -> (
-        String,
-        Arc<Mutex<String>>,
-        Arc<AtomicUsize>,
-        mpsc::Sender<()>,
-    ) {
-        let listener = TcpListener::bind("127.0.0.1:0").expect("listener should bind");
-        listener
-            .set_nonblocking(true)
-            .expect("listener should be nonblocking");
-        let addr = listener.local_addr().expect("addr should resolve");
-
-        let payload = Arc::new(Mutex::new(initial_payload));
-        let payload_for_server = Arc::clone(&payload);
-        let request_count = Arc::new(AtomicUsize::new(0));
-        let request_count_for_server = Arc::clone(&request_count);
-        let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>();
-
-        thread::spawn(move || loop {
-            if shutdown_rx.try_recv().is_ok() {
-                break;
+        let es256 = mint(
+            Algorithm::ES256,
+            EncodingKey::from_ec_der(EC_P256_PRIVATE_KEY),
+            "https://issuer.local",
+        );
+        let eddsa = mint(
+            Algorithm::EdDSA,
+            EncodingKey::from_ed_der(ED25519_PRIVATE_KEY),
+            "https://issuer.local",
+        );
+        let configured = |key: StaticPublicKey| {
+            AuthRuntimeConfig {
+                enabled: true,
+                expected_issuer: Some("https://issuer.local".to_string()),
+                expected_audience: Some("openportio-api".to_string()),
+                ..AuthRuntimeConfig::default()
             }
+            .with_public_key(key)
+        };
 
-            match listener.accept() {
-                Ok((mut stream, _)) => {
-                    request_count_for_server.fetch_add(1, Ordering::SeqCst);
-                    let mut request_buffer = [0_u8; 1024];
-                    let _ = stream.read(&mut request_buffer);
-
-                    let body = payload_for_server.lock().expect("payload lock").clone();
-                    let response = format!(
-                            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
-                            body.len(),
-                            body
-                        );
-                    let _ = stream.write_all(response.as_bytes());
-                    let _ = stream.flush();
-                }
-                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {
-                    thread::sleep(Duration::from_millis(20));
-                }
-                Err(_) => break,
+        let ec = StaticPublicKey::from_pem(EC_P256_PUBLIC_PEM, None).expect("ec key");
+        assert_eq!(ec.algorithm(), Algorithm::ES256);
+        let cfg = configured(ec);
+        assert_eq!(cfg.mode(), "public_key");
+        let principal = cfg
+            .authenticate_authorization_value_str(&es256)
+            .await
+            .expect("es256 token should validate");
+        assert_eq!(principal.subject, "svc-billing");
+        assert!(cfg
+            .authenticate_authorization_value_str(&eddsa)
+            .await
+            .is_err());
+        let err = cfg
+            .authenticate_authorization_value_str(&mint(
+                Algorithm::ES256,
+                EncodingKey::from_ec_der(EC_P256_PRIVATE_KEY),
+                "https://other.local",
+            ))
+            .await
+            .expect_err("issuer is still checked");
+        assert!(matches!(err, AuthRejection::InvalidToken(message) if message.contains("issuer")));
+
+        let ed = StaticPublicKey::from_pem(ED25519_PUBLIC_PEM, None).expect("ed key");
+        assert_eq!(ed.algorithm(), Algorithm::EdDSA);
+        let cfg = configured(ed);
+        cfg.authenticate_authorization_value_str(&eddsa)
+            .await
+            .expect("eddsa token should validate");
+        assert!(cfg
+            .authenticate_authorization_value_str(&es256)
+            .await
+            .is_err());
+
+        assert!(StaticPublicKey::from_pem(EC_P256_PUBLIC_PEM, Some(Algorithm::RS256)).is_err());
+        assert!(StaticPublicKey::from_pem(b"not a pem", None).is_err());
+    }
+
+    #[test]
+    fn malformed_auth_settings_are_errors_not_defaults() {
+        let _guard = ENV_LOCK.lock().expect("env lock");
+        clear_auth_env();
+
+        env::set_var("MELD_AUTH_JWKS_ALGORITHMS", "HS256");
+        assert_eq!(
+            AuthRuntimeConfig::from_env().err().as_deref(),
+            Some("`MELD_AUTH_JWKS_ALGORITHMS` lists no supported algorithms")
+        );
+        env::remove_var("MELD_AUTH_JWKS_ALGORITHMS");
+
+        env::set_var("OPENPORTIO_AUTH_ENABLED", "yes");
+        assert_eq!(
+            AuthRuntimeConfig::from_env().err().as_deref(),
+            Some("`OPENPORTIO_AUTH_ENABLED` has an invalid value `yes`")
+        );
+        assert_eq!(
+            AuthRuntimeConfig::or_reject_all(AuthRuntimeConfig::from_env()).mode(),
+            "unconfigured"
+        );
+
+        clear_auth_env();
+    }
+
+    #[test]
+    fn from_env_loads_public_key_from_path() {
+        let _guard = ENV_LOCK.lock().expect("env lock");
+        clear_auth_env();
+
+        env::set_var("MELD_AUTH_ENABLED", "true");
+        env::set_var(
+            "OPENPORTIO_AUTH_PUBLIC_KEY_PATH",
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/ed25519_public.pem"
+            ),
+        );
+        let cfg = AuthRuntimeConfig::from_env().expect("auth settings parse");
+        assert_eq!(cfg.mode(), "public_key");
+        assert_eq!(
+            cfg.public_key.as_ref().map(StaticPublicKey::algorithm),
+            Some(Algorithm::EdDSA)
+        );
+
+        env::set_var("OPENPORTIO_AUTH_PUBLIC_KEY_ALGORITHM", "ES256");
+        let err = AuthRuntimeConfig::from_env().expect_err("mismatched algorithms are rejected");
+        assert!(err.starts_with("invalid auth public key"), "{err}");
+
+        clear_auth_env();
+    }
+
+    #[test]
+    fn public_key_env_does_not_touch_time_validation() {
+        let _guard = ENV_LOCK.lock().expect("env lock");
+        clear_auth_env();
+
+        let pem = std::fs::read_to_string(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/ed25519_public.pem"
+        ))
+        .unwrap();
+        env::set_var("OPENPORTIO_AUTH_ENABLED", "true");
+        env::set_var("OPENPORTIO_AUTH_PUBLIC_KEY_PEM", pem);
+        env::set_var("OPENPORTIO_AUTH_PUBLIC_KEY_ALGORITHM", "EdDSA");
+        let cfg = AuthRuntimeConfig::from_env().expect("auth settings parse");
+        assert_eq!(cfg.mode(), "public_key");
+        assert_eq!(
+            cfg.jwt_time_validation.validate_iat,
+            JwtTimeValidation::default().validate_iat
+        );
+
+        clear_auth_env();
+    }
+
+    #[tokio::test]
+    async fn revoked_token_ids_are_rejected_and_store_errors_fail_closed() {
+        struct UnavailableStore;
+
+        #[axum::async_trait]
+        impl TokenRevocationStore for UnavailableStore {
+            async fn is_revoked(&self, _jti: &str) -> Result<bool, String> {
+                Err("connection refused".to_string())
             }
-        });
 
-        (
-            format!("http://{addr}/jwks"),
-            payload,
-            request_count,
-            shutdown_tx,
-        )
+            async fn revoke(&self, _jti: &str, _expires_at: Option<u64>) -> Result<(), String> {
+                Err("connection refused".to_string())
+            }
+        }
+
+        let idp = FakeIdp::spawn();
+        let store = crate::revocation::InMemoryRevocationStore::new();
+        store.revoke("revoked-jti", None).await.expect("revoke");
+        let cfg = AuthRuntimeConfig {
+            enabled: true,
+            jwks_url: Some(idp.jwks_url()),
+            jwks_provider: Some(Arc::new(JwksProvider::new(
+                idp.jwks_url(),
+                300,
+                default_jwks_algorithms(),
+            ))),
+            ..AuthRuntimeConfig::default()
+        }
+        .with_revocation_store(store);
+        let token_with = |jti: &str| idp.token().kid(idp.active_kid()).claim("jti", jti).mint();
+
+        let principal = cfg
+            .authenticate_authorization_value_str(&format!("Bearer {}", token_with("live-jti")))
+            .await
+            .expect("unrevoked token should validate");
+        assert_eq!(principal.token_id.as_deref(), Some("live-jti"));
+
+        let err = cfg
+            .authenticate_authorization_value_str(&format!("Bearer {}", token_with("revoked-jti")))
+            .await
+            .expect_err("revoked token should be rejected");
+        assert!(matches!(err, AuthRejection::InvalidToken(message) if message.contains("revoked")));
+
+        let err = cfg
+            .clone()
+            .with_revocation_store(UnavailableStore)
+            .authenticate_authorization_value_str(&format!("Bearer {}", token_with("live-jti")))
+            .await
+            .expect_err("store outage should fail closed");
+        assert!(matches!(err, AuthRejection::Misconfigured(_)));
+    }
+
+    #[tokio::test]
+    async fn required_auth_checks_expiry_against_the_clock_dependency() {
+        use axum::{middleware::from_fn_with_state, routing::get, Router};
+        use tower::ServiceExt;
+
+        let idp = FakeIdp::spawn();
+        let clock = TestClock::new();
+        let cfg = AuthRuntimeConfig {
+            enabled: true,
+            jwks_url: Some(idp.jwks_url()),
+            jwks_provider: Some(Arc::new(JwksProvider::new(
+                idp.jwks_url(),
+                300,
+                default_jwks_algorithms(),
+            ))),
+            ..AuthRuntimeConfig::default()
+        };
+        let app = crate::di::with_dependency(
+            Router::new()
+                .route("/notes", get(|| async { "ok" }))
+                .layer(from_fn_with_state(cfg, required_auth_middleware)),
+            clock.shared(),
+        );
+        let token = idp.token().expires_in(Duration::from_secs(60)).mint();
+        let call = || {
+            app.clone().oneshot(
+                Request::builder()
+                    .uri("/notes")
+                    .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        assert_eq!(call().await.unwrap().status(), StatusCode::OK);
+        // exp plus the 60s leeway
+        clock.advance(Duration::from_secs(121));
+        assert_eq!(call().await.unwrap().status(), StatusCode::UNAUTHORIZED);
+    }
+
+    fn build_rs256_token(idp: &FakeIdp, kid: &str) -> String {
+        idp.token()
+            .kid(kid)
+            .alg(Algorithm::RS256)
+            .issuer("https://issuer.local")
+            .audience("openportio-api")
+            .scope("read:notes")
+            .mint()
     }
 
     fn clear_auth_env() {
@@ -1004,6 +1752,13 @@ mod tests {
             "OPENPORTIO_AUTH_JWKS_ALGORITHMS",
             "OPENPORTIO_AUTH_ISSUER",
             "OPENPORTIO_AUTH_AUDIENCE",
+            "OPENPORTIO_AUTH_REVOCATION_REDIS_URL",
+            "OPENPORTIO_AUTH_JWT_LEEWAY_SECS",
+            "OPENPORTIO_AUTH_JWT_VALIDATE_NBF",
+            "OPENPORTIO_AUTH_JWT_VALIDATE_IAT",
+            "OPENPORTIO_AUTH_PUBLIC_KEY_PEM",
+            "OPENPORTIO_AUTH_PUBLIC_KEY_PATH",
+            "OPENPORTIO_AUTH_PUBLIC_KEY_ALGORITHM",
             "MELD_AUTH_ENABLED",
             "MELD_AUTH_JWT_SECRET",
             "MELD_AUTH_JWKS_URL",
@@ -1011,6 +1766,13 @@ mod tests {
             "MELD_AUTH_JWKS_ALGORITHMS",
             "MELD_AUTH_ISSUER",
             "MELD_AUTH_AUDIENCE",
+            "MELD_AUTH_REVOCATION_REDIS_URL",
+            "MELD_AUTH_JWT_LEEWAY_SECS",
+            "MELD_AUTH_JWT_VALIDATE_NBF",
+            "MELD_AUTH_JWT_VALIDATE_IAT",
+            "MELD_AUTH_PUBLIC_KEY_PEM",
+            "MELD_AUTH_PUBLIC_KEY_PATH",
+            "MELD_AUTH_PUBLIC_KEY_ALGORITHM",
             "ALLOY_AUTH_ENABLED",
             "ALLOY_AUTH_JWT_SECRET",
             "ALLOY_AUTH_JWKS_URL",
@@ -1018,6 +1780,13 @@ mod tests {
             "ALLOY_AUTH_JWKS_ALGORITHMS",
             "ALLOY_AUTH_ISSUER",
             "ALLOY_AUTH_AUDIENCE",
+            "ALLOY_AUTH_REVOCATION_REDIS_URL",
+            "ALLOY_AUTH_JWT_LEEWAY_SECS",
+            "ALLOY_AUTH_JWT_VALIDATE_NBF",
+            "ALLOY_AUTH_JWT_VALIDATE_IAT",
+            "ALLOY_AUTH_PUBLIC_KEY_PEM",
+            "ALLOY_AUTH_PUBLIC_KEY_PATH",
+            "ALLOY_AUTH_PUBLIC_KEY_ALGORITHM",
         ] {
             env::remove_var(key);
         }
@@ -0,0 +1,483 @@
+use std::{
+    fmt,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use axum::{
+    body::{Body, BodyDataStream, Bytes},
+    extract::{FromRequest, Request},
+    http::{header, StatusCode},
+    Json,
+};
+use futures_util::{Stream, StreamExt};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    api::{ApiError, ApiErrorResponse},
+    files::{too_large, upload_error, UploadLimits},
+};
+
+type ChunkCheck = Box<dyn FnMut(&Bytes) -> Result<(), ApiError> + Send>;
+
+/// A request body handed to the handler as it arrives instead of buffered: chunks are pulled
+/// only when the handler asks for the next one, so a slow consumer slows the client down.
+/// The total is capped by [`UploadLimits::max_bytes`] (the shared request body limit applies
+/// first); [`Self::with_chunk_check`] hooks run on every chunk, and returning an error from any
+/// of them, or from the handler, stops reading the rest of the body.
+///
+/// Yields `Result<Bytes, ApiError>`; [`Self::ndjson`] and [`Self::csv`] decode records on top.
+pub struct BodyStream {
+    body: BodyDataStream,
+    content_type: Option<String>,
+    max_bytes: u64,
+    max_line_bytes: usize,
+    received: u64,
+    checks: Vec<ChunkCheck>,
+    failed: bool,
+}
+
+impl BodyStream {
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    /// Bytes received so far.
+    pub fn bytes_read(&self) -> u64 {
+        self.received
+    }
+
+    /// Runs `check` on every chunk before it is yielded; an error ends the stream with it.
+    pub fn with_chunk_check(
+        mut self,
+        check: impl FnMut(&Bytes) -> Result<(), ApiError> + Send + 'static,
+    ) -> Self {
+        self.checks.push(Box::new(check));
+        self
+    }
+
+    /// One JSON value per line; blank lines are skipped. Lines longer than the upload memory
+    /// threshold are rejected rather than buffered.
+    pub fn ndjson<T: DeserializeOwned>(self) -> NdjsonReader<T> {
+        NdjsonReader {
+            lines: Lines::new(self),
+            _record: PhantomData,
+        }
+    }
+
+    /// RFC 4180 records: comma separated, `"` quoted fields (which may span lines) with `""`
+    /// escapes. The header row, if any, is returned like any other record.
+    pub fn csv(self) -> CsvReader {
+        CsvReader {
+            lines: Lines::new(self),
+        }
+    }
+}
+
+impl fmt::Debug for BodyStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BodyStream")
+            .field("content_type", &self.content_type)
+            .field("max_bytes", &self.max_bytes)
+            .field("received", &self.received)
+            .finish()
+    }
+}
+
+impl Stream for BodyStream {
+    type Item = Result<Bytes, ApiError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.failed {
+            return Poll::Ready(None);
+        }
+        let chunk = match futures_util::ready!(self.body.poll_next_unpin(cx)) {
+            Some(Ok(chunk)) => chunk,
+            Some(Err(err)) => {
+                self.failed = true;
+                return Poll::Ready(Some(Err(upload_error(
+                    StatusCode::BAD_REQUEST,
+                    "bad_request",
+                    format!("request body could not be read: {err}"),
+                ))));
+            }
+            None => return Poll::Ready(None),
+        };
+        self.received += chunk.len() as u64;
+        let checked = if self.received > self.max_bytes {
+            Err(too_large(self.max_bytes))
+        } else {
+            self.checks.iter_mut().try_for_each(|check| check(&chunk))
+        };
+        if let Err(err) = checked {
+            self.failed = true;
+            return Poll::Ready(Some(Err(err)));
+        }
+        Poll::Ready(Some(Ok(chunk)))
+    }
+}
+
+#[axum::async_trait]
+impl<S> FromRequest<S> for BodyStream
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, _state: &S) -> Result<Self, Self::Rejection> {
+        let limits: UploadLimits =
+            crate::di::resolve_from_extensions(req.extensions()).unwrap_or_default();
+        let declared = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok());
+        if declared.is_some_and(|len| len > limits.max_bytes()) {
+            return Err(too_large(limits.max_bytes()));
+        }
+        let content_type = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        Ok(Self {
+            body: req.into_body().into_data_stream(),
+            content_type,
+            max_bytes: limits.max_bytes(),
+            max_line_bytes: limits.memory_threshold(),
+            received: 0,
+            checks: Vec::new(),
+            failed: false,
+        })
+    }
+}
+
+/// Splits a [`BodyStream`] on `\n` (dropping a trailing `\r`), counting lines for errors.
+struct Lines {
+    body: BodyStream,
+    buffer: Vec<u8>,
+    line: usize,
+    done: bool,
+}
+
+impl Lines {
+    fn new(body: BodyStream) -> Self {
+        Self {
+            body,
+            buffer: Vec::new(),
+            line: 0,
+            done: false,
+        }
+    }
+
+    async fn next_line(&mut self) -> Option<Result<Vec<u8>, ApiError>> {
+        loop {
+            if let Some(end) = self.buffer.iter().position(|byte| *byte == b'\n') {
+                let rest = self.buffer.split_off(end + 1);
+                let mut line = std::mem::replace(&mut self.buffer, rest);
+                line.truncate(end);
+                if line.last() == Some(&b'\r') {
+                    line.pop();
+                }
+                self.line += 1;
+                return Some(Ok(line));
+            }
+            if self.buffer.len() > self.body.max_line_bytes {
+                self.done = true;
+                self.buffer.clear();
+                return Some(Err(record_error(
+                    "line_too_long",
+                    self.line + 1,
+                    format!("line exceeds {} bytes", self.body.max_line_bytes),
+                )));
+            }
+            if self.done {
+                if self.buffer.is_empty() {
+                    return None;
+                }
+                self.line += 1;
+                return Some(Ok(std::mem::take(&mut self.buffer)));
+            }
+            match self.body.next().await {
+                Some(Ok(chunk)) => self.buffer.extend_from_slice(&chunk),
+                Some(Err(err)) => {
+                    self.done = true;
+                    self.buffer.clear();
+                    return Some(Err(err));
+                }
+                None => self.done = true,
+            }
+        }
+    }
+}
+
+/// Records decoded from an NDJSON body by [`BodyStream::ndjson`].
+pub struct NdjsonReader<T> {
+    lines: Lines,
+    _record: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned> NdjsonReader<T> {
+    /// The next record; a malformed line is a `400` `invalid_ndjson` naming the line.
+    pub async fn next(&mut self) -> Option<Result<T, ApiError>> {
+        loop {
+            let line = match self.lines.next_line().await? {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err)),
+            };
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+            return Some(
+                serde_json::from_slice(&line).map_err(|err| {
+                    record_error("invalid_ndjson", self.lines.line, err.to_string())
+                }),
+            );
+        }
+    }
+
+    /// Lines read so far.
+    pub fn line(&self) -> usize {
+        self.lines.line
+    }
+}
+
+/// Records decoded from a CSV body by [`BodyStream::csv`].
+pub struct CsvReader {
+    lines: Lines,
+}
+
+impl CsvReader {
+    /// The next record's fields; a non-UTF-8 line or an unterminated quote is a `400`
+    /// `invalid_csv`.
+    pub async fn next(&mut self) -> Option<Result<Vec<String>, ApiError>> {
+        let mut pending = String::new();
+        loop {
+            let line = match self.lines.next_line().await {
+                Some(Ok(line)) => line,
+                Some(Err(err)) => return Some(Err(err)),
+                None if pending.is_empty() => return None,
+                None => {
+                    return Some(Err(record_error(
+                        "invalid_csv",
+                        self.lines.line,
+                        "unterminated quoted field".to_string(),
+                    )))
+                }
+            };
+            let Ok(line) = std::str::from_utf8(&line) else {
+                return Some(Err(record_error(
+                    "invalid_csv",
+                    self.lines.line,
+                    "line is not valid UTF-8".to_string(),
+                )));
+            };
+            if pending.is_empty() && line.is_empty() {
+                continue;
+            }
+            if !pending.is_empty() {
+                pending.push('\n');
+            }
+            pending.push_str(line);
+            if let Some(record) = parse_csv_record(&pending) {
+                return Some(Ok(record));
+            }
+        }
+    }
+
+    /// Lines read so far.
+    pub fn line(&self) -> usize {
+        self.lines.line
+    }
+}
+
+/// `None` while a quoted field is still open.
+fn parse_csv_record(text: &str) -> Option<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            (true, '"') => quoted = false,
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => fields.push(std::mem::take(&mut field)),
+            (_, c) => field.push(c),
+        }
+    }
+    if quoted {
+        return None;
+    }
+    fields.push(field);
+    Some(fields)
+}
+
+fn record_error(code: &str, line: usize, message: String) -> ApiError {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ApiErrorResponse {
+            code: code.to_string(),
+            message: format!("line {line}: {message}"),
+            detail: None,
+            details: Some(serde_json::json!({ "line": line })),
+        }),
+    )
+}
+
+/// Buffers `body` if it fits in `limit` bytes. A larger body, or one that fails part way, is
+/// handed back as an equivalent [`Body`] (the chunks read so far, then the rest) so callers
+/// that only buffer opportunistically can pass it on untouched.
+pub(crate) async fn buffer_within(body: Body, limit: usize) -> Result<Bytes, Body> {
+    if http_body::Body::size_hint(&body).lower() > limit as u64 {
+        return Err(body);
+    }
+    let mut stream = body.into_data_stream();
+    let mut chunks = Vec::new();
+    let mut total = 0;
+    while let Some(chunk) = stream.next().await {
+        let failed = match chunk {
+            Ok(chunk) => {
+                total += chunk.len();
+                chunks.push(Ok(chunk));
+                total > limit
+            }
+            Err(err) => {
+                chunks.push(Err(err));
+                true
+            }
+        };
+        if failed {
+            return Err(Body::from_stream(
+                futures_util::stream::iter(chunks).chain(stream),
+            ));
+        }
+    }
+    let mut buffered = Vec::with_capacity(total);
+    for chunk in chunks.into_iter().flatten() {
+        buffered.extend_from_slice(&chunk);
+    }
+    Ok(buffered.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, response::IntoResponse, routing::post, Router};
+    use serde::Deserialize;
+    use tower::ServiceExt;
+
+    #[derive(Deserialize)]
+    struct Event {
+        id: u32,
+    }
+
+    async fn sum_events(body: BodyStream) -> Result<String, ApiError> {
+        let mut events = body
+            .with_chunk_check(|chunk| match chunk.contains(&b'!') {
+                true => Err(upload_error(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "rejected_chunk",
+                    "no bangs".to_string(),
+                )),
+                false => Ok(()),
+            })
+            .ndjson::<Event>();
+        let mut sum = 0;
+        while let Some(event) = events.next().await {
+            sum += event?.id;
+        }
+        Ok(sum.to_string())
+    }
+
+    async fn call(app: Router, body: Body) -> (StatusCode, serde_json::Value) {
+        let response = app
+            .oneshot(Request::post("/").body(body).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = serde_json::from_slice(&bytes)
+            .unwrap_or_else(|_| serde_json::Value::String(String::from_utf8_lossy(&bytes).into()));
+        (status, body)
+    }
+
+    #[tokio::test]
+    async fn ndjson_records_stream_across_chunks_and_abort_on_errors() {
+        let app = crate::di::with_dependency_override(
+            Router::new().route("/", post(sum_events)),
+            UploadLimits::new().with_max_bytes(64),
+        );
+        let chunks = |parts: &'static [&'static str]| {
+            Body::from_stream(futures_util::stream::iter(
+                parts
+                    .iter()
+                    .map(|part| Ok::<_, std::io::Error>(Bytes::from_static(part.as_bytes()))),
+            ))
+        };
+
+        let (status, body) = call(
+            app.clone(),
+            chunks(&["{\"id\":1}\n{\"i", "d\":2}\r\n\n{\"id\":3}"]),
+        )
+        .await;
+        assert_eq!((status, body), (StatusCode::OK, serde_json::json!(6)));
+
+        let (status, body) =
+            call(app.clone(), chunks(&["{\"id\":1}\n", "oops\n{\"id\":2}\n"])).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "invalid_ndjson");
+        assert_eq!(body["details"]["line"], 2);
+
+        let (status, body) = call(app.clone(), chunks(&["{\"id\":1}\n", "!"])).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["code"], "rejected_chunk");
+
+        let (status, body) = call(app, Body::from("{\"id\":1}\n".repeat(10))).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(body["code"], "payload_too_large");
+    }
+
+    #[tokio::test]
+    async fn csv_records_handle_quotes_and_multiline_fields() {
+        let app = Router::new().route(
+            "/",
+            post(|body: BodyStream| async move {
+                let mut records = body.csv();
+                let mut rows = Vec::new();
+                while let Some(record) = records.next().await {
+                    match record {
+                        Ok(record) => rows.push(record),
+                        Err(err) => return err.into_response(),
+                    }
+                }
+                Json(rows).into_response()
+            }),
+        );
+
+        let (status, body) = call(
+            app.clone(),
+            Body::from("name,note\r\nada,\"says \"\"hi\"\"\"\nbob,\"two\nlines\",\n"),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body,
+            serde_json::json!([
+                ["name", "note"],
+                ["ada", "says \"hi\""],
+                ["bob", "two\nlines", ""]
+            ])
+        );
+
+        let (status, body) = call(app, Body::from("a,\"open\nnever closed")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "invalid_csv");
+    }
+}
@@ -0,0 +1,552 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, UNIX_EPOCH},
+};
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use openportio_core::MetricsSink;
+use serde::Deserialize;
+
+use crate::{
+    api::ApiErrorResponse,
+    audit::{AuditEvent, AuditEventKind},
+    auth::AuthRejection,
+    client_ip::ClientIp,
+    di::{clock_from_extensions, resolve_from_extensions},
+    middleware::is_grpc_request,
+};
+
+const DEFAULT_WINDOW: Duration = Duration::from_secs(15 * 60);
+const DEFAULT_DELAY_AFTER: u32 = 3;
+const DEFAULT_DELAY_STEP: Duration = Duration::from_millis(250);
+const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(2);
+const DEFAULT_BLOCK_AFTER: u32 = 10;
+const DEFAULT_BLOCK_FOR: Duration = Duration::from_secs(15 * 60);
+const SWEEP_INTERVAL_MS: u64 = 60_000;
+
+/// Failed-authentication counters keyed by `ip:<addr>` or `sub:<subject>`. Consulted on the
+/// auth path, so implementations must answer quickly; errors are logged and let the request
+/// through rather than locking everyone out.
+pub trait FailedAuthStore: Send + Sync + 'static {
+    fn get(&self, key: &str) -> Result<Option<FailedAuthRecord>, String>;
+
+    fn put(&self, key: &str, record: FailedAuthRecord) -> Result<(), String>;
+
+    fn remove(&self, key: &str) -> Result<(), String>;
+
+    /// Drops records whose window started before `window_started_before_ms` and that are not
+    /// blocked past `now_ms`. Called on every recorded failure, so implementations should
+    /// throttle the work; stores that expire keys themselves can keep the default no-op.
+    fn evict_stale(&self, window_started_before_ms: u64, now_ms: u64) -> Result<(), String> {
+        let _ = (window_started_before_ms, now_ms);
+        Ok(())
+    }
+}
+
+/// Failures counted since `window_started_ms`; times are Unix milliseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FailedAuthRecord {
+    pub failures: u32,
+    pub window_started_ms: u64,
+    pub blocked_until_ms: Option<u64>,
+}
+
+/// Process-local store; stale records are swept at most once a minute as failures come in.
+#[derive(Debug, Default)]
+pub struct InMemoryFailedAuthStore {
+    entries: Mutex<Entries>,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    records: HashMap<String, FailedAuthRecord>,
+    next_sweep_ms: u64,
+}
+
+impl InMemoryFailedAuthStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl FailedAuthStore for InMemoryFailedAuthStore {
+    fn get(&self, key: &str) -> Result<Option<FailedAuthRecord>, String> {
+        let guard = self
+            .entries
+            .lock()
+            .map_err(|_| "failed-auth store lock poisoned".to_string())?;
+        Ok(guard.records.get(key).copied())
+    }
+
+    fn put(&self, key: &str, record: FailedAuthRecord) -> Result<(), String> {
+        let mut guard = self
+            .entries
+            .lock()
+            .map_err(|_| "failed-auth store lock poisoned".to_string())?;
+        guard.records.insert(key.to_string(), record);
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> Result<(), String> {
+        let mut guard = self
+            .entries
+            .lock()
+            .map_err(|_| "failed-auth store lock poisoned".to_string())?;
+        guard.records.remove(key);
+        Ok(())
+    }
+
+    fn evict_stale(&self, window_started_before_ms: u64, now_ms: u64) -> Result<(), String> {
+        let mut guard = self
+            .entries
+            .lock()
+            .map_err(|_| "failed-auth store lock poisoned".to_string())?;
+        if now_ms < guard.next_sweep_ms {
+            return Ok(());
+        }
+        guard.records.retain(|_, record| {
+            record.window_started_ms >= window_started_before_ms
+                || record.blocked_until_ms.is_some_and(|until| until > now_ms)
+        });
+        guard.next_sweep_ms = now_ms.saturating_add(SWEEP_INTERVAL_MS);
+        Ok(())
+    }
+}
+
+/// Thresholds for [`BruteForceProtection`]. Failures are counted per client IP (and per token
+/// subject with [`Self::with_subject_tracking`]) within `window`; past `delay_after` each
+/// rejection is held back by `delay_step` more, up to `max_delay`; at `block_after` the key is
+/// rejected outright for `block_for`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BruteForcePolicy {
+    pub window: Duration,
+    pub delay_after: u32,
+    pub delay_step: Duration,
+    pub max_delay: Duration,
+    pub block_after: u32,
+    pub block_for: Duration,
+    /// Also count failures per `sub` claim of the rejected token. The claim is unverified, so
+    /// anyone can lock a subject out by sending bad tokens in its name.
+    pub track_subject: bool,
+}
+
+impl Default for BruteForcePolicy {
+    fn default() -> Self {
+        Self {
+            window: DEFAULT_WINDOW,
+            delay_after: DEFAULT_DELAY_AFTER,
+            delay_step: DEFAULT_DELAY_STEP,
+            max_delay: DEFAULT_MAX_DELAY,
+            block_after: DEFAULT_BLOCK_AFTER,
+            block_for: DEFAULT_BLOCK_FOR,
+            track_subject: false,
+        }
+    }
+}
+
+impl BruteForcePolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    pub fn with_delay(mut self, after: u32, step: Duration, max: Duration) -> Self {
+        self.delay_after = after;
+        self.delay_step = step;
+        self.max_delay = max;
+        self
+    }
+
+    pub fn with_block(mut self, after: u32, duration: Duration) -> Self {
+        self.block_after = after;
+        self.block_for = duration;
+        self
+    }
+
+    pub fn with_subject_tracking(mut self) -> Self {
+        self.track_subject = true;
+        self
+    }
+
+    fn delay_for(&self, failures: u32) -> Duration {
+        let over = failures.saturating_sub(self.delay_after);
+        self.delay_step.saturating_mul(over).min(self.max_delay)
+    }
+}
+
+/// Cloneable handle to a [`BruteForcePolicy`] and its [`FailedAuthStore`], resolved like any
+/// other dependency by the auth middleware. Register it with
+/// `OpenportioServer::with_brute_force_protection`.
+#[derive(Clone)]
+pub struct BruteForceProtection {
+    policy: BruteForcePolicy,
+    store: Arc<dyn FailedAuthStore>,
+    warned_keyless: Arc<AtomicBool>,
+}
+
+impl BruteForceProtection {
+    pub fn new(policy: BruteForcePolicy) -> Self {
+        Self {
+            policy,
+            store: Arc::new(InMemoryFailedAuthStore::new()),
+            warned_keyless: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn with_store(mut self, store: impl FailedAuthStore) -> Self {
+        self.store = Arc::new(store);
+        self
+    }
+
+    pub fn policy(&self) -> &BruteForcePolicy {
+        &self.policy
+    }
+
+    /// The current record for `key` (`ip:<addr>` or `sub:<subject>`).
+    pub fn record(&self, key: &str) -> Result<Option<FailedAuthRecord>, String> {
+        self.store.get(key)
+    }
+
+    /// Lifts a block early, e.g. from an admin tool.
+    pub fn reset(&self, key: &str) -> Result<(), String> {
+        self.store.remove(key)
+    }
+
+    pub(crate) fn from_extensions(extensions: &http::Extensions) -> Option<Self> {
+        resolve_from_extensions(extensions)
+    }
+
+    /// Remaining block time for the request's keys, if any is blocked.
+    pub(crate) fn blocked(&self, keys: &[String], now_ms: u64) -> Option<Duration> {
+        keys.iter()
+            .filter_map(|key| self.load(key))
+            .filter_map(|record| record.blocked_until_ms)
+            .filter(|until| *until > now_ms)
+            .max()
+            .map(|until| Duration::from_millis(until - now_ms))
+    }
+
+    /// Counts a failure against every key; returns the highest count and whether this failure
+    /// started a block.
+    pub(crate) fn record_failure(&self, keys: &[String], now_ms: u64) -> (u32, bool) {
+        let window_ms = self.policy.window.as_millis() as u64;
+        if let Err(err) = self
+            .store
+            .evict_stale(now_ms.saturating_sub(window_ms), now_ms)
+        {
+            tracing::warn!(error = %err, "failed-auth store eviction failed");
+        }
+        let mut highest = 0;
+        let mut started_block = false;
+        for key in keys {
+            let mut record = self
+                .load(key)
+                .filter(|record| now_ms.saturating_sub(record.window_started_ms) < window_ms)
+                .unwrap_or(FailedAuthRecord {
+                    failures: 0,
+                    window_started_ms: now_ms,
+                    blocked_until_ms: None,
+                });
+            record.failures = record.failures.saturating_add(1);
+            if record.failures >= self.policy.block_after {
+                record.blocked_until_ms = Some(now_ms + self.policy.block_for.as_millis() as u64);
+                record.failures = 0;
+                record.window_started_ms = now_ms;
+                started_block = true;
+                highest = highest.max(self.policy.block_after);
+            } else {
+                highest = highest.max(record.failures);
+            }
+            if let Err(err) = self.store.put(key, record) {
+                tracing::warn!(error = %err, "failed-auth store write failed");
+            }
+        }
+        (highest, started_block)
+    }
+
+    /// Clears subject counters only: a caller holding one valid token must not be able to reset
+    /// its address's count between guesses.
+    pub(crate) fn record_success(&self, keys: &[String]) {
+        for key in keys.iter().filter(|key| key.starts_with("sub:")) {
+            if let Err(err) = self.store.remove(key) {
+                tracing::warn!(error = %err, "failed-auth store write failed");
+            }
+        }
+    }
+
+    fn load(&self, key: &str) -> Option<FailedAuthRecord> {
+        self.store
+            .get(key)
+            .map_err(|err| tracing::warn!(error = %err, "failed-auth store read failed"))
+            .ok()
+            .flatten()
+    }
+}
+
+impl fmt::Debug for BruteForceProtection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BruteForceProtection")
+            .field("policy", &self.policy)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Per-request view used by the auth middleware and the gRPC auth layer.
+pub(crate) struct AttemptGuard {
+    protection: BruteForceProtection,
+    keys: Vec<String>,
+    now_ms: u64,
+    protocol: &'static str,
+    /// Template for the `LoginBlocked` events this attempt may emit.
+    event: AuditEvent,
+}
+
+impl AttemptGuard {
+    pub(crate) fn for_request(req: &Request) -> Option<Self> {
+        let protocol = if is_grpc_request(req.headers()) {
+            "grpc"
+        } else {
+            "rest"
+        };
+        let event = AuditEvent::from_request(AuditEventKind::LoginBlocked, req);
+        Self::new(req.headers(), req.extensions(), protocol, event)
+    }
+
+    /// For calls that reach a gRPC service without the router auth middleware.
+    pub(crate) fn for_grpc(parts: &http::request::Parts) -> Option<Self> {
+        let event = AuditEvent {
+            method: Some(parts.method.to_string()),
+            path: Some(parts.uri.path().to_string()),
+            ..AuditEvent::from_extensions(AuditEventKind::LoginBlocked, &parts.extensions)
+                .with_protocol("grpc")
+        };
+        Self::new(&parts.headers, &parts.extensions, "grpc", event)
+    }
+
+    fn new(
+        headers: &HeaderMap,
+        extensions: &http::Extensions,
+        protocol: &'static str,
+        event: AuditEvent,
+    ) -> Option<Self> {
+        let protection = BruteForceProtection::from_extensions(extensions)?;
+        let mut keys = Vec::new();
+        if let Some(ClientIp(ip)) = extensions.get::<ClientIp>() {
+            keys.push(format!("ip:{ip}"));
+        }
+        if protection.policy.track_subject {
+            if let Some(subject) = unverified_subject(headers) {
+                keys.push(format!("sub:{subject}"));
+            }
+        }
+        if keys.is_empty() && !protection.warned_keyless.swap(true, Ordering::Relaxed) {
+            tracing::warn!(
+                "brute-force protection has no client IP for this request and does not track \
+                 subjects, so failures go uncounted; serve with connect info or trusted proxies"
+            );
+        }
+        let now_ms = clock_from_extensions(extensions)
+            .system_time()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or_default();
+        Some(Self {
+            protection,
+            keys,
+            now_ms,
+            protocol,
+            event,
+        })
+    }
+
+    /// The rejection for a caller that is currently blocked.
+    pub(crate) fn blocked_response(&self, extensions: &http::Extensions) -> Option<Response> {
+        self.blocked(extensions)
+            .map(|remaining| blocked_response(self.protocol == "grpc", remaining))
+    }
+
+    /// [`Self::blocked_response`] as a gRPC status.
+    pub(crate) fn blocked_status(&self, extensions: &http::Extensions) -> Option<tonic::Status> {
+        self.blocked(extensions).map(|_| blocked_status())
+    }
+
+    fn blocked(&self, extensions: &http::Extensions) -> Option<Duration> {
+        let remaining = self.protection.blocked(&self.keys, self.now_ms)?;
+        self.event
+            .clone()
+            .with_reason("too many failed authentication attempts")
+            .emit(extensions);
+        self.count(extensions, "auth.blocked_requests");
+        Some(remaining)
+    }
+
+    /// Records the outcome of the attempt: a success clears subject counters and an invalid
+    /// token counts as a failure. Returns how long to hold the rejection back.
+    pub(crate) fn settle(
+        &self,
+        result: &Result<(), AuthRejection>,
+        extensions: &http::Extensions,
+    ) -> Duration {
+        match result {
+            Ok(()) => {
+                self.protection.record_success(&self.keys);
+                Duration::ZERO
+            }
+            Err(AuthRejection::InvalidToken(_)) => self.failed(extensions),
+            Err(_) => Duration::ZERO,
+        }
+    }
+
+    fn failed(&self, extensions: &http::Extensions) -> Duration {
+        let (failures, started_block) = self.protection.record_failure(&self.keys, self.now_ms);
+        self.count(extensions, "auth.failed_attempts");
+        if started_block {
+            tracing::warn!(
+                keys = ?self.keys,
+                block_secs = self.protection.policy.block_for.as_secs(),
+                "blocking caller after repeated authentication failures"
+            );
+            self.event
+                .clone()
+                .with_reason(format!(
+                    "blocked for {}s after {failures} failed attempts",
+                    self.protection.policy.block_for.as_secs()
+                ))
+                .emit(extensions);
+            self.count(extensions, "auth.blocks");
+        }
+        self.protection.policy.delay_for(failures)
+    }
+
+    fn count(&self, extensions: &http::Extensions, name: &str) {
+        if let Some(metrics) = resolve_from_extensions::<Arc<dyn MetricsSink>>(extensions) {
+            metrics.incr_counter_with(name, &[("protocol", self.protocol)]);
+        }
+    }
+}
+
+fn unverified_subject(headers: &HeaderMap) -> Option<String> {
+    #[derive(Deserialize)]
+    struct Claims {
+        sub: String,
+    }
+    let token = headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")?;
+    jsonwebtoken::dangerous::insecure_decode::<Claims>(token)
+        .ok()
+        .map(|data| data.claims.sub)
+}
+
+const BLOCKED_MESSAGE: &str = "too many failed authentication attempts; try again later";
+
+fn blocked_status() -> tonic::Status {
+    tonic::Status::resource_exhausted(BLOCKED_MESSAGE)
+}
+
+fn blocked_response(is_grpc: bool, remaining: Duration) -> Response {
+    let retry_after = remaining.as_secs().max(1);
+    if is_grpc {
+        return blocked_status().into_http().map(Body::new);
+    }
+    let mut response = (
+        StatusCode::TOO_MANY_REQUESTS,
+        Json(ApiErrorResponse {
+            code: "too_many_failed_attempts".to_string(),
+            message: BLOCKED_MESSAGE.to_string(),
+            detail: None,
+            details: Some(serde_json::json!({ "retry_after_seconds": retry_after })),
+        }),
+    )
+        .into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failures_delay_then_block_and_success_clears_the_record() {
+        let protection = BruteForceProtection::new(
+            BruteForcePolicy::new()
+                .with_window(Duration::from_secs(60))
+                .with_delay(2, Duration::from_millis(100), Duration::from_millis(250))
+                .with_block(4, Duration::from_secs(30)),
+        );
+        let keys = ["ip:10.0.0.1".to_string()];
+
+        assert_eq!(protection.record_failure(&keys, 1_000), (1, false));
+        assert_eq!(protection.record_failure(&keys, 2_000), (2, false));
+        assert_eq!(protection.policy().delay_for(2), Duration::ZERO);
+        assert_eq!(protection.record_failure(&keys, 3_000), (3, false));
+        assert_eq!(protection.policy().delay_for(3), Duration::from_millis(100));
+        assert_eq!(protection.policy().delay_for(9), Duration::from_millis(250));
+        assert_eq!(protection.blocked(&keys, 3_000), None);
+
+        assert_eq!(protection.record_failure(&keys, 4_000), (4, true));
+        assert_eq!(
+            protection.blocked(&keys, 5_000),
+            Some(Duration::from_secs(29))
+        );
+        assert_eq!(protection.blocked(&keys, 34_000), None);
+
+        // Failures outside the window start a new count.
+        assert_eq!(protection.record_failure(&keys, 100_000), (1, false));
+        assert_eq!(protection.record_failure(&keys, 200_000), (1, false));
+
+        let keys = ["ip:10.0.0.1".to_string(), "sub:ada".to_string()];
+        assert_eq!(protection.record_failure(&keys, 201_000), (2, false));
+        protection.record_success(&keys);
+        assert_eq!(protection.record("sub:ada"), Ok(None));
+        assert_eq!(
+            protection
+                .record("ip:10.0.0.1")
+                .unwrap()
+                .map(|record| record.failures),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn stale_records_are_evicted_once_their_window_and_block_lapse() {
+        let protection = BruteForceProtection::new(
+            BruteForcePolicy::new()
+                .with_window(Duration::from_secs(60))
+                .with_block(2, Duration::from_secs(300)),
+        );
+        let idle = ["ip:10.0.0.1".to_string()];
+        let blocked = ["ip:10.0.0.2".to_string()];
+        protection.record_failure(&idle, 1_000);
+        protection.record_failure(&blocked, 1_000);
+        assert_eq!(protection.record_failure(&blocked, 2_000), (2, true));
+
+        // Past the window but inside the block: only the idle record goes.
+        protection.record_failure(&["ip:10.0.0.3".to_string()], 120_000);
+        assert_eq!(protection.record("ip:10.0.0.1"), Ok(None));
+        assert!(protection.record("ip:10.0.0.2").unwrap().is_some());
+
+        protection.record_failure(&["ip:10.0.0.3".to_string()], 400_000);
+        assert_eq!(protection.record("ip:10.0.0.2"), Ok(None));
+    }
+}
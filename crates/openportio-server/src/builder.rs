@@ -1,50 +1,169 @@
-use std::{convert::Infallible, env, future::IntoFuture, io, net::SocketAddr, sync::Arc};
+use std::{
+    collections::HashMap, convert::Infallible, future::Future, net::SocketAddr, sync::Arc,
+    time::Duration,
+};
 
 use axum::Router;
+use futures_util::future::BoxFuture;
 use http::{Request, Response};
-use openportio_core::AppState;
+use openportio_core::{AppState, Environment, MetricsSink, SharedClock};
+use openportio_rpc::ServiceSummary;
 use tokio::net::TcpListener;
 use tokio::sync::watch;
+use tokio::task::{AbortHandle, JoinHandle};
 use tonic::{body::BoxBody, server::NamedService, service::Routes};
 use tower::Service;
 
-use crate::{build_router, di, grpc, middleware};
+use crate::{
+    adaptive_concurrency, alerts, api, api_docs, audit, auth, brute_force, build_router,
+    build_router_without_docs, buildinfo, client_concurrency, cors, di, diagnostics, drain,
+    error_reporting, flags, grpc, grpc_limits, handler_timeout, http_client, introspection,
+    lifecycle::{self, Phase, PhaseError},
+    log_filter, maintenance, middleware, panics, policy, profile, prometheus, quota, redirect,
+    response_headers, revocation, runtime_metrics,
+    server_error::ServerError,
+    signing, slow_requests, sse, tenancy, timings, tls, transport, versioning, ws, DOCS_PATHS,
+};
 
 type RouterCustomizer = Box<dyn Fn(Router) -> Router + Send + Sync + 'static>;
-type StartupHook = Box<dyn Fn(SocketAddr) + Send + Sync + 'static>;
 type ShutdownHook = Box<dyn Fn() + Send + Sync + 'static>;
+type DependencyProvider =
+    Box<dyn Fn(di::DependencyOverrides) -> di::DependencyOverrides + Send + Sync + 'static>;
+type BackgroundTask = Box<dyn FnOnce() -> BoxFuture<'static, ()> + Send + 'static>;
+type GrpcServiceAdder = Box<dyn Fn(Routes) -> Routes + Send + Sync + 'static>;
+
+enum GrpcRoutes {
+    /// The built-in Greeter plus services added with `with_grpc_service`, built from the final
+    /// state when the app is assembled so later state changes reach them.
+    Default {
+        auth: Box<auth::AuthRuntimeConfig>,
+        services: Vec<GrpcServiceAdder>,
+    },
+    Custom(Routes),
+}
 
 pub struct OpenportioServer {
     state: Arc<AppState>,
+    /// Set by `with_environment`; applied to the state when the app is built.
+    environment: Option<Environment>,
     addr: SocketAddr,
     rest_addr: Option<SocketAddr>,
     grpc_addr: Option<SocketAddr>,
+    addrs_set_in_code: AddrsSetInCode,
+    grpc_tls: Option<tls::GrpcTlsConfig>,
+    https_redirect: Option<redirect::HttpsRedirect>,
     rest_router: Option<Router>,
     raw_routers: Vec<Router>,
-    grpc_routes: Option<Routes>,
+    sse_topics: Vec<(String, sse::SseTopic)>,
+    nested_routers: Vec<versioning::MountedRouter>,
+    header_versioning: Option<versioning::HeaderVersioning>,
+    grpc_routes: Option<GrpcRoutes>,
+    grpc_catalog: Vec<ServiceSummary>,
+    grpc_service_names: Vec<String>,
+    route_descriptions: Vec<introspection::RestRouteDescription>,
+    admin_routes: bool,
+    admin_auth: Option<auth::AuthRuntimeConfig>,
+    build_info: Option<buildinfo::BuildInfo>,
+    log_filter: Option<log_filter::LogFilterHandle>,
+    request_signing: Option<signing::RequestSigning>,
+    /// `None` follows the environment: docs are served everywhere but production.
+    docs_enabled: Option<bool>,
+    required_auth: Option<auth::AuthRuntimeConfig>,
+    profile: Option<profile::Profile>,
     dependency_overrides: di::DependencyOverrides,
+    dependency_graph: di::DependencyGraph,
+    dependency_providers: HashMap<di::DependencyKey, DependencyProvider>,
     middleware_config: middleware::MiddlewareConfig,
+    http2_config: transport::Http2Config,
+    connection_lifecycle: ConnectionLifecycle,
+    socket_config: transport::SocketConfig,
+    bind_retry: transport::BindRetry,
     middleware_customizers: Vec<RouterCustomizer>,
-    startup_hooks: Vec<StartupHook>,
+    lifecycle: lifecycle::Lifecycle,
     shutdown_hooks: Vec<ShutdownHook>,
+    background_tasks: Vec<(String, BackgroundTask)>,
+    runtime_metrics: Option<runtime_metrics::RuntimeMetrics>,
+    drainer: drain::Drainer,
+    modules: Vec<String>,
+    env_errors: Vec<String>,
 }
 
 impl OpenportioServer {
+    /// Reads the `OPENPORTIO_*` settings. A set but malformed value does not fall back to the
+    /// default: it is kept and [`Self::run`] fails with [`ServerError::InvalidConfig`] naming
+    /// the variable. Settings read later (WebSocket and HTTP client limits) are checked here
+    /// too, so a typo is reported at startup instead of on first use.
     pub fn new() -> Self {
-        let state = Arc::new(AppState::local("openportio-server"));
+        let mut env_errors = Vec::new();
+        let mut state = AppState::local("openportio-server");
+        state.config.environment = Environment::from_env();
+        let state = Arc::new(state);
+        let grpc_catalog = default_grpc_catalog();
+        let auth = auth::AuthRuntimeConfig::from_env();
+        env_errors.extend(auth.as_ref().err().cloned());
+        let addr = load_addr_from_env().unwrap_or_else(|err| {
+            env_errors.push(err);
+            SocketAddr::from(([127, 0, 0, 1], 3000))
+        });
+        let rest_addr = or_record(crate::env::setting("REST_ADDR"), &mut env_errors);
+        let grpc_addr = or_record(crate::env::setting("GRPC_ADDR"), &mut env_errors);
+        let middleware_config =
+            or_record(middleware::MiddlewareConfig::from_env(), &mut env_errors);
+        let bind_retry = or_record(transport::BindRetry::from_env(), &mut env_errors);
+        let runtime_metrics =
+            or_record(runtime_metrics::RuntimeMetrics::from_env(), &mut env_errors);
+        let drainer = or_record(drain::Drainer::from_env(), &mut env_errors);
+        env_errors.extend(ws::WsLimits::from_env().err());
+        env_errors.extend(crate::ws_runtime_config().err());
+        env_errors.extend(http_client::HttpClientConfig::from_env().err());
         Self {
-            grpc_routes: Some(grpc::build_grpc_routes(state.clone())),
+            grpc_routes: Some(GrpcRoutes::Default {
+                auth: Box::new(auth::AuthRuntimeConfig::or_reject_all(auth)),
+                services: Vec::new(),
+            }),
+            grpc_service_names: grpc_catalog
+                .iter()
+                .map(|service| service.name.clone())
+                .collect(),
+            grpc_catalog,
+            route_descriptions: Vec::new(),
+            admin_routes: false,
+            admin_auth: None,
+            build_info: None,
+            log_filter: None,
+            request_signing: None,
+            docs_enabled: None,
+            required_auth: None,
+            profile: None,
             state,
-            addr: load_addr_from_env().unwrap_or(SocketAddr::from(([127, 0, 0, 1], 3000))),
-            rest_addr: None,
-            grpc_addr: None,
+            environment: None,
+            addr,
+            rest_addr,
+            grpc_addr,
+            addrs_set_in_code: AddrsSetInCode::default(),
+            grpc_tls: None,
+            https_redirect: None,
             rest_router: None,
             raw_routers: Vec::new(),
+            sse_topics: Vec::new(),
+            nested_routers: Vec::new(),
+            header_versioning: None,
             dependency_overrides: di::DependencyOverrides::default(),
-            middleware_config: middleware::MiddlewareConfig::from_env(),
+            dependency_graph: di::DependencyGraph::default(),
+            dependency_providers: HashMap::new(),
+            middleware_config,
+            http2_config: transport::Http2Config::default(),
+            connection_lifecycle: ConnectionLifecycle::default(),
+            socket_config: transport::SocketConfig::default(),
+            bind_retry,
             middleware_customizers: Vec::new(),
-            startup_hooks: Vec::new(),
+            lifecycle: lifecycle::Lifecycle::default(),
             shutdown_hooks: Vec::new(),
+            background_tasks: Vec::new(),
+            runtime_metrics,
+            drainer,
+            modules: Vec::new(),
+            env_errors,
         }
     }
 
@@ -52,16 +171,26 @@ impl OpenportioServer {
         self.addr = addr;
         self.rest_addr = None;
         self.grpc_addr = None;
+        self.addrs_set_in_code.addr = true;
         self
     }
 
     pub fn with_rest_addr(mut self, addr: SocketAddr) -> Self {
         self.rest_addr = Some(addr);
+        self.addrs_set_in_code.rest = true;
         self
     }
 
     pub fn with_grpc_addr(mut self, addr: SocketAddr) -> Self {
         self.grpc_addr = Some(addr);
+        self.addrs_set_in_code.grpc = true;
+        self
+    }
+
+    /// Terminates TLS (or mTLS) on the dedicated gRPC listener; requires dual-port mode. Without
+    /// this, `OPENPORTIO_GRPC_TLS_*` env vars are used when set.
+    pub fn with_grpc_tls(mut self, config: tls::GrpcTlsConfig) -> Self {
+        self.grpc_tls = Some(config);
         self
     }
 
@@ -80,16 +209,190 @@ impl OpenportioServer {
         self
     }
 
+    /// Mounts any tower service (a hyper/tower app being migrated, a metrics exporter, ...) at
+    /// `path` and everything below it; the service sees the path with the prefix stripped
+    /// (`OriginalUri` keeps the full one). It runs behind the shared middleware stack, and its
+    /// errors become `500` JSON errors. Mounting at `/` makes it the fallback for unmatched
+    /// requests.
+    pub fn with_service_at<S>(mut self, path: &str, service: S) -> Self
+    where
+        S: Service<axum::extract::Request> + Clone + Send + 'static,
+        S::Response: axum::response::IntoResponse + Send,
+        S::Error: Into<tower::BoxError> + Send,
+        S::Future: Send,
+    {
+        let service = axum::error_handling::HandleError::new(service, |err: S::Error| {
+            mounted_service_error(err.into())
+        });
+        let prefix = path.trim_end_matches('/');
+        let router = if prefix.is_empty() {
+            Router::new().fallback_service(service)
+        } else {
+            Router::new().nest_service(prefix, service)
+        };
+        self.raw_routers.push(router);
+        self.route_descriptions.push(
+            introspection::RestRouteDescription::new(format!("{prefix}/*"))
+                .with_method("ANY")
+                .with_middleware("mounted_service"),
+        );
+        self
+    }
+
+    /// Serves `GET path` as an SSE stream of the `AppState::events` topic, e.g.
+    /// `with_sse_topic("/events/notes", Topic::Notes)`; pass an [`sse::SseTopic`] for
+    /// principal-based filtering or a custom heartbeat.
+    pub fn with_sse_topic(mut self, path: &str, topic: impl Into<sse::SseTopic>) -> Self {
+        let topic = topic.into();
+        self.route_descriptions.push(
+            introspection::RestRouteDescription::new(path)
+                .with_method("GET")
+                .with_middleware("sse_topic"),
+        );
+        self.sse_topics.push((path.to_string(), topic));
+        self
+    }
+
+    /// Mounts `router` under `prefix`. Pass `NestedRouter::new(router).with_openapi(doc)` to
+    /// list its routes in `describe()` and serve `{prefix}/openapi.json` and `{prefix}/docs`.
+    pub fn nest(mut self, prefix: &str, router: impl Into<versioning::NestedRouter>) -> Self {
+        self.nested_routers
+            .push(versioning::MountedRouter::new(prefix, router.into(), None));
+        self
+    }
+
+    /// Like [`Self::nest`] under `/v{major}`, additionally tagging every OpenAPI operation with
+    /// the version so `/v1` and `/v2` trees get separate docs sections.
+    pub fn nest_versioned(
+        mut self,
+        version: versioning::ApiVersion,
+        router: impl Into<versioning::NestedRouter>,
+    ) -> Self {
+        self.nested_routers.push(versioning::MountedRouter::new(
+            &version.prefix(),
+            router.into(),
+            Some(version),
+        ));
+        self
+    }
+
+    /// Picks between version routers by the `Accept-Version` / `X-Api-Version` header, with
+    /// `Deprecation`/`Sunset` headers on deprecated versions; see [`versioning::HeaderVersioning`].
+    pub fn with_header_versioning(mut self, versioning: versioning::HeaderVersioning) -> Self {
+        self.header_versioning = Some(versioning);
+        self
+    }
+
     pub fn with_dependency<T>(mut self, value: T) -> Self
     where
         T: Clone + Send + Sync + 'static,
     {
         self.dependency_overrides = self.dependency_overrides.with(value);
+        self.dependency_graph
+            .provide(di::DependencyKey::of::<T>(), Vec::new());
+        self
+    }
+
+    /// Registers `value` as `Named<T, Q>`, so several instances of `T` (primary and replica
+    /// pools, per-upstream clients) can coexist; handlers extract `Named<T, Q>`.
+    pub fn with_named_dependency<Q, T>(self, value: T) -> Self
+    where
+        Q: di::Qualifier,
+        T: Clone + Send + Sync + 'static,
+    {
+        self.with_dependency(di::Named::<T, Q>::new(value))
+    }
+
+    /// Registers an async factory for `T` that handlers pull through `di::Lazy<T>`; it only runs
+    /// when the handler awaits `Lazy::get`, at most once per request.
+    pub fn with_lazy_dependency<T, F, Fut>(self, factory: F) -> Self
+    where
+        T: Send + 'static,
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = openportio_core::OpenportioResult<T>> + Send + 'static,
+    {
+        self.with_dependency(di::LazyProvider::new(factory))
+    }
+
+    pub fn with_dependency_provider<T, F>(
+        mut self,
+        requires: impl IntoIterator<Item = di::DependencyKey>,
+        provider: F,
+    ) -> Self
+    where
+        T: Clone + Send + Sync + 'static,
+        F: Fn(&di::DependencyOverrides) -> T + Send + Sync + 'static,
+    {
+        let key = di::DependencyKey::of::<T>();
+        self.dependency_graph
+            .provide(key, requires.into_iter().collect());
+        self.dependency_providers.insert(
+            key,
+            Box::new(move |overrides| {
+                let value = provider(&overrides);
+                overrides.with(value)
+            }),
+        );
+        self
+    }
+
+    /// Applies `module`'s registrations. Installing a module whose name is already installed is
+    /// a no-op (logged), so shared modules can be pulled in by several layers.
+    pub fn install<M: di::Module>(self, module: M) -> Self {
+        let name = module.name();
+        if self.modules.contains(&name) {
+            tracing::warn!(module = %name, "module already installed; skipping");
+            return self;
+        }
+        let mut server = module.register(self);
+        server.modules.push(name);
+        server
+    }
+
+    /// Handle to the registered overrides; tests open [`di::DependencyOverrides::scoped`] on it
+    /// to stub dependencies of an already built app.
+    pub fn dependency_overrides(&self) -> di::DependencyOverrides {
+        self.dependency_overrides.clone()
+    }
+
+    /// Registers the `SharedClock` used by rate limiting, token expiry, JWKS refresh and SSE
+    /// heartbeats (a `TestClock` in tests).
+    pub fn with_clock(self, clock: SharedClock) -> Self {
+        self.with_dependency(clock)
+    }
+
+    pub fn with_feature_flags(self, flags: flags::FeatureFlags) -> Self {
+        self.with_dependency(flags::FlagClient::new(flags))
+    }
+
+    /// Declares that `consumer` needs `T`, so `build_app()`/`run()` fail when nothing provides it.
+    /// `#[route]` handlers declare their `Named`/`Lazy` extractors themselves; declare those of
+    /// plain axum handlers and `GrpcContext` dependencies here, or a missing one only shows up as
+    /// a `500` per request.
+    pub fn require_dependency<T>(mut self, consumer: impl Into<String>) -> Self
+    where
+        T: 'static,
+    {
+        self.dependency_graph
+            .require(consumer, di::DependencyKey::of::<T>());
         self
     }
 
+    /// Checks the registered providers against `require_dependency` declarations and the
+    /// extractors of every `#[route]` handler linked into the binary.
+    pub fn validate_dependencies(&self) -> Result<(), di::DependencyGraphError> {
+        self.dependency_graph().validate().map(|_| ())
+    }
+
+    fn dependency_graph(&self) -> di::DependencyGraph {
+        let mut graph = self.dependency_graph.clone();
+        graph.require_routes();
+        graph
+    }
+
     pub fn without_grpc(mut self) -> Self {
         self.grpc_routes = None;
+        self.grpc_service_names.clear();
         self
     }
 
@@ -103,15 +406,91 @@ impl OpenportioServer {
         S::Future: Send + 'static,
     {
         let routes = match self.grpc_routes.take() {
-            Some(existing) => existing.add_service(service).prepare(),
-            None => Routes::new(service).prepare(),
+            Some(GrpcRoutes::Default { auth, mut services }) => {
+                // Tonic services are `Send` but not necessarily `Sync`.
+                let service = std::sync::Mutex::new(service);
+                services.push(Box::new(move |routes| {
+                    routes.add_service(service.lock().expect("grpc service lock").clone())
+                }));
+                GrpcRoutes::Default { auth, services }
+            }
+            Some(GrpcRoutes::Custom(existing)) => {
+                GrpcRoutes::Custom(existing.add_service(service).prepare())
+            }
+            None => GrpcRoutes::Custom(Routes::new(service).prepare()),
         };
         self.grpc_routes = Some(routes);
+        if !self.grpc_service_names.iter().any(|name| name == S::NAME) {
+            self.grpc_service_names.push(S::NAME.to_string());
+        }
         self
     }
 
+    /// Mounts a `#[grpc_service]` implementation behind a [`grpc::GrpcAuthService`] (using
+    /// the `require_auth` config, or `AuthRuntimeConfig::from_env()`), registering its
+    /// descriptor set when the macro was given one. Handlers resolve dependencies with
+    /// `GrpcContext::from_request(&request)`.
+    pub fn with_grpc<S>(self, service: S) -> Self
+    where
+        S: grpc::GrpcService,
+        <S::Server as Service<Request<BoxBody>>>::Future: Send + 'static,
+    {
+        let auth_cfg = self.required_auth.clone().unwrap_or_else(|| {
+            auth::AuthRuntimeConfig::or_reject_all(auth::AuthRuntimeConfig::from_env())
+        });
+        let server = grpc::GrpcAuthService::new(
+            S::configure_server(service.into_server(), &self.middleware_config.grpc_messages),
+            grpc::GrpcAuthInterceptor::new(auth_cfg),
+        );
+        let server = self.with_grpc_service(server);
+        match S::DESCRIPTOR_SET {
+            Some(descriptor_set) => server.with_grpc_descriptor_set(descriptor_set),
+            None => server,
+        }
+    }
+
+    /// Replaces the gRPC routes. `describe()` only lists services registered afterwards through
+    /// `with_grpc_service(...)` or `with_grpc_descriptor_set(...)`.
     pub fn with_grpc_routes(mut self, routes: Routes) -> Self {
-        self.grpc_routes = Some(routes.prepare());
+        self.grpc_routes = Some(GrpcRoutes::Custom(routes.prepare()));
+        self.grpc_service_names.clear();
+        self
+    }
+
+    /// Replaces the gRPC routes with every service in `registry` plus reflection over its
+    /// descriptor sets; `describe()` lists the registered services.
+    pub fn with_grpc_registry(mut self, registry: openportio_rpc::ServiceRegistry) -> Self {
+        self.grpc_catalog.extend(registry.catalog());
+        self.grpc_service_names = registry.service_names().to_vec();
+        self.grpc_service_names.extend(
+            [
+                tonic_reflection::pb::v1::FILE_DESCRIPTOR_SET,
+                tonic_reflection::pb::v1alpha::FILE_DESCRIPTOR_SET,
+            ]
+            .into_iter()
+            .flat_map(|set| openportio_rpc::describe_services(set).unwrap_or_default())
+            .map(|service| service.name),
+        );
+        self.grpc_routes = Some(GrpcRoutes::Custom(grpc::build_grpc_routes_from_registry(
+            registry,
+        )));
+        self
+    }
+
+    /// Registers the services in an encoded `FileDescriptorSet` so `describe()` can list them
+    /// with their methods.
+    pub fn with_grpc_descriptor_set(mut self, descriptor_set: &[u8]) -> Self {
+        match openportio_rpc::describe_services(descriptor_set) {
+            Ok(services) => {
+                for service in services {
+                    if !self.grpc_service_names.contains(&service.name) {
+                        self.grpc_service_names.push(service.name.clone());
+                    }
+                    self.grpc_catalog.push(service);
+                }
+            }
+            Err(err) => tracing::warn!(error = %err, "ignoring undecodable grpc descriptor set"),
+        }
         self
     }
 
@@ -122,14 +501,15 @@ impl OpenportioServer {
         self.configure_tonic_routes(configure)
     }
 
+    /// Rewrites the gRPC routes. The built-in Greeter is built from the state at this point, so
+    /// call it after `with_state`, `with_environment` and the other state-changing methods.
     pub fn configure_tonic_routes<F>(mut self, configure: F) -> Self
     where
         F: FnOnce(Routes) -> Routes,
     {
         self.grpc_routes = self
-            .grpc_routes
-            .take()
-            .map(|routes| configure(routes).prepare());
+            .build_grpc_routes()
+            .map(|routes| GrpcRoutes::Custom(configure(routes).prepare()));
         self
     }
 
@@ -138,6 +518,168 @@ impl OpenportioServer {
         self
     }
 
+    /// Applies a preset bundle of middleware, auth and docs settings. Call it first: later
+    /// builder calls still override single settings.
+    pub fn with_profile(mut self, profile: profile::Profile) -> Self {
+        self.middleware_config = profile.middleware_config(self.middleware_config);
+        if !profile.serves_docs() {
+            self.docs_enabled = Some(false);
+        }
+        self.profile = Some(profile);
+        if profile.requires_auth() {
+            self.require_auth(auth::AuthRuntimeConfig::or_reject_all(
+                auth::AuthRuntimeConfig::from_env_without_prefetch(),
+            ))
+        } else {
+            self
+        }
+    }
+
+    /// Authenticates every REST and gRPC request except health probes, even when
+    /// `OPENPORTIO_AUTH_ENABLED` is unset.
+    pub fn require_auth(mut self, mut auth_cfg: auth::AuthRuntimeConfig) -> Self {
+        auth_cfg.enabled = true;
+        self.required_auth = Some(auth_cfg);
+        self
+    }
+
+    /// Drops `/docs`, `/openapi.json`, the gRPC contract pages, and nested `{prefix}/docs`.
+    pub fn without_docs(mut self) -> Self {
+        self.docs_enabled = Some(false);
+        self
+    }
+
+    /// Serves docs even in the `production` environment, where they are off by default.
+    pub fn with_docs(mut self) -> Self {
+        self.docs_enabled = Some(true);
+        self
+    }
+
+    /// Overrides the environment read from `OPENPORTIO_ENV` (or set on the `with_state` state)
+    /// for every REST and gRPC route, whichever order the builder methods are called in.
+    pub fn with_environment(mut self, environment: impl Into<Environment>) -> Self {
+        self.environment = Some(environment.into());
+        self
+    }
+
+    fn environment(&self) -> &Environment {
+        self.environment
+            .as_ref()
+            .unwrap_or(&self.state.config.environment)
+    }
+
+    /// The state routes are built from, with the `with_environment` override applied.
+    fn app_state(&self) -> Arc<AppState> {
+        match &self.environment {
+            Some(environment) if *environment != self.state.config.environment => {
+                let mut state = AppState::clone(&self.state);
+                state.config.environment = environment.clone();
+                Arc::new(state)
+            }
+            _ => self.state.clone(),
+        }
+    }
+
+    fn serves_docs(&self) -> bool {
+        self.docs_enabled
+            .unwrap_or_else(|| self.environment().serves_docs_by_default())
+    }
+
+    /// Replaces the HTTP/2 settings. Keepalive, connection age and idle limits set with
+    /// their own builder calls still win, whichever call comes first.
+    pub fn with_http2_config(mut self, config: transport::Http2Config) -> Self {
+        self.http2_config = config;
+        self
+    }
+
+    /// HTTP/2 keepalive pings every `interval`; connections whose ping is not acknowledged
+    /// within `timeout` are dropped.
+    pub fn with_grpc_keepalive(mut self, interval: Duration, timeout: Duration) -> Self {
+        self.connection_lifecycle.keepalive = Some((interval, timeout));
+        self
+    }
+
+    /// Sends GOAWAY to connections older than `age` so clients reconnect (and rebalance after
+    /// deploys), then closes them once in-flight calls finish or `grace` elapses.
+    pub fn with_max_connection_age(mut self, age: Duration, grace: Duration) -> Self {
+        self.connection_lifecycle.max_age = Some((age, grace));
+        self
+    }
+
+    /// Closes connections that have had no call or response stream in flight for `idle`.
+    pub fn with_max_connection_idle(mut self, idle: Duration) -> Self {
+        self.connection_lifecycle.max_idle = Some(idle);
+        self
+    }
+
+    /// `with_http2_config` with the connection lifecycle builder calls applied on top.
+    fn effective_http2_config(&self) -> transport::Http2Config {
+        let mut config = self.http2_config.clone();
+        let lifecycle = self.connection_lifecycle;
+        if let Some((interval, timeout)) = lifecycle.keepalive {
+            config.keep_alive_interval = Some(interval);
+            config.keep_alive_timeout = Some(timeout);
+        }
+        if let Some((age, grace)) = lifecycle.max_age {
+            config.max_connection_age = Some(age);
+            config.max_connection_age_grace = Some(grace);
+        }
+        if let Some(idle) = lifecycle.max_idle {
+            config.max_connection_idle = Some(idle);
+        }
+        config
+    }
+
+    /// Per-message gRPC size limits and compression for services mounted with `with_grpc(...)`
+    /// after this call; replaces the `OPENPORTIO_GRPC_*_MESSAGE_BYTES` /
+    /// `OPENPORTIO_GRPC_{ACCEPT,SEND}_COMPRESSION` env values, which the built-in Greeter keeps.
+    /// Ready-made servers apply it themselves with [`grpc_limits::GrpcMessageConfig::configure`].
+    pub fn with_grpc_message_config(mut self, config: grpc_limits::GrpcMessageConfig) -> Self {
+        self.middleware_config.grpc_messages = config;
+        self
+    }
+
+    pub fn with_socket_config(mut self, config: transport::SocketConfig) -> Self {
+        self.socket_config = config;
+        self
+    }
+
+    /// Retries binding an address that is in use, and in the `local` environment falls back to
+    /// an ephemeral port; replaces the `OPENPORTIO_BIND_RETRY_*` /
+    /// `OPENPORTIO_BIND_EPHEMERAL_FALLBACK` env values.
+    pub fn with_bind_retry(mut self, retry: transport::BindRetry) -> Self {
+        self.bind_retry = retry;
+        self
+    }
+
+    /// Sets `SO_REUSEPORT` so several processes can bind the same address (one per core); the
+    /// kernel balances new connections between them.
+    pub fn reuse_port(mut self, enabled: bool) -> Self {
+        self.socket_config.reuse_port = enabled;
+        self
+    }
+
+    pub fn with_tcp_backlog(mut self, backlog: u32) -> Self {
+        self.socket_config.backlog = backlog;
+        self
+    }
+
+    pub fn with_tcp_nodelay(mut self, enabled: bool) -> Self {
+        self.socket_config.nodelay = enabled;
+        self
+    }
+
+    pub fn with_tcp_keepalive(mut self, idle: Duration) -> Self {
+        self.socket_config.keepalive = Some(idle);
+        self
+    }
+
+    /// Number of tasks accepting on each listener; raise it under heavy connection churn.
+    pub fn with_accept_workers(mut self, workers: usize) -> Self {
+        self.socket_config.accept_workers = workers;
+        self
+    }
+
     pub fn with_middleware<F>(mut self, f: F) -> Self
     where
         F: Fn(Router) -> Router + Send + Sync + 'static,
@@ -146,11 +688,37 @@ impl OpenportioServer {
         self
     }
 
-    pub fn on_startup<F>(mut self, hook: F) -> Self
+    /// Runs once per bound listener address; shorthand for an infallible
+    /// [`lifecycle::Phase::ListenersBound`] hook.
+    pub fn on_startup<F>(self, hook: F) -> Self
     where
         F: Fn(SocketAddr) + Send + Sync + 'static,
     {
-        self.startup_hooks.push(Box::new(hook));
+        self.on_phase(Phase::ListenersBound, move |context| {
+            for addr in &context.addrs {
+                hook(*addr);
+            }
+            std::future::ready(Ok(()))
+        })
+    }
+
+    /// Runs `hook` when `run()` reaches `phase`; a failing hook aborts startup unless the phase's
+    /// policy is [`lifecycle::PhaseFailurePolicy::Continue`].
+    pub fn on_phase<F, Fut>(mut self, phase: Phase, hook: F) -> Self
+    where
+        F: Fn(lifecycle::PhaseContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), tower::BoxError>> + Send + 'static,
+    {
+        self.lifecycle.on_phase(phase, hook);
+        self
+    }
+
+    pub fn with_phase_failure_policy(
+        mut self,
+        phase: Phase,
+        policy: lifecycle::PhaseFailurePolicy,
+    ) -> Self {
+        self.lifecycle.set_policy(phase, policy);
         self
     }
 
@@ -162,334 +730,2803 @@ impl OpenportioServer {
         self
     }
 
-    pub fn build_app(&self) -> Router {
-        let merged = match self.build_grpc_router() {
-            Some(grpc_router) => self.build_rest_router().merge(grpc_router),
-            None => self.build_rest_router(),
-        };
-        self.finalize_router(merged)
+    /// How long to keep serving after readiness starts failing on shutdown, so load balancers
+    /// notice before connections are refused (default 0, or `OPENPORTIO_PRE_STOP_DELAY_SECONDS`).
+    pub fn with_pre_stop_delay(mut self, delay: Duration) -> Self {
+        self.drainer.pre_stop_delay = delay;
+        self
     }
 
-    pub async fn run(self) -> Result<(), Box<dyn std::error::Error>> {
-        match self.dual_port_addrs()? {
-            Some((rest_addr, grpc_addr)) => self.run_dual_port(rest_addr, grpc_addr).await,
-            None => self.run_single_port().await,
-        }
+    /// Awaited when shutdown starts, right after `/readyz` starts failing and before the
+    /// pre-stop delay; deregister from service discovery here.
+    pub fn on_drain_start<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.drainer.on_start(Arc::new(move || Box::pin(hook())));
+        self
     }
 
-    fn build_rest_router(&self) -> Router {
-        let rest = self
-            .rest_router
-            .clone()
-            .unwrap_or_else(|| build_router(self.state.clone()));
-        self.raw_routers
-            .iter()
-            .cloned()
-            .fold(rest, |acc, router| acc.merge(router))
+    /// Awaited once the listeners are closed and every in-flight connection has finished.
+    pub fn on_drain_complete<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.drainer.on_complete(Arc::new(move || Box::pin(hook())));
+        self
     }
 
-    fn build_grpc_router(&self) -> Option<Router> {
-        self.grpc_routes
-            .clone()
-            .map(|routes| routes.into_axum_router())
+    /// How long each drain hook may run before shutdown moves on without it (default 10
+    /// seconds, or `OPENPORTIO_DRAIN_HOOK_TIMEOUT_SECONDS`).
+    pub fn with_drain_hook_timeout(mut self, timeout: Duration) -> Self {
+        self.drainer.hook_timeout = timeout;
+        self
     }
 
-    fn finalize_router(&self, router: Router) -> Router {
-        let app = middleware::apply_shared_middleware(router, &self.middleware_config);
-        let app = di::with_dependency_overrides(app, self.dependency_overrides.clone());
-        self.middleware_customizers
-            .iter()
-            .fold(app, |acc, customizer| customizer(acc))
+    /// Handle reporting whether shutdown draining has started.
+    pub fn drain_state(&self) -> drain::DrainState {
+        self.drainer.state.clone()
     }
 
-    fn dual_port_addrs(
-        &self,
-    ) -> Result<Option<(SocketAddr, SocketAddr)>, Box<dyn std::error::Error>> {
-        match (self.rest_addr, self.grpc_addr) {
-            (None, None) => Ok(None),
-            (Some(rest), Some(grpc)) => Ok(Some((rest, grpc))),
-            _ => Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "dual-port mode requires both with_rest_addr(...) and with_grpc_addr(...)",
-            )
-            .into()),
-        }
+    /// Close frame and drain window for open WebSocket sessions on shutdown (default
+    /// `1001 server shutting down` and 5 seconds, or `OPENPORTIO_WS_SHUTDOWN_*`).
+    pub fn with_ws_shutdown(mut self, config: ws::WsShutdownConfig) -> Self {
+        self.drainer.ws = ws::WsShutdown::new(config);
+        self
     }
 
-    async fn run_single_port(self) -> Result<(), Box<dyn std::error::Error>> {
-        let app = self.build_app();
-        let listener = TcpListener::bind(self.addr).await?;
+    /// Handle custom `WsGate`s register with (`WsGate::with_shutdown`) so their sessions are
+    /// closed cleanly on shutdown; also available to handlers as `Extension<WsShutdown>`. Call
+    /// it after `with_ws_shutdown`.
+    pub fn ws_shutdown(&self) -> ws::WsShutdown {
+        self.drainer.ws.clone()
+    }
 
-        for hook in &self.startup_hooks {
-            hook(self.addr);
-        }
-        tracing::info!(addr = %self.addr, "openportio-server listening");
+    /// Spawns `task` once the listener is bound; it is aborted when the server stops.
+    pub fn with_background_task<F, Fut>(mut self, name: impl Into<String>, task: F) -> Self
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.background_tasks
+            .push((name.into(), Box::new(move || Box::pin(task()))));
+        self
+    }
 
-        let shutdown_hooks = self.shutdown_hooks;
-        axum::serve(listener, app)
-            .with_graceful_shutdown(async move {
-                let _ = tokio::signal::ctrl_c().await;
-                for hook in &shutdown_hooks {
-                    hook();
-                }
-            })
-            .await?;
-        Ok(())
+    /// Replaces the default `HttpClientFactory` (built from `HttpClientConfig::from_env()` and
+    /// reporting to the state's metrics) handed to `Depends<HttpClientFactory>`.
+    pub fn with_http_client_factory(self, factory: http_client::HttpClientFactory) -> Self {
+        self.with_dependency(factory)
     }
 
-    async fn run_dual_port(
-        self,
-        rest_addr: SocketAddr,
-        grpc_addr: SocketAddr,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let grpc_router = self.build_grpc_router().ok_or_else(|| {
-            io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "dual-port mode requires gRPC routes; do not combine with without_grpc()",
-            )
-        })?;
-        let rest_app = self.finalize_router(self.build_rest_router());
-        let grpc_app = self.finalize_router(grpc_router);
+    /// Registers the relay's `Outbox` as a dependency (gRPC handlers call
+    /// `GrpcContext::depends::<Outbox>()`) and runs the relay as the `outbox-relay` background
+    /// task.
+    pub fn with_outbox_relay(self, relay: openportio_core::OutboxRelay) -> Self {
+        self.with_dependency(relay.outbox().clone())
+            .with_background_task("outbox-relay", move || relay.run())
+    }
 
-        let rest_listener = TcpListener::bind(rest_addr).await?;
-        let grpc_listener = TcpListener::bind(grpc_addr).await?;
+    /// Publishes tokio runtime and connection gauges to the state's `MetricsSink` while the
+    /// server runs; see [`runtime_metrics::RuntimeMetrics`].
+    pub fn with_runtime_metrics(mut self, config: runtime_metrics::RuntimeMetrics) -> Self {
+        self.runtime_metrics = Some(config);
+        self
+    }
 
-        for hook in &self.startup_hooks {
-            hook(rest_addr);
-        }
-        for hook in &self.startup_hooks {
-            hook(grpc_addr);
-        }
-        tracing::info!(addr = %rest_addr, mode = "dual-port", protocol = "rest", "openportio-server listening");
-        tracing::info!(addr = %grpc_addr, mode = "dual-port", protocol = "grpc", "openportio-server listening");
+    /// Makes `metrics` the state's `MetricsSink` and serves it at `GET /metrics` in the
+    /// Prometheus text format; see [`prometheus::PrometheusMetrics`]. Call it after
+    /// `with_state(...)`, which replaces the sink.
+    pub fn with_prometheus_metrics(mut self, metrics: Arc<prometheus::PrometheusMetrics>) -> Self {
+        Arc::make_mut(&mut self.state).metrics = metrics.clone();
+        self.merge_raw_router(metrics.router())
+    }
 
-        let (shutdown_tx, _) = watch::channel(false);
-        let mut rest_shutdown = shutdown_tx.subscribe();
-        let mut grpc_shutdown = shutdown_tx.subscribe();
+    /// Adds a REST route to `describe()`; routers passed to `with_rest_router(...)` and
+    /// `merge_raw_router(...)` are opaque and are not listed on their own.
+    pub fn with_route_description(mut self, route: introspection::RestRouteDescription) -> Self {
+        self.route_descriptions.push(route);
+        self
+    }
 
-        let rest_server = axum::serve(rest_listener, rest_app)
-            .with_graceful_shutdown(async move {
-                let _ = rest_shutdown.changed().await;
-            })
-            .into_future();
-        let grpc_server = axum::serve(grpc_listener, grpc_app)
-            .with_graceful_shutdown(async move {
-                let _ = grpc_shutdown.changed().await;
-            })
-            .into_future();
+    pub fn with_openapi_routes(mut self, doc: &utoipa::openapi::OpenApi) -> Self {
+        self.route_descriptions
+            .extend(introspection::rest_routes_from_openapi(doc));
+        self
+    }
 
-        tokio::pin!(rest_server);
-        tokio::pin!(grpc_server);
+    /// Customizes how extractor rejections (malformed bodies, queries and paths, missing
+    /// extensions, unmatched methods) become error responses, for `Validated*` extractors and
+    /// axum's own plain-text rejections alike.
+    pub fn with_rejection_handler<F>(self, handler: F) -> Self
+    where
+        F: Fn(&api::RequestRejection) -> api::ApiError + Send + Sync + 'static,
+    {
+        self.with_dependency(api::RejectionHandler::new(handler))
+    }
 
-        let first_exit = tokio::select! {
-            _ = tokio::signal::ctrl_c() => None,
-            result = &mut rest_server => Some(("rest", result)),
-            result = &mut grpc_server => Some(("grpc", result)),
-        };
+    /// Registers the tenant resolution policy used by the `TenantContext` extractor.
+    pub fn with_tenancy(self, tenancy: tenancy::Tenancy) -> Self {
+        self.with_dependency(tenancy)
+    }
 
-        let _ = shutdown_tx.send(true);
+    /// Registers the authorization policy consulted by `policy::require_policy(...)` guards.
+    pub fn with_policy(self, engine: impl policy::PolicyEngine) -> Self {
+        self.with_dependency(policy::Policy::new(engine))
+    }
 
-        let (rest_result, grpc_result) = match first_exit {
-            Some(("rest", result)) => (result, grpc_server.await),
-            Some(("grpc", result)) => (rest_server.await, result),
-            None => (rest_server.await, grpc_server.await),
-            Some((_other, _result)) => unreachable!("only rest/grpc branches are possible"),
-        };
+    /// Registers daily/monthly call quotas charged to the authenticated subject or API key.
+    pub fn with_quotas(self, quotas: quota::Quotas) -> Self {
+        self.with_dependency(quotas)
+    }
 
-        for hook in &self.shutdown_hooks {
-            hook();
-        }
+    /// Registers a token deny-list consulted by the auth middleware and gRPC interceptor. With
+    /// `with_admin_routes()`, `POST /internal/revocations` adds entries to it.
+    pub fn with_revocation_store(self, store: impl revocation::TokenRevocationStore) -> Self {
+        self.with_dependency(revocation::RevocationList::new(store))
+    }
 
-        rest_result?;
-        grpc_result?;
-        Ok(())
+    /// Counts failed token checks per client IP (and subject, if the policy tracks it), delaying
+    /// and then blocking repeat offenders; see [`brute_force::BruteForcePolicy`].
+    pub fn with_brute_force_protection(
+        self,
+        protection: brute_force::BruteForceProtection,
+    ) -> Self {
+        self.with_dependency(protection)
     }
-}
 
-impl Default for OpenportioServer {
-    fn default() -> Self {
-        Self::new()
+    /// Sends login, scope-denial, admin-access and config-change events to `log` instead of
+    /// the default `tracing` sink (target `audit`); see [`audit::JsonlAuditLog`].
+    pub fn with_audit_log(self, log: impl audit::AuditLog) -> Self {
+        self.with_dependency(audit::AuditLogger::new(log))
     }
-}
 
-fn load_addr_from_env() -> Result<SocketAddr, Box<dyn std::error::Error>> {
-    match read_env_with_aliases(&[
-        "OPENPORTIO_SERVER_ADDR",
-        "MELD_SERVER_ADDR",
-        "ALLOY_SERVER_ADDR",
-    ]) {
-        Ok(raw) => Ok(raw.parse()?),
-        Err(env::VarError::NotPresent) => Ok(SocketAddr::from(([127, 0, 0, 1], 3000))),
-        Err(err) => Err(Box::new(err)),
+    /// Shares `mode` with the maintenance middleware so the app can toggle it from code; with
+    /// `with_admin_routes()` it is also switchable via `PUT /internal/maintenance`.
+    pub fn with_maintenance_mode(mut self, mode: maintenance::MaintenanceMode) -> Self {
+        self.middleware_config.maintenance = mode;
+        self
     }
-}
 
-fn read_env_with_aliases(names: &[&str]) -> Result<String, env::VarError> {
-    for name in names {
-        match env::var(name) {
-            Ok(value) => return Ok(value),
-            Err(env::VarError::NotPresent) => continue,
-            Err(err) => return Err(err),
-        }
+    /// Adds `X-Response-Time-Ms` and a `Server-Timing` phase breakdown to every response (see
+    /// [`timings::RequestTimings`]); ignored in the `production` environment.
+    pub fn with_debug_timings(mut self) -> Self {
+        self.middleware_config.debug_timings = true;
+        self
     }
-    Err(env::VarError::NotPresent)
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use axum::{
-        body::to_bytes,
-        body::Body,
-        extract::FromRef,
-        http::{Request, StatusCode},
-        routing::get,
-    };
-    use std::sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc, LazyLock, Mutex,
-    };
-    use tonic::service::Routes;
+    /// Sheds REST and gRPC calls beyond an in-flight limit that follows observed latency (env:
+    /// `OPENPORTIO_ADAPTIVE_CONCURRENCY=aimd|gradient`); see
+    /// [`adaptive_concurrency::with_adaptive_concurrency`].
+    pub fn with_adaptive_concurrency(
+        mut self,
+        config: adaptive_concurrency::AdaptiveConcurrency,
+    ) -> Self {
+        self.middleware_config.adaptive_concurrency = Some(config);
+        self
+    }
+
+    /// Caps in-flight calls per principal (or client IP) so one client cannot take the whole
+    /// global budget (env: `OPENPORTIO_MAX_IN_FLIGHT_PER_CLIENT`); see
+    /// [`client_concurrency::with_client_concurrency`].
+    pub fn with_client_concurrency(
+        mut self,
+        config: client_concurrency::ClientConcurrency,
+    ) -> Self {
+        self.middleware_config.client_concurrency = Some(config);
+        self
+    }
+
+    /// Cancels handlers for `route` (REST template or gRPC method path) after `timeout` and
+    /// answers `504` / `DEADLINE_EXCEEDED`, independently of the total request timeout; see
+    /// [`handler_timeout::with_handler_timeouts`].
+    pub fn with_handler_timeout(mut self, route: impl Into<String>, timeout: Duration) -> Self {
+        self.middleware_config.handler_timeouts =
+            std::mem::take(&mut self.middleware_config.handler_timeouts).with_route(route, timeout);
+        self
+    }
+
+    /// Handler timeout for routes without their own (env: `OPENPORTIO_HANDLER_TIMEOUT_MS`).
+    pub fn with_default_handler_timeout(mut self, timeout: Duration) -> Self {
+        self.middleware_config.handler_timeouts =
+            std::mem::take(&mut self.middleware_config.handler_timeouts).with_default(timeout);
+        self
+    }
+
+    /// Logs a warning and counts `slow_requests` for every REST or gRPC call slower than
+    /// `threshold` (env: `OPENPORTIO_SLOW_REQUEST_THRESHOLD_MS`).
+    pub fn with_slow_request_threshold(mut self, threshold: Duration) -> Self {
+        self.middleware_config.slow_request_threshold = Some(threshold);
+        self
+    }
+
+    /// Sends an alert when a route's rolling error or 5xx rate crosses `rules` (env:
+    /// `OPENPORTIO_ALERT_ERROR_RATE`, `OPENPORTIO_ALERT_SERVER_ERROR_RATE`).
+    pub fn with_error_rate_alerts(mut self, rules: alerts::AlertRules) -> Self {
+        self.middleware_config.error_alerts = Some(rules);
+        self
+    }
+
+    /// Delivers error-rate alerts to `sink` instead of the default `tracing` sink (target
+    /// `alerts`); see [`alerts::WebhookAlertSink`].
+    pub fn with_alert_sink(self, sink: impl alerts::AlertSink) -> Self {
+        self.with_dependency(alerts::AlertNotifier::new(sink))
+    }
+
+    /// Reports every 5xx, gRPC server error and handler panic to `reporter` with the route,
+    /// principal and request id; see `error_reporting::SentryReporter` (feature `sentry`).
+    pub fn with_error_reporter(self, reporter: impl error_reporting::ErrorReporter) -> Self {
+        self.with_dependency(error_reporting::SharedErrorReporter::new(reporter))
+    }
+
+    /// Redirects (`308`) requests whose path has the non-canonical trailing slash, ahead of
+    /// routing (env: `OPENPORTIO_TRAILING_SLASH`).
+    pub fn with_trailing_slash(mut self, policy: redirect::TrailingSlash) -> Self {
+        self.middleware_config.path_normalization.trailing_slash = policy;
+        self
+    }
+
+    /// Redirects (`308`) paths with uppercase letters to their lowercase form (env:
+    /// `OPENPORTIO_LOWERCASE_PATHS`).
+    pub fn with_lowercase_paths(mut self) -> Self {
+        self.middleware_config.path_normalization.lowercase = true;
+        self
+    }
+
+    /// Default response headers, per-prefix overrides, and headers to strip (env:
+    /// `OPENPORTIO_RESPONSE_HEADERS`, `OPENPORTIO_REMOVE_RESPONSE_HEADERS`).
+    pub fn with_response_headers(mut self, headers: response_headers::ResponseHeaders) -> Self {
+        self.middleware_config.response_headers = headers;
+        self
+    }
+
+    /// Replaces the `OPENPORTIO_CORS_*` env policy; insecure combinations are logged at startup
+    /// and listed in diagnostics.
+    pub fn with_cors_policy(mut self, policy: cors::CorsPolicy) -> Self {
+        self.middleware_config.cors = policy;
+        self
+    }
+
+    /// Also listens on plain HTTP and redirects every request there to HTTPS (env:
+    /// `OPENPORTIO_HTTPS_REDIRECT_ADDR`, `OPENPORTIO_HTTPS_REDIRECT_PORT`).
+    pub fn with_https_redirect(mut self, redirect: redirect::HttpsRedirect) -> Self {
+        self.https_redirect = Some(redirect);
+        self
+    }
+
+    /// Serves the admin router (`/internal/routes`, diagnostics, maintenance, ...) next to the
+    /// REST routes. Admin calls always need a bearer token with [`auth::ADMIN_SCOPE`], verified
+    /// with [`Self::with_admin_auth`], else the `require_auth` config, else
+    /// `AuthRuntimeConfig::from_env()`.
+    pub fn with_admin_routes(mut self) -> Self {
+        self.admin_routes = true;
+        self
+    }
+
+    /// Token verification for the admin router, independent of the app's own auth.
+    pub fn with_admin_auth(mut self, mut auth_cfg: auth::AuthRuntimeConfig) -> Self {
+        auth_cfg.enabled = true;
+        self.admin_auth = Some(auth_cfg);
+        self
+    }
+
+    fn admin_auth_config(&self) -> auth::AuthRuntimeConfig {
+        let mut auth_cfg = self
+            .admin_auth
+            .clone()
+            .or_else(|| self.required_auth.clone())
+            .unwrap_or_else(|| {
+                auth::AuthRuntimeConfig::or_reject_all(
+                    auth::AuthRuntimeConfig::from_env_without_prefetch(),
+                )
+            });
+        auth_cfg.enabled = true;
+        auth_cfg
+    }
+
+    /// Verifies HMAC request signatures (webhook-style callers) before handlers run, independently
+    /// of `require_auth`; scope it with `RequestSigning::with_path_prefix`.
+    pub fn with_request_signing(mut self, signing: signing::RequestSigning) -> Self {
+        self.request_signing = Some(signing);
+        self
+    }
+
+    /// Logs `info` on startup and, with `with_admin_routes()`, serves it at `/internal/buildinfo`;
+    /// capture it with `openportio_buildinfo!()` in the application crate.
+    pub fn with_build_info(mut self, info: buildinfo::BuildInfo) -> Self {
+        self.build_info = Some(info);
+        self
+    }
+
+    /// With `with_admin_routes()`, serves `GET`/`PUT /internal/logging` to inspect and change
+    /// the log filter at runtime; create the handle with `log_filter::reloadable_env_filter`.
+    pub fn with_log_filter(mut self, handle: log_filter::LogFilterHandle) -> Self {
+        self.log_filter = Some(handle);
+        self
+    }
+
+    pub fn describe(&self) -> introspection::AppDescription {
+        let mut rest_routes = match self.rest_router {
+            Some(_) => Vec::new(),
+            None => crate::default_rest_route_descriptions(),
+        };
+        if !self.serves_docs() {
+            rest_routes.retain(|route| !DOCS_PATHS.contains(&route.path.as_str()));
+        }
+        rest_routes.extend(self.route_descriptions.iter().cloned());
+        for nested in &self.nested_routers {
+            if let Some(doc) = &nested.openapi {
+                rest_routes.extend(introspection::rest_routes_from_openapi(doc));
+                if self.serves_docs() {
+                    rest_routes.extend([nested.openapi_path(), nested.docs_path()].map(|path| {
+                        introspection::RestRouteDescription::new(path).with_method("GET")
+                    }));
+                }
+            }
+        }
+        if self.admin_routes {
+            rest_routes.push(
+                introspection::RestRouteDescription::new(introspection::ROUTES_PATH)
+                    .with_method("GET"),
+            );
+            rest_routes.push(
+                introspection::RestRouteDescription::new(diagnostics::DIAGNOSTICS_PATH)
+                    .with_method("GET"),
+            );
+            rest_routes.extend(["GET", "PUT"].map(|method| {
+                introspection::RestRouteDescription::new(maintenance::MAINTENANCE_PATH)
+                    .with_method(method)
+            }));
+            if self.build_info.is_some() {
+                rest_routes.push(
+                    introspection::RestRouteDescription::new(buildinfo::BUILDINFO_PATH)
+                        .with_method("GET"),
+                );
+            }
+            if self.log_filter.is_some() {
+                rest_routes.extend(["GET", "PUT"].map(|method| {
+                    introspection::RestRouteDescription::new(log_filter::LOGGING_PATH)
+                        .with_method(method)
+                }));
+            }
+            if self.revocation_list().is_some() {
+                rest_routes.push(
+                    introspection::RestRouteDescription::new(revocation::REVOCATIONS_PATH)
+                        .with_method("POST"),
+                );
+            }
+        }
+
+        let grpc_services = match self.grpc_routes {
+            Some(_) => self
+                .grpc_service_names
+                .iter()
+                .map(|name| introspection::GrpcServiceDescription {
+                    name: name.clone(),
+                    methods: self
+                        .grpc_catalog
+                        .iter()
+                        .find(|service| &service.name == name)
+                        .map(|service| service.methods.clone())
+                        .unwrap_or_default(),
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        introspection::AppDescription {
+            service_name: self.state.config.service_name.clone(),
+            middleware: self
+                .middleware_config
+                .layer_names()
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            rest_routes,
+            grpc_services,
+            background_tasks: self
+                .background_tasks
+                .iter()
+                .map(|(name, _)| name.clone())
+                .chain(self.runtime_metrics.map(|_| "runtime-metrics".to_string()))
+                .collect(),
+            modules: self.modules.clone(),
+        }
+    }
+
+    /// Resolved configuration, enabled features, listeners, and misconfiguration warnings; logged
+    /// on boot and served at `/internal/diagnostics` with `with_admin_routes()`.
+    pub fn diagnostics(&self) -> diagnostics::DiagnosticsReport {
+        let mut listeners = match self.dual_port_addrs() {
+            Ok(Some((rest_addr, grpc_addr))) => vec![
+                diagnostics::ListenerDescription::new("rest", rest_addr),
+                match &self.grpc_tls {
+                    Some(tls) => diagnostics::ListenerDescription::new("grpc", grpc_addr)
+                        .with_tls(tls.mode()),
+                    None => diagnostics::ListenerDescription::new("grpc", grpc_addr),
+                },
+            ],
+            _ => vec![diagnostics::ListenerDescription::new(
+                if self.grpc_routes.is_some() {
+                    "rest+grpc"
+                } else {
+                    "rest"
+                },
+                self.addr,
+            )],
+        };
+        if let Some(redirect) = &self.https_redirect {
+            listeners.push(diagnostics::ListenerDescription::new(
+                "https_redirect",
+                redirect.addr(),
+            ));
+        }
+        let auth_cfg = self.required_auth.clone().unwrap_or_else(|| {
+            auth::AuthRuntimeConfig::or_reject_all(
+                auth::AuthRuntimeConfig::from_env_without_prefetch(),
+            )
+        });
+        let auth_cfg = match self.revocation_list() {
+            Some(list) if auth_cfg.revocation_list().is_none() => {
+                auth_cfg.with_revocation_list(list)
+            }
+            _ => auth_cfg,
+        };
+        let description = self.describe();
+
+        diagnostics::collect(diagnostics::DiagnosticsInput {
+            service_name: &self.state.config.service_name,
+            environment: self.environment().as_str(),
+            addr: self.addr,
+            listeners,
+            middleware: &self.middleware_config,
+            auth: &auth_cfg,
+            route_count: description.rest_routes.len(),
+            grpc_services: description
+                .grpc_services
+                .into_iter()
+                .map(|service| service.name)
+                .collect(),
+            admin_auth: self.admin_routes.then(|| self.admin_auth_config()).as_ref(),
+            profile: self.profile,
+            docs: self.serves_docs(),
+            quotas: self
+                .dependency_overrides
+                .get::<quota::Quotas>()
+                .map(|quotas| quotas.summary()),
+            error_reporter: self
+                .dependency_overrides
+                .get::<error_reporting::SharedErrorReporter>()
+                .map(|reporter| reporter.name()),
+            socket: self.socket_config.summary(),
+            request_signing: self
+                .request_signing
+                .as_ref()
+                .map(signing::RequestSigning::summary),
+            api_versioning: self
+                .header_versioning
+                .as_ref()
+                .map(versioning::HeaderVersioning::summary),
+        })
+    }
+
+    /// Assembles the app without serving it, failing when the dependency graph has missing or
+    /// cyclic entries. Serve it with connect info (`into_make_service_with_connect_info`) so
+    /// client IPs resolve; brute-force protection and IP rate limits key on them.
+    pub fn build_app(&self) -> Result<Router, di::DependencyGraphError> {
+        self.assemble_app()
+    }
+
+    fn assemble_app(&self) -> Result<Router, di::DependencyGraphError> {
+        let overrides = self.resolve_dependencies()?;
+        let merged = match self.build_grpc_router() {
+            Some(grpc_router) => self.build_rest_router().merge(grpc_router),
+            None => self.build_rest_router(),
+        };
+        Ok(self.finalize_router(merged, &overrides))
+    }
+
+    /// Serves until a shutdown signal. Startup and serving failures come back as a
+    /// [`ServerError`]; a panicking background task drains the server and stops it with
+    /// [`ServerError::BackgroundTaskPanicked`].
+    pub async fn run(mut self) -> Result<(), ServerError> {
+        if !self.env_errors.is_empty() {
+            return Err(ServerError::InvalidConfig(self.env_errors.join("; ")));
+        }
+        self.http2_config = self.effective_http2_config();
+        self.http2_config
+            .validate()
+            .map_err(|err| ServerError::InvalidConfig(err.to_string()))?;
+        self.socket_config
+            .validate()
+            .map_err(|err| ServerError::InvalidConfig(err.to_string()))?;
+        let dual_port_addrs = self.dual_port_addrs()?;
+        self.run_phase(Phase::Config, &[]).await?;
+        self.run_phase(Phase::StateInit, &[]).await?;
+        match dual_port_addrs {
+            Some((rest_addr, grpc_addr)) => self.run_dual_port(rest_addr, grpc_addr).await,
+            None => self.run_single_port().await,
+        }
+    }
+
+    async fn run_phase(&self, phase: Phase, addrs: &[SocketAddr]) -> Result<(), PhaseError> {
+        self.lifecycle.run(phase, &self.app_state(), addrs).await
+    }
+
+    fn build_rest_router(&self) -> Router {
+        let rest = self
+            .rest_router
+            .clone()
+            .unwrap_or_else(|| match self.serves_docs() {
+                true => build_router(self.app_state()),
+                false => build_router_without_docs(self.app_state()),
+            });
+        let rest = if self.admin_routes {
+            let admin = Router::new()
+                .merge(introspection::routes_router(self.describe()))
+                .merge(diagnostics::diagnostics_router(self.diagnostics()))
+                .merge(maintenance::maintenance_router(
+                    self.middleware_config.maintenance.clone(),
+                ));
+            let admin = match &self.build_info {
+                Some(info) => admin.merge(buildinfo::buildinfo_router(info.clone())),
+                None => admin,
+            };
+            let admin = match self.revocation_list() {
+                Some(list) => admin.merge(revocation::revocations_router(list)),
+                None => admin,
+            };
+            let admin = match &self.log_filter {
+                Some(handle) => admin.merge(log_filter::logging_router(handle.clone())),
+                None => admin,
+            };
+            let admin = admin
+                .layer(axum::middleware::from_fn(audit::admin_access_middleware))
+                .layer(axum::middleware::from_fn_with_state(
+                    self.admin_auth_config(),
+                    auth::admin_auth_middleware,
+                ));
+            rest.merge(admin)
+        } else {
+            rest
+        };
+        let rest = self
+            .nested_routers
+            .iter()
+            .cloned()
+            .fold(rest, |acc, mut nested| {
+                if !self.serves_docs() {
+                    nested.openapi = None;
+                }
+                // `require_auth` protects every nested route, so document them that way.
+                if self.required_auth.is_some() {
+                    nested.openapi = nested.openapi.map(api_docs::require_bearer_auth);
+                }
+                acc.merge(nested.into_router())
+            });
+        let rest = self.sse_topics.iter().fold(rest, |acc, (path, topic)| {
+            acc.merge(sse::sse_topic_router(
+                path,
+                topic.clone(),
+                self.state.events.clone(),
+                self.state.metrics.clone(),
+            ))
+        });
+        self.raw_routers
+            .iter()
+            .cloned()
+            .fold(rest, |acc, router| acc.merge(router))
+    }
+
+    fn take_background_tasks(&mut self) -> Vec<(String, BackgroundTask)> {
+        let mut tasks = std::mem::take(&mut self.background_tasks);
+        if self.middleware_config.maintenance.flag_file().is_some() {
+            let mode = self.middleware_config.maintenance.clone();
+            tasks.push((
+                "maintenance-flag-file".to_string(),
+                Box::new(move || Box::pin(mode.watch_flag_file())),
+            ));
+        }
+        if let Some(config) = self.runtime_metrics {
+            let metrics = self.state.metrics.clone();
+            tasks.push((
+                "runtime-metrics".to_string(),
+                Box::new(move || Box::pin(config.run(metrics))),
+            ));
+        }
+        tasks
+    }
+
+    fn revocation_list(&self) -> Option<revocation::RevocationList> {
+        self.dependency_overrides
+            .get::<revocation::RevocationList>()
+    }
+
+    fn build_grpc_routes(&self) -> Option<Routes> {
+        match self.grpc_routes.as_ref()? {
+            GrpcRoutes::Default { auth, services } => Some(
+                services
+                    .iter()
+                    .fold(
+                        grpc::build_grpc_routes_with_auth(self.app_state(), (**auth).clone()),
+                        |routes, add| add(routes),
+                    )
+                    .prepare(),
+            ),
+            GrpcRoutes::Custom(routes) => Some(routes.clone()),
+        }
+    }
+
+    fn build_grpc_router(&self) -> Option<Router> {
+        self.build_grpc_routes().map(|routes| {
+            crate::grpc::with_grpc_observability(routes, self.state.metrics.clone())
+                .into_axum_router()
+        })
+    }
+
+    fn resolve_dependencies(&self) -> Result<di::DependencyOverrides, di::DependencyGraphError> {
+        let order = self.dependency_graph().validate()?;
+        let overrides = order
+            .iter()
+            .filter_map(|key| self.dependency_providers.get(key))
+            .fold(self.dependency_overrides.clone(), |acc, provider| {
+                provider(acc)
+            });
+        let overrides = match overrides.get::<flags::FlagClient>() {
+            Some(_) => overrides,
+            None => overrides.with(flags::FlagClient::new(self.state.flags.clone())),
+        };
+        let overrides = match overrides.get::<Arc<dyn MetricsSink>>() {
+            Some(_) => overrides,
+            None => overrides.with(self.state.metrics.clone()),
+        };
+        let overrides = match overrides.get::<http_client::HttpClientFactory>() {
+            Some(_) => overrides,
+            None => overrides.with(
+                http_client::HttpClientFactory::new(crate::env::or_default(
+                    http_client::HttpClientConfig::from_env(),
+                ))
+                .with_metrics(self.state.metrics.clone()),
+            ),
+        };
+        Ok(match overrides.get::<Environment>() {
+            Some(_) => overrides,
+            None => overrides.with(self.environment().clone()),
+        })
+    }
+
+    fn finalize_router(&self, router: Router, overrides: &di::DependencyOverrides) -> Router {
+        let route_methods = cors::RouteMethods::new(router.clone());
+        let debug_timings = self.debug_timings_enabled();
+        let router = if debug_timings {
+            timings::with_route_timings(router)
+        } else {
+            router
+        };
+        let router = handler_timeout::with_handler_timeouts(
+            router,
+            self.middleware_config.handler_timeouts.clone(),
+            self.state.metrics.clone(),
+        );
+        let router = panics::with_panic_recovery(router, self.state.metrics.clone());
+        let router = di::with_request_teardown(router);
+        let router = match &self.header_versioning {
+            Some(versioning) => versioning.wrap(router),
+            None => router,
+        };
+        // Inside the auth layer so quotas can be charged to the authenticated principal.
+        let router = match overrides.get::<quota::Quotas>() {
+            Some(_) => router.layer(axum::middleware::from_fn(quota::quota_middleware)),
+            None => router,
+        };
+        let router = match &self.middleware_config.client_concurrency {
+            Some(config) => client_concurrency::with_client_concurrency(
+                router,
+                config.clone(),
+                self.state.metrics.clone(),
+            ),
+            None => router,
+        };
+        let router = match &self.required_auth {
+            Some(auth_cfg) => router.layer(axum::middleware::from_fn_with_state(
+                auth_cfg.clone(),
+                auth::required_auth_middleware,
+            )),
+            None => router,
+        };
+        let router = match &self.request_signing {
+            Some(signing) => {
+                let signing = match self.build_grpc_router() {
+                    Some(grpc) => signing.clone().with_grpc_routes(grpc),
+                    None => signing.clone(),
+                };
+                signing::with_request_signing(router, signing)
+            }
+            None => router,
+        };
+        let router = match overrides.get::<api::RejectionHandler>() {
+            Some(handler) => router
+                .method_not_allowed_fallback(api::method_not_allowed)
+                .layer(axum::middleware::from_fn_with_state(
+                    handler,
+                    api::rejection_middleware,
+                )),
+            None => router,
+        };
+        let router = match &self.middleware_config.adaptive_concurrency {
+            Some(config) => adaptive_concurrency::with_adaptive_concurrency(
+                router,
+                config.clone(),
+                self.state.metrics.clone(),
+            ),
+            None => router,
+        };
+        let app = middleware::apply_shared_middleware(router, &self.middleware_config);
+        let app = if self.middleware_config.cors.is_enabled() {
+            app.layer(axum::Extension(route_methods))
+        } else {
+            app
+        };
+        let app = match self.middleware_config.slow_request_threshold {
+            Some(threshold) => slow_requests::with_slow_request_detection(
+                app,
+                threshold,
+                self.state.metrics.clone(),
+            ),
+            None => app,
+        };
+        let app = match &self.middleware_config.error_alerts {
+            Some(rules) => alerts::with_error_rate_alerts(app, rules.clone()),
+            None => app,
+        };
+        let app = match overrides.get::<error_reporting::SharedErrorReporter>() {
+            Some(_) => error_reporting::with_error_reporting(app),
+            None => app,
+        };
+        let app = app
+            .layer(axum::Extension(self.drainer.state.clone()))
+            .layer(axum::Extension(self.drainer.ws.clone()));
+        let app = di::with_grpc_context(app);
+        let app = di::with_service_registry(app, self.state.services.clone());
+        let app = di::with_dependency_overrides(app, overrides.clone());
+        let app = if debug_timings {
+            timings::with_response_timings(app)
+        } else {
+            app
+        };
+        self.middleware_customizers
+            .iter()
+            .fold(app, |acc, customizer| customizer(acc))
+    }
+
+    fn debug_timings_enabled(&self) -> bool {
+        if !self.middleware_config.debug_timings {
+            return false;
+        }
+        if self.environment().is_production() {
+            tracing::warn!("debug timings are never served in production; ignoring");
+            return false;
+        }
+        true
+    }
+
+    fn dual_port_addrs(&self) -> Result<Option<(SocketAddr, SocketAddr)>, ServerError> {
+        match (self.rest_addr, self.grpc_addr) {
+            (None, None) => Ok(None),
+            (Some(rest), Some(grpc)) => Ok(Some((rest, grpc))),
+            _ => Err(ServerError::InvalidDualPortConfig(
+                "dual-port mode requires both with_rest_addr(...) and with_grpc_addr(...) \
+                 (or OPENPORTIO_REST_ADDR and OPENPORTIO_GRPC_ADDR)"
+                    .to_string(),
+            )),
+        }
+    }
+
+    async fn run_single_port(mut self) -> Result<(), ServerError> {
+        if self.grpc_tls.is_some() {
+            return Err(ServerError::InvalidDualPortConfig(
+                "gRPC TLS requires dual-port mode; set with_rest_addr(...) and with_grpc_addr(...)"
+                    .to_string(),
+            ));
+        }
+        let app = self.assemble_app()?;
+        self.run_phase(Phase::DependencyValidation, &[]).await?;
+        let hint = addr_override_hint(self.addrs_set_in_code.addr, "SERVER_ADDR", "with_addr");
+        let listener = self.bind_listener(self.addr, &hint).await?;
+        let addr = listener.local_addr().unwrap_or(self.addr);
+        self.addr = addr;
+        let redirect = self.start_https_redirect().await?;
+
+        if let Err(err) = self.run_phase(Phase::ListenersBound, &[addr]).await {
+            redirect.iter().for_each(JoinHandle::abort);
+            return Err(err.into());
+        }
+        tracing::info!(addr = %addr, "openportio-server listening");
+        if let Some(info) = &self.build_info {
+            buildinfo::log(info);
+        }
+        self.diagnostics().log();
+        let background = BackgroundTasks::spawn(self.take_background_tasks());
+        if let Err(err) = self.run_phase(Phase::BackgroundTasksStarted, &[addr]).await {
+            background.abort();
+            redirect.iter().for_each(JoinHandle::abort);
+            return Err(err.into());
+        }
+
+        let shutdown_hooks = self.shutdown_hooks;
+        let drainer = self.drainer.clone();
+        let panicked = background.panicked();
+        let result = transport::serve(
+            listener,
+            app,
+            &self.http2_config,
+            &self.socket_config,
+            async move {
+                tokio::select! {
+                    _ = drain::shutdown_signal() => {}
+                    _ = panicked => {}
+                }
+                begin_shutdown(&drainer, &shutdown_hooks).await;
+            },
+        )
+        .await;
+        self.drainer.complete().await;
+        background.abort();
+        redirect.iter().for_each(JoinHandle::abort);
+        background.check()?;
+        result.map_err(|source| ServerError::Serve {
+            listener: "main",
+            source,
+        })
+    }
+
+    async fn bind_listener(
+        &self,
+        addr: SocketAddr,
+        override_hint: &str,
+    ) -> Result<TcpListener, ServerError> {
+        transport::bind_with_retry(
+            addr,
+            &self.socket_config,
+            &self.bind_retry,
+            self.environment().is_local(),
+            override_hint,
+        )
+        .await
+        .map_err(|source| ServerError::BindFailed { addr, source })
+    }
+
+    async fn start_https_redirect(&mut self) -> Result<Option<JoinHandle<()>>, ServerError> {
+        if self.https_redirect.is_none() {
+            self.https_redirect =
+                redirect::HttpsRedirect::from_env().map_err(ServerError::InvalidConfig)?;
+        }
+        let Some(redirect) = self.https_redirect else {
+            return Ok(None);
+        };
+        let listener =
+            TcpListener::bind(redirect.addr())
+                .await
+                .map_err(|source| ServerError::BindFailed {
+                    addr: redirect.addr(),
+                    source,
+                })?;
+        tracing::info!(addr = %redirect.addr(), https_port = redirect.https_port(), "https redirect listening");
+        Ok(Some(tokio::spawn(async move {
+            if let Err(err) = axum::serve(listener, redirect.router()).await {
+                tracing::error!(error = %err, "https redirect listener failed");
+            }
+        })))
+    }
+
+    async fn run_dual_port(
+        mut self,
+        rest_addr: SocketAddr,
+        grpc_addr: SocketAddr,
+    ) -> Result<(), ServerError> {
+        let grpc_router = self.build_grpc_router().ok_or_else(|| {
+            ServerError::InvalidDualPortConfig(
+                "dual-port mode requires gRPC routes; do not combine with without_grpc()"
+                    .to_string(),
+            )
+        })?;
+        let overrides = self.resolve_dependencies()?;
+        let rest_app = self.finalize_router(self.build_rest_router(), &overrides);
+        let grpc_app = self.finalize_router(grpc_router, &overrides);
+        self.run_phase(Phase::DependencyValidation, &[]).await?;
+        if self.grpc_tls.is_none() {
+            self.grpc_tls = tls::GrpcTlsConfig::from_env().map_err(ServerError::TlsConfig)?;
+        }
+        let grpc_tls = self
+            .grpc_tls
+            .as_ref()
+            .map(tls::GrpcTlsConfig::server_config)
+            .transpose()
+            .map_err(ServerError::TlsConfig)?;
+
+        let hint = addr_override_hint(self.addrs_set_in_code.rest, "REST_ADDR", "with_rest_addr");
+        let rest_listener = self.bind_listener(rest_addr, &hint).await?;
+        let hint = addr_override_hint(self.addrs_set_in_code.grpc, "GRPC_ADDR", "with_grpc_addr");
+        let grpc_listener = self.bind_listener(grpc_addr, &hint).await?;
+        let redirect = self.start_https_redirect().await?;
+        let rest_addr = rest_listener.local_addr().unwrap_or(rest_addr);
+        let grpc_addr = grpc_listener.local_addr().unwrap_or(grpc_addr);
+        self.rest_addr = Some(rest_addr);
+        self.grpc_addr = Some(grpc_addr);
+
+        if let Err(err) = self
+            .run_phase(Phase::ListenersBound, &[rest_addr, grpc_addr])
+            .await
+        {
+            redirect.iter().for_each(JoinHandle::abort);
+            return Err(err.into());
+        }
+        tracing::info!(addr = %rest_addr, mode = "dual-port", protocol = "rest", "openportio-server listening");
+        tracing::info!(addr = %grpc_addr, mode = "dual-port", protocol = "grpc", "openportio-server listening");
+        if let Some(info) = &self.build_info {
+            buildinfo::log(info);
+        }
+        self.diagnostics().log();
+        let background = BackgroundTasks::spawn(self.take_background_tasks());
+        if let Err(err) = self
+            .run_phase(Phase::BackgroundTasksStarted, &[rest_addr, grpc_addr])
+            .await
+        {
+            background.abort();
+            redirect.iter().for_each(JoinHandle::abort);
+            return Err(err.into());
+        }
+
+        let (shutdown_tx, _) = watch::channel(false);
+        let mut rest_shutdown = shutdown_tx.subscribe();
+        let mut grpc_shutdown = shutdown_tx.subscribe();
+
+        let rest_server = transport::serve(
+            rest_listener,
+            rest_app,
+            &self.http2_config,
+            &self.socket_config,
+            async move {
+                let _ = rest_shutdown.changed().await;
+            },
+        );
+        let grpc_shutdown = async move {
+            let _ = grpc_shutdown.changed().await;
+        };
+        let grpc_server = async {
+            match grpc_tls {
+                Some(tls) => {
+                    transport::serve_tls(
+                        grpc_listener,
+                        grpc_app,
+                        &self.http2_config,
+                        &self.socket_config,
+                        tls,
+                        grpc_shutdown,
+                    )
+                    .await
+                }
+                None => {
+                    transport::serve(
+                        grpc_listener,
+                        grpc_app,
+                        &self.http2_config,
+                        &self.socket_config,
+                        grpc_shutdown,
+                    )
+                    .await
+                }
+            }
+        };
+
+        tokio::pin!(rest_server);
+        tokio::pin!(grpc_server);
+
+        let first_exit = tokio::select! {
+            _ = async {
+                tokio::select! {
+                    _ = drain::shutdown_signal() => {}
+                    _ = background.panicked() => {}
+                }
+                begin_shutdown(&self.drainer, &self.shutdown_hooks).await;
+            } => FirstExit::Shutdown,
+            result = &mut rest_server => FirstExit::Rest(result),
+            result = &mut grpc_server => FirstExit::Grpc(result),
+        };
+
+        if !matches!(first_exit, FirstExit::Shutdown) {
+            // A listener failed before shutdown was requested; drain the other one the same way.
+            begin_shutdown(&self.drainer, &self.shutdown_hooks).await;
+        }
+        let _ = shutdown_tx.send(true);
+
+        let (rest_result, grpc_result) = match first_exit {
+            FirstExit::Rest(result) => (result, grpc_server.await),
+            FirstExit::Grpc(result) => (rest_server.await, result),
+            FirstExit::Shutdown => (rest_server.await, grpc_server.await),
+        };
+
+        self.drainer.complete().await;
+        background.abort();
+        redirect.iter().for_each(JoinHandle::abort);
+
+        background.check()?;
+        rest_result.map_err(|source| ServerError::Serve {
+            listener: "rest",
+            source,
+        })?;
+        grpc_result.map_err(|source| ServerError::Serve {
+            listener: "grpc",
+            source,
+        })
+    }
+}
+
+impl Default for OpenportioServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Which branch ended the dual-port serve loop first.
+enum FirstExit<T> {
+    Shutdown,
+    Rest(T),
+    Grpc(T),
+}
+
+/// Shutdown sequence shared by single- and dual-port mode: readiness fails, drain-start hooks
+/// run, the pre-stop delay passes and WebSocket sessions are closed, then the `on_shutdown`
+/// hooks run. Listeners stop accepting once this resolves; drain-complete hooks run after the
+/// last connection closes.
+async fn begin_shutdown(drainer: &drain::Drainer, shutdown_hooks: &[ShutdownHook]) {
+    drainer.begin().await;
+    for hook in shutdown_hooks {
+        hook();
+    }
+}
+
+async fn mounted_service_error(err: tower::BoxError) -> api::ApiError {
+    tracing::error!(error = %err, "mounted service failed");
+    (
+        http::StatusCode::INTERNAL_SERVER_ERROR,
+        axum::Json(api::ApiErrorResponse {
+            code: "internal_error".to_string(),
+            message: "mounted service failed".to_string(),
+            detail: None,
+            details: None,
+        }),
+    )
+}
+
+/// Spawned background tasks, watched so that a panic in one stops the server.
+struct BackgroundTasks {
+    aborts: Vec<AbortHandle>,
+    monitor: JoinHandle<()>,
+    panic: watch::Receiver<Option<(String, String)>>,
+}
+
+impl BackgroundTasks {
+    fn spawn(tasks: Vec<(String, BackgroundTask)>) -> Self {
+        let handles: Vec<_> = tasks
+            .into_iter()
+            .map(|(name, task)| {
+                tracing::info!(task = %name, "starting background task");
+                (name, tokio::spawn(task()))
+            })
+            .collect();
+        let aborts = handles
+            .iter()
+            .map(|(_, handle)| handle.abort_handle())
+            .collect();
+        let (panic_tx, panic) = watch::channel(None);
+        let monitor = tokio::spawn(watch_background_tasks(handles, panic_tx));
+        Self {
+            aborts,
+            monitor,
+            panic,
+        }
+    }
+
+    /// Resolves once a task panicked; never, if none does.
+    fn panicked(&self) -> impl Future<Output = ()> {
+        let mut panic = self.panic.clone();
+        async move {
+            if panic.wait_for(Option::is_some).await.is_err() {
+                std::future::pending::<()>().await;
+            }
+        }
+    }
+
+    fn check(&self) -> Result<(), ServerError> {
+        match self.panic.borrow().clone() {
+            Some((task, message)) => Err(ServerError::BackgroundTaskPanicked { task, message }),
+            None => Ok(()),
+        }
+    }
+
+    fn abort(&self) {
+        self.monitor.abort();
+        self.aborts.iter().for_each(AbortHandle::abort);
+    }
+}
+
+async fn watch_background_tasks(
+    mut handles: Vec<(String, JoinHandle<()>)>,
+    panic_tx: watch::Sender<Option<(String, String)>>,
+) {
+    while !handles.is_empty() {
+        let (result, index, _) =
+            futures_util::future::select_all(handles.iter_mut().map(|(_, handle)| handle)).await;
+        let (task, _) = handles.swap_remove(index);
+        let Err(err) = result else {
+            continue;
+        };
+        if !err.is_panic() {
+            continue;
+        }
+        let payload = err.into_panic();
+        let message = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("<non-string panic payload>")
+            .to_string();
+        tracing::error!(task = %task, panic = %message, "background task panicked; shutting down");
+        let _ = panic_tx.send(Some((task, message)));
+        return;
+    }
+}
+
+fn default_grpc_catalog() -> Vec<ServiceSummary> {
+    [
+        openportio_rpc::FILE_DESCRIPTOR_SET,
+        tonic_reflection::pb::v1::FILE_DESCRIPTOR_SET,
+        tonic_reflection::pb::v1alpha::FILE_DESCRIPTOR_SET,
+    ]
+    .into_iter()
+    .flat_map(|set| openportio_rpc::describe_services(set).unwrap_or_default())
+    .collect()
+}
+
+fn load_addr_from_env() -> Result<SocketAddr, String> {
+    Ok(crate::env::setting("SERVER_ADDR")?.unwrap_or(SocketAddr::from(([127, 0, 0, 1], 3000))))
+}
+
+/// Connection settings from `with_grpc_keepalive`, `with_max_connection_age` and
+/// `with_max_connection_idle`, kept apart from `with_http2_config` so neither call order drops
+/// them.
+#[derive(Debug, Clone, Copy, Default)]
+struct ConnectionLifecycle {
+    keepalive: Option<(Duration, Duration)>,
+    max_age: Option<(Duration, Duration)>,
+    max_idle: Option<Duration>,
+}
+
+/// Listen addresses chosen by `with_addr` / `with_rest_addr` / `with_grpc_addr` rather than by
+/// `OPENPORTIO_SERVER_ADDR` / `OPENPORTIO_REST_ADDR` / `OPENPORTIO_GRPC_ADDR` or the default.
+#[derive(Debug, Clone, Copy, Default)]
+struct AddrsSetInCode {
+    addr: bool,
+    rest: bool,
+    grpc: bool,
+}
+
+/// The setting a bind failure tells the operator to change: the builder call when the address
+/// was set in code, otherwise the env var (which also replaces the default).
+fn addr_override_hint(set_in_code: bool, env_suffix: &str, builder_method: &str) -> String {
+    if set_in_code {
+        format!("{builder_method}(...)")
+    } else {
+        format!("{}{env_suffix}", crate::env::PREFIX)
+    }
+}
+
+/// Records a malformed setting for [`OpenportioServer::run`] to report, keeping the default
+/// until then.
+fn or_record<T: Default>(loaded: Result<T, String>, errors: &mut Vec<String>) -> T {
+    loaded.unwrap_or_else(|err| {
+        errors.push(err);
+        T::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::to_bytes,
+        body::Body,
+        extract::FromRef,
+        http::{header, Request, StatusCode},
+        routing::get,
+    };
+    use std::env;
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    };
+    use tonic::service::Routes;
     use tower::util::ServiceExt;
 
-    static ENV_LOCK: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
+    use crate::env::ENV_LOCK;
+
+    fn admin_auth() -> auth::AuthRuntimeConfig {
+        let mut auth_cfg = auth::AuthRuntimeConfig::default();
+        auth_cfg.jwt_secret = Some("admin-secret".to_string());
+        auth_cfg
+    }
+
+    fn admin_request(uri: &str) -> http::request::Builder {
+        let token = jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &serde_json::json!({
+                "sub": "ops-1",
+                "exp": 4_102_444_800u64,
+                "scope": auth::ADMIN_SCOPE,
+            }),
+            &jsonwebtoken::EncodingKey::from_secret(b"admin-secret"),
+        )
+        .expect("token should encode");
+        Request::builder()
+            .uri(uri)
+            .header("authorization", format!("Bearer {token}"))
+    }
+
+    fn recording_phases(
+        server: OpenportioServer,
+        calls: &Arc<Mutex<Vec<String>>>,
+        failing: Option<Phase>,
+    ) -> OpenportioServer {
+        use lifecycle::Phase::*;
+        [
+            Config,
+            StateInit,
+            DependencyValidation,
+            ListenersBound,
+            BackgroundTasksStarted,
+        ]
+        .into_iter()
+        .fold(server, |server, phase| {
+            let calls = calls.clone();
+            server.on_phase(phase, move |context| {
+                calls
+                    .lock()
+                    .unwrap()
+                    .push(format!("{phase}:{}", context.addrs.len()));
+                std::future::ready(match failing == Some(phase) {
+                    true => Err("boom".into()),
+                    false => Ok(()),
+                })
+            })
+        })
+    }
+
+    #[tokio::test]
+    async fn run_walks_the_lifecycle_phases_in_order() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let started = Arc::new(tokio::sync::Notify::new());
+        let notify = started.clone();
+        let server = recording_phases(
+            OpenportioServer::new().with_addr(SocketAddr::from(([127, 0, 0, 1], 0))),
+            &calls,
+            None,
+        )
+        .on_startup({
+            let calls = calls.clone();
+            move |addr| {
+                assert_ne!(addr.port(), 0);
+                calls.lock().unwrap().push("on_startup".to_string());
+            }
+        })
+        .on_phase(Phase::BackgroundTasksStarted, move |_| {
+            notify.notify_one();
+            std::future::ready(Ok(()))
+        });
+        tokio::select! {
+            result = server.run() => panic!("server stopped early: {result:?}"),
+            _ = started.notified() => {}
+        }
+        assert_eq!(
+            *calls.lock().unwrap(),
+            [
+                "config:0",
+                "state_init:0",
+                "dependency_validation:0",
+                "listeners_bound:1",
+                "on_startup",
+                "background_tasks_started:1",
+            ]
+        );
+
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let err = recording_phases(OpenportioServer::new(), &calls, Some(Phase::StateInit))
+            .run()
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "startup phase `state_init` failed: boom");
+        assert_eq!(*calls.lock().unwrap(), ["config:0", "state_init:0"]);
+    }
+
+    #[tokio::test]
+    async fn run_reports_failures_as_server_errors() {
+        let err = OpenportioServer::new()
+            .with_rest_addr(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .run()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ServerError::InvalidDualPortConfig(_)));
+        assert_eq!(err.exit_code(), 78);
+
+        let err = tokio::time::timeout(
+            Duration::from_secs(10),
+            OpenportioServer::new()
+                .with_addr(SocketAddr::from(([127, 0, 0, 1], 0)))
+                .with_background_task("outbox", || async { panic!("relay lost its cursor") })
+                .run(),
+        )
+        .await
+        .expect("a panicking background task stops the server")
+        .unwrap_err();
+        assert!(matches!(
+            &err,
+            ServerError::BackgroundTaskPanicked { task, message }
+                if task == "outbox" && message == "relay lost its cursor"
+        ));
+        assert_eq!(err.exit_code(), 70);
+    }
+
+    #[tokio::test]
+    async fn readiness_fails_once_draining_starts() {
+        let server = OpenportioServer::new().with_pre_stop_delay(Duration::from_secs(30));
+        let app = server.build_app().expect("app should build");
+        let readyz = || {
+            Request::builder()
+                .uri("/readyz")
+                .body(Body::empty())
+                .unwrap()
+        };
+        let response = app.clone().oneshot(readyz()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        server.drain_state().start();
+        let response = app.oneshot(readyz()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["status"], "draining");
+    }
+
+    #[tokio::test]
+    async fn builder_creates_working_app() {
+        let app = OpenportioServer::new()
+            .build_app()
+            .expect("app should build");
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/health")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("health request should succeed");
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn builder_supports_custom_rest_and_middleware_chain() {
+        let custom_router = Router::new().route("/custom", get(|| async { "custom-ok" }));
+        let app = OpenportioServer::new()
+            .without_grpc()
+            .with_rest_router(custom_router)
+            .with_middleware(|router| router.route("/ping", get(|| async { "pong" })))
+            .build_app()
+            .expect("app should build");
+
+        let ping_response = app
+            .oneshot(Request::builder().uri("/ping").body(Body::empty()).unwrap())
+            .await
+            .expect("ping request should succeed");
+        assert_eq!(ping_response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn builder_supports_raw_router_merge() {
+        let raw_router = Router::new().route("/metrics", get(|| async { "metrics-ok" }));
+        let app = OpenportioServer::new()
+            .without_grpc()
+            .merge_raw_router(raw_router)
+            .build_app()
+            .expect("app should build");
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/metrics")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("metrics request should succeed");
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("response body");
+        assert_eq!(
+            String::from_utf8(body.to_vec()).expect("utf8"),
+            "metrics-ok"
+        );
+    }
+
+    #[tokio::test]
+    async fn prometheus_metrics_are_fed_by_requests_and_scraped_at_metrics() {
+        let metrics = Arc::new(prometheus::PrometheusMetrics::new());
+        let app = OpenportioServer::new()
+            .without_grpc()
+            .with_prometheus_metrics(metrics.clone())
+            .build_app()
+            .expect("app should build");
+        let mut collector = runtime_metrics::RuntimeCollector::new(
+            tokio::runtime::Handle::current(),
+            metrics.clone(),
+        );
+        collector.collect().await;
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/health")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(prometheus::METRICS_PATH)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(
+            body.contains("# TYPE http_health_requests counter"),
+            "{body}"
+        );
+        assert!(body.contains("# TYPE runtime_workers gauge"), "{body}");
+        assert!(body.contains("server_connections "), "{body}");
+    }
+
+    #[tokio::test]
+    async fn builder_supports_tonic_routes_configuration_hook() {
+        let app = OpenportioServer::new()
+            .configure_tonic(|routes| {
+                let router = routes
+                    .into_axum_router()
+                    .route("/grpc-hook", get(|| async { "grpc-hook-ok" }));
+                Routes::from(router)
+            })
+            .build_app()
+            .expect("app should build");
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/grpc-hook")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("grpc hook request should succeed");
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("response body");
+        assert_eq!(
+            String::from_utf8(body.to_vec()).expect("utf8"),
+            "grpc-hook-ok"
+        );
+    }
+
+    #[tokio::test]
+    async fn nest_versioned_serves_each_version_with_its_own_docs() {
+        use utoipa::openapi::{
+            path::{OperationBuilder, PathItem},
+            HttpMethod, OpenApiBuilder, PathsBuilder,
+        };
+
+        let doc = OpenApiBuilder::new()
+            .paths(PathsBuilder::new().path(
+                "/items",
+                PathItem::new(HttpMethod::Get, OperationBuilder::new()),
+            ))
+            .build();
+        let server = OpenportioServer::new()
+            .without_grpc()
+            .nest_versioned(
+                versioning::ApiVersion::V1,
+                versioning::NestedRouter::new(
+                    Router::new().route("/items", get(|| async { "v1" })),
+                )
+                .with_openapi(doc.clone()),
+            )
+            .nest_versioned(
+                versioning::ApiVersion::V2,
+                versioning::NestedRouter::new(
+                    Router::new().route("/items", get(|| async { "v2" })),
+                )
+                .with_openapi(doc),
+            )
+            .nest(
+                "/legacy",
+                Router::new().route("/items", get(|| async { "legacy" })),
+            );
+        let described = server.describe();
+        assert!(described
+            .rest_routes
+            .iter()
+            .any(|route| route.path == "/v2/items"));
+        let app = server.build_app().expect("app should build");
+
+        for (uri, expected) in [
+            ("/v1/items", "v1"),
+            ("/v2/items", "v2"),
+            ("/legacy/items", "legacy"),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .expect("request should succeed");
+            let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            assert_eq!(bytes, expected);
+        }
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/v2/openapi.json")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("request should succeed");
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let served: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(served["paths"]["/v2/items"]["get"]["tags"][0], "v2");
+        assert!(served["paths"]["/v1/items"].is_null());
+    }
+
+    #[tokio::test]
+    async fn hardened_profile_requires_auth_and_hides_docs() {
+        let mut auth_cfg = auth::AuthRuntimeConfig::default();
+        auth_cfg.jwt_secret = Some("profile-secret".to_string());
+        let server = OpenportioServer::new()
+            .with_profile(profile::Profile::Hardened)
+            .require_auth(auth_cfg);
+        let app = server.build_app().expect("app should build");
+        let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+        let response = app.clone().oneshot(get("/health")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-content-type-options"], "nosniff");
+
+        let response = app.clone().oneshot(get("/hello/ana")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let docs_free = OpenportioServer::new()
+            .without_grpc()
+            .without_docs()
+            .build_app()
+            .expect("app should build");
+        let response = docs_free.oneshot(get("/openapi.json")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let description = server.describe();
+        assert!(description
+            .rest_routes
+            .iter()
+            .all(|route| !DOCS_PATHS.contains(&route.path.as_str())));
+        assert!(description.middleware.contains(&"rate_limit".to_string()));
+
+        let report = server.diagnostics();
+        assert_eq!(report.features["profile"], "hardened");
+        assert_eq!(report.features["docs"], "disabled");
+        assert_eq!(report.features["auth"], "shared_secret");
+    }
+
+    #[tokio::test]
+    async fn production_environment_hides_docs_unless_asked_for() {
+        let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let production = OpenportioServer::new()
+            .without_grpc()
+            .with_environment(Environment::Production);
+        assert_eq!(production.diagnostics().environment, "production");
+        let response = production
+            .build_app()
+            .expect("app should build")
+            .oneshot(get("/openapi.json"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = OpenportioServer::new()
+            .without_grpc()
+            .with_environment("prod")
+            .with_docs()
+            .build_app()
+            .expect("app should build")
+            .oneshot(get("/openapi.json"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn environment_override_survives_a_later_state_and_reaches_state_routes() {
+        let state = Arc::new(AppState::local("builder-test"));
+        let server = OpenportioServer::new()
+            .with_environment(Environment::Staging)
+            .with_state(state.clone());
+        assert_eq!(server.diagnostics().environment, "staging");
+
+        let response = server
+            .build_app()
+            .expect("app should build")
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["environment"], "staging");
+        assert_eq!(state.config.environment, Environment::Local);
+    }
+
+    #[tokio::test]
+    async fn debug_timings_are_served_outside_production_only() {
+        let timed = |environment: Environment| async move {
+            let response = OpenportioServer::new()
+                .without_grpc()
+                .with_environment(environment)
+                .with_debug_timings()
+                .build_app()
+                .expect("app should build")
+                .oneshot(
+                    Request::builder()
+                        .uri("/hello/ana")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            response
+                .headers()
+                .get(timings::SERVER_TIMING_HEADER)
+                .map(|value| value.to_str().unwrap().to_string())
+        };
+
+        let server_timing = timed(Environment::Local)
+            .await
+            .expect("server-timing header");
+        assert!(server_timing.starts_with("middleware;dur="));
+        assert!(server_timing.contains("handler;dur="));
+        assert!(server_timing.contains("total;dur="));
+        assert_eq!(timed(Environment::Production).await, None);
+    }
+
+    #[tokio::test]
+    async fn header_versioning_dispatches_inside_the_shared_middleware_stack() {
+        let server = OpenportioServer::new()
+            .without_grpc()
+            .with_header_versioning(
+                versioning::HeaderVersioning::new()
+                    .version(
+                        versioning::ApiVersion::V1,
+                        Router::new().route("/items", get(|| async { "v1" })),
+                    )
+                    .version(
+                        versioning::ApiVersion::V2,
+                        Router::new().route("/items", get(|| async { "v2" })),
+                    )
+                    .with_default(versioning::DefaultVersion::Oldest),
+            );
+        assert_eq!(
+            server.diagnostics().features["api_versioning"],
+            "header v1, v2, default oldest"
+        );
+        let app = server.build_app().expect("app should build");
+        let call = |version: Option<&str>, uri: &str| {
+            let mut builder = Request::builder()
+                .uri(uri)
+                .header("x-request-id", "versioned-call");
+            if let Some(version) = version {
+                builder = builder.header(versioning::API_VERSION_HEADER, version);
+            }
+            app.clone().oneshot(builder.body(Body::empty()).unwrap())
+        };
+
+        let response = call(Some("2"), "/items").await.unwrap();
+        assert_eq!(response.headers()["x-request-id"], "versioned-call");
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"v2");
+
+        let response = call(None, "/items").await.unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"v1");
+
+        let response = call(Some("2"), "/health").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn with_service_at_mounts_tower_services_under_a_prefix() {
+        let server = OpenportioServer::new()
+            .without_grpc()
+            .with_service_at(
+                "/legacy/",
+                tower::service_fn(|req: axum::extract::Request| async move {
+                    Ok::<_, Infallible>(format!("legacy {}", req.uri().path()))
+                }),
+            )
+            .with_service_at(
+                "/broken",
+                tower::service_fn(|_req: axum::extract::Request| async {
+                    Err::<String, _>(std::io::Error::other("exporter offline"))
+                }),
+            );
+        assert!(server
+            .describe()
+            .rest_routes
+            .iter()
+            .any(|route| route.path == "/legacy/*" && route.methods == ["ANY"]));
+        let app = server.build_app().expect("app should build");
+        let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+        let response = app.clone().oneshot(get("/legacy/users/7")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"legacy /users/7");
+
+        let response = app.clone().oneshot(get("/broken/metrics")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let response = app.oneshot(get("/health")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn socket_options_show_up_in_diagnostics() {
+        let server = OpenportioServer::new()
+            .reuse_port(true)
+            .with_tcp_backlog(4096)
+            .with_tcp_nodelay(true)
+            .with_tcp_keepalive(Duration::from_secs(60))
+            .with_accept_workers(4);
+        assert_eq!(
+            server.diagnostics().features["socket"],
+            "backlog 4096, reuse_port, nodelay, keepalive 60s, 4 accept workers"
+        );
+        assert_eq!(
+            OpenportioServer::new().diagnostics().features["socket"],
+            "backlog 1024"
+        );
+    }
+
+    #[tokio::test]
+    async fn with_quotas_charges_principals_or_client_ips_and_rejects_when_exhausted() {
+        let mut auth_cfg = auth::AuthRuntimeConfig::default();
+        auth_cfg.enabled = true;
+        auth_cfg.jwt_secret = Some("quota-secret".to_string());
+        let token_for = |sub: &str| {
+            jsonwebtoken::encode(
+                &jsonwebtoken::Header::default(),
+                &serde_json::json!({ "sub": sub, "exp": 4_102_444_800u64 }),
+                &jsonwebtoken::EncodingKey::from_secret(b"quota-secret"),
+            )
+            .expect("token should encode")
+        };
+        let token = token_for("ana");
+        let server = OpenportioServer::new()
+            .without_grpc()
+            .with_rest_router(crate::build_router_with_auth(
+                Arc::new(AppState::local("test-server")),
+                auth_cfg,
+            ))
+            .merge_raw_router(Router::new().route("/reports", get(|| async { "report" })))
+            .with_quotas(
+                quota::Quotas::in_memory()
+                    .daily(1)
+                    .monthly(100)
+                    .with_api_keys(http::HeaderName::from_static("x-api-key"), |key| {
+                        (key == "live-key").then(|| "key-7".to_string())
+                    }),
+            );
+        assert_eq!(
+            server.diagnostics().features["quotas"],
+            "1 daily, 100 monthly"
+        );
+        let app = server.build_app().expect("app should build");
+        let call_with_key = |uri: &str, ip: [u8; 4], token: Option<&str>, key: Option<&str>| {
+            let mut request = Request::builder().uri(uri);
+            if let Some(token) = token {
+                request = request.header("authorization", format!("Bearer {token}"));
+            }
+            if let Some(key) = key {
+                request = request.header("x-api-key", key);
+            }
+            let mut request = request.body(Body::empty()).unwrap();
+            request
+                .extensions_mut()
+                .insert(axum::extract::ConnectInfo(SocketAddr::from((ip, 40_000))));
+            app.clone().oneshot(request)
+        };
+        let call =
+            |uri: &str, ip: [u8; 4], token: Option<&str>| call_with_key(uri, ip, token, None);
+
+        let response = call("/reports", [198, 51, 100, 1], None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[quota::QUOTA_LIMIT_HEADER], "1");
+        assert_eq!(response.headers()[quota::QUOTA_REMAINING_HEADER], "0");
+
+        // Made-up API keys no longer open a fresh budget.
+        let mut request = Request::builder()
+            .uri("/reports")
+            .header("x-api-key", "junk-1")
+            .body(Body::empty())
+            .unwrap();
+        request
+            .extensions_mut()
+            .insert(axum::extract::ConnectInfo(SocketAddr::from((
+                [198, 51, 100, 1],
+                40_001,
+            ))));
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key(header::RETRY_AFTER));
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "quota_exceeded");
+        assert_eq!(body["details"]["period"], "daily");
+
+        let response = call("/reports", [198, 51, 100, 2], None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // A known API key has its own budget, whatever address it calls from.
+        let response = call_with_key("/reports", [198, 51, 100, 1], None, Some("live-key"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = call_with_key("/reports", [198, 51, 100, 5], None, Some("live-key"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        // Per-route auth charges the verified principal, whatever address it calls from.
+        let response = call("/protected/whoami", [198, 51, 100, 3], Some(&token))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = call("/protected/whoami", [198, 51, 100, 4], Some(&token))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        // ... and the call is not also counted against that address.
+        let response = call("/reports", [198, 51, 100, 3], None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // An exhausted shared address doesn't turn away principals with budget left.
+        let shared = [198, 51, 100, 9];
+        assert_eq!(
+            call("/reports", shared, None).await.unwrap().status(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            call("/reports", shared, None).await.unwrap().status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        for sub in ["bo", "cy"] {
+            let response = call("/protected/whoami", shared, Some(&token_for(sub)))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{sub}");
+        }
+        let response = call("/protected/whoami", shared, Some(&token_for("bo")))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test]
+    async fn with_tenancy_feeds_tenant_context_extractor() {
+        let app = OpenportioServer::new()
+            .without_grpc()
+            .merge_raw_router(Router::new().route(
+                "/tenant",
+                get(|tenant: tenancy::TenantContext| async move { tenant.tenant_id().to_string() }),
+            ))
+            .with_tenancy(
+                tenancy::Tenancy::new()
+                    .resolve_with(tenancy::HeaderTenantResolver::default())
+                    .with_tenant("acme", tenancy::TenantConfig::new()),
+            )
+            .build_app()
+            .expect("app should build");
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/tenant")
+                    .header(tenancy::DEFAULT_TENANT_HEADER, "acme")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("request should succeed");
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(bytes, "acme");
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/tenant")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("request should succeed");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn grpc_registry_replaces_routes_and_feeds_describe() {
+        let state = Arc::new(AppState::local("test-server"));
+        let registry = grpc::default_service_registry(
+            state.clone(),
+            crate::auth::AuthRuntimeConfig::default(),
+        );
+        let server = OpenportioServer::new()
+            .with_state(state)
+            .with_grpc_routes(Routes::default())
+            .with_grpc_registry(registry);
+
+        let names = server
+            .describe()
+            .grpc_services
+            .into_iter()
+            .map(|service| service.name)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "openportio.v1.Greeter",
+                "grpc.reflection.v1.ServerReflection",
+                "grpc.reflection.v1alpha.ServerReflection",
+            ]
+        );
+        let _ = server.build_app().expect("app should build");
+    }
+
+    #[test]
+    fn configure_tonic_is_noop_when_grpc_is_disabled() {
+        let called = Arc::new(AtomicBool::new(false));
+        let marker = Arc::clone(&called);
+        let _ = OpenportioServer::new()
+            .without_grpc()
+            .configure_tonic(move |routes| {
+                marker.store(true, Ordering::SeqCst);
+                routes
+            })
+            .build_app()
+            .expect("app should build");
+        assert!(!called.load(Ordering::SeqCst));
+    }
+
+    #[derive(Clone)]
+    struct LabelDep(String);
+
+    impl FromRef<Arc<AppState>> for LabelDep {
+        fn from_ref(_state: &Arc<AppState>) -> Self {
+            Self("from-state".to_string())
+        }
+    }
+
+    async fn dep_handler(crate::di::Depends(dep): crate::di::Depends<LabelDep>) -> String {
+        dep.0
+    }
+
+    #[tokio::test]
+    async fn builder_supports_dependency_overrides() {
+        let app = OpenportioServer::new()
+            .without_grpc()
+            .with_rest_router(
+                Router::new()
+                    .route("/dep", get(dep_handler))
+                    .with_state(Arc::new(AppState::local("builder-test"))),
+            )
+            .with_dependency(LabelDep("override".to_string()))
+            .build_app()
+            .expect("app should build");
+
+        let response = app
+            .oneshot(Request::builder().uri("/dep").body(Body::empty()).unwrap())
+            .await
+            .expect("dep request should succeed");
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("response body");
+        assert_eq!(String::from_utf8(body.to_vec()).expect("utf8"), "override");
+    }
+
+    #[tokio::test]
+    async fn depends_resolves_services_registered_on_app_state() {
+        let state = Arc::new(
+            AppState::local("builder-test").with_service(LabelDep("registered".to_string())),
+        );
+        let app = OpenportioServer::new()
+            .without_grpc()
+            .with_state(state.clone())
+            .with_rest_router(
+                Router::new()
+                    .route("/dep", get(dep_handler))
+                    .with_state(state),
+            )
+            .build_app()
+            .expect("app should build");
+
+        let response = app
+            .oneshot(Request::builder().uri("/dep").body(Body::empty()).unwrap())
+            .await
+            .expect("dep request should succeed");
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("response body");
+        assert_eq!(
+            String::from_utf8(body.to_vec()).expect("utf8"),
+            "registered"
+        );
+    }
+
+    #[tokio::test]
+    async fn outbox_relay_registers_its_outbox_for_every_route() {
+        let state = Arc::new(AppState::local("builder-test"));
+        let outbox =
+            openportio_core::Outbox::new(openportio_core::outbox::InMemoryOutboxStore::new());
+        let app = OpenportioServer::new()
+            .with_rest_router(
+                Router::new()
+                    .route(
+                        "/outbox",
+                        get(|request: axum::extract::Request| async move {
+                            crate::di::resolve_from_extensions::<openportio_core::Outbox>(
+                                request.extensions(),
+                            )
+                            .is_some()
+                            .to_string()
+                        }),
+                    )
+                    .with_state(state.clone()),
+            )
+            .with_state(state.clone())
+            .with_outbox_relay(openportio_core::OutboxRelay::new(
+                outbox,
+                state.events.clone(),
+            ))
+            .build_app()
+            .expect("app should build");
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/outbox")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(String::from_utf8(body.to_vec()).unwrap(), "true");
+        assert!(state.service::<openportio_core::Outbox>().is_none());
+    }
+
+    #[tokio::test]
+    async fn scoped_overrides_reach_a_built_app() {
+        let server = OpenportioServer::new()
+            .without_grpc()
+            .with_rest_router(
+                Router::new()
+                    .route("/dep", get(dep_handler))
+                    .with_state(Arc::new(AppState::local("builder-test"))),
+            )
+            .with_dependency(LabelDep("registered".to_string()));
+        let app = server.build_app().expect("app should build");
+        let mock = crate::di::MockProvider::new(LabelDep("mocked".to_string()));
+        let _scope = server
+            .dependency_overrides()
+            .scoped()
+            .with_mock(mock.clone());
+
+        let response = app
+            .oneshot(Request::builder().uri("/dep").body(Body::empty()).unwrap())
+            .await
+            .expect("dep request should succeed");
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("response body");
+        assert_eq!(String::from_utf8(body.to_vec()).expect("utf8"), "mocked");
+        assert_eq!(mock.calls(), 1);
+    }
+
+    #[derive(Clone)]
+    struct PrefixDep(String);
 
     #[tokio::test]
-    async fn builder_creates_working_app() {
-        let app = OpenportioServer::new().build_app();
+    async fn builder_resolves_dependency_providers_in_graph_order() {
+        let app = OpenportioServer::new()
+            .without_grpc()
+            .with_rest_router(
+                Router::new()
+                    .route("/dep", get(dep_handler))
+                    .with_state(Arc::new(AppState::local("builder-test"))),
+            )
+            .with_dependency_provider(
+                [crate::di::DependencyKey::of::<PrefixDep>()],
+                |deps: &crate::di::DependencyOverrides| {
+                    let prefix = deps
+                        .get::<PrefixDep>()
+                        .expect("prefix should resolve first");
+                    LabelDep(format!("{}-provided", prefix.0))
+                },
+            )
+            .with_dependency(PrefixDep("graph".to_string()))
+            .require_dependency::<LabelDep>("GET /dep")
+            .build_app()
+            .expect("app should build");
+
+        let response = app
+            .oneshot(Request::builder().uri("/dep").body(Body::empty()).unwrap())
+            .await
+            .expect("dep request should succeed");
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("response body");
+        assert_eq!(
+            String::from_utf8(body.to_vec()).expect("utf8"),
+            "graph-provided"
+        );
+    }
+
+    #[test]
+    fn builder_fails_fast_on_missing_dependency() {
+        let server = OpenportioServer::new()
+            .without_grpc()
+            .require_dependency::<LabelDep>("GET /dep");
+
+        let err = server
+            .validate_dependencies()
+            .expect_err("missing dependency should fail validation");
+        assert!(err.to_string().contains("required by GET /dep"));
+        assert!(server.build_app().is_err());
+    }
+
+    #[tokio::test]
+    async fn undeclared_handler_dependencies_only_fail_per_request() {
+        crate::qualifier!(Reporting = "reporting");
+        async fn report(label: crate::di::Named<LabelDep, Reporting>) -> String {
+            label.into_inner().0
+        }
+        let router = || Router::new().route("/report", get(report));
+
+        // The graph cannot see a plain handler's extractor: the app builds, the request fails.
+        let app = OpenportioServer::new()
+            .without_grpc()
+            .merge_raw_router(router())
+            .build_app()
+            .expect("undeclared dependencies are not validated");
         let response = app
             .oneshot(
                 Request::builder()
-                    .uri("/health")
+                    .uri("/report")
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
-            .expect("health request should succeed");
-        assert_eq!(response.status(), StatusCode::OK);
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let err = OpenportioServer::new()
+            .without_grpc()
+            .merge_raw_router(router())
+            .require_dependency::<crate::di::Named<LabelDep, Reporting>>("GET /report")
+            .build_app()
+            .expect_err("declared dependencies are validated");
+        assert!(err.to_string().contains("required by GET /report"));
     }
 
     #[tokio::test]
-    async fn builder_supports_custom_rest_and_middleware_chain() {
-        let custom_router = Router::new().route("/custom", get(|| async { "custom-ok" }));
-        let app = OpenportioServer::new()
-            .without_grpc()
-            .with_rest_router(custom_router)
-            .with_middleware(|router| router.route("/ping", get(|| async { "pong" })))
-            .build_app();
+    async fn describe_lists_routes_services_and_tasks_and_admin_router_serves_it() {
+        let server = OpenportioServer::new()
+            .with_route_description(
+                introspection::RestRouteDescription::new("/notes")
+                    .with_method("post")
+                    .with_scope("notes:write"),
+            )
+            .with_background_task("outbox-relay", || async {})
+            .with_admin_routes()
+            .with_admin_auth(admin_auth());
 
-        let ping_response = app
-            .oneshot(Request::builder().uri("/ping").body(Body::empty()).unwrap())
+        let description = server.describe();
+        assert!(description
+            .rest_routes
+            .iter()
+            .any(|route| route.path == "/health" && route.methods == ["GET"]));
+        assert!(description.rest_routes.iter().any(|route| {
+            route.path == "/protected/whoami" && route.middleware.iter().any(|m| m == "auth")
+        }));
+        let notes = description
+            .rest_routes
+            .iter()
+            .find(|route| route.path == "/notes")
+            .expect("described route should be listed");
+        assert_eq!(notes.methods, ["POST"]);
+        assert_eq!(notes.scopes, ["notes:write"]);
+        let greeter = description
+            .grpc_services
+            .iter()
+            .find(|service| service.name == "openportio.v1.Greeter")
+            .expect("default greeter should be listed");
+        assert_eq!(greeter.methods, ["SayHello", "SayHelloStream"]);
+        assert_eq!(description.background_tasks, ["outbox-relay"]);
+        assert!(description.middleware.iter().any(|m| m == "timeout"));
+
+        let response = server
+            .build_app()
+            .expect("app should build")
+            .oneshot(
+                admin_request(introspection::ROUTES_PATH)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
             .await
-            .expect("ping request should succeed");
-        assert_eq!(ping_response.status(), StatusCode::OK);
+            .expect("routes request should succeed");
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("response body");
+        let served: serde_json::Value = serde_json::from_slice(&body).expect("json body");
+        assert_eq!(served["background_tasks"][0], "outbox-relay");
+        assert!(served["rest_routes"]
+            .as_array()
+            .expect("routes array")
+            .iter()
+            .any(|route| route["path"] == introspection::ROUTES_PATH));
+
+        assert!(OpenportioServer::new()
+            .without_grpc()
+            .describe()
+            .grpc_services
+            .is_empty());
     }
 
     #[tokio::test]
-    async fn builder_supports_raw_router_merge() {
-        let raw_router = Router::new().route("/metrics", get(|| async { "metrics-ok" }));
+    async fn rejection_handler_applies_to_validated_extractors_and_axum_rejections() {
+        #[derive(serde::Deserialize, validator::Validate, crate::RequestValidation)]
+        struct NoteDto {
+            #[validate(length(min = 1))]
+            title: String,
+        }
+
+        let router =
+            Router::new()
+                .route(
+                    "/notes",
+                    axum::routing::post(
+                        |api::ValidatedJson(note): api::ValidatedJson<NoteDto>| async move {
+                            note.title
+                        },
+                    ),
+                )
+                .route(
+                    "/raw",
+                    axum::routing::post(
+                        |axum::Json(value): axum::Json<serde_json::Value>| async move {
+                            value.to_string()
+                        },
+                    ),
+                )
+                .route(
+                    "/ext",
+                    get(|axum::Extension(value): axum::Extension<LabelDep>| async move { value.0 }),
+                )
+                .route(
+                    "/plain",
+                    get(|| async { (StatusCode::BAD_REQUEST, "handler says no") }),
+                );
         let app = OpenportioServer::new()
             .without_grpc()
-            .merge_raw_router(raw_router)
-            .build_app();
+            .with_rest_router(router)
+            .with_rejection_handler(|rejection| {
+                (
+                    rejection.status,
+                    axum::Json(api::ApiErrorResponse {
+                        code: format!("{:?}", rejection.kind).to_lowercase(),
+                        message: "custom".to_string(),
+                        detail: None,
+                        details: None,
+                    }),
+                )
+            })
+            .build_app()
+            .expect("app should build");
+
+        let cases = [
+            ("POST", "/notes", "{", StatusCode::BAD_REQUEST, "body"),
+            ("POST", "/raw", "{", StatusCode::BAD_REQUEST, "body"),
+            (
+                "GET",
+                "/notes",
+                "",
+                StatusCode::METHOD_NOT_ALLOWED,
+                "methodnotallowed",
+            ),
+            (
+                "GET",
+                "/ext",
+                "",
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "missingextension",
+            ),
+        ];
+        for (method, uri, body, status, code) in cases {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method(method)
+                        .uri(uri)
+                        .header("content-type", "application/json")
+                        .body(Body::from(body))
+                        .unwrap(),
+                )
+                .await
+                .expect("request should complete");
+            assert_eq!(response.status(), status, "{method} {uri}");
+            let body = to_bytes(response.into_body(), usize::MAX)
+                .await
+                .expect("response body");
+            let body: serde_json::Value = serde_json::from_slice(&body).expect("json body");
+            assert_eq!(body["code"], code, "{method} {uri}");
+        }
 
         let response = app
             .oneshot(
                 Request::builder()
-                    .uri("/metrics")
+                    .uri("/plain")
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
-            .expect("metrics request should succeed");
+            .expect("request should complete");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("response body");
+        assert_eq!(&body[..], b"handler says no");
+    }
+
+    #[tokio::test]
+    async fn admin_router_serves_build_info() {
+        let server = OpenportioServer::new()
+            .without_grpc()
+            .with_admin_routes()
+            .with_admin_auth(admin_auth())
+            .with_build_info(crate::openportio_buildinfo!());
+        assert!(server
+            .describe()
+            .rest_routes
+            .iter()
+            .any(|route| route.path == buildinfo::BUILDINFO_PATH));
+
+        let response = server
+            .build_app()
+            .expect("app should build")
+            .oneshot(
+                admin_request(buildinfo::BUILDINFO_PATH)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("buildinfo request should succeed");
         assert_eq!(response.status(), StatusCode::OK);
         let body = to_bytes(response.into_body(), usize::MAX)
             .await
             .expect("response body");
-        assert_eq!(
-            String::from_utf8(body.to_vec()).expect("utf8"),
-            "metrics-ok"
-        );
+        let served: buildinfo::BuildInfo = serde_json::from_slice(&body).expect("json body");
+        assert_eq!(served.name, "openportio-server");
+        assert_eq!(served.version, env!("CARGO_PKG_VERSION"));
     }
 
     #[tokio::test]
-    async fn builder_supports_tonic_routes_configuration_hook() {
+    async fn admin_log_filter_endpoint_rejects_unauthenticated_changes() {
+        let (_filter, handle) =
+            log_filter::reloadable_env_filter::<tracing_subscriber::Registry>("info").unwrap();
         let app = OpenportioServer::new()
-            .configure_tonic(|routes| {
-                let router = routes
-                    .into_axum_router()
-                    .route("/grpc-hook", get(|| async { "grpc-hook-ok" }));
-                Routes::from(router)
-            })
-            .build_app();
+            .without_grpc()
+            .with_admin_routes()
+            .with_admin_auth(admin_auth())
+            .with_log_filter(handle.clone())
+            .build_app()
+            .expect("app should build");
+        let put = |request: http::request::Builder| {
+            app.clone().oneshot(
+                request
+                    .method("PUT")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"filter":"trace"}"#))
+                    .unwrap(),
+            )
+        };
+
+        let response = put(Request::builder().uri(log_filter::LOGGING_PATH))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(handle.current(), "info");
+
+        let response = put(admin_request(log_filter::LOGGING_PATH)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(handle.current(), "trace");
+    }
+
+    #[tokio::test]
+    async fn maintenance_switch_needs_an_admin_token() {
+        let mode = maintenance::MaintenanceMode::new();
+        let app = OpenportioServer::new()
+            .without_grpc()
+            .with_maintenance_mode(mode.clone())
+            .with_admin_routes()
+            .with_admin_auth(admin_auth())
+            .build_app()
+            .expect("app should build");
+        let put = |request: http::request::Builder| {
+            app.clone().oneshot(
+                request
+                    .method("PUT")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"enabled":true}"#))
+                    .unwrap(),
+            )
+        };
+
+        let response = put(Request::builder().uri(maintenance::MAINTENANCE_PATH))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(!mode.is_active());
+
+        let response = put(admin_request(maintenance::MAINTENANCE_PATH))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(mode.is_active());
+    }
+
+    #[tokio::test]
+    async fn admin_router_serves_diagnostics_report() {
+        let server = OpenportioServer::new()
+            .with_addr(SocketAddr::from(([127, 0, 0, 1], 4200)))
+            .with_admin_routes()
+            .with_admin_auth(admin_auth());
+        let report = server.diagnostics();
+        assert_eq!(report.listeners[0].protocol, "rest+grpc");
+        assert_eq!(report.listeners[0].addr, "127.0.0.1:4200");
+        assert_eq!(report.route_count, server.describe().rest_routes.len());
+        assert!(report.features.contains_key("auth"));
 
+        let app = server.build_app().expect("app should build");
         let response = app
+            .clone()
             .oneshot(
                 Request::builder()
-                    .uri("/grpc-hook")
+                    .uri(diagnostics::DIAGNOSTICS_PATH)
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
-            .expect("grpc hook request should succeed");
+            .expect("anonymous diagnostics request should complete");
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = app
+            .oneshot(
+                admin_request(diagnostics::DIAGNOSTICS_PATH)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("diagnostics request should succeed");
         assert_eq!(response.status(), StatusCode::OK);
         let body = to_bytes(response.into_body(), usize::MAX)
             .await
             .expect("response body");
-        assert_eq!(
-            String::from_utf8(body.to_vec()).expect("utf8"),
-            "grpc-hook-ok"
-        );
+        let served: serde_json::Value = serde_json::from_slice(&body).expect("json body");
+        assert_eq!(served["service_name"], "openportio-server");
+        assert!(served["grpc_services"]
+            .as_array()
+            .expect("services array")
+            .iter()
+            .any(|service| service == "openportio.v1.Greeter"));
     }
 
-    #[test]
-    fn configure_tonic_is_noop_when_grpc_is_disabled() {
-        let called = Arc::new(AtomicBool::new(false));
-        let marker = Arc::clone(&called);
-        let _ = OpenportioServer::new()
+    #[tokio::test]
+    async fn admin_revocation_endpoint_rejects_revoked_tokens_via_di_store() {
+        let mut auth_cfg = crate::auth::AuthRuntimeConfig::default();
+        auth_cfg.enabled = true;
+        auth_cfg.jwt_secret = Some("dev-secret".to_string());
+        let token = jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &serde_json::json!({ "sub": "user-1", "exp": 4_102_444_800u64, "jti": "token-1" }),
+            &jsonwebtoken::EncodingKey::from_secret(b"dev-secret"),
+        )
+        .expect("token should encode");
+        let app = OpenportioServer::new()
             .without_grpc()
-            .configure_tonic(move |routes| {
-                marker.store(true, Ordering::SeqCst);
-                routes
-            })
-            .build_app();
-        assert!(!called.load(Ordering::SeqCst));
+            .with_rest_router(crate::build_router_with_auth(
+                Arc::new(AppState::local("test-server")),
+                auth_cfg,
+            ))
+            .with_revocation_store(revocation::InMemoryRevocationStore::new())
+            .with_admin_routes()
+            .with_admin_auth(admin_auth())
+            .build_app()
+            .expect("app should build");
+        let whoami = || {
+            Request::builder()
+                .uri("/protected/whoami")
+                .header("authorization", format!("Bearer {token}"))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let revoke = |request: http::request::Builder| {
+            app.clone().oneshot(
+                request
+                    .method("POST")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"jti":"token-1","expires_at":4102444800}"#))
+                    .unwrap(),
+            )
+        };
+
+        let response = app.clone().oneshot(whoami()).await.expect("whoami");
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Anonymous callers and tokens without the admin scope cannot revoke anything.
+        let response = revoke(Request::builder().uri(revocation::REVOCATIONS_PATH))
+            .await
+            .expect("revoke");
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = revoke(
+            Request::builder()
+                .uri(revocation::REVOCATIONS_PATH)
+                .header("authorization", format!("Bearer {token}")),
+        )
+        .await
+        .expect("revoke");
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = app.clone().oneshot(whoami()).await.expect("whoami");
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = revoke(admin_request(revocation::REVOCATIONS_PATH))
+            .await
+            .expect("revoke");
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        let response = app.oneshot(whoami()).await.expect("whoami");
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("response body");
+        assert!(String::from_utf8_lossy(&body).contains("token has been revoked"));
     }
 
-    #[derive(Clone)]
-    struct LabelDep(String);
+    #[tokio::test]
+    async fn installed_modules_bring_their_dependencies_routes_and_tasks() {
+        struct GreetingModule {
+            greeting: &'static str,
+        }
 
-    impl FromRef<Arc<AppState>> for LabelDep {
-        fn from_ref(_state: &Arc<AppState>) -> Self {
-            Self("from-state".to_string())
+        impl di::Module for GreetingModule {
+            fn register(&self, server: OpenportioServer) -> OpenportioServer {
+                server
+                    .with_dependency(LabelDep(self.greeting.to_string()))
+                    .merge_raw_router(
+                        Router::new()
+                            .route("/greeting", get(dep_handler))
+                            .with_state(Arc::new(AppState::local("module-test"))),
+                    )
+                    .with_background_task("greeting-refresh", || async {})
+            }
         }
-    }
 
-    async fn dep_handler(crate::di::Depends(dep): crate::di::Depends<LabelDep>) -> String {
-        dep.0
+        let server = OpenportioServer::new()
+            .without_grpc()
+            .install(GreetingModule { greeting: "hi" })
+            .install(GreetingModule {
+                greeting: "ignored",
+            });
+        let description = server.describe();
+        assert_eq!(description.modules, ["GreetingModule"]);
+        assert_eq!(description.background_tasks, ["greeting-refresh"]);
+
+        let app = server.build_app().expect("app should build");
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/greeting")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"hi");
     }
 
     #[tokio::test]
-    async fn builder_supports_dependency_overrides() {
+    async fn auth_and_admin_calls_are_written_to_the_audit_log() {
+        #[derive(Clone, Default)]
+        struct Recorder(Arc<std::sync::Mutex<Vec<audit::AuditEvent>>>);
+        impl audit::AuditLog for Recorder {
+            fn record(&self, event: &audit::AuditEvent) {
+                self.0.lock().unwrap().push(event.clone());
+            }
+        }
+
+        let mut auth_cfg = auth::AuthRuntimeConfig::default();
+        auth_cfg.enabled = true;
+        auth_cfg.jwt_secret = Some("audit-secret".to_string());
+        let token = jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &serde_json::json!({
+                "sub": "ops-1",
+                "exp": 4_102_444_800u64,
+                "scope": auth::ADMIN_SCOPE,
+            }),
+            &jsonwebtoken::EncodingKey::from_secret(b"audit-secret"),
+        )
+        .expect("token should encode");
+        let recorder = Recorder::default();
         let app = OpenportioServer::new()
             .without_grpc()
-            .with_rest_router(
-                Router::new()
-                    .route("/dep", get(dep_handler))
-                    .with_state(Arc::new(AppState::local("builder-test"))),
-            )
-            .with_dependency(LabelDep("override".to_string()))
-            .build_app();
+            .require_auth(auth_cfg)
+            .with_admin_routes()
+            .with_audit_log(recorder.clone())
+            .build_app()
+            .expect("app should build");
 
         let response = app
-            .oneshot(Request::builder().uri("/dep").body(Body::empty()).unwrap())
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/hello/ana")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
             .await
-            .expect("dep request should succeed");
-        assert_eq!(response.status(), StatusCode::OK);
-        let body = to_bytes(response.into_body(), usize::MAX)
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri(maintenance::MAINTENANCE_PATH)
+                    .header("authorization", format!("Bearer {token}"))
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"enabled":false}"#))
+                    .unwrap(),
+            )
             .await
-            .expect("response body");
-        assert_eq!(String::from_utf8(body.to_vec()).expect("utf8"), "override");
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let events = recorder.0.lock().unwrap();
+        let kinds: Vec<_> = events.iter().map(|event| event.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                audit::AuditEventKind::LoginFailed,
+                audit::AuditEventKind::LoginSucceeded,
+                audit::AuditEventKind::ConfigReload,
+                audit::AuditEventKind::AdminAccess,
+            ]
+        );
+        assert_eq!(events[0].reason.as_deref(), Some("missing bearer token"));
+        assert_eq!(events[0].path.as_deref(), Some("/hello/ana"));
+        assert!(events[0].request_id.is_some());
+        assert_eq!(events[1].principal.as_deref(), Some("ops-1"));
+        assert_eq!(events[2].principal.as_deref(), Some("ops-1"));
+        assert_eq!(events[3].method.as_deref(), Some("PUT"));
+        assert_eq!(events[3].status, Some(200));
+    }
+
+    #[tokio::test]
+    async fn repeated_bad_tokens_get_the_caller_blocked() {
+        let mut auth_cfg = auth::AuthRuntimeConfig::default();
+        auth_cfg.enabled = true;
+        auth_cfg.jwt_secret = Some("guess-me".to_string());
+        let protection = brute_force::BruteForceProtection::new(
+            brute_force::BruteForcePolicy::new()
+                .with_delay(1, Duration::from_millis(100), Duration::from_secs(1))
+                .with_block(3, Duration::from_secs(60)),
+        );
+        let app = OpenportioServer::new()
+            .without_grpc()
+            .require_auth(auth_cfg)
+            .with_brute_force_protection(protection.clone())
+            .build_app()
+            .expect("app should build");
+        let attempt = |token: &str| {
+            let mut request = Request::builder()
+                .uri("/hello/ana")
+                .header("authorization", format!("Bearer {token}"))
+                .body(Body::empty())
+                .unwrap();
+            request
+                .extensions_mut()
+                .insert(axum::extract::ConnectInfo(SocketAddr::from((
+                    [198, 51, 100, 7],
+                    40_000,
+                ))));
+            app.clone().oneshot(request)
+        };
+
+        for _ in 0..2 {
+            assert_eq!(
+                attempt("not-a-jwt").await.unwrap().status(),
+                StatusCode::UNAUTHORIZED
+            );
+        }
+        assert_eq!(
+            protection
+                .record("ip:198.51.100.7")
+                .unwrap()
+                .map(|record| record.failures),
+            Some(2)
+        );
+        assert_eq!(
+            attempt("not-a-jwt").await.unwrap().status(),
+            StatusCode::UNAUTHORIZED
+        );
+
+        let token = jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &serde_json::json!({ "sub": "ana", "exp": 4_102_444_800u64 }),
+            &jsonwebtoken::EncodingKey::from_secret(b"guess-me"),
+        )
+        .expect("token should encode");
+        let response = attempt(&token).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key("retry-after"));
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "too_many_failed_attempts");
+
+        protection.reset("ip:198.51.100.7").unwrap();
+        assert_eq!(attempt(&token).await.unwrap().status(), StatusCode::OK);
     }
 
     #[test]
@@ -514,6 +3551,28 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn malformed_env_settings_fail_run_instead_of_defaulting() {
+        let server = {
+            let _guard = ENV_LOCK.lock().expect("env lock");
+            env::set_var("OPENPORTIO_SERVER_ADDR", "localhost:http");
+            env::set_var("OPENPORTIO_TIMEOUT_SECONDS", "30s");
+            let server = OpenportioServer::new();
+            env::remove_var("OPENPORTIO_SERVER_ADDR");
+            env::remove_var("OPENPORTIO_TIMEOUT_SECONDS");
+            server
+        };
+
+        match server.run().await {
+            Err(ServerError::InvalidConfig(message)) => assert_eq!(
+                message,
+                "`OPENPORTIO_SERVER_ADDR` has an invalid value `localhost:http`; \
+                 `OPENPORTIO_TIMEOUT_SECONDS` has an invalid value `30s`"
+            ),
+            other => panic!("expected InvalidConfig, got {other:?}"),
+        }
+    }
+
     #[test]
     fn dual_port_requires_both_addresses() {
         let err = OpenportioServer::new()
@@ -524,4 +3583,63 @@ mod tests {
             .to_string()
             .contains("requires both with_rest_addr(...) and with_grpc_addr(...)"));
     }
+
+    #[test]
+    fn connection_lifecycle_calls_survive_a_later_http2_config() {
+        let second = Duration::from_secs(1);
+        let server = OpenportioServer::new()
+            .with_grpc_keepalive(20 * second, 5 * second)
+            .with_max_connection_age(300 * second, 30 * second)
+            .with_http2_config(transport::Http2Config::long_lived_streaming())
+            .with_max_connection_idle(60 * second);
+
+        let config = server.effective_http2_config();
+        assert_eq!(config.keep_alive_interval, Some(20 * second));
+        assert_eq!(config.keep_alive_timeout, Some(5 * second));
+        assert_eq!(config.max_connection_age, Some(300 * second));
+        assert_eq!(config.max_connection_age_grace, Some(30 * second));
+        assert_eq!(config.max_connection_idle, Some(60 * second));
+        assert_eq!(
+            config.max_concurrent_streams,
+            transport::Http2Config::long_lived_streaming().max_concurrent_streams
+        );
+    }
+
+    #[tokio::test]
+    async fn bind_failures_name_the_setting_that_chose_the_address() {
+        let taken = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+        let taken_addr = taken.local_addr().expect("local addr");
+        let ephemeral = SocketAddr::from(([127, 0, 0, 1], 0));
+        let from_env = {
+            let _guard = ENV_LOCK.lock().expect("env lock");
+            env::set_var("OPENPORTIO_REST_ADDR", taken_addr.to_string());
+            env::set_var("OPENPORTIO_GRPC_ADDR", ephemeral.to_string());
+            let server = OpenportioServer::new();
+            env::remove_var("OPENPORTIO_REST_ADDR");
+            env::remove_var("OPENPORTIO_GRPC_ADDR");
+            server
+        };
+        let from_code = OpenportioServer::new()
+            .with_rest_addr(taken_addr)
+            .with_grpc_addr(ephemeral);
+
+        for (server, hint) in [
+            (from_env, "OPENPORTIO_REST_ADDR"),
+            (from_code, "with_rest_addr(...)"),
+        ] {
+            // Outside `local`, so the port conflict is not papered over with an ephemeral port.
+            match server.with_environment(Environment::Production).run().await {
+                Err(ServerError::BindFailed { addr, source }) => {
+                    assert_eq!(addr, taken_addr);
+                    assert!(
+                        source
+                            .to_string()
+                            .contains(&format!("set {hint} to a free address")),
+                        "{source}"
+                    );
+                }
+                other => panic!("expected BindFailed, got {other:?}"),
+            }
+        }
+    }
 }
@@ -0,0 +1,49 @@
+use std::sync::Arc;
+
+use axum::{routing::get, Json, Router};
+pub use openportio_core::buildinfo::BuildInfo;
+
+pub const BUILDINFO_PATH: &str = "/internal/buildinfo";
+
+/// Admin route answering `GET /internal/buildinfo` with `info`, so deploy tooling can check which
+/// build is running.
+pub fn buildinfo_router(info: BuildInfo) -> Router {
+    let info = Arc::new(info);
+    Router::new().route(
+        BUILDINFO_PATH,
+        get(move || {
+            let info = Arc::clone(&info);
+            async move { Json(info.as_ref().clone()) }
+        }),
+    )
+}
+
+pub(crate) fn log(info: &BuildInfo) {
+    tracing::info!(
+        name = %info.name,
+        version = %info.version,
+        git_sha = info.git_sha.as_deref().unwrap_or("unknown"),
+        build_timestamp = info.build_timestamp.as_deref().unwrap_or("unknown"),
+        rustc_version = info.rustc_version.as_deref().unwrap_or("unknown"),
+        features = %info.features.join(","),
+        "build info"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn build_script_fills_in_build_info() {
+        let info = crate::openportio_buildinfo!();
+        assert_eq!(info.name, "openportio-server");
+        assert!(!info.version.is_empty());
+        assert!(info
+            .build_timestamp
+            .as_deref()
+            .is_some_and(|timestamp| !timestamp.is_empty()));
+        assert!(info
+            .rustc_version
+            .as_deref()
+            .is_some_and(|version| version.starts_with("rustc ")));
+    }
+}
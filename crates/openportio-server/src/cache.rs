@@ -0,0 +1,433 @@
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::{from_fn_with_state, Next},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use openportio_core::events::{BusEvent, EventBus, EventTopic};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tokio::{sync::broadcast, task::JoinHandle};
+
+use crate::{api::ApiErrorResponse, body_stream::buffer_within};
+
+const DEFAULT_MAX_CACHED_BODY_BYTES: usize = 1_048_576;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CachePolicy {
+    NoStore,
+    /// Clients may store the response but must revalidate with `If-None-Match` every time.
+    NoCache,
+    Private {
+        max_age: Duration,
+    },
+    Public {
+        max_age: Duration,
+    },
+}
+
+impl CachePolicy {
+    pub fn header_value(&self) -> HeaderValue {
+        let value = match self {
+            Self::NoStore => "no-store".to_string(),
+            Self::NoCache => "no-cache".to_string(),
+            Self::Private { max_age } => format!("private, max-age={}", max_age.as_secs()),
+            Self::Public { max_age } => format!("public, max-age={}", max_age.as_secs()),
+        };
+        HeaderValue::from_str(&value).expect("cache-control value is ascii")
+    }
+}
+
+/// JSON responder that sets a strong `ETag` (and optionally `Cache-Control`). Pair it with
+/// [`CacheLayer`] to answer `If-None-Match` with `304 Not Modified`.
+#[derive(Debug, Clone)]
+pub struct Cached<T> {
+    value: T,
+    policy: Option<CachePolicy>,
+}
+
+impl<T> Cached<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            policy: None,
+        }
+    }
+
+    pub fn with_policy(mut self, policy: CachePolicy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+}
+
+impl<T> IntoResponse for Cached<T>
+where
+    T: Serialize,
+{
+    fn into_response(self) -> Response {
+        let bytes = match serde_json::to_vec(&self.value) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                tracing::error!(error = %err, "failed to serialize cached response");
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiErrorResponse::internal_server_error()),
+                )
+                    .into_response();
+            }
+        };
+
+        let etag = strong_etag(&bytes, 0);
+        let mut response = (
+            [(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/json"),
+            )],
+            bytes,
+        )
+            .into_response();
+        let headers = response.headers_mut();
+        headers.insert(header::ETAG, etag);
+        if let Some(policy) = self.policy {
+            headers.insert(header::CACHE_CONTROL, policy.header_value());
+        }
+        response
+    }
+}
+
+/// Tag generations mixed into ETags; bumping a tag forces clients to refetch even when the body
+/// bytes are unchanged.
+#[derive(Debug, Clone, Default)]
+pub struct CacheInvalidator {
+    generations: Arc<Mutex<HashMap<String, u64>>>,
+}
+
+impl CacheInvalidator {
+    pub fn invalidate(&self, tag: &str) {
+        if let Ok(mut guard) = self.generations.lock() {
+            *guard.entry(tag.to_string()).or_default() += 1;
+        }
+    }
+
+    pub fn generation(&self, tag: &str) -> u64 {
+        self.generations
+            .lock()
+            .ok()
+            .and_then(|guard| guard.get(tag).copied())
+            .unwrap_or_default()
+    }
+
+    /// Invalidates the published tag for every message received on `events`.
+    pub fn listen<T>(&self, events: broadcast::Receiver<T>) -> JoinHandle<()>
+    where
+        T: AsRef<str> + Clone + Send + 'static,
+    {
+        self.spawn_listener(events, |invalidator, tag| {
+            invalidator.invalidate(tag.as_ref())
+        })
+    }
+
+    /// Invalidates tags for every event published on `topic`: the topic name (`notes`) and the
+    /// topic plus event kind (`notes:deleted`), so a layer tagged `notes` refetches on any change
+    /// and one tagged `notes:deleted` only on deletions. Runs until the task is aborted.
+    pub fn listen_bus(&self, bus: &EventBus, topic: impl EventTopic) -> JoinHandle<()> {
+        self.spawn_listener(bus.subscribe(topic), |invalidator, event: BusEvent| {
+            invalidator.invalidate(&event.topic);
+            invalidator.invalidate(&format!("{}:{}", event.topic, event.kind));
+        })
+    }
+
+    fn spawn_listener<T, F>(
+        &self,
+        mut events: broadcast::Receiver<T>,
+        on_event: F,
+    ) -> JoinHandle<()>
+    where
+        T: Clone + Send + 'static,
+        F: Fn(&Self, T) + Send + 'static,
+    {
+        let invalidator = self.clone();
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => on_event(&invalidator, event),
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!(skipped, "cache invalidation listener lagged");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                }
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CacheLayer {
+    policy: CachePolicy,
+    tag: Option<String>,
+    invalidator: CacheInvalidator,
+    max_body_bytes: usize,
+}
+
+impl CacheLayer {
+    pub fn new(policy: CachePolicy) -> Self {
+        Self {
+            policy,
+            tag: None,
+            invalidator: CacheInvalidator::default(),
+            max_body_bytes: DEFAULT_MAX_CACHED_BODY_BYTES,
+        }
+    }
+
+    pub fn with_tag(mut self, tag: impl Into<String>, invalidator: CacheInvalidator) -> Self {
+        self.tag = Some(tag.into());
+        self.invalidator = invalidator;
+        self
+    }
+
+    pub fn with_max_body_bytes(mut self, max_body_bytes: usize) -> Self {
+        self.max_body_bytes = max_body_bytes;
+        self
+    }
+
+    /// Applies the layer to the routes already registered on `router`.
+    pub fn apply<S>(self, router: Router<S>) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        router.route_layer(from_fn_with_state(self, cache_middleware))
+    }
+
+    fn generation(&self) -> u64 {
+        self.tag
+            .as_deref()
+            .map(|tag| self.invalidator.generation(tag))
+            .unwrap_or_default()
+    }
+}
+
+async fn cache_middleware(State(layer): State<CacheLayer>, req: Request, next: Next) -> Response {
+    if !matches!(*req.method(), Method::GET | Method::HEAD) {
+        return next.run(req).await;
+    }
+    let if_none_match = req.headers().get(header::IF_NONE_MATCH).cloned();
+    let response = next.run(req).await;
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+
+    let generation = layer.generation();
+    let (mut parts, body) = response.into_parts();
+    let (etag, body) = match parts.headers.get(header::ETAG) {
+        Some(existing) if generation == 0 => (existing.clone(), body),
+        Some(existing) => (strong_etag(existing.as_bytes(), generation), body),
+        None if is_json(&parts.headers) => match buffer_within(body, layer.max_body_bytes).await {
+            Ok(bytes) => (strong_etag(&bytes, generation), Body::from(bytes)),
+            Err(body) => {
+                tracing::debug!(
+                    "response too large to compute an etag; passing it through uncached"
+                );
+                return Response::from_parts(parts, body);
+            }
+        },
+        None => return Response::from_parts(parts, body),
+    };
+
+    parts.headers.insert(header::ETAG, etag.clone());
+    parts
+        .headers
+        .entry(header::CACHE_CONTROL)
+        .or_insert_with(|| layer.policy.header_value());
+
+    if if_none_match.is_some_and(|value| etag_matches(&value, &etag)) {
+        let mut not_modified = StatusCode::NOT_MODIFIED.into_response();
+        for name in [header::ETAG, header::CACHE_CONTROL, header::VARY] {
+            if let Some(value) = parts.headers.get(&name) {
+                not_modified.headers_mut().insert(name, value.clone());
+            }
+        }
+        return not_modified;
+    }
+    Response::from_parts(parts, body)
+}
+
+fn is_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"))
+}
+
+fn strong_etag(bytes: &[u8], generation: u64) -> HeaderValue {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    if generation > 0 {
+        hasher.update(generation.to_be_bytes());
+    }
+    let digest = hasher.finalize();
+    let mut etag = String::with_capacity(34);
+    etag.push('"');
+    for byte in &digest[..16] {
+        let _ = write!(etag, "{byte:02x}");
+    }
+    etag.push('"');
+    HeaderValue::from_str(&etag).expect("hex etag is a valid header value")
+}
+
+// `If-None-Match` uses weak comparison, so `W/"x"` matches `"x"`.
+fn etag_matches(if_none_match: &HeaderValue, etag: &HeaderValue) -> bool {
+    let Ok(candidates) = if_none_match.to_str() else {
+        return false;
+    };
+    let Ok(etag) = etag.to_str() else {
+        return false;
+    };
+    let etag = etag.trim_start_matches("W/");
+    candidates
+        .split(',')
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::Request, routing::get};
+    use tower::util::ServiceExt;
+
+    #[derive(Serialize)]
+    struct Note {
+        id: u32,
+        title: &'static str,
+    }
+
+    fn app(layer: CacheLayer) -> Router {
+        let router = Router::new()
+            .route("/json", get(|| async { Json(Note { id: 1, title: "a" }) }))
+            .route(
+                "/cached",
+                get(|| async {
+                    Cached::new(Note { id: 2, title: "b" }).with_policy(CachePolicy::NoCache)
+                }),
+            )
+            .route("/text", get(|| async { "plain" }));
+        layer.apply(router)
+    }
+
+    async fn get_with(app: &Router, uri: &str, if_none_match: Option<&HeaderValue>) -> Response {
+        let mut builder = Request::builder().uri(uri);
+        if let Some(value) = if_none_match {
+            builder = builder.header(header::IF_NONE_MATCH, value);
+        }
+        app.clone()
+            .oneshot(builder.body(Body::empty()).unwrap())
+            .await
+            .expect("request should complete")
+    }
+
+    #[tokio::test]
+    async fn json_responses_get_etag_and_honor_if_none_match() {
+        let app = app(CacheLayer::new(CachePolicy::Private {
+            max_age: Duration::from_secs(60),
+        }));
+
+        let first = get_with(&app, "/json", None).await;
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(
+            first.headers().get(header::CACHE_CONTROL).unwrap(),
+            "private, max-age=60"
+        );
+        let etag = first.headers().get(header::ETAG).expect("etag").clone();
+
+        let revalidated = get_with(&app, "/json", Some(&etag)).await;
+        assert_eq!(revalidated.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(revalidated.headers().get(header::ETAG), Some(&etag));
+
+        let text = get_with(&app, "/text", None).await;
+        assert!(text.headers().get(header::ETAG).is_none());
+    }
+
+    #[tokio::test]
+    async fn cached_responder_policy_wins_over_layer_default() {
+        let app = app(CacheLayer::new(CachePolicy::NoStore));
+        let response = get_with(&app, "/cached", None).await;
+        assert_eq!(
+            response.headers().get(header::CACHE_CONTROL).unwrap(),
+            "no-cache"
+        );
+        let weak = HeaderValue::from_str(&format!(
+            "W/{}",
+            response.headers()[header::ETAG].to_str().unwrap()
+        ))
+        .unwrap();
+        let revalidated = get_with(&app, "/cached", Some(&weak)).await;
+        assert_eq!(revalidated.status(), StatusCode::NOT_MODIFIED);
+    }
+
+    #[tokio::test]
+    async fn invalidation_events_rotate_etags() {
+        let invalidator = CacheInvalidator::default();
+        let app = app(CacheLayer::new(CachePolicy::NoCache).with_tag("notes", invalidator.clone()));
+        let etag = get_with(&app, "/json", None).await.headers()[header::ETAG].clone();
+
+        let (tx, rx) = broadcast::channel::<String>(4);
+        let listener = invalidator.listen(rx);
+        tx.send("notes".to_string()).expect("send invalidation");
+        drop(tx);
+        listener.await.expect("listener should finish");
+
+        let response = get_with(&app, "/json", Some(&etag)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(response.headers()[header::ETAG], etag);
+    }
+
+    #[tokio::test]
+    async fn bus_events_invalidate_topic_and_kind_tags() {
+        let invalidator = CacheInvalidator::default();
+        let app = app(CacheLayer::new(CachePolicy::NoCache).with_tag("notes", invalidator.clone()));
+        let etag = get_with(&app, "/json", None).await.headers()[header::ETAG].clone();
+
+        let bus = EventBus::new();
+        let listener = invalidator.listen_bus(&bus, "notes");
+        bus.publish("notes", "deleted", &serde_json::json!({ "id": 1 }))
+            .expect("publish");
+        bus.publish("audit", "login", &serde_json::json!({}))
+            .expect("publish");
+        for _ in 0..100 {
+            if invalidator.generation("notes:deleted") > 0 {
+                break;
+            }
+            tokio::task::yield_now().await;
+        }
+        listener.abort();
+
+        assert_eq!(invalidator.generation("notes"), 1);
+        assert_eq!(invalidator.generation("notes:deleted"), 1);
+        assert_eq!(invalidator.generation("audit"), 0);
+        let response = get_with(&app, "/json", Some(&etag)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(response.headers()[header::ETAG], etag);
+    }
+
+    #[tokio::test]
+    async fn oversized_responses_pass_through_without_an_etag() {
+        let app = app(CacheLayer::new(CachePolicy::NoCache).with_max_body_bytes(8));
+        let response = get_with(&app, "/json", None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get(header::ETAG).is_none());
+        assert!(response.headers().get(header::CACHE_CONTROL).is_none());
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], br#"{"id":1,"title":"a"}"#);
+    }
+}
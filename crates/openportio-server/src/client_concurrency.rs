@@ -0,0 +1,314 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::{from_fn_with_state, Next},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use openportio_core::{auth::AuthPrincipal, MetricsSink};
+
+use crate::{
+    api::ApiErrorResponse,
+    client_ip::ClientIp,
+    middleware::{is_grpc_request, HEALTH_PATHS},
+};
+
+const DEFAULT_TOP_OFFENDERS: usize = 5;
+/// Rejection counts kept per client; past this only the current top offenders survive.
+const MAX_TRACKED_OFFENDERS: usize = 10_000;
+
+/// Per-client cap on in-flight calls, so one client cannot take the whole global
+/// `max_in_flight_requests` (or adaptive) budget. Clients are the authenticated subject, else
+/// the resolved client IP.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientConcurrency {
+    pub max_in_flight: usize,
+    pub retry_after_seconds: u64,
+    /// How many of the most rejected clients are reported as gauges.
+    pub top_offenders: usize,
+}
+
+impl ClientConcurrency {
+    pub fn new(max_in_flight: usize) -> Self {
+        Self {
+            max_in_flight: max_in_flight.max(1),
+            retry_after_seconds: 1,
+            top_offenders: DEFAULT_TOP_OFFENDERS,
+        }
+    }
+
+    pub fn with_retry_after(mut self, seconds: u64) -> Self {
+        self.retry_after_seconds = seconds;
+        self
+    }
+
+    pub fn with_top_offenders(mut self, count: usize) -> Self {
+        self.top_offenders = count;
+        self
+    }
+
+    /// `OPENPORTIO_MAX_IN_FLIGHT_PER_CLIENT` enables the cap.
+    pub(crate) fn from_env() -> Result<Option<Self>, String> {
+        Ok(crate::env::setting::<usize>("MAX_IN_FLIGHT_PER_CLIENT")?
+            .filter(|max| *max > 0)
+            .map(Self::new))
+    }
+}
+
+#[derive(Debug, Default)]
+struct Clients {
+    in_flight: HashMap<String, usize>,
+    rejections: HashMap<String, u64>,
+}
+
+#[derive(Clone)]
+struct ClientLimiter {
+    config: Arc<ClientConcurrency>,
+    clients: Arc<Mutex<Clients>>,
+    metrics: Arc<dyn MetricsSink>,
+}
+
+impl ClientLimiter {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Clients> {
+        self.clients.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn try_acquire(&self, client: String) -> Result<ClientPermit, String> {
+        let mut clients = self.lock();
+        let in_flight = clients.in_flight.entry(client.clone()).or_insert(0);
+        if *in_flight >= self.config.max_in_flight {
+            return Err(client);
+        }
+        *in_flight += 1;
+        Ok(ClientPermit {
+            limiter: self.clone(),
+            client,
+        })
+    }
+
+    /// Counts a rejection and republishes the top offenders as
+    /// `client_concurrency.top_offender{rank, client}` gauges.
+    fn record_rejection(&self, client: &str, protocol: &str) {
+        self.metrics
+            .incr_counter_with("client_concurrency.rejected", &[("protocol", protocol)]);
+        let top = {
+            let mut clients = self.lock();
+            *clients.rejections.entry(client.to_string()).or_insert(0) += 1;
+            let top = top_offenders(&clients.rejections, self.config.top_offenders);
+            if clients.rejections.len() > MAX_TRACKED_OFFENDERS {
+                clients
+                    .rejections
+                    .retain(|client, _| top.iter().any(|(top, _)| top == client));
+            }
+            top
+        };
+        for (rank, (client, rejections)) in top.iter().enumerate() {
+            let rank = (rank + 1).to_string();
+            self.metrics.set_gauge(
+                "client_concurrency.top_offender",
+                *rejections as f64,
+                &[("rank", rank.as_str()), ("client", client.as_str())],
+            );
+        }
+    }
+}
+
+fn top_offenders(rejections: &HashMap<String, u64>, count: usize) -> Vec<(String, u64)> {
+    let mut top: Vec<_> = rejections
+        .iter()
+        .map(|(client, rejections)| (client.clone(), *rejections))
+        .collect();
+    top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    top.truncate(count);
+    top
+}
+
+/// Released when the call's response head is produced or the call is dropped.
+struct ClientPermit {
+    limiter: ClientLimiter,
+    client: String,
+}
+
+impl Drop for ClientPermit {
+    fn drop(&mut self) {
+        let mut clients = self.limiter.lock();
+        if let Some(in_flight) = clients.in_flight.get_mut(&self.client) {
+            *in_flight -= 1;
+            if *in_flight == 0 {
+                clients.in_flight.remove(&self.client);
+            }
+        }
+    }
+}
+
+/// Rejects a client's calls beyond [`ClientConcurrency::max_in_flight`] with `429`
+/// `too_many_concurrent_requests` + `Retry-After` (gRPC `RESOURCE_EXHAUSTED`), counted as
+/// `client_concurrency.rejected` (label `protocol`). Health probes are exempt. Install it
+/// inside the auth layer so REST calls are keyed by principal; gRPC principals are resolved
+/// by the interceptor later, so gRPC calls are keyed by client IP.
+pub fn with_client_concurrency(
+    router: Router,
+    config: ClientConcurrency,
+    metrics: Arc<dyn MetricsSink>,
+) -> Router {
+    let limiter = ClientLimiter {
+        config: Arc::new(config),
+        clients: Arc::new(Mutex::new(Clients::default())),
+        metrics,
+    };
+    router.layer(from_fn_with_state(limiter, client_concurrency_middleware))
+}
+
+fn client_key(req: &Request) -> String {
+    if let Some(principal) = req.extensions().get::<AuthPrincipal>() {
+        return format!("subject:{}", principal.subject);
+    }
+    match req.extensions().get::<ClientIp>() {
+        Some(ClientIp(ip)) => format!("ip:{ip}"),
+        None => "unknown".to_string(),
+    }
+}
+
+async fn client_concurrency_middleware(
+    State(limiter): State<ClientLimiter>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if HEALTH_PATHS.contains(&req.uri().path()) {
+        return next.run(req).await;
+    }
+    let permit = match limiter.try_acquire(client_key(&req)) {
+        Ok(permit) => permit,
+        Err(client) => {
+            let is_grpc = is_grpc_request(req.headers());
+            let protocol = if is_grpc { "grpc" } else { "rest" };
+            tracing::warn!(
+                client = %client,
+                limit = limiter.config.max_in_flight,
+                protocol,
+                "rejecting request: client in-flight limit reached"
+            );
+            limiter.record_rejection(&client, protocol);
+            return too_many_concurrent_requests(&limiter.config, is_grpc);
+        }
+    };
+    let response = next.run(req).await;
+    drop(permit);
+    response
+}
+
+fn too_many_concurrent_requests(config: &ClientConcurrency, is_grpc: bool) -> Response {
+    let message = format!(
+        "too many concurrent requests from this client (limit {}); retry later",
+        config.max_in_flight
+    );
+    if is_grpc {
+        return tonic::Status::resource_exhausted(message)
+            .into_http()
+            .map(Body::new);
+    }
+    let mut response = (
+        StatusCode::TOO_MANY_REQUESTS,
+        Json(ApiErrorResponse {
+            code: "too_many_concurrent_requests".to_string(),
+            message,
+            detail: None,
+            details: Some(serde_json::json!({
+                "limit": config.max_in_flight,
+                "retry_after_seconds": config.retry_after_seconds,
+            })),
+        }),
+    )
+        .into_response();
+    response.headers_mut().insert(
+        header::RETRY_AFTER,
+        HeaderValue::from(config.retry_after_seconds),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use openportio_test::RecordingMetrics;
+    use std::{net::IpAddr, time::Duration};
+    use tokio::sync::Notify;
+    use tower::util::ServiceExt;
+
+    #[tokio::test]
+    async fn each_client_gets_its_own_in_flight_budget() {
+        let release = Arc::new(Notify::new());
+        let metrics = Arc::new(RecordingMetrics::default());
+        let app = with_client_concurrency(
+            Router::new().route(
+                "/slow",
+                get({
+                    let release = release.clone();
+                    move || async move { release.notified().await }
+                }),
+            ),
+            ClientConcurrency::new(1).with_retry_after(2),
+            metrics.clone(),
+        );
+        let call = |ip: &str, principal: Option<&str>| {
+            let mut request = Request::get("/slow").body(Body::empty()).unwrap();
+            request
+                .extensions_mut()
+                .insert(ClientIp(ip.parse::<IpAddr>().unwrap()));
+            if let Some(subject) = principal {
+                request.extensions_mut().insert(AuthPrincipal {
+                    subject: subject.to_string(),
+                    issuer: None,
+                    audience: Vec::new(),
+                    scopes: Vec::new(),
+                    token_id: None,
+                    tenant_id: None,
+                });
+            }
+            app.clone().oneshot(request)
+        };
+
+        let busy = tokio::spawn(call("10.0.0.1", None));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let response = call("10.0.0.1", None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "2");
+        // Same address, but an authenticated subject is its own client.
+        let other = tokio::spawn(call("10.0.0.1", Some("svc-reports")));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!other.is_finished());
+
+        release.notify_waiters();
+        assert_eq!(busy.await.unwrap().unwrap().status(), StatusCode::OK);
+        assert_eq!(other.await.unwrap().unwrap().status(), StatusCode::OK);
+        assert_eq!(
+            metrics.counters(),
+            ["client_concurrency.rejected{protocol=rest}"]
+        );
+        assert_eq!(
+            metrics.gauges(),
+            ["client_concurrency.top_offender{rank=1,client=ip:10.0.0.1}=1"]
+        );
+    }
+
+    #[test]
+    fn top_offenders_are_ranked_by_rejections() {
+        let rejections = HashMap::from([
+            ("ip:10.0.0.1".to_string(), 3),
+            ("subject:a".to_string(), 7),
+            ("ip:10.0.0.2".to_string(), 3),
+        ]);
+        assert_eq!(
+            top_offenders(&rejections, 2),
+            [("subject:a".to_string(), 7), ("ip:10.0.0.1".to_string(), 3)]
+        );
+    }
+}
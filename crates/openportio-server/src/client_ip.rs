@@ -0,0 +1,396 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+    sync::Arc,
+};
+
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+pub use ipnet::IpNet;
+
+use crate::{api::ApiErrorResponse, middleware::is_grpc_request};
+
+const FORWARDED_HEADER: &str = "forwarded";
+const X_FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+/// The resolved client address, inserted into request extensions by the shared middleware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+/// CIDR allow/deny lists. A matching deny entry always rejects; a non-empty allowlist rejects
+/// every address it does not contain.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IpFilter {
+    allow: Vec<IpNet>,
+    deny: Vec<IpNet>,
+}
+
+impl IpFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn allow(mut self, net: IpNet) -> Self {
+        self.allow.push(net);
+        self
+    }
+
+    pub fn deny(mut self, net: IpNet) -> Self {
+        self.deny.push(net);
+        self
+    }
+
+    pub fn allowlist(&self) -> &[IpNet] {
+        &self.allow
+    }
+
+    pub fn denylist(&self) -> &[IpNet] {
+        &self.deny
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    pub fn is_allowed(&self, ip: IpAddr) -> bool {
+        if self.deny.iter().any(|net| net.contains(&ip)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|net| net.contains(&ip))
+    }
+
+    // Unknown addresses only pass filters without an allowlist.
+    fn is_allowed_opt(&self, ip: Option<IpAddr>) -> bool {
+        match ip {
+            Some(ip) => self.is_allowed(ip),
+            None => self.allow.is_empty(),
+        }
+    }
+}
+
+/// The forwarding header trusted proxies set. Only this one is read: a proxy that appends to
+/// `X-Forwarded-For` passes a client-supplied `Forwarded` header through untouched, so reading
+/// both would let clients choose their own address.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ForwardedHeader {
+    #[default]
+    XForwardedFor,
+    Forwarded,
+}
+
+impl ForwardedHeader {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::XForwardedFor => X_FORWARDED_FOR_HEADER,
+            Self::Forwarded => FORWARDED_HEADER,
+        }
+    }
+}
+
+impl FromStr for ForwardedHeader {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            X_FORWARDED_FOR_HEADER | "xff" => Ok(Self::XForwardedFor),
+            FORWARDED_HEADER => Ok(Self::Forwarded),
+            other => Err(format!("unsupported trusted proxy header `{other}`")),
+        }
+    }
+}
+
+/// Parses a comma-separated list of CIDR ranges or bare addresses (`10.0.0.0/8, 192.0.2.7`),
+/// skipping invalid entries with a warning.
+pub fn parse_ip_nets(raw: &str) -> Vec<IpNet> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let parsed = entry
+                .parse::<IpNet>()
+                .ok()
+                .or_else(|| entry.parse::<IpAddr>().ok().map(IpNet::from));
+            if parsed.is_none() {
+                tracing::warn!(entry = %entry, "ignoring invalid ip range");
+            }
+            parsed
+        })
+        .collect()
+}
+
+/// Resolves the client address from the socket peer and, when the peer is a trusted proxy, the
+/// chain in `header`: the right-most hop that is not itself a trusted proxy wins. Headers from
+/// untrusted peers are ignored.
+pub fn resolve_client_ip(
+    headers: &HeaderMap,
+    peer: Option<IpAddr>,
+    trusted_proxies: &[IpNet],
+    header: ForwardedHeader,
+) -> Option<IpAddr> {
+    let peer = peer?;
+    let is_trusted = |ip: &IpAddr| trusted_proxies.iter().any(|net| net.contains(ip));
+    if !is_trusted(&peer) {
+        return Some(peer);
+    }
+
+    let chain = forwarded_chain(headers, header);
+    Some(
+        chain
+            .iter()
+            .rev()
+            .find(|hop| !is_trusted(hop))
+            .or_else(|| chain.first())
+            .copied()
+            .unwrap_or(peer),
+    )
+}
+
+fn forwarded_chain(headers: &HeaderMap, header: ForwardedHeader) -> Vec<IpAddr> {
+    let elements = headers
+        .get_all(header.as_str())
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','));
+    match header {
+        ForwardedHeader::XForwardedFor => elements.filter_map(parse_hop).collect(),
+        ForwardedHeader::Forwarded => elements
+            .filter_map(|element| {
+                element.split(';').find_map(|pair| {
+                    let (key, value) = pair.split_once('=')?;
+                    key.trim()
+                        .eq_ignore_ascii_case("for")
+                        .then(|| parse_hop(value))
+                        .flatten()
+                })
+            })
+            .collect(),
+    }
+}
+
+// Accepts `1.2.3.4`, `1.2.3.4:80`, `"[2001:db8::1]:4711"`; obfuscated ids and `unknown` yield
+// `None`.
+fn parse_hop(raw: &str) -> Option<IpAddr> {
+    let raw = raw.trim().trim_matches('"');
+    if let Some(rest) = raw.strip_prefix('[') {
+        return rest.split_once(']')?.0.parse().ok();
+    }
+    raw.parse::<IpAddr>()
+        .ok()
+        .or_else(|| raw.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+}
+
+#[derive(Clone)]
+pub(crate) struct ClientIpState {
+    trusted_proxies: Arc<[IpNet]>,
+    header: ForwardedHeader,
+    filter: Option<IpFilter>,
+}
+
+impl ClientIpState {
+    pub(crate) fn new(
+        trusted_proxies: &[IpNet],
+        header: ForwardedHeader,
+        filter: &IpFilter,
+    ) -> Self {
+        Self {
+            trusted_proxies: trusted_proxies.into(),
+            header,
+            filter: (!filter.is_empty()).then(|| filter.clone()),
+        }
+    }
+}
+
+pub(crate) async fn client_ip_middleware(
+    State(state): State<ClientIpState>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let peer = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let client_ip = resolve_client_ip(req.headers(), peer, &state.trusted_proxies, state.header);
+    if let Some(filter) = &state.filter {
+        if !filter.is_allowed_opt(client_ip) {
+            return forbidden_response(client_ip, is_grpc_request(req.headers()));
+        }
+    }
+    if let Some(ip) = client_ip {
+        req.extensions_mut().insert(ClientIp(ip));
+    }
+    next.run(req).await
+}
+
+/// Per-route enforcement, for use with `route_layer(from_fn_with_state(filter, ip_filter_middleware))`.
+/// Relies on the [`ClientIp`] resolved by the shared middleware, falling back to the socket peer.
+pub async fn ip_filter_middleware(
+    State(filter): State<IpFilter>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let client_ip = req
+        .extensions()
+        .get::<ClientIp>()
+        .map(|ClientIp(ip)| *ip)
+        .or_else(|| {
+            req.extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip())
+        });
+    if !filter.is_allowed_opt(client_ip) {
+        return forbidden_response(client_ip, is_grpc_request(req.headers()));
+    }
+    next.run(req).await
+}
+
+fn forbidden_response(client_ip: Option<IpAddr>, is_grpc: bool) -> Response {
+    let message = "client address is not allowed";
+    if is_grpc {
+        return tonic::Status::permission_denied(message)
+            .into_http()
+            .map(Body::new);
+    }
+    (
+        StatusCode::FORBIDDEN,
+        Json(ApiErrorResponse {
+            code: "ip_forbidden".to_string(),
+            message: message.to_string(),
+            detail: None,
+            details: Some(serde_json::json!({
+                "client_ip": client_ip.map(|ip| ip.to_string()),
+            })),
+        }),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::to_bytes,
+        http::{HeaderValue, Request},
+        middleware::from_fn_with_state,
+        routing::get,
+        Router,
+    };
+    use tower::util::ServiceExt;
+
+    fn ip(raw: &str) -> IpAddr {
+        raw.parse().unwrap()
+    }
+
+    #[test]
+    fn resolves_client_ip_through_trusted_proxies_only() {
+        let trusted = parse_ip_nets("10.0.0.0/8, 192.0.2.1");
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            X_FORWARDED_FOR_HEADER,
+            HeaderValue::from_static("203.0.113.9, 198.51.100.4, 10.1.1.1"),
+        );
+
+        let xff = ForwardedHeader::XForwardedFor;
+        assert_eq!(
+            resolve_client_ip(&headers, Some(ip("10.0.0.2")), &trusted, xff),
+            Some(ip("198.51.100.4"))
+        );
+        assert_eq!(
+            resolve_client_ip(&headers, Some(ip("198.51.100.77")), &trusted, xff),
+            Some(ip("198.51.100.77"))
+        );
+
+        headers.insert(
+            FORWARDED_HEADER,
+            HeaderValue::from_static(r#"for="[2001:db8::7]:4711";proto=https, for=192.0.2.1"#),
+        );
+        assert_eq!(
+            resolve_client_ip(
+                &headers,
+                Some(ip("10.0.0.2")),
+                &trusted,
+                ForwardedHeader::Forwarded
+            ),
+            Some(ip("2001:db8::7"))
+        );
+    }
+
+    #[test]
+    fn only_the_configured_forwarding_header_is_read() {
+        let trusted = parse_ip_nets("10.0.0.0/8");
+        let mut headers = HeaderMap::new();
+        // The proxy appended the real client to X-Forwarded-For and passed the client's own
+        // `Forwarded` header through.
+        headers.insert(FORWARDED_HEADER, HeaderValue::from_static("for=192.0.2.66"));
+        headers.insert(
+            X_FORWARDED_FOR_HEADER,
+            HeaderValue::from_static("203.0.113.9"),
+        );
+
+        assert_eq!(
+            resolve_client_ip(
+                &headers,
+                Some(ip("10.0.0.2")),
+                &trusted,
+                ForwardedHeader::XForwardedFor
+            ),
+            Some(ip("203.0.113.9"))
+        );
+        assert_eq!(
+            "Forwarded".parse::<ForwardedHeader>(),
+            Ok(ForwardedHeader::Forwarded)
+        );
+        assert!("both".parse::<ForwardedHeader>().is_err());
+    }
+
+    #[test]
+    fn deny_entries_win_over_allowlist() {
+        let filter = IpFilter::new()
+            .allow("10.0.0.0/8".parse().unwrap())
+            .deny("10.6.0.0/16".parse().unwrap());
+
+        assert!(filter.is_allowed(ip("10.1.2.3")));
+        assert!(!filter.is_allowed(ip("10.6.0.1")));
+        assert!(!filter.is_allowed(ip("203.0.113.1")));
+        assert!(!filter.is_allowed_opt(None));
+        assert!(IpFilter::new()
+            .deny("10.6.0.0/16".parse().unwrap())
+            .is_allowed_opt(None));
+    }
+
+    #[tokio::test]
+    async fn route_filter_rejects_with_structured_403() {
+        let app = Router::new()
+            .route("/admin", get(|| async { "ok" }))
+            .route_layer(from_fn_with_state(
+                IpFilter::new().allow("10.0.0.0/8".parse().unwrap()),
+                ip_filter_middleware,
+            ));
+
+        let mut allowed = Request::builder()
+            .uri("/admin")
+            .body(Body::empty())
+            .unwrap();
+        allowed.extensions_mut().insert(ClientIp(ip("10.0.0.9")));
+        let response = app.clone().oneshot(allowed).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let mut denied = Request::builder()
+            .uri("/admin")
+            .body(Body::empty())
+            .unwrap();
+        denied.extensions_mut().insert(ClientIp(ip("203.0.113.5")));
+        let response = app.oneshot(denied).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body: serde_json::Value =
+            serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap())
+                .unwrap();
+        assert_eq!(body["code"], "ip_forbidden");
+        assert_eq!(body["details"]["client_ip"], "203.0.113.5");
+    }
+}
@@ -0,0 +1,380 @@
+use std::{
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+use axum::{
+    extract::{FromRef, Request},
+    middleware::Next,
+    response::Response,
+};
+use openportio_core::{auth::AuthPrincipal, AppState, CancellationToken, MetricsSink};
+
+use crate::{
+    di::resolve_from_extensions,
+    middleware::{is_grpc_request, RequestDeadline},
+    trace_context::TraceContext,
+};
+
+/// Per-request facts shared by REST and gRPC handlers: request id, trace ids, client deadline,
+/// a cancellation token, and, once auth and tenancy have run, the principal and tenant. Extract it with
+/// `Depends<RequestContext>` or call [`RequestContextExt::ctx`] on a `tonic::Request`.
+///
+/// Clones share state, so values stored with [`Self::insert`] by middleware are visible to the
+/// handler.
+#[derive(Clone)]
+pub struct RequestContext {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    trace: TraceContext,
+    deadline: Option<RequestDeadline>,
+    cancellation: CancellationToken,
+    principal: RwLock<Option<AuthPrincipal>>,
+    tenant_id: RwLock<Option<String>>,
+    values: RwLock<http::Extensions>,
+}
+
+impl RequestContext {
+    /// A context for work outside a request (jobs, tests): a new root trace, nothing else set.
+    pub fn detached() -> Self {
+        Self::new(TraceContext::new_root(), None, CancellationToken::new())
+    }
+
+    fn new(
+        trace: TraceContext,
+        deadline: Option<RequestDeadline>,
+        cancellation: CancellationToken,
+    ) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                trace,
+                deadline,
+                cancellation,
+                principal: RwLock::default(),
+                tenant_id: RwLock::default(),
+                values: RwLock::default(),
+            }),
+        }
+    }
+
+    /// The context in `extensions`, or a detached one when the shared middleware did not run.
+    pub fn from_extensions(extensions: &http::Extensions) -> Self {
+        extensions
+            .get::<Self>()
+            .cloned()
+            .unwrap_or_else(Self::detached)
+    }
+
+    /// The `x-request-id` of the call (generated by the shared middleware when absent).
+    pub fn request_id(&self) -> Option<&str> {
+        self.inner.trace.request_id()
+    }
+
+    pub fn trace(&self) -> &TraceContext {
+        &self.inner.trace
+    }
+
+    pub fn trace_id(&self) -> String {
+        self.inner.trace.trace_id()
+    }
+
+    pub fn span_id(&self) -> String {
+        self.inner.trace.span_id()
+    }
+
+    /// The client's `grpc-timeout` / request timeout, when deadlines are honoured.
+    pub fn deadline(&self) -> Option<RequestDeadline> {
+        self.inner.deadline
+    }
+
+    /// Cancelled when the request is abandoned before its response is ready: the client
+    /// disconnected or the request timeout gave up on the handler. Handlers are dropped at that
+    /// point anyway; pass the token (or a child) to spawned tasks and blocking work, or wrap
+    /// calls in `openportio_core::select_with_cancel`.
+    pub fn cancellation(&self) -> CancellationToken {
+        self.inner.cancellation.clone()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancellation.is_cancelled()
+    }
+
+    pub fn principal(&self) -> Option<AuthPrincipal> {
+        read(&self.inner.principal).clone()
+    }
+
+    pub fn tenant_id(&self) -> Option<String> {
+        read(&self.inner.tenant_id).clone()
+    }
+
+    /// Stores a typed value for the rest of the request, replacing any previous `T`.
+    pub fn insert<T>(&self, value: T)
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        write(&self.inner.values).insert(value);
+    }
+
+    pub fn get<T>(&self) -> Option<T>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        read(&self.inner.values).get::<T>().cloned()
+    }
+
+    pub(crate) fn set_principal(&self, principal: &AuthPrincipal) {
+        *write(&self.inner.principal) = Some(principal.clone());
+    }
+
+    pub(crate) fn set_tenant_id(&self, tenant_id: &str) {
+        *write(&self.inner.tenant_id) = Some(tenant_id.to_string());
+    }
+}
+
+fn read<T>(lock: &RwLock<T>) -> std::sync::RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(|err| err.into_inner())
+}
+
+fn write<T>(lock: &RwLock<T>) -> std::sync::RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(|err| err.into_inner())
+}
+
+impl fmt::Debug for RequestContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestContext")
+            .field("request_id", &self.request_id())
+            .field("trace_id", &self.trace_id())
+            .field("deadline", &self.deadline())
+            .field("cancelled", &self.is_cancelled())
+            .field(
+                "principal",
+                &read(&self.inner.principal)
+                    .as_ref()
+                    .map(|principal| &principal.subject),
+            )
+            .field("tenant_id", &self.tenant_id())
+            .finish_non_exhaustive()
+    }
+}
+
+// `Depends<RequestContext>` finds the middleware's context in the request extensions first;
+// these fallbacks only run on routers served without the shared middleware.
+impl FromRef<Arc<AppState>> for RequestContext {
+    fn from_ref(_state: &Arc<AppState>) -> Self {
+        Self::detached()
+    }
+}
+
+impl FromRef<()> for RequestContext {
+    fn from_ref(_state: &()) -> Self {
+        Self::detached()
+    }
+}
+
+/// `request.ctx()` in tonic handlers.
+pub trait RequestContextExt {
+    fn ctx(&self) -> RequestContext;
+}
+
+impl<T> RequestContextExt for tonic::Request<T> {
+    fn ctx(&self) -> RequestContext {
+        RequestContext::from_extensions(self.extensions())
+    }
+}
+
+pub(crate) async fn request_context_middleware(mut req: Request, next: Next) -> Response {
+    if req.extensions().get::<RequestContext>().is_some() {
+        return next.run(req).await;
+    }
+    let trace = req
+        .extensions()
+        .get::<TraceContext>()
+        .cloned()
+        .unwrap_or_else(|| TraceContext::from_headers(req.headers()));
+    let deadline = req.extensions().get::<RequestDeadline>().copied();
+    let cancellation = CancellationToken::new();
+    let guard = AbortGuard {
+        token: cancellation.clone(),
+        metrics: resolve_from_extensions::<Arc<dyn MetricsSink>>(req.extensions()),
+        protocol: if is_grpc_request(req.headers()) {
+            "grpc"
+        } else {
+            "rest"
+        },
+        completed: false,
+    };
+    req.extensions_mut()
+        .insert(RequestContext::new(trace, deadline, cancellation));
+    let response = next.run(req).await;
+    guard.complete();
+    response
+}
+
+/// Dropped with the request future. Unless the response was produced first, it cancels the
+/// request's token and counts `requests.aborted` (label `protocol`), separately from
+/// `http.requests` / `grpc.requests` which only see finished calls.
+struct AbortGuard {
+    token: CancellationToken,
+    metrics: Option<Arc<dyn MetricsSink>>,
+    protocol: &'static str,
+    completed: bool,
+}
+
+impl AbortGuard {
+    fn complete(mut self) {
+        self.completed = true;
+    }
+}
+
+impl Drop for AbortGuard {
+    fn drop(&mut self) {
+        if self.completed {
+            return;
+        }
+        self.token.cancel();
+        tracing::debug!(
+            protocol = self.protocol,
+            "request aborted before its response"
+        );
+        if let Some(metrics) = &self.metrics {
+            metrics.incr_counter_with("requests.aborted", &[("protocol", self.protocol)]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        di::Depends,
+        middleware::{apply_shared_middleware, MiddlewareConfig},
+    };
+    use axum::{body::Body, routing::get, Router};
+    use openportio_test::RecordingMetrics;
+    use tower::ServiceExt;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Locale(&'static str);
+
+    async fn context_handler(Depends(ctx): Depends<RequestContext>) -> String {
+        format!(
+            "{}|{}|{}|{:?}",
+            ctx.request_id().unwrap_or_default(),
+            ctx.trace_id(),
+            ctx.deadline().is_some(),
+            ctx.get::<Locale>()
+        )
+    }
+
+    #[tokio::test]
+    async fn shared_middleware_builds_one_context_per_request() {
+        let app = apply_shared_middleware(
+            Router::new()
+                .route("/ctx", get(context_handler))
+                .layer(axum::middleware::from_fn(
+                    |req: Request, next: Next| async move {
+                        RequestContext::from_extensions(req.extensions()).insert(Locale("de"));
+                        next.run(req).await
+                    },
+                )),
+            &MiddlewareConfig {
+                honor_client_deadlines: true,
+                ..MiddlewareConfig::default()
+            },
+        );
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/ctx")
+                    .header("x-request-id", "req-42")
+                    .header(
+                        "traceparent",
+                        "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+                    )
+                    .header("grpc-timeout", "5S")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(
+            std::str::from_utf8(&body).unwrap(),
+            "req-42|4bf92f3577b34da6a3ce929d0e0e4736|true|Some(Locale(\"de\"))"
+        );
+    }
+
+    #[tokio::test]
+    async fn abandoned_requests_cancel_their_context_and_count_as_aborted() {
+        let metrics = Arc::new(RecordingMetrics::default());
+        let (observed_tx, observed_rx) = tokio::sync::oneshot::channel::<()>();
+        let observed_tx = Arc::new(std::sync::Mutex::new(Some(observed_tx)));
+        let slow = move |Depends(ctx): Depends<RequestContext>| {
+            let observed_tx = observed_tx.clone();
+            async move {
+                let token = ctx.cancellation();
+                tokio::spawn(async move {
+                    token.cancelled().await;
+                    if let Some(tx) = observed_tx.lock().unwrap().take() {
+                        let _ = tx.send(());
+                    }
+                });
+                std::future::pending::<()>().await
+            }
+        };
+        let app = apply_shared_middleware(
+            Router::new()
+                .route("/slow", get(slow))
+                .route("/fast", get(|| async { "ok" })),
+            &MiddlewareConfig::default(),
+        );
+        let app = crate::di::with_dependency_overrides(
+            app,
+            crate::di::DependencyOverrides::default().with(metrics.clone() as Arc<dyn MetricsSink>),
+        );
+
+        let request = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let abandoned = tokio::time::timeout(
+            std::time::Duration::from_millis(50),
+            app.clone().oneshot(request("/slow")),
+        )
+        .await;
+        assert!(abandoned.is_err(), "the client gave up first");
+        observed_rx
+            .await
+            .expect("spawned work sees the cancellation");
+
+        let response = app.oneshot(request("/fast")).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert_eq!(metrics.counters(), ["requests.aborted{protocol=rest}"]);
+    }
+
+    #[test]
+    fn grpc_handlers_read_the_context_and_see_the_authenticated_principal() {
+        let context = RequestContext::detached();
+        let mut request = tonic::Request::new(());
+        request.extensions_mut().insert(context.clone());
+        context.set_principal(&AuthPrincipal {
+            subject: "svc-a".to_string(),
+            issuer: None,
+            audience: Vec::new(),
+            scopes: Vec::new(),
+            token_id: None,
+            tenant_id: None,
+        });
+        context.set_tenant_id("acme");
+
+        let ctx = request.ctx();
+        assert_eq!(
+            ctx.principal().map(|principal| principal.subject),
+            Some("svc-a".to_string())
+        );
+        assert_eq!(ctx.tenant_id().as_deref(), Some("acme"));
+        assert_eq!(ctx.trace_id(), context.trace_id());
+        assert!(tonic::Request::new(()).ctx().principal().is_none());
+    }
+}
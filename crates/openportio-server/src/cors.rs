@@ -0,0 +1,551 @@
+use std::{convert::Infallible, sync::Arc, time::Duration};
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri},
+    middleware::Next,
+    response::Response,
+    Router,
+};
+use openportio_core::MetricsSink;
+use tower::{Layer, ServiceExt};
+use tower_http::cors::{AllowHeaders, AllowOrigin, CorsLayer};
+
+use crate::{
+    di::resolve_from_extensions, middleware::CorsAllowOrigins, response_headers::path_has_prefix,
+};
+
+/// Cross-origin policy installed by the shared middleware. Nothing is emitted until origins are
+/// allowed; headers and expose headers default to none, so preflights only succeed for what is
+/// listed. Without a method list, servers built by `OpenportioServer` answer preflights with the
+/// methods the requested route declares. Route policies replace the default for paths under their prefix
+/// (segment-wise, the longest prefix wins), and a route policy without origins turns CORS off
+/// there.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CorsPolicy {
+    pub allow_origins: CorsAllowOrigins,
+    pub allow_methods: Vec<Method>,
+    pub allow_headers: Vec<HeaderName>,
+    /// Any requested header; answered by mirroring `Access-Control-Request-Headers` when
+    /// credentials are allowed, since browsers reject `*` there.
+    pub allow_any_header: bool,
+    pub allow_credentials: bool,
+    pub max_age: Option<Duration>,
+    pub expose_headers: Vec<HeaderName>,
+    pub(crate) routes: Vec<(String, CorsPolicy)>,
+}
+
+impl CorsPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_origins(mut self, origins: impl IntoIterator<Item = HeaderValue>) -> Self {
+        let origins: Vec<_> = origins.into_iter().collect();
+        self.allow_origins = if origins.is_empty() {
+            CorsAllowOrigins::None
+        } else {
+            CorsAllowOrigins::List(origins)
+        };
+        self
+    }
+
+    pub fn with_any_origin(mut self) -> Self {
+        self.allow_origins = CorsAllowOrigins::Any;
+        self
+    }
+
+    pub fn with_methods(mut self, methods: impl IntoIterator<Item = Method>) -> Self {
+        self.allow_methods = methods.into_iter().collect();
+        self
+    }
+
+    pub fn with_headers(mut self, headers: impl IntoIterator<Item = HeaderName>) -> Self {
+        self.allow_headers = headers.into_iter().collect();
+        self
+    }
+
+    pub fn with_any_header(mut self) -> Self {
+        self.allow_any_header = true;
+        self
+    }
+
+    /// Ignored with any origin: browsers reject credentialed `*` responses, so the policy drops
+    /// credentials and [`Self::warnings`] reports it.
+    pub fn with_credentials(mut self, allow: bool) -> Self {
+        self.allow_credentials = allow;
+        self
+    }
+
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn with_expose_headers(mut self, headers: impl IntoIterator<Item = HeaderName>) -> Self {
+        self.expose_headers = headers.into_iter().collect();
+        self
+    }
+
+    /// Uses `policy` instead of this one for paths under `prefix`.
+    pub fn with_route_policy(mut self, prefix: impl Into<String>, policy: CorsPolicy) -> Self {
+        self.routes.push((prefix.into(), policy));
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !matches!(self.allow_origins, CorsAllowOrigins::None)
+            || self.routes.iter().any(|(_, policy)| policy.is_enabled())
+    }
+
+    /// Insecure or self-defeating combinations, for startup diagnostics.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        match &self.allow_origins {
+            CorsAllowOrigins::Any if self.allow_credentials => warnings.push(
+                "CORS allows credentials with any origin; credentials are not sent".to_string(),
+            ),
+            CorsAllowOrigins::List(origins) if origins.iter().any(|origin| origin == "null") => {
+                warnings.push(
+                    "CORS allows the `null` origin (sandboxed iframes, file URLs)".to_string(),
+                )
+            }
+            _ => {}
+        }
+        if self.allow_credentials && self.allow_any_header {
+            warnings.push("CORS mirrors any requested header on credentialed requests".to_string());
+        }
+        for (prefix, policy) in &self.routes {
+            warnings.extend(
+                policy
+                    .warnings()
+                    .into_iter()
+                    .map(|warning| format!("{warning} (under {prefix})")),
+            );
+        }
+        warnings
+    }
+
+    /// `*`, the origin list, or `disabled`, plus the options that are set, e.g. for diagnostics.
+    pub fn summary(&self) -> String {
+        let mut parts = vec![match &self.allow_origins {
+            CorsAllowOrigins::None => "disabled".to_string(),
+            CorsAllowOrigins::Any => "*".to_string(),
+            CorsAllowOrigins::List(origins) => origins
+                .iter()
+                .map(|origin| origin.to_str().unwrap_or("<binary>"))
+                .collect::<Vec<_>>()
+                .join(","),
+        }];
+        if !self.allow_methods.is_empty() {
+            let methods: Vec<_> = self.allow_methods.iter().map(Method::as_str).collect();
+            parts.push(format!("methods={}", methods.join(",")));
+        }
+        if self.allow_any_header {
+            parts.push("headers=*".to_string());
+        } else if !self.allow_headers.is_empty() {
+            parts.push(format!("headers={}", join_names(&self.allow_headers)));
+        }
+        if self.allow_credentials {
+            parts.push("credentials".to_string());
+        }
+        if let Some(max_age) = self.max_age {
+            parts.push(format!("max_age={}s", max_age.as_secs()));
+        }
+        if !self.expose_headers.is_empty() {
+            parts.push(format!("expose={}", join_names(&self.expose_headers)));
+        }
+        if !self.routes.is_empty() {
+            parts.push(format!("routes={}", self.routes.len()));
+        }
+        parts.join("; ")
+    }
+
+    fn layer(&self) -> Option<CorsLayer> {
+        let origin = match &self.allow_origins {
+            CorsAllowOrigins::None => return None,
+            CorsAllowOrigins::Any => AllowOrigin::any(),
+            CorsAllowOrigins::List(origins) => AllowOrigin::list(origins.clone()),
+        };
+        let credentials =
+            self.allow_credentials && !matches!(self.allow_origins, CorsAllowOrigins::Any);
+        let headers = match (self.allow_any_header, credentials) {
+            (true, true) => AllowHeaders::mirror_request(),
+            (true, false) => AllowHeaders::any(),
+            (false, _) => AllowHeaders::list(self.allow_headers.clone()),
+        };
+        let mut layer = CorsLayer::new()
+            .allow_origin(origin)
+            .allow_methods(self.allow_methods.clone())
+            .allow_headers(headers)
+            .allow_credentials(credentials)
+            .expose_headers(self.expose_headers.clone());
+        if let Some(max_age) = self.max_age {
+            layer = layer.max_age(max_age);
+        }
+        Some(layer)
+    }
+}
+
+fn join_names(names: &[HeaderName]) -> String {
+    names
+        .iter()
+        .map(HeaderName::as_str)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Layers built once from a [`CorsPolicy`]; `None` entries leave responses untouched.
+#[derive(Clone)]
+pub(crate) struct CorsLayers(Arc<CorsLayersInner>);
+
+struct CorsLayersInner {
+    default: Option<PathCors>,
+    routes: Vec<(String, Option<PathCors>)>,
+}
+
+struct PathCors {
+    layer: CorsLayer,
+    allow_origins: CorsAllowOrigins,
+    /// No methods listed: preflights are answered with what the route declares.
+    route_methods: bool,
+}
+
+impl CorsPolicy {
+    fn path_cors(&self) -> Option<PathCors> {
+        Some(PathCors {
+            layer: self.layer()?,
+            allow_origins: self.allow_origins.clone(),
+            route_methods: self.allow_methods.is_empty(),
+        })
+    }
+}
+
+impl PathCors {
+    fn allows_origin(&self, origin: &HeaderValue) -> bool {
+        match &self.allow_origins {
+            CorsAllowOrigins::None => false,
+            CorsAllowOrigins::Any => true,
+            CorsAllowOrigins::List(origins) => origins.contains(origin),
+        }
+    }
+}
+
+impl CorsLayers {
+    pub(crate) fn new(policy: &CorsPolicy) -> Self {
+        for warning in policy.warnings() {
+            tracing::warn!(%warning, "insecure cors policy");
+        }
+        Self(Arc::new(CorsLayersInner {
+            default: policy.path_cors(),
+            routes: policy
+                .routes
+                .iter()
+                .map(|(prefix, route)| (prefix.clone(), route.path_cors()))
+                .collect(),
+        }))
+    }
+
+    fn for_path(&self, path: &str) -> Option<&PathCors> {
+        self.0
+            .routes
+            .iter()
+            .filter(|(prefix, _)| path_has_prefix(path, prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.0.default.as_ref(), |(_, cors)| cors.as_ref())
+    }
+}
+
+/// The application's routes without its middleware, asked which methods a path declares so
+/// preflights for policies without a method list can be answered per route. The builder
+/// exposes it to [`cors_middleware`] as a request extension.
+#[derive(Clone)]
+pub(crate) struct RouteMethods(Router);
+
+impl RouteMethods {
+    pub(crate) fn new(router: Router) -> Self {
+        Self(router)
+    }
+
+    /// Methods from the `Allow` header the router sends for an `OPTIONS` request to `uri`;
+    /// `None` for unknown paths. A route with its own `OPTIONS` handler runs it.
+    async fn declared(&self, uri: &Uri) -> Option<Vec<Method>> {
+        let request = Request::builder()
+            .method(Method::OPTIONS)
+            .uri(uri.clone())
+            .body(Body::empty())
+            .ok()?;
+        let response = match self.0.clone().oneshot(request).await {
+            Ok(response) => response,
+            Err(never) => match never {},
+        };
+        if response.status() == StatusCode::NOT_FOUND {
+            return None;
+        }
+        let methods = allowed_methods(response.headers());
+        (!methods.is_empty()).then_some(methods)
+    }
+}
+
+fn allowed_methods(headers: &HeaderMap) -> Vec<Method> {
+    headers
+        .get_all(header::ALLOW)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|method| Method::from_bytes(method.trim().as_bytes()).ok())
+        .collect()
+}
+
+pub(crate) async fn cors_middleware(
+    State(layers): State<CorsLayers>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let Some(cors) = layers.for_path(req.uri().path()) else {
+        return next.run(req).await;
+    };
+    let is_options = req.method() == Method::OPTIONS;
+    let is_preflight = is_options
+        && req
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+    if let Some(origin) = req.headers().get(header::ORIGIN) {
+        if !cors.allows_origin(origin) {
+            record_rejected_origin(&req, origin, is_preflight);
+        }
+    }
+
+    let route_methods = req.extensions().get::<RouteMethods>().cloned();
+    let uri = req.uri().clone();
+    if is_options && !is_preflight {
+        // `CorsLayer` would answer every `OPTIONS` as a preflight; plain ones go to the route.
+        let response = next.run(req).await;
+        if response.status() != StatusCode::METHOD_NOT_ALLOWED {
+            return response;
+        }
+        let methods = match route_methods {
+            Some(route_methods) => route_methods.declared(&uri).await,
+            None => None,
+        };
+        return methods.map_or(response, answer_options);
+    }
+
+    let mut layer = cors.layer.clone();
+    if is_preflight && cors.route_methods {
+        if let Some(route_methods) = &route_methods {
+            if let Some(methods) = route_methods.declared(&uri).await {
+                layer = layer.allow_methods(methods);
+            }
+        }
+    }
+    let response: Result<Response, Infallible> = layer.layer(next).oneshot(req).await;
+    match response {
+        Ok(response) => response,
+        Err(never) => match never {},
+    }
+}
+
+/// A plain `OPTIONS` request to a route without an `OPTIONS` handler gets `204` and the
+/// route's methods in `Allow` instead of `405`.
+fn answer_options(mut methods: Vec<Method>) -> Response {
+    if !methods.contains(&Method::OPTIONS) {
+        methods.push(Method::OPTIONS);
+    }
+    let allow: Vec<_> = methods.iter().map(Method::as_str).collect();
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = StatusCode::NO_CONTENT;
+    if let Ok(allow) = HeaderValue::from_str(&allow.join(",")) {
+        response.headers_mut().insert(header::ALLOW, allow);
+    }
+    response
+}
+
+fn record_rejected_origin(req: &Request, origin: &HeaderValue, preflight: bool) {
+    tracing::debug!(
+        origin = origin.to_str().unwrap_or("<binary>"),
+        method = %req.method(),
+        path = %req.uri().path(),
+        preflight,
+        "cors origin rejected"
+    );
+    if let Some(metrics) = resolve_from_extensions::<Arc<dyn MetricsSink>>(req.extensions()) {
+        metrics.incr_counter_with(
+            "cors.rejected_origins",
+            &[("preflight", if preflight { "true" } else { "false" })],
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::di::DependencyOverrides;
+    use axum::{middleware::from_fn_with_state, routing::get, Extension};
+    use openportio_test::RecordingMetrics;
+
+    fn app(policy: &CorsPolicy) -> Router {
+        Router::new()
+            .route("/notes", get(|| async { "ok" }))
+            .route("/public/feed", get(|| async { "ok" }))
+            .route("/internal/stats", get(|| async { "ok" }))
+            .layer(from_fn_with_state(CorsLayers::new(policy), cors_middleware))
+    }
+
+    fn preflight(path: &str, origin: &str) -> Request {
+        Request::builder()
+            .method(Method::OPTIONS)
+            .uri(path)
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "PUT")
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "x-tenant")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn full_policy_answers_preflights_and_route_policies_override_it() {
+        let policy = CorsPolicy::new()
+            .with_origins([HeaderValue::from_static("https://app.example")])
+            .with_methods([Method::GET, Method::PUT])
+            .with_headers([HeaderName::from_static("x-tenant")])
+            .with_credentials(true)
+            .with_max_age(Duration::from_secs(600))
+            .with_expose_headers([HeaderName::from_static("x-request-id")])
+            .with_route_policy("/public", CorsPolicy::new().with_any_origin())
+            .with_route_policy("/internal", CorsPolicy::new());
+
+        let response = app(&policy)
+            .oneshot(preflight("/notes", "https://app.example"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers();
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://app.example"
+        );
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_METHODS], "GET,PUT");
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_HEADERS], "x-tenant");
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        assert_eq!(headers[header::ACCESS_CONTROL_MAX_AGE], "600");
+
+        let response = app(&policy)
+            .oneshot(
+                Request::builder()
+                    .uri("/notes")
+                    .header(header::ORIGIN, "https://app.example")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()[header::ACCESS_CONTROL_EXPOSE_HEADERS],
+            "x-request-id"
+        );
+
+        let response = app(&policy)
+            .oneshot(preflight("/public/feed", "https://other.example"))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert!(!response
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_CREDENTIALS));
+
+        let response = app(&policy)
+            .oneshot(preflight("/internal/stats", "https://app.example"))
+            .await
+            .unwrap();
+        assert!(!response
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[tokio::test]
+    async fn preflights_use_route_methods_and_plain_options_get_allow() {
+        let routes = Router::new().route("/notes", get(|| async { "ok" }).post(|| async { "ok" }));
+        let policy = CorsPolicy::new()
+            .with_origins([HeaderValue::from_static("https://app.example")])
+            .with_max_age(Duration::from_secs(300));
+        let metrics = Arc::new(RecordingMetrics::default());
+        let app = routes
+            .clone()
+            .layer(from_fn_with_state(
+                CorsLayers::new(&policy),
+                cors_middleware,
+            ))
+            .layer(Extension(RouteMethods::new(routes)))
+            .layer(Extension(
+                DependencyOverrides::default().with(metrics.clone() as Arc<dyn MetricsSink>),
+            ));
+
+        let response = app
+            .clone()
+            .oneshot(preflight("/notes", "https://app.example"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::ACCESS_CONTROL_ALLOW_METHODS],
+            "GET,HEAD,POST"
+        );
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_MAX_AGE], "300");
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method(Method::OPTIONS)
+                    .uri("/notes")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(response.headers()[header::ALLOW], "GET,HEAD,POST,OPTIONS");
+
+        let response = app
+            .oneshot(preflight("/notes", "https://evil.example"))
+            .await
+            .unwrap();
+        assert!(!response
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+        assert_eq!(
+            metrics.counters(),
+            ["cors.rejected_origins{preflight=true}"]
+        );
+    }
+
+    #[tokio::test]
+    async fn any_origin_with_credentials_warns_and_drops_credentials() {
+        let policy = CorsPolicy::new()
+            .with_any_origin()
+            .with_any_header()
+            .with_credentials(true);
+        assert_eq!(
+            policy.warnings(),
+            [
+                "CORS allows credentials with any origin; credentials are not sent",
+                "CORS mirrors any requested header on credentialed requests",
+            ]
+        );
+
+        let response = app(&policy)
+            .oneshot(preflight("/notes", "https://evil.example"))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert_eq!(
+            response.headers()[header::ACCESS_CONTROL_ALLOW_HEADERS],
+            "*"
+        );
+        assert!(!response
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_CREDENTIALS));
+    }
+}
@@ -0,0 +1,441 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::{from_fn_with_state, Next},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use openportio_core::auth::AuthPrincipal;
+use sha2::{Digest, Sha256};
+use tokio::sync::watch;
+
+use crate::{
+    api::ApiErrorResponse, body_stream::buffer_within, client_ip::ClientIp,
+    di::clock_from_extensions,
+};
+
+/// Set to `true` on responses replayed from an earlier identical request.
+pub const DEDUPLICATED_HEADER: &str = "x-deduplicated";
+
+const DEFAULT_DEDUP_WINDOW: Duration = Duration::from_secs(5);
+const DEFAULT_MAX_DEDUP_BODY_BYTES: usize = 1_048_576;
+
+/// What a duplicate arriving inside the window gets back.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicateAction {
+    /// The original response, once it is ready, with `x-deduplicated: true` (default).
+    #[default]
+    Replay,
+    /// `409 Conflict` with code `duplicate_request`.
+    Reject,
+}
+
+#[derive(Debug, Clone)]
+struct StoredResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl StoredResponse {
+    fn replay(&self) -> Response {
+        let mut response = Response::new(Body::from(self.body.clone()));
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers.clone();
+        response
+            .headers_mut()
+            .insert(DEDUPLICATED_HEADER, HeaderValue::from_static("true"));
+        response
+    }
+}
+
+/// `None` while the original request is still running; the sender is dropped without a value
+/// when it fails, so waiters fall through to their own attempt.
+type Outcome = watch::Receiver<Option<Arc<StoredResponse>>>;
+
+#[derive(Debug)]
+struct Entry {
+    outcome: Outcome,
+    expires_at: Option<Instant>,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    entries: HashMap<[u8; 32], Entry>,
+    /// Expired entries are swept at most once a window rather than on every request.
+    next_sweep: Option<Instant>,
+}
+
+/// The slot of a request that is running. Dropping it without [`Self::store`] (the request
+/// failed, was not remembered, or its future was cancelled) frees the key for the next attempt.
+struct Claim {
+    entries: Arc<Mutex<Entries>>,
+    key: [u8; 32],
+    tx: watch::Sender<Option<Arc<StoredResponse>>>,
+}
+
+impl Claim {
+    fn store(self, stored: StoredResponse, expires_at: Instant) {
+        let mut entries = self.entries.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(entry) = entries.entries.get_mut(&self.key) {
+            entry.expires_at = Some(expires_at);
+        }
+        drop(entries);
+        let _ = self.tx.send(Some(Arc::new(stored)));
+    }
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        // `tx` is still alive here, so an unfinished entry under this key is ours.
+        let mut entries = self.entries.lock().unwrap_or_else(|err| err.into_inner());
+        if entries
+            .entries
+            .get(&self.key)
+            .is_some_and(|entry| entry.expires_at.is_none())
+        {
+            entries.entries.remove(&self.key);
+        }
+    }
+}
+
+/// Short-window guard against accidental double submissions (double clicks, client retries
+/// racing the first attempt). Requests are keyed on principal, method, path and query, and a
+/// SHA-256 of the body; only successful responses are remembered, so a failed attempt can be
+/// retried straight away. Successful responses larger than the body limit are passed through
+/// without being remembered.
+#[derive(Debug, Clone)]
+pub struct DedupLayer {
+    window: Duration,
+    action: DuplicateAction,
+    methods: Vec<Method>,
+    max_body_bytes: usize,
+    entries: Arc<Mutex<Entries>>,
+}
+
+impl Default for DedupLayer {
+    fn default() -> Self {
+        Self::new(DEFAULT_DEDUP_WINDOW)
+    }
+}
+
+impl DedupLayer {
+    /// Deduplicates `POST` requests within `window` of the original's completion.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            action: DuplicateAction::default(),
+            methods: vec![Method::POST],
+            max_body_bytes: DEFAULT_MAX_DEDUP_BODY_BYTES,
+            entries: Arc::default(),
+        }
+    }
+
+    pub fn with_action(mut self, action: DuplicateAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Methods to deduplicate; others pass straight through.
+    pub fn with_methods(mut self, methods: impl IntoIterator<Item = Method>) -> Self {
+        self.methods = methods.into_iter().collect();
+        self
+    }
+
+    /// Largest request or response body buffered for hashing and replay; larger requests get
+    /// `413`, larger responses are returned as they are and not replayed.
+    pub fn with_max_body_bytes(mut self, max_body_bytes: usize) -> Self {
+        self.max_body_bytes = max_body_bytes;
+        self
+    }
+
+    /// Applies the layer to the routes already registered on `router`.
+    pub fn apply<S>(self, router: Router<S>) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        router.route_layer(from_fn_with_state(self, dedup_middleware))
+    }
+
+    /// Registers a new in-flight request, or returns the outcome of the identical one that is
+    /// running or finished inside the window.
+    fn claim(&self, key: [u8; 32], now: Instant) -> Result<Claim, Outcome> {
+        let mut entries = self.entries.lock().unwrap_or_else(|err| err.into_inner());
+        if entries
+            .next_sweep
+            .is_none_or(|next_sweep| now >= next_sweep)
+        {
+            entries
+                .entries
+                .retain(|_, entry| entry.expires_at.is_none_or(|expires_at| expires_at > now));
+            entries.next_sweep = Some(now + self.window);
+        }
+        if let Some(existing) = entries.entries.get(&key) {
+            let live = existing
+                .expires_at
+                .is_none_or(|expires_at| expires_at > now);
+            let finished_or_running =
+                existing.outcome.borrow().is_some() || existing.outcome.has_changed().is_ok();
+            if live && finished_or_running {
+                return Err(existing.outcome.clone());
+            }
+        }
+        let (tx, outcome) = watch::channel(None);
+        entries.entries.insert(
+            key,
+            Entry {
+                outcome,
+                expires_at: None,
+            },
+        );
+        Ok(Claim {
+            entries: self.entries.clone(),
+            key,
+            tx,
+        })
+    }
+}
+
+async fn dedup_middleware(State(layer): State<DedupLayer>, req: Request, next: Next) -> Response {
+    if !layer.methods.contains(req.method()) {
+        return next.run(req).await;
+    }
+
+    let (parts, body) = req.into_parts();
+    let body = match to_bytes(body, layer.max_body_bytes).await {
+        Ok(body) => body,
+        Err(_) => {
+            return (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                Json(error_body(
+                    "payload_too_large",
+                    "request body exceeds the deduplication limit",
+                )),
+            )
+                .into_response()
+        }
+    };
+    let key = request_key(&parts, &body);
+    let clock = clock_from_extensions(&parts.extensions);
+    let req = Request::from_parts(parts, Body::from(body));
+
+    let claim = match layer.claim(key, clock.now()) {
+        Ok(claim) => claim,
+        Err(_) if layer.action == DuplicateAction::Reject => return duplicate_response(),
+        Err(mut outcome) => {
+            let replayed = outcome
+                .wait_for(Option::is_some)
+                .await
+                .ok()
+                .and_then(|stored| stored.as_ref().map(|stored| stored.replay()));
+            return match replayed {
+                Some(response) => response,
+                // The original failed or was cancelled; this request becomes a fresh attempt.
+                None => next.run(req).await,
+            };
+        }
+    };
+
+    let response = next.run(req).await;
+    if !response.status().is_success() {
+        return response;
+    }
+    let (parts, body) = response.into_parts();
+    let body = match buffer_within(body, layer.max_body_bytes).await {
+        Ok(body) => body,
+        Err(body) => {
+            tracing::debug!("response too large to remember for deduplication");
+            return Response::from_parts(parts, body);
+        }
+    };
+    let stored = StoredResponse {
+        status: parts.status,
+        headers: parts.headers.clone(),
+        body: body.clone(),
+    };
+    claim.store(stored, clock.now() + layer.window);
+    Response::from_parts(parts, Body::from(body))
+}
+
+fn duplicate_response() -> Response {
+    (
+        StatusCode::CONFLICT,
+        Json(error_body(
+            "duplicate_request",
+            "an identical request was just submitted",
+        )),
+    )
+        .into_response()
+}
+
+fn error_body(code: &str, message: &str) -> ApiErrorResponse {
+    ApiErrorResponse {
+        code: code.to_string(),
+        message: message.to_string(),
+        detail: None,
+        details: None,
+    }
+}
+
+/// SHA-256 over the caller identity, method, path with query, and body.
+fn request_key(parts: &http::request::Parts, body: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    match (
+        parts.extensions.get::<AuthPrincipal>(),
+        parts.headers.get(header::AUTHORIZATION),
+        parts.extensions.get::<ClientIp>(),
+    ) {
+        (Some(principal), _, _) => {
+            hasher.update(b"principal:");
+            hasher.update(principal.subject.as_bytes());
+        }
+        (None, Some(authorization), _) => {
+            hasher.update(b"authorization:");
+            hasher.update(authorization.as_bytes());
+        }
+        (None, None, Some(ClientIp(ip))) => {
+            hasher.update(b"ip:");
+            hasher.update(ip.to_string().as_bytes());
+        }
+        (None, None, None) => hasher.update(b"anonymous"),
+    }
+    hasher.update([0]);
+    hasher.update(parts.method.as_str().as_bytes());
+    hasher.update([0]);
+    let path = parts
+        .uri
+        .path_and_query()
+        .map_or(parts.uri.path(), |path| path.as_str());
+    hasher.update(path.as_bytes());
+    hasher.update([0]);
+    hasher.update(body);
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::di::DependencyOverrides;
+    use axum::routing::post;
+    use openportio_core::clock::{SharedClock, TestClock};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tower::ServiceExt;
+
+    fn app(layer: DedupLayer, calls: Arc<AtomicUsize>, clock: &TestClock) -> Router {
+        let router = Router::new().route(
+            "/orders",
+            post(move |body: String| {
+                let calls = calls.clone();
+                async move {
+                    let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
+                    if body == "fail" {
+                        return (StatusCode::BAD_GATEWAY, "upstream down".to_string());
+                    }
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    (StatusCode::CREATED, format!("order-{n}"))
+                }
+            }),
+        );
+        layer.apply(router).layer(axum::Extension(
+            DependencyOverrides::default().with::<SharedClock>(clock.shared()),
+        ))
+    }
+
+    async fn submit(app: &Router, user: &str, body: &'static str) -> Response {
+        app.clone()
+            .oneshot(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri("/orders")
+                    .header(header::AUTHORIZATION, format!("Bearer {user}"))
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    async fn text(response: Response) -> String {
+        String::from_utf8(to_bytes(response.into_body(), 1024).await.unwrap().to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn concurrent_and_rapid_duplicates_replay_the_original_response() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let clock = TestClock::new();
+        let app = app(
+            DedupLayer::new(Duration::from_secs(2)),
+            calls.clone(),
+            &clock,
+        );
+
+        let (first, racing) = tokio::join!(submit(&app, "a", "{}"), submit(&app, "a", "{}"));
+        assert_eq!(first.status(), StatusCode::CREATED);
+        assert!(first.headers().get(DEDUPLICATED_HEADER).is_none());
+        assert_eq!(racing.status(), StatusCode::CREATED);
+        assert_eq!(racing.headers()[DEDUPLICATED_HEADER], "true");
+        assert_eq!(text(racing).await, "order-1");
+
+        let other_user = submit(&app, "b", "{}").await;
+        assert_eq!(text(other_user).await, "order-2");
+        let other_body = submit(&app, "a", "{\"qty\":2}").await;
+        assert_eq!(text(other_body).await, "order-3");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        clock.advance(Duration::from_secs(3));
+        assert_eq!(text(submit(&app, "a", "{}").await).await, "order-4");
+    }
+
+    #[tokio::test]
+    async fn reject_mode_answers_conflict_and_failures_are_not_remembered() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let clock = TestClock::new();
+        let app = app(
+            DedupLayer::default().with_action(DuplicateAction::Reject),
+            calls.clone(),
+            &clock,
+        );
+
+        assert_eq!(submit(&app, "a", "{}").await.status(), StatusCode::CREATED);
+        let duplicate = submit(&app, "a", "{}").await;
+        assert_eq!(duplicate.status(), StatusCode::CONFLICT);
+        assert!(text(duplicate).await.contains("duplicate_request"));
+
+        assert_eq!(
+            submit(&app, "a", "fail").await.status(),
+            StatusCode::BAD_GATEWAY
+        );
+        assert_eq!(
+            submit(&app, "a", "fail").await.status(),
+            StatusCode::BAD_GATEWAY
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn oversized_responses_pass_through_and_cancelled_requests_free_their_key() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let clock = TestClock::new();
+        let layer = DedupLayer::default().with_max_body_bytes(4);
+        let app = app(layer.clone(), calls.clone(), &clock);
+
+        let first = submit(&app, "a", "{}").await;
+        assert_eq!(first.status(), StatusCode::CREATED);
+        assert_eq!(text(first).await, "order-1");
+        let second = submit(&app, "a", "{}").await;
+        assert!(second.headers().get(DEDUPLICATED_HEADER).is_none());
+        assert_eq!(text(second).await, "order-2");
+
+        let cancelled =
+            tokio::time::timeout(Duration::from_millis(1), submit(&app, "a", "[2]")).await;
+        assert!(cancelled.is_err());
+        assert!(layer.entries.lock().unwrap().entries.is_empty());
+    }
+}
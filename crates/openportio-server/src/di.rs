@@ -1,16 +1,30 @@
 use std::{
     any::{Any, TypeId},
-    collections::HashMap,
-    sync::{Arc, Mutex},
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt,
+    future::Future,
+    marker::PhantomData,
+    ops::Deref,
+    sync::{Arc, Mutex, RwLock},
 };
 
 use axum::{
-    extract::{FromRef, FromRequestParts},
+    extract::{FromRef, FromRequestParts, Request},
     http::{request::Parts, StatusCode},
+    middleware::{from_fn, Next},
+    response::{IntoResponse, Response},
     Extension, Json, Router,
 };
 
-use crate::api::{ApiError, ApiErrorResponse};
+use futures_util::future::BoxFuture;
+use openportio_core::{clock, OpenportioResult, ServiceRegistry, SharedClock};
+use tokio::sync::OnceCell;
+
+use crate::{
+    api::{map_domain_error_to_grpc, map_domain_error_to_rest, ApiError, ApiErrorResponse},
+    context::RequestContext,
+    middleware::is_grpc_request,
+};
 
 #[derive(Debug, Clone)]
 pub struct Depends<T>(pub T);
@@ -18,18 +32,43 @@ pub struct Depends<T>(pub T);
 #[derive(Debug, Clone)]
 pub struct DependencyOverride<T>(pub T);
 
+type Resolver = Arc<dyn Fn() -> Box<dyn Any + Send + Sync> + Send + Sync>;
+type ResolverMap = HashMap<TypeId, Resolver>;
+
 #[derive(Clone, Default)]
 pub struct DependencyOverrides {
-    values: Arc<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>,
+    values: Arc<ResolverMap>,
+    // Shared by every clone, so a scope opened in a test reaches the router's copy.
+    scoped: Arc<RwLock<ResolverMap>>,
 }
 
 impl DependencyOverrides {
-    pub fn with<T>(mut self, value: T) -> Self
+    pub fn with<T>(self, value: T) -> Self
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.with_resolver::<T>(value_resolver(value))
+    }
+
+    pub fn with_named<Q, T>(self, value: T) -> Self
     where
+        Q: Qualifier,
         T: Clone + Send + Sync + 'static,
     {
+        self.with(Named::<T, Q>::new(value))
+    }
+
+    /// Resolves `T` through `mock`, which records every value handed out.
+    pub fn with_mock<T>(self, mock: MockProvider<T>) -> Self
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.with_resolver::<T>(mock.resolver())
+    }
+
+    fn with_resolver<T: 'static>(mut self, resolver: Resolver) -> Self {
         let map = Arc::make_mut(&mut self.values);
-        map.insert(TypeId::of::<T>(), Arc::new(value));
+        map.insert(TypeId::of::<T>(), resolver);
         self
     }
 
@@ -37,13 +76,370 @@ impl DependencyOverrides {
     where
         T: Clone + Send + Sync + 'static,
     {
-        self.values
-            .get(&TypeId::of::<T>())
-            .and_then(|value| value.as_ref().downcast_ref::<T>())
-            .cloned()
+        let key = TypeId::of::<T>();
+        let scoped = self
+            .scoped
+            .read()
+            .ok()
+            .and_then(|scoped| scoped.get(&key).cloned());
+        scoped
+            .or_else(|| self.values.get(&key).cloned())
+            .and_then(|resolve| resolve().downcast::<T>().ok())
+            .map(|value| *value)
+    }
+
+    /// Opens a scope whose overrides win over every other registration on this set and its
+    /// clones (including the copy layered onto a router) until the returned guard drops, e.g.
+    /// `let _scope = overrides.scoped().with(FakeRepo::default());` inside one test.
+    pub fn scoped(&self) -> ScopedOverrides {
+        ScopedOverrides {
+            scoped: self.scoped.clone(),
+            previous: Vec::new(),
+        }
+    }
+}
+
+fn value_resolver<T>(value: T) -> Resolver
+where
+    T: Clone + Send + Sync + 'static,
+{
+    Arc::new(move || Box::new(value.clone()))
+}
+
+/// Guard returned by [`DependencyOverrides::scoped`]; dropping it restores what each overridden
+/// type resolved to before, so nested scopes unwind in order.
+#[must_use = "the overrides are removed when the guard is dropped"]
+pub struct ScopedOverrides {
+    scoped: Arc<RwLock<ResolverMap>>,
+    previous: Vec<(TypeId, Option<Resolver>)>,
+}
+
+impl ScopedOverrides {
+    pub fn with<T>(self, value: T) -> Self
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.with_resolver::<T>(value_resolver(value))
+    }
+
+    pub fn with_mock<T>(self, mock: MockProvider<T>) -> Self
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.with_resolver::<T>(mock.resolver())
+    }
+
+    fn with_resolver<T: 'static>(mut self, resolver: Resolver) -> Self {
+        let key = TypeId::of::<T>();
+        let mut scoped = self
+            .scoped
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let previous = scoped.insert(key, resolver);
+        drop(scoped);
+        self.previous.push((key, previous));
+        self
+    }
+}
+
+impl Drop for ScopedOverrides {
+    fn drop(&mut self) {
+        let mut scoped = self
+            .scoped
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for (key, previous) in self.previous.drain(..).rev() {
+            match previous {
+                Some(resolver) => scoped.insert(key, resolver),
+                None => scoped.remove(&key),
+            };
+        }
+    }
+}
+
+/// Test double for a dependency: hands out values from a factory and records each one, so
+/// handler tests can assert how often (and with what) `Depends<T>` / `GrpcContext` resolved `T`.
+/// Clones share the record.
+pub struct MockProvider<T> {
+    factory: Arc<dyn Fn() -> T + Send + Sync>,
+    resolved: Arc<Mutex<Vec<T>>>,
+}
+
+impl<T> Clone for MockProvider<T> {
+    fn clone(&self) -> Self {
+        Self {
+            factory: self.factory.clone(),
+            resolved: self.resolved.clone(),
+        }
+    }
+}
+
+impl<T> MockProvider<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    /// Resolves to a clone of `value` every time.
+    pub fn new(value: T) -> Self {
+        Self::from_fn(move || value.clone())
+    }
+
+    pub fn from_fn(factory: impl Fn() -> T + Send + Sync + 'static) -> Self {
+        Self {
+            factory: Arc::new(factory),
+            resolved: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// How many times the dependency was resolved (at most once per request or call).
+    pub fn calls(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn resolved(&self) -> Vec<T> {
+        self.lock().clone()
+    }
+
+    pub fn last(&self) -> Option<T> {
+        self.lock().last().cloned()
+    }
+
+    pub fn reset(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<T>> {
+        self.resolved
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn resolver(self) -> Resolver {
+        Arc::new(move || {
+            let value = (self.factory)();
+            self.lock().push(value.clone());
+            Box::new(value)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DependencyKey {
+    type_id: TypeId,
+    type_name: &'static str,
+}
+
+impl DependencyKey {
+    pub fn of<T>() -> Self
+    where
+        T: 'static,
+    {
+        Self {
+            type_id: TypeId::of::<T>(),
+            type_name: std::any::type_name::<T>(),
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingDependency {
+    pub consumer: String,
+    pub dependency: &'static str,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DependencyGraphError {
+    pub missing: Vec<MissingDependency>,
+    pub cycles: Vec<Vec<&'static str>>,
+}
+
+impl fmt::Display for DependencyGraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "dependency graph validation failed")?;
+        for missing in &self.missing {
+            writeln!(
+                f,
+                "  - missing dependency `{}` required by {}",
+                missing.dependency, missing.consumer
+            )?;
+        }
+        for cycle in &self.cycles {
+            writeln!(f, "  - dependency cycle: {}", cycle.join(" -> "))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for DependencyGraphError {}
+
+/// Registered providers and declared consumers, checked for missing entries and cycles before
+/// the app is built. `#[route]` handlers declare their `Named<T, Q>` and `Lazy<T>` extractors
+/// through [`RouteDependencies`] (see [`Self::require_routes`]); plain axum handlers and
+/// `GrpcContext::depends` callers are declared with `require_dependency`, or a missing
+/// registration only fails per request. `Depends<T>` always resolves (it falls back to
+/// `FromRef<S>`), so it never needs a provider.
+#[derive(Debug, Clone, Default)]
+pub struct DependencyGraph {
+    providers: BTreeMap<DependencyKey, Vec<DependencyKey>>,
+    // Each requirement is met by any one of its keys (`Lazy<T>` takes `LazyProvider<T>` or `T`).
+    requirements: Vec<(String, Vec<DependencyKey>)>,
+}
+
+impl DependencyGraph {
+    pub fn provide(&mut self, key: DependencyKey, requires: Vec<DependencyKey>) {
+        self.providers.insert(key, requires);
+    }
+
+    pub fn require(&mut self, consumer: impl Into<String>, key: DependencyKey) {
+        self.require_any(consumer, vec![key]);
+    }
+
+    /// Declares that `consumer` needs one of `keys`; a miss is reported under the first.
+    pub fn require_any(&mut self, consumer: impl Into<String>, keys: Vec<DependencyKey>) {
+        if !keys.is_empty() {
+            self.requirements.push((consumer.into(), keys));
+        }
+    }
+
+    /// Declares the dependencies of every `#[route]` handler linked into the binary.
+    pub fn require_routes(&mut self) {
+        for route in inventory::iter::<RouteDependencies> {
+            for extractor in route.extractors {
+                self.require_any(route.route, extractor());
+            }
+        }
+    }
+
+    pub fn is_provided(&self, key: &DependencyKey) -> bool {
+        self.providers.contains_key(key)
+    }
+
+    pub fn validate(&self) -> Result<Vec<DependencyKey>, DependencyGraphError> {
+        let mut report = DependencyGraphError::default();
+
+        for (provider, requires) in &self.providers {
+            for dependency in requires {
+                if !self.providers.contains_key(dependency) {
+                    report.missing.push(MissingDependency {
+                        consumer: format!("provider `{}`", provider.type_name()),
+                        dependency: dependency.type_name(),
+                    });
+                }
+            }
+        }
+        for (consumer, keys) in &self.requirements {
+            if !keys.iter().any(|key| self.providers.contains_key(key)) {
+                report.missing.push(MissingDependency {
+                    consumer: consumer.clone(),
+                    dependency: keys[0].type_name(),
+                });
+            }
+        }
+
+        let mut order = Vec::with_capacity(self.providers.len());
+        let mut visited = BTreeSet::new();
+        let mut stack = Vec::new();
+        for key in self.providers.keys() {
+            self.visit(
+                *key,
+                &mut visited,
+                &mut stack,
+                &mut order,
+                &mut report.cycles,
+            );
+        }
+
+        if report.missing.is_empty() && report.cycles.is_empty() {
+            Ok(order)
+        } else {
+            Err(report)
+        }
+    }
+
+    fn visit(
+        &self,
+        key: DependencyKey,
+        visited: &mut BTreeSet<DependencyKey>,
+        stack: &mut Vec<DependencyKey>,
+        order: &mut Vec<DependencyKey>,
+        cycles: &mut Vec<Vec<&'static str>>,
+    ) {
+        if let Some(position) = stack.iter().position(|entry| *entry == key) {
+            let mut cycle = stack[position..]
+                .iter()
+                .map(DependencyKey::type_name)
+                .collect::<Vec<_>>();
+            cycle.push(key.type_name());
+            cycles.push(cycle);
+            return;
+        }
+        if !visited.insert(key) {
+            return;
+        }
+
+        stack.push(key);
+        for dependency in self.providers.get(&key).into_iter().flatten() {
+            if self.providers.contains_key(dependency) {
+                self.visit(*dependency, visited, stack, order, cycles);
+            }
+        }
+        stack.pop();
+        order.push(key);
+    }
+}
+
+/// The injected extractors of a `#[route]` handler, submitted to `inventory` by the macro and
+/// read by [`DependencyGraph::require_routes`].
+#[doc(hidden)]
+pub struct RouteDependencies {
+    /// `GET /notes/:id`, as written in the attribute.
+    pub route: &'static str,
+    /// One entry per `Depends`/`Named`/`Lazy` argument: the keys that satisfy it.
+    pub extractors: &'static [fn() -> Vec<DependencyKey>],
+}
+
+inventory::collect!(RouteDependencies);
+
+/// Resolves to the keys an extractor needs registered, and to none through
+/// [`ExtractorDependencyFallback`] for anything else, so `#[route]` can ask about every
+/// argument whose type looks like a DI extractor.
+#[doc(hidden)]
+pub struct ExtractorDependency<T>(PhantomData<T>);
+
+impl<T: 'static> ExtractorDependency<Depends<T>> {
+    /// Falls back to `FromRef<S>`, so nothing has to be registered.
+    pub fn keys() -> Vec<DependencyKey> {
+        Vec::new()
+    }
+}
+
+impl<T: 'static, Q: 'static> ExtractorDependency<Named<T, Q>> {
+    pub fn keys() -> Vec<DependencyKey> {
+        vec![DependencyKey::of::<Named<T, Q>>()]
+    }
+}
+
+impl<T: 'static> ExtractorDependency<Lazy<T>> {
+    pub fn keys() -> Vec<DependencyKey> {
+        vec![
+            DependencyKey::of::<LazyProvider<T>>(),
+            DependencyKey::of::<T>(),
+        ]
     }
 }
 
+#[doc(hidden)]
+pub trait ExtractorDependencyFallback {
+    fn keys() -> Vec<DependencyKey> {
+        Vec::new()
+    }
+}
+
+impl<T> ExtractorDependencyFallback for ExtractorDependency<T> {}
+
 #[derive(Clone, Default)]
 struct DependencyCache {
     values: Arc<Mutex<HashMap<TypeId, Box<dyn Any + Send + Sync>>>>,
@@ -91,45 +487,277 @@ where
     type Rejection = ApiError;
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        if let Some(cache) = parts.extensions.get::<DependencyCache>() {
-            if let Some(value) = cache.get::<T>().map_err(|err| {
-                tracing::error!(?err, "failed to read request dependency cache");
-                internal_di_error("request dependency cache is unavailable")
-            })? {
-                return Ok(Self(value));
-            }
+        resolve_cached(parts, || Some(T::from_ref(state)))?
+            .map(Self)
+            .ok_or_else(|| internal_di_error("dependency is not registered"))
+    }
+}
+
+/// A package of related registrations (dependencies, routes, background tasks, hooks) applied
+/// to the server by `OpenportioServer::install`, so a library or app layer ships its wiring in
+/// one place:
+///
+/// ```ignore
+/// impl Module for NotesModule {
+///     fn register(&self, server: OpenportioServer) -> OpenportioServer {
+///         server
+///             .with_dependency(NotesRepo::new(self.pool.clone()))
+///             .nest("/notes", notes_router())
+///             .with_background_task("notes-compaction", compact_notes)
+///     }
+/// }
+/// ```
+pub trait Module {
+    /// Identifies the module in `/internal/routes`; a second module with the same name is not
+    /// installed. Defaults to the type name.
+    fn name(&self) -> String {
+        std::any::type_name::<Self>()
+            .rsplit("::")
+            .next()
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    fn register(&self, server: crate::OpenportioServer) -> crate::OpenportioServer;
+}
+
+/// Marks one of several instances of the same type, e.g. the `Replica` in
+/// `Named<PgPool, Replica>`. Declare markers with [`crate::qualifier!`].
+pub trait Qualifier: Send + Sync + 'static {
+    const NAME: &'static str;
+}
+
+/// A `T` registered under the qualifier `Q`. Each `Named<T, Q>` is its own type, so a primary
+/// and a replica pool coexist in overrides and the `ServiceRegistry`; it is also an extractor
+/// resolving like `Depends<T>` without the state fallback.
+pub struct Named<T, Q> {
+    value: T,
+    qualifier: PhantomData<fn() -> Q>,
+}
+
+impl<T, Q: Qualifier> Named<T, Q> {
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            qualifier: PhantomData,
         }
+    }
 
-        let value = if let Some(overrides) = parts.extensions.get::<DependencyOverrides>() {
-            if let Some(value) = overrides.get::<T>() {
-                value
-            } else if let Some(override_value) = parts.extensions.get::<DependencyOverride<T>>() {
-                override_value.0.clone()
-            } else {
-                T::from_ref(state)
+    pub fn name(&self) -> &'static str {
+        Q::NAME
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T: Clone, Q> Clone for Named<T, Q> {
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+            qualifier: PhantomData,
+        }
+    }
+}
+
+impl<T: fmt::Debug, Q: Qualifier> fmt::Debug for Named<T, Q> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Named")
+            .field("name", &Q::NAME)
+            .field("value", &self.value)
+            .finish()
+    }
+}
+
+impl<T, Q> Deref for Named<T, Q> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+#[axum::async_trait]
+impl<T, Q, S> FromRequestParts<S> for Named<T, Q>
+where
+    T: Clone + Send + Sync + 'static,
+    Q: Qualifier,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        resolve_cached(parts, || None)?.ok_or_else(|| {
+            internal_di_error(format!(
+                "dependency `{}` named `{}` is not registered",
+                std::any::type_name::<T>(),
+                Q::NAME
+            ))
+        })
+    }
+}
+
+/// Declares a unit [`Qualifier`] type: `qualifier!(pub Replica = "replica");`.
+#[macro_export]
+macro_rules! qualifier {
+    ($vis:vis $marker:ident = $name:literal) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+        $vis struct $marker;
+
+        impl $crate::di::Qualifier for $marker {
+            const NAME: &'static str = $name;
+        }
+    };
+}
+
+/// Async factory for a per-request dependency (a transaction, a tenant-scoped client), run by
+/// [`Lazy<T>`] the first time a handler asks for it. Register it like any other dependency,
+/// e.g. `OpenportioServer::with_lazy_dependency(...)`.
+pub struct LazyProvider<T> {
+    factory: Arc<dyn Fn() -> BoxFuture<'static, OpenportioResult<T>> + Send + Sync>,
+}
+
+impl<T> LazyProvider<T>
+where
+    T: Send + 'static,
+{
+    pub fn new<F, Fut>(factory: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = OpenportioResult<T>> + Send + 'static,
+    {
+        Self {
+            factory: Arc::new(move || Box::pin(factory())),
+        }
+    }
+}
+
+impl<T> Clone for LazyProvider<T> {
+    fn clone(&self) -> Self {
+        Self {
+            factory: self.factory.clone(),
+        }
+    }
+}
+
+/// Extractor that defers resolving `T` until [`Lazy::get`] is awaited, so code paths that never
+/// touch the dependency never build it. A plain `T` registered as a dependency wins (handy for
+/// test overrides); otherwise the registered [`LazyProvider<T>`] runs. The first result is
+/// shared by every `Lazy<T>` in the same request.
+pub struct Lazy<T> {
+    source: LazySource<T>,
+    cell: Arc<OnceCell<T>>,
+}
+
+enum LazySource<T> {
+    Ready(T),
+    Provider(LazyProvider<T>),
+    Missing,
+}
+
+/// The per-request slot shared by every `Lazy<T>` extracted for the same request.
+struct LazyCell<T>(Arc<OnceCell<T>>);
+
+impl<T> Clone for LazyCell<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Lazy<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    /// Builds (or returns the already built) dependency. Provider errors map to REST errors
+    /// like any other domain error; a missing registration is a `500`.
+    pub async fn get(&self) -> Result<T, ApiError> {
+        self.cell
+            .get_or_try_init(|| async {
+                match &self.source {
+                    LazySource::Ready(value) => Ok(value.clone()),
+                    LazySource::Provider(provider) => {
+                        (provider.factory)().await.map_err(map_domain_error_to_rest)
+                    }
+                    LazySource::Missing => Err(internal_di_error(format!(
+                        "dependency `{}` is not registered",
+                        std::any::type_name::<T>()
+                    ))),
+                }
+            })
+            .await
+            .cloned()
+    }
+
+    /// Whether `get` has already produced a value in this request.
+    pub fn is_resolved(&self) -> bool {
+        self.cell.initialized()
+    }
+}
+
+#[axum::async_trait]
+impl<T, S> FromRequestParts<S> for Lazy<T>
+where
+    T: Clone + Send + Sync + 'static,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let source = match resolve_cached::<T>(parts, || None)? {
+            Some(value) => LazySource::Ready(value),
+            None => resolve_from_extensions::<LazyProvider<T>>(&parts.extensions)
+                .map_or(LazySource::Missing, LazySource::Provider),
+        };
+        let cell = match parts.extensions.get::<LazyCell<T>>() {
+            Some(LazyCell(cell)) => cell.clone(),
+            None => {
+                let cell = Arc::new(OnceCell::new());
+                parts.extensions.insert(LazyCell(cell.clone()));
+                cell
             }
-        } else if let Some(override_value) = parts.extensions.get::<DependencyOverride<T>>() {
-            override_value.0.clone()
-        } else {
-            T::from_ref(state)
         };
+        Ok(Self { source, cell })
+    }
+}
 
-        if let Some(cache) = parts.extensions.get_mut::<DependencyCache>() {
-            cache.insert(value.clone()).map_err(|err| {
-                tracing::error!(?err, "failed to write request dependency cache");
-                internal_di_error("request dependency cache is unavailable")
-            })?;
-        } else {
-            let mut cache = DependencyCache::default();
-            cache.insert(value.clone()).map_err(|err| {
-                tracing::error!(?err, "failed to initialize request dependency cache");
-                internal_di_error("request dependency cache is unavailable")
-            })?;
-            parts.extensions.insert(cache);
+/// Request-cache lookup shared by the extractors; `fallback` runs only when neither the cache
+/// nor the request extensions provide `T`.
+fn resolve_cached<T>(
+    parts: &mut Parts,
+    fallback: impl FnOnce() -> Option<T>,
+) -> Result<Option<T>, ApiError>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    if let Some(cache) = parts.extensions.get::<DependencyCache>() {
+        if let Some(value) = cache.get::<T>().map_err(|err| {
+            tracing::error!(?err, "failed to read request dependency cache");
+            internal_di_error("request dependency cache is unavailable")
+        })? {
+            return Ok(Some(value));
         }
+    }
+
+    let Some(value) = resolve_from_extensions::<T>(&parts.extensions).or_else(fallback) else {
+        return Ok(None);
+    };
 
-        Ok(Self(value))
+    if let Some(cache) = parts.extensions.get_mut::<DependencyCache>() {
+        cache.insert(value.clone()).map_err(|err| {
+            tracing::error!(?err, "failed to write request dependency cache");
+            internal_di_error("request dependency cache is unavailable")
+        })?;
+    } else {
+        let mut cache = DependencyCache::default();
+        cache.insert(value.clone()).map_err(|err| {
+            tracing::error!(?err, "failed to initialize request dependency cache");
+            internal_di_error("request dependency cache is unavailable")
+        })?;
+        parts.extensions.insert(cache);
     }
+
+    Ok(Some(value))
 }
 
 pub fn with_dependency<S, T>(router: Router<S>, value: T) -> Router<S>
@@ -155,6 +783,274 @@ where
     router.layer(Extension(overrides))
 }
 
+/// Exposes `registry` (usually `AppState::services`) to `Depends<T>` and `GrpcContext`, after
+/// overrides and before the `FromRef` state fallback. `OpenportioServer` applies it with the
+/// state's registry.
+pub fn with_service_registry<S>(router: Router<S>, registry: ServiceRegistry) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router.layer(Extension(registry))
+}
+
+/// Looks `T` up in the request's `DependencyOverrides`, then in a `DependencyOverride<T>`
+/// extension, then in the `ServiceRegistry`; shared by `Depends<T>` and framework code that runs
+/// outside extraction. `RequestContext` is the one type also read as a plain request extension,
+/// so other extensions can't stand in for a registered dependency.
+pub fn resolve_from_extensions<T>(extensions: &http::Extensions) -> Option<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    extensions
+        .get::<DependencyOverrides>()
+        .and_then(DependencyOverrides::get::<T>)
+        .or_else(|| {
+            extensions
+                .get::<DependencyOverride<T>>()
+                .map(|value| value.0.clone())
+        })
+        .or_else(|| {
+            extensions
+                .get::<ServiceRegistry>()
+                .and_then(ServiceRegistry::get::<T>)
+        })
+        .or_else(|| {
+            (TypeId::of::<T>() == TypeId::of::<RequestContext>())
+                .then(|| extensions.get::<T>().cloned())
+                .flatten()
+        })
+}
+
+/// The `SharedClock` registered as a dependency (a `TestClock` in tests), or the system clock.
+pub(crate) fn clock_from_extensions(extensions: &http::Extensions) -> SharedClock {
+    resolve_from_extensions(extensions).unwrap_or_else(clock::system_clock)
+}
+
+/// Dependency access for tonic handlers, mirroring `Depends<T>`: overrides registered on the
+/// router (or builder) win, and resolved values are cached for the rest of the call.
+///
+/// Populated by [`with_grpc_context`], which `OpenportioServer` applies to every app.
+#[derive(Clone, Default)]
+pub struct GrpcContext {
+    overrides: DependencyOverrides,
+    extensions: Arc<http::Extensions>,
+    cache: DependencyCache,
+}
+
+impl GrpcContext {
+    /// Returns the context layered onto `request`, or one built from its extensions when the
+    /// layer is missing (for example in unit tests calling a service directly).
+    pub fn from_request<B>(request: &tonic::Request<B>) -> Self {
+        Self::from_extensions(request.extensions())
+    }
+
+    fn from_extensions(extensions: &http::Extensions) -> Self {
+        if let Some(context) = extensions.get::<GrpcContext>() {
+            return context.clone();
+        }
+        Self {
+            overrides: extensions
+                .get::<DependencyOverrides>()
+                .cloned()
+                .unwrap_or_default(),
+            extensions: Arc::new(extensions.clone()),
+            cache: DependencyCache::default(),
+        }
+    }
+
+    /// Resolves `T` from the call cache, then `DependencyOverrides`, then a
+    /// `DependencyOverride<T>` extension, then the `ServiceRegistry`. The error converts into an `INTERNAL` status.
+    pub fn depends<T>(&self) -> Result<T, GrpcDependencyError>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.resolve(|| None)
+    }
+
+    /// Like [`Self::depends`], falling back to `T::from_ref(state)` as `Depends<T>` does.
+    pub fn depends_with_state<T, S>(&self, state: &S) -> Result<T, GrpcDependencyError>
+    where
+        T: FromRef<S> + Clone + Send + Sync + 'static,
+    {
+        self.resolve(|| Some(T::from_ref(state)))
+    }
+
+    fn resolve<T>(&self, fallback: impl FnOnce() -> Option<T>) -> Result<T, GrpcDependencyError>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        let cache_error = |err: DependencyCacheError| {
+            tracing::error!(?err, "failed to access grpc dependency cache");
+            GrpcDependencyError::CacheUnavailable
+        };
+        if let Some(value) = self.cache.get::<T>().map_err(cache_error)? {
+            return Ok(value);
+        }
+        let value = self
+            .overrides
+            .get::<T>()
+            .or_else(|| resolve_from_extensions(&self.extensions))
+            .or_else(fallback)
+            .ok_or(GrpcDependencyError::Missing(std::any::type_name::<T>()))?;
+        self.cache
+            .clone()
+            .insert(value.clone())
+            .map_err(cache_error)?;
+        Ok(value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GrpcDependencyError {
+    Missing(&'static str),
+    CacheUnavailable,
+}
+
+impl fmt::Display for GrpcDependencyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing(dependency) => write!(f, "dependency `{dependency}` is not registered"),
+            Self::CacheUnavailable => f.write_str("request dependency cache is unavailable"),
+        }
+    }
+}
+
+impl std::error::Error for GrpcDependencyError {}
+
+impl From<GrpcDependencyError> for tonic::Status {
+    fn from(err: GrpcDependencyError) -> Self {
+        tonic::Status::internal(err.to_string())
+    }
+}
+
+/// Inserts a [`GrpcContext`] into every gRPC request so tonic handlers can resolve the same
+/// dependencies as REST handlers. Must be layered inside the dependency override layers.
+pub fn with_grpc_context<S>(router: Router<S>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router.layer(from_fn(grpc_context_middleware))
+}
+
+async fn grpc_context_middleware(mut req: Request, next: Next) -> Response {
+    let is_grpc = req
+        .headers()
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/grpc"));
+    if is_grpc && req.extensions().get::<GrpcContext>().is_none() {
+        let context = GrpcContext::from_extensions(req.extensions());
+        req.extensions_mut().insert(context);
+    }
+    next.run(req).await
+}
+
+/// What teardown hooks learn about the finished request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestOutcome {
+    pub status: StatusCode,
+    /// The `grpc-status` header of trailers-only gRPC responses (errors); successful calls
+    /// report theirs in trailers and leave this `None`.
+    pub grpc_status: Option<tonic::Code>,
+}
+
+impl RequestOutcome {
+    fn from_response(response: &Response) -> Self {
+        Self {
+            status: response.status(),
+            grpc_status: response
+                .headers()
+                .get("grpc-status")
+                .map(|value| tonic::Code::from_bytes(value.as_bytes())),
+        }
+    }
+
+    /// A 2xx response that is not a gRPC error.
+    pub fn is_success(&self) -> bool {
+        self.status.is_success() && self.grpc_status.is_none_or(|code| code == tonic::Code::Ok)
+    }
+}
+
+type TeardownHook =
+    Box<dyn FnOnce(RequestOutcome) -> BoxFuture<'static, OpenportioResult<()>> + Send>;
+
+/// Per-request hooks that run after the handler returned and before the response is sent, most
+/// recently registered first — how request-scoped resources (a database transaction) are
+/// committed or released. Extractors find it in the request extensions, where
+/// [`with_request_teardown`] puts it; `OpenportioServer` applies that layer to every app.
+///
+/// A failing hook turns a successful response into its mapped domain error, and the remaining
+/// hooks see that failure as their outcome. Hooks do not run when the request future is dropped
+/// (client disconnect), so resources must also clean up on `Drop`.
+#[derive(Clone, Default)]
+pub struct RequestTeardown {
+    hooks: Arc<Mutex<Vec<TeardownHook>>>,
+}
+
+impl RequestTeardown {
+    pub fn from_extensions(extensions: &http::Extensions) -> Option<Self> {
+        extensions.get::<Self>().cloned()
+    }
+
+    pub fn on_teardown<F, Fut>(&self, hook: F)
+    where
+        F: FnOnce(RequestOutcome) -> Fut + Send + 'static,
+        Fut: Future<Output = OpenportioResult<()>> + Send + 'static,
+    {
+        self.lock()
+            .push(Box::new(move |outcome| Box::pin(hook(outcome))));
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<TeardownHook>> {
+        self.hooks.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl fmt::Debug for RequestTeardown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestTeardown")
+            .field("hooks", &self.lock().len())
+            .finish()
+    }
+}
+
+/// Gives every request a [`RequestTeardown`] and runs its hooks once the inner service
+/// responded. Layer it outside panic recovery and handler timeouts so hooks see their
+/// responses too.
+pub fn with_request_teardown<S>(router: Router<S>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router.layer(from_fn(request_teardown_middleware))
+}
+
+async fn request_teardown_middleware(mut req: Request, next: Next) -> Response {
+    let teardown = RequestTeardown::default();
+    req.extensions_mut().insert(teardown.clone());
+    let is_grpc = is_grpc_request(req.headers());
+
+    let mut response = next.run(req).await;
+    let hooks = std::mem::take(&mut *teardown.lock());
+    let mut outcome = RequestOutcome::from_response(&response);
+    for hook in hooks.into_iter().rev() {
+        let Err(err) = hook(outcome).await else {
+            continue;
+        };
+        tracing::error!(error = %err, "request teardown failed");
+        if outcome.is_success() {
+            response = if is_grpc {
+                map_domain_error_to_grpc(err)
+                    .into_http()
+                    .map(axum::body::Body::new)
+            } else {
+                map_domain_error_to_rest(err).into_response()
+            };
+            outcome = RequestOutcome::from_response(&response);
+        }
+    }
+    response
+}
+
 pub fn internal_di_error(message: impl Into<String>) -> ApiError {
     (
         StatusCode::INTERNAL_SERVER_ERROR,
@@ -171,7 +1067,11 @@ pub fn internal_di_error(message: impl Into<String>) -> ApiError {
 mod tests {
     use super::*;
     use axum::{
-        body::to_bytes, extract::State, http::Request, response::IntoResponse, routing::get,
+        body::to_bytes,
+        extract::{Query, State},
+        http::Request,
+        response::IntoResponse,
+        routing::get,
     };
     use serde::Serialize;
     use std::sync::{
@@ -384,4 +1284,329 @@ mod tests {
         assert_eq!(body.version, "v2");
         assert_eq!(counter.load(Ordering::SeqCst), 0);
     }
+
+    crate::qualifier!(Primary = "primary");
+    crate::qualifier!(Replica = "replica");
+    crate::qualifier!(Analytics = "analytics");
+
+    async fn named_dep_handler(
+        primary: Named<LabelDep, Primary>,
+        replica: Named<LabelDep, Replica>,
+    ) -> String {
+        format!("{}/{}", primary.label, replica.into_inner().label)
+    }
+
+    #[tokio::test]
+    async fn named_dependencies_of_one_type_coexist() {
+        let label = |label: &str| LabelDep {
+            label: label.to_string(),
+        };
+        let app = with_service_registry(
+            with_dependency_overrides(
+                Router::new().route("/named", get(named_dep_handler)).route(
+                    "/missing",
+                    get(|_: Named<LabelDep, Analytics>| async { "unreachable" }),
+                ),
+                DependencyOverrides::default().with_named::<Primary, _>(label("db-primary")),
+            ),
+            ServiceRegistry::new().with(Named::<_, Replica>::new(label("db-replica"))),
+        );
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/named")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&bytes[..], b"db-primary/db-replica");
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/missing")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert!(body["message"]
+            .as_str()
+            .unwrap()
+            .contains("named `analytics` is not registered"));
+
+        let mut request = tonic::Request::new(());
+        request
+            .extensions_mut()
+            .insert(DependencyOverrides::default().with_named::<Replica, _>(label("grpc")));
+        let ctx = GrpcContext::from_request(&request);
+        let replica = ctx.depends::<Named<LabelDep, Replica>>().unwrap();
+        assert_eq!(
+            (replica.name(), replica.label.as_str()),
+            ("replica", "grpc")
+        );
+        assert!(ctx.depends::<Named<LabelDep, Primary>>().is_err());
+    }
+
+    #[tokio::test]
+    async fn lazy_dependencies_build_once_and_only_when_asked_for() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let provider = {
+            let counter = counter.clone();
+            LazyProvider::new(move || {
+                let counter = counter.clone();
+                async move {
+                    let built = counter.fetch_add(1, Ordering::SeqCst) + 1;
+                    Ok(LabelDep {
+                        label: format!("built-{built}"),
+                    })
+                }
+            })
+        };
+        async fn handler(
+            first: Lazy<LabelDep>,
+            second: Lazy<LabelDep>,
+            Query(query): Query<HashMap<String, String>>,
+        ) -> Result<String, ApiError> {
+            if !query.contains_key("use") {
+                return Ok("skipped".to_string());
+            }
+            let a = first.get().await?.label;
+            assert!(second.is_resolved());
+            Ok(format!("{a}/{}", second.get().await?.label))
+        }
+        let call = |app: Router, uri: &'static str| async move {
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .uri(uri)
+                        .body(axum::body::Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            String::from_utf8(bytes.to_vec()).unwrap()
+        };
+        let app = with_dependency_override(Router::new().route("/", get(handler)), provider);
+
+        assert_eq!(call(app.clone(), "/").await, "skipped");
+        assert_eq!(counter.load(Ordering::SeqCst), 0);
+        assert_eq!(call(app.clone(), "/?use=1").await, "built-1/built-1");
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+
+        let overridden = with_dependency_override(
+            app,
+            LabelDep {
+                label: "override".to_string(),
+            },
+        );
+        assert_eq!(call(overridden, "/?use=1").await, "override/override");
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn plain_extensions_only_stand_in_for_the_request_context() {
+        let mut extensions = http::Extensions::new();
+        extensions.insert(LabelDep {
+            label: "extension".to_string(),
+        });
+        assert!(resolve_from_extensions::<LabelDep>(&extensions).is_none());
+
+        let context = RequestContext::detached();
+        extensions.insert(context.clone());
+        let resolved = resolve_from_extensions::<RequestContext>(&extensions).expect("context");
+        assert_eq!(resolved.trace_id(), context.trace_id());
+    }
+
+    #[test]
+    fn grpc_context_resolves_overrides_and_reports_missing_dependencies() {
+        let mut request = tonic::Request::new(());
+        request
+            .extensions_mut()
+            .insert(DependencyOverrides::default().with(VersionDep {
+                version: "override".to_string(),
+            }));
+        request
+            .extensions_mut()
+            .insert(DependencyOverride(LabelDep {
+                label: "single".to_string(),
+            }));
+        let ctx = GrpcContext::from_request(&request);
+
+        let version = ctx.depends::<VersionDep>().expect("override");
+        assert_eq!(version.version, "override");
+        let label = ctx.depends::<LabelDep>().expect("single override");
+        assert_eq!(label.label, "single");
+
+        let missing = ctx
+            .depends::<String>()
+            .expect_err("nothing provides String");
+        assert_eq!(tonic::Status::from(missing).code(), tonic::Code::Internal);
+    }
+
+    #[test]
+    fn dependency_graph_orders_providers_before_dependents() {
+        let mut graph = DependencyGraph::default();
+        graph.provide(
+            DependencyKey::of::<LabelDep>(),
+            vec![DependencyKey::of::<VersionDep>()],
+        );
+        graph.provide(DependencyKey::of::<VersionDep>(), Vec::new());
+        graph.require("GET /dep", DependencyKey::of::<LabelDep>());
+
+        let order = graph.validate().expect("graph should be valid");
+        assert_eq!(
+            order,
+            vec![
+                DependencyKey::of::<VersionDep>(),
+                DependencyKey::of::<LabelDep>()
+            ]
+        );
+    }
+
+    #[test]
+    fn dependency_graph_reports_missing_and_cyclic_dependencies() {
+        let mut graph = DependencyGraph::default();
+        graph.provide(
+            DependencyKey::of::<LabelDep>(),
+            vec![DependencyKey::of::<VersionDep>()],
+        );
+        graph.provide(
+            DependencyKey::of::<VersionDep>(),
+            vec![DependencyKey::of::<LabelDep>()],
+        );
+        graph.require("GET /state", DependencyKey::of::<TestState>());
+
+        let err = graph.validate().expect_err("graph should be invalid");
+        assert_eq!(err.missing.len(), 1);
+        assert_eq!(err.missing[0].consumer, "GET /state");
+        assert_eq!(err.cycles.len(), 1);
+        assert_eq!(err.cycles[0].len(), 3);
+
+        let report = err.to_string();
+        assert!(report.contains("missing dependency"));
+        assert!(report.contains("TestState"));
+        assert!(report.contains("dependency cycle"));
+    }
+
+    #[tokio::test]
+    async fn scoped_overrides_and_mocks_unwind_when_the_guard_drops() {
+        let state = TestState {
+            label: "state-value".to_string(),
+            build_counter: Arc::new(AtomicUsize::new(0)),
+        };
+        let overrides = DependencyOverrides::default().with(LabelDep {
+            label: "registered".to_string(),
+        });
+        let app = with_dependency_overrides(
+            Router::new()
+                .route("/dep", get(dep_handler))
+                .with_state(state),
+            overrides.clone(),
+        );
+        let label = |app: Router| async move {
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .uri("/dep")
+                        .body(axum::body::Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .expect("request should complete");
+            let bytes = to_bytes(response.into_body(), usize::MAX)
+                .await
+                .expect("body bytes");
+            serde_json::from_slice::<serde_json::Value>(&bytes).expect("json body")["a"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string()
+        };
+
+        let mock = MockProvider::new(LabelDep {
+            label: "mocked".to_string(),
+        });
+        {
+            let _scope = overrides.scoped().with_mock(mock.clone());
+            assert_eq!(label(app.clone()).await, "mocked");
+            {
+                let _inner = overrides.scoped().with(LabelDep {
+                    label: "inner".to_string(),
+                });
+                assert_eq!(label(app.clone()).await, "inner");
+            }
+            assert_eq!(label(app.clone()).await, "mocked");
+        }
+        assert_eq!(label(app).await, "registered");
+        // Both `Depends<LabelDep>` in a request share one resolution.
+        assert_eq!(mock.calls(), 2);
+        assert_eq!(mock.last().map(|dep| dep.label).as_deref(), Some("mocked"));
+    }
+
+    #[tokio::test]
+    async fn teardown_hooks_see_the_outcome_and_can_fail_the_response() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        async fn register(
+            parts: http::request::Parts,
+            seen: Arc<Mutex<Vec<String>>>,
+            fail: bool,
+        ) -> StatusCode {
+            let teardown = RequestTeardown::from_extensions(&parts.extensions).unwrap();
+            for name in ["first", "second"] {
+                let seen = seen.clone();
+                teardown.on_teardown(move |outcome| async move {
+                    seen.lock()
+                        .unwrap()
+                        .push(format!("{name}:{}", outcome.status.as_u16()));
+                    if fail && name == "second" {
+                        return Err(openportio_core::OpenportioError::Unavailable(
+                            "commit failed".to_string(),
+                        ));
+                    }
+                    Ok(())
+                });
+            }
+            StatusCode::CREATED
+        }
+        let app = with_request_teardown(
+            Router::new()
+                .route(
+                    "/ok",
+                    get({
+                        let seen = seen.clone();
+                        move |parts: http::request::Parts| register(parts, seen, false)
+                    }),
+                )
+                .route(
+                    "/fail",
+                    get({
+                        let seen = seen.clone();
+                        move |parts: http::request::Parts| register(parts, seen, true)
+                    }),
+                ),
+        );
+
+        let call = |uri: &'static str| {
+            app.clone()
+                .oneshot(Request::get(uri).body(axum::body::Body::empty()).unwrap())
+        };
+        assert_eq!(call("/ok").await.unwrap().status(), StatusCode::CREATED);
+        assert_eq!(
+            call("/fail").await.unwrap().status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(
+            *seen.lock().unwrap(),
+            ["second:201", "first:201", "second:201", "first:503"]
+        );
+    }
 }
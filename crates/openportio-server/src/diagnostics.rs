@@ -0,0 +1,631 @@
+use std::{collections::BTreeMap, env, fmt::Write as _, net::SocketAddr, sync::Arc};
+
+use axum::{routing::get, Json, Router};
+use openportio_core::Environment;
+use serde::Serialize;
+
+use crate::{
+    auth::AuthRuntimeConfig,
+    middleware::{CorsAllowOrigins, MiddlewareConfig, OverloadPolicy, RateLimit},
+    profile::Profile,
+    redirect::TrailingSlash,
+};
+
+pub const DIAGNOSTICS_PATH: &str = "/internal/diagnostics";
+
+const REDACTED: &str = "<redacted>";
+
+/// Startup report logged on boot and served at [`DIAGNOSTICS_PATH`] by the admin router.
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+pub struct DiagnosticsReport {
+    pub service_name: String,
+    pub environment: String,
+    pub config: Vec<ConfigValue>,
+    pub features: BTreeMap<String, String>,
+    pub listeners: Vec<ListenerDescription>,
+    pub route_count: usize,
+    pub grpc_services: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct ConfigValue {
+    pub key: String,
+    pub value: String,
+    /// `env:<VAR>`, `builder`, or `default`.
+    pub source: String,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct ListenerDescription {
+    /// `rest+grpc` for the multiplexed single port, otherwise `rest` or `grpc`.
+    pub protocol: String,
+    pub addr: String,
+    /// `tls` or `mtls (...)` when the listener terminates TLS.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls: Option<String>,
+}
+
+impl ListenerDescription {
+    pub fn new(protocol: impl Into<String>, addr: SocketAddr) -> Self {
+        Self {
+            protocol: protocol.into(),
+            addr: addr.to_string(),
+            tls: None,
+        }
+    }
+
+    pub fn with_tls(mut self, mode: impl Into<String>) -> Self {
+        self.tls = Some(mode.into());
+        self
+    }
+}
+
+impl DiagnosticsReport {
+    pub fn is_local_environment(&self) -> bool {
+        Environment::from(self.environment.as_str()).is_local()
+    }
+
+    /// Human-readable multi-line summary for the startup log.
+    pub fn banner(&self) -> String {
+        let mut banner = format!("{} ({})\n", self.service_name, self.environment);
+        for listener in &self.listeners {
+            let _ = writeln!(
+                banner,
+                "  listening  {} on {}",
+                listener.protocol, listener.addr
+            );
+        }
+        let _ = writeln!(
+            banner,
+            "  routes     {} REST, {} gRPC services",
+            self.route_count,
+            self.grpc_services.len()
+        );
+        for (name, value) in &self.features {
+            let _ = writeln!(banner, "  {name:<10} {value}");
+        }
+        for warning in &self.warnings {
+            let _ = writeln!(banner, "  warning    {warning}");
+        }
+        banner.truncate(banner.trim_end().len());
+        banner
+    }
+
+    pub fn log(&self) {
+        tracing::info!(
+            report = %serde_json::to_string(self).unwrap_or_default(),
+            "startup diagnostics\n{}",
+            self.banner()
+        );
+        for warning in &self.warnings {
+            tracing::warn!(warning = %warning, "startup diagnostics warning");
+        }
+    }
+}
+
+pub(crate) struct DiagnosticsInput<'a> {
+    pub service_name: &'a str,
+    pub environment: &'a str,
+    pub addr: SocketAddr,
+    pub listeners: Vec<ListenerDescription>,
+    pub middleware: &'a MiddlewareConfig,
+    pub auth: &'a AuthRuntimeConfig,
+    pub route_count: usize,
+    pub grpc_services: Vec<String>,
+    /// Token verification for the admin router, when it is served.
+    pub admin_auth: Option<&'a AuthRuntimeConfig>,
+    pub profile: Option<Profile>,
+    pub docs: bool,
+    pub quotas: Option<String>,
+    pub error_reporter: Option<&'static str>,
+    pub socket: String,
+    pub request_signing: Option<String>,
+    pub api_versioning: Option<String>,
+}
+
+pub(crate) fn collect(input: DiagnosticsInput<'_>) -> DiagnosticsReport {
+    let defaults = MiddlewareConfig::default();
+    let middleware = input.middleware;
+    let auth = input.auth;
+
+    let mut config = vec![
+        config_value(
+            "server.addr",
+            "SERVER_ADDR",
+            input.addr.to_string(),
+            input.addr == SocketAddr::from(([127, 0, 0, 1], 3000)),
+        ),
+        config_value(
+            "timeout_seconds",
+            "TIMEOUT_SECONDS",
+            middleware.timeout_seconds.to_string(),
+            middleware.timeout_seconds == defaults.timeout_seconds,
+        ),
+        config_value(
+            "max_in_flight_requests",
+            "MAX_IN_FLIGHT_REQUESTS",
+            middleware.max_in_flight_requests.to_string(),
+            middleware.max_in_flight_requests == defaults.max_in_flight_requests,
+        ),
+        config_value(
+            "request_body_limit_bytes",
+            "REQUEST_BODY_LIMIT_BYTES",
+            middleware.max_request_body_bytes.to_string(),
+            middleware.max_request_body_bytes == defaults.max_request_body_bytes,
+        ),
+        config_value(
+            "cors_allow_origins",
+            "CORS_ALLOW_ORIGINS",
+            cors_summary(&middleware.cors.allow_origins),
+            matches!(middleware.cors.allow_origins, CorsAllowOrigins::None),
+        ),
+        config_value(
+            "overload_policy",
+            "OVERLOAD_POLICY",
+            overload_summary(middleware.overload_policy).to_string(),
+            middleware.overload_policy == defaults.overload_policy,
+        ),
+        config_value(
+            "health_reserved_in_flight",
+            "HEALTH_RESERVED_IN_FLIGHT",
+            middleware.health_reserved_in_flight.to_string(),
+            middleware.health_reserved_in_flight == defaults.health_reserved_in_flight,
+        ),
+        config_value(
+            "honor_client_deadlines",
+            "HONOR_CLIENT_DEADLINES",
+            middleware.honor_client_deadlines.to_string(),
+            middleware.honor_client_deadlines == defaults.honor_client_deadlines,
+        ),
+        config_value(
+            "security_headers",
+            "SECURITY_HEADERS",
+            middleware.security_headers.to_string(),
+            middleware.security_headers == defaults.security_headers,
+        ),
+        config_value(
+            "grpc_messages",
+            "GRPC_MAX_DECODING_MESSAGE_BYTES",
+            middleware.grpc_messages.summary(),
+            middleware.grpc_messages == defaults.grpc_messages,
+        ),
+        config_value(
+            "auth.enabled",
+            "AUTH_ENABLED",
+            auth.enabled.to_string(),
+            !auth.enabled,
+        ),
+    ];
+    let optional = [
+        (
+            "auth.jwt_secret",
+            "AUTH_JWT_SECRET",
+            auth.jwt_secret.as_ref().map(|_| REDACTED.to_string()),
+        ),
+        ("auth.jwks_url", "AUTH_JWKS_URL", auth.jwks_url.clone()),
+        ("auth.issuer", "AUTH_ISSUER", auth.expected_issuer.clone()),
+        (
+            "auth.audience",
+            "AUTH_AUDIENCE",
+            auth.expected_audience.clone(),
+        ),
+        (
+            "trusted_proxies",
+            "TRUSTED_PROXIES",
+            (!middleware.trusted_proxies.is_empty()).then(|| {
+                middleware
+                    .trusted_proxies
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(",")
+            }),
+        ),
+        (
+            "trusted_proxy_header",
+            "TRUSTED_PROXY_HEADER",
+            (!middleware.trusted_proxies.is_empty())
+                .then(|| middleware.trusted_proxy_header.as_str().to_string()),
+        ),
+        (
+            "rate_limit",
+            "RATE_LIMIT_PER_MINUTE",
+            middleware
+                .rate_limit
+                .map(|limit| rate_limit_summary(&limit)),
+        ),
+        (
+            "handler_timeout_ms",
+            "HANDLER_TIMEOUT_MS",
+            middleware
+                .handler_timeouts
+                .default_timeout()
+                .map(|timeout| timeout.as_millis().to_string()),
+        ),
+        (
+            "response_headers",
+            "RESPONSE_HEADERS",
+            middleware.response_headers.defaults_summary(),
+        ),
+        (
+            "remove_response_headers",
+            "REMOVE_RESPONSE_HEADERS",
+            middleware.response_headers.removed_summary(),
+        ),
+        (
+            "auth.revocation_redis_url",
+            "AUTH_REVOCATION_REDIS_URL",
+            auth.revocation_list().map(|_| REDACTED.to_string()),
+        ),
+    ];
+    for (key, suffix, value) in optional {
+        if let Some(value) = value {
+            config.push(config_value(key, suffix, value, false));
+        }
+    }
+
+    let mut features = BTreeMap::new();
+    features.insert("auth".to_string(), auth.mode().to_string());
+    features.insert("cors".to_string(), middleware.cors.summary());
+    features.insert(
+        "overload".to_string(),
+        format!(
+            "{} (max {} in flight)",
+            overload_summary(middleware.overload_policy),
+            middleware.max_in_flight_requests
+        ),
+    );
+    features.insert(
+        "ip_filter".to_string(),
+        if middleware.ip_filter.is_empty() {
+            "disabled".to_string()
+        } else {
+            format!(
+                "{} allowed / {} denied ranges",
+                middleware.ip_filter.allowlist().len(),
+                middleware.ip_filter.denylist().len()
+            )
+        },
+    );
+    features.insert(
+        "rate_limit".to_string(),
+        middleware
+            .rate_limit
+            .map(|limit| format!("{} per client", rate_limit_summary(&limit)))
+            .unwrap_or_else(|| "disabled".to_string()),
+    );
+    features.insert(
+        "maintenance".to_string(),
+        match (
+            middleware.maintenance.is_active(),
+            middleware.maintenance.flag_file(),
+        ) {
+            (true, _) => "active".to_string(),
+            (false, Some(path)) => format!("inactive (flag file {})", path.display()),
+            (false, None) => "inactive".to_string(),
+        },
+    );
+    features.insert(
+        "path_normalization".to_string(),
+        match (
+            middleware.path_normalization.trailing_slash,
+            middleware.path_normalization.lowercase,
+        ) {
+            (TrailingSlash::Preserve, false) => "disabled".to_string(),
+            (trailing_slash, lowercase) => format!(
+                "trailing slash {}{}",
+                trailing_slash.name(),
+                if lowercase { ", lowercase" } else { "" }
+            ),
+        },
+    );
+    features.insert(
+        "adaptive_concurrency".to_string(),
+        middleware
+            .adaptive_concurrency
+            .as_ref()
+            .map(|config| {
+                format!(
+                    "{} ({}..={})",
+                    config.name(),
+                    config.min_limit,
+                    config.max_limit
+                )
+            })
+            .unwrap_or_else(|| "disabled".to_string()),
+    );
+    features.insert(
+        "client_concurrency".to_string(),
+        middleware
+            .client_concurrency
+            .as_ref()
+            .map(|config| format!("{} in flight per client", config.max_in_flight))
+            .unwrap_or_else(|| "disabled".to_string()),
+    );
+    features.insert(
+        "handler_timeouts".to_string(),
+        middleware.handler_timeouts.summary(),
+    );
+    features.insert(
+        "slow_requests".to_string(),
+        middleware
+            .slow_request_threshold
+            .map(|threshold| format!("over {}ms", threshold.as_millis()))
+            .unwrap_or_else(|| "disabled".to_string()),
+    );
+    features.insert(
+        "error_alerts".to_string(),
+        middleware
+            .error_alerts
+            .as_ref()
+            .map(|rules| {
+                let thresholds: Vec<_> = [
+                    rules.error_rate.map(|rate| format!("errors >= {rate}")),
+                    rules.server_error_rate.map(|rate| format!("5xx >= {rate}")),
+                ]
+                .into_iter()
+                .flatten()
+                .collect();
+                format!("{} per {}s", thresholds.join(", "), rules.window.as_secs())
+            })
+            .unwrap_or_else(|| "disabled".to_string()),
+    );
+    features.insert(
+        "error_reporting".to_string(),
+        input.error_reporter.unwrap_or("disabled").to_string(),
+    );
+    features.insert(
+        "quotas".to_string(),
+        input
+            .quotas
+            .clone()
+            .unwrap_or_else(|| "disabled".to_string()),
+    );
+    features.insert("socket".to_string(), input.socket.clone());
+    features.insert(
+        "request_signing".to_string(),
+        input
+            .request_signing
+            .clone()
+            .unwrap_or_else(|| "disabled".to_string()),
+    );
+    features.insert(
+        "api_versioning".to_string(),
+        input
+            .api_versioning
+            .clone()
+            .unwrap_or_else(|| "disabled".to_string()),
+    );
+    features.insert(
+        "profile".to_string(),
+        input
+            .profile
+            .map(|profile| profile.name())
+            .unwrap_or("none")
+            .to_string(),
+    );
+    features.insert(
+        "docs".to_string(),
+        if input.docs { "enabled" } else { "disabled" }.to_string(),
+    );
+    features.insert(
+        "revocation".to_string(),
+        if auth.revocation_list().is_some() {
+            "enabled"
+        } else {
+            "disabled"
+        }
+        .to_string(),
+    );
+    features.insert(
+        "admin".to_string(),
+        if input.admin_auth.is_some() {
+            "enabled"
+        } else {
+            "disabled"
+        }
+        .to_string(),
+    );
+
+    let mut report = DiagnosticsReport {
+        service_name: input.service_name.to_string(),
+        environment: input.environment.to_string(),
+        config,
+        features,
+        listeners: input.listeners,
+        route_count: input.route_count,
+        grpc_services: input.grpc_services,
+        warnings: Vec::new(),
+    };
+    report.warnings = warnings(&report, middleware, auth, input.admin_auth);
+    report
+}
+
+pub fn diagnostics_router(report: DiagnosticsReport) -> Router {
+    let report = Arc::new(report);
+    Router::new().route(
+        DIAGNOSTICS_PATH,
+        get(move || {
+            let report = Arc::clone(&report);
+            async move { Json(report.as_ref().clone()) }
+        }),
+    )
+}
+
+fn warnings(
+    report: &DiagnosticsReport,
+    middleware: &MiddlewareConfig,
+    auth: &AuthRuntimeConfig,
+    admin_auth: Option<&AuthRuntimeConfig>,
+) -> Vec<String> {
+    let local = report.is_local_environment();
+    let mut warnings = Vec::new();
+    match auth.mode() {
+        "disabled" if !local => warnings.push(format!(
+            "auth is disabled in the `{}` environment",
+            report.environment
+        )),
+        "unconfigured" => warnings.push(
+            "auth is enabled but no JWT secret, public key or JWKS url is configured".to_string(),
+        ),
+        _ => {}
+    }
+    if auth.enabled && auth.expected_audience.is_none() && !local {
+        warnings.push("auth audience is not checked; set OPENPORTIO_AUTH_AUDIENCE".to_string());
+    }
+    if matches!(middleware.cors.allow_origins, CorsAllowOrigins::Any) && !local {
+        warnings.push("CORS allows any origin".to_string());
+    }
+    warnings.extend(middleware.cors.warnings());
+    if admin_auth.is_some_and(|admin| admin.mode() == "unconfigured") {
+        warnings.push(
+            "admin routes have no JWT secret, public key or JWKS url to verify tokens with; every admin call is rejected"
+                .to_string(),
+        );
+    }
+    if middleware
+        .handler_timeouts
+        .longest()
+        .is_some_and(|timeout| timeout.as_secs() >= middleware.timeout_seconds)
+    {
+        warnings.push(format!(
+            "a handler timeout is not shorter than the {}s request timeout and never fires",
+            middleware.timeout_seconds
+        ));
+    }
+    if middleware.overload_policy == OverloadPolicy::Shed
+        && middleware.health_reserved_in_flight == 0
+    {
+        warnings.push("load shedding has no reserved budget for health checks".to_string());
+    }
+    warnings
+}
+
+fn config_value(key: &str, env_suffix: &str, value: String, is_default: bool) -> ConfigValue {
+    let source = crate::env::names(env_suffix)
+        .into_iter()
+        .find(|name| env::var_os(name).is_some())
+        .map(|name| format!("env:{name}"))
+        .unwrap_or_else(|| if is_default { "default" } else { "builder" }.to_string());
+    ConfigValue {
+        key: key.to_string(),
+        value,
+        source,
+    }
+}
+
+fn cors_summary(origins: &CorsAllowOrigins) -> String {
+    match origins {
+        CorsAllowOrigins::None => "disabled".to_string(),
+        CorsAllowOrigins::Any => "*".to_string(),
+        CorsAllowOrigins::List(origins) => origins
+            .iter()
+            .filter_map(|origin| origin.to_str().ok())
+            .collect::<Vec<_>>()
+            .join(","),
+    }
+}
+
+fn rate_limit_summary(limit: &RateLimit) -> String {
+    format!("{} / {}s", limit.max_requests, limit.window.as_secs())
+}
+
+fn overload_summary(policy: OverloadPolicy) -> &'static str {
+    match policy {
+        OverloadPolicy::Queue => "queue",
+        OverloadPolicy::Shed => "shed",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cors::CorsPolicy;
+
+    fn unconfigured_admin_auth() -> &'static AuthRuntimeConfig {
+        let mut auth = AuthRuntimeConfig::default();
+        auth.enabled = true;
+        Box::leak(Box::new(auth))
+    }
+
+    fn input<'a>(
+        environment: &'a str,
+        middleware: &'a MiddlewareConfig,
+        auth: &'a AuthRuntimeConfig,
+    ) -> DiagnosticsInput<'a> {
+        let addr = SocketAddr::from(([0, 0, 0, 0], 8080));
+        DiagnosticsInput {
+            service_name: "notes",
+            environment,
+            addr,
+            listeners: vec![ListenerDescription::new("rest+grpc", addr)],
+            middleware,
+            auth,
+            route_count: 3,
+            grpc_services: vec!["openportio.v1.Greeter".to_string()],
+            // Like the builder: the admin router reuses the app's auth when it has one.
+            admin_auth: Some(match auth.enabled {
+                true => auth,
+                false => unconfigured_admin_auth(),
+            }),
+            profile: None,
+            docs: true,
+            quotas: None,
+            error_reporter: None,
+            socket: "backlog 1024".to_string(),
+            request_signing: None,
+            api_versioning: None,
+        }
+    }
+
+    #[test]
+    fn production_without_auth_is_flagged_and_secrets_are_redacted() {
+        let middleware = MiddlewareConfig {
+            cors: CorsPolicy::new().with_any_origin().with_credentials(true),
+            ..MiddlewareConfig::default()
+        };
+        let report = collect(input(
+            "production",
+            &middleware,
+            &AuthRuntimeConfig::default(),
+        ));
+        assert_eq!(report.features["auth"], "disabled");
+        assert!(report
+            .warnings
+            .iter()
+            .any(|warning| warning.contains("auth is disabled in the `production`")));
+        assert!(report
+            .warnings
+            .iter()
+            .any(|w| w == "CORS allows any origin"));
+        assert!(report
+            .warnings
+            .iter()
+            .any(|w| w.contains("CORS allows credentials with any origin")));
+        assert!(report.warnings.iter().any(|w| w.contains("admin routes")));
+        let addr = report
+            .config
+            .iter()
+            .find(|value| value.key == "server.addr")
+            .expect("addr entry");
+        assert_eq!(addr.value, "0.0.0.0:8080");
+        assert!(addr.source == "builder" || addr.source.starts_with("env:"));
+        assert!(report
+            .banner()
+            .contains("listening  rest+grpc on 0.0.0.0:8080"));
+
+        let mut auth = AuthRuntimeConfig::default();
+        auth.enabled = true;
+        auth.jwt_secret = Some("dev-secret".to_string());
+        let report = collect(input("local", &MiddlewareConfig::default(), &auth));
+        assert_eq!(report.features["auth"], "shared_secret");
+        assert!(report.warnings.is_empty());
+        let secret = report
+            .config
+            .iter()
+            .find(|value| value.key == "auth.jwt_secret")
+            .expect("secret entry");
+        assert_eq!(secret.value, REDACTED);
+    }
+}
@@ -0,0 +1,232 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use futures_util::future::BoxFuture;
+
+use crate::ws::{WsShutdown, WsShutdownConfig};
+
+pub(crate) type DrainHook = Arc<dyn Fn() -> BoxFuture<'static, ()> + Send + Sync + 'static>;
+
+const DEFAULT_HOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Set once shutdown starts; `/readyz` answers `503` `draining` from then on so load balancers
+/// stop routing new traffic before the listener closes. Handlers can read it with
+/// `Extension<DrainState>`.
+#[derive(Debug, Clone, Default)]
+pub struct DrainState(Arc<AtomicBool>);
+
+impl DrainState {
+    pub fn is_draining(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn start(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Shutdown sequence for rollouts behind external load balancers: on `SIGTERM` / Ctrl-C,
+/// readiness flips to failing and the `on_drain_start` hooks run (deregister from service
+/// discovery here), then the server keeps serving for the pre-stop delay before it stops
+/// accepting connections and waits for in-flight ones. Open WebSocket sessions get a close frame
+/// first (see [`WsShutdown`]). `on_drain_complete` hooks run once every connection is closed.
+/// Hooks are awaited one at a time in registration order, each cut off after `hook_timeout`.
+#[derive(Clone)]
+pub(crate) struct Drainer {
+    pub(crate) pre_stop_delay: Duration,
+    pub(crate) hook_timeout: Duration,
+    pub(crate) state: DrainState,
+    pub(crate) ws: WsShutdown,
+    on_start: Vec<DrainHook>,
+    on_complete: Vec<DrainHook>,
+}
+
+impl Default for Drainer {
+    fn default() -> Self {
+        Self {
+            pre_stop_delay: Duration::ZERO,
+            hook_timeout: DEFAULT_HOOK_TIMEOUT,
+            state: DrainState::default(),
+            ws: WsShutdown::default(),
+            on_start: Vec::new(),
+            on_complete: Vec::new(),
+        }
+    }
+}
+
+impl Drainer {
+    pub(crate) fn from_env() -> Result<Self, String> {
+        Ok(Self {
+            pre_stop_delay: crate::env::setting("PRE_STOP_DELAY_SECONDS")?
+                .map(Duration::from_secs)
+                .unwrap_or_default(),
+            hook_timeout: crate::env::setting("DRAIN_HOOK_TIMEOUT_SECONDS")?
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_HOOK_TIMEOUT),
+            ws: WsShutdown::new(WsShutdownConfig::from_env()?),
+            ..Self::default()
+        })
+    }
+
+    pub(crate) fn on_start(&mut self, hook: DrainHook) {
+        self.on_start.push(hook);
+    }
+
+    pub(crate) fn on_complete(&mut self, hook: DrainHook) {
+        self.on_complete.push(hook);
+    }
+
+    /// Fails readiness, runs the start hooks, and waits out the pre-stop delay.
+    pub(crate) async fn begin(&self) {
+        self.state.start();
+        tracing::info!(
+            pre_stop_delay_ms = self.pre_stop_delay.as_millis() as u64,
+            "draining: readiness now failing"
+        );
+        self.run_hooks("start", &self.on_start).await;
+        tokio::time::sleep(self.pre_stop_delay).await;
+        self.ws.close_all().await;
+        tracing::info!("draining: closing listeners and waiting for in-flight connections");
+    }
+
+    pub(crate) async fn complete(&self) {
+        tracing::info!("draining complete");
+        self.run_hooks("complete", &self.on_complete).await;
+    }
+
+    async fn run_hooks(&self, stage: &'static str, hooks: &[DrainHook]) {
+        for (index, hook) in hooks.iter().enumerate() {
+            if tokio::time::timeout(self.hook_timeout, hook())
+                .await
+                .is_err()
+            {
+                tracing::warn!(
+                    stage,
+                    hook = index,
+                    timeout_ms = self.hook_timeout.as_millis() as u64,
+                    "drain hook timed out"
+                );
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Drainer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Drainer")
+            .field("pre_stop_delay", &self.pre_stop_delay)
+            .field("hook_timeout", &self.hook_timeout)
+            .field("state", &self.state)
+            .field("ws_open_sockets", &self.ws.open_sockets())
+            .field("on_start", &self.on_start.len())
+            .field("on_complete", &self.on_complete.len())
+            .finish()
+    }
+}
+
+/// Resolves on Ctrl-C or, on Unix, `SIGTERM` (what Kubernetes and ECS send).
+pub(crate) async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let terminate = async {
+            match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+                Ok(mut signal) => {
+                    signal.recv().await;
+                }
+                Err(err) => {
+                    tracing::warn!(error = %err, "could not listen for SIGTERM");
+                    std::future::pending::<()>().await;
+                }
+            }
+        };
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate => {}
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn begin_fails_readiness_runs_hooks_then_waits_the_pre_stop_delay() {
+        let started = Arc::new(AtomicUsize::new(0));
+        let completed = Arc::new(AtomicUsize::new(0));
+        let mut drainer = Drainer {
+            pre_stop_delay: Duration::from_millis(50),
+            ..Drainer::default()
+        };
+        let hook = started.clone();
+        drainer.on_start(Arc::new(move || {
+            let hook = hook.clone();
+            Box::pin(async move {
+                tokio::task::yield_now().await;
+                hook.fetch_add(1, Ordering::Relaxed);
+            })
+        }));
+        let hook = completed.clone();
+        drainer.on_complete(Arc::new(move || {
+            let hook = hook.clone();
+            Box::pin(async move {
+                hook.fetch_add(1, Ordering::Relaxed);
+            })
+        }));
+        let state = drainer.state.clone();
+
+        let begun = std::time::Instant::now();
+        let begin = tokio::spawn({
+            let drainer = drainer.clone();
+            async move { drainer.begin().await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(state.is_draining());
+        assert_eq!(started.load(Ordering::Relaxed), 1);
+        assert!(!begin.is_finished());
+
+        begin.await.unwrap();
+        assert!(begun.elapsed() >= Duration::from_millis(50));
+        assert_eq!(completed.load(Ordering::Relaxed), 0);
+        drainer.complete().await;
+        assert_eq!(completed.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn hooks_run_in_order_and_a_stuck_hook_is_cut_off() {
+        let order = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut drainer = Drainer {
+            hook_timeout: Duration::from_millis(50),
+            ..Drainer::default()
+        };
+        for name in ["stuck", "deregister"] {
+            let order = order.clone();
+            drainer.on_start(Arc::new(move || {
+                let order = order.clone();
+                Box::pin(async move {
+                    if name == "stuck" {
+                        std::future::pending::<()>().await;
+                    }
+                    order.lock().unwrap().push(name);
+                })
+            }));
+        }
+
+        let begun = std::time::Instant::now();
+        drainer.begin().await;
+        assert!(begun.elapsed() >= Duration::from_millis(50));
+        assert_eq!(*order.lock().unwrap(), ["deregister"]);
+    }
+}
@@ -0,0 +1,113 @@
+use std::{env::VarError, str::FromStr};
+
+pub(crate) const PREFIX: &str = "OPENPORTIO_";
+
+/// Prefixes kept for settings that predate the `OPENPORTIO_` rename.
+const LEGACY_PREFIXES: [&str; 2] = ["MELD_", "ALLOY_"];
+
+/// Settings that existed before the rename and are still read under their `MELD_` / `ALLOY_`
+/// names. Settings added since are only read as `OPENPORTIO_*`.
+const LEGACY_SETTINGS: [&str; 14] = [
+    "AUTH_AUDIENCE",
+    "AUTH_ENABLED",
+    "AUTH_ISSUER",
+    "AUTH_JWKS_ALGORITHMS",
+    "AUTH_JWKS_REFRESH_SECS",
+    "AUTH_JWKS_URL",
+    "AUTH_JWT_SECRET",
+    "CORS_ALLOW_ORIGINS",
+    "MAX_IN_FLIGHT_REQUESTS",
+    "REQUEST_BODY_LIMIT_BYTES",
+    "SERVER_ADDR",
+    "TIMEOUT_SECONDS",
+    "WS_IDLE_TIMEOUT_SECS",
+    "WS_MAX_TEXT_BYTES",
+];
+
+/// Every variable `suffix` is read from, in order: `OPENPORTIO_{suffix}`, then the legacy
+/// aliases for settings that have them.
+pub(crate) fn names(suffix: &str) -> Vec<String> {
+    let mut names = vec![format!("{PREFIX}{suffix}")];
+    if LEGACY_SETTINGS.contains(&suffix) {
+        names.extend(LEGACY_PREFIXES.map(|prefix| format!("{prefix}{suffix}")));
+    }
+    names
+}
+
+/// The name and raw value of the first of [`names`] that is set. A set but non-unicode value is
+/// an error, not a reason to try the next name.
+pub(crate) fn lookup(suffix: &str) -> Result<Option<(String, String)>, String> {
+    for name in names(suffix) {
+        match std::env::var(&name) {
+            Ok(value) => return Ok(Some((name, value))),
+            Err(VarError::NotPresent) => continue,
+            Err(VarError::NotUnicode(_)) => return Err(format!("`{name}` is not valid unicode")),
+        }
+    }
+    Ok(None)
+}
+
+/// The raw value of setting `suffix`, if set.
+pub(crate) fn var(suffix: &str) -> Result<Option<String>, String> {
+    Ok(lookup(suffix)?.map(|(_, value)| value))
+}
+
+/// Setting `suffix` parsed as `T`, e.g. `setting::<u64>("BIND_RETRY_ATTEMPTS")`. Unset is
+/// `Ok(None)`; a set but malformed value is an error naming the variable, so callers fall back
+/// to their default only when nothing was configured. The first set name decides: a malformed
+/// value never falls through to a legacy alias.
+pub(crate) fn setting<T: FromStr>(suffix: &str) -> Result<Option<T>, String> {
+    let Some((name, value)) = lookup(suffix)? else {
+        return Ok(None);
+    };
+    value
+        .trim()
+        .parse()
+        .map(Some)
+        .map_err(|_| format!("`{name}` has an invalid value `{value}`"))
+}
+
+/// Serializes the tests of every module that sets process environment variables.
+#[cfg(test)]
+pub(crate) static ENV_LOCK: std::sync::LazyLock<std::sync::Mutex<()>> =
+    std::sync::LazyLock::new(|| std::sync::Mutex::new(()));
+
+/// Unwraps settings for code paths that cannot return the error, logging it and keeping the
+/// defaults. `OpenportioServer::new` reads the same settings and `run()` refuses to start on the
+/// error, so only routers assembled by hand get here with a malformed value.
+pub(crate) fn or_default<T: Default>(loaded: Result<T, String>) -> T {
+    loaded.unwrap_or_else(|err| {
+        tracing::error!(error = %err, "invalid setting; using defaults");
+        T::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_settings_are_openportio_only_and_the_first_set_name_decides() {
+        std::env::set_var("MELD_ENV_HELPER_TEST", "7");
+        assert_eq!(setting::<u64>("ENV_HELPER_TEST"), Ok(None));
+        std::env::set_var("OPENPORTIO_ENV_HELPER_TEST", " 42 ");
+        assert_eq!(setting::<u64>("ENV_HELPER_TEST"), Ok(Some(42)));
+        std::env::remove_var("MELD_ENV_HELPER_TEST");
+
+        assert_eq!(
+            names("WS_MAX_TEXT_BYTES"),
+            [
+                "OPENPORTIO_WS_MAX_TEXT_BYTES",
+                "MELD_WS_MAX_TEXT_BYTES",
+                "ALLOY_WS_MAX_TEXT_BYTES"
+            ]
+        );
+        std::env::set_var("OPENPORTIO_ENV_HELPER_TEST", "soon");
+        assert_eq!(
+            setting::<u64>("ENV_HELPER_TEST"),
+            Err("`OPENPORTIO_ENV_HELPER_TEST` has an invalid value `soon`".to_string())
+        );
+        assert_eq!(var("ENV_HELPER_TEST"), Ok(Some("soon".to_string())));
+        std::env::remove_var("OPENPORTIO_ENV_HELPER_TEST");
+    }
+}
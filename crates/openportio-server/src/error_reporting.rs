@@ -0,0 +1,473 @@
+use std::{sync::Arc, time::UNIX_EPOCH};
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{header, StatusCode},
+    middleware::{from_fn, Next},
+    response::Response,
+    Router,
+};
+use http_body::Body as _;
+use serde::Serialize;
+
+use crate::{
+    api::ApiErrorResponse,
+    auth::CallPrincipal,
+    di::{clock_from_extensions, resolve_from_extensions},
+    middleware::is_grpc_request,
+    slow_requests::{capture_matched_route, MatchedRoute},
+};
+
+const REQUEST_ID_HEADER: &str = "x-request-id";
+const UNMATCHED_ROUTE: &str = "unmatched";
+/// REST error bodies up to this size are read for their `code` and `message`.
+const MAX_ERROR_BODY_BYTES: u64 = 64 * 1024;
+const MAX_MESSAGE_CHARS: usize = 512;
+/// gRPC codes that mean the server failed rather than the caller: UNKNOWN, DEADLINE_EXCEEDED,
+/// INTERNAL, UNAVAILABLE, DATA_LOSS.
+const GRPC_SERVER_ERROR_CODES: &[tonic::Code] = &[
+    tonic::Code::Unknown,
+    tonic::Code::DeadlineExceeded,
+    tonic::Code::Internal,
+    tonic::Code::Unavailable,
+    tonic::Code::DataLoss,
+];
+
+/// Destination for server errors and panics (Sentry, an incident webhook, ...). Register one
+/// with `OpenportioServer::with_error_reporter`. `report` runs on the request path, so
+/// implementations should hand slow deliveries off to a background task.
+pub trait ErrorReporter: Send + Sync + 'static {
+    fn report(&self, report: &ErrorReport);
+
+    /// Shown in startup diagnostics.
+    fn name(&self) -> &'static str {
+        "custom"
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorReportKind {
+    /// A REST 5xx or a gRPC UNKNOWN/DEADLINE_EXCEEDED/INTERNAL/UNAVAILABLE/DATA_LOSS status.
+    ServerError,
+    /// A handler panicked; `message` is the panic message.
+    Panic,
+}
+
+impl ErrorReportKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::ServerError => "server_error",
+            Self::Panic => "panic",
+        }
+    }
+}
+
+/// One failed call. Only the error `code` and `message` are kept (the message truncated to 512
+/// characters); `detail`/`details`, headers and bodies never leave the process.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ErrorReport {
+    pub kind: ErrorReportKind,
+    pub protocol: &'static str,
+    pub method: String,
+    /// REST route template (`/users/:id`, `unmatched` for unrouted paths) or gRPC method path.
+    pub route: String,
+    pub status: u16,
+    pub grpc_status: Option<i32>,
+    /// `ApiErrorResponse::code`, the gRPC code name, or `panic`.
+    pub code: String,
+    pub message: String,
+    pub request_id: Option<String>,
+    pub principal: Option<String>,
+    /// Wall-clock time in Unix milliseconds, from the registered `SharedClock`.
+    pub timestamp_ms: u64,
+}
+
+/// Cloneable handle to the registered [`ErrorReporter`], resolved like any other dependency.
+#[derive(Clone)]
+pub struct SharedErrorReporter(Arc<dyn ErrorReporter>);
+
+impl SharedErrorReporter {
+    pub fn new(reporter: impl ErrorReporter) -> Self {
+        Self(Arc::new(reporter))
+    }
+
+    pub fn report(&self, report: &ErrorReport) {
+        self.0.report(report);
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.0.name()
+    }
+}
+
+/// Attached to panic responses by the panic-recovery layer, so the report carries the panic
+/// message instead of the generic `internal_error` body. The route is captured there because
+/// a panicking handler never returns its `MatchedRoute`.
+#[derive(Debug, Clone)]
+pub(crate) struct PanicMessage {
+    pub(crate) message: String,
+    pub(crate) route: Option<String>,
+}
+
+/// Sends an [`ErrorReport`] to the registered [`SharedErrorReporter`] for every REST 5xx,
+/// gRPC server-error status and handler panic, with the route, principal subject and request
+/// id. Requests pass through untouched when no reporter is registered. gRPC errors are read
+/// from trailers-only responses, so errors raised mid-stream are not reported.
+pub fn with_error_reporting(router: Router) -> Router {
+    // `route_layer` panics on a router without routes.
+    let router = if router.has_routes() {
+        router.route_layer(from_fn(capture_matched_route))
+    } else {
+        router
+    };
+    router.layer(from_fn(error_reporting_middleware))
+}
+
+async fn error_reporting_middleware(mut req: Request, next: Next) -> Response {
+    let Some(reporter) = resolve_from_extensions::<SharedErrorReporter>(req.extensions()) else {
+        return next.run(req).await;
+    };
+    let is_grpc = is_grpc_request(req.headers());
+    let method = req.method().to_string();
+    let path = req.uri().path().to_string();
+    let request_id = header_value(req.headers(), REQUEST_ID_HEADER);
+    let principal = CallPrincipal::attach(req.extensions_mut());
+    let clock = clock_from_extensions(req.extensions());
+
+    let response = next.run(req).await;
+    let grpc_status = is_grpc
+        .then(|| tonic::Status::from_header_map(response.headers()))
+        .flatten();
+    let panic = response.extensions().get::<PanicMessage>().cloned();
+    let failed = match &grpc_status {
+        Some(status) => GRPC_SERVER_ERROR_CODES.contains(&status.code()),
+        None => response.status().is_server_error(),
+    };
+    if !failed && panic.is_none() {
+        return response;
+    }
+
+    let (route, code, message, response) = match (&panic, &grpc_status) {
+        (Some(PanicMessage { message, route }), _) => (
+            match route {
+                Some(route) if !is_grpc => route.clone(),
+                _ => route_of(&response, is_grpc, path),
+            },
+            "panic".to_string(),
+            message.clone(),
+            response,
+        ),
+        (None, Some(status)) => (
+            path,
+            format!("{:?}", status.code()),
+            status.message().to_string(),
+            response,
+        ),
+        (None, None) => {
+            let route = route_of(&response, is_grpc, path);
+            let (code, message, response) = read_rest_error(response).await;
+            (route, code, message, response)
+        }
+    };
+    reporter.report(&ErrorReport {
+        kind: if panic.is_some() {
+            ErrorReportKind::Panic
+        } else {
+            ErrorReportKind::ServerError
+        },
+        protocol: if is_grpc { "grpc" } else { "rest" },
+        method,
+        route,
+        status: response.status().as_u16(),
+        grpc_status: grpc_status.map(|status| status.code() as i32),
+        code,
+        message: truncate(&message),
+        request_id: request_id.or_else(|| header_value(response.headers(), REQUEST_ID_HEADER)),
+        principal: principal.get(),
+        timestamp_ms: clock
+            .system_time()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or_default(),
+    });
+    response
+}
+
+fn route_of(response: &Response, is_grpc: bool, path: String) -> String {
+    if is_grpc {
+        return path;
+    }
+    response
+        .extensions()
+        .get::<MatchedRoute>()
+        .map(|MatchedRoute(route)| route.clone())
+        .unwrap_or_else(|| UNMATCHED_ROUTE.to_string())
+}
+
+/// `code` and `message` of a JSON `ApiErrorResponse` body; other bodies report the status.
+async fn read_rest_error(response: Response) -> (String, String, Response) {
+    let fallback = |status: StatusCode| {
+        (
+            status
+                .canonical_reason()
+                .unwrap_or("server_error")
+                .to_ascii_lowercase()
+                .replace(' ', "_"),
+            status.to_string(),
+        )
+    };
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    let small = response
+        .body()
+        .size_hint()
+        .exact()
+        .is_some_and(|len| len <= MAX_ERROR_BODY_BYTES);
+    if !is_json || !small {
+        let (code, message) = fallback(response.status());
+        return (code, message, response);
+    }
+
+    let (parts, body) = response.into_parts();
+    let bytes = to_bytes(body, MAX_ERROR_BODY_BYTES as usize)
+        .await
+        .unwrap_or_default();
+    let (code, message) = match serde_json::from_slice::<ApiErrorResponse>(&bytes) {
+        Ok(error) => (error.code, error.message),
+        Err(_) => fallback(parts.status),
+    };
+    (
+        code,
+        message,
+        Response::from_parts(parts, Body::from(bytes)),
+    )
+}
+
+fn header_value(headers: &http::HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+fn truncate(message: &str) -> String {
+    match message.char_indices().nth(MAX_MESSAGE_CHARS) {
+        Some((end, _)) => format!("{}…", &message[..end]),
+        None => message.to_string(),
+    }
+}
+
+/// Reports errors to Sentry through its envelope endpoint, from a blocking task so a slow or
+/// unreachable Sentry never delays responses; failed deliveries are logged. Events carry the
+/// route as transaction, the principal subject as user id and the rest as tags.
+#[cfg(feature = "sentry")]
+#[derive(Debug, Clone)]
+pub struct SentryReporter {
+    dsn: String,
+    envelope_url: String,
+    auth_header: String,
+    environment: Option<String>,
+    release: Option<String>,
+    client: ureq::Agent,
+}
+
+#[cfg(feature = "sentry")]
+impl SentryReporter {
+    /// Accepts DSNs of the form `https://<public_key>@<host>[/<path>]/<project_id>`.
+    pub fn new(dsn: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid Sentry DSN `{dsn}`");
+        let (scheme, rest) = dsn.split_once("://").ok_or_else(invalid)?;
+        let (credentials, location) = rest.split_once('@').ok_or_else(invalid)?;
+        let public_key = credentials.split(':').next().unwrap_or_default();
+        let (prefix, project_id) = location.rsplit_once('/').ok_or_else(invalid)?;
+        if public_key.is_empty() || project_id.is_empty() || prefix.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            dsn: dsn.to_string(),
+            envelope_url: format!("{scheme}://{prefix}/api/{project_id}/envelope/"),
+            auth_header: format!(
+                "Sentry sentry_version=7, sentry_client=openportio/{}, sentry_key={public_key}",
+                env!("CARGO_PKG_VERSION")
+            ),
+            environment: None,
+            release: None,
+            client: ureq::AgentBuilder::new()
+                .timeout(std::time::Duration::from_secs(5))
+                .build(),
+        })
+    }
+
+    /// `OPENPORTIO_SENTRY_DSN`; `None` when unset or invalid.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let Some(dsn) = crate::env::var("SENTRY_DSN")?.filter(|dsn| !dsn.trim().is_empty()) else {
+            return Ok(None);
+        };
+        Self::new(dsn.trim())
+            .map(Some)
+            .map_err(|err| format!("`OPENPORTIO_SENTRY_DSN` is invalid: {err}"))
+    }
+
+    pub fn with_environment(mut self, environment: impl Into<String>) -> Self {
+        self.environment = Some(environment.into());
+        self
+    }
+
+    pub fn with_release(mut self, release: impl Into<String>) -> Self {
+        self.release = Some(release.into());
+        self
+    }
+
+    fn envelope(&self, report: &ErrorReport) -> String {
+        let event_id = uuid::Uuid::new_v4().simple().to_string();
+        let mut tags = serde_json::json!({
+            "kind": report.kind.as_str(),
+            "protocol": report.protocol,
+            "route": report.route,
+            "status": report.status.to_string(),
+            "code": report.code,
+        });
+        if let Some(request_id) = &report.request_id {
+            tags["request_id"] = request_id.clone().into();
+        }
+        if let Some(grpc_status) = report.grpc_status {
+            tags["grpc_status"] = grpc_status.to_string().into();
+        }
+        let event = serde_json::json!({
+            "event_id": event_id,
+            "timestamp": report.timestamp_ms as f64 / 1000.0,
+            "platform": "other",
+            "level": if report.kind == ErrorReportKind::Panic { "fatal" } else { "error" },
+            "logger": "openportio",
+            "transaction": format!("{} {}", report.method, report.route),
+            "environment": self.environment,
+            "release": self.release,
+            "exception": { "values": [{ "type": report.code, "value": report.message }] },
+            "tags": tags,
+            "user": report.principal.as_ref().map(|subject| serde_json::json!({ "id": subject })),
+        });
+        format!(
+            "{}\n{}\n{}\n",
+            serde_json::json!({ "event_id": event_id, "dsn": self.dsn }),
+            serde_json::json!({ "type": "event" }),
+            event
+        )
+    }
+
+    fn post(&self, envelope: String) {
+        let result = self
+            .client
+            .post(&self.envelope_url)
+            .set("content-type", "application/x-sentry-envelope")
+            .set("x-sentry-auth", &self.auth_header)
+            .send_string(&envelope);
+        if let Err(err) = result {
+            tracing::warn!(error = %err, "failed to deliver Sentry event");
+        }
+    }
+}
+
+#[cfg(feature = "sentry")]
+impl ErrorReporter for SentryReporter {
+    fn report(&self, report: &ErrorReport) {
+        let envelope = self.envelope(report);
+        let reporter = self.clone();
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn_blocking(move || reporter.post(envelope));
+            }
+            Err(_) => reporter.post(envelope),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "sentry"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{api::map_domain_error_to_rest, di, panics::with_panic_recovery};
+    use axum::{response::IntoResponse, routing::get};
+    use openportio_core::{NoopMetrics, OpenportioError};
+    use std::sync::Mutex;
+    use tower::util::ServiceExt;
+
+    #[derive(Clone, Default)]
+    struct RecordingReporter(Arc<Mutex<Vec<ErrorReport>>>);
+
+    impl ErrorReporter for RecordingReporter {
+        fn report(&self, report: &ErrorReport) {
+            self.0.lock().unwrap().push(report.clone());
+        }
+    }
+
+    async fn failing() -> Response {
+        map_domain_error_to_rest(OpenportioError::Internal("pool exhausted".to_string()))
+            .into_response()
+    }
+
+    async fn boom() -> &'static str {
+        panic!("invariant broken for order 7")
+    }
+
+    #[tokio::test]
+    async fn server_errors_and_panics_are_reported_once_with_request_context() {
+        let reporter = RecordingReporter::default();
+        let app = di::with_dependency(
+            with_error_reporting(with_panic_recovery(
+                Router::new()
+                    .route("/orders/:id", get(failing))
+                    .route("/boom", get(boom))
+                    .route("/ok", get(|| async { "ok" })),
+                Arc::new(NoopMetrics),
+            )),
+            SharedErrorReporter::new(reporter.clone()),
+        );
+        let call = |uri: &str| {
+            app.clone().oneshot(
+                Request::get(uri)
+                    .header(REQUEST_ID_HEADER, "req-1")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        let response = call("/orders/7").await.unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body: ApiErrorResponse =
+            serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap())
+                .unwrap();
+        assert_eq!(body.code, "internal_error", "the body is passed on intact");
+        assert_eq!(call("/boom").await.unwrap().status(), 500);
+        assert_eq!(call("/ok").await.unwrap().status(), 200);
+
+        let reports = reporter.0.lock().unwrap().clone();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].kind, ErrorReportKind::ServerError);
+        assert_eq!(reports[0].route, "/orders/:id");
+        assert_eq!(reports[0].code, "internal_error");
+        assert_eq!(reports[0].request_id.as_deref(), Some("req-1"));
+        assert_eq!(reports[1].kind, ErrorReportKind::Panic);
+        assert_eq!(reports[1].route, "/boom");
+        assert_eq!(reports[1].message, "invariant broken for order 7");
+    }
+
+    #[cfg(feature = "sentry")]
+    #[test]
+    fn sentry_dsns_map_to_the_envelope_endpoint() {
+        let reporter = SentryReporter::new("https://abc123@o1.ingest.sentry.io/42").unwrap();
+        assert_eq!(
+            reporter.envelope_url,
+            "https://o1.ingest.sentry.io/api/42/envelope/"
+        );
+        assert!(reporter.auth_header.ends_with("sentry_key=abc123"));
+        assert!(SentryReporter::new("not-a-dsn").is_err());
+    }
+}
@@ -0,0 +1,560 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use axum::{
+    body::{Body, Bytes},
+    http::{header, HeaderMap, HeaderValue},
+    response::{IntoResponse, Response},
+};
+use futures_util::{stream::BoxStream, Stream, StreamExt};
+use http_body::Frame;
+use serde::{
+    de::{MapAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+use serde_json::Value;
+use utoipa::{
+    openapi::{
+        content::ContentBuilder, response::ResponseBuilder, ObjectBuilder, RefOr,
+        Response as OpenApiResponse, Type,
+    },
+    IntoResponses, ToSchema,
+};
+
+use crate::{api::ApiErrorResponse, files::content_disposition};
+
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+pub const CSV_CONTENT_TYPE: &str = "text/csv; charset=utf-8";
+/// Trailer naming the error code of an NDJSON export that failed part way.
+pub const STREAM_ERROR_TRAILER: &str = "x-stream-error";
+/// Records are coalesced into chunks of about this size while the source has them ready.
+const CHUNK_BYTES: usize = 16 * 1024;
+
+/// Streams `records` as newline-delimited JSON, one record per line. Records are pulled only
+/// when the client is ready for more, so a slow client slows the source down.
+///
+/// Headers are sent before the first record, so a source error cannot change the status any
+/// more: the stream ends with a final `{"error": ApiErrorResponse}` line (code `stream_error`,
+/// `details.records` counting the lines before it) and an `x-stream-error` trailer for clients
+/// that read trailers. The error itself is logged, not sent.
+pub struct NdjsonStream<T> {
+    records: BoxStream<'static, Result<T, String>>,
+    disposition: Option<HeaderValue>,
+}
+
+/// Streams `records` as RFC 4180 CSV (`\r\n` line endings, fields quoted when needed) with a
+/// header row from the first record's field names. Records must serialize as structs or maps
+/// of scalars; nested values are written as JSON text and `null` as an empty field.
+///
+/// CSV has no place for an in-band error, so a source error aborts the response instead: the
+/// client sees a truncated transfer rather than a complete-looking file.
+pub struct CsvStream<T> {
+    records: BoxStream<'static, Result<T, String>>,
+    disposition: Option<HeaderValue>,
+    header: bool,
+}
+
+fn boxed<T, E, S>(records: S) -> BoxStream<'static, Result<T, String>>
+where
+    S: Stream<Item = Result<T, E>> + Send + 'static,
+    E: fmt::Display,
+{
+    records
+        .map(|record| record.map_err(|err| err.to_string()))
+        .boxed()
+}
+
+impl<T> NdjsonStream<T>
+where
+    T: Serialize + Send + 'static,
+{
+    pub fn new<E, S>(records: S) -> Self
+    where
+        S: Stream<Item = Result<T, E>> + Send + 'static,
+        E: fmt::Display,
+    {
+        Self {
+            records: boxed(records),
+            disposition: None,
+        }
+    }
+
+    /// `Content-Disposition: attachment` so browsers save the export as `file_name`.
+    pub fn attachment(mut self, file_name: &str) -> Self {
+        self.disposition = Some(content_disposition("attachment", file_name));
+        self
+    }
+}
+
+impl<T> CsvStream<T>
+where
+    T: Serialize + Send + 'static,
+{
+    pub fn new<E, S>(records: S) -> Self
+    where
+        S: Stream<Item = Result<T, E>> + Send + 'static,
+        E: fmt::Display,
+    {
+        Self {
+            records: boxed(records),
+            disposition: None,
+            header: true,
+        }
+    }
+
+    /// `Content-Disposition: attachment` so browsers save the export as `file_name`.
+    pub fn attachment(mut self, file_name: &str) -> Self {
+        self.disposition = Some(content_disposition("attachment", file_name));
+        self
+    }
+
+    pub fn without_header(mut self) -> Self {
+        self.header = false;
+        self
+    }
+}
+
+impl<T> fmt::Debug for NdjsonStream<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NdjsonStream").finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Debug for CsvStream<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CsvStream")
+            .field("header", &self.header)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> IntoResponse for NdjsonStream<T>
+where
+    T: Serialize + Send + 'static,
+{
+    fn into_response(self) -> Response {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(NDJSON_CONTENT_TYPE),
+        );
+        headers.insert(
+            header::TRAILER,
+            HeaderValue::from_static(STREAM_ERROR_TRAILER),
+        );
+        if let Some(disposition) = self.disposition {
+            headers.insert(header::CONTENT_DISPOSITION, disposition);
+        }
+        let body = RecordBody::new(self.records, Encoder::Ndjson);
+        (headers, Body::new(body)).into_response()
+    }
+}
+
+impl<T> IntoResponse for CsvStream<T>
+where
+    T: Serialize + Send + 'static,
+{
+    fn into_response(self) -> Response {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(CSV_CONTENT_TYPE),
+        );
+        if let Some(disposition) = self.disposition {
+            headers.insert(header::CONTENT_DISPOSITION, disposition);
+        }
+        let encoder = Encoder::Csv {
+            header: self.header,
+            columns: None,
+        };
+        (headers, Body::new(RecordBody::new(self.records, encoder))).into_response()
+    }
+}
+
+/// Documents a `200` whose `application/x-ndjson` body is a sequence of `T`, for
+/// `#[utoipa::path(responses(NdjsonStream<T>))]`.
+impl<T: ToSchema> IntoResponses for NdjsonStream<T> {
+    fn responses() -> BTreeMap<String, RefOr<OpenApiResponse>> {
+        let response = ResponseBuilder::new()
+            .description(format!(
+                "Newline-delimited JSON, one `{}` per line; a final `error` line reports a failure mid-stream",
+                T::name()
+            ))
+            .content(
+                NDJSON_CONTENT_TYPE,
+                ContentBuilder::new().schema(Some(T::schema())).build(),
+            )
+            .build();
+        BTreeMap::from([("200".to_string(), response.into())])
+    }
+}
+
+/// Documents a `200` with a `text/csv` body whose columns are the fields of `T`.
+impl<T: ToSchema> IntoResponses for CsvStream<T> {
+    fn responses() -> BTreeMap<String, RefOr<OpenApiResponse>> {
+        let response = ResponseBuilder::new()
+            .description(format!(
+                "CSV with a header row and one `{}` per row; truncated if the export fails",
+                T::name()
+            ))
+            .content(
+                "text/csv",
+                ContentBuilder::new()
+                    .schema(Some(ObjectBuilder::new().schema_type(Type::String).build()))
+                    .build(),
+            )
+            .build();
+        BTreeMap::from([("200".to_string(), response.into())])
+    }
+}
+
+enum Encoder {
+    Ndjson,
+    Csv {
+        header: bool,
+        columns: Option<Vec<String>>,
+    },
+}
+
+impl Encoder {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Ndjson => "ndjson",
+            Self::Csv { .. } => "csv",
+        }
+    }
+
+    fn encode<T: Serialize>(&mut self, record: &T, out: &mut Vec<u8>) -> Result<(), String> {
+        match self {
+            Self::Ndjson => {
+                serde_json::to_writer(&mut *out, record).map_err(|err| err.to_string())?;
+                out.push(b'\n');
+            }
+            Self::Csv { header, columns } => {
+                let row = csv_row(record)?;
+                let columns = columns.get_or_insert_with(|| {
+                    let names: Vec<String> = row.iter().map(|(name, _)| name.clone()).collect();
+                    if *header {
+                        write_csv_line(out, names.iter().map(String::as_str));
+                    }
+                    names
+                });
+                let cells: Vec<String> = columns
+                    .iter()
+                    .map(|column| {
+                        row.iter()
+                            .find(|(name, _)| name == column)
+                            .map(|(_, value)| csv_cell(value))
+                            .unwrap_or_default()
+                    })
+                    .collect();
+                write_csv_line(out, cells.iter().map(String::as_str));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The record's fields in serialization order. `serde_json::Value` maps sort their keys, so the
+/// JSON text is read back through [`OrderedRow`] instead.
+fn csv_row<T: Serialize>(record: &T) -> Result<Vec<(String, Value)>, String> {
+    let json = serde_json::to_vec(record).map_err(|err| err.to_string())?;
+    serde_json::from_slice::<OrderedRow>(&json)
+        .map(|row| row.0)
+        .map_err(|_| "CSV records must serialize as structs or maps".to_string())
+}
+
+struct OrderedRow(Vec<(String, Value)>);
+
+impl<'de> Deserialize<'de> for OrderedRow {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct RowVisitor;
+
+        impl<'de> Visitor<'de> for RowVisitor {
+            type Value = OrderedRow;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a map of fields")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<OrderedRow, A::Error> {
+                let mut fields = Vec::new();
+                while let Some(field) = map.next_entry()? {
+                    fields.push(field);
+                }
+                Ok(OrderedRow(fields))
+            }
+        }
+
+        deserializer.deserialize_map(RowVisitor)
+    }
+}
+
+fn csv_cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn write_csv_line<'a>(out: &mut Vec<u8>, cells: impl Iterator<Item = &'a str>) {
+    for (index, cell) in cells.enumerate() {
+        if index > 0 {
+            out.push(b',');
+        }
+        if cell.contains([',', '"', '\n', '\r']) {
+            out.push(b'"');
+            out.extend_from_slice(cell.replace('"', "\"\"").as_bytes());
+            out.push(b'"');
+        } else {
+            out.extend_from_slice(cell.as_bytes());
+        }
+    }
+    out.extend_from_slice(b"\r\n");
+}
+
+enum State {
+    Streaming,
+    /// Buffered output goes first, then the failure is reported.
+    Failed(String),
+    Trailers(HeaderMap),
+    Done,
+}
+
+struct RecordBody<T> {
+    records: BoxStream<'static, Result<T, String>>,
+    encoder: Encoder,
+    state: State,
+    written: u64,
+    _record: PhantomData<fn() -> T>,
+}
+
+impl<T: Serialize> RecordBody<T> {
+    fn new(records: BoxStream<'static, Result<T, String>>, encoder: Encoder) -> Self {
+        Self {
+            records,
+            encoder,
+            state: State::Streaming,
+            written: 0,
+            _record: PhantomData,
+        }
+    }
+
+    /// Ends the stream after `buffer`: NDJSON appends an error line and a trailer, CSV aborts.
+    fn fail(&mut self, message: String, buffer: &mut Vec<u8>) {
+        tracing::error!(
+            format = self.encoder.name(),
+            records = self.written,
+            error = %message,
+            "export stream failed"
+        );
+        match self.encoder {
+            Encoder::Ndjson => {
+                let error = ApiErrorResponse {
+                    code: "stream_error".to_string(),
+                    message: "the export failed before all records were sent".to_string(),
+                    detail: None,
+                    details: Some(serde_json::json!({ "records": self.written })),
+                };
+                serde_json::to_writer(&mut *buffer, &serde_json::json!({ "error": error }))
+                    .expect("error records serialize");
+                buffer.push(b'\n');
+                let mut trailers = HeaderMap::new();
+                trailers.insert(
+                    STREAM_ERROR_TRAILER,
+                    HeaderValue::from_static("stream_error"),
+                );
+                self.state = State::Trailers(trailers);
+            }
+            Encoder::Csv { .. } => self.state = State::Failed(message),
+        }
+    }
+}
+
+impl<T: Serialize> http_body::Body for RecordBody<T> {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = &mut *self;
+        match std::mem::replace(&mut this.state, State::Done) {
+            State::Streaming => this.state = State::Streaming,
+            State::Failed(message) => {
+                return Poll::Ready(Some(Err(axum::Error::new(std::io::Error::other(message)))))
+            }
+            State::Trailers(trailers) => return Poll::Ready(Some(Ok(Frame::trailers(trailers)))),
+            State::Done => return Poll::Ready(None),
+        }
+
+        let mut buffer = Vec::new();
+        while buffer.len() < CHUNK_BYTES {
+            match this.records.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(record))) => {
+                    if let Err(err) = this.encoder.encode(&record, &mut buffer) {
+                        this.fail(err, &mut buffer);
+                        break;
+                    }
+                    this.written += 1;
+                }
+                Poll::Ready(Some(Err(err))) => {
+                    this.fail(err, &mut buffer);
+                    break;
+                }
+                Poll::Ready(None) => {
+                    this.state = State::Done;
+                    break;
+                }
+                Poll::Pending if buffer.is_empty() => return Poll::Pending,
+                Poll::Pending => break,
+            }
+        }
+        if buffer.is_empty() {
+            // Nothing was buffered, so go straight to whatever ended the stream.
+            return self.poll_frame(cx);
+        }
+        Poll::Ready(Some(Ok(Frame::data(Bytes::from(buffer)))))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        matches!(self.state, State::Done)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::Request, routing::get, Router};
+    use http_body::Body as _;
+    use tower::util::ServiceExt;
+
+    #[derive(Serialize, ToSchema)]
+    struct Note {
+        id: u32,
+        title: String,
+        tags: Vec<String>,
+        archived: Option<bool>,
+    }
+
+    fn notes(fail_after: Option<u32>) -> impl Stream<Item = Result<Note, String>> + Send {
+        futures_util::stream::iter(1..=3).map(move |id| match fail_after {
+            Some(limit) if id > limit => Err("connection reset".to_string()),
+            _ => Ok(Note {
+                id,
+                title: format!("note, \"{id}\""),
+                tags: vec!["a".to_string()],
+                archived: (id == 2).then_some(true),
+            }),
+        })
+    }
+
+    async fn collect(response: Response) -> (Result<String, ()>, Option<HeaderMap>) {
+        let mut body = response.into_body();
+        let mut text = Vec::new();
+        let mut trailers = None;
+        while let Some(frame) = std::future::poll_fn(|cx| Pin::new(&mut body).poll_frame(cx)).await
+        {
+            let Ok(frame) = frame else {
+                return (Err(()), trailers);
+            };
+            match frame.into_data() {
+                Ok(data) => text.extend_from_slice(&data),
+                Err(frame) => trailers = frame.into_trailers().ok(),
+            }
+        }
+        (Ok(String::from_utf8(text).unwrap()), trailers)
+    }
+
+    #[tokio::test]
+    async fn ndjson_streams_records_and_reports_failures_in_band() {
+        let app = Router::new()
+            .route("/ok", get(export_notes))
+            .route("/fail", get(|| async { NdjsonStream::new(notes(Some(1))) }));
+
+        let response = app
+            .clone()
+            .oneshot(Request::get("/ok").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            NDJSON_CONTENT_TYPE
+        );
+        let (text, trailers) = collect(response).await;
+        let lines: Vec<Value> = text
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1]["archived"], true);
+        assert!(trailers.is_none());
+
+        let response = app
+            .oneshot(Request::get("/fail").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let (text, trailers) = collect(response).await;
+        let text = text.unwrap();
+        let last: Value = serde_json::from_str(text.lines().last().unwrap()).unwrap();
+        assert_eq!(text.lines().count(), 2);
+        assert_eq!(last["error"]["code"], "stream_error");
+        assert_eq!(last["error"]["details"]["records"], 1);
+        assert_eq!(trailers.unwrap()[STREAM_ERROR_TRAILER], "stream_error");
+    }
+
+    #[tokio::test]
+    async fn csv_quotes_fields_in_declaration_order_and_aborts_on_failure() {
+        let response = CsvStream::new(notes(None))
+            .attachment("notes.csv")
+            .into_response();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], CSV_CONTENT_TYPE);
+        assert_eq!(
+            response.headers()[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"notes.csv\""
+        );
+        let (text, _) = collect(response).await;
+        assert_eq!(
+            text.unwrap(),
+            "id,title,tags,archived\r\n\
+             1,\"note, \"\"1\"\"\",\"[\"\"a\"\"]\",\r\n\
+             2,\"note, \"\"2\"\"\",\"[\"\"a\"\"]\",true\r\n\
+             3,\"note, \"\"3\"\"\",\"[\"\"a\"\"]\",\r\n"
+        );
+
+        let (text, _) = collect(CsvStream::new(notes(Some(2))).into_response()).await;
+        assert!(text.is_err());
+    }
+
+    #[utoipa::path(get, path = "/notes.ndjson", responses(NdjsonStream<Note>))]
+    async fn export_notes() -> NdjsonStream<Note> {
+        NdjsonStream::new(notes(None))
+    }
+
+    #[test]
+    fn responses_document_the_content_types() {
+        #[derive(utoipa::OpenApi)]
+        #[openapi(paths(export_notes))]
+        struct ExportDoc;
+
+        let doc = serde_json::to_value(<ExportDoc as utoipa::OpenApi>::openapi()).unwrap();
+        let ndjson = &doc["paths"]["/notes.ndjson"]["get"]["responses"];
+        assert_eq!(
+            ndjson["200"]["content"][NDJSON_CONTENT_TYPE]["schema"]["properties"]["title"]["type"],
+            "string"
+        );
+        let csv = serde_json::to_value(CsvStream::<Note>::responses()).unwrap();
+        assert_eq!(
+            csv["200"]["content"]["text/csv"]["schema"]["type"],
+            "string"
+        );
+    }
+}
@@ -0,0 +1,748 @@
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{FromRequest, Request},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures_util::{stream, StreamExt};
+use sha2::{Digest, Sha256};
+use tokio::{
+    fs::File,
+    io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+};
+
+use crate::api::{ApiError, ApiErrorResponse};
+
+const DEFAULT_MAX_UPLOAD_BYTES: u64 = 100 * 1024 * 1024;
+const DEFAULT_MEMORY_THRESHOLD: usize = 1024 * 1024;
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Limits for [`StreamingUpload`], resolved from DI (`with_dependency(UploadLimits::new()...)`)
+/// or request extensions; defaults to 100 MiB, spooling to a temp file past 1 MiB. The shared
+/// request body limit (`OPENPORTIO_REQUEST_BODY_LIMIT_BYTES`) applies first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadLimits {
+    max_bytes: u64,
+    memory_threshold: usize,
+    temp_dir: Option<PathBuf>,
+}
+
+impl Default for UploadLimits {
+    fn default() -> Self {
+        Self {
+            max_bytes: DEFAULT_MAX_UPLOAD_BYTES,
+            memory_threshold: DEFAULT_MEMORY_THRESHOLD,
+            temp_dir: None,
+        }
+    }
+}
+
+impl UploadLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Uploads larger than this are written to a temp file instead of kept in memory.
+    pub fn with_memory_threshold(mut self, bytes: usize) -> Self {
+        self.memory_threshold = bytes;
+        self
+    }
+
+    /// Directory for spooled uploads (default: `std::env::temp_dir()`).
+    pub fn with_temp_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.temp_dir = Some(dir.into());
+        self
+    }
+
+    pub fn max_bytes(&self) -> u64 {
+        self.max_bytes
+    }
+
+    pub fn memory_threshold(&self) -> usize {
+        self.memory_threshold
+    }
+}
+
+/// A raw request body read chunk by chunk: the size is checked against [`UploadLimits`] (and
+/// against `Content-Length` when sent), a SHA-256 is computed on the way, and large bodies are
+/// spooled to a temp file that is deleted on drop unless [`Self::persist`]ed.
+///
+/// Rejections: `413` `payload_too_large`, `400` `upload_length_mismatch`, `400` `bad_request`
+/// when the body cannot be read, `500` when the temp file cannot be written.
+pub struct StreamingUpload {
+    content_type: Option<String>,
+    file_name: Option<String>,
+    len: u64,
+    sha256: String,
+    data: Spooled,
+}
+
+enum Spooled {
+    Memory(Bytes),
+    File(TempFile),
+}
+
+impl StreamingUpload {
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Lowercase hex SHA-256 of the body.
+    pub fn sha256(&self) -> &str {
+        &self.sha256
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    /// The `filename` from a `Content-Disposition` request header, when sent, reduced to its
+    /// final path component: `../../etc/passwd` and `C:\temp\x.txt` become `passwd` and
+    /// `x.txt`, and names that are only `.`/`..` are dropped. It is still client input — never
+    /// use it as a storage path without your own checks.
+    pub fn file_name(&self) -> Option<&str> {
+        self.file_name.as_deref()
+    }
+
+    pub fn is_in_memory(&self) -> bool {
+        matches!(self.data, Spooled::Memory(_))
+    }
+
+    /// Reads the whole upload into memory.
+    pub async fn bytes(&self) -> io::Result<Bytes> {
+        match &self.data {
+            Spooled::Memory(bytes) => Ok(bytes.clone()),
+            Spooled::File(file) => tokio::fs::read(&file.path).await.map(Bytes::from),
+        }
+    }
+
+    pub async fn reader(&self) -> io::Result<Box<dyn AsyncRead + Send + Unpin>> {
+        Ok(match &self.data {
+            Spooled::Memory(bytes) => Box::new(io::Cursor::new(bytes.clone())),
+            Spooled::File(file) => Box::new(File::open(&file.path).await?),
+        })
+    }
+
+    /// Moves the upload to `path`; spooled uploads are renamed when possible.
+    pub async fn persist(self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        match self.data {
+            Spooled::Memory(bytes) => tokio::fs::write(path, bytes).await,
+            Spooled::File(file) => {
+                if tokio::fs::rename(&file.path, path).await.is_err() {
+                    // Different filesystem: copy, then let the drop remove the temp file.
+                    tokio::fs::copy(&file.path, path).await?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Debug for StreamingUpload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamingUpload")
+            .field("content_type", &self.content_type)
+            .field("file_name", &self.file_name)
+            .field("len", &self.len)
+            .field("sha256", &self.sha256)
+            .field("in_memory", &self.is_in_memory())
+            .finish()
+    }
+}
+
+struct TempFile {
+    path: PathBuf,
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[axum::async_trait]
+impl<S> FromRequest<S> for StreamingUpload
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, _state: &S) -> Result<Self, Self::Rejection> {
+        let limits: UploadLimits =
+            crate::di::resolve_from_extensions(req.extensions()).unwrap_or_default();
+        let headers = req.headers();
+        let declared = headers
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok());
+        if declared.is_some_and(|len| len > limits.max_bytes) {
+            return Err(too_large(limits.max_bytes));
+        }
+        let content_type = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let file_name = headers
+            .get(header::CONTENT_DISPOSITION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(disposition_file_name);
+
+        let mut body = req.into_body().into_data_stream();
+        let mut hasher = Sha256::new();
+        let mut len = 0u64;
+        let mut memory = Vec::new();
+        let mut spool: Option<(TempFile, File)> = None;
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk.map_err(|err| {
+                upload_error(
+                    StatusCode::BAD_REQUEST,
+                    "bad_request",
+                    format!("upload body could not be read: {err}"),
+                )
+            })?;
+            len += chunk.len() as u64;
+            if len > limits.max_bytes {
+                return Err(too_large(limits.max_bytes));
+            }
+            hasher.update(&chunk);
+            if spool.is_none() && memory.len() + chunk.len() > limits.memory_threshold {
+                let (temp, mut file) = create_temp_file(&limits).await.map_err(spool_error)?;
+                file.write_all(&memory).await.map_err(spool_error)?;
+                memory = Vec::new();
+                spool = Some((temp, file));
+            }
+            match &mut spool {
+                Some((_, file)) => file.write_all(&chunk).await.map_err(spool_error)?,
+                None => memory.extend_from_slice(&chunk),
+            }
+        }
+        if let Some(declared) = declared.filter(|declared| *declared != len) {
+            return Err(upload_error(
+                StatusCode::BAD_REQUEST,
+                "upload_length_mismatch",
+                format!("upload declared {declared} bytes but sent {len}"),
+            ));
+        }
+        let data = match spool {
+            Some((temp, mut file)) => {
+                file.flush().await.map_err(spool_error)?;
+                Spooled::File(temp)
+            }
+            None => Spooled::Memory(Bytes::from(memory)),
+        };
+        Ok(Self {
+            content_type,
+            file_name,
+            len,
+            sha256: hasher
+                .finalize()
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect(),
+            data,
+        })
+    }
+}
+
+async fn create_temp_file(limits: &UploadLimits) -> io::Result<(TempFile, File)> {
+    let dir = limits.temp_dir.clone().unwrap_or_else(std::env::temp_dir);
+    let temp = TempFile {
+        path: dir.join(format!("openportio-upload-{}", uuid::Uuid::new_v4())),
+    };
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let file = options.open(&temp.path).await?;
+    Ok((temp, file))
+}
+
+fn disposition_file_name(value: &str) -> Option<String> {
+    value.split(';').map(str::trim).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("filename")
+            .then(|| value.trim().trim_matches('"'))
+            .and_then(|name| name.rsplit(['/', '\\']).next())
+            .filter(|name| !matches!(*name, "" | "." | ".."))
+            .map(str::to_string)
+    })
+}
+
+pub(crate) fn too_large(limit: u64) -> ApiError {
+    (
+        StatusCode::PAYLOAD_TOO_LARGE,
+        Json(ApiErrorResponse {
+            code: "payload_too_large".to_string(),
+            message: format!("upload exceeds the {limit} byte limit"),
+            detail: None,
+            details: Some(serde_json::json!({ "limit_bytes": limit })),
+        }),
+    )
+}
+
+fn spool_error(err: io::Error) -> ApiError {
+    tracing::error!(error = %err, "could not spool upload to disk");
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ApiErrorResponse::internal_server_error()),
+    )
+}
+
+pub(crate) fn upload_error(status: StatusCode, code: &str, message: String) -> ApiError {
+    (
+        status,
+        Json(ApiErrorResponse {
+            code: code.to_string(),
+            message,
+            detail: None,
+            details: None,
+        }),
+    )
+}
+
+/// Streams a file or any `AsyncRead` without buffering it, with `Content-Disposition` and
+/// single-range `Range` support (`206` / `416`) when the length is known.
+pub struct FileResponse {
+    source: Source,
+    len: Option<u64>,
+    content_type: HeaderValue,
+    disposition: Option<HeaderValue>,
+    range: Option<String>,
+}
+
+enum Source {
+    File(File),
+    Reader(Box<dyn AsyncRead + Send + Unpin>),
+}
+
+impl FileResponse {
+    /// Opens `path`; ranges are served by seeking.
+    pub async fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path).await?;
+        let len = file.metadata().await?.len();
+        Ok(Self::new(Source::File(file), Some(len)))
+    }
+
+    /// Streams `reader`; pass `len` to send `Content-Length` and serve ranges (by skipping).
+    pub fn from_reader(reader: impl AsyncRead + Send + Unpin + 'static, len: Option<u64>) -> Self {
+        Self::new(Source::Reader(Box::new(reader)), len)
+    }
+
+    fn new(source: Source, len: Option<u64>) -> Self {
+        Self {
+            source,
+            len,
+            content_type: HeaderValue::from_static("application/octet-stream"),
+            disposition: None,
+            range: None,
+        }
+    }
+
+    pub fn with_content_type(mut self, content_type: HeaderValue) -> Self {
+        self.content_type = content_type;
+        self
+    }
+
+    /// `Content-Disposition: attachment` so browsers download as `file_name`.
+    pub fn attachment(mut self, file_name: &str) -> Self {
+        self.disposition = Some(content_disposition("attachment", file_name));
+        self
+    }
+
+    pub fn inline(mut self, file_name: &str) -> Self {
+        self.disposition = Some(content_disposition("inline", file_name));
+        self
+    }
+
+    /// Honors the request's `Range` header; multi-range requests get the whole file.
+    pub fn with_range(mut self, request_headers: &HeaderMap) -> Self {
+        self.range = request_headers
+            .get(header::RANGE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        self
+    }
+}
+
+impl IntoResponse for FileResponse {
+    fn into_response(self) -> Response {
+        let mut status = StatusCode::OK;
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, self.content_type);
+        if let Some(disposition) = self.disposition {
+            headers.insert(header::CONTENT_DISPOSITION, disposition);
+        }
+        let mut start = 0;
+        let mut remaining = self.len;
+        if let Some(len) = self.len {
+            headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+            match self.range.as_deref().map(|range| parse_range(range, len)) {
+                Some(RangeRequest::Satisfiable(first, last)) => {
+                    status = StatusCode::PARTIAL_CONTENT;
+                    start = first;
+                    remaining = Some(last - first + 1);
+                    headers.insert(
+                        header::CONTENT_RANGE,
+                        header_value(format!("bytes {first}-{last}/{len}")),
+                    );
+                }
+                Some(RangeRequest::Unsatisfiable) => {
+                    headers.insert(
+                        header::CONTENT_RANGE,
+                        header_value(format!("bytes */{len}")),
+                    );
+                    return (StatusCode::RANGE_NOT_SATISFIABLE, headers).into_response();
+                }
+                Some(RangeRequest::Ignored) | None => {}
+            }
+        }
+        if let Some(remaining) = remaining {
+            headers.insert(header::CONTENT_LENGTH, header_value(remaining.to_string()));
+        }
+        let body = Body::from_stream(stream::try_unfold(
+            (self.source, start, remaining, vec![0u8; CHUNK_SIZE]),
+            read_chunk,
+        ));
+        (status, headers, body).into_response()
+    }
+}
+
+type ChunkState = (Source, u64, Option<u64>, Vec<u8>);
+
+async fn read_chunk(
+    (mut source, skip, remaining, mut buf): ChunkState,
+) -> io::Result<Option<(Bytes, ChunkState)>> {
+    if skip > 0 {
+        match &mut source {
+            Source::File(file) => {
+                file.seek(io::SeekFrom::Start(skip)).await?;
+            }
+            Source::Reader(reader) => {
+                tokio::io::copy(&mut reader.take(skip), &mut tokio::io::sink()).await?;
+            }
+        }
+    }
+    let want = remaining.map_or(CHUNK_SIZE, |remaining| {
+        remaining.min(CHUNK_SIZE as u64) as usize
+    });
+    if want == 0 {
+        return Ok(None);
+    }
+    let read = match &mut source {
+        Source::File(file) => file.read(&mut buf[..want]).await?,
+        Source::Reader(reader) => reader.read(&mut buf[..want]).await?,
+    };
+    if read == 0 {
+        return Ok(None);
+    }
+    let chunk = Bytes::copy_from_slice(&buf[..read]);
+    let remaining = remaining.map(|remaining| remaining - read as u64);
+    Ok(Some((chunk, (source, 0, remaining, buf))))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum RangeRequest {
+    Satisfiable(u64, u64),
+    Unsatisfiable,
+    /// Malformed or multi-range: served as a whole.
+    Ignored,
+}
+
+fn parse_range(value: &str, len: u64) -> RangeRequest {
+    let Some(spec) = value.trim().strip_prefix("bytes=") else {
+        return RangeRequest::Ignored;
+    };
+    if spec.contains(',') {
+        return RangeRequest::Ignored;
+    }
+    let Some((first, last)) = spec.trim().split_once('-') else {
+        return RangeRequest::Ignored;
+    };
+    let (first, last) = match (first.trim(), last.trim()) {
+        ("", "") => return RangeRequest::Ignored,
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) => return RangeRequest::Unsatisfiable,
+            Ok(suffix) => (len.saturating_sub(suffix), len.saturating_sub(1)),
+            Err(_) => return RangeRequest::Ignored,
+        },
+        (first, "") => match first.parse::<u64>() {
+            Ok(first) => (first, len.saturating_sub(1)),
+            Err(_) => return RangeRequest::Ignored,
+        },
+        (first, last) => match (first.parse::<u64>(), last.parse::<u64>()) {
+            (Ok(first), Ok(last)) if first <= last => (first, last.min(len.saturating_sub(1))),
+            _ => return RangeRequest::Ignored,
+        },
+    };
+    if len == 0 || first >= len {
+        return RangeRequest::Unsatisfiable;
+    }
+    RangeRequest::Satisfiable(first, last)
+}
+
+/// `kind; filename="ascii fallback"; filename*=UTF-8''percent-encoded`.
+pub(crate) fn content_disposition(kind: &str, file_name: &str) -> HeaderValue {
+    let fallback: String = file_name
+        .chars()
+        .map(|c| match c {
+            '"' | '\\' => '_',
+            c if c.is_ascii_graphic() || c == ' ' => c,
+            _ => '_',
+        })
+        .collect();
+    let mut value = format!("{kind}; filename=\"{fallback}\"");
+    if fallback != file_name {
+        let encoded: String = file_name
+            .bytes()
+            .map(|byte| match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'.' | b'-' | b'_' | b'~' => {
+                    (byte as char).to_string()
+                }
+                _ => format!("%{byte:02X}"),
+            })
+            .collect();
+        value.push_str(&format!("; filename*=UTF-8''{encoded}"));
+    }
+    header_value(value)
+}
+
+fn header_value(value: String) -> HeaderValue {
+    HeaderValue::from_str(&value).expect("generated header values are visible ascii")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::to_bytes,
+        routing::{get, post},
+        Router,
+    };
+    use tower::ServiceExt;
+
+    fn upload_app(limits: UploadLimits) -> Router {
+        let app = Router::new().route(
+            "/upload",
+            post(|upload: StreamingUpload| async move {
+                let body = upload.bytes().await.unwrap();
+                Json(serde_json::json!({
+                    "len": upload.len(),
+                    "sha256": upload.sha256(),
+                    "in_memory": upload.is_in_memory(),
+                    "file_name": upload.file_name(),
+                    "intact": body.len() as u64 == upload.len(),
+                }))
+            }),
+        );
+        crate::di::with_dependency_override(app, limits)
+    }
+
+    async fn send(app: Router, request: axum::http::request::Builder, body: Body) -> Response {
+        app.oneshot(request.body(body).unwrap()).await.unwrap()
+    }
+
+    async fn json(response: Response) -> serde_json::Value {
+        serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap()
+    }
+
+    fn chunked(parts: &[&'static str]) -> Body {
+        Body::from_stream(stream::iter(
+            parts
+                .iter()
+                .map(|part| Ok::<_, io::Error>(Bytes::from_static(part.as_bytes())))
+                .collect::<Vec<_>>(),
+        ))
+    }
+
+    #[tokio::test]
+    async fn uploads_are_hashed_spooled_and_limited() {
+        let limits = UploadLimits::new()
+            .with_max_bytes(16)
+            .with_memory_threshold(4);
+        let post = || Request::builder().method("POST").uri("/upload");
+
+        let small = json(send(upload_app(limits.clone()), post(), Body::from("abc")).await).await;
+        assert_eq!(small["len"], 3);
+        assert_eq!(
+            small["sha256"],
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(small["in_memory"], true);
+
+        let spooled = send(
+            upload_app(limits.clone()),
+            post().header(
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"notes.txt\"",
+            ),
+            chunked(&["abc", "defg", "hij"]),
+        )
+        .await;
+        let spooled = json(spooled).await;
+        assert_eq!(spooled["len"], 10);
+        assert_eq!(spooled["in_memory"], false);
+        assert_eq!(spooled["intact"], true);
+        assert_eq!(spooled["file_name"], "notes.txt");
+
+        let too_large = send(
+            upload_app(limits.clone()),
+            post(),
+            chunked(&["0123456789", "0123456789"]),
+        )
+        .await;
+        assert_eq!(too_large.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(json(too_large).await["details"]["limit_bytes"], 16);
+
+        let mismatch = send(
+            upload_app(limits),
+            post().header(header::CONTENT_LENGTH, "5"),
+            chunked(&["abc"]),
+        )
+        .await;
+        assert_eq!(mismatch.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(json(mismatch).await["code"], "upload_length_mismatch");
+    }
+
+    #[test]
+    fn disposition_file_names_keep_only_the_final_component() {
+        let name = |value: &str| disposition_file_name(value);
+        assert_eq!(
+            name("attachment; filename=\"../../etc/passwd\"").as_deref(),
+            Some("passwd")
+        );
+        assert_eq!(
+            name("attachment; filename=\"C:\\temp\\x.txt\"").as_deref(),
+            Some("x.txt")
+        );
+        assert_eq!(
+            name("attachment; filename=notes.txt").as_deref(),
+            Some("notes.txt")
+        );
+        assert_eq!(name("attachment; filename=\"..\""), None);
+        assert_eq!(name("attachment; filename=\"uploads/\""), None);
+    }
+
+    #[tokio::test]
+    async fn spooled_uploads_persist_and_clean_up() {
+        let dir = std::env::temp_dir().join(format!("openportio-files-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let limits = UploadLimits::new()
+            .with_memory_threshold(2)
+            .with_temp_dir(&dir);
+        let request = Request::builder()
+            .method("POST")
+            .extension(crate::di::DependencyOverride(limits))
+            .body(Body::from("spooled"))
+            .unwrap();
+        let upload = StreamingUpload::from_request(request, &()).await.unwrap();
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let spooled = std::fs::read_dir(&dir).unwrap().next().unwrap().unwrap();
+            let mode = spooled.metadata().unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let target = dir.join("kept.bin");
+        upload.persist(&target).await.unwrap();
+        assert_eq!(std::fs::read(&target).unwrap(), b"spooled");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        let request = Request::builder()
+            .method("POST")
+            .extension(crate::di::DependencyOverride(
+                UploadLimits::new()
+                    .with_memory_threshold(2)
+                    .with_temp_dir(&dir),
+            ))
+            .body(Body::from("dropped"))
+            .unwrap();
+        drop(StreamingUpload::from_request(request, &()).await.unwrap());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn download_app() -> Router {
+        Router::new().route(
+            "/report",
+            get(|headers: HeaderMap| async move {
+                FileResponse::from_reader(io::Cursor::new(b"0123456789".to_vec()), Some(10))
+                    .with_content_type(HeaderValue::from_static("text/plain"))
+                    .attachment("résumé \"v2\".txt")
+                    .with_range(&headers)
+            }),
+        )
+    }
+
+    async fn download(range: Option<&str>) -> (StatusCode, HeaderMap, String) {
+        let mut request = Request::builder().uri("/report");
+        if let Some(range) = range {
+            request = request.header(header::RANGE, range);
+        }
+        let response = send(download_app(), request, Body::empty()).await;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, headers, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn downloads_stream_with_ranges_and_disposition() {
+        let (status, headers, body) = download(None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "0123456789");
+        assert_eq!(headers[header::CONTENT_LENGTH], "10");
+        assert_eq!(headers[header::ACCEPT_RANGES], "bytes");
+        assert_eq!(
+            headers[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"r_sum_ _v2_.txt\"; filename*=UTF-8''r%C3%A9sum%C3%A9%20%22v2%22.txt"
+        );
+
+        let (status, headers, body) = download(Some("bytes=2-4")).await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        assert_eq!(body, "234");
+        assert_eq!(headers[header::CONTENT_RANGE], "bytes 2-4/10");
+
+        assert_eq!(download(Some("bytes=-3")).await.2, "789");
+        assert_eq!(download(Some("bytes=7-")).await.2, "789");
+        assert_eq!(download(Some("bytes=0-1,4-5")).await.0, StatusCode::OK);
+
+        let (status, headers, _) = download(Some("bytes=20-")).await;
+        assert_eq!(status, StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(headers[header::CONTENT_RANGE], "bytes */10");
+    }
+
+    #[tokio::test]
+    async fn files_are_served_by_seeking() {
+        let path = std::env::temp_dir().join(format!("openportio-file-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, b"hello, file").unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RANGE, HeaderValue::from_static("bytes=7-"));
+        let response = FileResponse::open(&path)
+            .await
+            .unwrap()
+            .with_range(&headers)
+            .into_response();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"file");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,349 @@
+use std::{
+    collections::HashMap,
+    io::Read,
+    marker::PhantomData,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use axum::{
+    extract::{FromRef, FromRequestParts},
+    http::{request::Parts, StatusCode},
+    Json,
+};
+use openportio_core::{auth::AuthPrincipal, AppState};
+use serde::Deserialize;
+use serde_json::Value;
+
+pub use openportio_core::flags::{
+    EnvFlagProvider, FeatureFlags, FlagProvider, FlagRule, StaticFlagProvider,
+};
+
+use crate::{
+    api::{ApiError, ApiErrorResponse},
+    di::{DependencyOverride, DependencyOverrides},
+    tenancy::TenantContext,
+};
+
+const DEFAULT_HTTP_FLAGS_TIMEOUT_SECS: u64 = 2;
+const MAX_HTTP_FLAGS_RESPONSE_BYTES: usize = 64 * 1024;
+
+/// Request-facing flag handle, resolvable through `Depends<FlagClient>`.
+#[derive(Debug, Clone, Default)]
+pub struct FlagClient {
+    flags: FeatureFlags,
+}
+
+impl FlagClient {
+    pub fn new(flags: FeatureFlags) -> Self {
+        Self { flags }
+    }
+
+    pub fn is_enabled(&self, flag: &str) -> bool {
+        self.flags.is_enabled(flag)
+    }
+
+    pub fn is_enabled_for(&self, flag: &str, principal: &AuthPrincipal) -> bool {
+        self.flags.is_enabled_for(flag, &principal.subject)
+    }
+
+    pub fn evaluate(&self, flag: &str, principal: Option<&AuthPrincipal>) -> bool {
+        match principal {
+            Some(principal) => self.is_enabled_for(flag, principal),
+            None => self.is_enabled(flag),
+        }
+    }
+}
+
+impl FromRef<Arc<AppState>> for FlagClient {
+    fn from_ref(state: &Arc<AppState>) -> Self {
+        Self::new(state.flags.clone())
+    }
+}
+
+pub trait FeatureName: Send + Sync + 'static {
+    const NAME: &'static str;
+}
+
+/// Extractor that rejects with `404` when feature `F` is off for the current principal.
+/// Emitted by `#[route(..., feature = "...")]`.
+#[derive(Debug, Clone, Copy)]
+pub struct FeatureGate<F>(PhantomData<F>);
+
+#[axum::async_trait]
+impl<F, S> FromRequestParts<S> for FeatureGate<F>
+where
+    F: FeatureName,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let client = request_flag_client(parts);
+        if client.evaluate(F::NAME, parts.extensions.get::<AuthPrincipal>()) {
+            Ok(Self(PhantomData))
+        } else {
+            Err(feature_disabled_error())
+        }
+    }
+}
+
+fn request_flag_client(parts: &Parts) -> FlagClient {
+    if let Some(client) = parts
+        .extensions
+        .get::<TenantContext>()
+        .and_then(TenantContext::flag_override)
+    {
+        return client;
+    }
+    parts
+        .extensions
+        .get::<DependencyOverrides>()
+        .and_then(DependencyOverrides::get::<FlagClient>)
+        .or_else(|| {
+            parts
+                .extensions
+                .get::<DependencyOverride<FlagClient>>()
+                .map(|value| value.0.clone())
+        })
+        .unwrap_or_default()
+}
+
+fn feature_disabled_error() -> ApiError {
+    (
+        StatusCode::NOT_FOUND,
+        Json(ApiErrorResponse {
+            code: "not_found".to_string(),
+            message: "not found".to_string(),
+            detail: None,
+            details: None,
+        }),
+    )
+}
+
+/// Serves flag rules from a JSON object of `{"flag": true | false | 25 | "25%"}` fetched by
+/// [`Self::watch`], which re-fetches it every refresh interval off the request path; lookups only
+/// read the last good payload (nothing until the first fetch lands). Share it through
+/// `FeatureFlags::from_provider` and run `watch` as a background task:
+///
+/// ```rust,ignore
+/// let provider = Arc::new(HttpFlagProvider::new(url, Duration::from_secs(30)));
+/// let flags = FeatureFlags::from_provider(provider.clone());
+/// let server = server.with_background_task("feature-flags", move || provider.watch());
+/// ```
+#[derive(Debug)]
+pub struct HttpFlagProvider {
+    url: String,
+    refresh_interval: Duration,
+    client: ureq::Agent,
+    rules: RwLock<HashMap<String, FlagRule>>,
+}
+
+impl HttpFlagProvider {
+    pub fn new(url: impl Into<String>, refresh_interval: Duration) -> Self {
+        let timeout = Duration::from_secs(DEFAULT_HTTP_FLAGS_TIMEOUT_SECS);
+        Self {
+            url: url.into(),
+            refresh_interval: refresh_interval.max(Duration::from_secs(1)),
+            client: ureq::AgentBuilder::new()
+                .timeout_connect(timeout)
+                .timeout_read(timeout)
+                .build(),
+            rules: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Fetches the payload now, blocking the calling thread; the cached rules are kept when it
+    /// fails.
+    pub fn refresh(&self) -> Result<(), String> {
+        let rules = self.fetch_rules()?;
+        *self
+            .rules
+            .write()
+            .map_err(|_| "flag cache lock poisoned".to_string())? = rules;
+        Ok(())
+    }
+
+    /// Refreshes right away and then once per refresh interval, on the blocking pool.
+    pub async fn watch(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(self.refresh_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            let provider = Arc::clone(&self);
+            let refreshed = tokio::task::spawn_blocking(move || provider.refresh())
+                .await
+                .unwrap_or_else(|err| Err(format!("feature flag refresh task failed: {err}")));
+            if let Err(err) = refreshed {
+                tracing::warn!(error = %err, "feature flag refresh failed; using cached rules");
+            }
+        }
+    }
+
+    fn fetch_rules(&self) -> Result<HashMap<String, FlagRule>, String> {
+        let response = self
+            .client
+            .get(&self.url)
+            .call()
+            .map_err(|err| format!("failed to fetch feature flags: {err}"))?;
+        let mut bytes = Vec::new();
+        response
+            .into_reader()
+            .take((MAX_HTTP_FLAGS_RESPONSE_BYTES + 1) as u64)
+            .read_to_end(&mut bytes)
+            .map_err(|err| format!("failed to read feature flags body: {err}"))?;
+        if bytes.len() > MAX_HTTP_FLAGS_RESPONSE_BYTES {
+            return Err(format!(
+                "feature flags payload exceeds max size of {MAX_HTTP_FLAGS_RESPONSE_BYTES} bytes"
+            ));
+        }
+        parse_flag_payload(&bytes)
+    }
+}
+
+impl FlagProvider for HttpFlagProvider {
+    fn rule(&self, flag: &str) -> Option<FlagRule> {
+        self.rules
+            .read()
+            .ok()
+            .and_then(|rules| rules.get(flag).copied())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawFlagRule {
+    Bool(bool),
+    Percent(u8),
+    Text(String),
+}
+
+fn parse_flag_payload(bytes: &[u8]) -> Result<HashMap<String, FlagRule>, String> {
+    let raw = serde_json::from_slice::<HashMap<String, Value>>(bytes)
+        .map_err(|err| format!("invalid feature flags payload: {err}"))?;
+    let mut rules = HashMap::with_capacity(raw.len());
+    for (flag, value) in raw {
+        let rule = match serde_json::from_value::<RawFlagRule>(value) {
+            Ok(RawFlagRule::Bool(true)) => Ok(FlagRule::On),
+            Ok(RawFlagRule::Bool(false)) => Ok(FlagRule::Off),
+            Ok(RawFlagRule::Percent(percent)) => Ok(FlagRule::percentage(percent)),
+            Ok(RawFlagRule::Text(text)) => text.parse::<FlagRule>(),
+            Err(err) => Err(err.to_string()),
+        };
+        match rule {
+            Ok(rule) => {
+                rules.insert(flag, rule);
+            }
+            Err(err) => {
+                tracing::warn!(flag = %flag, error = %err, "ignoring invalid feature flag rule");
+            }
+        }
+    }
+    Ok(rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request, routing::get, Router};
+    use tower::util::ServiceExt;
+
+    struct BetaFeature;
+
+    impl FeatureName for BetaFeature {
+        const NAME: &'static str = "beta";
+    }
+
+    async fn beta_handler(_gate: FeatureGate<BetaFeature>) -> &'static str {
+        "beta"
+    }
+
+    fn app_with_rule(rule: FlagRule) -> Router {
+        let flags = FeatureFlags::new(StaticFlagProvider::new().with("beta", rule));
+        crate::di::with_dependency(
+            Router::new().route("/beta", get(beta_handler)),
+            FlagClient::new(flags),
+        )
+    }
+
+    #[tokio::test]
+    async fn feature_gate_returns_not_found_when_flag_is_off() {
+        let response = app_with_rule(FlagRule::Off)
+            .oneshot(Request::builder().uri("/beta").body(Body::empty()).unwrap())
+            .await
+            .expect("request should complete");
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = app_with_rule(FlagRule::On)
+            .oneshot(Request::builder().uri("/beta").body(Body::empty()).unwrap())
+            .await
+            .expect("request should complete");
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn feature_gate_evaluates_rollout_against_request_principal() {
+        let flags = FeatureFlags::new(StaticFlagProvider::new().with("beta", FlagRule::On));
+        let client = FlagClient::new(flags);
+        let principal = AuthPrincipal {
+            subject: "user-1".to_string(),
+            issuer: None,
+            audience: vec![],
+            scopes: vec![],
+            token_id: None,
+            tenant_id: None,
+        };
+        assert!(client.evaluate("beta", Some(&principal)));
+
+        let response = app_with_rule(FlagRule::Percentage(100))
+            .oneshot(
+                Request::builder()
+                    .uri("/beta")
+                    .extension(principal)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("request should complete");
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn http_payload_accepts_bool_percent_and_text_rules() {
+        let rules = parse_flag_payload(
+            br#"{"a": true, "b": false, "c": 25, "d": "40%", "e": {"nested": 1}}"#,
+        )
+        .expect("payload should parse");
+        assert_eq!(rules.get("a"), Some(&FlagRule::On));
+        assert_eq!(rules.get("b"), Some(&FlagRule::Off));
+        assert_eq!(rules.get("c"), Some(&FlagRule::Percentage(25)));
+        assert_eq!(rules.get("d"), Some(&FlagRule::Percentage(40)));
+        assert!(!rules.contains_key("e"));
+        assert!(parse_flag_payload(b"[1, 2]").is_err());
+    }
+
+    #[tokio::test]
+    async fn http_provider_refreshes_in_the_background() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route("/flags", get(|| async { r#"{"beta": true}"# }));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let provider = Arc::new(HttpFlagProvider::new(
+            format!("http://{addr}/flags"),
+            Duration::from_secs(60),
+        ));
+        let flags = FeatureFlags::from_provider(provider.clone());
+        assert!(!flags.is_enabled("beta"));
+
+        let watcher = tokio::spawn(provider.watch());
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !flags.is_enabled("beta") {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("first refresh should land");
+        watcher.abort();
+    }
+}
@@ -1,12 +1,39 @@
-use std::sync::Arc;
+use std::{
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Instant,
+};
 
-use crate::auth::AuthRuntimeConfig;
-use openportio_core::AppState;
+use crate::{
+    api::{
+        api_error_to_grpc, validate_request_with_deps, AsyncRequestValidation, RequestValidation,
+        ValidationDeps,
+    },
+    audit::{AuditEvent, AuditEventKind, AuditLogger},
+    auth::{AuthRejection, AuthRuntimeConfig, CallPrincipal},
+    brute_force::AttemptGuard,
+    grpc_limits::{GrpcMessageConfig, GrpcServerSettings},
+    revocation::RevocationList,
+    tls::PeerIdentity,
+    trace_context::REQUEST_ID_HEADER,
+};
+use axum::{
+    body::{Body, Bytes},
+    extract::{ConnectInfo, MatchedPath, State},
+    middleware::{from_fn_with_state, Next},
+};
+use http::{HeaderMap, HeaderValue};
+use http_body::Frame;
+use openportio_core::{auth::AuthPrincipal, AppState, MetricsSink};
 use openportio_rpc::{
-    build_hello_response, Greeter, GreeterServer, HelloRequest, HelloResponse, FILE_DESCRIPTOR_SET,
+    build_hello_response, build_hello_stream, Greeter, GreeterServer, HelloRequest, HelloResponse,
+    HelloStreamRequest, ServiceRegistry, FILE_DESCRIPTOR_SET,
 };
 use tonic::service::Routes;
-use tonic::{service::interceptor::InterceptedService, Request, Response, Status};
+use tonic::{Code, Request, Response, Status};
+use tracing::Instrument;
 
 #[derive(Clone)]
 pub struct GreeterService {
@@ -29,39 +56,104 @@ impl Greeter for GreeterService {
             build_hello_response(&self.state, request.into_inner()).map_err(map_error)?;
         Ok(Response::new(response))
     }
+
+    type SayHelloStreamStream = tonic::codegen::BoxStream<HelloResponse>;
+
+    async fn say_hello_stream(
+        &self,
+        request: Request<HelloStreamRequest>,
+    ) -> Result<Response<Self::SayHelloStreamStream>, Status> {
+        let responses = build_hello_stream(&self.state, request.into_inner()).map_err(map_error)?;
+        Ok(Response::new(Box::pin(tonic::codegen::tokio_stream::iter(
+            responses.into_iter().map(Ok),
+        ))))
+    }
 }
 
-pub fn build_grpc_service(
-    state: Arc<AppState>,
-) -> InterceptedService<GreeterServer<GreeterService>, GrpcAuthInterceptor> {
-    build_grpc_service_with_auth(state, AuthRuntimeConfig::from_env())
+pub fn build_grpc_service(state: Arc<AppState>) -> GrpcAuthService<GreeterServer<GreeterService>> {
+    build_grpc_service_with_auth(
+        state,
+        AuthRuntimeConfig::or_reject_all(AuthRuntimeConfig::from_env()),
+    )
 }
 
 pub fn build_grpc_service_with_auth(
     state: Arc<AppState>,
     auth_cfg: AuthRuntimeConfig,
-) -> InterceptedService<GreeterServer<GreeterService>, GrpcAuthInterceptor> {
-    let service = GreeterServer::new(GreeterService::new(state));
-    InterceptedService::new(service, GrpcAuthInterceptor { auth_cfg })
+) -> GrpcAuthService<GreeterServer<GreeterService>> {
+    let service = crate::env::or_default(crate::middleware::grpc_messages_from_env())
+        .configure(GreeterServer::new(GreeterService::new(state)));
+    GrpcAuthService::new(service, GrpcAuthInterceptor { auth_cfg })
+}
+
+impl<T> GrpcServerSettings for GreeterServer<T> {
+    fn max_decoding_message_size(self, limit: usize) -> Self {
+        GreeterServer::max_decoding_message_size(self, limit)
+    }
+
+    fn max_encoding_message_size(self, limit: usize) -> Self {
+        GreeterServer::max_encoding_message_size(self, limit)
+    }
+
+    fn accept_compressed(self, encoding: crate::grpc_limits::CompressionEncoding) -> Self {
+        GreeterServer::accept_compressed(self, encoding)
+    }
+
+    fn send_compressed(self, encoding: crate::grpc_limits::CompressionEncoding) -> Self {
+        GreeterServer::send_compressed(self, encoding)
+    }
 }
 
 pub fn build_grpc_routes(state: Arc<AppState>) -> Routes {
-    build_grpc_routes_with_auth(state, AuthRuntimeConfig::from_env())
+    build_grpc_routes_with_auth(
+        state,
+        AuthRuntimeConfig::or_reject_all(AuthRuntimeConfig::from_env()),
+    )
 }
 
 pub fn build_grpc_routes_with_auth(state: Arc<AppState>, auth_cfg: AuthRuntimeConfig) -> Routes {
-    let reflection_v1 = tonic_reflection::server::Builder::configure()
-        .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
+    let metrics = state.metrics.clone();
+    with_grpc_observability(
+        build_grpc_routes_from_registry(default_service_registry(state, auth_cfg)),
+        metrics,
+    )
+}
+
+/// The built-in Greeter service with its descriptor set.
+pub fn default_service_registry(
+    state: Arc<AppState>,
+    auth_cfg: AuthRuntimeConfig,
+) -> ServiceRegistry {
+    ServiceRegistry::new()
+        .with_descriptor_set(FILE_DESCRIPTOR_SET)
+        .register(build_grpc_service_with_auth(state, auth_cfg))
+}
+
+/// Turns `registry` into routes, adding v1 and v1alpha reflection over its descriptor sets.
+pub fn build_grpc_routes_from_registry(registry: ServiceRegistry) -> Routes {
+    let (reflection_v1, reflection_v1alpha) = registry.descriptor_sets().iter().fold(
+        (
+            tonic_reflection::server::Builder::configure(),
+            tonic_reflection::server::Builder::configure(),
+        ),
+        |(v1, v1alpha), set| {
+            (
+                v1.register_encoded_file_descriptor_set(set),
+                v1alpha.register_encoded_file_descriptor_set(set),
+            )
+        },
+    );
+    let reflection_v1 = reflection_v1
         .build_v1()
         .expect("reflection service (v1) should build");
-    let reflection_v1alpha = tonic_reflection::server::Builder::configure()
-        .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
+    let reflection_v1alpha = reflection_v1alpha
         .build_v1alpha()
         .expect("reflection service (v1alpha) should build");
 
-    Routes::new(build_grpc_service_with_auth(state, auth_cfg))
-        .add_service(reflection_v1)
-        .add_service(reflection_v1alpha)
+    registry
+        .register(reflection_v1)
+        .register(reflection_v1alpha)
+        .into_routes()
         .prepare()
 }
 
@@ -69,29 +161,704 @@ fn map_error(err: openportio_core::OpenportioError) -> Status {
     crate::api::map_domain_error_to_grpc(err)
 }
 
+const GRPC_VALIDATION_SOURCE: &str = "message";
+
+/// A tonic request whose decoded message passed its [`RequestValidation`] rules (for prost
+/// types, derive `validator::Validate` through `tonic_build`'s `type_attribute` /
+/// `field_attribute`). Failures become `INVALID_ARGUMENT` whose details hold the REST
+/// `ApiErrorResponse` JSON, with issues located at `["message", field]`.
+#[derive(Debug)]
+pub struct ValidatedRequest<T>(Request<T>);
+
+impl<T: RequestValidation> ValidatedRequest<T> {
+    // `Status` is what tonic handlers return, so `?` works without conversion.
+    #[allow(clippy::result_large_err)]
+    pub fn new(request: Request<T>) -> Result<Self, Status> {
+        request
+            .get_ref()
+            .validate_request(GRPC_VALIDATION_SOURCE)
+            .map_err(api_error_to_grpc)?;
+        Ok(Self(request))
+    }
+}
+
+impl<T: AsyncRequestValidation> ValidatedRequest<T> {
+    /// Also runs [`AsyncRequestValidation`], resolving its dependencies from the call's
+    /// extensions like `GrpcContext`.
+    pub async fn new_async(request: Request<T>) -> Result<Self, Status> {
+        let deps = ValidationDeps::from_extensions(request.extensions());
+        validate_request_with_deps(request.get_ref(), GRPC_VALIDATION_SOURCE, &deps)
+            .await
+            .map_err(api_error_to_grpc)?;
+        Ok(Self(request))
+    }
+}
+
+impl<T> ValidatedRequest<T> {
+    pub fn get_ref(&self) -> &T {
+        self.0.get_ref()
+    }
+
+    pub fn metadata(&self) -> &tonic::metadata::MetadataMap {
+        self.0.metadata()
+    }
+
+    pub fn into_request(self) -> Request<T> {
+        self.0
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0.into_inner()
+    }
+}
+
+impl<T: RequestValidation> TryFrom<Request<T>> for ValidatedRequest<T> {
+    type Error = Status;
+
+    fn try_from(request: Request<T>) -> Result<Self, Self::Error> {
+        Self::new(request)
+    }
+}
+
+/// Bearer-token (or mTLS peer) authentication for gRPC services, applied by wrapping a server
+/// in [`GrpcAuthService`]. Revocation lookups are awaited, so a slow deny-list store only holds
+/// up the call that needs it.
 #[derive(Clone)]
 pub struct GrpcAuthInterceptor {
     auth_cfg: AuthRuntimeConfig,
 }
 
-impl tonic::service::Interceptor for GrpcAuthInterceptor {
-    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
-        if !self.auth_cfg.enabled {
-            return Ok(request);
+impl GrpcAuthInterceptor {
+    pub fn new(auth_cfg: AuthRuntimeConfig) -> Self {
+        Self { auth_cfg }
+    }
+
+    /// Verifies the call and stores the principal in its extensions. With a registered
+    /// `BruteForceProtection`, blocked callers are turned away with `RESOURCE_EXHAUSTED` before
+    /// their token is checked, and invalid tokens are counted and delayed as on REST routes.
+    pub async fn authorize(&self, parts: &mut http::request::Parts) -> Result<(), Status> {
+        // Already verified by the router auth middleware (`OpenportioServer::require_auth`).
+        if !self.auth_cfg.enabled || parts.extensions.get::<AuthPrincipal>().is_some() {
+            return Ok(());
+        }
+        let guard = AttemptGuard::for_grpc(parts);
+        if let Some(status) = guard
+            .as_ref()
+            .and_then(|guard| guard.blocked_status(&parts.extensions))
+        {
+            return Err(status);
+        }
+        let logger = AuditLogger::from_extensions(&parts.extensions);
+        let event = AuditEvent::from_extensions(AuditEventKind::LoginFailed, &parts.extensions)
+            .with_protocol("grpc");
+        let result = self.authenticate(parts).await;
+        match &result {
+            Ok(()) => logger.record(&AuditEvent {
+                kind: AuditEventKind::LoginSucceeded,
+                principal: parts
+                    .extensions
+                    .get::<AuthPrincipal>()
+                    .map(|principal| principal.subject.clone()),
+                ..event
+            }),
+            Err(rejection) => logger.record(&event.with_reason(rejection.reason())),
+        }
+        let delay = guard
+            .as_ref()
+            .map(|guard| guard.settle(&result, &parts.extensions))
+            .unwrap_or_default();
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        result.map_err(AuthRejection::into_grpc_status)
+    }
+
+    async fn authenticate(&self, parts: &mut http::request::Parts) -> Result<(), AuthRejection> {
+        if !parts.headers.contains_key(http::header::AUTHORIZATION) {
+            if let Some(principal) = PeerIdentity::principal_from_extensions(&parts.extensions) {
+                CallPrincipal::record(&parts.extensions, &principal);
+                parts.extensions.insert(principal);
+                return Ok(());
+            }
         }
 
-        let auth_value = request
-            .metadata()
-            .get("authorization")
-            .ok_or_else(|| Status::unauthenticated("missing bearer token"))?
+        let auth_value = parts
+            .headers
+            .get(http::header::AUTHORIZATION)
+            .ok_or(AuthRejection::MissingAuthorization)?
             .to_str()
-            .map_err(|_| Status::unauthenticated("authorization metadata is invalid"))?;
+            .map_err(|_| {
+                AuthRejection::InvalidToken("authorization metadata is invalid".to_string())
+            })?;
 
+        let revocation = self
+            .auth_cfg
+            .revocation_list()
+            .cloned()
+            .or_else(|| RevocationList::from_extensions(&parts.extensions));
+        let clock = self.auth_cfg.clock_for(&parts.extensions);
         let principal = self
             .auth_cfg
-            .authenticate_authorization_value_str(auth_value)
-            .map_err(|err| err.into_grpc_status())?;
-        request.extensions_mut().insert(principal);
-        Ok(request)
+            .authenticate_with_revocation(auth_value, revocation.as_ref(), &*clock)
+            .await?;
+        CallPrincipal::record(&parts.extensions, &principal);
+        parts.extensions.insert(principal);
+        Ok(())
+    }
+}
+
+/// A gRPC server behind a [`GrpcAuthInterceptor`]: unauthenticated calls get the interceptor's
+/// status without reaching the server.
+#[derive(Clone)]
+pub struct GrpcAuthService<S> {
+    inner: S,
+    interceptor: GrpcAuthInterceptor,
+}
+
+impl<S> GrpcAuthService<S> {
+    pub fn new(inner: S, interceptor: GrpcAuthInterceptor) -> Self {
+        Self { inner, interceptor }
+    }
+}
+
+impl<S, B> tower::Service<http::Request<B>> for GrpcAuthService<S>
+where
+    S: tower::Service<http::Request<B>, Response = http::Response<tonic::body::BoxBody>>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = futures_util::future::BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        // The readied service handles this call; the clone takes its place for the next one.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let interceptor = self.interceptor.clone();
+        Box::pin(async move {
+            let (mut parts, body) = request.into_parts();
+            match interceptor.authorize(&mut parts).await {
+                Ok(()) => inner.call(http::Request::from_parts(parts, body)).await,
+                Err(status) => Ok(status.into_http()),
+            }
+        })
+    }
+}
+
+impl<S: tonic::server::NamedService> tonic::server::NamedService for GrpcAuthService<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+/// A tonic service implementation that knows its generated server type; implemented by
+/// `#[grpc_service]` and mounted with `OpenportioServer::with_grpc`.
+pub trait GrpcService: Sized + Send + Sync + 'static {
+    type Server: tower::Service<
+            http::Request<tonic::body::BoxBody>,
+            Response = http::Response<tonic::body::BoxBody>,
+            Error = std::convert::Infallible,
+        > + tonic::server::NamedService
+        + Clone
+        + Send
+        + 'static;
+
+    /// Encoded `FileDescriptorSet` describing the service, for reflection and `describe()`.
+    const DESCRIPTOR_SET: Option<&'static [u8]> = None;
+
+    fn into_server(self) -> Self::Server;
+
+    /// Applies the builder's [`GrpcMessageConfig`] to the generated server. `#[grpc_service]`
+    /// forwards it to tonic's generated setters; the default leaves the server unchanged.
+    fn configure_server(server: Self::Server, config: &GrpcMessageConfig) -> Self::Server {
+        let _ = config;
+        server
+    }
+}
+
+/// Caller identity inside tonic service impls. `GrpcAuthInterceptor` and
+/// `OpenportioServer::require_auth` put the verified `AuthPrincipal` (or the mTLS peer identity)
+/// into the call's extensions; calls made with auth disabled carry none.
+pub trait GrpcPrincipalExt {
+    fn principal(&self) -> Option<&AuthPrincipal>;
+
+    /// Extensions of the call, used to record `scope_denied` audit events.
+    #[doc(hidden)]
+    fn call_extensions(&self) -> Option<&http::Extensions> {
+        None
+    }
+
+    #[doc(hidden)]
+    fn audit_scope_denied(&self, principal: &AuthPrincipal, reason: &str) {
+        if let Some(extensions) = self.call_extensions() {
+            AuditEvent::from_extensions(AuditEventKind::ScopeDenied, extensions)
+                .with_protocol("grpc")
+                .with_principal(principal.subject.clone())
+                .with_reason(reason)
+                .emit(extensions);
+        }
+    }
+
+    /// `UNAUTHENTICATED` when the call carries no principal.
+    #[allow(clippy::result_large_err)]
+    fn require_principal(&self) -> Result<&AuthPrincipal, Status> {
+        self.principal()
+            .ok_or_else(|| Status::unauthenticated("missing bearer token"))
+    }
+
+    /// `PERMISSION_DENIED` unless the principal holds every one of `scopes`.
+    #[allow(clippy::result_large_err)]
+    fn require_scopes(&self, scopes: &[&str]) -> Result<&AuthPrincipal, Status> {
+        let principal = self.require_principal()?;
+        let missing: Vec<&str> = scopes
+            .iter()
+            .copied()
+            .filter(|scope| !principal.scopes.iter().any(|held| held == scope))
+            .collect();
+        if !missing.is_empty() {
+            let status =
+                Status::permission_denied(format!("missing required scope: {}", missing.join(" ")));
+            self.audit_scope_denied(principal, status.message());
+            return Err(status);
+        }
+        Ok(principal)
+    }
+
+    /// `PERMISSION_DENIED` unless the principal holds at least one of `scopes`.
+    #[allow(clippy::result_large_err)]
+    fn require_any_scope(&self, scopes: &[&str]) -> Result<&AuthPrincipal, Status> {
+        let principal = self.require_principal()?;
+        if !scopes
+            .iter()
+            .any(|scope| principal.scopes.iter().any(|held| held == scope))
+        {
+            let status =
+                Status::permission_denied(format!("requires one of scopes: {}", scopes.join(" ")));
+            self.audit_scope_denied(principal, status.message());
+            return Err(status);
+        }
+        Ok(principal)
+    }
+}
+
+impl<T> GrpcPrincipalExt for Request<T> {
+    fn principal(&self) -> Option<&AuthPrincipal> {
+        self.extensions().get::<AuthPrincipal>()
+    }
+
+    fn call_extensions(&self) -> Option<&http::Extensions> {
+        Some(self.extensions())
+    }
+}
+
+impl<T> GrpcPrincipalExt for ValidatedRequest<T> {
+    fn principal(&self) -> Option<&AuthPrincipal> {
+        self.0.principal()
+    }
+
+    fn call_extensions(&self) -> Option<&http::Extensions> {
+        self.0.call_extensions()
+    }
+}
+
+/// Logs every gRPC call (method, status code, latency, peer, principal, request id) and records
+/// `grpc.requests` / `grpc.request.duration_seconds` through `metrics`. Calls to unregistered
+/// services or methods are labelled `unmatched`, so clients cannot add label values. Latency covers the full
+/// response stream, and the status comes from trailers. Nested applications are no-ops, so
+/// wrapping routes that already carry the layer does not double count.
+///
+/// Calls share the REST `x-request-id`: the id from the call metadata (or a new one) is put on
+/// a `grpc` span around the handler and echoed in the response metadata.
+pub fn with_grpc_observability(routes: Routes, metrics: Arc<dyn MetricsSink>) -> Routes {
+    Routes::from(
+        routes
+            .into_axum_router()
+            .layer(from_fn_with_state(metrics, grpc_observability_middleware)),
+    )
+}
+
+/// Label value for calls that reached no registered service or method.
+pub(crate) const UNMATCHED_LABEL: &str = "unmatched";
+
+/// Whether a call's method path may become a metric label: it was routed to a registered
+/// service and not answered `UNIMPLEMENTED`, tonic's reply to methods the service lacks. Anything
+/// else could be made up by the client.
+pub(crate) fn is_known_method(routed: bool, code: Option<Code>) -> bool {
+    routed && code != Some(Code::Unimplemented)
+}
+
+/// Marks calls already observed by an outer application of the layer.
+#[derive(Debug, Clone, Copy)]
+struct GrpcCallObserved;
+
+async fn grpc_observability_middleware(
+    State(metrics): State<Arc<dyn MetricsSink>>,
+    mut req: axum::extract::Request,
+    next: Next,
+) -> axum::response::Response {
+    let is_grpc = req
+        .headers()
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/grpc"));
+    if !is_grpc || req.extensions().get::<GrpcCallObserved>().is_some() {
+        return next.run(req).await;
+    }
+
+    req.extensions_mut().insert(GrpcCallObserved);
+    let request_id = ensure_request_id(req.headers_mut());
+    let principal = CallPrincipal::attach(req.extensions_mut());
+    let (service, method) = req
+        .uri()
+        .path()
+        .trim_start_matches('/')
+        .split_once('/')
+        .map(|(service, method)| (service.to_string(), method.to_string()))
+        .unwrap_or_else(|| (req.uri().path().to_string(), String::new()));
+    let span = tracing::info_span!(
+        "grpc",
+        grpc.service = %service,
+        grpc.method = %method,
+        request_id = %request_id.to_str().unwrap_or("-"),
+    );
+    let call = GrpcCall {
+        metrics,
+        routed: req.extensions().get::<MatchedPath>().is_some(),
+        service,
+        method,
+        request_id: request_id.clone(),
+        peer: req
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| *addr),
+        principal,
+        started: Instant::now(),
+    };
+
+    let mut response = next.run(req).instrument(span).await;
+    response
+        .headers_mut()
+        .entry(REQUEST_ID_HEADER)
+        .or_insert(request_id);
+    // Trailers-only responses (most errors) carry the status in the headers.
+    if let Some(code) = grpc_status(response.headers()) {
+        call.finish(code);
+        return response;
+    }
+    response.map(|body| {
+        Body::new(ObservedBody {
+            inner: body,
+            call: Some(call),
+        })
+    })
+}
+
+struct GrpcCall {
+    metrics: Arc<dyn MetricsSink>,
+    routed: bool,
+    service: String,
+    method: String,
+    request_id: HeaderValue,
+    peer: Option<SocketAddr>,
+    principal: CallPrincipal,
+    started: Instant,
+}
+
+/// The call's `x-request-id`, generated (as the shared middleware does for REST) when the
+/// client sent none.
+fn ensure_request_id(headers: &mut HeaderMap) -> HeaderValue {
+    headers
+        .entry(REQUEST_ID_HEADER)
+        .or_insert_with(|| {
+            HeaderValue::from_str(&uuid::Uuid::new_v4().to_string())
+                .expect("a uuid is a valid header value")
+        })
+        .clone()
+}
+
+impl GrpcCall {
+    fn finish(self, code: Code) {
+        let elapsed = self.started.elapsed();
+        let code_name = format!("{code:?}");
+        let principal = self.principal.get();
+        let peer = self.peer.map(|peer| peer.to_string());
+        tracing::info!(
+            grpc.service = %self.service,
+            grpc.method = %self.method,
+            grpc.code = %code_name,
+            latency_ms = elapsed.as_millis() as u64,
+            peer = peer.as_deref().unwrap_or("-"),
+            principal = principal.as_deref().unwrap_or("-"),
+            request_id = self.request_id.to_str().unwrap_or("-"),
+            "grpc call finished"
+        );
+        let service = match self.routed {
+            true => self.service.as_str(),
+            false => UNMATCHED_LABEL,
+        };
+        let method = match is_known_method(self.routed, Some(code)) {
+            true => self.method.as_str(),
+            false => UNMATCHED_LABEL,
+        };
+        let labels = [
+            ("service", service),
+            ("method", method),
+            ("code", code_name.as_str()),
+        ];
+        self.metrics.incr_counter_with("grpc.requests", &labels);
+        self.metrics.observe_histogram(
+            "grpc.request.duration_seconds",
+            elapsed.as_secs_f64(),
+            &labels,
+        );
+    }
+}
+
+pub(crate) fn grpc_status(headers: &HeaderMap) -> Option<Code> {
+    headers
+        .get("grpc-status")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<i32>().ok())
+        .map(Code::from_i32)
+}
+
+struct ObservedBody {
+    inner: Body,
+    call: Option<GrpcCall>,
+}
+
+impl http_body::Body for ObservedBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let polled = Pin::new(&mut self.inner).poll_frame(cx);
+        match &polled {
+            Poll::Ready(Some(Ok(frame))) => {
+                if let Some(code) = frame.trailers_ref().and_then(grpc_status) {
+                    if let Some(call) = self.call.take() {
+                        call.finish(code);
+                    }
+                }
+            }
+            Poll::Ready(Some(Err(_))) => {
+                if let Some(call) = self.call.take() {
+                    call.finish(Code::Internal);
+                }
+            }
+            Poll::Ready(None) => {
+                if let Some(call) = self.call.take() {
+                    call.finish(Code::Unknown);
+                }
+            }
+            Poll::Pending => {}
+        }
+        polled
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl Drop for ObservedBody {
+    fn drop(&mut self) {
+        // The client went away before trailers were sent.
+        if let Some(call) = self.call.take() {
+            call.finish(Code::Cancelled);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower::util::ServiceExt;
+    use validator::Validate;
+
+    #[derive(Debug, Validate, crate::RequestValidation)]
+    struct CreateNote {
+        #[validate(length(min = 1, message = "title must not be empty"))]
+        title: String,
+    }
+
+    #[test]
+    fn invalid_messages_become_invalid_argument_with_bad_request_details() {
+        let status = ValidatedRequest::new(Request::new(CreateNote {
+            title: String::new(),
+        }))
+        .expect_err("empty title is invalid");
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(status.message(), "request validation failed");
+        let details = openportio_rpc::ErrorDetails::from_status(&status).unwrap();
+        let violation = &details.bad_request.unwrap().field_violations[0];
+        assert_eq!(violation.field, "title");
+        assert_eq!(violation.description, "title must not be empty");
+        let info = details.error_info.unwrap();
+        assert_eq!(info.reason, "VALIDATION_ERROR");
+        assert_eq!(info.domain, "openportio");
+
+        let request = ValidatedRequest::try_from(Request::new(CreateNote {
+            title: "groceries".to_string(),
+        }))
+        .expect("non-empty title is valid");
+        assert_eq!(request.into_inner().title, "groceries");
+    }
+
+    #[tokio::test]
+    async fn repeated_bad_tokens_lock_the_grpc_caller_out() {
+        let mut cfg = AuthRuntimeConfig::default();
+        cfg.enabled = true;
+        cfg.jwt_secret = Some("guess-me".to_string());
+        let protection = crate::brute_force::BruteForceProtection::new(
+            crate::brute_force::BruteForcePolicy::new()
+                .with_block(2, std::time::Duration::from_secs(60)),
+        );
+        let overrides = crate::di::DependencyOverrides::default().with(protection.clone());
+        let call = |token: &str| {
+            http::Request::builder()
+                .uri("/notes.Notes/List")
+                .header("content-type", "application/grpc")
+                .header("authorization", format!("Bearer {token}"))
+                .extension(overrides.clone())
+                .extension(crate::client_ip::ClientIp("198.51.100.7".parse().unwrap()))
+                .body(())
+                .unwrap()
+                .into_parts()
+                .0
+        };
+        let interceptor = GrpcAuthInterceptor::new(cfg);
+
+        for _ in 0..2 {
+            let status = interceptor
+                .authorize(&mut call("not-a-jwt"))
+                .await
+                .unwrap_err();
+            assert_eq!(status.code(), Code::Unauthenticated);
+        }
+        let token = jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &serde_json::json!({ "sub": "ana", "exp": 4_102_444_800u64 }),
+            &jsonwebtoken::EncodingKey::from_secret(b"guess-me"),
+        )
+        .unwrap();
+        let status = interceptor.authorize(&mut call(&token)).await.unwrap_err();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+
+        protection.reset("ip:198.51.100.7").unwrap();
+        interceptor.authorize(&mut call(&token)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn interceptor_exposes_the_verified_principal_and_scope_checks() {
+        let mut cfg = AuthRuntimeConfig::default();
+        cfg.enabled = true;
+        cfg.jwt_secret = Some("dev-secret".to_string());
+        let token = jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &serde_json::json!({"sub": "user-1", "exp": 4_102_444_800u64, "scope": "notes:read"}),
+            &jsonwebtoken::EncodingKey::from_secret(b"dev-secret"),
+        )
+        .unwrap();
+        #[derive(Clone, Default)]
+        struct Recorder(Arc<std::sync::Mutex<Vec<AuditEvent>>>);
+        impl crate::audit::AuditLog for Recorder {
+            fn record(&self, event: &AuditEvent) {
+                self.0.lock().unwrap().push(event.clone());
+            }
+        }
+        let recorder = Recorder::default();
+        let (mut parts, ()) = http::Request::builder()
+            .header("authorization", format!("Bearer {token}"))
+            .extension(
+                crate::di::DependencyOverrides::default().with(AuditLogger::new(recorder.clone())),
+            )
+            .body(())
+            .unwrap()
+            .into_parts();
+        GrpcAuthInterceptor::new(cfg)
+            .authorize(&mut parts)
+            .await
+            .unwrap();
+        let request = Request::from_http(http::Request::from_parts(parts, ()));
+        assert_eq!(request.require_principal().unwrap().subject, "user-1");
+        assert!(request.require_scopes(&["notes:read"]).is_ok());
+        assert!(request
+            .require_any_scope(&["notes:write", "notes:read"])
+            .is_ok());
+        let denied = request
+            .require_scopes(&["notes:read", "notes:write"])
+            .unwrap_err();
+        assert_eq!(denied.code(), Code::PermissionDenied);
+        assert_eq!(denied.message(), "missing required scope: notes:write");
+        let events = recorder.0.lock().unwrap().clone();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].kind, AuditEventKind::LoginSucceeded);
+        assert_eq!(events[1].kind, AuditEventKind::ScopeDenied);
+        assert_eq!(events[1].protocol, "grpc");
+        assert_eq!(events[1].principal.as_deref(), Some("user-1"));
+        assert_eq!(
+            events[1].reason.as_deref(),
+            Some("missing required scope: notes:write")
+        );
+
+        let anonymous = Request::new(());
+        assert!(anonymous.principal().is_none());
+        assert_eq!(
+            anonymous
+                .require_scopes(&["notes:read"])
+                .unwrap_err()
+                .code(),
+            Code::Unauthenticated
+        );
+    }
+
+    #[tokio::test]
+    async fn grpc_calls_share_and_echo_the_request_id() {
+        async fn call(headers: HeaderMap) -> ([(&'static str, HeaderValue); 2], ()) {
+            let seen = headers[REQUEST_ID_HEADER].clone();
+            (
+                [
+                    ("grpc-status", HeaderValue::from_static("0")),
+                    ("seen-id", seen),
+                ],
+                (),
+            )
+        }
+        let app = with_grpc_observability(
+            Routes::from(axum::Router::new().route("/pkg.Svc/Call", axum::routing::post(call))),
+            Arc::new(openportio_core::NoopMetrics),
+        )
+        .into_axum_router();
+        let request = |request_id: Option<&str>| {
+            let mut request =
+                http::Request::post("/pkg.Svc/Call").header("content-type", "application/grpc");
+            if let Some(request_id) = request_id {
+                request = request.header(REQUEST_ID_HEADER, request_id);
+            }
+            request.body(Body::empty()).unwrap()
+        };
+
+        let response = app.clone().oneshot(request(Some("req-7"))).await.unwrap();
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "req-7");
+        assert_eq!(response.headers()["seen-id"], "req-7");
+
+        let response = app.oneshot(request(None)).await.unwrap();
+        let generated = &response.headers()[REQUEST_ID_HEADER];
+        assert_eq!(generated.len(), 36);
+        assert_eq!(&response.headers()["seen-id"], generated);
     }
 }
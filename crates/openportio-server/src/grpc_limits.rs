@@ -0,0 +1,297 @@
+use std::{fmt, str::FromStr};
+
+pub use tonic::codec::CompressionEncoding;
+
+/// tonic's default decoding limit, made explicit.
+pub const DEFAULT_MAX_DECODING_MESSAGE_SIZE: usize = 4 * 1024 * 1024;
+
+/// Per-message limits and compression for tonic generated servers. Services mounted with
+/// `OpenportioServer::with_grpc` and the built-in Greeter are configured from it; ready-made
+/// servers passed to `with_grpc_service`, `with_grpc_routes` or a registry go through
+/// [`Self::configure`] (or tonic's own setters) before they are registered. Oversized messages
+/// fail with tonic's `OUT_OF_RANGE`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrpcMessageConfig {
+    pub max_decoding_message_size: usize,
+    /// `usize::MAX` (the default) leaves responses unchecked, like tonic.
+    pub max_encoding_message_size: usize,
+    /// Request encodings the server decompresses. Empty (the default) rejects compressed
+    /// requests with `UNIMPLEMENTED`. tonic checks the decoding limit against the compressed
+    /// message and inflates it without a bound, so only accept compression from trusted clients.
+    pub accept_compression: Vec<GrpcCompression>,
+    /// Response encodings used when the client lists them in `grpc-accept-encoding`.
+    pub send_compression: Vec<GrpcCompression>,
+}
+
+impl Default for GrpcMessageConfig {
+    fn default() -> Self {
+        Self {
+            max_decoding_message_size: DEFAULT_MAX_DECODING_MESSAGE_SIZE,
+            max_encoding_message_size: usize::MAX,
+            accept_compression: Vec::new(),
+            send_compression: Vec::new(),
+        }
+    }
+}
+
+impl GrpcMessageConfig {
+    /// Parses a comma-separated encoding list such as `gzip,zstd`; unknown entries are logged
+    /// and skipped.
+    pub fn parse_compression(value: &str) -> Vec<GrpcCompression> {
+        let mut encodings = Vec::new();
+        for name in value
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            match name.parse::<GrpcCompression>() {
+                Ok(encoding) if !encodings.contains(&encoding) => encodings.push(encoding),
+                Ok(_) => {}
+                Err(err) => tracing::warn!(encoding = name, "{err}"),
+            }
+        }
+        encodings
+    }
+
+    /// `max_decoding=..., max_encoding=..., accept=..., send=...` for startup diagnostics.
+    pub fn summary(&self) -> String {
+        let encoding = match self.max_encoding_message_size {
+            usize::MAX => "unlimited".to_string(),
+            limit => limit.to_string(),
+        };
+        format!(
+            "max_decoding={}, max_encoding={encoding}, accept={}, send={}",
+            self.max_decoding_message_size,
+            encoding_list(&self.accept_compression),
+            encoding_list(&self.send_compression)
+        )
+    }
+
+    /// Applies the limits and encodings to a tonic generated server.
+    pub fn configure<S: GrpcServerSettings>(&self, server: S) -> S {
+        let server = server
+            .max_decoding_message_size(self.max_decoding_message_size)
+            .max_encoding_message_size(self.max_encoding_message_size);
+        let server = self
+            .accept_compression
+            .iter()
+            .fold(server, |server, encoding| {
+                server.accept_compressed(encoding.encoding())
+            });
+        self.send_compression
+            .iter()
+            .fold(server, |server, encoding| {
+                server.send_compressed(encoding.encoding())
+            })
+    }
+}
+
+fn encoding_list(encodings: &[GrpcCompression]) -> String {
+    match encodings {
+        [] => "none".to_string(),
+        encodings => encodings
+            .iter()
+            .map(|encoding| encoding.as_str())
+            .collect::<Vec<_>>()
+            .join(","),
+    }
+}
+
+/// The message settings tonic generates on every `*Server` type, for
+/// [`GrpcMessageConfig::configure`]. Implement it for a generated server in its own crate by
+/// forwarding each method to the inherent one of the same name; `#[grpc_service]` services
+/// don't need it.
+pub trait GrpcServerSettings: Sized {
+    fn max_decoding_message_size(self, limit: usize) -> Self;
+    fn max_encoding_message_size(self, limit: usize) -> Self;
+    fn accept_compressed(self, encoding: CompressionEncoding) -> Self;
+    fn send_compressed(self, encoding: CompressionEncoding) -> Self;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrpcCompression {
+    Gzip,
+    Zstd,
+}
+
+impl GrpcCompression {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    pub fn encoding(self) -> CompressionEncoding {
+        match self {
+            Self::Gzip => CompressionEncoding::Gzip,
+            Self::Zstd => CompressionEncoding::Zstd,
+        }
+    }
+}
+
+impl FromStr for GrpcCompression {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "gzip" => Ok(Self::Gzip),
+            "zstd" => Ok(Self::Zstd),
+            other => Err(format!("unsupported grpc compression encoding `{other}`")),
+        }
+    }
+}
+
+impl fmt::Display for GrpcCompression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{future::poll_fn, io::Write, pin::Pin, sync::Arc};
+
+    use super::*;
+    use crate::{
+        auth::AuthRuntimeConfig,
+        grpc::{GreeterService, GrpcAuthInterceptor, GrpcAuthService},
+    };
+    use axum::body::Body;
+    use flate2::{write::GzEncoder, Compression};
+    use http::{HeaderMap, Request};
+    use http_body::Body as _;
+    use openportio_core::AppState;
+    use openportio_rpc::{GreeterServer, ServiceRegistry};
+    use tower::ServiceExt;
+
+    fn greeter(config: GrpcMessageConfig) -> axum::Router {
+        let mut auth = AuthRuntimeConfig::default();
+        auth.enabled = false;
+        let server = config.configure(GreeterServer::new(GreeterService::new(Arc::new(
+            AppState::local("grpc-limits"),
+        ))));
+        crate::grpc::build_grpc_routes_from_registry(
+            ServiceRegistry::new()
+                .register(GrpcAuthService::new(server, GrpcAuthInterceptor::new(auth))),
+        )
+        .into_axum_router()
+    }
+
+    fn frame(compressed: bool, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![u8::from(compressed)];
+        frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    /// `HelloRequest { name }` encoded by hand (field 1, length-delimited).
+    fn hello(name: &str) -> Vec<u8> {
+        let mut message = vec![0x0a, name.len() as u8];
+        message.extend_from_slice(name.as_bytes());
+        message
+    }
+
+    fn gzip(message: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(message).unwrap();
+        frame(true, &encoder.finish().unwrap())
+    }
+
+    async fn say_hello(
+        app: axum::Router,
+        body: Vec<u8>,
+        headers: &[(&'static str, &'static str)],
+    ) -> HeaderMap {
+        let mut request = Request::builder()
+            .method("POST")
+            .uri("/openportio.v1.Greeter/SayHello")
+            .header("content-type", "application/grpc")
+            .header("te", "trailers");
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let response = app
+            .oneshot(request.body(Body::from(body)).unwrap())
+            .await
+            .unwrap();
+        let mut headers = response.headers().clone();
+        let mut body = response.into_body();
+        while let Some(frame) = poll_fn(|cx| Pin::new(&mut body).poll_frame(cx)).await {
+            if let Ok(trailers) = frame.unwrap().into_trailers() {
+                headers.extend(trailers);
+            }
+        }
+        headers
+    }
+
+    #[tokio::test]
+    async fn oversized_messages_fail_with_out_of_range() {
+        let app = greeter(GrpcMessageConfig {
+            max_decoding_message_size: 16,
+            ..GrpcMessageConfig::default()
+        });
+        let ok = say_hello(app.clone(), frame(false, &hello("Rust")), &[]).await;
+        assert_eq!(ok["grpc-status"], "0");
+
+        let large = say_hello(app, frame(false, &hello(&"x".repeat(40))), &[]).await;
+        assert_eq!(large["grpc-status"], "11");
+
+        let app = greeter(GrpcMessageConfig {
+            max_encoding_message_size: 4,
+            ..GrpcMessageConfig::default()
+        });
+        let response = say_hello(app, frame(false, &hello("Rust")), &[]).await;
+        assert_eq!(response["grpc-status"], "11");
+    }
+
+    #[tokio::test]
+    async fn configured_encodings_are_accepted_and_sent() {
+        let gzipped = gzip(&hello("Rust"));
+        let rejected = say_hello(
+            greeter(GrpcMessageConfig::default()),
+            gzipped.clone(),
+            &[("grpc-encoding", "gzip")],
+        )
+        .await;
+        assert_eq!(rejected["grpc-status"], "12");
+
+        let app = greeter(GrpcMessageConfig {
+            accept_compression: vec![GrpcCompression::Gzip],
+            send_compression: vec![GrpcCompression::Zstd],
+            ..GrpcMessageConfig::default()
+        });
+        let accepted = say_hello(app.clone(), gzipped, &[("grpc-encoding", "gzip")]).await;
+        assert_eq!(accepted["grpc-status"], "0");
+        assert!(accepted.get("grpc-encoding").is_none());
+
+        let compressed = say_hello(
+            app,
+            frame(false, &hello("Rust")),
+            &[("grpc-accept-encoding", "zstd")],
+        )
+        .await;
+        assert_eq!(compressed["grpc-status"], "0");
+        assert_eq!(compressed["grpc-encoding"], "zstd");
+
+        let app = greeter(GrpcMessageConfig {
+            max_decoding_message_size: 16,
+            accept_compression: vec![GrpcCompression::Gzip],
+            ..GrpcMessageConfig::default()
+        });
+        let response = say_hello(app, gzip(&hello("Rust")), &[("grpc-encoding", "gzip")]).await;
+        assert_eq!(response["grpc-status"], "11");
+    }
+
+    #[test]
+    fn parses_compression_lists() {
+        assert_eq!(
+            GrpcMessageConfig::parse_compression("gzip, zstd,GZIP, br"),
+            vec![GrpcCompression::Gzip, GrpcCompression::Zstd]
+        );
+        assert_eq!(
+            GrpcMessageConfig::default().summary(),
+            "max_decoding=4194304, max_encoding=unlimited, accept=none, send=none"
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::StatusCode,
+    middleware::{from_fn_with_state, Next},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use openportio_core::MetricsSink;
+
+use crate::{api::ApiErrorResponse, context::RequestContext, middleware::is_grpc_request};
+
+/// How long handlers may run before they are cancelled, separate from the total request timeout
+/// (`MiddlewareConfig::timeout_seconds`) that also covers middleware, queueing and body reads.
+/// Routes are REST templates as registered (`/reports/:id`) or gRPC method paths
+/// (`/pkg.Service/Method`); other routes use the default, if any.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HandlerTimeouts {
+    default: Option<Duration>,
+    routes: BTreeMap<String, Duration>,
+}
+
+impl HandlerTimeouts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_default(mut self, timeout: Duration) -> Self {
+        self.default = Some(timeout);
+        self
+    }
+
+    pub fn with_route(mut self, route: impl Into<String>, timeout: Duration) -> Self {
+        self.routes.insert(route.into(), timeout);
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.default.is_some() || !self.routes.is_empty()
+    }
+
+    pub fn default_timeout(&self) -> Option<Duration> {
+        self.default
+    }
+
+    pub fn timeout_for(&self, route: &str) -> Option<Duration> {
+        self.routes.get(route).copied().or(self.default)
+    }
+
+    /// The longest configured timeout.
+    pub(crate) fn longest(&self) -> Option<Duration> {
+        self.routes.values().copied().chain(self.default).max()
+    }
+
+    pub(crate) fn summary(&self) -> String {
+        match (self.default, self.routes.len()) {
+            (None, 0) => "disabled".to_string(),
+            (Some(default), 0) => format!("{}ms", default.as_millis()),
+            (Some(default), routes) => {
+                format!("{}ms, {routes} route overrides", default.as_millis())
+            }
+            (None, routes) => format!("{routes} routes"),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct TimeoutState {
+    timeouts: Arc<HandlerTimeouts>,
+    metrics: Arc<dyn MetricsSink>,
+}
+
+/// Cancels handlers that run longer than their [`HandlerTimeouts`] entry and answers
+/// `504 handler_timeout` (REST) or `DEADLINE_EXCEEDED` (gRPC); each one is counted as
+/// `handler_timeouts` (labels `protocol`, `route`). The request's
+/// `RequestContext::cancellation()` is cancelled too, so spawned work stops with the handler.
+///
+/// Only the time until the response head counts: SSE, WebSocket and gRPC streams run on after
+/// their handler returned and are governed by their own keep-alive and idle settings.
+pub fn with_handler_timeouts(
+    router: Router,
+    timeouts: HandlerTimeouts,
+    metrics: Arc<dyn MetricsSink>,
+) -> Router {
+    // `route_layer` panics on a router without routes.
+    if !timeouts.is_enabled() || !router.has_routes() {
+        return router;
+    }
+    router.route_layer(from_fn_with_state(
+        TimeoutState {
+            timeouts: Arc::new(timeouts),
+            metrics,
+        },
+        handler_timeout_middleware,
+    ))
+}
+
+async fn handler_timeout_middleware(
+    State(state): State<TimeoutState>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let is_grpc = is_grpc_request(req.headers());
+    let route = if is_grpc {
+        req.uri().path().to_string()
+    } else {
+        req.extensions()
+            .get::<MatchedPath>()
+            .map(|path| path.as_str().to_string())
+            .unwrap_or_else(|| req.uri().path().to_string())
+    };
+    let Some(timeout) = state.timeouts.timeout_for(&route) else {
+        return next.run(req).await;
+    };
+    let cancellation = req
+        .extensions()
+        .get::<RequestContext>()
+        .map(RequestContext::cancellation);
+
+    match tokio::time::timeout(timeout, next.run(req)).await {
+        Ok(response) => response,
+        Err(_) => {
+            if let Some(cancellation) = cancellation {
+                cancellation.cancel();
+            }
+            let protocol = if is_grpc { "grpc" } else { "rest" };
+            tracing::warn!(
+                protocol,
+                route = %route,
+                timeout_ms = timeout.as_millis() as u64,
+                "handler timed out"
+            );
+            state.metrics.incr_counter_with(
+                "handler_timeouts",
+                &[("protocol", protocol), ("route", route.as_str())],
+            );
+            let message = format!("handler did not finish within {}ms", timeout.as_millis());
+            if is_grpc {
+                tonic::Status::deadline_exceeded(message)
+                    .into_http()
+                    .map(axum::body::Body::new)
+            } else {
+                (
+                    StatusCode::GATEWAY_TIMEOUT,
+                    Json(ApiErrorResponse {
+                        code: "handler_timeout".to_string(),
+                        message,
+                        detail: None,
+                        details: Some(
+                            serde_json::json!({ "timeout_ms": timeout.as_millis() as u64 }),
+                        ),
+                    }),
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::{to_bytes, Body},
+        routing::{get, post},
+    };
+    use openportio_test::RecordingMetrics;
+    use tower::util::ServiceExt;
+
+    async fn sleepy() -> &'static str {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        "done"
+    }
+
+    #[tokio::test]
+    async fn slow_handlers_are_cut_off_per_route() {
+        let metrics = Arc::new(RecordingMetrics::default());
+        let app = with_handler_timeouts(
+            Router::new()
+                .route("/reports/:id", get(sleepy))
+                .route("/exports", get(sleepy))
+                .route("/pkg.Svc/Call", post(sleepy)),
+            HandlerTimeouts::new()
+                .with_default(Duration::from_millis(20))
+                .with_route("/exports", Duration::from_secs(5)),
+            metrics.clone(),
+        );
+        let call = |request: Request| app.clone().oneshot(request);
+
+        let response = call(Request::get("/reports/7").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        let body: ApiErrorResponse =
+            serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap())
+                .unwrap();
+        assert_eq!(body.code, "handler_timeout");
+        assert_eq!(body.details.unwrap()["timeout_ms"], 20);
+
+        let response = call(Request::get("/exports").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = call(
+            Request::post("/pkg.Svc/Call")
+                .header("content-type", "application/grpc")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.headers()["grpc-status"], "4");
+
+        assert_eq!(
+            metrics.counters(),
+            [
+                "handler_timeouts{protocol=rest,route=/reports/:id}",
+                "handler_timeouts{protocol=grpc,route=/pkg.Svc/Call}",
+            ]
+        );
+    }
+}
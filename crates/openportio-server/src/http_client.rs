@@ -0,0 +1,556 @@
+use std::{
+    fmt,
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
+};
+
+use axum::extract::FromRef;
+use http::{header, HeaderValue, Method, StatusCode};
+use openportio_core::{
+    hedge::{hedge, HedgePolicy},
+    retry::{with_retry_by, RetryError, RetryPolicy, Retryability},
+    AppState, MetricsSink,
+};
+
+use crate::trace_context::{
+    TraceContext, REQUEST_ID_HEADER, TRACEPARENT_HEADER, TRACESTATE_HEADER,
+};
+
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+const DEFAULT_POOL_MAX_IDLE_PER_HOST: usize = 32;
+
+/// Settings shared by every [`HttpClient`] a factory hands out.
+#[derive(Debug, Clone)]
+pub struct HttpClientConfig {
+    pub connect_timeout: Duration,
+    /// Whole-request timeout, per attempt.
+    pub timeout: Duration,
+    pub pool_idle_timeout: Duration,
+    pub pool_max_idle_per_host: usize,
+    pub user_agent: String,
+    /// Applied to idempotent requests only (`GET`, `HEAD`, `PUT`, `DELETE`, `OPTIONS`).
+    pub retry: RetryPolicy,
+    /// Backup attempts for slow `GET` / `HEAD` / `OPTIONS` requests; off by default.
+    pub hedge: Option<HedgePolicy>,
+    /// Send `traceparent`, `tracestate` and `x-request-id` from the request being served.
+    pub propagate_trace: bool,
+}
+
+impl Default for HttpClientConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            timeout: DEFAULT_TIMEOUT,
+            pool_idle_timeout: DEFAULT_POOL_IDLE_TIMEOUT,
+            pool_max_idle_per_host: DEFAULT_POOL_MAX_IDLE_PER_HOST,
+            user_agent: format!("openportio/{}", env!("CARGO_PKG_VERSION")),
+            retry: RetryPolicy::new(),
+            hedge: None,
+            propagate_trace: true,
+        }
+    }
+}
+
+impl HttpClientConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Defaults overridden by `OPENPORTIO_HTTP_CLIENT_CONNECT_TIMEOUT_SECS`,
+    /// `OPENPORTIO_HTTP_CLIENT_TIMEOUT_SECS` and `OPENPORTIO_HTTP_CLIENT_MAX_ATTEMPTS`.
+    pub fn from_env() -> Result<Self, String> {
+        let defaults = Self::default();
+        let secs = |suffix: &str, default: Duration| {
+            Ok::<_, String>(
+                crate::env::setting::<u64>(suffix)?
+                    .filter(|secs| *secs > 0)
+                    .map(Duration::from_secs)
+                    .unwrap_or(default),
+            )
+        };
+        let retry = match crate::env::setting::<u32>("HTTP_CLIENT_MAX_ATTEMPTS")? {
+            Some(attempts) => defaults.retry.clone().with_max_attempts(attempts),
+            None => defaults.retry.clone(),
+        };
+        Ok(Self {
+            connect_timeout: secs("HTTP_CLIENT_CONNECT_TIMEOUT_SECS", defaults.connect_timeout)?,
+            timeout: secs("HTTP_CLIENT_TIMEOUT_SECS", defaults.timeout)?,
+            retry,
+            ..defaults
+        })
+    }
+
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_pool(mut self, idle_timeout: Duration, max_idle_per_host: usize) -> Self {
+        self.pool_idle_timeout = idle_timeout;
+        self.pool_max_idle_per_host = max_idle_per_host;
+        self
+    }
+
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Hedges safe requests with a cloneable body (see [`hedge`]). Every client of the factory
+    /// shares `policy`'s latency percentile, so give each downstream its own factory.
+    pub fn with_hedging(mut self, policy: HedgePolicy) -> Self {
+        self.hedge = Some(policy);
+        self
+    }
+
+    pub fn without_trace_propagation(mut self) -> Self {
+        self.propagate_trace = false;
+        self
+    }
+}
+
+/// Owns one connection pool and hands out named [`HttpClient`]s over it. Injected by
+/// `OpenportioServer` (replace it with `with_http_client_factory`); extract it with
+/// `Depends<HttpClientFactory>`.
+#[derive(Clone)]
+pub struct HttpClientFactory {
+    client: reqwest::Client,
+    config: Arc<HttpClientConfig>,
+    metrics: Option<Arc<dyn MetricsSink>>,
+}
+
+impl HttpClientFactory {
+    /// Panics, like `reqwest::Client::new`, if the TLS backend cannot be initialized; see
+    /// [`Self::try_new`].
+    pub fn new(config: HttpClientConfig) -> Self {
+        Self::try_new(config).expect("http client factory should build")
+    }
+
+    pub fn try_new(config: HttpClientConfig) -> Result<Self, reqwest::Error> {
+        let client = reqwest::Client::builder()
+            .connect_timeout(config.connect_timeout)
+            .timeout(config.timeout)
+            .pool_idle_timeout(config.pool_idle_timeout)
+            .pool_max_idle_per_host(config.pool_max_idle_per_host)
+            .user_agent(config.user_agent.clone())
+            .build()?;
+        Ok(Self {
+            client,
+            config: Arc::new(config),
+            metrics: None,
+        })
+    }
+
+    pub fn from_env() -> Result<Self, String> {
+        HttpClientConfig::from_env().map(Self::new)
+    }
+
+    /// Records `http_client.requests`, `http_client.retries`, `http_client.hedges` and
+    /// `http_client.duration_ms`.
+    pub fn with_metrics(mut self, metrics: Arc<dyn MetricsSink>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn config(&self) -> &HttpClientConfig {
+        &self.config
+    }
+
+    /// A client whose metrics carry `client=<name>` (`"payments"`, `"geo-api"`).
+    pub fn client(&self, name: impl Into<String>) -> HttpClient {
+        HttpClient {
+            name: name.into().into(),
+            factory: self.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for HttpClientFactory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpClientFactory")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Used when the router was not built by `OpenportioServer`: a process-wide factory from
+/// [`HttpClientConfig::from_env`], reporting to the state's metrics.
+impl FromRef<Arc<AppState>> for HttpClientFactory {
+    fn from_ref(state: &Arc<AppState>) -> Self {
+        static FALLBACK: OnceLock<HttpClientFactory> = OnceLock::new();
+        state.service::<Self>().unwrap_or_else(|| {
+            FALLBACK
+                .get_or_init(|| Self::new(crate::env::or_default(HttpClientConfig::from_env())))
+                .clone()
+                .with_metrics(state.metrics.clone())
+        })
+    }
+}
+
+/// A named handle on the factory's pool. Build requests with [`Self::get`] /
+/// [`Self::request`] and send them with [`Self::send`] for retries, trace propagation and
+/// metrics; [`Self::inner`] is the plain `reqwest::Client`.
+#[derive(Clone)]
+pub struct HttpClient {
+    name: Arc<str>,
+    factory: HttpClientFactory,
+}
+
+impl HttpClient {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn inner(&self) -> &reqwest::Client {
+        &self.factory.client
+    }
+
+    pub fn get(&self, url: impl reqwest::IntoUrl) -> reqwest::RequestBuilder {
+        self.factory.client.get(url)
+    }
+
+    pub fn post(&self, url: impl reqwest::IntoUrl) -> reqwest::RequestBuilder {
+        self.factory.client.post(url)
+    }
+
+    pub fn request(&self, method: Method, url: impl reqwest::IntoUrl) -> reqwest::RequestBuilder {
+        self.factory.client.request(method, url)
+    }
+
+    /// Sends `request`. Idempotent requests with a cloneable body are retried on connect
+    /// errors, timeouts and `429` / `502` / `503` / `504` (honouring `Retry-After`); when the
+    /// retries run out, the last response is returned as-is. With hedging configured, each
+    /// attempt of a safe request may also race backup copies.
+    pub async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        let mut request = request.build()?;
+        if self.factory.config.propagate_trace {
+            inject_trace_headers(request.headers_mut());
+        }
+        let method = request.method().clone();
+        let cloneable = request.try_clone().is_some();
+        let retry = match is_idempotent(&method) && cloneable {
+            true => self.factory.config.retry.clone(),
+            false => RetryPolicy::new().with_max_attempts(1),
+        };
+        let hedging = self
+            .factory
+            .config
+            .hedge
+            .as_ref()
+            .filter(|_| is_safe(&method) && cloneable);
+
+        let method = &method;
+        let mut original = Some(request);
+        let mut attempt = 0;
+        let result = with_retry_by(&retry, Failure::retryability, || {
+            attempt += 1;
+            let first = attempt == 1;
+            // Only cloneable requests get a retry policy, so the original is taken at most once.
+            let request = match original.as_ref().and_then(reqwest::Request::try_clone) {
+                Some(request) => request,
+                None => original
+                    .take()
+                    .expect("non-cloneable requests are sent once"),
+            };
+            async move {
+                if !first {
+                    self.count("http_client.retries", &[]);
+                }
+                let Some(policy) = hedging else {
+                    return self.execute(method, request).await;
+                };
+                let mut copies = 0;
+                hedge(policy, || {
+                    copies += 1;
+                    if copies > 1 {
+                        self.count("http_client.hedges", &[]);
+                    }
+                    let request = request.try_clone().expect("hedged requests are cloneable");
+                    self.execute(method, request)
+                })
+                .await
+            }
+        })
+        .await;
+
+        match result {
+            Ok(response) => Ok(response),
+            Err(
+                RetryError::NonRetryable(failure) | RetryError::Exhausted { last: failure, .. },
+            ) => failure.into_result(),
+            Err(RetryError::Cancelled { last, .. }) => last
+                .expect("an attempt ran before cancellation")
+                .into_result(),
+        }
+    }
+
+    async fn execute(
+        &self,
+        method: &Method,
+        request: reqwest::Request,
+    ) -> Result<reqwest::Response, Failure> {
+        let started = Instant::now();
+        let result = self.factory.client.execute(request).await;
+        self.record(method, &result, started.elapsed());
+        match result {
+            Ok(response) if is_retryable_status(response.status()) => {
+                Err(Failure::Status(response))
+            }
+            Ok(response) => Ok(response),
+            Err(err) => Err(Failure::Transport(err)),
+        }
+    }
+
+    fn record(
+        &self,
+        method: &Method,
+        result: &Result<reqwest::Response, reqwest::Error>,
+        elapsed: Duration,
+    ) {
+        let status = match result {
+            Ok(response) => response.status().as_u16().to_string(),
+            Err(err) if err.is_timeout() => "timeout".to_string(),
+            Err(_) => "error".to_string(),
+        };
+        self.count(
+            "http_client.requests",
+            &[("method", method.as_str()), ("status", &status)],
+        );
+        if let Some(metrics) = &self.factory.metrics {
+            metrics.observe_histogram(
+                "http_client.duration_ms",
+                elapsed.as_secs_f64() * 1000.0,
+                &[("client", &self.name), ("method", method.as_str())],
+            );
+        }
+    }
+
+    fn count(&self, name: &str, labels: &[(&str, &str)]) {
+        if let Some(metrics) = &self.factory.metrics {
+            let mut all = vec![("client", &*self.name)];
+            all.extend_from_slice(labels);
+            metrics.incr_counter_with(name, &all);
+        }
+    }
+}
+
+impl fmt::Debug for HttpClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpClient")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+enum Failure {
+    Status(reqwest::Response),
+    Transport(reqwest::Error),
+}
+
+impl Failure {
+    fn retryability(&self) -> Retryability {
+        match self {
+            Self::Status(response) if response.status() == StatusCode::TOO_MANY_REQUESTS => {
+                Retryability::Throttled {
+                    retry_after: response
+                        .headers()
+                        .get(header::RETRY_AFTER)
+                        .and_then(|value| value.to_str().ok())
+                        .and_then(|value| value.trim().parse().ok())
+                        .map(Duration::from_secs),
+                }
+            }
+            Self::Status(_) => Retryability::Retryable,
+            Self::Transport(err) if err.is_connect() || err.is_timeout() => Retryability::Retryable,
+            Self::Transport(_) => Retryability::NonRetryable,
+        }
+    }
+
+    fn into_result(self) -> Result<reqwest::Response, reqwest::Error> {
+        match self {
+            Self::Status(response) => Ok(response),
+            Self::Transport(err) => Err(err),
+        }
+    }
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS
+    )
+}
+
+fn is_safe(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// The current request's trace as a child span (or a new root trace), without overwriting
+/// headers the caller set.
+fn inject_trace_headers(headers: &mut http::HeaderMap) {
+    let context = TraceContext::current()
+        .map(|current| current.child())
+        .unwrap_or_else(TraceContext::new_root);
+    let entries = [
+        (TRACEPARENT_HEADER, Some(context.traceparent())),
+        (TRACESTATE_HEADER, context.tracestate().map(str::to_string)),
+        (REQUEST_ID_HEADER, context.request_id().map(str::to_string)),
+    ];
+    for (name, value) in entries {
+        if headers.contains_key(name) {
+            continue;
+        }
+        if let Some(value) = value.and_then(|value| HeaderValue::try_from(value).ok()) {
+            headers.insert(name, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use axum::{http::HeaderMap, routing::any, Router};
+    use openportio_test::RecordingMetrics;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn retries_idempotent_calls_and_propagates_the_trace() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let app = Router::new().route(
+            "/flaky",
+            any({
+                let calls = calls.clone();
+                move |headers: HeaderMap| async move {
+                    if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                        return (StatusCode::SERVICE_UNAVAILABLE, String::new());
+                    }
+                    let header = |name: &str| headers[name].to_str().unwrap().to_string();
+                    (
+                        StatusCode::OK,
+                        format!(
+                            "{} {}",
+                            header(REQUEST_ID_HEADER),
+                            header(TRACEPARENT_HEADER)
+                        ),
+                    )
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let metrics = Arc::new(RecordingMetrics::default());
+        let factory = HttpClientFactory::new(
+            HttpClientConfig::new()
+                .with_retry(RetryPolicy::new().with_initial_backoff(Duration::from_millis(1))),
+        )
+        .with_metrics(metrics.clone());
+        let client = factory.client("flaky-api");
+        let inbound = TraceContext::new_root().with_request_id("req-9");
+        let trace_id = inbound.trace_id();
+
+        let response = inbound
+            .scope(client.send(client.get(format!("http://{addr}/flaky"))))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.text().await.unwrap();
+        let (request_id, traceparent) = body.split_once(' ').unwrap();
+        assert_eq!(request_id, "req-9");
+        assert!(traceparent.contains(&trace_id));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // POST is not retried; the 503 comes back to the caller.
+        calls.store(0, Ordering::SeqCst);
+        let response = client
+            .send(client.post(format!("http://{addr}/flaky")))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let recorded = metrics.counters();
+        assert_eq!(
+            recorded
+                .iter()
+                .filter(|name| name.starts_with("http_client.retries"))
+                .count(),
+            1
+        );
+        assert_eq!(
+            metrics.count("http_client.requests{client=flaky-api,method=GET,status=503}"),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn slow_reads_are_hedged() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let app = Router::new().route(
+            "/slow-once",
+            any({
+                let calls = calls.clone();
+                move || async move {
+                    let call = calls.fetch_add(1, Ordering::SeqCst);
+                    if call == 0 {
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                    }
+                    call.to_string()
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let metrics = Arc::new(RecordingMetrics::default());
+        let factory = HttpClientFactory::new(
+            HttpClientConfig::new().with_hedging(HedgePolicy::fixed(Duration::from_millis(20))),
+        )
+        .with_metrics(metrics.clone());
+        let client = factory.client("search");
+        let started = Instant::now();
+        let response = client
+            .send(client.get(format!("http://{addr}/slow-once")))
+            .await
+            .unwrap();
+        assert_eq!(response.text().await.unwrap(), "1");
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert_eq!(
+            metrics
+                .counters()
+                .iter()
+                .filter(|name| name.starts_with("http_client.hedges"))
+                .count(),
+            1
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use std::sync::Arc;
+
+use axum::{routing::get, Json, Router};
+use serde::Serialize;
+use utoipa::openapi::{
+    path::{Operation, PathItem},
+    security::SecurityRequirement,
+    OpenApi,
+};
+
+pub const ROUTES_PATH: &str = "/internal/routes";
+
+/// Structured view of what a built server exposes, served at [`ROUTES_PATH`] by the admin router.
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+pub struct AppDescription {
+    pub service_name: String,
+    /// Shared layers applied to every REST and gRPC route, outermost first.
+    pub middleware: Vec<String>,
+    pub rest_routes: Vec<RestRouteDescription>,
+    pub grpc_services: Vec<GrpcServiceDescription>,
+    pub background_tasks: Vec<String>,
+    /// Names of the `di::Module`s installed, in installation order.
+    pub modules: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+pub struct RestRouteDescription {
+    pub path: String,
+    pub methods: Vec<String>,
+    pub scopes: Vec<String>,
+    /// Route-specific layers on top of the shared stack.
+    pub middleware: Vec<String>,
+}
+
+impl RestRouteDescription {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_method(mut self, method: impl Into<String>) -> Self {
+        self.methods.push(method.into().to_ascii_uppercase());
+        self
+    }
+
+    pub fn with_scope(mut self, scope: impl Into<String>) -> Self {
+        self.scopes.push(scope.into());
+        self
+    }
+
+    pub fn with_middleware(mut self, name: impl Into<String>) -> Self {
+        self.middleware.push(name.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+pub struct GrpcServiceDescription {
+    pub name: String,
+    pub methods: Vec<String>,
+}
+
+/// Lists every operation in `doc`, taking scopes from operation-level security requirements or,
+/// when absent, from the document-level default.
+pub fn rest_routes_from_openapi(doc: &OpenApi) -> Vec<RestRouteDescription> {
+    let default_security = doc.security.as_deref().unwrap_or_default();
+    doc.paths
+        .paths
+        .iter()
+        .map(|(path, item)| {
+            let operations = path_operations(item);
+            let mut route = RestRouteDescription::new(path.clone());
+            for (method, operation) in &operations {
+                route.methods.push((*method).to_string());
+                let security = operation.security.as_deref().unwrap_or(default_security);
+                for scope in security_scopes(security) {
+                    if !route.scopes.contains(&scope) {
+                        route.scopes.push(scope);
+                    }
+                }
+            }
+            if !route.scopes.is_empty() {
+                route.middleware.push("auth".to_string());
+            }
+            route
+        })
+        .collect()
+}
+
+pub fn routes_router(description: AppDescription) -> Router {
+    let description = Arc::new(description);
+    Router::new().route(
+        ROUTES_PATH,
+        get(move || {
+            let description = Arc::clone(&description);
+            async move { Json(description.as_ref().clone()) }
+        }),
+    )
+}
+
+fn path_operations(item: &PathItem) -> Vec<(&'static str, &Operation)> {
+    [
+        ("GET", item.get.as_ref()),
+        ("PUT", item.put.as_ref()),
+        ("POST", item.post.as_ref()),
+        ("DELETE", item.delete.as_ref()),
+        ("OPTIONS", item.options.as_ref()),
+        ("HEAD", item.head.as_ref()),
+        ("PATCH", item.patch.as_ref()),
+        ("TRACE", item.trace.as_ref()),
+    ]
+    .into_iter()
+    .filter_map(|(method, operation)| operation.map(|operation| (method, operation)))
+    .collect()
+}
+
+// `SecurityRequirement` keeps its scheme map private; its JSON form is `{"scheme": ["scope"]}`.
+fn security_scopes(requirements: &[SecurityRequirement]) -> Vec<String> {
+    requirements
+        .iter()
+        .filter_map(|requirement| serde_json::to_value(requirement).ok())
+        .filter_map(|value| match value {
+            serde_json::Value::Object(schemes) => Some(schemes),
+            _ => None,
+        })
+        .flat_map(|schemes| schemes.into_iter())
+        .filter_map(|(_, scopes)| serde_json::from_value::<Vec<String>>(scopes).ok())
+        .flatten()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utoipa::openapi::{
+        path::{HttpMethod, OperationBuilder, PathItem},
+        PathsBuilder,
+    };
+
+    #[test]
+    fn openapi_operations_become_routes_with_scopes() {
+        let secured = OperationBuilder::new()
+            .security(SecurityRequirement::new(
+                "bearer",
+                ["notes:write", "notes:read"],
+            ))
+            .build();
+        let mut doc = OpenApi::new(
+            utoipa::openapi::Info::new("test", "1"),
+            PathsBuilder::new()
+                .path("/notes", PathItem::new(HttpMethod::Get, Operation::new()))
+                .path("/notes/{id}", PathItem::new(HttpMethod::Put, secured)),
+        );
+        doc.security = Some(vec![SecurityRequirement::new("bearer", ["notes:read"])]);
+
+        let routes = rest_routes_from_openapi(&doc);
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[0].path, "/notes");
+        assert_eq!(routes[0].methods, vec!["GET"]);
+        assert_eq!(routes[0].scopes, vec!["notes:read"]);
+        assert_eq!(routes[1].methods, vec!["PUT"]);
+        assert_eq!(routes[1].scopes, vec!["notes:write", "notes:read"]);
+        assert_eq!(routes[1].middleware, vec!["auth"]);
+    }
+}
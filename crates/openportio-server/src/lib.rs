@@ -1,9 +1,8 @@
 extern crate self as openportio_server;
 use std::{
     convert::Infallible,
-    env,
     sync::{Arc, OnceLock},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use axum::{
@@ -14,7 +13,7 @@ use axum::{
     middleware::from_fn_with_state,
     response::{
         sse::{Event, KeepAlive, Sse},
-        Html,
+        Html, IntoResponse, Response,
     },
     routing::get,
     Json, Router,
@@ -38,16 +37,68 @@ use utoipa::{
 };
 use utoipa_swagger_ui::SwaggerUi;
 
+pub mod adaptive_concurrency;
+pub mod alerts;
 pub mod api;
+pub mod api_docs;
+pub mod audit;
 pub mod auth;
+pub mod body_stream;
+pub mod brute_force;
 pub mod builder;
+pub mod buildinfo;
+pub mod cache;
+pub mod client_concurrency;
+pub mod client_ip;
+pub mod context;
+pub mod cors;
+pub mod dedup;
 pub mod di;
+pub mod diagnostics;
+pub mod drain;
+mod env;
+pub mod error_reporting;
+pub mod export_stream;
+pub mod files;
+pub mod flags;
 pub mod grpc;
+pub mod grpc_limits;
+pub mod handler_timeout;
+pub mod http_client;
+pub mod introspection;
+pub mod lifecycle;
+pub mod log_filter;
+pub mod maintenance;
 pub mod middleware;
+pub mod panics;
+pub mod patch;
+pub mod policy;
+pub mod profile;
+pub mod prometheus;
+pub mod quota;
+pub mod redirect;
+pub mod response_headers;
+pub mod revocation;
+pub mod runtime_metrics;
+pub mod server_error;
+pub mod signing;
+pub mod slow_requests;
+pub mod sse;
+pub mod tenancy;
+pub mod timings;
+pub mod tls;
+pub mod trace_context;
+pub mod transport;
+pub mod versioning;
+pub mod ws;
 use crate::api::ApiErrorResponse;
 pub use builder::OpenportioServer;
-pub use openportio_macros::{dto, route};
+#[doc(hidden)]
+pub use inventory;
+pub use openportio_core::{alloy_buildinfo, meld_buildinfo, openportio_buildinfo};
+pub use openportio_macros::{dto, grpc_service, route, RequestValidation, TypedPath};
 pub use serde;
+pub use server_error::ServerError;
 pub use utoipa;
 pub use utoipa::ToSchema as MeldSchema;
 pub use utoipa::ToSchema as OpenPortIOSchema;
@@ -61,19 +112,25 @@ pub type AlloyServer = OpenportioServer;
 
 pub mod prelude {
     pub use crate::api::{
-        ApiError, ApiErrorResponse, RequestValidation, ValidatedJson, ValidatedParts,
-        ValidatedPath, ValidatedQuery,
+        ApiError, ApiErrorResponse, AsyncRequestValidation, Envelope, Enveloped, ListParams,
+        ListResource, RequestValidation, TypedPath, ValidatedJson, ValidatedJsonAsync,
+        ValidatedParts, ValidatedPath, ValidatedQuery, ValidationDeps,
     };
+    pub use crate::context::{RequestContext, RequestContextExt};
     pub use crate::di::{
         with_dependency, with_dependency_override, with_dependency_overrides, DependencyOverrides,
-        Depends,
+        Depends, Lazy, Module, Named, Qualifier,
     };
+    pub use crate::flags::FlagClient;
+    pub use crate::grpc::GrpcPrincipalExt;
+    pub use crate::patch::{ValidatedJsonPatch, ValidatedMergePatch};
+    pub use crate::qualifier;
     pub use crate::AlloyServer;
     pub use crate::MeldServer;
     pub use crate::OpenportioServer;
     pub use crate::{
         dto, route, MeldSchema, MeldValidate, OpenPortIOSchema, OpenPortIOValidate,
-        OpenportioSchema, OpenportioValidate,
+        OpenportioSchema, OpenportioValidate, RequestValidation, TypedPath,
     };
 }
 
@@ -88,6 +145,15 @@ pub struct HealthResponse {
     pub status: String,
 }
 
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+pub struct ReadinessResponse {
+    /// `ready`, `degraded`, `not_ready` or `draining`.
+    pub status: String,
+    /// Startup validator results from `AppStateBuilder::init`.
+    #[schema(value_type = Vec<Object>)]
+    pub checks: Vec<openportio_core::startup::StartupCheck>,
+}
+
 #[derive(Debug, Serialize, utoipa::ToSchema)]
 pub struct HelloRestResponse {
     pub message: String,
@@ -108,10 +174,11 @@ struct ServerSentEventPayload {
 
 #[derive(OpenApi)]
 #[openapi(
-    paths(root, health, hello, protected_whoami),
+    paths(root, health, readyz, hello, protected_whoami),
     components(schemas(
         RootResponse,
         HealthResponse,
+        ReadinessResponse,
         HelloRestResponse,
         ProtectedWhoAmIResponse,
         ApiErrorResponse,
@@ -169,9 +236,13 @@ impl Modify for ApiDocDefaults {
                 );
                 if path.starts_with("/protected/") {
                     ensure_error_response(operation, "401", "Unauthorized", "ApiErrorResponse");
+                    operation.security.get_or_insert_with(|| {
+                        vec![api_docs::bearer_auth_requirement(Vec::<String>::new())]
+                    });
                 }
             }
         }
+        api_docs::register_bearer_auth_scheme(openapi);
     }
 }
 
@@ -225,10 +296,41 @@ fn ensure_error_response(
 }
 
 pub fn build_router(state: Arc<AppState>) -> Router {
-    build_router_with_auth(state, auth::AuthRuntimeConfig::from_env())
+    build_router_with_auth(
+        state,
+        auth::AuthRuntimeConfig::or_reject_all(auth::AuthRuntimeConfig::from_env()),
+    )
 }
 
 pub fn build_router_with_auth(state: Arc<AppState>, auth_cfg: auth::AuthRuntimeConfig) -> Router {
+    api_router(auth_cfg, default_ws_gate(&state))
+        .merge(docs_router())
+        .with_state(state)
+}
+
+/// [`build_router`] without the Swagger UI, OpenAPI document and gRPC contract pages.
+pub(crate) fn build_router_without_docs(state: Arc<AppState>) -> Router {
+    let auth_cfg = auth::AuthRuntimeConfig::or_reject_all(auth::AuthRuntimeConfig::from_env());
+    api_router(auth_cfg, default_ws_gate(&state)).with_state(state)
+}
+
+/// Paths served by the docs half of [`build_router`].
+pub(crate) const DOCS_PATHS: &[&str] = &[
+    "/docs",
+    "/openapi.json",
+    "/grpc/contracts",
+    "/grpc/contracts.md",
+    "/grpc/contracts/openapi.json",
+];
+
+fn default_ws_gate(state: &AppState) -> ws::WsGate {
+    ws::WsGate::new(
+        crate::env::or_default(ws::WsLimits::from_env()),
+        state.metrics.clone(),
+    )
+}
+
+fn api_router(auth_cfg: auth::AuthRuntimeConfig, ws_gate: ws::WsGate) -> Router<Arc<AppState>> {
     let protected = Router::new()
         .route("/protected/whoami", get(protected_whoami))
         .route_layer(from_fn_with_state(auth_cfg, auth::rest_auth_middleware));
@@ -236,22 +338,54 @@ pub fn build_router_with_auth(state: Arc<AppState>, auth_cfg: auth::AuthRuntimeC
     Router::new()
         .route("/", get(root))
         .route("/health", get(health))
+        .route("/readyz", get(readyz))
         .route("/hello/:name", get(hello))
         .route("/events", get(events))
-        .route("/ws", get(ws_handler))
+        .route("/ws", get(ws_handler).layer(Extension(ws_gate)))
         .merge(protected)
+}
+
+fn docs_router() -> Router<Arc<AppState>> {
+    Router::new()
         .route("/grpc/contracts", get(grpc_contracts))
         .route("/grpc/contracts.md", get(grpc_contracts_markdown))
         .route(
             "/grpc/contracts/openapi.json",
             get(grpc_contracts_openapi_bridge),
         )
-        .merge(SwaggerUi::new("/docs").url("/openapi.json", ApiDoc::openapi()))
-        .with_state(state)
+        .merge(SwaggerUi::new("/docs").url(
+            "/openapi.json",
+            api_docs::public_openapi(api_docs::with_dto_schemas(ApiDoc::openapi())),
+        ))
+}
+
+pub(crate) fn default_rest_route_descriptions() -> Vec<introspection::RestRouteDescription> {
+    let mut routes = introspection::rest_routes_from_openapi(&rest_openapi_document());
+    for route in &mut routes {
+        if route.path.starts_with("/protected/") {
+            route.middleware.push("auth".to_string());
+        }
+    }
+    routes.extend(
+        [
+            "/events",
+            "/ws",
+            "/grpc/contracts",
+            "/grpc/contracts.md",
+            "/grpc/contracts/openapi.json",
+            "/docs",
+            "/openapi.json",
+        ]
+        .map(|path| introspection::RestRouteDescription::new(path).with_method("GET")),
+    );
+    routes
 }
 
 pub fn build_multiplexed_router(state: Arc<AppState>) -> Router {
-    build_multiplexed_router_with_auth(state, auth::AuthRuntimeConfig::from_env())
+    build_multiplexed_router_with_auth(
+        state,
+        auth::AuthRuntimeConfig::or_reject_all(auth::AuthRuntimeConfig::from_env()),
+    )
 }
 
 pub fn build_multiplexed_router_with_auth(
@@ -275,7 +409,7 @@ pub fn build_multiplexed_router_with_auth(
 async fn root(State(state): State<Arc<AppState>>) -> Json<RootResponse> {
     Json(RootResponse {
         service_name: state.config.service_name.clone(),
-        environment: state.config.environment.clone(),
+        environment: state.config.environment.to_string(),
     })
 }
 
@@ -288,12 +422,49 @@ async fn root(State(state): State<Arc<AppState>>) -> Json<RootResponse> {
     )
 )]
 async fn health(State(state): State<Arc<AppState>>) -> Json<HealthResponse> {
-    state.metrics.incr_counter("http.health.requests");
+    state.metrics.incr_counter_with(
+        "http.health.requests",
+        &[("route", "/health"), ("method", "GET")],
+    );
     Json(HealthResponse {
         status: "OK".to_string(),
     })
 }
 
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "rest",
+    responses(
+        (status = 200, description = "Ready, possibly with degraded dependencies", body = ReadinessResponse),
+        (status = 503, description = "A startup check marked the app as not ready", body = ReadinessResponse)
+    )
+)]
+async fn readyz(
+    State(state): State<Arc<AppState>>,
+    drain: Option<Extension<drain::DrainState>>,
+) -> (StatusCode, Json<ReadinessResponse>) {
+    let report = &state.startup;
+    let draining = drain.is_some_and(|Extension(drain)| drain.is_draining());
+    let status = if report.is_ready() && !draining {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (
+        status,
+        Json(ReadinessResponse {
+            status: if draining {
+                "draining"
+            } else {
+                report.status()
+            }
+            .to_string(),
+            checks: report.checks.clone(),
+        }),
+    )
+}
+
 #[utoipa::path(
     get,
     path = "/hello/{name}",
@@ -309,12 +480,33 @@ async fn hello(
     Path(name): Path<String>,
     State(state): State<Arc<AppState>>,
 ) -> Result<Json<HelloRestResponse>, api::ApiError> {
-    let response = build_hello_response(&state, HelloRequest { name }).map_err(map_error)?;
+    let started = Instant::now();
+    let result = build_hello_response(&state, HelloRequest { name }).map_err(map_error);
+    let status = match &result {
+        Ok(_) => StatusCode::OK,
+        Err((status, _)) => *status,
+    };
+    record_http_request(&state, "/hello/{name}", status, started);
+    let response = result?;
     Ok(Json(HelloRestResponse {
         message: response.message,
     }))
 }
 
+fn record_http_request(state: &AppState, route: &str, status: StatusCode, started: Instant) {
+    let labels = [
+        ("route", route),
+        ("method", "GET"),
+        ("status", status.as_str()),
+    ];
+    state.metrics.incr_counter_with("http.requests", &labels);
+    state.metrics.observe_histogram(
+        "http.request.duration_seconds",
+        started.elapsed().as_secs_f64(),
+        &labels,
+    );
+}
+
 #[utoipa::path(
     get,
     path = "/protected/whoami",
@@ -347,96 +539,97 @@ const WS_DEFAULT_MAX_TEXT_BYTES: usize = 4 * 1024;
 const WS_DEFAULT_IDLE_TIMEOUT_SECS: u64 = 45;
 
 #[derive(Clone, Copy)]
-struct WsRuntimeConfig {
+pub(crate) struct WsRuntimeConfig {
     max_text_bytes: usize,
     idle_timeout: Duration,
 }
 
-async fn ws_handler(ws: WebSocketUpgrade) -> impl axum::response::IntoResponse {
-    let cfg = ws_runtime_config();
+impl Default for WsRuntimeConfig {
+    fn default() -> Self {
+        Self {
+            max_text_bytes: WS_DEFAULT_MAX_TEXT_BYTES,
+            idle_timeout: Duration::from_secs(WS_DEFAULT_IDLE_TIMEOUT_SECS),
+        }
+    }
+}
+
+async fn ws_handler(
+    ws: WebSocketUpgrade,
+    Extension(gate): Extension<ws::WsGate>,
+    shutdown: Option<Extension<ws::WsShutdown>>,
+    client_ip: Option<Extension<client_ip::ClientIp>>,
+) -> Response {
+    let gate = match shutdown {
+        Some(Extension(shutdown)) => gate.with_shutdown(shutdown),
+        None => gate,
+    };
+    let permit = match gate.try_acquire(client_ip.map(|Extension(client_ip::ClientIp(ip))| ip)) {
+        Ok(permit) => permit,
+        Err(rejection) => return rejection.into_response(),
+    };
+    let cfg = crate::env::or_default(ws_runtime_config());
     ws.max_message_size(cfg.max_text_bytes)
-        .on_upgrade(move |socket| handle_ws_session(socket, cfg))
+        .on_upgrade(move |socket| handle_ws_session(socket, cfg, gate, permit))
 }
 
-async fn handle_ws_session(mut socket: WebSocket, cfg: WsRuntimeConfig) {
+async fn handle_ws_session(
+    socket: WebSocket,
+    cfg: WsRuntimeConfig,
+    gate: ws::WsGate,
+    _permit: ws::WsPermit,
+) {
     tracing::info!("websocket connection opened");
+    let (sink, mut stream) = futures_util::StreamExt::split(socket);
+    let (outbound, writer) = gate.outbound(sink);
     loop {
-        let next_message = tokio::time::timeout(cfg.idle_timeout, socket.recv()).await;
+        let next_message = tokio::time::timeout(cfg.idle_timeout, stream.next()).await;
         let Some(result) = (match next_message {
             Ok(result) => result,
             Err(_) => {
                 tracing::info!("websocket idle timeout reached, closing connection");
-                let _ = socket.close().await;
-                return;
+                break;
             }
         }) else {
             tracing::info!("websocket connection closed by client");
-            return;
+            break;
         };
 
-        match result {
-            Ok(Message::Text(text)) => {
-                if text.len() > cfg.max_text_bytes {
-                    let _ = socket.send(Message::Close(None)).await;
-                    return;
-                }
-                if socket
-                    .send(Message::Text(format!("echo: {text}")))
-                    .await
-                    .is_err()
-                {
-                    tracing::warn!("failed to send websocket text frame");
-                    return;
-                }
-            }
-            Ok(Message::Ping(payload)) => {
-                if socket.send(Message::Pong(payload)).await.is_err() {
-                    tracing::warn!("failed to send websocket pong");
-                    return;
-                }
+        let reply = match result {
+            Ok(Message::Text(text)) if text.len() > cfg.max_text_bytes => {
+                let _ = outbound.send(Message::Close(None));
+                break;
             }
-            Ok(Message::Close(_)) => {
-                let _ = socket.close().await;
-                return;
-            }
-            Ok(_) => {}
+            Ok(Message::Text(text)) => Message::Text(format!("echo: {text}")),
+            Ok(Message::Ping(payload)) => Message::Pong(payload),
+            Ok(Message::Close(_)) => break,
+            Ok(_) => continue,
             Err(err) => {
                 tracing::warn!(error = %err, "websocket receive error");
-                return;
+                break;
             }
+        };
+        if outbound.send(reply) == Err(ws::WsSendError::Closed) {
+            tracing::warn!("websocket outbound closed, ending session");
+            break;
         }
     }
+    // Dropping the last sender lets the writer flush what is queued and close the socket.
+    drop(outbound);
+    let _ = writer.await;
 }
 
-fn ws_runtime_config() -> WsRuntimeConfig {
-    let max_text_bytes = read_env_with_aliases::<usize>(&[
-        "OPENPORTIO_WS_MAX_TEXT_BYTES",
-        "MELD_WS_MAX_TEXT_BYTES",
-        "ALLOY_WS_MAX_TEXT_BYTES",
-    ])
-    .filter(|v| *v > 0)
-    .unwrap_or(WS_DEFAULT_MAX_TEXT_BYTES);
-    let idle_timeout_secs = read_env_with_aliases::<u64>(&[
-        "OPENPORTIO_WS_IDLE_TIMEOUT_SECS",
-        "MELD_WS_IDLE_TIMEOUT_SECS",
-        "ALLOY_WS_IDLE_TIMEOUT_SECS",
-    ])
-    .filter(|v| *v > 0)
-    .unwrap_or(WS_DEFAULT_IDLE_TIMEOUT_SECS);
-
-    WsRuntimeConfig {
+pub(crate) fn ws_runtime_config() -> Result<WsRuntimeConfig, String> {
+    let max_text_bytes = crate::env::setting::<usize>("WS_MAX_TEXT_BYTES")?
+        .filter(|v| *v > 0)
+        .unwrap_or(WS_DEFAULT_MAX_TEXT_BYTES);
+    let idle_timeout_secs = crate::env::setting::<u64>("WS_IDLE_TIMEOUT_SECS")?
+        .filter(|v| *v > 0)
+        .unwrap_or(WS_DEFAULT_IDLE_TIMEOUT_SECS);
+
+    Ok(WsRuntimeConfig {
         max_text_bytes,
         idle_timeout: Duration::from_secs(idle_timeout_secs),
-    }
-}
-
-fn read_env_with_aliases<T>(names: &[&str]) -> Option<T>
-where
-    T: std::str::FromStr,
-{
-    names
-        .iter()
-        .find_map(|name| env::var(name).ok().and_then(|raw| raw.parse::<T>().ok()))
+    })
 }
 
 async fn grpc_contracts() -> Html<&'static str> {
@@ -583,6 +776,99 @@ mod tests {
     use tokio_stream::StreamExt;
     use tower::util::ServiceExt;
 
+    #[tokio::test]
+    async fn ws_endpoint_rejects_connections_over_the_client_cap() {
+        use futures_util::SinkExt;
+        use tokio_tungstenite::tungstenite::{self, Message as WsMessage};
+
+        let state = Arc::new(AppState::local("test-server"));
+        let gate = ws::WsGate::new(
+            ws::WsLimits {
+                max_connections_per_client: Some(1),
+                ..ws::WsLimits::default()
+            },
+            state.metrics.clone(),
+        );
+        let app = api_router(auth::AuthRuntimeConfig::default(), gate.clone()).with_state(state);
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .expect("bind test listener");
+        let url = format!("ws://{}/ws", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let (mut first, _) = tokio_tungstenite::connect_async(&url)
+            .await
+            .expect("first connection fits the cap");
+        first.send(WsMessage::Text("hi".into())).await.unwrap();
+        let echoed = timeout(Duration::from_secs(5), first.next())
+            .await
+            .expect("echo in time")
+            .expect("stream open")
+            .expect("echo frame");
+        assert_eq!(echoed, WsMessage::Text("echo: hi".into()));
+
+        match tokio_tungstenite::connect_async(&url).await {
+            Err(tungstenite::Error::Http(response)) => {
+                assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS)
+            }
+            other => panic!("second connection must be rejected, got {other:?}"),
+        }
+
+        first.close(None).await.unwrap();
+        timeout(Duration::from_secs(5), async {
+            while gate.active_connections() > 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("closing the session frees its slot");
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn readyz_reflects_startup_checks() {
+        use openportio_core::{startup::FailurePolicy, AppConfig, AppStateBuilder};
+
+        let state = AppStateBuilder::new(AppConfig::local("test-server"))
+            .validate("database", FailurePolicy::NotReady, || async {
+                Err::<(), _>("connection refused")
+            })
+            .init()
+            .await
+            .expect("not-ready checks do not abort startup");
+        let response = build_router(Arc::new(state))
+            .oneshot(
+                Request::builder()
+                    .uri("/readyz")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("request should succeed");
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body: Value = serde_json::from_slice(
+            &to_bytes(response.into_body(), usize::MAX)
+                .await
+                .expect("body"),
+        )
+        .expect("json");
+        assert_eq!(body["status"], "not_ready");
+        assert_eq!(body["checks"][0]["name"], "database");
+        assert_eq!(body["checks"][0]["status"], "failed");
+        assert_eq!(body["checks"][0]["message"], "connection refused");
+
+        let response = build_router(Arc::new(AppState::local("test-server")))
+            .oneshot(
+                Request::builder()
+                    .uri("/readyz")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("request should succeed");
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
     #[tokio::test]
     async fn health_returns_ok() {
         let app = build_router(Arc::new(AppState::local("test-server")));
@@ -758,6 +1044,38 @@ mod tests {
         .expect("token should encode")
     }
 
+    #[tokio::test]
+    async fn hello_records_labeled_request_metrics() {
+        let metrics = Arc::new(openportio_test::RecordingMetrics::default());
+        let state = AppState::new(
+            openportio_core::AppConfig::local("test-server"),
+            Arc::new(openportio_core::StaticGreetingEngine::new("Hello")),
+            metrics.clone(),
+        );
+        let app = build_router(Arc::new(state));
+
+        for uri in ["/hello/Rust", "/hello/%20"] {
+            app.clone()
+                .oneshot(
+                    Request::builder()
+                        .uri(uri)
+                        .body(axum::body::Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .expect("request should succeed");
+        }
+
+        assert_eq!(
+            metrics.count("http.requests{route=/hello/{name},method=GET,status=200}"),
+            1
+        );
+        assert_eq!(
+            metrics.count("http.requests{route=/hello/{name},method=GET,status=400}"),
+            1
+        );
+    }
+
     #[tokio::test]
     async fn protected_route_returns_anonymous_when_auth_disabled() {
         let mut auth_cfg = auth::AuthRuntimeConfig::default();
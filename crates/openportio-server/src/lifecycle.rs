@@ -0,0 +1,188 @@
+use std::{collections::HashMap, error::Error, fmt, future::Future, net::SocketAddr, sync::Arc};
+
+use futures_util::future::BoxFuture;
+use openportio_core::AppState;
+use tower::BoxError;
+
+type PhaseHook =
+    Box<dyn Fn(PhaseContext) -> BoxFuture<'static, Result<(), BoxError>> + Send + Sync + 'static>;
+
+/// Startup phases of `OpenportioServer::run`, in the order they run. Hooks registered with
+/// `on_phase` run sequentially, in registration order, when their phase is reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Phase {
+    /// HTTP/2 and socket settings were validated and the listener mode resolved.
+    Config,
+    /// Before the app is assembled: connect pools or warm caches on `AppState` here.
+    StateInit,
+    /// The dependency graph resolved and the router was built.
+    DependencyValidation,
+    /// Listeners are bound; [`PhaseContext::addrs`] holds their addresses.
+    ListenersBound,
+    /// Background tasks were spawned; the server starts accepting right after.
+    BackgroundTasksStarted,
+}
+
+impl Phase {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Config => "config",
+            Self::StateInit => "state_init",
+            Self::DependencyValidation => "dependency_validation",
+            Self::ListenersBound => "listeners_bound",
+            Self::BackgroundTasksStarted => "background_tasks_started",
+        }
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What a failing hook does to startup.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PhaseFailurePolicy {
+    /// `run()` returns the [`PhaseError`] without serving (default).
+    #[default]
+    Abort,
+    /// The failure is logged and the remaining hooks and phases still run.
+    Continue,
+}
+
+#[derive(Clone)]
+pub struct PhaseContext {
+    pub phase: Phase,
+    pub state: Arc<AppState>,
+    /// Bound listener addresses; empty before [`Phase::ListenersBound`].
+    pub addrs: Vec<SocketAddr>,
+}
+
+#[derive(Debug)]
+pub struct PhaseError {
+    pub phase: Phase,
+    pub source: BoxError,
+}
+
+impl fmt::Display for PhaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "startup phase `{}` failed: {}", self.phase, self.source)
+    }
+}
+
+impl Error for PhaseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Lifecycle {
+    hooks: Vec<(Phase, PhaseHook)>,
+    policies: HashMap<Phase, PhaseFailurePolicy>,
+}
+
+impl Lifecycle {
+    pub(crate) fn on_phase<F, Fut>(&mut self, phase: Phase, hook: F)
+    where
+        F: Fn(PhaseContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), BoxError>> + Send + 'static,
+    {
+        self.hooks
+            .push((phase, Box::new(move |context| Box::pin(hook(context)))));
+    }
+
+    pub(crate) fn set_policy(&mut self, phase: Phase, policy: PhaseFailurePolicy) {
+        self.policies.insert(phase, policy);
+    }
+
+    pub(crate) async fn run(
+        &self,
+        phase: Phase,
+        state: &Arc<AppState>,
+        addrs: &[SocketAddr],
+    ) -> Result<(), PhaseError> {
+        let policy = self.policies.get(&phase).copied().unwrap_or_default();
+        for (_, hook) in self
+            .hooks
+            .iter()
+            .filter(|(hook_phase, _)| *hook_phase == phase)
+        {
+            let context = PhaseContext {
+                phase,
+                state: state.clone(),
+                addrs: addrs.to_vec(),
+            };
+            match (hook(context).await, policy) {
+                (Ok(()), _) => {}
+                (Err(source), PhaseFailurePolicy::Abort) => {
+                    return Err(PhaseError { phase, source })
+                }
+                (Err(err), PhaseFailurePolicy::Continue) => {
+                    tracing::warn!(phase = %phase, error = %err, "startup hook failed; continuing");
+                }
+            }
+        }
+        tracing::debug!(phase = %phase, "startup phase complete");
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Lifecycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Lifecycle")
+            .field("hooks", &self.hooks.len())
+            .field("policies", &self.policies)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn hooks_run_per_phase_and_failures_follow_the_phase_policy() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let mut lifecycle = Lifecycle::default();
+        for (phase, fails) in [
+            (Phase::StateInit, false),
+            (Phase::Config, true),
+            (Phase::StateInit, true),
+            (Phase::StateInit, false),
+        ] {
+            let calls = calls.clone();
+            lifecycle.on_phase(phase, move |context| {
+                let calls = calls.clone();
+                async move {
+                    calls.lock().unwrap().push(context.phase);
+                    if fails {
+                        Err("unreachable dependency".into())
+                    } else {
+                        Ok(())
+                    }
+                }
+            });
+        }
+        lifecycle.set_policy(Phase::Config, PhaseFailurePolicy::Continue);
+        let state = Arc::new(AppState::local("lifecycle-test"));
+
+        lifecycle.run(Phase::Config, &state, &[]).await.unwrap();
+        let err = lifecycle
+            .run(Phase::StateInit, &state, &[])
+            .await
+            .unwrap_err();
+        assert_eq!(err.phase, Phase::StateInit);
+        assert_eq!(
+            err.to_string(),
+            "startup phase `state_init` failed: unreachable dependency"
+        );
+        assert_eq!(
+            *calls.lock().unwrap(),
+            [Phase::Config, Phase::StateInit, Phase::StateInit]
+        );
+    }
+}
@@ -0,0 +1,239 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use axum::{
+    extract::State,
+    http::{Extensions, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tracing_subscriber::{reload, EnvFilter};
+
+use crate::{
+    api::{ApiErrorResponse, ValidatedJson},
+    audit::{AuditEvent, AuditEventKind},
+};
+
+pub const LOGGING_PATH: &str = "/internal/logging";
+
+type Reload = dyn Fn(EnvFilter) -> Result<(), reload::Error> + Send + Sync;
+
+/// Runtime handle to the process's `EnvFilter`, for `PUT /internal/logging` or app code.
+/// Clones share the filter.
+#[derive(Clone)]
+pub struct LogFilterHandle {
+    reload: Arc<Reload>,
+    initial: Arc<str>,
+    current: Arc<Mutex<String>>,
+    /// Bumped on every change so a pending revert never undoes a newer filter.
+    generation: Arc<AtomicU64>,
+}
+
+impl std::fmt::Debug for LogFilterHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LogFilterHandle")
+            .field("initial", &self.initial)
+            .field("current", &self.current())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Builds a reloadable `EnvFilter` layer from `directives` (for example
+/// `Environment::default_log_filter()`) and the handle that changes it:
+///
+/// ```ignore
+/// let (filter, log_filter) = log_filter::reloadable_env_filter("info")?;
+/// tracing_subscriber::registry().with(filter).with(fmt::layer()).init();
+/// OpenportioServer::new().with_admin_routes().with_log_filter(log_filter)
+/// ```
+pub fn reloadable_env_filter<S>(
+    directives: &str,
+) -> Result<(reload::Layer<EnvFilter, S>, LogFilterHandle), tracing_subscriber::filter::ParseError>
+where
+    S: tracing::Subscriber + 'static,
+{
+    let (layer, handle) = reload::Layer::new(EnvFilter::try_new(directives)?);
+    Ok((layer, LogFilterHandle::new(handle, directives)))
+}
+
+impl LogFilterHandle {
+    /// Wraps a `reload::Handle` for an `EnvFilter` that currently holds `directives`.
+    pub fn new<S>(handle: reload::Handle<EnvFilter, S>, directives: impl Into<String>) -> Self
+    where
+        S: 'static,
+    {
+        let directives = directives.into();
+        Self {
+            reload: Arc::new(move |filter| handle.reload(filter)),
+            initial: directives.clone().into(),
+            current: Arc::new(Mutex::new(directives)),
+            generation: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// The directives in effect.
+    pub fn current(&self) -> String {
+        self.current
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+
+    /// The directives the handle was created with.
+    pub fn initial(&self) -> &str {
+        &self.initial
+    }
+
+    /// Replaces the filter; invalid directives leave the current one in place.
+    pub fn set(&self, directives: &str) -> Result<(), String> {
+        self.apply(directives).map(|_| ())
+    }
+
+    /// Like [`Self::set`], going back to the initial directives after `ttl` unless the filter
+    /// changed again in the meantime. Needs a Tokio runtime.
+    pub fn set_for(&self, directives: &str, ttl: Duration) -> Result<(), String> {
+        let generation = self.apply(directives)?;
+        let handle = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(ttl).await;
+            if handle.generation.load(Ordering::SeqCst) != generation {
+                return;
+            }
+            match handle.apply(&handle.initial.clone()) {
+                Ok(_) => tracing::warn!(filter = %handle.initial, "log filter reverted"),
+                Err(err) => tracing::error!(error = %err, "failed to revert log filter"),
+            }
+        });
+        Ok(())
+    }
+
+    pub fn reset(&self) -> Result<(), String> {
+        self.set(&self.initial.clone())
+    }
+
+    fn apply(&self, directives: &str) -> Result<u64, String> {
+        let filter = EnvFilter::try_new(directives).map_err(|err| err.to_string())?;
+        let mut current = self.current.lock().unwrap_or_else(|err| err.into_inner());
+        (self.reload)(filter).map_err(|err| err.to_string())?;
+        *current = directives.to_string();
+        Ok(self.generation.fetch_add(1, Ordering::SeqCst) + 1)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LogFilterStatus {
+    filter: String,
+    initial: String,
+}
+
+#[derive(Debug, Deserialize, validator::Validate, crate::RequestValidation)]
+struct LogFilterUpdate {
+    /// `EnvFilter` directives, e.g. `info,openportio_server=debug,sqlx=warn`; omit to reset.
+    #[validate(length(min = 1, max = 1024))]
+    filter: Option<String>,
+    /// Revert to the initial filter after this many seconds.
+    #[validate(range(min = 1, max = 86400))]
+    ttl_seconds: Option<u64>,
+}
+
+/// `GET` reports and `PUT {"filter": "info,my_crate=debug", "ttl_seconds": 600}` changes the
+/// log filter; `PUT {}` resets it. Serve it behind auth like the rest of the admin router.
+pub fn logging_router(handle: LogFilterHandle) -> Router {
+    Router::new()
+        .route(LOGGING_PATH, get(log_filter_status).put(update_log_filter))
+        .with_state(handle)
+}
+
+async fn log_filter_status(State(handle): State<LogFilterHandle>) -> Json<LogFilterStatus> {
+    Json(LogFilterStatus {
+        filter: handle.current(),
+        initial: handle.initial().to_string(),
+    })
+}
+
+async fn update_log_filter(
+    State(handle): State<LogFilterHandle>,
+    extensions: Extensions,
+    ValidatedJson(update): ValidatedJson<LogFilterUpdate>,
+) -> Response {
+    let directives = update
+        .filter
+        .unwrap_or_else(|| handle.initial().to_string());
+    let result = match update.ttl_seconds {
+        Some(seconds) => handle.set_for(&directives, Duration::from_secs(seconds)),
+        None => handle.set(&directives),
+    };
+    if let Err(err) = result {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ApiErrorResponse {
+                code: "invalid_log_filter".to_string(),
+                message: format!("log filter directives could not be applied: {err}"),
+                detail: None,
+                details: None,
+            }),
+        )
+            .into_response();
+    }
+    tracing::warn!(filter = %directives, ttl_seconds = update.ttl_seconds, "log filter changed");
+    AuditEvent::from_extensions(AuditEventKind::ConfigReload, &extensions)
+        .with_reason(format!("log filter set to `{directives}`"))
+        .emit(&extensions);
+    log_filter_status(State(handle)).await.into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::{to_bytes, Body},
+        http::Request,
+    };
+    use tower::util::ServiceExt;
+    use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+    #[tokio::test]
+    async fn put_changes_the_filter_and_ttl_reverts_it() {
+        let (filter, handle) = reloadable_env_filter::<Registry>("info").unwrap();
+        let subscriber = Registry::default().with(filter);
+        let _guard = tracing::subscriber::set_default(subscriber);
+        let app = logging_router(handle.clone());
+        let put = |body: &'static str| {
+            app.clone().oneshot(
+                Request::put(LOGGING_PATH)
+                    .header("content-type", "application/json")
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+        };
+
+        assert!(!tracing::enabled!(tracing::Level::DEBUG));
+        let response = put(r#"{"filter":"debug"}"#).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let status: LogFilterStatus =
+            serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap())
+                .unwrap();
+        assert_eq!(
+            (status.filter.as_str(), status.initial.as_str()),
+            ("debug", "info")
+        );
+        assert!(tracing::enabled!(tracing::Level::DEBUG));
+
+        let response = put(r#"{"filter":"info,[{bad"}"#).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(handle.current(), "debug");
+
+        handle.set_for("trace", Duration::from_millis(20)).unwrap();
+        assert!(tracing::enabled!(tracing::Level::TRACE));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(handle.current(), "info");
+        assert!(!tracing::enabled!(tracing::Level::DEBUG));
+    }
+}
@@ -1,13 +1,23 @@
-use openportio_server::OpenportioServer;
+use openportio_core::Environment;
+use openportio_server::{log_filter, openportio_buildinfo, OpenportioServer};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let directives = std::env::var("RUST_LOG")
+        .ok()
+        .filter(|directives| tracing_subscriber::EnvFilter::try_new(directives).is_ok())
+        .unwrap_or_else(|| Environment::from_env().default_log_filter().to_string());
+    let (filter, log_filter) = log_filter::reloadable_env_filter(&directives)?;
     tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::from_default_env())
+        .with(filter)
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    OpenportioServer::new().run().await?;
+    OpenportioServer::new()
+        .with_build_info(openportio_buildinfo!())
+        .with_log_filter(log_filter)
+        .run()
+        .await?;
     Ok(())
 }
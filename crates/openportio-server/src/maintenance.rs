@@ -0,0 +1,302 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, Extensions, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{ApiErrorResponse, ValidatedJson},
+    audit::{AuditEvent, AuditEventKind},
+    middleware::{is_grpc_request, HEALTH_PATHS},
+};
+
+pub const MAINTENANCE_PATH: &str = "/internal/maintenance";
+
+const DEFAULT_RETRY_AFTER_SECONDS: u64 = 60;
+const FLAG_FILE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+// Admin routes stay reachable so maintenance can be switched off again.
+const ADMIN_PREFIX: &str = "/internal/";
+
+/// Shared maintenance switch. While on, the shared middleware answers `503` + `Retry-After`
+/// (gRPC `UNAVAILABLE`) for everything except health probes and `/internal/*` admin routes.
+/// Clones share the same switch.
+#[derive(Debug, Clone)]
+pub struct MaintenanceMode {
+    enabled: Arc<AtomicBool>,
+    retry_after_seconds: Arc<AtomicU64>,
+    flag_file: Option<Arc<PathBuf>>,
+    /// Last observed presence of the flag file, refreshed by [`MaintenanceMode::watch_flag_file`].
+    flag_present: Arc<AtomicBool>,
+}
+
+impl Default for MaintenanceMode {
+    fn default() -> Self {
+        Self {
+            enabled: Arc::new(AtomicBool::new(false)),
+            retry_after_seconds: Arc::new(AtomicU64::new(DEFAULT_RETRY_AFTER_SECONDS)),
+            flag_file: None,
+            flag_present: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+impl MaintenanceMode {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also in maintenance while `path` exists, e.g. `touch /run/app/maintenance` during a deploy.
+    /// The file is checked once here and then polled by [`Self::watch_flag_file`], which
+    /// `OpenportioServer` runs as a background task; requests never touch the disk.
+    pub fn with_flag_file(mut self, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        self.flag_present.store(path.exists(), Ordering::Relaxed);
+        self.flag_file = Some(Arc::new(path));
+        self
+    }
+
+    pub fn with_retry_after_seconds(self, seconds: u64) -> Self {
+        self.retry_after_seconds.store(seconds, Ordering::Relaxed);
+        self
+    }
+
+    pub fn enable(&self) {
+        self.enabled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn disable(&self) {
+        self.enabled.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed) || self.flag_present.load(Ordering::Relaxed)
+    }
+
+    pub fn retry_after_seconds(&self) -> u64 {
+        self.retry_after_seconds.load(Ordering::Relaxed)
+    }
+
+    pub fn flag_file(&self) -> Option<&PathBuf> {
+        self.flag_file.as_deref()
+    }
+
+    /// Polls the flag file every second; returns right away without one.
+    pub async fn watch_flag_file(self) {
+        if self.flag_file.is_none() {
+            return;
+        }
+        let mut interval = tokio::time::interval(FLAG_FILE_POLL_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            self.refresh_flag_file().await;
+        }
+    }
+
+    async fn refresh_flag_file(&self) {
+        let Some(path) = &self.flag_file else {
+            return;
+        };
+        let present = tokio::fs::try_exists(path.as_path()).await.unwrap_or(false);
+        if self.flag_present.swap(present, Ordering::Relaxed) != present {
+            tracing::warn!(flag_file = %path.display(), present, "maintenance flag file changed");
+        }
+    }
+}
+
+pub(crate) async fn maintenance_middleware(
+    State(mode): State<MaintenanceMode>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let path = req.uri().path();
+    if !mode.is_active() || HEALTH_PATHS.contains(&path) || path.starts_with(ADMIN_PREFIX) {
+        return next.run(req).await;
+    }
+    maintenance_response(mode.retry_after_seconds(), is_grpc_request(req.headers()))
+}
+
+fn maintenance_response(retry_after_seconds: u64, is_grpc: bool) -> Response {
+    let message = "service is under maintenance; retry later";
+    let mut response = if is_grpc {
+        tonic::Status::unavailable(message)
+            .into_http()
+            .map(Body::new)
+    } else {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ApiErrorResponse {
+                code: "maintenance".to_string(),
+                message: message.to_string(),
+                detail: None,
+                details: None,
+            }),
+        )
+            .into_response()
+    };
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(retry_after_seconds));
+    response
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MaintenanceStatus {
+    enabled: bool,
+    retry_after_seconds: u64,
+}
+
+#[derive(Debug, Deserialize, validator::Validate, crate::RequestValidation)]
+struct MaintenanceUpdate {
+    enabled: bool,
+    #[validate(range(min = 1))]
+    retry_after_seconds: Option<u64>,
+}
+
+/// `GET` reports and `PUT {"enabled": true, "retry_after_seconds": 120}` flips the switch.
+/// The flag file, when configured, keeps maintenance on regardless.
+pub fn maintenance_router(mode: MaintenanceMode) -> Router {
+    Router::new()
+        .route(
+            MAINTENANCE_PATH,
+            get(maintenance_status).put(update_maintenance),
+        )
+        .with_state(mode)
+}
+
+async fn maintenance_status(State(mode): State<MaintenanceMode>) -> Json<MaintenanceStatus> {
+    Json(MaintenanceStatus {
+        enabled: mode.is_active(),
+        retry_after_seconds: mode.retry_after_seconds(),
+    })
+}
+
+async fn update_maintenance(
+    State(mode): State<MaintenanceMode>,
+    extensions: Extensions,
+    ValidatedJson(update): ValidatedJson<MaintenanceUpdate>,
+) -> Json<MaintenanceStatus> {
+    if let Some(seconds) = update.retry_after_seconds {
+        mode.retry_after_seconds.store(seconds, Ordering::Relaxed);
+    }
+    if update.enabled {
+        mode.enable();
+    } else {
+        mode.disable();
+    }
+    tracing::warn!(enabled = update.enabled, "maintenance mode switched");
+    AuditEvent::from_extensions(AuditEventKind::ConfigReload, &extensions)
+        .with_reason(format!("maintenance mode enabled={}", update.enabled))
+        .emit(&extensions);
+    maintenance_status(State(mode)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::{apply_shared_middleware, MiddlewareConfig};
+    use axum::http::Request;
+    use tower::util::ServiceExt;
+
+    #[tokio::test]
+    async fn maintenance_rejects_traffic_but_serves_probes_and_admin() {
+        let mode = MaintenanceMode::new().with_retry_after_seconds(30);
+        let config = MiddlewareConfig {
+            maintenance: mode.clone(),
+            ..MiddlewareConfig::default()
+        };
+        let app = apply_shared_middleware(
+            Router::new()
+                .route("/orders", get(|| async { "orders" }))
+                .route("/livez", get(|| async { "live" }))
+                .merge(maintenance_router(mode.clone())),
+            &config,
+        );
+        let request = |method: &str, uri: &str, body: &'static str| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(request("GET", "/orders", ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .clone()
+            .oneshot(request("PUT", MAINTENANCE_PATH, r#"{"enabled":true}"#))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(mode.is_active());
+
+        let response = app
+            .clone()
+            .oneshot(request("GET", "/orders", ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "30");
+
+        let response = app
+            .clone()
+            .oneshot(request("GET", "/livez", ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let mut grpc = request("POST", "/openportio.v1.Greeter/SayHello", "");
+        grpc.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/grpc"),
+        );
+        let response = app.clone().oneshot(grpc).await.unwrap();
+        assert_eq!(
+            response.headers()["grpc-status"],
+            (tonic::Code::Unavailable as i32).to_string().as_str()
+        );
+
+        mode.disable();
+        let response = app.oneshot(request("GET", "/orders", "")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn flag_file_turns_maintenance_on_while_present() {
+        let dir = std::env::temp_dir().join(format!("openportio-maint-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let flag = dir.join("maintenance");
+        std::fs::write(&flag, b"").unwrap();
+        let mode = MaintenanceMode::new().with_flag_file(&flag);
+        assert!(mode.is_active());
+        std::fs::remove_file(&flag).unwrap();
+        // Only the poller looks at the disk.
+        assert!(mode.is_active());
+        mode.refresh_flag_file().await;
+        assert!(!mode.is_active());
+        std::fs::write(&flag, b"").unwrap();
+        mode.refresh_flag_file().await;
+        assert!(mode.is_active());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,24 +1,73 @@
-use std::{env, str::FromStr, time::Duration};
+use std::{
+    collections::HashMap,
+    fmt,
+    net::IpAddr,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    time::Duration,
+};
 
 use axum::{
+    body::Body,
     error_handling::HandleErrorLayer,
-    http::{HeaderName, HeaderValue, StatusCode},
-    BoxError, Router,
+    extract::{Request, State},
+    http::{header, HeaderName, HeaderValue, Method, StatusCode},
+    middleware::{from_fn, from_fn_with_state, Next},
+    response::{IntoResponse, Response},
+    BoxError, Json, Router,
 };
+use futures_util::StreamExt;
+use tokio::{sync::Semaphore, time::Instant};
 use tower::{limit::ConcurrencyLimitLayer, timeout::TimeoutLayer, ServiceBuilder};
 use tower_http::{
-    cors::{Any, CorsLayer},
-    limit::RequestBodyLimitLayer,
     request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
-    trace::TraceLayer,
+    trace::{MakeSpan, TraceLayer},
+};
+use tracing::Span;
+
+use crate::{
+    adaptive_concurrency::AdaptiveConcurrency,
+    alerts::AlertRules,
+    api::ApiErrorResponse,
+    client_concurrency::ClientConcurrency,
+    client_ip::{self, ForwardedHeader, IpFilter, IpNet},
+    context,
+    cors::{self, CorsLayers, CorsPolicy},
+    di,
+    grpc_limits::GrpcMessageConfig,
+    handler_timeout::HandlerTimeouts,
+    maintenance::{self, MaintenanceMode},
+    redirect::{self, PathNormalization},
+    response_headers::{self, ResponseHeaders},
+    trace_context,
 };
 
 const REQUEST_ID_HEADER: &str = "x-request-id";
 const DEFAULT_TIMEOUT_SECONDS: u64 = 15;
 const DEFAULT_MAX_IN_FLIGHT_REQUESTS: usize = 1024;
 const DEFAULT_REQUEST_BODY_LIMIT_BYTES: usize = 1_048_576;
+const DEFAULT_HEALTH_RESERVED_IN_FLIGHT: usize = 8;
+const DEFAULT_LOAD_SHED_RETRY_AFTER_SECONDS: u64 = 1;
+const RATE_LIMIT_MAX_TRACKED_CLIENTS: usize = 10_000;
+pub(crate) const HEALTH_PATHS: &[&str] = &["/health", "/livez", "/readyz"];
+// Only filled in when the handler did not set the header itself.
+const SECURITY_HEADERS: &[(&str, &str)] = &[
+    ("x-content-type-options", "nosniff"),
+    ("x-frame-options", "DENY"),
+    ("referrer-policy", "no-referrer"),
+    (
+        "strict-transport-security",
+        "max-age=31536000; includeSubDomains",
+    ),
+    ("cross-origin-opener-policy", "same-origin"),
+];
+pub const GRPC_TIMEOUT_HEADER: &str = "grpc-timeout";
+pub const REQUEST_TIMEOUT_HEADER: &str = "x-request-timeout";
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub enum CorsAllowOrigins {
     #[default]
     None,
@@ -26,12 +75,81 @@ pub enum CorsAllowOrigins {
     List(Vec<HeaderValue>),
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverloadPolicy {
+    #[default]
+    Queue,
+    Shed,
+}
+
+impl FromStr for OverloadPolicy {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "queue" => Ok(Self::Queue),
+            "shed" | "load_shed" | "load-shed" => Ok(Self::Shed),
+            other => Err(format!("unsupported overload policy `{other}`")),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct MiddlewareConfig {
     pub timeout_seconds: u64,
     pub max_in_flight_requests: usize,
     pub max_request_body_bytes: usize,
-    pub cors_allow_origins: CorsAllowOrigins,
+    /// Origins, methods, headers, credentials and per-route overrides; see [`CorsPolicy`].
+    pub cors: CorsPolicy,
+    pub overload_policy: OverloadPolicy,
+    pub health_reserved_in_flight: usize,
+    pub load_shed_retry_after_seconds: u64,
+    /// Latency-driven in-flight limit applied inside the fixed `max_in_flight_requests`.
+    pub adaptive_concurrency: Option<AdaptiveConcurrency>,
+    /// In-flight cap per principal or client IP, below the global ceilings.
+    pub client_concurrency: Option<ClientConcurrency>,
+    pub honor_client_deadlines: bool,
+    pub trusted_proxies: Vec<IpNet>,
+    /// The one forwarding header read from trusted proxies.
+    pub trusted_proxy_header: ForwardedHeader,
+    pub ip_filter: IpFilter,
+    pub security_headers: bool,
+    pub rate_limit: Option<RateLimit>,
+    pub maintenance: MaintenanceMode,
+    /// Calls slower than this are logged and counted by the slow request detector.
+    pub slow_request_threshold: Option<Duration>,
+    /// Handler execution timeouts, checked inside the total `timeout_seconds`.
+    pub handler_timeouts: HandlerTimeouts,
+    /// Adds `X-Response-Time-Ms` and `Server-Timing` phase timings to responses; ignored in
+    /// production.
+    pub debug_timings: bool,
+    /// Per-route error-rate thresholds reported to the registered `AlertSink`.
+    pub error_alerts: Option<AlertRules>,
+    pub path_normalization: PathNormalization,
+    /// Default `Server` / `Cache-Control` / custom headers and headers to strip.
+    pub response_headers: ResponseHeaders,
+    /// Per-message gRPC size limits and accepted request compression.
+    pub grpc_messages: GrpcMessageConfig,
+}
+
+/// Fixed-window request budget per resolved client address; health probes are exempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub max_requests: u32,
+    pub window: Duration,
+}
+
+impl RateLimit {
+    pub fn new(max_requests: u32, window: Duration) -> Self {
+        Self {
+            max_requests,
+            window,
+        }
+    }
+
+    pub fn per_minute(max_requests: u32) -> Self {
+        Self::new(max_requests, Duration::from_secs(60))
+    }
 }
 
 impl Default for MiddlewareConfig {
@@ -40,62 +158,551 @@ impl Default for MiddlewareConfig {
             timeout_seconds: DEFAULT_TIMEOUT_SECONDS,
             max_in_flight_requests: DEFAULT_MAX_IN_FLIGHT_REQUESTS,
             max_request_body_bytes: DEFAULT_REQUEST_BODY_LIMIT_BYTES,
-            cors_allow_origins: CorsAllowOrigins::None,
+            cors: CorsPolicy::default(),
+            overload_policy: OverloadPolicy::Queue,
+            health_reserved_in_flight: DEFAULT_HEALTH_RESERVED_IN_FLIGHT,
+            load_shed_retry_after_seconds: DEFAULT_LOAD_SHED_RETRY_AFTER_SECONDS,
+            adaptive_concurrency: None,
+            client_concurrency: None,
+            honor_client_deadlines: true,
+            trusted_proxies: Vec::new(),
+            trusted_proxy_header: ForwardedHeader::default(),
+            ip_filter: IpFilter::default(),
+            security_headers: false,
+            rate_limit: None,
+            maintenance: MaintenanceMode::default(),
+            slow_request_threshold: None,
+            handler_timeouts: HandlerTimeouts::default(),
+            debug_timings: false,
+            error_alerts: None,
+            path_normalization: PathNormalization::default(),
+            response_headers: ResponseHeaders::default(),
+            grpc_messages: GrpcMessageConfig::default(),
         }
     }
 }
 
 impl MiddlewareConfig {
-    pub fn from_env() -> Self {
-        Self {
-            timeout_seconds: read_env_with_aliases(&[
-                "OPENPORTIO_TIMEOUT_SECONDS",
-                "MELD_TIMEOUT_SECONDS",
-                "ALLOY_TIMEOUT_SECONDS",
-            ])
-            .unwrap_or(DEFAULT_TIMEOUT_SECONDS),
-            max_in_flight_requests: read_env_with_aliases(&[
-                "OPENPORTIO_MAX_IN_FLIGHT_REQUESTS",
-                "MELD_MAX_IN_FLIGHT_REQUESTS",
-                "ALLOY_MAX_IN_FLIGHT_REQUESTS",
-            ])
-            .unwrap_or(DEFAULT_MAX_IN_FLIGHT_REQUESTS),
-            max_request_body_bytes: read_env_with_aliases(&[
-                "OPENPORTIO_REQUEST_BODY_LIMIT_BYTES",
-                "MELD_REQUEST_BODY_LIMIT_BYTES",
-                "ALLOY_REQUEST_BODY_LIMIT_BYTES",
-            ])
-            .unwrap_or(DEFAULT_REQUEST_BODY_LIMIT_BYTES),
-            cors_allow_origins: parse_cors_allow_origins(read_env_string_with_aliases(&[
-                "OPENPORTIO_CORS_ALLOW_ORIGINS",
-                "MELD_CORS_ALLOW_ORIGINS",
-                "ALLOY_CORS_ALLOW_ORIGINS",
-            ])),
+    /// Reads the `OPENPORTIO_*` middleware settings; unset ones keep their defaults, and a set
+    /// but malformed one is an error.
+    pub fn from_env() -> Result<Self, String> {
+        Ok(Self {
+            timeout_seconds: crate::env::setting("TIMEOUT_SECONDS")?
+                .unwrap_or(DEFAULT_TIMEOUT_SECONDS),
+            max_in_flight_requests: crate::env::setting("MAX_IN_FLIGHT_REQUESTS")?
+                .unwrap_or(DEFAULT_MAX_IN_FLIGHT_REQUESTS),
+            max_request_body_bytes: crate::env::setting("REQUEST_BODY_LIMIT_BYTES")?
+                .unwrap_or(DEFAULT_REQUEST_BODY_LIMIT_BYTES),
+            cors: cors_policy_from_env()?,
+            overload_policy: crate::env::setting("OVERLOAD_POLICY")?.unwrap_or_default(),
+            health_reserved_in_flight: crate::env::setting("HEALTH_RESERVED_IN_FLIGHT")?
+                .unwrap_or(DEFAULT_HEALTH_RESERVED_IN_FLIGHT),
+            load_shed_retry_after_seconds: crate::env::setting("LOAD_SHED_RETRY_AFTER_SECONDS")?
+                .unwrap_or(DEFAULT_LOAD_SHED_RETRY_AFTER_SECONDS),
+            adaptive_concurrency: AdaptiveConcurrency::from_env()?,
+            client_concurrency: ClientConcurrency::from_env()?,
+            honor_client_deadlines: crate::env::setting("HONOR_CLIENT_DEADLINES")?.unwrap_or(true),
+            trusted_proxies: read_ip_nets("TRUSTED_PROXIES")?,
+            trusted_proxy_header: crate::env::setting("TRUSTED_PROXY_HEADER")?.unwrap_or_default(),
+            ip_filter: ip_filter_from_env()?,
+            security_headers: crate::env::setting("SECURITY_HEADERS")?.unwrap_or(false),
+            rate_limit: crate::env::setting("RATE_LIMIT_PER_MINUTE")?.map(RateLimit::per_minute),
+            maintenance: maintenance_from_env()?,
+            slow_request_threshold: crate::env::setting("SLOW_REQUEST_THRESHOLD_MS")?
+                .map(Duration::from_millis),
+            handler_timeouts: crate::env::setting("HANDLER_TIMEOUT_MS")?
+                .map(|ms| HandlerTimeouts::new().with_default(Duration::from_millis(ms)))
+                .unwrap_or_default(),
+            debug_timings: crate::env::setting("DEBUG_TIMINGS")?.unwrap_or(false),
+            error_alerts: AlertRules::from_env()?,
+            path_normalization: PathNormalization {
+                trailing_slash: crate::env::setting("TRAILING_SLASH")?.unwrap_or_default(),
+                lowercase: crate::env::setting("LOWERCASE_PATHS")?.unwrap_or(false),
+            },
+            response_headers: ResponseHeaders::parse(
+                crate::env::var("RESPONSE_HEADERS")?.as_deref(),
+                crate::env::var("REMOVE_RESPONSE_HEADERS")?.as_deref(),
+            ),
+            grpc_messages: grpc_messages_from_env()?,
+        })
+    }
+
+    /// Names of the layers `apply_shared_middleware` installs, outermost first.
+    pub fn layer_names(&self) -> Vec<&'static str> {
+        let mut names = vec!["set_request_id", "propagate_request_id", "trace", "timeout"];
+        if self.overload_policy == OverloadPolicy::Queue {
+            names.push("concurrency_limit");
+        }
+        if self.response_headers.is_enabled() {
+            names.push("response_headers");
+        }
+        if self.security_headers {
+            names.push("security_headers");
+        }
+        if self.path_normalization.is_enabled() {
+            names.push("path_normalization");
+        }
+        names.push("maintenance");
+        names.push("client_ip");
+        if self.rate_limit.is_some() {
+            names.push("rate_limit");
         }
+        names.push("body_limit");
+        if self.overload_policy == OverloadPolicy::Shed {
+            names.push("load_shed");
+        }
+        if self.honor_client_deadlines {
+            names.push("client_deadline");
+        }
+        if self.cors.is_enabled() {
+            names.push("cors");
+        }
+        names.push("trace_context");
+        names.push("request_context");
+        names
+    }
+}
+
+/// Client-supplied deadline attached to request extensions (and therefore to
+/// `tonic::Request::extensions()` for gRPC handlers).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestDeadline {
+    deadline: Instant,
+}
+
+impl RequestDeadline {
+    /// `None` when `timeout` is too far out for an `Instant`, which the middleware treats as no
+    /// deadline.
+    pub fn after(timeout: Duration) -> Option<Self> {
+        Instant::now()
+            .checked_add(timeout)
+            .map(|deadline| Self { deadline })
+    }
+
+    pub fn instant(&self) -> Instant {
+        self.deadline
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.deadline.saturating_duration_since(Instant::now())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_zero()
     }
 }
 
 pub fn apply_shared_middleware(app: Router, config: &MiddlewareConfig) -> Router {
-    let app = match &config.cors_allow_origins {
-        CorsAllowOrigins::None => app,
-        CorsAllowOrigins::Any => app.layer(CorsLayer::new().allow_origin(Any)),
-        CorsAllowOrigins::List(origins) => {
-            app.layer(CorsLayer::new().allow_origin(origins.clone()))
-        }
+    let app = app.layer(from_fn(context::request_context_middleware));
+    let app = app.layer(from_fn(trace_context::trace_context_middleware));
+    let app = if config.cors.is_enabled() {
+        app.layer(from_fn_with_state(
+            CorsLayers::new(&config.cors),
+            cors::cors_middleware,
+        ))
+    } else {
+        app
+    };
+
+    let app = if config.honor_client_deadlines {
+        app.layer(from_fn(deadline_middleware))
+    } else {
+        app
+    };
+
+    let app = match config.overload_policy {
+        OverloadPolicy::Queue => app,
+        OverloadPolicy::Shed => app.layer(from_fn_with_state(
+            LoadShedState::new(config),
+            load_shed_middleware,
+        )),
+    };
+    let app = app.layer(from_fn_with_state(
+        config.max_request_body_bytes,
+        body_limit_middleware,
+    ));
+    let app = match config.rate_limit {
+        Some(limit) => app.layer(from_fn_with_state(
+            RateLimitState::new(limit),
+            rate_limit_middleware,
+        )),
+        None => app,
+    };
+    let app = app.layer(from_fn_with_state(
+        client_ip::ClientIpState::new(
+            &config.trusted_proxies,
+            config.trusted_proxy_header,
+            &config.ip_filter,
+        ),
+        client_ip::client_ip_middleware,
+    ));
+    let app = app.layer(from_fn_with_state(
+        config.maintenance.clone(),
+        maintenance::maintenance_middleware,
+    ));
+    let app = if config.path_normalization.is_enabled() {
+        app.layer(from_fn_with_state(
+            config.path_normalization,
+            redirect::path_normalization_middleware,
+        ))
+    } else {
+        app
+    };
+    let app = if config.security_headers {
+        app.layer(from_fn(security_headers_middleware))
+    } else {
+        app
     };
+    let app = if config.response_headers.is_enabled() {
+        app.layer(from_fn_with_state(
+            config.response_headers.clone(),
+            response_headers::response_headers_middleware,
+        ))
+    } else {
+        app
+    };
+    let queue_limit = (config.overload_policy == OverloadPolicy::Queue)
+        .then(|| ConcurrencyLimitLayer::new(config.max_in_flight_requests));
 
     app.layer(
         ServiceBuilder::new()
             .layer(HandleErrorLayer::new(handle_middleware_error))
-            .layer(TraceLayer::new_for_http())
-            .layer(PropagateRequestIdLayer::new(header_name()))
             .layer(SetRequestIdLayer::new(header_name(), MakeRequestUuid))
-            .layer(RequestBodyLimitLayer::new(config.max_request_body_bytes))
+            .layer(PropagateRequestIdLayer::new(header_name()))
+            .layer(TraceLayer::new_for_http().make_span_with(RequestSpan))
             .layer(TimeoutLayer::new(Duration::from_secs(
                 config.timeout_seconds,
             )))
-            .layer(ConcurrencyLimitLayer::new(config.max_in_flight_requests)),
+            .option_layer(queue_limit),
+    )
+}
+
+/// `TraceLayer` span carrying the `x-request-id` for both protocols, so REST and gRPC handler
+/// logs correlate by id; gRPC calls are named by their method path.
+#[derive(Debug, Clone, Copy)]
+struct RequestSpan;
+
+impl<B> MakeSpan<B> for RequestSpan {
+    fn make_span(&mut self, req: &axum::http::Request<B>) -> Span {
+        let request_id = req
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("-");
+        if is_grpc_request(req.headers()) {
+            tracing::debug_span!("request", protocol = "grpc", rpc = %req.uri().path(), request_id)
+        } else {
+            tracing::debug_span!(
+                "request",
+                protocol = "rest",
+                method = %req.method(),
+                uri = %req.uri(),
+                version = ?req.version(),
+                request_id,
+            )
+        }
+    }
+}
+
+// Replaces tower-http's `RequestBodyLimitLayer` so oversized bodies get a JSON `413` for REST and
+// `RESOURCE_EXHAUSTED` for gRPC, whether the limit trips on `Content-Length` or while streaming.
+async fn body_limit_middleware(State(limit): State<usize>, req: Request, next: Next) -> Response {
+    let is_grpc = is_grpc_request(req.headers());
+    let declared_length = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    if declared_length.is_some_and(|length| length > limit as u64) {
+        return payload_too_large_response(limit, is_grpc);
+    }
+
+    let exceeded = Arc::new(AtomicBool::new(false));
+    let (parts, body) = req.into_parts();
+    let body = {
+        let exceeded = Arc::clone(&exceeded);
+        let mut received = 0usize;
+        Body::from_stream(body.into_data_stream().map(move |chunk| {
+            let bytes = chunk?;
+            received = received.saturating_add(bytes.len());
+            if received > limit {
+                exceeded.store(true, Ordering::Relaxed);
+                return Err(axum::Error::new(BodyLimitExceeded { limit }));
+            }
+            Ok(bytes)
+        }))
+    };
+
+    let response = next.run(Request::from_parts(parts, body)).await;
+    if exceeded.load(Ordering::Relaxed) {
+        tracing::warn!(limit, grpc = is_grpc, "request body exceeded size limit");
+        return payload_too_large_response(limit, is_grpc);
+    }
+    response
+}
+
+#[derive(Debug)]
+struct BodyLimitExceeded {
+    limit: usize,
+}
+
+impl fmt::Display for BodyLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "request body exceeds the {} byte limit", self.limit)
+    }
+}
+
+impl std::error::Error for BodyLimitExceeded {}
+
+fn payload_too_large_response(limit: usize, is_grpc: bool) -> Response {
+    let message = BodyLimitExceeded { limit }.to_string();
+    if is_grpc {
+        return tonic::Status::resource_exhausted(message)
+            .into_http()
+            .map(Body::new);
+    }
+    (
+        StatusCode::PAYLOAD_TOO_LARGE,
+        Json(ApiErrorResponse {
+            code: "payload_too_large".to_string(),
+            message,
+            detail: None,
+            details: Some(serde_json::json!({ "limit_bytes": limit })),
+        }),
     )
+        .into_response()
+}
+
+#[derive(Clone)]
+struct LoadShedState {
+    general: Arc<Semaphore>,
+    health: Arc<Semaphore>,
+    retry_after_seconds: u64,
+}
+
+impl LoadShedState {
+    fn new(config: &MiddlewareConfig) -> Self {
+        Self {
+            general: Arc::new(Semaphore::new(config.max_in_flight_requests)),
+            health: Arc::new(Semaphore::new(config.health_reserved_in_flight)),
+            retry_after_seconds: config.load_shed_retry_after_seconds,
+        }
+    }
+}
+
+async fn load_shed_middleware(
+    State(state): State<LoadShedState>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let permit = match state.general.clone().try_acquire_owned() {
+        Ok(permit) => Some(permit),
+        Err(_) if HEALTH_PATHS.contains(&req.uri().path()) => {
+            state.health.clone().try_acquire_owned().ok()
+        }
+        Err(_) => None,
+    };
+
+    let Some(_permit) = permit else {
+        tracing::warn!(path = %req.uri().path(), "shedding request: in-flight limit reached");
+        return overloaded_response(state.retry_after_seconds);
+    };
+    next.run(req).await
+}
+
+pub(crate) fn overloaded_response(retry_after_seconds: u64) -> Response {
+    let mut response = (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(ApiErrorResponse {
+            code: "overloaded".to_string(),
+            message: "server is overloaded; retry later".to_string(),
+            detail: None,
+            details: None,
+        }),
+    )
+        .into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(retry_after_seconds));
+    response
+}
+
+// Window start and requests counted so far, keyed by client address.
+type RateLimitWindows = HashMap<Option<IpAddr>, (Instant, u32)>;
+
+#[derive(Clone)]
+struct RateLimitState {
+    limit: RateLimit,
+    windows: Arc<Mutex<RateLimitWindows>>,
+}
+
+impl RateLimitState {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            windows: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    // Requests without a resolved address share one budget. Returns the seconds until the
+    // client's window resets once the budget is spent.
+    fn consume(&self, client_ip: Option<IpAddr>, now: Instant) -> Result<(), u64> {
+        let window = self.limit.window;
+        let mut windows = self.windows.lock().unwrap_or_else(PoisonError::into_inner);
+        if windows.len() >= RATE_LIMIT_MAX_TRACKED_CLIENTS {
+            windows.retain(|_, (started, _)| now.duration_since(*started) < window);
+        }
+        let (started, count) = windows.entry(client_ip).or_insert((now, 0));
+        if now.duration_since(*started) >= window {
+            *started = now;
+            *count = 0;
+        }
+        if *count >= self.limit.max_requests {
+            let remaining = window.saturating_sub(now.duration_since(*started));
+            return Err(remaining.as_secs().max(1));
+        }
+        *count += 1;
+        Ok(())
+    }
+}
+
+async fn rate_limit_middleware(
+    State(state): State<RateLimitState>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if HEALTH_PATHS.contains(&req.uri().path()) {
+        return next.run(req).await;
+    }
+    let client_ip = req
+        .extensions()
+        .get::<client_ip::ClientIp>()
+        .map(|client_ip::ClientIp(ip)| *ip);
+    let now = Instant::from_std(di::clock_from_extensions(req.extensions()).now());
+    if let Err(retry_after_seconds) = state.consume(client_ip, now) {
+        tracing::warn!(client_ip = ?client_ip, "rate limit exceeded");
+        return rate_limited_response(retry_after_seconds, is_grpc_request(req.headers()));
+    }
+    next.run(req).await
+}
+
+fn rate_limited_response(retry_after_seconds: u64, is_grpc: bool) -> Response {
+    let message = "rate limit exceeded; retry later";
+    if is_grpc {
+        return tonic::Status::resource_exhausted(message)
+            .into_http()
+            .map(Body::new);
+    }
+    let mut response = (
+        StatusCode::TOO_MANY_REQUESTS,
+        Json(ApiErrorResponse {
+            code: "rate_limited".to_string(),
+            message: message.to_string(),
+            detail: None,
+            details: Some(serde_json::json!({ "retry_after_seconds": retry_after_seconds })),
+        }),
+    )
+        .into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(retry_after_seconds));
+    response
+}
+
+async fn security_headers_middleware(req: Request, next: Next) -> Response {
+    let mut response = next.run(req).await;
+    let headers = response.headers_mut();
+    for (name, value) in SECURITY_HEADERS {
+        headers
+            .entry(*name)
+            .or_insert_with(|| HeaderValue::from_static(value));
+    }
+    response
+}
+
+async fn deadline_middleware(mut req: Request, next: Next) -> Response {
+    let Some((timeout, deadline)) = client_timeout(req.headers())
+        .and_then(|timeout| RequestDeadline::after(timeout).map(|deadline| (timeout, deadline)))
+    else {
+        return next.run(req).await;
+    };
+    let is_grpc = is_grpc_request(req.headers());
+    req.extensions_mut().insert(deadline);
+
+    match tokio::time::timeout_at(deadline.instant(), next.run(req)).await {
+        Ok(response) => response,
+        Err(_) => {
+            tracing::warn!(
+                timeout_ms = timeout.as_millis() as u64,
+                grpc = is_grpc,
+                "client deadline exceeded; cancelling handler"
+            );
+            deadline_exceeded_response(is_grpc)
+        }
+    }
+}
+
+fn client_timeout(headers: &axum::http::HeaderMap) -> Option<Duration> {
+    let header_value = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    header_value(GRPC_TIMEOUT_HEADER)
+        .and_then(parse_grpc_timeout)
+        .or_else(|| header_value(REQUEST_TIMEOUT_HEADER).and_then(parse_request_timeout))
+}
+
+pub(crate) fn is_grpc_request(headers: &axum::http::HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/grpc"))
+}
+
+/// Parses the gRPC wire format: up to 8 digits followed by one of `H`, `M`, `S`, `m`, `u`, `n`.
+pub fn parse_grpc_timeout(raw: &str) -> Option<Duration> {
+    let raw = raw.trim();
+    let (digits, unit) = raw.split_at(raw.len().checked_sub(1)?);
+    if digits.is_empty() || digits.len() > 8 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let value = digits.parse::<u64>().ok()?;
+    match unit {
+        "H" => Some(Duration::from_secs(value * 3600)),
+        "M" => Some(Duration::from_secs(value * 60)),
+        "S" => Some(Duration::from_secs(value)),
+        "m" => Some(Duration::from_millis(value)),
+        "u" => Some(Duration::from_micros(value)),
+        "n" => Some(Duration::from_nanos(value)),
+        _ => None,
+    }
+}
+
+/// Parses `x-request-timeout` values: `250ms`, `2s`, or bare milliseconds (`1500`).
+pub fn parse_request_timeout(raw: &str) -> Option<Duration> {
+    let raw = raw.trim();
+    if let Some(millis) = raw.strip_suffix("ms") {
+        return millis.trim().parse::<u64>().ok().map(Duration::from_millis);
+    }
+    if let Some(secs) = raw.strip_suffix('s') {
+        return secs.trim().parse::<u64>().ok().map(Duration::from_secs);
+    }
+    raw.parse::<u64>().ok().map(Duration::from_millis)
+}
+
+fn deadline_exceeded_response(is_grpc: bool) -> Response {
+    if is_grpc {
+        return tonic::Status::deadline_exceeded("deadline exceeded")
+            .into_http()
+            .map(Body::new);
+    }
+    (
+        StatusCode::GATEWAY_TIMEOUT,
+        Json(ApiErrorResponse {
+            code: "deadline_exceeded".to_string(),
+            message: "request deadline exceeded".to_string(),
+            detail: None,
+            details: None,
+        }),
+    )
+        .into_response()
 }
 
 async fn handle_middleware_error(error: BoxError) -> (StatusCode, String) {
@@ -114,22 +721,95 @@ fn header_name() -> HeaderName {
     HeaderName::from_static(REQUEST_ID_HEADER)
 }
 
-fn read_env<T>(name: &str) -> Option<T>
-where
-    T: FromStr,
-{
-    env::var(name).ok().and_then(|raw| raw.parse::<T>().ok())
+fn ip_filter_from_env() -> Result<IpFilter, String> {
+    let allow = read_ip_nets("IP_ALLOWLIST")?;
+    let deny = read_ip_nets("IP_DENYLIST")?;
+    let filter = allow.into_iter().fold(IpFilter::new(), IpFilter::allow);
+    Ok(deny.into_iter().fold(filter, IpFilter::deny))
 }
 
-fn read_env_with_aliases<T>(names: &[&str]) -> Option<T>
-where
-    T: FromStr,
-{
-    names.iter().find_map(|name| read_env(name))
+fn maintenance_from_env() -> Result<MaintenanceMode, String> {
+    let mode = MaintenanceMode::new();
+    let mode = match crate::env::setting("MAINTENANCE_RETRY_AFTER_SECONDS")? {
+        Some(seconds) => mode.with_retry_after_seconds(seconds),
+        None => mode,
+    };
+    let mode = match crate::env::var("MAINTENANCE_FILE")? {
+        Some(path) => mode.with_flag_file(path),
+        None => mode,
+    };
+    if crate::env::setting("MAINTENANCE_MODE")?.unwrap_or(false) {
+        mode.enable();
+    }
+    Ok(mode)
 }
 
-fn read_env_string_with_aliases(names: &[&str]) -> Option<String> {
-    names.iter().find_map(|name| env::var(name).ok())
+pub(crate) fn grpc_messages_from_env() -> Result<GrpcMessageConfig, String> {
+    let defaults = GrpcMessageConfig::default();
+    Ok(GrpcMessageConfig {
+        max_decoding_message_size: crate::env::setting("GRPC_MAX_DECODING_MESSAGE_BYTES")?
+            .unwrap_or(defaults.max_decoding_message_size),
+        max_encoding_message_size: crate::env::setting("GRPC_MAX_ENCODING_MESSAGE_BYTES")?
+            .unwrap_or(defaults.max_encoding_message_size),
+        accept_compression: crate::env::var("GRPC_ACCEPT_COMPRESSION")?
+            .map(|value| GrpcMessageConfig::parse_compression(&value))
+            .unwrap_or(defaults.accept_compression),
+        send_compression: crate::env::var("GRPC_SEND_COMPRESSION")?
+            .map(|value| GrpcMessageConfig::parse_compression(&value))
+            .unwrap_or(defaults.send_compression),
+    })
+}
+
+fn read_ip_nets(suffix: &str) -> Result<Vec<IpNet>, String> {
+    Ok(crate::env::var(suffix)?
+        .map(|raw| client_ip::parse_ip_nets(&raw))
+        .unwrap_or_default())
+}
+
+fn cors_policy_from_env() -> Result<CorsPolicy, String> {
+    let mut policy = CorsPolicy {
+        allow_origins: parse_cors_allow_origins(crate::env::var("CORS_ALLOW_ORIGINS")?),
+        allow_credentials: crate::env::setting("CORS_ALLOW_CREDENTIALS")?.unwrap_or(false),
+        max_age: crate::env::setting("CORS_MAX_AGE_SECONDS")?.map(Duration::from_secs),
+        expose_headers: parse_header_names(crate::env::var("CORS_EXPOSE_HEADERS")?),
+        ..CorsPolicy::default()
+    };
+    if let Some(raw) = crate::env::var("CORS_ALLOW_METHODS")? {
+        policy.allow_methods = raw
+            .split(',')
+            .map(str::trim)
+            .filter(|method| !method.is_empty())
+            .filter_map(|method| match Method::from_str(&method.to_ascii_uppercase()) {
+                Ok(method) => Some(method),
+                Err(err) => {
+                    tracing::warn!(method = %method, error = %err, "ignoring invalid cors method");
+                    None
+                }
+            })
+            .collect();
+    }
+    let headers = crate::env::var("CORS_ALLOW_HEADERS")?;
+    if headers.as_deref().map(str::trim) == Some("*") {
+        policy.allow_any_header = true;
+    } else {
+        policy.allow_headers = parse_header_names(headers);
+    }
+    Ok(policy)
+}
+
+fn parse_header_names(raw: Option<String>) -> Vec<HeaderName> {
+    raw.unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .filter_map(|name| match HeaderName::from_str(name) {
+            Ok(name) => Some(name),
+            Err(err) => {
+                tracing::warn!(header = %name, error = %err, "ignoring invalid cors header");
+                None
+            }
+        })
+        .collect()
 }
 
 fn parse_cors_allow_origins(raw: Option<String>) -> CorsAllowOrigins {
@@ -169,14 +849,14 @@ fn parse_cors_allow_origins(raw: Option<String>) -> CorsAllowOrigins {
 mod tests {
     use super::*;
     use axum::{
-        body::Body,
+        body::{to_bytes, Body},
         http::{header::ORIGIN, Request},
         routing::{get, post},
     };
-    use std::sync::{LazyLock, Mutex};
+    use std::env;
     use tower::util::ServiceExt;
 
-    static ENV_LOCK: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
+    use crate::env::ENV_LOCK;
 
     #[test]
     fn default_config_is_reasonable() {
@@ -190,7 +870,7 @@ mod tests {
             config.max_request_body_bytes,
             DEFAULT_REQUEST_BODY_LIMIT_BYTES
         );
-        assert!(matches!(config.cors_allow_origins, CorsAllowOrigins::None));
+        assert_eq!(config.cors, CorsPolicy::default());
     }
 
     #[test]
@@ -255,6 +935,61 @@ mod tests {
             .await
             .expect("request should complete");
         assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("response body");
+        let body: serde_json::Value = serde_json::from_slice(&body).expect("json body");
+        assert_eq!(body["code"], "payload_too_large");
+        assert_eq!(body["details"]["limit_bytes"], 8);
+    }
+
+    #[tokio::test]
+    async fn streamed_oversized_bodies_map_to_json_413_and_grpc_resource_exhausted() {
+        let config = MiddlewareConfig {
+            max_request_body_bytes: 8,
+            ..MiddlewareConfig::default()
+        };
+        let app = apply_shared_middleware(
+            Router::new().route("/echo", post(|body: String| async move { body })),
+            &config,
+        );
+        let chunked = || {
+            Body::from_stream(futures_util::stream::iter([
+                Ok::<_, std::io::Error>("01234".to_string()),
+                Ok("56789".to_string()),
+            ]))
+        };
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/echo")
+                    .body(chunked())
+                    .unwrap(),
+            )
+            .await
+            .expect("request should complete");
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/echo")
+                    .header(header::CONTENT_TYPE, "application/grpc")
+                    .body(chunked())
+                    .unwrap(),
+            )
+            .await
+            .expect("request should complete");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()["grpc-status"],
+            (tonic::Code::ResourceExhausted as i32).to_string().as_str()
+        );
     }
 
     #[tokio::test]
@@ -265,6 +1000,340 @@ mod tests {
         assert_eq!(body, "internal server error");
     }
 
+    #[tokio::test]
+    async fn rate_limit_is_per_client_and_security_headers_fill_gaps() {
+        let config = MiddlewareConfig {
+            security_headers: true,
+            rate_limit: Some(RateLimit::per_minute(1)),
+            ..MiddlewareConfig::default()
+        };
+        let app = apply_shared_middleware(
+            Router::new()
+                .route("/health", get(|| async { "ok" }))
+                .route(
+                    "/framed",
+                    get(|| async { ([("x-frame-options", "SAMEORIGIN")], "ok") }),
+                ),
+            &config,
+        );
+        let request = |path: &str, peer: [u8; 4]| {
+            let mut request = Request::builder().uri(path).body(Body::empty()).unwrap();
+            request.extensions_mut().insert(axum::extract::ConnectInfo(
+                std::net::SocketAddr::from((peer, 4000)),
+            ));
+            request
+        };
+
+        let response = app
+            .clone()
+            .oneshot(request("/framed", [10, 0, 0, 1]))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-frame-options"], "SAMEORIGIN");
+        assert_eq!(response.headers()["x-content-type-options"], "nosniff");
+
+        let response = app
+            .clone()
+            .oneshot(request("/framed", [10, 0, 0, 1]))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key(header::RETRY_AFTER));
+        assert_eq!(response.headers()["x-frame-options"], "DENY");
+        let body: serde_json::Value =
+            serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap())
+                .unwrap();
+        assert_eq!(body["code"], "rate_limited");
+
+        let response = app
+            .clone()
+            .oneshot(request("/framed", [10, 0, 0, 2]))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app
+            .oneshot(request("/health", [10, 0, 0, 1]))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn rate_limit_windows_follow_the_clock_dependency() {
+        let clock = openportio_core::TestClock::new();
+        let config = MiddlewareConfig {
+            rate_limit: Some(RateLimit::per_minute(1)),
+            ..MiddlewareConfig::default()
+        };
+        let app = di::with_dependency(
+            apply_shared_middleware(Router::new().route("/", get(|| async { "ok" })), &config),
+            clock.shared(),
+        );
+        let call = || {
+            app.clone()
+                .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+        };
+
+        assert_eq!(call().await.unwrap().status(), StatusCode::OK);
+        clock.advance(Duration::from_secs(45));
+        let response = call().await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "15");
+        clock.advance(Duration::from_secs(15));
+        assert_eq!(call().await.unwrap().status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn load_shed_policy_rejects_excess_requests_but_serves_health() {
+        let config = MiddlewareConfig {
+            max_in_flight_requests: 1,
+            health_reserved_in_flight: 1,
+            overload_policy: OverloadPolicy::Shed,
+            load_shed_retry_after_seconds: 3,
+            ..MiddlewareConfig::default()
+        };
+        let release = Arc::new(tokio::sync::Notify::new());
+        let entered = Arc::new(tokio::sync::Notify::new());
+        let (release_handler, entered_handler) = (release.clone(), entered.clone());
+        let app = apply_shared_middleware(
+            Router::new()
+                .route(
+                    "/slow",
+                    get(move || async move {
+                        entered_handler.notify_one();
+                        release_handler.notified().await;
+                        "slow"
+                    }),
+                )
+                .route("/fast", get(|| async { "fast" }))
+                .route("/health", get(|| async { "ok" })),
+            &config,
+        );
+
+        let slow = tokio::spawn(
+            app.clone()
+                .oneshot(Request::builder().uri("/slow").body(Body::empty()).unwrap()),
+        );
+        entered.notified().await;
+
+        let shed = app
+            .clone()
+            .oneshot(Request::builder().uri("/fast").body(Body::empty()).unwrap())
+            .await
+            .expect("request should complete");
+        assert_eq!(shed.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            shed.headers()
+                .get(header::RETRY_AFTER)
+                .expect("retry-after header"),
+            "3"
+        );
+
+        let health = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/health")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("request should complete");
+        assert_eq!(health.status(), StatusCode::OK);
+
+        release.notify_one();
+        let slow = slow
+            .await
+            .expect("slow task should join")
+            .expect("slow request should complete");
+        assert_eq!(slow.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn parses_grpc_and_rest_timeout_formats() {
+        assert_eq!(parse_grpc_timeout("250m"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_grpc_timeout("2S"), Some(Duration::from_secs(2)));
+        assert_eq!(parse_grpc_timeout("1H"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse_grpc_timeout("123456789S"), None);
+        assert_eq!(parse_grpc_timeout("10x"), None);
+        assert_eq!(parse_grpc_timeout("S"), None);
+
+        assert_eq!(
+            parse_request_timeout("250ms"),
+            Some(Duration::from_millis(250))
+        );
+        assert_eq!(parse_request_timeout("3s"), Some(Duration::from_secs(3)));
+        assert_eq!(
+            parse_request_timeout("1500"),
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(parse_request_timeout("soon"), None);
+    }
+
+    #[tokio::test]
+    async fn client_deadline_maps_to_gateway_timeout_and_grpc_status() {
+        let app = apply_shared_middleware(
+            Router::new().route(
+                "/slow",
+                post(|| async {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    "slow"
+                }),
+            ),
+            &MiddlewareConfig::default(),
+        );
+
+        let rest = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/slow")
+                    .header(REQUEST_TIMEOUT_HEADER, "20ms")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("request should complete");
+        assert_eq!(rest.status(), StatusCode::GATEWAY_TIMEOUT);
+
+        let grpc = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/slow")
+                    .header(header::CONTENT_TYPE, "application/grpc")
+                    .header(GRPC_TIMEOUT_HEADER, "20m")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("request should complete");
+        assert_eq!(grpc.status(), StatusCode::OK);
+        assert_eq!(
+            grpc.headers()
+                .get("grpc-status")
+                .expect("grpc-status header"),
+            "4"
+        );
+    }
+
+    #[tokio::test]
+    async fn client_deadline_is_visible_to_handlers() {
+        let app = apply_shared_middleware(
+            Router::new().route(
+                "/deadline",
+                get(
+                    |axum::Extension(deadline): axum::Extension<RequestDeadline>| async move {
+                        deadline.remaining().as_millis().to_string()
+                    },
+                ),
+            ),
+            &MiddlewareConfig::default(),
+        );
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/deadline")
+                    .header(REQUEST_TIMEOUT_HEADER, "2s")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("request should complete");
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn unrepresentable_deadlines_are_ignored() {
+        assert_eq!(RequestDeadline::after(Duration::MAX), None);
+
+        let app = apply_shared_middleware(
+            Router::new().route(
+                "/deadline",
+                get(
+                    |deadline: Option<axum::Extension<RequestDeadline>>| async move {
+                        deadline.is_some().to_string()
+                    },
+                ),
+            ),
+            &MiddlewareConfig::default(),
+        );
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/deadline")
+                    .header(REQUEST_TIMEOUT_HEADER, format!("{}s", u64::MAX))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("request should complete");
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"false");
+    }
+
+    #[tokio::test]
+    async fn global_ip_filter_uses_forwarded_client_from_trusted_proxy() {
+        let config = MiddlewareConfig {
+            trusted_proxies: client_ip::parse_ip_nets("10.0.0.0/8"),
+            ip_filter: IpFilter::new().deny("203.0.113.0/24".parse().unwrap()),
+            ..MiddlewareConfig::default()
+        };
+        let app = apply_shared_middleware(
+            Router::new().route(
+                "/whoami",
+                get(
+                    |axum::Extension(client_ip::ClientIp(ip)): axum::Extension<
+                        client_ip::ClientIp,
+                    >| async move { ip.to_string() },
+                ),
+            ),
+            &config,
+        );
+        let request =
+            |forwarded_for: &'static str| {
+                let mut req = Request::builder()
+                    .uri("/whoami")
+                    .header("x-forwarded-for", forwarded_for)
+                    .body(Body::empty())
+                    .unwrap();
+                req.extensions_mut().insert(axum::extract::ConnectInfo(
+                    std::net::SocketAddr::from(([10, 0, 0, 1], 40000)),
+                ));
+                req
+            };
+
+        let response = app.clone().oneshot(request("198.51.100.2")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, "198.51.100.2");
+
+        let response = app.clone().oneshot(request("203.0.113.9")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        // A client-supplied `Forwarded` header does not override the proxy's X-Forwarded-For.
+        let mut spoofed = request("203.0.113.9");
+        spoofed
+            .headers_mut()
+            .insert("forwarded", "for=198.51.100.2".parse().unwrap());
+        let response = app.oneshot(spoofed).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn overload_policy_parses_from_env_values() {
+        assert_eq!("shed".parse::<OverloadPolicy>(), Ok(OverloadPolicy::Shed));
+        assert_eq!(
+            " Queue ".parse::<OverloadPolicy>(),
+            Ok(OverloadPolicy::Queue)
+        );
+        assert!("drop".parse::<OverloadPolicy>().is_err());
+    }
+
     #[test]
     fn from_env_supports_meld_compatibility_aliases() {
         let _guard = ENV_LOCK.lock().expect("env lock");
@@ -274,15 +1343,76 @@ mod tests {
         env::set_var("MELD_MAX_IN_FLIGHT_REQUESTS", "77");
         env::set_var("MELD_REQUEST_BODY_LIMIT_BYTES", "4096");
         env::set_var("MELD_CORS_ALLOW_ORIGINS", "https://legacy.example");
+        // Settings added after the rename have no legacy aliases.
+        env::set_var("MELD_TRUSTED_PROXIES", "10.0.0.0/8");
+        env::set_var("ALLOY_IP_DENYLIST", "192.0.2.13");
+        env::set_var("OPENPORTIO_IP_ALLOWLIST", "192.0.2.0/24, not-an-ip");
 
-        let cfg = MiddlewareConfig::from_env();
+        let cfg = MiddlewareConfig::from_env().expect("settings should parse");
         assert_eq!(cfg.timeout_seconds, 9);
         assert_eq!(cfg.max_in_flight_requests, 77);
         assert_eq!(cfg.max_request_body_bytes, 4096);
-        match cfg.cors_allow_origins {
+        match cfg.cors.allow_origins {
             CorsAllowOrigins::List(origins) => assert_eq!(origins.len(), 1),
             _ => panic!("expected list cors config"),
         }
+        assert!(cfg.trusted_proxies.is_empty());
+        assert_eq!(cfg.ip_filter.allowlist().len(), 1);
+        assert!(cfg.ip_filter.is_allowed("192.0.2.13".parse().unwrap()));
+
+        clear_middleware_env();
+    }
+
+    #[test]
+    fn from_env_reads_load_shed_settings_without_legacy_aliases() {
+        let _guard = ENV_LOCK.lock().expect("env lock");
+        clear_middleware_env();
+
+        env::set_var("MELD_OVERLOAD_POLICY", "shed");
+        env::set_var("ALLOY_LOAD_SHED_RETRY_AFTER_SECONDS", "30");
+        env::set_var("OPENPORTIO_HEALTH_RESERVED_IN_FLIGHT", "2");
+        let cfg = MiddlewareConfig::from_env().expect("settings should parse");
+        assert_eq!(cfg.overload_policy, OverloadPolicy::default());
+        assert_eq!(
+            cfg.load_shed_retry_after_seconds,
+            DEFAULT_LOAD_SHED_RETRY_AFTER_SECONDS
+        );
+        assert_eq!(cfg.health_reserved_in_flight, 2);
+
+        env::set_var("OPENPORTIO_OVERLOAD_POLICY", "shed");
+        env::set_var("OPENPORTIO_LOAD_SHED_RETRY_AFTER_SECONDS", "5");
+        let cfg = MiddlewareConfig::from_env().expect("settings should parse");
+        assert_eq!(cfg.overload_policy, OverloadPolicy::Shed);
+        assert_eq!(cfg.load_shed_retry_after_seconds, 5);
+
+        clear_middleware_env();
+    }
+
+    #[test]
+    fn from_env_reads_the_full_cors_policy() {
+        let _guard = ENV_LOCK.lock().expect("env lock");
+        clear_middleware_env();
+
+        env::set_var("OPENPORTIO_CORS_ALLOW_ORIGINS", "https://app.example");
+        env::set_var("OPENPORTIO_CORS_ALLOW_METHODS", "get, put, bad method");
+        env::set_var("OPENPORTIO_CORS_ALLOW_HEADERS", "*");
+        env::set_var("OPENPORTIO_CORS_ALLOW_CREDENTIALS", "true");
+        env::set_var("OPENPORTIO_CORS_MAX_AGE_SECONDS", "600");
+        env::set_var("OPENPORTIO_CORS_EXPOSE_HEADERS", "x-request-id, etag");
+
+        let cors = MiddlewareConfig::from_env()
+            .expect("settings should parse")
+            .cors;
+        assert_eq!(cors.allow_methods, [Method::GET, Method::PUT]);
+        assert!(cors.allow_any_header);
+        assert!(cors.allow_credentials);
+        assert_eq!(cors.max_age, Some(Duration::from_secs(600)));
+        assert_eq!(cors.expose_headers.len(), 2);
+        assert_eq!(
+            cors.summary(),
+            "https://app.example; methods=GET,PUT; headers=*; credentials; max_age=600s; \
+             expose=x-request-id,etag"
+        );
 
         clear_middleware_env();
     }
@@ -301,6 +1431,32 @@ mod tests {
             "ALLOY_MAX_IN_FLIGHT_REQUESTS",
             "ALLOY_REQUEST_BODY_LIMIT_BYTES",
             "ALLOY_CORS_ALLOW_ORIGINS",
+            "OPENPORTIO_OVERLOAD_POLICY",
+            "OPENPORTIO_HEALTH_RESERVED_IN_FLIGHT",
+            "OPENPORTIO_LOAD_SHED_RETRY_AFTER_SECONDS",
+            "MELD_OVERLOAD_POLICY",
+            "MELD_HEALTH_RESERVED_IN_FLIGHT",
+            "MELD_LOAD_SHED_RETRY_AFTER_SECONDS",
+            "ALLOY_OVERLOAD_POLICY",
+            "ALLOY_HEALTH_RESERVED_IN_FLIGHT",
+            "ALLOY_LOAD_SHED_RETRY_AFTER_SECONDS",
+            "OPENPORTIO_HONOR_CLIENT_DEADLINES",
+            "MELD_HONOR_CLIENT_DEADLINES",
+            "ALLOY_HONOR_CLIENT_DEADLINES",
+            "OPENPORTIO_TRUSTED_PROXIES",
+            "MELD_TRUSTED_PROXIES",
+            "ALLOY_TRUSTED_PROXIES",
+            "OPENPORTIO_IP_ALLOWLIST",
+            "MELD_IP_ALLOWLIST",
+            "ALLOY_IP_ALLOWLIST",
+            "OPENPORTIO_IP_DENYLIST",
+            "MELD_IP_DENYLIST",
+            "ALLOY_IP_DENYLIST",
+            "OPENPORTIO_CORS_ALLOW_METHODS",
+            "OPENPORTIO_CORS_ALLOW_HEADERS",
+            "OPENPORTIO_CORS_ALLOW_CREDENTIALS",
+            "OPENPORTIO_CORS_MAX_AGE_SECONDS",
+            "OPENPORTIO_CORS_EXPOSE_HEADERS",
         ] {
             env::remove_var(key);
         }
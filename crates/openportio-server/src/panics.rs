@@ -0,0 +1,217 @@
+use std::{any::Any, convert::Infallible, sync::Arc};
+
+use axum::{
+    body::Body,
+    extract::{MatchedPath, Request, State},
+    http::{Method, StatusCode},
+    middleware::{from_fn_with_state, Next},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use openportio_core::{Environment, MetricsSink};
+use tower::{Layer, ServiceExt};
+use tower_http::catch_panic::{CatchPanicLayer, ResponseForPanic};
+
+use crate::{
+    api::ApiErrorResponse, auth::CallPrincipal, di::resolve_from_extensions,
+    error_reporting::PanicMessage, middleware::is_grpc_request,
+};
+
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Turns a panicking REST handler into `500 internal_error` and a panicking gRPC handler into
+/// `INTERNAL`, instead of dropping the connection. Each panic is logged with `tracing::error!`
+/// (method, path, principal, request id, panic message) and counted as `panics` (label
+/// `protocol`). The panic message is only sent to the client when the `Environment` dependency
+/// is local or dev (REST `details.panic`, gRPC status message); a registered
+/// `SharedErrorReporter` always receives it through `with_error_reporting`.
+pub fn with_panic_recovery(router: Router, metrics: Arc<dyn MetricsSink>) -> Router {
+    router.layer(from_fn_with_state(metrics, panic_recovery_middleware))
+}
+
+async fn panic_recovery_middleware(
+    State(metrics): State<Arc<dyn MetricsSink>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let handler = PanicHandler {
+        is_grpc: is_grpc_request(req.headers()),
+        method: req.method().clone(),
+        path: req.uri().path().to_string(),
+        route: req
+            .extensions()
+            .get::<MatchedPath>()
+            .map(|path| path.as_str().to_string()),
+        request_id: req
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string),
+        principal: CallPrincipal::attach(req.extensions_mut()),
+        expose_message: resolve_from_extensions::<Environment>(req.extensions())
+            .is_some_and(|environment| environment.exposes_error_details()),
+        metrics,
+    };
+    let response: Result<_, Infallible> = CatchPanicLayer::custom(handler)
+        .layer(next)
+        .oneshot(req)
+        .await;
+    match response {
+        Ok(response) => response.map(Body::new),
+        Err(never) => match never {},
+    }
+}
+
+#[derive(Clone)]
+struct PanicHandler {
+    is_grpc: bool,
+    method: Method,
+    path: String,
+    route: Option<String>,
+    request_id: Option<String>,
+    principal: CallPrincipal,
+    expose_message: bool,
+    metrics: Arc<dyn MetricsSink>,
+}
+
+impl ResponseForPanic for PanicHandler {
+    type ResponseBody = Body;
+
+    fn response_for_panic(&mut self, err: Box<dyn Any + Send + 'static>) -> Response<Body> {
+        let message = err
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| err.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("<non-string panic payload>");
+        let protocol = if self.is_grpc { "grpc" } else { "rest" };
+        let principal = self.principal.get();
+        tracing::error!(
+            protocol,
+            method = %self.method,
+            path = %self.path,
+            principal = principal.as_deref().unwrap_or("-"),
+            request_id = self.request_id.as_deref().unwrap_or("-"),
+            panic = message,
+            "handler panicked"
+        );
+        self.metrics
+            .incr_counter_with("panics", &[("protocol", protocol)]);
+        let mut response = if self.is_grpc {
+            let status_message = match self.expose_message {
+                true => format!("internal error (panic: {message})"),
+                false => "internal error".to_string(),
+            };
+            tonic::Status::internal(status_message)
+                .into_http()
+                .map(Body::new)
+        } else {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiErrorResponse {
+                    details: self
+                        .expose_message
+                        .then(|| serde_json::json!({ "panic": message })),
+                    ..ApiErrorResponse::internal_server_error()
+                }),
+            )
+                .into_response()
+        };
+        response.extensions_mut().insert(PanicMessage {
+            message: message.to_string(),
+            route: self.route.clone(),
+        });
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::to_bytes, routing::get};
+    use openportio_test::RecordingMetrics;
+
+    async fn boom() -> &'static str {
+        panic!("db pool poisoned")
+    }
+
+    #[tokio::test]
+    async fn panics_become_structured_errors_for_rest_and_grpc() {
+        let metrics = Arc::new(RecordingMetrics::default());
+        let app = with_panic_recovery(
+            Router::new()
+                .route("/boom", get(boom))
+                .route("/pkg.Svc/Call", axum::routing::post(boom))
+                .route("/ok", get(|| async { "ok" })),
+            metrics.clone(),
+        );
+
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/boom").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body: ApiErrorResponse =
+            serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap())
+                .unwrap();
+        assert_eq!(body.code, "internal_error");
+        assert!(!body.message.contains("poisoned"));
+        assert_eq!(body.details, None);
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri("/pkg.Svc/Call")
+                    .header("content-type", "application/grpc")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["grpc-status"], "13");
+
+        let response = app
+            .oneshot(Request::builder().uri("/ok").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            metrics.counters(),
+            ["panics{protocol=rest}", "panics{protocol=grpc}"]
+        );
+    }
+
+    #[tokio::test]
+    async fn local_environments_see_the_panic_message() {
+        let app = |environment: Environment| {
+            with_panic_recovery(
+                Router::new().route("/boom", get(boom)),
+                Arc::new(RecordingMetrics::default()),
+            )
+            .layer(axum::Extension(
+                crate::di::DependencyOverrides::default().with(environment),
+            ))
+        };
+
+        for (environment, expected) in [
+            (
+                Environment::Dev,
+                Some(serde_json::json!({"panic": "db pool poisoned"})),
+            ),
+            (Environment::Production, None),
+        ] {
+            let response = app(environment)
+                .oneshot(Request::builder().uri("/boom").body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            let body: ApiErrorResponse =
+                serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap())
+                    .unwrap();
+            assert_eq!(body.details, expected);
+            assert_eq!(body.message, "internal server error");
+        }
+    }
+}
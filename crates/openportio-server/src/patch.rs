@@ -0,0 +1,700 @@
+use std::{fmt, marker::PhantomData};
+
+use axum::{
+    body::Bytes,
+    extract::{FromRequest, Request},
+    http::{header, StatusCode},
+    Json,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::api::{
+    reject, rejection_handler, ApiError, ApiErrorResponse, ApiValidationIssue, RejectionKind,
+    RequestRejection, RequestValidation,
+};
+
+pub const JSON_PATCH_CONTENT_TYPE: &str = "application/json-patch+json";
+pub const MERGE_PATCH_CONTENT_TYPE: &str = "application/merge-patch+json";
+
+/// One RFC 6902 operation; `path` and `from` are JSON Pointers (RFC 6901).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOperation {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value },
+}
+
+impl PatchOperation {
+    pub fn path(&self) -> &str {
+        match self {
+            Self::Add { path, .. }
+            | Self::Remove { path }
+            | Self::Replace { path, .. }
+            | Self::Move { path, .. }
+            | Self::Copy { path, .. }
+            | Self::Test { path, .. } => path,
+        }
+    }
+
+    // The member of the operation that supplied the value now at `path`.
+    fn source_member(&self) -> &'static str {
+        match self {
+            Self::Remove { .. } => "path",
+            Self::Move { .. } | Self::Copy { .. } => "from",
+            Self::Add { .. } | Self::Replace { .. } | Self::Test { .. } => "value",
+        }
+    }
+}
+
+/// Why operation `index` of a JSON Patch could not be applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchError {
+    pub index: usize,
+    pub kind: PatchErrorKind,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchErrorKind {
+    /// A pointer is malformed or names a location that does not exist.
+    InvalidPath,
+    /// A `test` operation did not match.
+    TestFailed,
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "patch operation {} failed: {}", self.index, self.message)
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+/// Applies `operations` in order. The patch is atomic: on error `document` is left unchanged.
+pub fn apply_json_patch(
+    document: &mut Value,
+    operations: &[PatchOperation],
+) -> Result<(), PatchError> {
+    let mut patched = document.clone();
+    for (index, operation) in operations.iter().enumerate() {
+        apply_operation(&mut patched, operation).map_err(|(kind, message)| PatchError {
+            index,
+            kind,
+            message,
+        })?;
+    }
+    *document = patched;
+    Ok(())
+}
+
+/// Applies an RFC 7396 merge patch: objects merge recursively, `null` removes a member and any
+/// other value replaces the target.
+pub fn apply_merge_patch(document: &mut Value, patch: &Value) {
+    let Value::Object(members) = patch else {
+        *document = patch.clone();
+        return;
+    };
+    if !document.is_object() {
+        *document = Value::Object(Map::new());
+    }
+    let Value::Object(target) = document else {
+        return;
+    };
+    for (key, value) in members {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            apply_merge_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+// The location in the patch document of the last operation that wrote `field`.
+fn locate_in_json_patch(operations: &[PatchOperation], field: &str) -> Option<Vec<String>> {
+    let pointer = format!("/{}", field.replace('~', "~0").replace('/', "~1"));
+    operations
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|(_, operation)| !matches!(operation, PatchOperation::Test { .. }))
+        .find_map(|(index, operation)| {
+            let path = operation.path();
+            let mut loc = vec![
+                "body".to_string(),
+                index.to_string(),
+                operation.source_member().to_string(),
+            ];
+            if path.is_empty() {
+                loc.push(field.to_string());
+            } else if path != pointer && !path.starts_with(&format!("{pointer}/")) {
+                return None;
+            }
+            Some(loc)
+        })
+}
+
+type OperationError = (PatchErrorKind, String);
+
+fn apply_operation(document: &mut Value, operation: &PatchOperation) -> Result<(), OperationError> {
+    match operation {
+        PatchOperation::Add { path, value } => add(document, path, value.clone()),
+        PatchOperation::Remove { path } => remove(document, path).map(drop),
+        PatchOperation::Replace { path, value } => {
+            *lookup_mut(document, path)? = value.clone();
+            Ok(())
+        }
+        PatchOperation::Move { from, path } => {
+            if path.starts_with(&format!("{from}/")) {
+                return Err(invalid(format!(
+                    "cannot move `{from}` into its own child `{path}`"
+                )));
+            }
+            let value = remove(document, from)?;
+            add(document, path, value)
+        }
+        PatchOperation::Copy { from, path } => {
+            let value = lookup_mut(document, from)?.clone();
+            add(document, path, value)
+        }
+        PatchOperation::Test { path, value } => {
+            if lookup_mut(document, path)? == value {
+                Ok(())
+            } else {
+                Err((
+                    PatchErrorKind::TestFailed,
+                    format!("value at `{path}` does not match"),
+                ))
+            }
+        }
+    }
+}
+
+fn invalid(message: String) -> OperationError {
+    (PatchErrorKind::InvalidPath, message)
+}
+
+fn lookup_mut<'a>(document: &'a mut Value, path: &str) -> Result<&'a mut Value, OperationError> {
+    check_pointer(path)?;
+    document
+        .pointer_mut(path)
+        .ok_or_else(|| invalid(format!("path `{path}` does not exist")))
+}
+
+// Splits a pointer into its parent pointer and unescaped last token; `None` for the root.
+fn split_pointer(path: &str) -> Result<Option<(&str, String)>, OperationError> {
+    check_pointer(path)?;
+    Ok(path
+        .rsplit_once('/')
+        .map(|(parent, last)| (parent, unescape(last))))
+}
+
+fn check_pointer(path: &str) -> Result<(), OperationError> {
+    if path.is_empty() || path.starts_with('/') {
+        Ok(())
+    } else {
+        Err(invalid(format!("`{path}` is not a JSON Pointer")))
+    }
+}
+
+fn unescape(token: &str) -> String {
+    token.replace("~1", "/").replace("~0", "~")
+}
+
+fn array_index(token: &str, len: usize, path: &str) -> Result<usize, OperationError> {
+    token
+        .parse::<usize>()
+        .ok()
+        .filter(|index| *index <= len && (token == "0" || !token.starts_with('0')))
+        .ok_or_else(|| invalid(format!("`{path}` is not a valid array index")))
+}
+
+fn add(document: &mut Value, path: &str, value: Value) -> Result<(), OperationError> {
+    let Some((parent, token)) = split_pointer(path)? else {
+        *document = value;
+        return Ok(());
+    };
+    match lookup_mut(document, parent)? {
+        Value::Object(members) => {
+            members.insert(token, value);
+            Ok(())
+        }
+        Value::Array(items) if token == "-" => {
+            items.push(value);
+            Ok(())
+        }
+        Value::Array(items) => {
+            let index = array_index(&token, items.len(), path)?;
+            items.insert(index, value);
+            Ok(())
+        }
+        _ => Err(invalid(format!("parent of `{path}` is not a container"))),
+    }
+}
+
+fn remove(document: &mut Value, path: &str) -> Result<Value, OperationError> {
+    let Some((parent, token)) = split_pointer(path)? else {
+        return Err(invalid("cannot remove the whole document".to_string()));
+    };
+    let missing = || invalid(format!("path `{path}` does not exist"));
+    match lookup_mut(document, parent)? {
+        Value::Object(members) => members.remove(&token).ok_or_else(missing),
+        Value::Array(items) => {
+            let index = array_index(&token, items.len(), path)?;
+            if index == items.len() {
+                return Err(missing());
+            }
+            Ok(items.remove(index))
+        }
+        _ => Err(missing()),
+    }
+}
+
+/// A JSON Patch (`application/json-patch+json`) for a `T`. [`Self::apply`] patches a stored
+/// value and re-runs `T`'s validation on the result.
+///
+/// Validation issues are located in the patch document: an issue on `title` set by operation 1
+/// has `loc: ["body", "1", "value"]`. Issues on fields the patch did not touch are located at
+/// `["resource", field]`.
+#[derive(Debug, Clone)]
+pub struct ValidatedJsonPatch<T> {
+    operations: Vec<PatchOperation>,
+    _resource: PhantomData<fn() -> T>,
+}
+
+impl<T> ValidatedJsonPatch<T> {
+    pub fn operations(&self) -> &[PatchOperation] {
+        &self.operations
+    }
+
+    pub fn into_operations(self) -> Vec<PatchOperation> {
+        self.operations
+    }
+}
+
+impl<T> ValidatedJsonPatch<T>
+where
+    T: Serialize + DeserializeOwned + RequestValidation,
+{
+    /// `base` with the patch applied. A failed `test` operation is `409 conflict`; a bad pointer
+    /// or an invalid result is `400 validation_error`.
+    pub fn apply(&self, base: &T) -> Result<T, ApiError> {
+        let mut document = to_document(base)?;
+        apply_json_patch(&mut document, &self.operations).map_err(|err| {
+            let loc = vec![
+                "body".to_string(),
+                err.index.to_string(),
+                "path".to_string(),
+            ];
+            match err.kind {
+                PatchErrorKind::TestFailed => (
+                    StatusCode::CONFLICT,
+                    Json(ApiErrorResponse {
+                        code: "conflict".to_string(),
+                        message: err.to_string(),
+                        detail: None,
+                        details: None,
+                    }),
+                ),
+                PatchErrorKind::InvalidPath => patch_issues_error(
+                    "patch could not be applied",
+                    vec![(String::new(), issue(loc, err.message, "invalid_patch"))],
+                ),
+            }
+        })?;
+        validate_patched(document, |field| {
+            locate_in_json_patch(&self.operations, field)
+        })
+    }
+}
+
+#[axum::async_trait]
+impl<T, S> FromRequest<S> for ValidatedJsonPatch<T>
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let operations = read_patch_body(req, state, JSON_PATCH_CONTENT_TYPE).await?;
+        Ok(Self {
+            operations,
+            _resource: PhantomData,
+        })
+    }
+}
+
+/// A JSON Merge Patch (`application/merge-patch+json`) for a `T`. [`Self::apply`] merges it into
+/// a stored value and re-runs `T`'s validation on the result.
+///
+/// Issues on fields present in the patch keep `loc: ["body", field]`; issues on untouched fields
+/// are located at `["resource", field]`.
+#[derive(Debug, Clone)]
+pub struct ValidatedMergePatch<T> {
+    patch: Value,
+    _resource: PhantomData<fn() -> T>,
+}
+
+impl<T> ValidatedMergePatch<T> {
+    pub fn patch(&self) -> &Value {
+        &self.patch
+    }
+
+    pub fn into_patch(self) -> Value {
+        self.patch
+    }
+}
+
+impl<T> ValidatedMergePatch<T>
+where
+    T: Serialize + DeserializeOwned + RequestValidation,
+{
+    /// `base` with the patch merged in; an invalid result is `400 validation_error`.
+    pub fn apply(&self, base: &T) -> Result<T, ApiError> {
+        let mut document = to_document(base)?;
+        apply_merge_patch(&mut document, &self.patch);
+        validate_patched(document, |field| match &self.patch {
+            Value::Object(members) if !members.contains_key(field) => None,
+            _ => Some(vec!["body".to_string(), field.to_string()]),
+        })
+    }
+}
+
+#[axum::async_trait]
+impl<T, S> FromRequest<S> for ValidatedMergePatch<T>
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let patch = read_patch_body(req, state, MERGE_PATCH_CONTENT_TYPE).await?;
+        Ok(Self {
+            patch,
+            _resource: PhantomData,
+        })
+    }
+}
+
+async fn read_patch_body<P, S>(req: Request, state: &S, content_type: &str) -> Result<P, ApiError>
+where
+    P: DeserializeOwned,
+    S: Send + Sync,
+{
+    let handler = rejection_handler(req.extensions());
+    let matches = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|value| value.trim().eq_ignore_ascii_case(content_type));
+    if !matches {
+        return Err(reject(
+            handler.as_ref(),
+            RequestRejection::new(
+                RejectionKind::Other,
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                format!("expected request with `Content-Type: {content_type}`"),
+            ),
+        ));
+    }
+    let bytes = Bytes::from_request(req, state).await.map_err(|err| {
+        reject(
+            handler.as_ref(),
+            RequestRejection::new(RejectionKind::Body, err.status(), err.body_text()),
+        )
+    })?;
+    serde_json::from_slice(&bytes).map_err(|err| {
+        reject(
+            handler.as_ref(),
+            RequestRejection::new(
+                RejectionKind::Body,
+                StatusCode::BAD_REQUEST,
+                err.to_string(),
+            ),
+        )
+    })
+}
+
+fn to_document<T: Serialize>(base: &T) -> Result<Value, ApiError> {
+    serde_json::to_value(base).map_err(|err| {
+        tracing::error!(error = %err, "patch base value is not serializable");
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiErrorResponse::internal_server_error()),
+        )
+    })
+}
+
+// Deserializes and validates the patched document, relocating each issue with `locate`
+// (`None` means the patch did not touch the field).
+fn validate_patched<T, F>(document: Value, locate: F) -> Result<T, ApiError>
+where
+    T: DeserializeOwned + RequestValidation,
+    F: Fn(&str) -> Option<Vec<String>>,
+{
+    let value: T = serde_json::from_value(document).map_err(|err| {
+        patch_issues_error(
+            "patched resource is invalid",
+            vec![(
+                String::new(),
+                issue(
+                    vec!["body".to_string()],
+                    err.to_string(),
+                    "invalid_patch_result",
+                ),
+            )],
+        )
+    })?;
+    match value.validate_request("body") {
+        Ok(()) => Ok(value),
+        Err((StatusCode::BAD_REQUEST, Json(body))) if body.code == "validation_error" => {
+            let issues = body
+                .detail
+                .unwrap_or_default()
+                .into_iter()
+                .map(|mut issue| {
+                    let field = issue.loc.get(1).cloned().unwrap_or_default();
+                    issue.loc = locate(&field).unwrap_or_else(|| {
+                        let mut loc = issue.loc.clone();
+                        loc[0] = "resource".to_string();
+                        loc
+                    });
+                    (field, issue)
+                })
+                .collect();
+            Err(patch_issues_error(
+                "patched resource failed validation",
+                issues,
+            ))
+        }
+        Err(err) => Err(err),
+    }
+}
+
+fn issue(loc: Vec<String>, msg: impl Into<String>, issue_type: &str) -> ApiValidationIssue {
+    ApiValidationIssue {
+        loc,
+        msg: msg.into(),
+        issue_type: issue_type.to_string(),
+    }
+}
+
+// `details` stays keyed by field name, as for `ValidatedJson`.
+fn patch_issues_error(message: &str, issues: Vec<(String, ApiValidationIssue)>) -> ApiError {
+    let mut fields = Map::new();
+    for (field, issue) in &issues {
+        if field.is_empty() {
+            continue;
+        }
+        if let Value::Array(messages) = fields.entry(field.clone()).or_insert_with(|| json!([])) {
+            messages.push(json!(issue.msg));
+        }
+    }
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ApiErrorResponse::validation(
+            message,
+            Some(issues.into_iter().map(|(_, issue)| issue).collect()),
+            Some(Value::Object(fields)),
+        )),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::patch, Router};
+    use tower::ServiceExt;
+
+    #[derive(
+        Debug,
+        Clone,
+        PartialEq,
+        Serialize,
+        Deserialize,
+        validator::Validate,
+        crate::RequestValidation,
+    )]
+    struct Note {
+        #[validate(length(min = 1))]
+        title: String,
+        #[validate(length(max = 2))]
+        tags: Vec<String>,
+    }
+
+    fn stored() -> Note {
+        Note {
+            title: "groceries".to_string(),
+            tags: vec!["home".to_string()],
+        }
+    }
+
+    fn ops(raw: Value) -> Vec<PatchOperation> {
+        serde_json::from_value(raw).unwrap()
+    }
+
+    #[test]
+    fn json_patch_applies_every_operation_atomically() {
+        let mut document = json!({"a": {"b": 1}, "list": [1, 2], "x~/y": true});
+        apply_json_patch(
+            &mut document,
+            &ops(json!([
+                {"op": "add", "path": "/list/-", "value": 3},
+                {"op": "add", "path": "/list/0", "value": 0},
+                {"op": "replace", "path": "/a/b", "value": 2},
+                {"op": "copy", "from": "/a", "path": "/c"},
+                {"op": "move", "from": "/x~0~1y", "path": "/moved"},
+                {"op": "remove", "path": "/list/1"},
+                {"op": "test", "path": "/c/b", "value": 2},
+            ])),
+        )
+        .unwrap();
+        assert_eq!(
+            document,
+            json!({"a": {"b": 2}, "c": {"b": 2}, "list": [0, 2, 3], "moved": true})
+        );
+
+        let err = apply_json_patch(
+            &mut document,
+            &ops(json!([
+                {"op": "remove", "path": "/moved"},
+                {"op": "test", "path": "/a/b", "value": 3},
+            ])),
+        )
+        .unwrap_err();
+        assert_eq!(err.index, 1);
+        assert_eq!(err.kind, PatchErrorKind::TestFailed);
+        assert_eq!(document["moved"], true);
+
+        let err = apply_json_patch(
+            &mut document,
+            &ops(json!([{"op": "remove", "path": "/list/01"}])),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind, PatchErrorKind::InvalidPath);
+    }
+
+    #[test]
+    fn merge_patch_merges_objects_and_removes_nulls() {
+        let mut document = json!({"title": "a", "meta": {"pinned": true, "color": "red"}});
+        apply_merge_patch(
+            &mut document,
+            &json!({"title": "b", "meta": {"color": null, "size": 2}}),
+        );
+        assert_eq!(
+            document,
+            json!({"title": "b", "meta": {"pinned": true, "size": 2}})
+        );
+    }
+
+    async fn send(content_type: &str, body: Value) -> (StatusCode, Value) {
+        let app =
+            Router::new()
+                .route(
+                    "/notes/json",
+                    patch(|patch: ValidatedJsonPatch<Note>| async move {
+                        patch.apply(&stored()).map(Json)
+                    }),
+                )
+                .route(
+                    "/notes/merge",
+                    patch(|patch: ValidatedMergePatch<Note>| async move {
+                        patch.apply(&stored()).map(Json)
+                    }),
+                );
+        let uri = if content_type == MERGE_PATCH_CONTENT_TYPE {
+            "/notes/merge"
+        } else {
+            "/notes/json"
+        };
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("PATCH")
+                    .uri(uri)
+                    .header(header::CONTENT_TYPE, content_type)
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn patches_are_applied_and_revalidated_with_patch_locations() {
+        let (status, body) = send(
+            JSON_PATCH_CONTENT_TYPE,
+            json!([
+                {"op": "add", "path": "/tags/-", "value": "food"},
+                {"op": "replace", "path": "/title", "value": "weekly"},
+            ]),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!({"title": "weekly", "tags": ["home", "food"]}));
+
+        let (status, body) = send(
+            JSON_PATCH_CONTENT_TYPE,
+            json!([
+                {"op": "replace", "path": "/title", "value": ""},
+                {"op": "add", "path": "/tags/0", "value": "a"},
+                {"op": "add", "path": "/tags/0", "value": "b"},
+            ]),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let mut locs: Vec<Value> = body["detail"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|issue| issue["loc"].clone())
+            .collect();
+        locs.sort_by_key(|loc| loc.to_string());
+        assert_eq!(
+            locs,
+            [json!(["body", "0", "value"]), json!(["body", "2", "value"])]
+        );
+        assert!(body["details"]["title"].is_array());
+
+        let (status, body) = send(
+            JSON_PATCH_CONTENT_TYPE,
+            json!([{"op": "test", "path": "/title", "value": "stale"}]),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["code"], "conflict");
+
+        let (status, body) = send(
+            JSON_PATCH_CONTENT_TYPE,
+            json!([{"op": "remove", "path": "/missing"}]),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["detail"][0]["loc"], json!(["body", "0", "path"]));
+        assert_eq!(body["detail"][0]["type"], "invalid_patch");
+
+        let (status, body) = send(MERGE_PATCH_CONTENT_TYPE, json!({"title": ""})).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["detail"][0]["loc"], json!(["body", "title"]));
+
+        let (status, _) = send(MERGE_PATCH_CONTENT_TYPE, json!({"tags": null})).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, body) = send("application/json", json!({"title": "x"})).await;
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert!(body["message"]
+            .as_str()
+            .unwrap()
+            .contains(JSON_PATCH_CONTENT_TYPE));
+    }
+}
@@ -0,0 +1,563 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    fmt,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use axum::{
+    extract::Request,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures_util::future::BoxFuture;
+use openportio_core::auth::AuthPrincipal;
+use serde::{Deserialize, Serialize};
+use tonic::{body::BoxBody, server::NamedService, Status};
+use tower::{Layer, Service};
+
+use crate::{
+    api::ApiErrorResponse,
+    audit::{AuditEvent, AuditEventKind, AuditLogger},
+    di,
+    middleware::is_grpc_request,
+    tenancy::TenantContext,
+};
+
+/// Input to an authorization decision: who is calling (subject, scopes, tenant) and what they
+/// want to do (`resource:action`, e.g. `notes:write`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyRequest {
+    pub subject: String,
+    pub scopes: Vec<String>,
+    pub tenant_id: Option<String>,
+    pub resource: String,
+    pub action: String,
+}
+
+impl PolicyRequest {
+    /// Splits `permission` at its last `:` into resource and action; a bare `notes` means any
+    /// action (`*`) on `notes`.
+    pub fn new(principal: &AuthPrincipal, tenant_id: Option<String>, permission: &str) -> Self {
+        let (resource, action) = split_permission(permission);
+        Self {
+            subject: principal.subject.clone(),
+            scopes: principal.scopes.clone(),
+            tenant_id: tenant_id.or_else(|| principal.tenant_id.clone()),
+            resource: resource.to_string(),
+            action: action.to_string(),
+        }
+    }
+
+    pub fn permission(&self) -> String {
+        format!("{}:{}", self.resource, self.action)
+    }
+}
+
+/// Decides whether a [`PolicyRequest`] is allowed. [`RulePolicy`] is the built-in evaluator;
+/// implement this trait to delegate to an external engine (OPA, Cedar, ...). Errors reject
+/// the request with `500`/`INTERNAL`.
+#[axum::async_trait]
+pub trait PolicyEngine: Send + Sync + 'static {
+    async fn allows(&self, request: &PolicyRequest) -> Result<bool, String>;
+}
+
+/// Cloneable handle to a [`PolicyEngine`], resolvable from `DependencyOverrides`; register it
+/// with `OpenportioServer::with_policy(...)`.
+#[derive(Clone)]
+pub struct Policy {
+    engine: Arc<dyn PolicyEngine>,
+}
+
+impl Policy {
+    pub fn new(engine: impl PolicyEngine) -> Self {
+        Self {
+            engine: Arc::new(engine),
+        }
+    }
+
+    pub async fn allows(&self, request: &PolicyRequest) -> Result<bool, String> {
+        self.engine.allows(request).await
+    }
+
+    pub(crate) fn from_extensions(extensions: &http::Extensions) -> Option<Self> {
+        di::resolve_from_extensions(extensions)
+    }
+}
+
+impl fmt::Debug for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Policy").finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Effect {
+    Allow,
+    Deny,
+}
+
+/// One declarative rule. `permission` matches `resource:action` with `*` wildcards per part
+/// (`notes:*`, `*:read`, `*`). Every listed scope must be held; `subjects` and `tenants`, when
+/// non-empty, must contain the caller's.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyRule {
+    pub effect: Effect,
+    pub permission: String,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    #[serde(default)]
+    pub subjects: Vec<String>,
+    #[serde(default)]
+    pub tenants: Vec<String>,
+}
+
+impl PolicyRule {
+    pub fn allow(permission: impl Into<String>) -> Self {
+        Self::new(Effect::Allow, permission)
+    }
+
+    pub fn deny(permission: impl Into<String>) -> Self {
+        Self::new(Effect::Deny, permission)
+    }
+
+    fn new(effect: Effect, permission: impl Into<String>) -> Self {
+        Self {
+            effect,
+            permission: permission.into(),
+            scopes: Vec::new(),
+            subjects: Vec::new(),
+            tenants: Vec::new(),
+        }
+    }
+
+    pub fn with_scope(mut self, scope: impl Into<String>) -> Self {
+        self.scopes.push(scope.into());
+        self
+    }
+
+    pub fn for_subject(mut self, subject: impl Into<String>) -> Self {
+        self.subjects.push(subject.into());
+        self
+    }
+
+    pub fn in_tenant(mut self, tenant_id: impl Into<String>) -> Self {
+        self.tenants.push(tenant_id.into());
+        self
+    }
+
+    fn matches(&self, request: &PolicyRequest) -> bool {
+        let (resource, action) = split_permission(&self.permission);
+        wildcard_matches(resource, &request.resource)
+            && wildcard_matches(action, &request.action)
+            && self
+                .scopes
+                .iter()
+                .all(|scope| request.scopes.contains(scope))
+            && (self.subjects.is_empty() || self.subjects.contains(&request.subject))
+            && (self.tenants.is_empty()
+                || request
+                    .tenant_id
+                    .as_ref()
+                    .is_some_and(|tenant| self.tenants.contains(tenant)))
+    }
+}
+
+/// Built-in rule evaluator: a matching `deny` rule wins, then any matching `allow` rule;
+/// everything else is denied.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RulePolicy {
+    pub rules: Vec<PolicyRule>,
+}
+
+impl RulePolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn rule(mut self, rule: PolicyRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Parses `{"rules": [{"effect": "allow", "permission": "notes:write", "scopes": [...]}]}`.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|err| format!("invalid policy rules: {err}"))
+    }
+
+    pub fn evaluate(&self, request: &PolicyRequest) -> bool {
+        let matching = || self.rules.iter().filter(|rule| rule.matches(request));
+        !matching().any(|rule| rule.effect == Effect::Deny)
+            && matching().any(|rule| rule.effect == Effect::Allow)
+    }
+}
+
+#[axum::async_trait]
+impl PolicyEngine for RulePolicy {
+    async fn allows(&self, request: &PolicyRequest) -> Result<bool, String> {
+        Ok(self.evaluate(request))
+    }
+}
+
+fn split_permission(permission: &str) -> (&str, &str) {
+    permission.rsplit_once(':').unwrap_or((permission, "*"))
+}
+
+fn wildcard_matches(pattern: &str, value: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => value.starts_with(prefix),
+        None => pattern == value,
+    }
+}
+
+/// Route guard checking the caller against the registered [`Policy`]. REST routes use it with
+/// `route_layer(require_policy("notes:write"))` inside the auth layer; tonic services wrap the
+/// generated server, inside the auth interceptor:
+/// `GrpcAuthService::new(require_policy("greeter:call").layer(GreeterServer::new(svc)), auth)`.
+pub fn require_policy(permission: impl Into<String>) -> PolicyGuard {
+    PolicyGuard {
+        permission: Some(permission.into()),
+        methods: Arc::new(HashMap::new()),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PolicyGuard {
+    permission: Option<String>,
+    methods: Arc<HashMap<String, String>>,
+}
+
+impl PolicyGuard {
+    /// Per-method permission for a guarded gRPC service, e.g. `("DeleteNote", "notes:delete")`;
+    /// other methods fall back to the guard's permission.
+    pub fn with_method_policy(
+        mut self,
+        method: impl Into<String>,
+        permission: impl Into<String>,
+    ) -> Self {
+        Arc::make_mut(&mut self.methods).insert(method.into(), permission.into());
+        self
+    }
+
+    fn permission_for(&self, path: &str) -> Option<String> {
+        let method = path.rsplit('/').next().unwrap_or_default();
+        self.methods
+            .get(method)
+            .or(self.permission.as_ref())
+            .cloned()
+    }
+}
+
+impl<S> Layer<S> for PolicyGuard {
+    type Service = PolicyService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        PolicyService {
+            inner,
+            guard: self.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PolicyService<S> {
+    inner: S,
+    guard: PolicyGuard,
+}
+
+impl<S: NamedService> NamedService for PolicyService<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+impl<S> Service<Request> for PolicyService<S>
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Response, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let is_grpc = is_grpc_request(req.headers());
+        let protocol = if is_grpc { "grpc" } else { "rest" };
+        let check = PolicyCheck::new(&self.guard, req.uri().path(), req.extensions(), protocol);
+        let inner = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, inner);
+        Box::pin(async move {
+            match check.run().await {
+                Ok(()) => inner.call(req).await,
+                Err(rejection) if is_grpc => Ok(rejection
+                    .into_grpc_status()
+                    .into_http()
+                    .map(axum::body::Body::new)),
+                Err(rejection) => Ok(rejection.into_response()),
+            }
+        })
+    }
+}
+
+impl<S> Service<http::Request<BoxBody>> for PolicyService<S>
+where
+    S: Service<http::Request<BoxBody>, Response = http::Response<BoxBody>, Error = Infallible>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Self::Response, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<BoxBody>) -> Self::Future {
+        let check = PolicyCheck::new(&self.guard, req.uri().path(), req.extensions(), "grpc");
+        let inner = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, inner);
+        Box::pin(async move {
+            match check.run().await {
+                Ok(()) => inner.call(req).await,
+                Err(rejection) => Ok(rejection.into_grpc_status().into_http()),
+            }
+        })
+    }
+}
+
+struct PolicyCheck {
+    audit: Option<(AuditLogger, AuditEvent)>,
+    permission: Option<String>,
+    policy: Option<Policy>,
+    principal: Option<AuthPrincipal>,
+    tenant_id: Option<String>,
+}
+
+impl PolicyCheck {
+    fn new(
+        guard: &PolicyGuard,
+        path: &str,
+        extensions: &http::Extensions,
+        protocol: &'static str,
+    ) -> Self {
+        let permission = guard.permission_for(path);
+        Self {
+            audit: permission.as_ref().map(|_| {
+                (
+                    AuditLogger::from_extensions(extensions),
+                    AuditEvent::from_extensions(AuditEventKind::ScopeDenied, extensions)
+                        .with_protocol(protocol),
+                )
+            }),
+            permission,
+            policy: Policy::from_extensions(extensions),
+            principal: extensions.get::<AuthPrincipal>().cloned(),
+            tenant_id: extensions
+                .get::<TenantContext>()
+                .map(|tenant| tenant.tenant_id().to_string()),
+        }
+    }
+
+    async fn run(self) -> Result<(), PolicyRejection> {
+        let Some(permission) = self.permission else {
+            return Ok(());
+        };
+        let policy = self.policy.ok_or_else(|| {
+            PolicyRejection::Misconfigured("authorization policy is not configured".to_string())
+        })?;
+        let principal = self.principal.ok_or(PolicyRejection::Unauthenticated)?;
+        let request = PolicyRequest::new(&principal, self.tenant_id, &permission);
+        match policy.allows(&request).await {
+            Ok(true) => Ok(()),
+            Ok(false) => {
+                if let Some((logger, event)) = self.audit {
+                    logger.record(&event.with_reason(format!("policy denies `{permission}`")));
+                }
+                Err(PolicyRejection::Denied(permission))
+            }
+            Err(err) => Err(PolicyRejection::Misconfigured(format!(
+                "policy evaluation failed: {err}"
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyRejection {
+    Unauthenticated,
+    Denied(String),
+    Misconfigured(String),
+}
+
+impl PolicyRejection {
+    pub fn into_grpc_status(self) -> Status {
+        match self {
+            Self::Unauthenticated => Status::unauthenticated("missing authenticated principal"),
+            Self::Denied(permission) => {
+                Status::permission_denied(format!("policy denies `{permission}`"))
+            }
+            Self::Misconfigured(message) => Status::internal(message),
+        }
+    }
+}
+
+impl IntoResponse for PolicyRejection {
+    fn into_response(self) -> Response {
+        let (status, code, message) = match self {
+            Self::Unauthenticated => (
+                StatusCode::UNAUTHORIZED,
+                "unauthorized",
+                "missing authenticated principal".to_string(),
+            ),
+            Self::Denied(permission) => (
+                StatusCode::FORBIDDEN,
+                "forbidden",
+                format!("policy denies `{permission}`"),
+            ),
+            Self::Misconfigured(message) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, "internal_error", message)
+            }
+        };
+        (
+            status,
+            Json(ApiErrorResponse {
+                code: code.to_string(),
+                message,
+                detail: None,
+                details: None,
+            }),
+        )
+            .into_response()
+    }
+}
+
+/// Delegates decisions to an Open Policy Agent decision endpoint such as
+/// `http://opa:8181/v1/data/openportio/authz/allow`, posting `{"input": <PolicyRequest>}` and
+/// reading a boolean `result` (missing means deny).
+#[cfg(feature = "opa")]
+pub struct OpaPolicy {
+    url: String,
+    client: ureq::Agent,
+}
+
+#[cfg(feature = "opa")]
+impl OpaPolicy {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            client: ureq::AgentBuilder::new()
+                .timeout(std::time::Duration::from_secs(2))
+                .build(),
+        }
+    }
+}
+
+#[cfg(feature = "opa")]
+#[axum::async_trait]
+impl PolicyEngine for OpaPolicy {
+    async fn allows(&self, request: &PolicyRequest) -> Result<bool, String> {
+        let body = serde_json::json!({ "input": request }).to_string();
+        let client = self.client.clone();
+        let url = self.url.clone();
+        let response = tokio::task::spawn_blocking(move || {
+            client
+                .post(&url)
+                .set("content-type", "application/json")
+                .send_string(&body)
+                .map_err(|err| format!("opa request failed: {err}"))?
+                .into_string()
+                .map_err(|err| format!("failed to read opa response: {err}"))
+        })
+        .await
+        .map_err(|err| format!("opa request panicked: {err}"))??;
+        let decision: serde_json::Value = serde_json::from_str(&response)
+            .map_err(|err| format!("opa returned invalid json: {err}"))?;
+        Ok(decision["result"].as_bool().unwrap_or(false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::get, Router};
+    use tower::util::ServiceExt;
+
+    fn principal(subject: &str, scopes: &[&str]) -> AuthPrincipal {
+        AuthPrincipal {
+            subject: subject.to_string(),
+            issuer: None,
+            audience: vec![],
+            scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
+            token_id: None,
+            tenant_id: Some("acme".to_string()),
+        }
+    }
+
+    #[test]
+    fn deny_rules_win_and_unmatched_permissions_are_denied() {
+        let policy = RulePolicy::from_json(
+            r#"{"rules": [
+                {"effect": "allow", "permission": "notes:*", "scopes": ["notes:write"]},
+                {"effect": "allow", "permission": "notes:read"},
+                {"effect": "deny", "permission": "notes:delete", "tenants": ["acme"]}
+            ]}"#,
+        )
+        .expect("rules parse");
+        let writer = principal("user-1", &["notes:write"]);
+        let reader = principal("user-2", &[]);
+        let check = |who: &AuthPrincipal, permission| {
+            policy.evaluate(&PolicyRequest::new(who, None, permission))
+        };
+
+        assert!(check(&writer, "notes:write"));
+        assert!(!check(&writer, "notes:delete"));
+        assert!(check(&reader, "notes:read"));
+        assert!(!check(&reader, "notes:write"));
+        assert!(!check(&writer, "billing:read"));
+    }
+
+    #[tokio::test]
+    async fn rest_guard_rejects_missing_principals_and_denied_permissions() {
+        let policy = Policy::new(
+            RulePolicy::new().rule(PolicyRule::allow("notes:write").with_scope("notes:write")),
+        );
+        let app = di::with_dependency(
+            Router::new()
+                .route("/notes", get(|| async { "ok" }))
+                .route_layer(require_policy("notes:write")),
+            policy,
+        );
+        let request = |principal: Option<AuthPrincipal>| {
+            let mut request = Request::builder()
+                .uri("/notes")
+                .body(Body::empty())
+                .unwrap();
+            if let Some(principal) = principal {
+                request.extensions_mut().insert(principal);
+            }
+            request
+        };
+
+        let response = app.clone().oneshot(request(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = app
+            .clone()
+            .oneshot(request(Some(principal("user-2", &[]))))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = app
+            .oneshot(request(Some(principal("user-1", &["notes:write"]))))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
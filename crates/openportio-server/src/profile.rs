@@ -0,0 +1,93 @@
+use std::fmt;
+
+use crate::middleware::{MiddlewareConfig, OverloadPolicy, RateLimit};
+
+const HARDENED_BODY_LIMIT_BYTES: usize = 256 * 1024;
+const HARDENED_RATE_LIMIT_PER_MINUTE: u32 = 600;
+
+/// Curated presets for `OpenportioServer::with_profile(...)`. Builder calls made after the
+/// profile still override individual settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    /// Framework defaults: env-driven middleware, docs served, auth opt-in.
+    Minimal,
+    /// `Minimal` plus security response headers.
+    Standard,
+    /// Security headers, a 256 KiB body limit, a per-client rate limit, load shedding, auth on
+    /// every route except health probes, and no docs endpoints.
+    Hardened,
+}
+
+impl Profile {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Minimal => "minimal",
+            Self::Standard => "standard",
+            Self::Hardened => "hardened",
+        }
+    }
+
+    /// `base` with the profile applied. Hardened limits only ever tighten what `base` (usually
+    /// the environment) configured.
+    pub fn middleware_config(self, base: MiddlewareConfig) -> MiddlewareConfig {
+        match self {
+            Self::Minimal => base,
+            Self::Standard => MiddlewareConfig {
+                security_headers: true,
+                ..base
+            },
+            Self::Hardened => MiddlewareConfig {
+                security_headers: true,
+                max_request_body_bytes: base.max_request_body_bytes.min(HARDENED_BODY_LIMIT_BYTES),
+                rate_limit: base
+                    .rate_limit
+                    .or(Some(RateLimit::per_minute(HARDENED_RATE_LIMIT_PER_MINUTE))),
+                overload_policy: OverloadPolicy::Shed,
+                ..base
+            },
+        }
+    }
+
+    pub fn serves_docs(self) -> bool {
+        self != Self::Hardened
+    }
+
+    pub fn requires_auth(self) -> bool {
+        self == Self::Hardened
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hardened_only_tightens_the_base_config() {
+        let loose = Profile::Hardened.middleware_config(MiddlewareConfig::default());
+        assert!(loose.security_headers);
+        assert_eq!(loose.max_request_body_bytes, HARDENED_BODY_LIMIT_BYTES);
+        assert_eq!(
+            loose.rate_limit,
+            Some(RateLimit::per_minute(HARDENED_RATE_LIMIT_PER_MINUTE))
+        );
+        assert_eq!(loose.overload_policy, OverloadPolicy::Shed);
+
+        let strict = Profile::Hardened.middleware_config(MiddlewareConfig {
+            max_request_body_bytes: 1024,
+            rate_limit: Some(RateLimit::per_minute(10)),
+            ..MiddlewareConfig::default()
+        });
+        assert_eq!(strict.max_request_body_bytes, 1024);
+        assert_eq!(strict.rate_limit, Some(RateLimit::per_minute(10)));
+
+        let minimal = Profile::Minimal.middleware_config(MiddlewareConfig::default());
+        assert!(!minimal.security_headers);
+        assert_eq!(minimal.rate_limit, None);
+    }
+}
@@ -0,0 +1,267 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use axum::{extract::State, http::header, response::IntoResponse, routing::get, Router};
+use openportio_core::{MetricLabels, MetricsSink};
+
+/// Path of the scrape endpoint mounted by `OpenportioServer::with_prometheus_metrics`.
+pub const METRICS_PATH: &str = "/metrics";
+
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Prometheus' default histogram buckets, in seconds.
+const BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// In-process `MetricsSink` that keeps every counter, gauge and histogram it is handed and
+/// renders them in the Prometheus text format. Metric names have `.` (and anything else
+/// Prometheus rejects) replaced by `_`, so `http.request.duration_seconds` is scraped as
+/// `http_request_duration_seconds`. A name keeps the kind it was first reported with; later
+/// reports of another kind are dropped.
+#[derive(Debug, Default)]
+pub struct PrometheusMetrics {
+    families: Mutex<BTreeMap<String, Family>>,
+}
+
+type LabelSet = Vec<(String, String)>;
+
+#[derive(Debug)]
+enum Family {
+    Counter(BTreeMap<LabelSet, f64>),
+    Gauge(BTreeMap<LabelSet, f64>),
+    Histogram(BTreeMap<LabelSet, Histogram>),
+}
+
+#[derive(Debug, Default)]
+struct Histogram {
+    buckets: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl PrometheusMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The current values in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let families = self.families.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut out = String::new();
+        for (name, family) in families.iter() {
+            match family {
+                Family::Counter(series) => {
+                    let _ = writeln!(out, "# TYPE {name} counter");
+                    for (labels, value) in series {
+                        let _ = writeln!(out, "{name}{} {value}", format_labels(labels, None));
+                    }
+                }
+                Family::Gauge(series) => {
+                    let _ = writeln!(out, "# TYPE {name} gauge");
+                    for (labels, value) in series {
+                        let _ = writeln!(out, "{name}{} {value}", format_labels(labels, None));
+                    }
+                }
+                Family::Histogram(series) => {
+                    let _ = writeln!(out, "# TYPE {name} histogram");
+                    for (labels, histogram) in series {
+                        let mut cumulative = 0;
+                        for (bound, count) in BUCKETS.iter().zip(histogram.buckets) {
+                            cumulative += count;
+                            let le = bound.to_string();
+                            let _ = writeln!(
+                                out,
+                                "{name}_bucket{} {cumulative}",
+                                format_labels(labels, Some(&le))
+                            );
+                        }
+                        let _ = writeln!(
+                            out,
+                            "{name}_bucket{} {}",
+                            format_labels(labels, Some("+Inf")),
+                            histogram.count
+                        );
+                        let plain = format_labels(labels, None);
+                        let _ = writeln!(out, "{name}_sum{plain} {}", histogram.sum);
+                        let _ = writeln!(out, "{name}_count{plain} {}", histogram.count);
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// `GET /metrics` serving [`Self::render`].
+    pub fn router(self: Arc<Self>) -> Router {
+        Router::new()
+            .route(METRICS_PATH, get(scrape))
+            .with_state(self)
+    }
+
+    fn update(
+        &self,
+        name: &str,
+        labels: MetricLabels<'_>,
+        new: fn() -> Family,
+        apply: impl FnOnce(&mut Family, LabelSet),
+    ) {
+        let mut families = self.families.lock().unwrap_or_else(PoisonError::into_inner);
+        let family = families.entry(metric_name(name)).or_insert_with(new);
+        let mut labels: LabelSet = labels
+            .iter()
+            .map(|(key, value)| (metric_name(key), value.to_string()))
+            .collect();
+        labels.sort();
+        apply(family, labels);
+    }
+}
+
+impl MetricsSink for PrometheusMetrics {
+    fn incr_counter(&self, name: &str) {
+        self.incr_counter_with(name, &[]);
+    }
+
+    fn incr_counter_with(&self, name: &str, labels: MetricLabels<'_>) {
+        self.update(
+            name,
+            labels,
+            || Family::Counter(BTreeMap::new()),
+            |family, labels| {
+                if let Family::Counter(series) = family {
+                    *series.entry(labels).or_default() += 1.0;
+                }
+            },
+        );
+    }
+
+    fn observe_histogram(&self, name: &str, value: f64, labels: MetricLabels<'_>) {
+        self.update(
+            name,
+            labels,
+            || Family::Histogram(BTreeMap::new()),
+            |family, labels| {
+                if let Family::Histogram(series) = family {
+                    let histogram = series.entry(labels).or_default();
+                    if let Some(bucket) = BUCKETS.iter().position(|bound| value <= *bound) {
+                        histogram.buckets[bucket] += 1;
+                    }
+                    histogram.sum += value;
+                    histogram.count += 1;
+                }
+            },
+        );
+    }
+
+    fn set_gauge(&self, name: &str, value: f64, labels: MetricLabels<'_>) {
+        self.update(
+            name,
+            labels,
+            || Family::Gauge(BTreeMap::new()),
+            |family, labels| {
+                if let Family::Gauge(series) = family {
+                    series.insert(labels, value);
+                }
+            },
+        );
+    }
+}
+
+async fn scrape(State(metrics): State<Arc<PrometheusMetrics>>) -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, CONTENT_TYPE)], metrics.render())
+}
+
+fn metric_name(name: &str) -> String {
+    name.chars()
+        .enumerate()
+        .map(|(index, ch)| match ch {
+            'a'..='z' | 'A'..='Z' | '_' | ':' => ch,
+            '0'..='9' if index > 0 => ch,
+            _ => '_',
+        })
+        .collect()
+}
+
+fn format_labels(labels: &LabelSet, le: Option<&str>) -> String {
+    let pairs: Vec<String> = labels
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .chain(le.map(|le| ("le", le)))
+        .map(|(key, value)| format!("{key}=\"{}\"", escape(value)))
+        .collect();
+    if pairs.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", pairs.join(","))
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[test]
+    fn renders_counters_gauges_and_histograms_in_text_format() {
+        let metrics = PrometheusMetrics::new();
+        metrics.incr_counter_with("http.requests", &[("route", "/a"), ("method", "GET")]);
+        metrics.incr_counter_with("http.requests", &[("method", "GET"), ("route", "/a")]);
+        metrics.set_gauge("runtime.worker_busy_ratio", 0.5, &[("worker", "0")]);
+        metrics.set_gauge("runtime.worker_busy_ratio", 0.25, &[("worker", "0")]);
+        metrics.observe_histogram("runtime.scheduling_delay_seconds", 0.02, &[]);
+        metrics.observe_histogram("runtime.scheduling_delay_seconds", 20.0, &[]);
+        metrics.set_gauge("http.requests", 9.0, &[]);
+        metrics.incr_counter_with("odd", &[("path", "say \"hi\"\n")]);
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE http_requests counter\n"));
+        assert!(text.contains("http_requests{method=\"GET\",route=\"/a\"} 2\n"));
+        assert!(!text.contains("http_requests 9"));
+        assert!(text.contains("# TYPE runtime_worker_busy_ratio gauge\n"));
+        assert!(text.contains("runtime_worker_busy_ratio{worker=\"0\"} 0.25\n"));
+        assert!(text.contains("# TYPE runtime_scheduling_delay_seconds histogram\n"));
+        assert!(text.contains("runtime_scheduling_delay_seconds_bucket{le=\"0.01\"} 0\n"));
+        assert!(text.contains("runtime_scheduling_delay_seconds_bucket{le=\"0.025\"} 1\n"));
+        assert!(text.contains("runtime_scheduling_delay_seconds_bucket{le=\"10\"} 1\n"));
+        assert!(text.contains("runtime_scheduling_delay_seconds_bucket{le=\"+Inf\"} 2\n"));
+        assert!(text.contains("runtime_scheduling_delay_seconds_sum 20.02\n"));
+        assert!(text.contains("runtime_scheduling_delay_seconds_count 2\n"));
+        assert!(text.contains("odd{path=\"say \\\"hi\\\"\\n\"} 1\n"));
+    }
+
+    #[tokio::test]
+    async fn router_serves_the_scrape_endpoint() {
+        let metrics = Arc::new(PrometheusMetrics::new());
+        metrics.set_gauge("server.connections", 3.0, &[]);
+        let response = metrics
+            .router()
+            .oneshot(
+                Request::builder()
+                    .uri(METRICS_PATH)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], CONTENT_TYPE);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(
+            std::str::from_utf8(&body).unwrap(),
+            "# TYPE server_connections gauge\nserver_connections 3\n"
+        );
+    }
+}
@@ -0,0 +1,567 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+    sync::{Arc, Mutex, PoisonError},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Request},
+    http::{header, HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use openportio_core::auth::AuthPrincipal;
+
+use crate::{
+    api::ApiErrorResponse,
+    client_ip::ClientIp,
+    di,
+    middleware::{is_grpc_request, HEALTH_PATHS},
+};
+
+pub const QUOTA_LIMIT_HEADER: &str = "x-quota-limit";
+pub const QUOTA_REMAINING_HEADER: &str = "x-quota-remaining";
+pub const QUOTA_RESET_HEADER: &str = "x-quota-reset";
+
+const SECONDS_PER_DAY: u64 = 86_400;
+/// How often [`InMemoryQuotaStore`] drops counters of finished windows.
+const SWEEP_INTERVAL_SECONDS: u64 = 60;
+
+/// Accounting window of a [`Quota`], aligned to UTC calendar days and months.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QuotaPeriod {
+    Daily,
+    Monthly,
+}
+
+impl QuotaPeriod {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Daily => "daily",
+            Self::Monthly => "monthly",
+        }
+    }
+
+    /// Label of the window containing `now` (`2024-05-17` / `2024-05`) and its end, both in
+    /// unix seconds.
+    fn window(self, now: u64) -> (String, u64) {
+        let days = now / SECONDS_PER_DAY;
+        let (year, month, day) = civil_from_days(days);
+        match self {
+            Self::Daily => (
+                format!("{year:04}-{month:02}-{day:02}"),
+                (days + 1) * SECONDS_PER_DAY,
+            ),
+            Self::Monthly => {
+                let (next_year, next_month) = if month == 12 {
+                    (year + 1, 1)
+                } else {
+                    (year, month + 1)
+                };
+                (
+                    format!("{year:04}-{month:02}"),
+                    days_from_civil(next_year, next_month, 1) * SECONDS_PER_DAY,
+                )
+            }
+        }
+    }
+}
+
+impl FromStr for QuotaPeriod {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "daily" | "day" => Ok(Self::Daily),
+            "monthly" | "month" => Ok(Self::Monthly),
+            other => Err(format!("unsupported quota period `{other}`")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quota {
+    pub limit: u64,
+    pub period: QuotaPeriod,
+}
+
+impl Quota {
+    pub fn daily(limit: u64) -> Self {
+        Self {
+            limit,
+            period: QuotaPeriod::Daily,
+        }
+    }
+
+    pub fn monthly(limit: u64) -> Self {
+        Self {
+            limit,
+            period: QuotaPeriod::Monthly,
+        }
+    }
+}
+
+/// Persistent counters behind [`Quotas`]. Like the revocation store this runs on the request
+/// path synchronously, so implementations must answer quickly.
+pub trait QuotaStore: Send + Sync + 'static {
+    /// Adds one to `key` and returns the new count. `expires_at` (unix seconds) is the end of the
+    /// key's window; the counter may be dropped after it.
+    fn increment(&self, key: &str, expires_at: u64) -> Result<u64, String>;
+
+    /// Takes back one earlier [`Self::increment`] of `key`, when a call charged to its client IP
+    /// or API key turns out to belong to a verified principal. Never goes below zero.
+    fn decrement(&self, key: &str) -> Result<(), String>;
+}
+
+/// Single-instance store; replicas each keep their own counts. Counters of finished windows
+/// are swept at most once a minute.
+#[derive(Debug, Default)]
+pub struct InMemoryQuotaStore {
+    counters: Mutex<Counters>,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    /// Count and window end (unix seconds) per key.
+    entries: HashMap<String, (u64, u64)>,
+    next_sweep: u64,
+}
+
+impl InMemoryQuotaStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl QuotaStore for InMemoryQuotaStore {
+    fn increment(&self, key: &str, expires_at: u64) -> Result<u64, String> {
+        let mut counters = self.counters.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = unix_now();
+        if now >= counters.next_sweep {
+            counters.entries.retain(|_, (_, expiry)| *expiry > now);
+            counters.next_sweep = now + SWEEP_INTERVAL_SECONDS;
+        }
+        let (count, expiry) = counters
+            .entries
+            .entry(key.to_string())
+            .or_insert((0, expires_at));
+        if *expiry <= now {
+            *count = 0;
+            *expiry = expires_at;
+        }
+        *count += 1;
+        Ok(*count)
+    }
+
+    fn decrement(&self, key: &str) -> Result<(), String> {
+        let mut counters = self.counters.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some((count, _)) = counters.entries.get_mut(key) {
+            *count = count.saturating_sub(1);
+        }
+        Ok(())
+    }
+}
+
+/// Who a call is charged to: the verified principal, else a known API key, else the resolved
+/// client IP. Nothing a client can make up freely (an unverified header) becomes a key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuotaSubject {
+    Principal(String),
+    /// The id [`Quotas::with_api_keys`] resolved the presented key to, never the raw key.
+    ApiKey(String),
+    ClientIp(IpAddr),
+}
+
+impl QuotaSubject {
+    fn storage_key(&self) -> String {
+        match self {
+            Self::Principal(subject) => format!("subject:{subject}"),
+            Self::ApiKey(id) => format!("api_key:{id}"),
+            Self::ClientIp(ip) => format!("ip:{ip}"),
+        }
+    }
+}
+
+/// Marks a call as charged (at unix second `at`), so a later auth layer can move the charge to
+/// the principal instead of counting the call twice.
+#[derive(Debug, Clone)]
+struct QuotaCharged {
+    subject: QuotaSubject,
+    at: u64,
+}
+
+type ApiKeyResolver = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+#[derive(Clone)]
+struct ApiKeys {
+    header: HeaderName,
+    resolve: ApiKeyResolver,
+}
+
+/// Usage of the most constrained quota after a call was charged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaUsage {
+    pub quota: Quota,
+    pub remaining: u64,
+    pub reset_after_seconds: u64,
+    pub exhausted: bool,
+}
+
+/// Daily/monthly call budgets per principal, API key or client IP, complementing the
+/// per-address rate limit. Register with `OpenportioServer::with_quotas(...)`; health probes are
+/// not counted.
+#[derive(Clone)]
+pub struct Quotas {
+    quotas: Vec<Quota>,
+    store: Arc<dyn QuotaStore>,
+    api_keys: Option<ApiKeys>,
+}
+
+impl Quotas {
+    pub fn new(store: impl QuotaStore) -> Self {
+        Self {
+            quotas: Vec::new(),
+            store: Arc::new(store),
+            api_keys: None,
+        }
+    }
+
+    pub fn in_memory() -> Self {
+        Self::new(InMemoryQuotaStore::new())
+    }
+
+    pub fn with_quota(mut self, quota: Quota) -> Self {
+        self.quotas.push(quota);
+        self
+    }
+
+    pub fn daily(self, limit: u64) -> Self {
+        self.with_quota(Quota::daily(limit))
+    }
+
+    pub fn monthly(self, limit: u64) -> Self {
+        self.with_quota(Quota::monthly(limit))
+    }
+
+    /// Charges calls without a principal to the API key in `header` when `resolve` maps it to
+    /// a key id (`None` for unknown keys, which fall back to the client IP). Look the key up in
+    /// your key store here; only the returned id is stored.
+    pub fn with_api_keys<F>(mut self, header: HeaderName, resolve: F) -> Self
+    where
+        F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+    {
+        self.api_keys = Some(ApiKeys {
+            header,
+            resolve: Arc::new(resolve),
+        });
+        self
+    }
+
+    pub fn quotas(&self) -> &[Quota] {
+        &self.quotas
+    }
+
+    /// The verified principal of the call, else its known API key, else its client IP (or
+    /// socket peer).
+    pub fn subject(&self, parts: &http::request::Parts) -> Option<QuotaSubject> {
+        let extensions = &parts.extensions;
+        if let Some(principal) = extensions.get::<AuthPrincipal>() {
+            return Some(QuotaSubject::Principal(principal.subject.clone()));
+        }
+        if let Some(api_keys) = &self.api_keys {
+            let id = parts
+                .headers
+                .get(&api_keys.header)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|key| (api_keys.resolve)(key.trim()));
+            if let Some(id) = id {
+                return Some(QuotaSubject::ApiKey(id));
+            }
+        }
+        extensions
+            .get::<ClientIp>()
+            .map(|ClientIp(ip)| *ip)
+            .or_else(|| {
+                extensions
+                    .get::<ConnectInfo<SocketAddr>>()
+                    .map(|ConnectInfo(addr)| addr.ip())
+            })
+            .map(QuotaSubject::ClientIp)
+    }
+
+    /// Charges one call to `subject` against every configured quota.
+    pub fn consume(&self, subject: &QuotaSubject) -> Result<Option<QuotaUsage>, String> {
+        self.consume_at(subject, unix_now())
+    }
+
+    fn consume_at(&self, subject: &QuotaSubject, now: u64) -> Result<Option<QuotaUsage>, String> {
+        let key = subject.storage_key();
+        let mut tightest: Option<QuotaUsage> = None;
+        for quota in &self.quotas {
+            let (window, ends_at) = quota.period.window(now);
+            let used = self
+                .store
+                .increment(&format!("{key}:{}:{window}", quota.period.name()), ends_at)?;
+            let usage = QuotaUsage {
+                quota: *quota,
+                remaining: quota.limit.saturating_sub(used),
+                reset_after_seconds: ends_at.saturating_sub(now),
+                exhausted: used > quota.limit,
+            };
+            let sort_key = |usage: &QuotaUsage| (!usage.exhausted, usage.remaining);
+            if tightest.is_none_or(|current| sort_key(&usage) < sort_key(&current)) {
+                tightest = Some(usage);
+            }
+        }
+        Ok(tightest)
+    }
+
+    /// Takes back a charge [`Self::consume_at`] made to `subject` at `at`.
+    fn refund_at(&self, subject: &QuotaSubject, at: u64) -> Result<(), String> {
+        let key = subject.storage_key();
+        for quota in &self.quotas {
+            let (window, _) = quota.period.window(at);
+            self.store
+                .decrement(&format!("{key}:{}:{window}", quota.period.name()))?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn from_extensions(extensions: &http::Extensions) -> Option<Self> {
+        di::resolve_from_extensions(extensions)
+    }
+
+    pub(crate) fn summary(&self) -> String {
+        self.quotas
+            .iter()
+            .map(|quota| format!("{} {}", quota.limit, quota.period.name()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl fmt::Debug for Quotas {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Quotas")
+            .field("quotas", &self.quotas)
+            .field(
+                "api_key_header",
+                &self.api_keys.as_ref().map(|api_keys| &api_keys.header),
+            )
+            .finish_non_exhaustive()
+    }
+}
+
+// Runs inside the `require_auth` layer, so those principals are known. A call authenticated
+// later by a per-route `rest_auth_middleware` has its charge moved to the principal there, so it
+// is counted once; gRPC principals are only resolved by the interceptor, so gRPC calls are
+// charged to their API key or client IP.
+//
+// A REST call presenting a bearer token may still be verified by such a per-route layer, so its
+// address's budget is counted but not enforced here: an exhausted shared IP must not turn away a
+// principal with budget left.
+pub(crate) async fn quota_middleware(req: Request, next: Next) -> Response {
+    if HEALTH_PATHS.contains(&req.uri().path()) {
+        return next.run(req).await;
+    }
+    let Some(quotas) = Quotas::from_extensions(req.extensions()) else {
+        return next.run(req).await;
+    };
+    let (parts, body) = req.into_parts();
+    let subject = quotas.subject(&parts);
+    let req = Request::from_parts(parts, body);
+    match subject {
+        Some(subject) => {
+            let enforce = !matches!(subject, QuotaSubject::ClientIp(_))
+                || is_grpc_request(req.headers())
+                || !req.headers().contains_key(header::AUTHORIZATION);
+            charge(&quotas, subject, enforce, req, next).await
+        }
+        None => next.run(req).await,
+    }
+}
+
+/// Charges the principal a per-route auth layer has just verified, taking back the charge the
+/// call already made to its API key or client IP.
+pub(crate) async fn charge_verified_principal(req: Request, next: Next) -> Response {
+    let (Some(quotas), Some(principal)) = (
+        Quotas::from_extensions(req.extensions()),
+        req.extensions().get::<AuthPrincipal>(),
+    ) else {
+        return next.run(req).await;
+    };
+    let subject = QuotaSubject::Principal(principal.subject.clone());
+    match req.extensions().get::<QuotaCharged>() {
+        Some(charged) if charged.subject == subject => return next.run(req).await,
+        Some(charged) => {
+            if let Err(err) = quotas.refund_at(&charged.subject, charged.at) {
+                tracing::warn!(error = %err, "quota store unavailable; earlier charge kept");
+            }
+        }
+        None => {}
+    }
+    charge(&quotas, subject, true, req, next).await
+}
+
+async fn charge(
+    quotas: &Quotas,
+    subject: QuotaSubject,
+    enforce: bool,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let at = unix_now();
+    let usage = match quotas.consume_at(&subject, at) {
+        Ok(Some(usage)) => usage,
+        Ok(None) => return next.run(req).await,
+        Err(err) => {
+            // Quota accounting fails open: an unavailable store must not take the API down.
+            tracing::warn!(error = %err, "quota store unavailable; request not counted");
+            return next.run(req).await;
+        }
+    };
+    let mut response = if usage.exhausted && enforce {
+        tracing::warn!(
+            subject = ?subject,
+            period = usage.quota.period.name(),
+            limit = usage.quota.limit,
+            "quota exhausted"
+        );
+        quota_exceeded_response(&usage, is_grpc_request(req.headers()))
+    } else {
+        req.extensions_mut().insert(QuotaCharged { subject, at });
+        next.run(req).await
+    };
+    // A principal charged further in reports its own usage.
+    let headers = response.headers_mut();
+    if !headers.contains_key(QUOTA_LIMIT_HEADER) {
+        for (name, value) in [
+            (QUOTA_LIMIT_HEADER, usage.quota.limit),
+            (QUOTA_REMAINING_HEADER, usage.remaining),
+            (QUOTA_RESET_HEADER, usage.reset_after_seconds),
+        ] {
+            headers.insert(HeaderName::from_static(name), HeaderValue::from(value));
+        }
+    }
+    response
+}
+
+fn quota_exceeded_response(usage: &QuotaUsage, is_grpc: bool) -> Response {
+    let message = format!("{} quota exhausted", usage.quota.period.name());
+    if is_grpc {
+        return tonic::Status::resource_exhausted(message)
+            .into_http()
+            .map(Body::new);
+    }
+    let mut response = (
+        StatusCode::TOO_MANY_REQUESTS,
+        Json(ApiErrorResponse {
+            code: "quota_exceeded".to_string(),
+            message,
+            detail: None,
+            details: Some(serde_json::json!({
+                "period": usage.quota.period.name(),
+                "limit": usage.quota.limit,
+                "reset_after_seconds": usage.reset_after_seconds,
+            })),
+        }),
+    )
+        .into_response();
+    response.headers_mut().insert(
+        header::RETRY_AFTER,
+        HeaderValue::from(usage.reset_after_seconds),
+    );
+    response
+}
+
+// Howard Hinnant's days <-> civil date conversions, restricted to dates after the epoch.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let yoe = year - era * 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_align_to_utc_days_and_months() {
+        // 2024-02-29T12:00:00Z
+        let now = 1_709_208_000;
+        let (label, ends_at) = QuotaPeriod::Daily.window(now);
+        assert_eq!(label, "2024-02-29");
+        assert_eq!(ends_at, 1_709_251_200);
+        let (label, ends_at) = QuotaPeriod::Monthly.window(now);
+        assert_eq!(label, "2024-02");
+        assert_eq!(ends_at, 1_709_251_200);
+        let (label, ends_at) = QuotaPeriod::Monthly.window(1_703_980_800);
+        assert_eq!(label, "2023-12");
+        assert_eq!(ends_at, 1_704_067_200);
+    }
+
+    #[test]
+    fn tightest_quota_is_reported_and_exhaustion_is_per_subject() {
+        let quotas = Quotas::in_memory().daily(2).monthly(10);
+        let alice = QuotaSubject::Principal("alice".to_string());
+        let key = QuotaSubject::ClientIp(IpAddr::from([203, 0, 113, 9]));
+        let now = unix_now();
+
+        let first = quotas.consume_at(&alice, now).unwrap().unwrap();
+        assert_eq!(
+            (first.quota.period, first.remaining),
+            (QuotaPeriod::Daily, 1)
+        );
+        quotas.consume_at(&alice, now).unwrap();
+        let third = quotas.consume_at(&alice, now).unwrap().unwrap();
+        assert!(third.exhausted);
+        assert!(third.reset_after_seconds <= SECONDS_PER_DAY);
+
+        let other = quotas.consume_at(&key, now).unwrap().unwrap();
+        assert!(!other.exhausted);
+    }
+
+    #[test]
+    fn in_memory_store_restarts_finished_windows() {
+        let store = InMemoryQuotaStore::new();
+        let past = unix_now() - 1;
+        assert_eq!(store.increment("ip:203.0.113.9:daily:old", past), Ok(1));
+        assert_eq!(store.increment("ip:203.0.113.9:daily:old", past), Ok(1));
+        let ends_at = unix_now() + 60;
+        assert_eq!(store.increment("subject:alice", ends_at), Ok(1));
+        assert_eq!(store.increment("subject:alice", ends_at), Ok(2));
+        store.decrement("subject:alice").unwrap();
+        store.decrement("subject:alice").unwrap();
+        store.decrement("subject:alice").unwrap();
+        assert_eq!(store.increment("subject:alice", ends_at), Ok(1));
+    }
+}
@@ -0,0 +1,324 @@
+use std::{net::SocketAddr, str::FromStr};
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Router,
+};
+
+use crate::middleware::{is_grpc_request, HEALTH_PATHS};
+
+const DEFAULT_HTTPS_PORT: u16 = 443;
+
+/// What the path normalization layer does with a trailing `/`. The root path is left alone.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TrailingSlash {
+    #[default]
+    Preserve,
+    /// Redirect `/orders/` to `/orders`.
+    Trim,
+    /// Redirect `/orders` to `/orders/`. File-like paths (`/openapi.json`) and health probes
+    /// keep their form.
+    Append,
+}
+
+impl TrailingSlash {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Preserve => "preserve",
+            Self::Trim => "trim",
+            Self::Append => "append",
+        }
+    }
+}
+
+impl FromStr for TrailingSlash {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "preserve" | "" => Ok(Self::Preserve),
+            "trim" | "strip" => Ok(Self::Trim),
+            "append" | "add" => Ok(Self::Append),
+            other => Err(format!("unsupported trailing slash policy `{other}`")),
+        }
+    }
+}
+
+/// Canonical-path rules applied by the shared middleware ahead of the handlers: requests for a
+/// non-canonical path get a `308 Permanent Redirect` to the canonical one (query preserved), so
+/// routes only need to be registered once. gRPC calls are never rewritten.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PathNormalization {
+    pub trailing_slash: TrailingSlash,
+    /// Redirect paths containing uppercase ASCII letters to their lowercase form. Avoid with
+    /// case-sensitive path parameters.
+    pub lowercase: bool,
+}
+
+impl PathNormalization {
+    pub fn is_enabled(&self) -> bool {
+        self.trailing_slash != TrailingSlash::Preserve || self.lowercase
+    }
+
+    /// The canonical form of `path`, or `None` when it already is canonical. Leading slashes
+    /// collapse to one, so the relative `Location` can never read as a protocol-relative URL
+    /// (`//evil.example`).
+    pub fn normalize(&self, path: &str) -> Option<String> {
+        let path_without_leading = path.trim_start_matches(['/', '\\']);
+        let mut normalized = format!("/{path_without_leading}");
+        if self.lowercase {
+            normalized.make_ascii_lowercase();
+        }
+        match self.trailing_slash {
+            TrailingSlash::Preserve => {}
+            TrailingSlash::Trim => {
+                let trimmed = normalized.trim_end_matches('/');
+                normalized = if trimmed.is_empty() {
+                    "/".to_string()
+                } else {
+                    trimmed.to_string()
+                };
+            }
+            TrailingSlash::Append => {
+                let last_segment = normalized.rsplit('/').next().unwrap_or_default();
+                if !normalized.ends_with('/')
+                    && !last_segment.contains('.')
+                    && !HEALTH_PATHS.contains(&normalized.as_str())
+                {
+                    normalized.push('/');
+                }
+            }
+        }
+        (normalized != path).then_some(normalized)
+    }
+}
+
+pub(crate) async fn path_normalization_middleware(
+    State(normalization): State<PathNormalization>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if is_grpc_request(req.headers()) {
+        return next.run(req).await;
+    }
+    match normalization.normalize(req.uri().path()) {
+        Some(path) => {
+            let location = match req.uri().query() {
+                Some(query) => format!("{path}?{query}"),
+                None => path,
+            };
+            permanent_redirect(&location)
+        }
+        None => next.run(req).await,
+    }
+}
+
+fn permanent_redirect(location: &str) -> Response {
+    match HeaderValue::from_str(location) {
+        Ok(location) => (
+            StatusCode::PERMANENT_REDIRECT,
+            [(header::LOCATION, location)],
+        )
+            .into_response(),
+        Err(_) => StatusCode::BAD_REQUEST.into_response(),
+    }
+}
+
+/// Plain-HTTP listener that answers every request with a `308` to the same host and path over
+/// HTTPS, for deployments terminating TLS on this process or right in front of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HttpsRedirect {
+    addr: SocketAddr,
+    https_port: u16,
+}
+
+impl HttpsRedirect {
+    pub fn new(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            https_port: DEFAULT_HTTPS_PORT,
+        }
+    }
+
+    /// Port put into the `Location` header; omitted when it is `443`.
+    pub fn with_https_port(mut self, port: u16) -> Self {
+        self.https_port = port;
+        self
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn https_port(&self) -> u16 {
+        self.https_port
+    }
+
+    /// `OPENPORTIO_HTTPS_REDIRECT_ADDR` (e.g. `0.0.0.0:80`) plus optional
+    /// `OPENPORTIO_HTTPS_REDIRECT_PORT`.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let Some(addr) =
+            crate::env::var("HTTPS_REDIRECT_ADDR")?.filter(|value| !value.trim().is_empty())
+        else {
+            return Ok(None);
+        };
+        let addr = addr
+            .parse()
+            .map_err(|err| format!("invalid https redirect address `{addr}`: {err}"))?;
+        let redirect = Self::new(addr);
+        match crate::env::var("HTTPS_REDIRECT_PORT")?.filter(|value| !value.trim().is_empty()) {
+            Some(port) => port
+                .parse()
+                .map(|port| Some(redirect.with_https_port(port)))
+                .map_err(|err| format!("invalid https redirect port `{port}`: {err}")),
+            None => Ok(Some(redirect)),
+        }
+    }
+
+    pub fn router(&self) -> Router {
+        Router::new()
+            .fallback(https_redirect)
+            .with_state(self.https_port)
+    }
+}
+
+async fn https_redirect(State(https_port): State<u16>, req: Request) -> Response {
+    let host = req
+        .headers()
+        .get(header::HOST)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|host| host.parse::<axum::http::uri::Authority>().ok());
+    let Some(host) = host else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    let authority = match https_port {
+        DEFAULT_HTTPS_PORT => host.host().to_string(),
+        port => format!("{}:{port}", host.host()),
+    };
+    let path_and_query = req
+        .uri()
+        .path_and_query()
+        .map(|value| value.as_str())
+        .unwrap_or("/");
+    match Uri::builder()
+        .scheme("https")
+        .authority(authority)
+        .path_and_query(path_and_query)
+        .build()
+    {
+        Ok(uri) => permanent_redirect(&uri.to_string()),
+        Err(_) => StatusCode::BAD_REQUEST.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::get};
+    use tower::util::ServiceExt;
+
+    #[test]
+    fn normalization_redirects_only_non_canonical_paths() {
+        let trim = PathNormalization {
+            trailing_slash: TrailingSlash::Trim,
+            lowercase: true,
+        };
+        assert_eq!(trim.normalize("/Orders/"), Some("/orders".to_string()));
+        assert_eq!(trim.normalize("/orders"), None);
+        assert_eq!(trim.normalize("/"), None);
+
+        let append = PathNormalization {
+            trailing_slash: TrailingSlash::Append,
+            lowercase: false,
+        };
+        assert_eq!(append.normalize("/orders"), Some("/orders/".to_string()));
+        assert_eq!(append.normalize("/openapi.json"), None);
+        assert_eq!(append.normalize("/livez"), None);
+    }
+
+    #[test]
+    fn leading_slashes_never_produce_a_protocol_relative_location() {
+        let trim = PathNormalization {
+            trailing_slash: TrailingSlash::Trim,
+            lowercase: false,
+        };
+        assert_eq!(
+            trim.normalize("//evil.example/"),
+            Some("/evil.example".to_string())
+        );
+        assert_eq!(
+            trim.normalize("/\\evil.example"),
+            Some("/evil.example".to_string())
+        );
+        assert_eq!(trim.normalize("//"), Some("/".to_string()));
+
+        let append = PathNormalization {
+            trailing_slash: TrailingSlash::Append,
+            lowercase: false,
+        };
+        assert_eq!(
+            append.normalize("///evil.example/path"),
+            Some("/evil.example/path/".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn redirect_listener_points_at_https_with_path_and_query() {
+        let app = HttpsRedirect::new(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .with_https_port(8443)
+            .router();
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri("/orders?page=2")
+                    .header(header::HOST, "api.example.com:8080")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(
+            response.headers()[header::LOCATION],
+            "https://api.example.com:8443/orders?page=2"
+        );
+
+        let app = crate::middleware::apply_shared_middleware(
+            Router::new().route("/orders", get(|| async { "orders" })),
+            &crate::middleware::MiddlewareConfig {
+                path_normalization: PathNormalization {
+                    trailing_slash: TrailingSlash::Trim,
+                    lowercase: false,
+                },
+                ..Default::default()
+            },
+        );
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri("/orders/?page=2")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(response.headers()[header::LOCATION], "/orders?page=2");
+
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri("//evil.example/")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(response.headers()[header::LOCATION], "/evil.example");
+    }
+}
@@ -0,0 +1,289 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::middleware::is_grpc_request;
+
+/// Default response headers added by the shared middleware, e.g. `Server` or
+/// `Cache-Control: no-store`. Defaults only fill gaps, so a handler that sets a header keeps its
+/// value. Headers listed with [`Self::without_header`] are stripped from every response, gRPC
+/// included; defaults are not added to gRPC responses.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResponseHeaders {
+    defaults: Vec<(HeaderName, HeaderValue)>,
+    remove: Vec<HeaderName>,
+    routes: Vec<RouteHeaders>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RouteHeaders {
+    prefix: String,
+    name: HeaderName,
+    /// `None` keeps the default off this prefix.
+    value: Option<HeaderValue>,
+}
+
+impl ResponseHeaders {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.defaults.retain(|(existing, _)| *existing != name);
+        self.defaults.push((name, value));
+        self
+    }
+
+    pub fn with_server(self, value: HeaderValue) -> Self {
+        self.with_header(header::SERVER, value)
+    }
+
+    pub fn with_cache_control(self, value: HeaderValue) -> Self {
+        self.with_header(header::CACHE_CONTROL, value)
+    }
+
+    /// Removes `name` from every response, e.g. an `X-Powered-By` set by an embedded service.
+    pub fn without_header(mut self, name: HeaderName) -> Self {
+        if !self.remove.contains(&name) {
+            self.remove.push(name);
+        }
+        self
+    }
+
+    /// Uses `value` instead of the default `name` for paths under `prefix` (segment-wise, the
+    /// longest prefix wins), e.g. a long `Cache-Control` for `/assets`.
+    pub fn with_route_header(
+        mut self,
+        prefix: impl Into<String>,
+        name: HeaderName,
+        value: HeaderValue,
+    ) -> Self {
+        self.routes.push(RouteHeaders {
+            prefix: prefix.into(),
+            name,
+            value: Some(value),
+        });
+        self
+    }
+
+    /// Skips the default `name` for paths under `prefix`.
+    pub fn skip_route_header(mut self, prefix: impl Into<String>, name: HeaderName) -> Self {
+        self.routes.push(RouteHeaders {
+            prefix: prefix.into(),
+            name,
+            value: None,
+        });
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.defaults.is_empty() || !self.remove.is_empty() || !self.routes.is_empty()
+    }
+
+    /// `name=value` defaults joined by `; `, e.g. for startup diagnostics.
+    pub fn defaults_summary(&self) -> Option<String> {
+        (!self.defaults.is_empty()).then(|| {
+            self.defaults
+                .iter()
+                .map(|(name, value)| format!("{name}={}", value.to_str().unwrap_or("<binary>")))
+                .collect::<Vec<_>>()
+                .join("; ")
+        })
+    }
+
+    pub fn removed_summary(&self) -> Option<String> {
+        (!self.remove.is_empty()).then(|| {
+            self.remove
+                .iter()
+                .map(HeaderName::as_str)
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+    }
+
+    /// Parses `name=value` pairs separated by `;` (values may contain `,` and `=`) and a
+    /// comma-separated removal list; invalid entries are logged and skipped.
+    pub fn parse(defaults: Option<&str>, remove: Option<&str>) -> Self {
+        let mut headers = Self::new();
+        for entry in defaults.unwrap_or_default().split(';') {
+            let entry = entry.trim();
+            if entry.is_empty() {
+                continue;
+            }
+            let parsed = entry.split_once('=').and_then(|(name, value)| {
+                Some((
+                    HeaderName::from_bytes(name.trim().as_bytes()).ok()?,
+                    HeaderValue::from_str(value.trim()).ok()?,
+                ))
+            });
+            match parsed {
+                Some((name, value)) => headers = headers.with_header(name, value),
+                None => tracing::warn!(entry, "ignoring invalid response header"),
+            }
+        }
+        for name in remove.unwrap_or_default().split(',').map(str::trim) {
+            if name.is_empty() {
+                continue;
+            }
+            match HeaderName::from_bytes(name.as_bytes()) {
+                Ok(name) => headers = headers.without_header(name),
+                Err(_) => tracing::warn!(name, "ignoring invalid response header name"),
+            }
+        }
+        headers
+    }
+
+    fn route_override(&self, path: &str, name: &HeaderName) -> Option<&RouteHeaders> {
+        self.routes
+            .iter()
+            .filter(|route| route.name == *name && path_has_prefix(path, &route.prefix))
+            .max_by_key(|route| route.prefix.len())
+    }
+
+    fn apply(&self, path: &str, is_grpc: bool, response: &mut Response) {
+        let headers = response.headers_mut();
+        for name in &self.remove {
+            headers.remove(name);
+        }
+        if is_grpc {
+            return;
+        }
+        let route_only = self
+            .routes
+            .iter()
+            .filter(|route| !self.defaults.iter().any(|(name, _)| *name == route.name))
+            .map(|route| (&route.name, None));
+        let defaults = self
+            .defaults
+            .iter()
+            .map(|(name, value)| (name, Some(value)))
+            .chain(route_only);
+        for (name, default) in defaults {
+            let value = match self.route_override(path, name) {
+                Some(route) => route.value.as_ref(),
+                None => default,
+            };
+            if let Some(value) = value {
+                headers.entry(name).or_insert_with(|| value.clone());
+            }
+        }
+    }
+}
+
+pub(crate) fn path_has_prefix(path: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    prefix.is_empty()
+        || path
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+pub(crate) async fn response_headers_middleware(
+    State(headers): State<ResponseHeaders>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let path = req.uri().path().to_string();
+    let is_grpc = is_grpc_request(req.headers());
+    let mut response = next.run(req).await;
+    headers.apply(&path, is_grpc, &mut response);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, response::IntoResponse, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn app(headers: ResponseHeaders) -> Router {
+        Router::new()
+            .route("/notes", get(|| async { "notes" }))
+            .route("/assets/app.js", get(|| async { "js" }))
+            .route("/assets/raw", get(|| async { "raw" }))
+            .route(
+                "/legacy",
+                get(|| async {
+                    (
+                        [("x-powered-by", "php"), ("cache-control", "max-age=60")],
+                        "legacy",
+                    )
+                        .into_response()
+                }),
+            )
+            .layer(axum::middleware::from_fn_with_state(
+                headers,
+                response_headers_middleware,
+            ))
+    }
+
+    async fn call(app: &Router, path: &str, grpc: bool) -> axum::http::HeaderMap {
+        let mut request = axum::http::Request::builder().uri(path);
+        if grpc {
+            request = request.header("content-type", "application/grpc");
+        }
+        app.clone()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .headers()
+            .clone()
+    }
+
+    #[tokio::test]
+    async fn defaults_fill_gaps_route_overrides_apply_and_sensitive_headers_are_removed() {
+        let app = app(ResponseHeaders::new()
+            .with_server(HeaderValue::from_static("openportio"))
+            .with_cache_control(HeaderValue::from_static("no-store"))
+            .with_header(
+                HeaderName::from_static("x-org"),
+                HeaderValue::from_static("acme"),
+            )
+            .with_route_header(
+                "/assets",
+                header::CACHE_CONTROL,
+                HeaderValue::from_static("public, max-age=3600"),
+            )
+            .skip_route_header("/assets/raw", header::CACHE_CONTROL)
+            .without_header(HeaderName::from_static("x-powered-by")));
+
+        let notes = call(&app, "/notes", false).await;
+        assert_eq!(notes["server"], "openportio");
+        assert_eq!(notes["cache-control"], "no-store");
+        assert_eq!(notes["x-org"], "acme");
+
+        let asset = call(&app, "/assets/app.js", false).await;
+        assert_eq!(asset["cache-control"], "public, max-age=3600");
+        assert!(call(&app, "/assets/raw", false)
+            .await
+            .get("cache-control")
+            .is_none());
+
+        let legacy = call(&app, "/legacy", false).await;
+        assert_eq!(legacy["cache-control"], "max-age=60");
+        assert!(legacy.get("x-powered-by").is_none());
+
+        let grpc = call(&app, "/notes", true).await;
+        assert!(grpc.get("cache-control").is_none());
+    }
+
+    #[test]
+    fn parses_env_style_lists() {
+        let headers = ResponseHeaders::parse(
+            Some("server=openportio; cache-control=no-store, max-age=0; bad header=x"),
+            Some("x-powered-by, ,server"),
+        );
+        assert_eq!(
+            headers.defaults_summary().as_deref(),
+            Some("server=openportio; cache-control=no-store, max-age=0")
+        );
+        assert_eq!(
+            headers.removed_summary().as_deref(),
+            Some("x-powered-by,server")
+        );
+        assert!(!ResponseHeaders::parse(None, None).is_enabled());
+    }
+}
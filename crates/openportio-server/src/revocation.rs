@@ -0,0 +1,542 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    future::Future,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    extract::{Json, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::post,
+    Router,
+};
+use serde::Deserialize;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+
+use crate::{
+    api::{ApiErrorResponse, ValidatedJson},
+    di,
+};
+
+pub const REVOCATIONS_PATH: &str = "/internal/revocations";
+
+const DEFAULT_REDIS_PORT: u16 = 6379;
+const DEFAULT_REDIS_KEY_PREFIX: &str = "openportio:revoked:";
+const DEFAULT_REDIS_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_IDLE_REDIS_CONNECTIONS: usize = 8;
+/// Upper bound for a reply line or bulk string; the store only reads status and integer replies.
+const MAX_REDIS_REPLY_BYTES: u64 = 64 * 1024;
+
+/// Deny-list of token ids (`jti`), consulted on the authentication path of every REST request
+/// and gRPC call that carries a token with a `jti`.
+#[axum::async_trait]
+pub trait TokenRevocationStore: Send + Sync + 'static {
+    async fn is_revoked(&self, jti: &str) -> Result<bool, String>;
+
+    /// `expires_at` is the token's `exp` (unix seconds); entries may be dropped after it passes.
+    async fn revoke(&self, jti: &str, expires_at: Option<u64>) -> Result<(), String>;
+}
+
+/// Cloneable handle to a [`TokenRevocationStore`], resolvable from `DependencyOverrides`.
+#[derive(Clone)]
+pub struct RevocationList {
+    store: Arc<dyn TokenRevocationStore>,
+}
+
+impl RevocationList {
+    pub fn new(store: impl TokenRevocationStore) -> Self {
+        Self {
+            store: Arc::new(store),
+        }
+    }
+
+    pub async fn is_revoked(&self, jti: &str) -> Result<bool, String> {
+        self.store.is_revoked(jti).await
+    }
+
+    pub async fn revoke(&self, jti: &str, expires_at: Option<u64>) -> Result<(), String> {
+        self.store.revoke(jti, expires_at).await
+    }
+
+    pub(crate) fn from_extensions(extensions: &http::Extensions) -> Option<Self> {
+        di::resolve_from_extensions(extensions)
+    }
+}
+
+impl fmt::Debug for RevocationList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RevocationList").finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct InMemoryRevocationStore {
+    entries: RwLock<HashMap<String, Option<u64>>>,
+}
+
+impl InMemoryRevocationStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[axum::async_trait]
+impl TokenRevocationStore for InMemoryRevocationStore {
+    async fn is_revoked(&self, jti: &str) -> Result<bool, String> {
+        let guard = self
+            .entries
+            .read()
+            .map_err(|_| "revocation store lock poisoned".to_string())?;
+        Ok(match guard.get(jti) {
+            Some(Some(expires_at)) => *expires_at > unix_now(),
+            Some(None) => true,
+            None => false,
+        })
+    }
+
+    async fn revoke(&self, jti: &str, expires_at: Option<u64>) -> Result<(), String> {
+        let mut guard = self
+            .entries
+            .write()
+            .map_err(|_| "revocation store lock poisoned".to_string())?;
+        let now = unix_now();
+        guard.retain(|_, expiry| expiry.is_none_or(|expiry| expiry > now));
+        guard.insert(jti.to_string(), expires_at);
+        Ok(())
+    }
+}
+
+type RedisConnection = BufReader<TcpStream>;
+
+/// Redis-backed store speaking RESP over a small pool of connections. Entries are written with
+/// `SET <prefix><jti> 1 EXAT <exp>` so Redis evicts them once the token would have expired anyway.
+/// Connecting and every command are bounded by [`Self::with_timeout`] (2 s by default).
+pub struct RedisRevocationStore {
+    addr: String,
+    password: Option<String>,
+    database: Option<u32>,
+    key_prefix: String,
+    timeout: Duration,
+    idle: Mutex<Vec<RedisConnection>>,
+}
+
+impl RedisRevocationStore {
+    /// Accepts `redis://[:password@]host[:port][/db]`.
+    pub fn new(url: &str) -> Result<Self, String> {
+        let rest = url
+            .strip_prefix("redis://")
+            .ok_or_else(|| format!("unsupported redis url `{url}`; expected redis://"))?;
+        let (credentials, rest) = match rest.rsplit_once('@') {
+            Some((credentials, rest)) => (Some(credentials), rest),
+            None => (None, rest),
+        };
+        let password = credentials
+            .map(|credentials| {
+                credentials
+                    .split_once(':')
+                    .map_or(credentials, |(_, password)| password)
+            })
+            .filter(|password| !password.is_empty())
+            .map(str::to_string);
+        let (host, database) = match rest.split_once('/') {
+            Some((host, "")) => (host, None),
+            Some((host, db)) => (
+                host,
+                Some(
+                    db.parse::<u32>()
+                        .map_err(|_| format!("invalid redis database `{db}`"))?,
+                ),
+            ),
+            None => (rest, None),
+        };
+        if host.is_empty() {
+            return Err(format!("redis url `{url}` has no host"));
+        }
+        let addr = if host.contains(':') {
+            host.to_string()
+        } else {
+            format!("{host}:{DEFAULT_REDIS_PORT}")
+        };
+
+        Ok(Self {
+            addr,
+            password,
+            database,
+            key_prefix: DEFAULT_REDIS_KEY_PREFIX.to_string(),
+            timeout: DEFAULT_REDIS_TIMEOUT,
+            idle: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn with_key_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.key_prefix = prefix.into();
+        self
+    }
+
+    /// Bound for connecting (including `AUTH`/`SELECT`) and for each command round trip.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    async fn command(&self, args: &[&str]) -> Result<RespValue, String> {
+        // A stale pooled connection gets one retry on a fresh one before the error is surfaced.
+        if let Some(mut connection) = self.checkout() {
+            match self
+                .bounded("command", send_command(&mut connection, args))
+                .await
+            {
+                Ok(value) => {
+                    self.checkin(connection);
+                    return Ok(value);
+                }
+                Err(err) => tracing::debug!(error = %err, "redis command failed; reconnecting"),
+            }
+        }
+        let mut connection = self.connect().await?;
+        let value = self
+            .bounded("command", send_command(&mut connection, args))
+            .await?;
+        self.checkin(connection);
+        Ok(value)
+    }
+
+    fn checkout(&self) -> Option<RedisConnection> {
+        self.idle
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .pop()
+    }
+
+    fn checkin(&self, connection: RedisConnection) {
+        let mut idle = self.idle.lock().unwrap_or_else(|err| err.into_inner());
+        if idle.len() < MAX_IDLE_REDIS_CONNECTIONS {
+            idle.push(connection);
+        }
+    }
+
+    async fn connect(&self) -> Result<RedisConnection, String> {
+        self.bounded("connect", async {
+            let stream = TcpStream::connect(&self.addr)
+                .await
+                .map_err(|err| format!("failed to connect to redis at {}: {err}", self.addr))?;
+            let mut connection = BufReader::new(stream);
+            if let Some(password) = &self.password {
+                expect_ok(send_command(&mut connection, &["AUTH", password]).await?)?;
+            }
+            if let Some(database) = self.database {
+                expect_ok(
+                    send_command(&mut connection, &["SELECT", &database.to_string()]).await?,
+                )?;
+            }
+            Ok(connection)
+        })
+        .await
+    }
+
+    async fn bounded<T>(
+        &self,
+        operation: &str,
+        future: impl Future<Output = Result<T, String>>,
+    ) -> Result<T, String> {
+        tokio::time::timeout(self.timeout, future)
+            .await
+            .unwrap_or_else(|_| {
+                Err(format!(
+                    "redis {operation} at {} timed out after {:?}",
+                    self.addr, self.timeout
+                ))
+            })
+    }
+}
+
+impl fmt::Debug for RedisRevocationStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RedisRevocationStore")
+            .field("addr", &self.addr)
+            .field("database", &self.database)
+            .field("key_prefix", &self.key_prefix)
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+#[axum::async_trait]
+impl TokenRevocationStore for RedisRevocationStore {
+    async fn is_revoked(&self, jti: &str) -> Result<bool, String> {
+        let key = format!("{}{jti}", self.key_prefix);
+        match self.command(&["EXISTS", &key]).await? {
+            RespValue::Integer(count) => Ok(count > 0),
+            other => Err(format!("unexpected redis EXISTS reply: {other:?}")),
+        }
+    }
+
+    async fn revoke(&self, jti: &str, expires_at: Option<u64>) -> Result<(), String> {
+        let key = format!("{}{jti}", self.key_prefix);
+        let reply = match expires_at {
+            Some(expires_at) if expires_at <= unix_now() => return Ok(()),
+            Some(expires_at) => {
+                self.command(&["SET", &key, "1", "EXAT", &expires_at.to_string()])
+                    .await?
+            }
+            None => self.command(&["SET", &key, "1"]).await?,
+        };
+        expect_ok(reply)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum RespValue {
+    Simple(String),
+    Integer(i64),
+    Bulk(Option<Vec<u8>>),
+}
+
+async fn send_command(
+    connection: &mut RedisConnection,
+    args: &[&str],
+) -> Result<RespValue, String> {
+    let mut frame = format!("*{}\r\n", args.len()).into_bytes();
+    for arg in args {
+        frame.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        frame.extend_from_slice(arg.as_bytes());
+        frame.extend_from_slice(b"\r\n");
+    }
+    connection
+        .get_mut()
+        .write_all(&frame)
+        .await
+        .map_err(|err| format!("failed to write redis command: {err}"))?;
+    read_reply(connection).await
+}
+
+async fn read_reply(connection: &mut RedisConnection) -> Result<RespValue, String> {
+    let mut line = String::new();
+    let read = (&mut *connection)
+        .take(MAX_REDIS_REPLY_BYTES)
+        .read_line(&mut line)
+        .await
+        .map_err(|err| format!("failed to read redis reply: {err}"))?;
+    if read == 0 {
+        return Err("redis closed the connection".to_string());
+    }
+    if !line.ends_with('\n') {
+        return Err(format!(
+            "redis reply line exceeds {MAX_REDIS_REPLY_BYTES} bytes"
+        ));
+    }
+    let line = line.trim_end_matches(['\r', '\n']);
+    let (kind, payload) = line.split_at(line.len().min(1));
+    match kind {
+        "+" => Ok(RespValue::Simple(payload.to_string())),
+        "-" => Err(format!("redis error: {payload}")),
+        ":" => payload
+            .parse()
+            .map(RespValue::Integer)
+            .map_err(|_| format!("invalid redis integer reply `{payload}`")),
+        "$" => {
+            let len: i64 = payload
+                .parse()
+                .map_err(|_| format!("invalid redis bulk length `{payload}`"))?;
+            let Ok(len) = u64::try_from(len) else {
+                return Ok(RespValue::Bulk(None));
+            };
+            if len > MAX_REDIS_REPLY_BYTES {
+                return Err(format!(
+                    "redis bulk reply of {len} bytes exceeds {MAX_REDIS_REPLY_BYTES}"
+                ));
+            }
+            // Bounded above, so the terminator cannot overflow the length.
+            let mut bytes = vec![0; len as usize + 2];
+            connection
+                .read_exact(&mut bytes)
+                .await
+                .map_err(|err| format!("failed to read redis bulk reply: {err}"))?;
+            bytes.truncate(len as usize);
+            Ok(RespValue::Bulk(Some(bytes)))
+        }
+        _ => Err(format!("unsupported redis reply `{line}`")),
+    }
+}
+
+fn expect_ok(reply: RespValue) -> Result<(), String> {
+    match reply {
+        RespValue::Simple(status) if status == "OK" => Ok(()),
+        other => Err(format!("unexpected redis reply: {other:?}")),
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Deserialize, validator::Validate, crate::RequestValidation)]
+pub struct RevokeTokenRequest {
+    #[validate(length(min = 1, max = 512))]
+    pub jti: String,
+    /// Token `exp` in unix seconds; lets the store forget the entry once the token is dead anyway.
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+}
+
+/// Admin route accepting `POST /internal/revocations` with a [`RevokeTokenRequest`] body.
+pub fn revocations_router(list: RevocationList) -> Router {
+    Router::new()
+        .route(REVOCATIONS_PATH, post(revoke_token))
+        .with_state(list)
+}
+
+async fn revoke_token(
+    State(list): State<RevocationList>,
+    ValidatedJson(request): ValidatedJson<RevokeTokenRequest>,
+) -> Response {
+    match list.revoke(&request.jti, request.expires_at).await {
+        Ok(()) => {
+            tracing::info!(jti = %request.jti, "token revoked");
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(err) => {
+            tracing::error!(error = %err, "failed to store token revocation");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiErrorResponse::internal_server_error()),
+            )
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{io::AsyncWriteExt, net::TcpListener};
+
+    #[tokio::test]
+    async fn in_memory_store_forgets_expired_entries() {
+        let store = InMemoryRevocationStore::new();
+        store.revoke("live", None).await.unwrap();
+        store.revoke("dead", Some(unix_now() - 1)).await.unwrap();
+        assert!(store.is_revoked("live").await.unwrap());
+        assert!(!store.is_revoked("dead").await.unwrap());
+        assert!(!store.is_revoked("unknown").await.unwrap());
+    }
+
+    #[test]
+    fn redis_url_parsing_supports_password_port_and_database() {
+        let store = RedisRevocationStore::new("redis://:s3cret@cache.internal:6380/2").unwrap();
+        assert_eq!(store.addr, "cache.internal:6380");
+        assert_eq!(store.password.as_deref(), Some("s3cret"));
+        assert_eq!(store.database, Some(2));
+
+        let store = RedisRevocationStore::new("redis://localhost").unwrap();
+        assert_eq!(store.addr, "localhost:6379");
+        assert!(RedisRevocationStore::new("http://localhost").is_err());
+    }
+
+    /// Accepts one connection and answers each command with `reply(args, keys)`.
+    async fn fake_redis(
+        reply: fn(&[String], &mut Vec<String>) -> String,
+    ) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut keys = Vec::new();
+            let mut commands = Vec::new();
+            loop {
+                let mut header = String::new();
+                if reader.read_line(&mut header).await.unwrap_or(0) == 0 {
+                    break;
+                }
+                let argc: usize = header.trim()[1..].parse().unwrap();
+                let mut args = Vec::new();
+                for _ in 0..argc {
+                    let mut len = String::new();
+                    reader.read_line(&mut len).await.unwrap();
+                    let mut value = vec![0; len.trim()[1..].parse::<usize>().unwrap() + 2];
+                    reader.read_exact(&mut value).await.unwrap();
+                    args.push(String::from_utf8_lossy(&value[..value.len() - 2]).to_string());
+                }
+                let response = reply(&args, &mut keys);
+                commands.push(args.join(" "));
+                if reader
+                    .get_mut()
+                    .write_all(response.as_bytes())
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+            }
+            commands
+        });
+        (format!("redis://{addr}"), server)
+    }
+
+    #[tokio::test]
+    async fn redis_store_speaks_resp_set_and_exists() {
+        let (url, server) = fake_redis(|args, keys| match args[0].as_str() {
+            "SET" => {
+                keys.push(args[1].clone());
+                "+OK\r\n".to_string()
+            }
+            "EXISTS" => format!(":{}\r\n", u8::from(keys.contains(&args[1]))),
+            _ => "-ERR unknown command\r\n".to_string(),
+        })
+        .await;
+
+        let store = RedisRevocationStore::new(&url).unwrap();
+        assert!(!store.is_revoked("abc").await.unwrap());
+        let expires_at = unix_now() + 60;
+        store.revoke("abc", Some(expires_at)).await.unwrap();
+        assert!(store.is_revoked("abc").await.unwrap());
+        drop(store);
+
+        let commands = server.await.unwrap();
+        assert_eq!(
+            commands,
+            vec![
+                "EXISTS openportio:revoked:abc".to_string(),
+                format!("SET openportio:revoked:abc 1 EXAT {expires_at}"),
+                "EXISTS openportio:revoked:abc".to_string(),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn redis_store_rejects_oversized_bulk_replies() {
+        let (url, _server) = fake_redis(|_, _| "$9223372036854775807\r\n".to_string()).await;
+        let store = RedisRevocationStore::new(&url).unwrap();
+        let err = store.is_revoked("abc").await.unwrap_err();
+        assert!(err.contains("exceeds"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn redis_store_times_out_instead_of_waiting_on_a_silent_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        // Accepts and never answers.
+        let _server = tokio::spawn(async move {
+            let (_stream, _) = listener.accept().await.unwrap();
+            std::future::pending::<()>().await
+        });
+
+        let store = RedisRevocationStore::new(&format!("redis://{addr}"))
+            .unwrap()
+            .with_timeout(Duration::from_millis(50));
+        let started = std::time::Instant::now();
+        let err = store.is_revoked("abc").await.unwrap_err();
+        assert!(err.contains("timed out"), "{err}");
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+}
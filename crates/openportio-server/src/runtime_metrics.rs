@@ -0,0 +1,176 @@
+use std::{sync::Arc, time::Duration};
+
+use openportio_core::MetricsSink;
+use tokio::{runtime::Handle, time::Instant};
+
+use crate::transport;
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Periodically publishes tokio runtime gauges and the open connection count to the
+/// `MetricsSink`, for saturation that request metrics cannot show:
+///
+/// - `runtime.workers`, `runtime.alive_tasks`, `runtime.global_queue_depth`
+/// - `runtime.worker_busy_ratio` (label `worker`): busy time over the last interval, `0..=1`
+/// - `runtime.scheduling_delay_seconds` (histogram): how long a freshly spawned probe task waits
+///   before its first poll
+/// - `server.connections`: connections currently open on the server's listeners
+///
+/// `OpenportioServer::with_prometheus_metrics` scrapes them at `/metrics` along with the request
+/// metrics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuntimeMetrics {
+    interval: Duration,
+}
+
+impl Default for RuntimeMetrics {
+    fn default() -> Self {
+        Self {
+            interval: DEFAULT_INTERVAL,
+        }
+    }
+}
+
+impl RuntimeMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Publish interval (default 10s).
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval.max(Duration::from_millis(10));
+        self
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Enabled by `OPENPORTIO_RUNTIME_METRICS_INTERVAL_SECONDS`.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        Ok(
+            crate::env::setting::<u64>("RUNTIME_METRICS_INTERVAL_SECONDS")?
+                .filter(|seconds| *seconds > 0)
+                .map(|seconds| Self::new().with_interval(Duration::from_secs(seconds))),
+        )
+    }
+
+    /// Publishes every interval until the task is aborted. Must run inside a tokio runtime.
+    pub async fn run(self, metrics: Arc<dyn MetricsSink>) {
+        let mut collector = RuntimeCollector::new(Handle::current(), metrics);
+        let mut ticker = tokio::time::interval(self.interval);
+        loop {
+            ticker.tick().await;
+            collector.collect().await;
+        }
+    }
+}
+
+/// Keeps the previous busy totals so each publish reports the ratio for its own interval.
+pub struct RuntimeCollector {
+    handle: Handle,
+    metrics: Arc<dyn MetricsSink>,
+    busy: Vec<Duration>,
+    last: Instant,
+}
+
+impl RuntimeCollector {
+    pub fn new(handle: Handle, metrics: Arc<dyn MetricsSink>) -> Self {
+        let busy = worker_busy(&handle);
+        Self {
+            handle,
+            metrics,
+            busy,
+            last: Instant::now(),
+        }
+    }
+
+    pub async fn collect(&mut self) {
+        let runtime = self.handle.metrics();
+        let workers = runtime.num_workers();
+        self.metrics
+            .set_gauge("runtime.workers", workers as f64, &[]);
+        self.metrics
+            .set_gauge("runtime.alive_tasks", runtime.num_alive_tasks() as f64, &[]);
+        self.metrics.set_gauge(
+            "runtime.global_queue_depth",
+            runtime.global_queue_depth() as f64,
+            &[],
+        );
+        self.metrics.set_gauge(
+            "server.connections",
+            transport::open_connections() as f64,
+            &[],
+        );
+
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        let busy = worker_busy(&self.handle);
+        if elapsed > 0.0 {
+            for (worker, (current, previous)) in busy.iter().zip(&self.busy).enumerate() {
+                let ratio = current.saturating_sub(*previous).as_secs_f64() / elapsed;
+                let worker = worker.to_string();
+                self.metrics.set_gauge(
+                    "runtime.worker_busy_ratio",
+                    ratio.min(1.0),
+                    &[("worker", worker.as_str())],
+                );
+            }
+        }
+        self.busy = busy;
+        self.last = now;
+
+        let spawned = Instant::now();
+        if let Ok(delay) = self.handle.spawn(async move { spawned.elapsed() }).await {
+            self.metrics.observe_histogram(
+                "runtime.scheduling_delay_seconds",
+                delay.as_secs_f64(),
+                &[],
+            );
+        }
+    }
+}
+
+#[cfg(target_has_atomic = "64")]
+fn worker_busy(handle: &Handle) -> Vec<Duration> {
+    let runtime = handle.metrics();
+    (0..runtime.num_workers())
+        .map(|worker| runtime.worker_total_busy_duration(worker))
+        .collect()
+}
+
+#[cfg(not(target_has_atomic = "64"))]
+fn worker_busy(_handle: &Handle) -> Vec<Duration> {
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openportio_test::RecordingMetrics;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn publishes_runtime_gauges_busy_ratios_and_scheduling_delay() {
+        let metrics = Arc::new(RecordingMetrics::default());
+        let mut collector = RuntimeCollector::new(Handle::current(), metrics.clone());
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        collector.collect().await;
+
+        assert_eq!(metrics.gauge_values("runtime.workers"), [2.0]);
+        assert_eq!(metrics.gauge_values("runtime.alive_tasks").len(), 1);
+        assert_eq!(metrics.gauge_values("runtime.global_queue_depth").len(), 1);
+        assert_eq!(metrics.gauge_values("server.connections").len(), 1);
+        assert!(matches!(
+            metrics.histogram_values("runtime.scheduling_delay_seconds")[..],
+            [delay] if delay >= 0.0
+        ));
+        let ratios = metrics.gauge_values("runtime.worker_busy_ratio");
+        assert_eq!(ratios.len(), 2);
+        assert!(ratios.iter().all(|ratio| (0.0..=1.0).contains(ratio)));
+        assert!(metrics
+            .gauges()
+            .iter()
+            .filter(|gauge| gauge.starts_with("runtime.worker_busy_ratio"))
+            .all(|gauge| gauge.starts_with("runtime.worker_busy_ratio{worker=")));
+    }
+}
@@ -0,0 +1,93 @@
+use std::{error::Error, fmt, io, net::SocketAddr};
+
+use crate::{di::DependencyGraphError, lifecycle::PhaseError};
+
+/// Why [`OpenportioServer::run`](crate::OpenportioServer::run) stopped. Supervisors can match
+/// on the variant or use [`ServerError::exit_code`] to tell configuration mistakes (restarting
+/// will not help) from environmental failures (a restart may).
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ServerError {
+    /// HTTP/2, socket, redirect or environment settings were rejected before anything was bound.
+    InvalidConfig(String),
+    /// REST/gRPC addresses, gRPC routes or gRPC TLS combined in a way the run mode cannot serve.
+    InvalidDualPortConfig(String),
+    /// The dependency graph has missing or cyclic entries.
+    Dependencies(DependencyGraphError),
+    /// A lifecycle hook failed with [`PhaseFailurePolicy::Abort`](crate::lifecycle::PhaseFailurePolicy::Abort).
+    Phase(PhaseError),
+    /// gRPC TLS certificates or keys could not be loaded.
+    TlsConfig(io::Error),
+    /// A listener could not be bound (after any configured bind retries).
+    BindFailed { addr: SocketAddr, source: io::Error },
+    /// A listener failed while serving; `listener` is `main`, `rest` or `grpc`.
+    Serve {
+        listener: &'static str,
+        source: io::Error,
+    },
+    /// A background task panicked; the server drained and stopped.
+    BackgroundTaskPanicked { task: String, message: String },
+}
+
+impl ServerError {
+    /// A sysexits-style process exit code: `78` (config) for configuration and TLS errors,
+    /// `75` (temporary failure) for bind, serve and startup-phase failures, `70` (software)
+    /// for background task panics.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::InvalidConfig(_)
+            | Self::InvalidDualPortConfig(_)
+            | Self::Dependencies(_)
+            | Self::TlsConfig(_) => 78,
+            Self::Phase(_) | Self::BindFailed { .. } | Self::Serve { .. } => 75,
+            Self::BackgroundTaskPanicked { .. } => 70,
+        }
+    }
+}
+
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidConfig(message) | Self::InvalidDualPortConfig(message) => {
+                f.write_str(message)
+            }
+            Self::Dependencies(err) => err.fmt(f),
+            Self::Phase(err) => err.fmt(f),
+            Self::TlsConfig(err) => write!(f, "invalid gRPC TLS configuration: {err}"),
+            Self::BindFailed { source, .. } => source.fmt(f),
+            Self::Serve { listener, source } => {
+                write!(f, "{listener} listener failed: {source}")
+            }
+            Self::BackgroundTaskPanicked { task, message } => {
+                write!(f, "background task `{task}` panicked: {message}")
+            }
+        }
+    }
+}
+
+impl Error for ServerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Dependencies(err) => Some(err),
+            Self::Phase(err) => Some(err),
+            Self::TlsConfig(source)
+            | Self::BindFailed { source, .. }
+            | Self::Serve { source, .. } => Some(source),
+            Self::InvalidConfig(_)
+            | Self::InvalidDualPortConfig(_)
+            | Self::BackgroundTaskPanicked { .. } => None,
+        }
+    }
+}
+
+impl From<DependencyGraphError> for ServerError {
+    fn from(err: DependencyGraphError) -> Self {
+        Self::Dependencies(err)
+    }
+}
+
+impl From<PhaseError> for ServerError {
+    fn from(err: PhaseError) -> Self {
+        Self::Phase(err)
+    }
+}
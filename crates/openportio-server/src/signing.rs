@@ -0,0 +1,588 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{Method, StatusCode, Uri},
+    middleware::{from_fn, from_fn_with_state, Next},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use tower::ServiceExt;
+
+use crate::{
+    api::ApiErrorResponse, middleware::HEALTH_PATHS, response_headers::path_has_prefix,
+    versioning::probe_route,
+};
+
+pub const DEFAULT_SIGNATURE_HEADER: &str = "x-signature";
+pub const DEFAULT_TIMESTAMP_HEADER: &str = "x-signature-timestamp";
+pub const DEFAULT_CLIENT_ID_HEADER: &str = "x-client-id";
+
+const DEFAULT_TOLERANCE: Duration = Duration::from_secs(300);
+const DEFAULT_MAX_BODY_BYTES: usize = 1024 * 1024;
+const SIGNATURE_PREFIX: &str = "sha256=";
+const REPLAY_SWEEP_INTERVAL_SECS: u64 = 60;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Shared-secret lookup by client id. Called on every signed request, so implementations should
+/// answer from memory or a cache.
+pub trait SigningSecrets: Send + Sync + 'static {
+    fn secret(&self, client_id: &str) -> Option<Vec<u8>>;
+}
+
+#[derive(Clone, Default)]
+pub struct StaticSigningSecrets {
+    secrets: HashMap<String, Vec<u8>>,
+}
+
+impl StaticSigningSecrets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_client(mut self, client_id: impl Into<String>, secret: impl AsRef<[u8]>) -> Self {
+        self.secrets
+            .insert(client_id.into(), secret.as_ref().to_vec());
+        self
+    }
+}
+
+impl SigningSecrets for StaticSigningSecrets {
+    fn secret(&self, client_id: &str) -> Option<Vec<u8>> {
+        self.secrets.get(client_id).cloned()
+    }
+}
+
+impl fmt::Debug for StaticSigningSecrets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut clients: Vec<_> = self.secrets.keys().collect();
+        clients.sort_unstable();
+        f.debug_struct("StaticSigningSecrets")
+            .field("clients", &clients)
+            .finish()
+    }
+}
+
+/// HMAC-SHA256 request signature verification for webhook-style callers, independent of JWT
+/// auth. Callers send their client id, a unix-seconds timestamp, and
+/// `hex(hmac_sha256(secret, canonical_string(...)))` (optionally prefixed `sha256=`).
+///
+/// Each accepted signature is remembered until its timestamp leaves the tolerance window, and a
+/// second request carrying it is rejected as `replayed_signature`. The cache lives in this
+/// process: behind several instances, a captured request can still be replayed once per
+/// instance, so keep the tolerance short or make handlers idempotent.
+#[derive(Clone)]
+pub struct RequestSigning {
+    secrets: Arc<dyn SigningSecrets>,
+    signature_header: String,
+    timestamp_header: String,
+    client_id_header: String,
+    tolerance: Duration,
+    max_body_bytes: usize,
+    path_prefixes: Vec<String>,
+    /// The gRPC routes with every service replaced by [`probe_route`]; paths they match are
+    /// exempt.
+    grpc_probe: Option<Router>,
+    seen: Arc<Mutex<SeenSignatures>>,
+}
+
+/// Accepted signatures with the unix second after which their timestamp is stale anyway.
+#[derive(Debug, Default)]
+struct SeenSignatures {
+    entries: HashMap<(String, Vec<u8>), u64>,
+    next_sweep: u64,
+}
+
+impl RequestSigning {
+    pub fn new(secrets: impl SigningSecrets) -> Self {
+        Self {
+            secrets: Arc::new(secrets),
+            signature_header: DEFAULT_SIGNATURE_HEADER.to_string(),
+            timestamp_header: DEFAULT_TIMESTAMP_HEADER.to_string(),
+            client_id_header: DEFAULT_CLIENT_ID_HEADER.to_string(),
+            tolerance: DEFAULT_TOLERANCE,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            path_prefixes: Vec::new(),
+            grpc_probe: None,
+            seen: Arc::new(Mutex::new(SeenSignatures::default())),
+        }
+    }
+
+    pub fn with_signature_header(mut self, name: impl Into<String>) -> Self {
+        self.signature_header = name.into().to_ascii_lowercase();
+        self
+    }
+
+    pub fn with_timestamp_header(mut self, name: impl Into<String>) -> Self {
+        self.timestamp_header = name.into().to_ascii_lowercase();
+        self
+    }
+
+    pub fn with_client_id_header(mut self, name: impl Into<String>) -> Self {
+        self.client_id_header = name.into().to_ascii_lowercase();
+        self
+    }
+
+    /// Maximum clock skew between the signed timestamp and now, in either direction.
+    pub fn with_tolerance(mut self, tolerance: Duration) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Larger bodies are rejected with 413 before hashing.
+    pub fn with_max_body_bytes(mut self, limit: usize) -> Self {
+        self.max_body_bytes = limit;
+        self
+    }
+
+    /// Only verify requests under `prefix` (repeatable, segment-wise: `/admin` covers
+    /// `/admin/users` but not `/administrator`); without any, every REST route except health
+    /// probes is verified.
+    pub fn with_path_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.path_prefixes.push(prefix.into());
+        self
+    }
+
+    /// Exempts requests that route to one of `grpc`'s services. Decided by path rather than
+    /// `content-type`, which the caller controls.
+    pub(crate) fn with_grpc_routes(mut self, grpc: Router) -> Self {
+        self.grpc_probe = Some(grpc.layer(from_fn(probe_route)));
+        self
+    }
+
+    pub(crate) fn summary(&self) -> String {
+        let scope = match self.path_prefixes.as_slice() {
+            [] => "all routes".to_string(),
+            prefixes => prefixes.join(", "),
+        };
+        format!(
+            "hmac-sha256 on {scope}, {}s tolerance",
+            self.tolerance.as_secs()
+        )
+    }
+
+    async fn applies_to(&self, method: &Method, uri: &Uri) -> bool {
+        let path = uri.path();
+        if HEALTH_PATHS.contains(&path) {
+            return false;
+        }
+        let covered = self.path_prefixes.is_empty()
+            || self
+                .path_prefixes
+                .iter()
+                .any(|prefix| path_has_prefix(path, prefix));
+        covered && !self.routes_to_grpc(method, uri).await
+    }
+
+    async fn routes_to_grpc(&self, method: &Method, uri: &Uri) -> bool {
+        let Some(probe) = &self.grpc_probe else {
+            return false;
+        };
+        let Ok(probe_req) = Request::builder()
+            .method(method.clone())
+            .uri(uri.clone())
+            .body(Body::empty())
+        else {
+            return false;
+        };
+        let response = probe.clone().oneshot(probe_req).await.into_response();
+        response.status() == StatusCode::OK
+    }
+
+    fn header<'a>(&self, req: &'a Request, name: &str) -> Option<&'a str> {
+        req.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    }
+
+    async fn verify(&self, req: Request) -> Result<Request, SignatureRejection> {
+        let client_id = self
+            .header(&req, &self.client_id_header)
+            .ok_or(SignatureRejection::Missing(self.client_id_header.clone()))?
+            .to_string();
+        let timestamp = self
+            .header(&req, &self.timestamp_header)
+            .ok_or(SignatureRejection::Missing(self.timestamp_header.clone()))?
+            .to_string();
+        let signature = self
+            .header(&req, &self.signature_header)
+            .ok_or(SignatureRejection::Missing(self.signature_header.clone()))?;
+        let signature = decode_hex(
+            signature
+                .strip_prefix(SIGNATURE_PREFIX)
+                .unwrap_or(signature),
+        )
+        .ok_or(SignatureRejection::Invalid)?;
+
+        let signed_at = timestamp
+            .parse::<u64>()
+            .map_err(|_| SignatureRejection::Stale)?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        if now.abs_diff(signed_at) > self.tolerance.as_secs() {
+            return Err(SignatureRejection::Stale);
+        }
+        let secret = self
+            .secrets
+            .secret(&client_id)
+            .ok_or(SignatureRejection::UnknownClient)?;
+
+        let (parts, body) = req.into_parts();
+        let body = to_bytes(body, self.max_body_bytes)
+            .await
+            .map_err(|_| SignatureRejection::TooLarge(self.max_body_bytes))?;
+        let path = parts
+            .uri
+            .path_and_query()
+            .map(|path| path.as_str())
+            .unwrap_or("/");
+        let canonical = canonical_string(&parts.method, path, &timestamp, &body);
+        let mut mac =
+            HmacSha256::new_from_slice(&secret).map_err(|_| SignatureRejection::Invalid)?;
+        mac.update(canonical.as_bytes());
+        mac.verify_slice(&signature)
+            .map_err(|_| SignatureRejection::Invalid)?;
+        self.remember(
+            &client_id,
+            signature,
+            signed_at + self.tolerance.as_secs(),
+            now,
+        )?;
+
+        let mut req = Request::from_parts(parts, Body::from(body));
+        req.extensions_mut().insert(SignedClient(client_id));
+        Ok(req)
+    }
+}
+
+impl RequestSigning {
+    /// Records an accepted signature, failing if it was already accepted and is not yet stale.
+    fn remember(
+        &self,
+        client_id: &str,
+        signature: Vec<u8>,
+        expires_at: u64,
+        now: u64,
+    ) -> Result<(), SignatureRejection> {
+        let mut seen = self.seen.lock().unwrap_or_else(PoisonError::into_inner);
+        if now >= seen.next_sweep {
+            seen.entries.retain(|_, expires_at| *expires_at >= now);
+            seen.next_sweep = now + REPLAY_SWEEP_INTERVAL_SECS;
+        }
+        match seen
+            .entries
+            .insert((client_id.to_string(), signature), expires_at)
+        {
+            Some(previous) if previous >= now => Err(SignatureRejection::Replayed),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Debug for RequestSigning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestSigning")
+            .field("signature_header", &self.signature_header)
+            .field("timestamp_header", &self.timestamp_header)
+            .field("client_id_header", &self.client_id_header)
+            .field("tolerance", &self.tolerance)
+            .field("path_prefixes", &self.path_prefixes)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Client id of a verified signed request, available to handlers as `Extension<SignedClient>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedClient(pub String);
+
+/// `"{timestamp}\n{METHOD}\n{path?query}\n{hex(sha256(body))}"`, the string both sides sign.
+pub fn canonical_string(
+    method: &Method,
+    path_and_query: &str,
+    timestamp: &str,
+    body: &[u8],
+) -> String {
+    format!(
+        "{timestamp}\n{}\n{path_and_query}\n{}",
+        method.as_str(),
+        encode_hex(&Sha256::digest(body))
+    )
+}
+
+/// Hex signature for `canonical`, as a client would compute it.
+pub fn sign(secret: &[u8], canonical: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("hmac accepts keys of any length");
+    mac.update(canonical.as_bytes());
+    encode_hex(&mac.finalize().into_bytes())
+}
+
+/// Verifies every request `signing` applies to before it reaches `router`'s handlers.
+pub fn with_request_signing(router: Router, signing: RequestSigning) -> Router {
+    router.layer(from_fn_with_state(signing, signature_middleware))
+}
+
+async fn signature_middleware(
+    State(signing): State<RequestSigning>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let (method, uri) = (req.method().clone(), req.uri().clone());
+    if !signing.applies_to(&method, &uri).await {
+        return next.run(req).await;
+    }
+    match signing.verify(req).await {
+        Ok(req) => next.run(req).await,
+        Err(rejection) => {
+            tracing::debug!(reason = %rejection.message(), "request signature rejected");
+            rejection.into_response()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SignatureRejection {
+    Missing(String),
+    UnknownClient,
+    Stale,
+    Invalid,
+    Replayed,
+    TooLarge(usize),
+}
+
+impl SignatureRejection {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Missing(_) => "missing_signature",
+            Self::UnknownClient => "unknown_client",
+            Self::Stale => "stale_signature",
+            Self::Invalid => "invalid_signature",
+            Self::Replayed => "replayed_signature",
+            Self::TooLarge(_) => "payload_too_large",
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            Self::Missing(header) => format!("missing `{header}` header"),
+            Self::UnknownClient => "unknown signing client".to_string(),
+            Self::Stale => {
+                "signature timestamp is missing, malformed, or outside the allowed window"
+                    .to_string()
+            }
+            Self::Invalid => "request signature does not match".to_string(),
+            Self::Replayed => "request signature was already used".to_string(),
+            Self::TooLarge(limit) => format!("signed request body exceeds {limit} bytes"),
+        }
+    }
+}
+
+impl IntoResponse for SignatureRejection {
+    fn into_response(self) -> Response {
+        let status = match self {
+            Self::TooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            _ => StatusCode::UNAUTHORIZED,
+        };
+        (
+            status,
+            Json(ApiErrorResponse {
+                code: self.code().to_string(),
+                message: self.message(),
+                detail: None,
+                details: None,
+            }),
+        )
+            .into_response()
+    }
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn decode_hex(raw: &str) -> Option<Vec<u8>> {
+    if !raw.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..raw.len())
+        .step_by(2)
+        .map(|idx| u8::from_str_radix(raw.get(idx..idx + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::post, Extension};
+
+    fn now() -> String {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn signed_requests_are_verified_and_rejections_are_structured() {
+        let signing =
+            RequestSigning::new(StaticSigningSecrets::new().with_client("billing", "s3cret"))
+                .with_path_prefix("/webhooks");
+        let app = with_request_signing(
+            Router::new()
+                .route(
+                    "/webhooks/invoice",
+                    post(
+                        |Extension(client): Extension<SignedClient>, body: String| async move {
+                            format!("{} {body}", client.0)
+                        },
+                    ),
+                )
+                .route("/open", post(|| async { "open" }))
+                .route("/webhooks-archive", post(|| async { "archive" })),
+            signing,
+        );
+        let call = |client: &str, timestamp: String, signature: String, body: &'static str| {
+            app.clone().oneshot(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri("/webhooks/invoice?attempt=1")
+                    .header(DEFAULT_CLIENT_ID_HEADER, client)
+                    .header(DEFAULT_TIMESTAMP_HEADER, timestamp)
+                    .header(DEFAULT_SIGNATURE_HEADER, signature)
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+        };
+        let signature = |timestamp: &str, body: &str| {
+            sign(
+                b"s3cret",
+                &canonical_string(
+                    &Method::POST,
+                    "/webhooks/invoice?attempt=1",
+                    timestamp,
+                    body.as_bytes(),
+                ),
+            )
+        };
+        let code = |response: Response| async move {
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()["code"].clone()
+        };
+
+        let ts = now();
+        let response = call(
+            "billing",
+            ts.clone(),
+            format!("sha256={}", signature(&ts, "paid")),
+            "paid",
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"billing paid");
+
+        let response = call(
+            "billing",
+            ts.clone(),
+            format!("sha256={}", signature(&ts, "paid")),
+            "paid",
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(code(response).await, "replayed_signature");
+
+        let response = call("billing", ts.clone(), signature(&ts, "paid"), "refunded")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(code(response).await, "invalid_signature");
+
+        let stale = "1000".to_string();
+        let response = call("billing", stale.clone(), signature(&stale, "paid"), "paid")
+            .await
+            .unwrap();
+        assert_eq!(code(response).await, "stale_signature");
+
+        let response = call("crm", ts.clone(), signature(&ts, "paid"), "paid")
+            .await
+            .unwrap();
+        assert_eq!(code(response).await, "unknown_client");
+
+        for uri in ["/open", "/webhooks-archive"] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method(Method::POST)
+                        .uri(uri)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn grpc_exemption_follows_routing_not_content_type() {
+        let grpc = Router::new()
+            .route("/notes.Notes/*rest", post(|| async { "grpc" }))
+            .fallback(|| async { StatusCode::NOT_IMPLEMENTED });
+        let signing =
+            RequestSigning::new(StaticSigningSecrets::new().with_client("billing", "s3cret"))
+                .with_grpc_routes(grpc.clone());
+        let app = with_request_signing(
+            Router::new()
+                .route("/webhooks/invoice", post(|| async { "paid" }))
+                .merge(grpc),
+            signing,
+        );
+        let call = |uri: &'static str| {
+            app.clone().oneshot(
+                Request::builder()
+                    .method(Method::POST)
+                    .uri(uri)
+                    .header("content-type", "application/grpc")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        let response = call("/webhooks/invoice").await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = call("/notes.Notes/List").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn accepted_signatures_are_remembered_until_stale() {
+        let signing = RequestSigning::new(StaticSigningSecrets::new());
+        assert_eq!(signing.remember("billing", vec![1], 400, 100), Ok(()));
+        assert_eq!(signing.remember("crm", vec![1], 400, 100), Ok(()));
+        assert_eq!(
+            signing.remember("billing", vec![1], 400, 200),
+            Err(SignatureRejection::Replayed)
+        );
+
+        assert_eq!(signing.remember("billing", vec![2], 700, 401), Ok(()));
+        let seen = signing.seen.lock().unwrap();
+        assert_eq!(seen.entries.len(), 1);
+        assert!(seen.entries.contains_key(&("billing".to_string(), vec![2])));
+    }
+}
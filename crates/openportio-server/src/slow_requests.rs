@@ -0,0 +1,215 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::{from_fn, from_fn_with_state, Next},
+    response::Response,
+    Router,
+};
+use openportio_core::MetricsSink;
+use tokio::time::Instant;
+
+use crate::{
+    auth::CallPrincipal,
+    grpc::{grpc_status, is_known_method},
+    middleware::is_grpc_request,
+};
+
+const REQUEST_ID_HEADER: &str = "x-request-id";
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+#[derive(Clone)]
+struct SlowRequestDetector {
+    threshold: Duration,
+    metrics: Arc<dyn MetricsSink>,
+}
+
+/// Route template copied from `MatchedPath` into the response, where the outer detector can
+/// read it.
+#[derive(Debug, Clone)]
+pub(crate) struct MatchedRoute(pub(crate) String);
+
+/// Logs a `tracing::warn!` (route, duration, principal, request id) and bumps `slow_requests`
+/// (labels `protocol`, `route`) for every REST or gRPC call whose response takes longer than
+/// `threshold`. REST routes are labelled with their template (`/users/:id`), gRPC calls with
+/// their method path; calls that matched no route or method are labelled `unmatched`. Latency is measured until the response head, so streaming bodies are not
+/// included.
+pub fn with_slow_request_detection(
+    router: Router,
+    threshold: Duration,
+    metrics: Arc<dyn MetricsSink>,
+) -> Router {
+    // `route_layer` panics on a router without routes.
+    let router = if router.has_routes() {
+        router.route_layer(from_fn(capture_matched_route))
+    } else {
+        router
+    };
+    router.layer(from_fn_with_state(
+        SlowRequestDetector { threshold, metrics },
+        slow_request_middleware,
+    ))
+}
+
+pub(crate) async fn capture_matched_route(req: Request, next: Next) -> Response {
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string());
+    let mut response = next.run(req).await;
+    if let Some(route) = route {
+        response.extensions_mut().insert(MatchedRoute(route));
+    }
+    response
+}
+
+async fn slow_request_middleware(
+    State(detector): State<SlowRequestDetector>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let started = Instant::now();
+    let is_grpc = is_grpc_request(req.headers());
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let principal = CallPrincipal::attach(req.extensions_mut());
+
+    let response = next.run(req).await;
+    let elapsed = started.elapsed();
+    if elapsed <= detector.threshold {
+        return response;
+    }
+
+    let matched = response
+        .extensions()
+        .get::<MatchedRoute>()
+        .map(|MatchedRoute(route)| route.clone());
+    let (protocol, route) = if is_grpc {
+        let known = is_known_method(matched.is_some(), grpc_status(response.headers()));
+        (
+            "grpc",
+            if known {
+                path
+            } else {
+                UNMATCHED_ROUTE.to_string()
+            },
+        )
+    } else {
+        (
+            "rest",
+            matched.unwrap_or_else(|| UNMATCHED_ROUTE.to_string()),
+        )
+    };
+    let principal = principal.get();
+    tracing::warn!(
+        protocol,
+        method = %method,
+        route = %route,
+        status = response.status().as_u16(),
+        duration_ms = elapsed.as_millis() as u64,
+        threshold_ms = detector.threshold.as_millis() as u64,
+        principal = principal.as_deref().unwrap_or("-"),
+        request_id = request_id.as_deref().unwrap_or("-"),
+        "slow request"
+    );
+    detector.metrics.incr_counter_with(
+        "slow_requests",
+        &[("protocol", protocol), ("route", route.as_str())],
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::StatusCode, routing::get};
+    use openportio_test::RecordingMetrics;
+    use tower::util::ServiceExt;
+
+    #[tokio::test]
+    async fn slow_routes_are_counted_by_template() {
+        let metrics = Arc::new(RecordingMetrics::default());
+        let app = with_slow_request_detection(
+            Router::new()
+                .route("/fast", get(|| async { "fast" }))
+                .route(
+                    "/users/:id",
+                    get(|| async {
+                        tokio::time::sleep(Duration::from_millis(30)).await;
+                        "slow"
+                    }),
+                ),
+            Duration::from_millis(10),
+            metrics.clone(),
+        );
+
+        for uri in ["/fast", "/users/1", "/users/2"] {
+            let response = app
+                .clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        assert_eq!(
+            metrics.counters(),
+            vec![
+                "slow_requests{protocol=rest,route=/users/:id}".to_string(),
+                "slow_requests{protocol=rest,route=/users/:id}".to_string(),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn unknown_grpc_methods_share_the_unmatched_label() {
+        async fn slow_call(req: Request) -> Response {
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            let code = match req.uri().path().ends_with("/Known") {
+                true => "0",
+                false => "12",
+            };
+            Response::builder()
+                .header("content-type", "application/grpc")
+                .header("grpc-status", code)
+                .body(Body::empty())
+                .unwrap()
+        }
+        let metrics = Arc::new(RecordingMetrics::default());
+        let app = with_slow_request_detection(
+            Router::new()
+                .route("/pkg.Svc/*rest", axum::routing::post(slow_call))
+                .fallback(slow_call),
+            Duration::from_millis(10),
+            metrics.clone(),
+        );
+
+        for uri in ["/pkg.Svc/Known", "/pkg.Svc/Made-up-1", "/made.Up/Call"] {
+            app.clone()
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri(uri)
+                        .header("content-type", "application/grpc")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(
+            metrics.counters(),
+            vec![
+                "slow_requests{protocol=grpc,route=/pkg.Svc/Known}".to_string(),
+                "slow_requests{protocol=grpc,route=unmatched}".to_string(),
+                "slow_requests{protocol=grpc,route=unmatched}".to_string(),
+            ]
+        );
+    }
+}
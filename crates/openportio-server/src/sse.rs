@@ -0,0 +1,313 @@
+use std::{
+    convert::Infallible,
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use axum::{
+    extract::Request,
+    response::{
+        sse::{Event, Sse},
+        IntoResponse, Response,
+    },
+    routing::get,
+    Router,
+};
+use futures_util::stream;
+use openportio_core::{
+    auth::AuthPrincipal,
+    events::{BusEvent, EventBus, EventTopic},
+    MetricsSink, SharedClock,
+};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::di;
+
+const DEFAULT_HEARTBEAT: Duration = Duration::from_secs(15);
+
+type EventFilter = dyn Fn(Option<&AuthPrincipal>, &BusEvent) -> bool + Send + Sync;
+
+/// An SSE route fed by one event-bus topic: every event published there is sent as
+/// `id: {event.id}`, `event: {event.kind}`, `data: {event.data as JSON}`.
+#[derive(Clone)]
+pub struct SseTopic {
+    topic: String,
+    filter: Option<Arc<EventFilter>>,
+    heartbeat: Duration,
+}
+
+impl SseTopic {
+    pub fn new(topic: impl EventTopic) -> Self {
+        Self {
+            topic: topic.topic_name().into_owned(),
+            filter: None,
+            heartbeat: DEFAULT_HEARTBEAT,
+        }
+    }
+
+    /// Drops events for which `filter` returns false. The principal is the authenticated caller
+    /// (when the route runs behind auth), so streams can be narrowed to what each user may see.
+    pub fn with_filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(Option<&AuthPrincipal>, &BusEvent) -> bool + Send + Sync + 'static,
+    {
+        self.filter = Some(Arc::new(filter));
+        self
+    }
+
+    /// Keep-alive comment interval (default 15s), measured on the `SharedClock` dependency.
+    pub fn with_heartbeat(mut self, interval: Duration) -> Self {
+        self.heartbeat = interval;
+        self
+    }
+
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+}
+
+impl<T: EventTopic> From<T> for SseTopic {
+    fn from(topic: T) -> Self {
+        Self::new(topic)
+    }
+}
+
+impl fmt::Debug for SseTopic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SseTopic")
+            .field("topic", &self.topic)
+            .field("filtered", &self.filter.is_some())
+            .field("heartbeat", &self.heartbeat)
+            .finish()
+    }
+}
+
+/// `GET path` streaming `topic` from `bus`. The `sse.clients` gauge (label `topic`) tracks open
+/// streams.
+pub fn sse_topic_router(
+    path: &str,
+    topic: SseTopic,
+    bus: EventBus,
+    metrics: Arc<dyn MetricsSink>,
+) -> Router {
+    let route = Arc::new(TopicRoute {
+        topic,
+        bus,
+        metrics,
+        clients: AtomicUsize::new(0),
+    });
+    Router::new().route(
+        path,
+        get(move |req: Request| {
+            let route = Arc::clone(&route);
+            async move {
+                let clock = di::clock_from_extensions(req.extensions());
+                route.stream(req.extensions().get::<AuthPrincipal>().cloned(), clock)
+            }
+        }),
+    )
+}
+
+struct TopicRoute {
+    topic: SseTopic,
+    bus: EventBus,
+    metrics: Arc<dyn MetricsSink>,
+    clients: AtomicUsize,
+}
+
+impl TopicRoute {
+    fn stream(self: Arc<Self>, principal: Option<AuthPrincipal>, clock: SharedClock) -> Response {
+        let receiver = self.bus.subscribe(self.topic.topic.clone());
+        let heartbeat = self.topic.heartbeat;
+        let client = ClientGuard::open(self);
+        let events = stream::unfold(
+            (receiver, client, principal, clock),
+            move |(mut receiver, client, principal, clock)| async move {
+                // Restarted after every frame, so idle streams get a comment each interval.
+                let mut keep_alive = clock.sleep(heartbeat);
+                loop {
+                    let received = tokio::select! {
+                        received = receiver.recv() => received,
+                        () = &mut keep_alive => {
+                            return Some((
+                                Ok::<_, Infallible>(Event::default().comment("")),
+                                (receiver, client, principal, clock),
+                            ));
+                        }
+                    };
+                    let event = match received {
+                        Ok(event) => event,
+                        Err(RecvError::Lagged(skipped)) => {
+                            tracing::warn!(
+                                topic = %client.route.topic.topic,
+                                skipped,
+                                "sse subscriber lagged; events dropped"
+                            );
+                            continue;
+                        }
+                        Err(RecvError::Closed) => return None,
+                    };
+                    if !client.allows(principal.as_ref(), &event) {
+                        continue;
+                    }
+                    return Some((Ok(to_sse(&event)), (receiver, client, principal, clock)));
+                }
+            },
+        );
+        Sse::new(events).into_response()
+    }
+
+    fn set_gauge(&self, clients: usize) {
+        self.metrics.set_gauge(
+            "sse.clients",
+            clients as f64,
+            &[("topic", self.topic.topic.as_str())],
+        );
+    }
+}
+
+/// Counts a connected client for as long as its stream is alive.
+struct ClientGuard {
+    route: Arc<TopicRoute>,
+}
+
+impl ClientGuard {
+    fn open(route: Arc<TopicRoute>) -> Self {
+        let clients = route.clients.fetch_add(1, Ordering::Relaxed) + 1;
+        route.set_gauge(clients);
+        Self { route }
+    }
+
+    fn allows(&self, principal: Option<&AuthPrincipal>, event: &BusEvent) -> bool {
+        self.route
+            .topic
+            .filter
+            .as_ref()
+            .is_none_or(|filter| filter(principal, event))
+    }
+}
+
+impl Drop for ClientGuard {
+    fn drop(&mut self) {
+        let clients = self.route.clients.fetch_sub(1, Ordering::Relaxed) - 1;
+        self.route.set_gauge(clients);
+    }
+}
+
+fn to_sse(event: &BusEvent) -> Event {
+    Event::default()
+        .id(event.id.to_string())
+        .event(event.kind.as_str())
+        .data(event.data.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use futures_util::StreamExt;
+    use openportio_test::RecordingMetrics;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn topic_streams_serialize_events_filter_by_principal_and_count_clients() {
+        let bus = EventBus::new();
+        let metrics = Arc::new(RecordingMetrics::default());
+        let topic = SseTopic::new("notes").with_filter(|principal, event| {
+            principal.is_some_and(|principal| event.data["owner"] == principal.subject.as_str())
+        });
+        let app = sse_topic_router("/events/notes", topic, bus.clone(), metrics.clone());
+
+        let mut req = Request::builder()
+            .uri("/events/notes")
+            .body(Body::empty())
+            .unwrap();
+        req.extensions_mut().insert(AuthPrincipal {
+            subject: "alice".to_string(),
+            issuer: None,
+            audience: Vec::new(),
+            scopes: Vec::new(),
+            token_id: None,
+            tenant_id: None,
+        });
+        let response = app.oneshot(req).await.unwrap();
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+        assert_eq!(bus.subscriber_count("notes"), 1);
+
+        bus.publish(
+            "notes",
+            "created",
+            &serde_json::json!({"owner": "bob", "id": 1}),
+        )
+        .unwrap();
+        bus.publish(
+            "notes",
+            "created",
+            &serde_json::json!({"owner": "alice", "id": 2}),
+        )
+        .unwrap();
+        let mut body = response.into_body().into_data_stream();
+        let frame = tokio::time::timeout(Duration::from_secs(1), body.next())
+            .await
+            .expect("event should arrive")
+            .unwrap()
+            .unwrap();
+        let frame = String::from_utf8(frame.to_vec()).unwrap();
+        assert!(frame.contains("event: created\n"), "{frame}");
+        assert!(
+            frame.contains(r#"data: {"id":2,"owner":"alice"}"#),
+            "{frame}"
+        );
+        assert!(frame.contains("id: 2\n"), "{frame}");
+
+        drop(body);
+        assert_eq!(
+            metrics.gauges(),
+            ["sse.clients{topic=notes}=1", "sse.clients{topic=notes}=0"]
+        );
+        assert_eq!(bus.subscriber_count("notes"), 0);
+    }
+
+    #[tokio::test]
+    async fn idle_streams_send_keep_alive_comments_on_the_clock_dependency() {
+        let clock = openportio_core::TestClock::new();
+        let bus = EventBus::new();
+        let app = di::with_dependency(
+            sse_topic_router(
+                "/events/notes",
+                SseTopic::new("notes").with_heartbeat(Duration::from_secs(30)),
+                bus.clone(),
+                Arc::new(RecordingMetrics::default()),
+            ),
+            clock.shared(),
+        );
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/events/notes")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let mut body = response.into_body().into_data_stream();
+        let mut next = Box::pin(body.next());
+        assert!(futures_util::poll!(&mut next).is_pending());
+
+        clock.advance(Duration::from_secs(29));
+        tokio::task::yield_now().await;
+        assert!(futures_util::poll!(&mut next).is_pending());
+
+        clock.advance(Duration::from_secs(1));
+        let frame = tokio::time::timeout(Duration::from_secs(1), next)
+            .await
+            .expect("keep-alive should arrive")
+            .unwrap()
+            .unwrap();
+        assert_eq!(&frame[..], b": \n\n");
+    }
+}
@@ -0,0 +1,601 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{FromRequestParts, RawPathParams, Request, State},
+    http::{header, request::Parts, HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use openportio_core::{auth::AuthPrincipal, flags::FeatureFlags};
+
+use crate::{api::ApiErrorResponse, context::RequestContext, di, flags::FlagClient};
+
+pub const DEFAULT_TENANT_HEADER: &str = "x-tenant-id";
+pub const DEFAULT_TENANT_PATH_PARAM: &str = "tenant_id";
+const WINDOW_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Extracts a tenant id from a request. Resolvers registered on [`Tenancy`] are tried in order.
+pub trait TenantResolver: Send + Sync + 'static {
+    fn resolve(&self, parts: &Parts) -> Option<String>;
+}
+
+/// Reads the `tenant_id` / `tid` claim of the authenticated principal.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ClaimTenantResolver;
+
+impl TenantResolver for ClaimTenantResolver {
+    fn resolve(&self, parts: &Parts) -> Option<String> {
+        parts
+            .extensions
+            .get::<AuthPrincipal>()
+            .and_then(|principal| principal.tenant_id.clone())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HeaderTenantResolver {
+    header: HeaderName,
+}
+
+impl HeaderTenantResolver {
+    pub fn new(header: HeaderName) -> Self {
+        Self { header }
+    }
+}
+
+impl Default for HeaderTenantResolver {
+    fn default() -> Self {
+        Self::new(HeaderName::from_static(DEFAULT_TENANT_HEADER))
+    }
+}
+
+impl TenantResolver for HeaderTenantResolver {
+    fn resolve(&self, parts: &Parts) -> Option<String> {
+        parts
+            .headers
+            .get(&self.header)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    }
+}
+
+/// Uses the left-most label of `Host` under `base_domain`: `acme.api.example.com` with base
+/// domain `api.example.com` resolves to `acme`.
+#[derive(Debug, Clone)]
+pub struct SubdomainTenantResolver {
+    base_domain: String,
+}
+
+impl SubdomainTenantResolver {
+    pub fn new(base_domain: impl Into<String>) -> Self {
+        Self {
+            base_domain: base_domain.into().trim_matches('.').to_ascii_lowercase(),
+        }
+    }
+}
+
+impl TenantResolver for SubdomainTenantResolver {
+    fn resolve(&self, parts: &Parts) -> Option<String> {
+        let host = parts
+            .headers
+            .get(header::HOST)
+            .and_then(|value| value.to_str().ok())?;
+        let host = host.split(':').next()?.to_ascii_lowercase();
+        let subdomain = host.strip_suffix(&self.base_domain)?.strip_suffix('.')?;
+        let tenant = subdomain.rsplit('.').next()?;
+        (!tenant.is_empty()).then(|| tenant.to_string())
+    }
+}
+
+/// Per-tenant overrides. Unset fields fall back to the app-wide behaviour.
+#[derive(Clone, Default)]
+pub struct TenantConfig {
+    rate_limit: Option<(u32, Duration)>,
+    flags: Option<FeatureFlags>,
+    settings: BTreeMap<String, String>,
+}
+
+impl TenantConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// At most `max_requests` per `window` (fixed window); excess requests get `429`.
+    pub fn with_rate_limit(mut self, max_requests: u32, window: Duration) -> Self {
+        self.rate_limit = Some((max_requests, window));
+        self
+    }
+
+    /// Flags evaluated for this tenant's requests instead of the app-wide `FlagClient`.
+    pub fn with_flags(mut self, flags: FeatureFlags) -> Self {
+        self.flags = Some(flags);
+        self
+    }
+
+    pub fn with_setting(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.settings.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn rate_limit(&self) -> Option<(u32, Duration)> {
+        self.rate_limit
+    }
+
+    pub fn setting(&self, key: &str) -> Option<&str> {
+        self.settings.get(key).map(String::as_str)
+    }
+}
+
+/// Tenant resolution and isolation policy, registered with `OpenportioServer::with_tenancy(...)`
+/// (or as a dependency) and enforced by the [`TenantContext`] extractor and
+/// [`tenancy_middleware`].
+#[derive(Clone)]
+pub struct Tenancy {
+    resolvers: Vec<Arc<dyn TenantResolver>>,
+    tenants: Arc<HashMap<String, TenantConfig>>,
+    default_config: TenantConfig,
+    allow_unknown: bool,
+    path_param: String,
+    windows: Arc<Mutex<RateWindows>>,
+}
+
+/// Fixed windows per tenant id. Ids can come from a header, so windows that have run out are
+/// swept (at most once a minute) instead of being kept for every id ever seen.
+#[derive(Debug, Default)]
+struct RateWindows {
+    /// Window start, requests counted, and window length per tenant.
+    entries: HashMap<String, (Instant, u32, Duration)>,
+    next_sweep: Option<Instant>,
+}
+
+impl Default for Tenancy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tenancy {
+    pub fn new() -> Self {
+        Self {
+            resolvers: Vec::new(),
+            tenants: Arc::new(HashMap::new()),
+            default_config: TenantConfig::default(),
+            allow_unknown: false,
+            path_param: DEFAULT_TENANT_PATH_PARAM.to_string(),
+            windows: Arc::new(Mutex::new(RateWindows::default())),
+        }
+    }
+
+    pub fn resolve_with(mut self, resolver: impl TenantResolver) -> Self {
+        self.resolvers.push(Arc::new(resolver));
+        self
+    }
+
+    pub fn with_tenant(mut self, tenant_id: impl Into<String>, config: TenantConfig) -> Self {
+        Arc::make_mut(&mut self.tenants).insert(tenant_id.into(), config);
+        self
+    }
+
+    /// Config for tenants without their own entry; only reachable with
+    /// [`Self::allow_unknown_tenants`].
+    pub fn with_default_config(mut self, config: TenantConfig) -> Self {
+        self.default_config = config;
+        self
+    }
+
+    /// Accepts tenants that were not registered with [`Self::with_tenant`].
+    pub fn allow_unknown_tenants(mut self) -> Self {
+        self.allow_unknown = true;
+        self
+    }
+
+    /// Path parameter compared against the resolved tenant (default `tenant_id`).
+    pub fn with_path_param(mut self, name: impl Into<String>) -> Self {
+        self.path_param = name.into();
+        self
+    }
+
+    /// Resolves the tenant for `parts` and applies the isolation checks: known tenant, principal
+    /// claim agreeing with the resolved tenant, and rate limit. Path parameters are only checked
+    /// by the extractor and middleware, which have access to the matched route.
+    pub fn resolve(&self, parts: &Parts) -> Result<TenantContext, TenantRejection> {
+        self.resolve_for_path(parts, None)
+    }
+
+    async fn resolve_request(&self, parts: &mut Parts) -> Result<TenantContext, TenantRejection> {
+        let path_tenant = RawPathParams::from_request_parts(parts, &())
+            .await
+            .ok()
+            .and_then(|params| {
+                params
+                    .iter()
+                    .find(|(key, _)| *key == self.path_param)
+                    .map(|(_, value)| value.to_string())
+            });
+        self.resolve_for_path(parts, path_tenant.as_deref())
+    }
+
+    fn resolve_for_path(
+        &self,
+        parts: &Parts,
+        path_tenant: Option<&str>,
+    ) -> Result<TenantContext, TenantRejection> {
+        let tenant_id = self
+            .resolvers
+            .iter()
+            .find_map(|resolver| resolver.resolve(parts))
+            .ok_or(TenantRejection::Unresolved)?;
+
+        let config = match self.tenants.get(&tenant_id) {
+            Some(config) => config.clone(),
+            None if self.allow_unknown => self.default_config.clone(),
+            None => return Err(TenantRejection::UnknownTenant(tenant_id)),
+        };
+
+        if let Some(claimed) = parts
+            .extensions
+            .get::<AuthPrincipal>()
+            .and_then(|principal| principal.tenant_id.as_deref())
+        {
+            if claimed != tenant_id {
+                return Err(TenantRejection::Mismatch(tenant_id));
+            }
+        }
+
+        if path_tenant.is_some_and(|value| value != tenant_id) {
+            return Err(TenantRejection::CrossTenant(tenant_id));
+        }
+
+        if let Some((max_requests, window)) = config.rate_limit {
+            self.consume(&tenant_id, max_requests, window, Instant::now())?;
+        }
+
+        if let Some(context) = parts.extensions.get::<RequestContext>() {
+            context.set_tenant_id(&tenant_id);
+        }
+        Ok(TenantContext {
+            tenant_id,
+            config: Arc::new(config),
+        })
+    }
+
+    fn consume(
+        &self,
+        tenant_id: &str,
+        max_requests: u32,
+        window: Duration,
+        now: Instant,
+    ) -> Result<(), TenantRejection> {
+        let mut windows = self
+            .windows
+            .lock()
+            .map_err(|_| TenantRejection::Misconfigured("tenant rate limit lock poisoned"))?;
+        if windows
+            .next_sweep
+            .is_none_or(|next_sweep| now >= next_sweep)
+        {
+            windows
+                .entries
+                .retain(|_, (started, _, window)| now.duration_since(*started) < *window);
+            windows.next_sweep = Some(now + WINDOW_SWEEP_INTERVAL);
+        }
+        let (started, count, tracked_window) = windows
+            .entries
+            .entry(tenant_id.to_string())
+            .or_insert((now, 0, window));
+        *tracked_window = window;
+        if now.duration_since(*started) >= window {
+            *started = now;
+            *count = 0;
+        }
+        if *count >= max_requests {
+            let retry_after = window.saturating_sub(now.duration_since(*started));
+            return Err(TenantRejection::RateLimited {
+                tenant_id: tenant_id.to_string(),
+                retry_after_seconds: retry_after.as_secs().max(1),
+            });
+        }
+        *count += 1;
+        Ok(())
+    }
+}
+
+/// The tenant of the current request. Extract it directly in handlers; the first extraction is
+/// cached on the request so the rate limit is only charged once.
+#[derive(Clone)]
+pub struct TenantContext {
+    tenant_id: String,
+    config: Arc<TenantConfig>,
+}
+
+impl TenantContext {
+    pub fn tenant_id(&self) -> &str {
+        &self.tenant_id
+    }
+
+    pub fn config(&self) -> &TenantConfig {
+        &self.config
+    }
+
+    /// The tenant's flags when configured, otherwise `fallback`.
+    pub fn flags(&self, fallback: FlagClient) -> FlagClient {
+        match &self.config.flags {
+            Some(flags) => FlagClient::new(flags.clone()),
+            None => fallback,
+        }
+    }
+
+    pub(crate) fn flag_override(&self) -> Option<FlagClient> {
+        self.config.flags.clone().map(FlagClient::new)
+    }
+}
+
+impl std::fmt::Debug for TenantContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TenantContext")
+            .field("tenant_id", &self.tenant_id)
+            .finish_non_exhaustive()
+    }
+}
+
+#[axum::async_trait]
+impl<S> FromRequestParts<S> for TenantContext
+where
+    S: Send + Sync,
+{
+    type Rejection = TenantRejection;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(context) = parts.extensions.get::<TenantContext>() {
+            return Ok(context.clone());
+        }
+        let tenancy = di::resolve_from_extensions::<Tenancy>(&parts.extensions)
+            .ok_or(TenantRejection::Misconfigured("tenancy is not configured"))?;
+        let context = tenancy.resolve_request(parts).await?;
+        parts.extensions.insert(context.clone());
+        Ok(context)
+    }
+}
+
+/// Enforces tenancy on routes that never extract [`TenantContext`], e.g.
+/// `route_layer(from_fn_with_state(tenancy, tenancy_middleware))`.
+pub async fn tenancy_middleware(
+    State(tenancy): State<Tenancy>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let (mut parts, body) = req.into_parts();
+    match tenancy.resolve_request(&mut parts).await {
+        Ok(context) => {
+            parts.extensions.insert(context);
+            next.run(Request::from_parts(parts, body)).await
+        }
+        Err(rejection) => rejection.into_response(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TenantRejection {
+    Unresolved,
+    UnknownTenant(String),
+    Mismatch(String),
+    CrossTenant(String),
+    RateLimited {
+        tenant_id: String,
+        retry_after_seconds: u64,
+    },
+    Misconfigured(&'static str),
+}
+
+impl IntoResponse for TenantRejection {
+    fn into_response(self) -> Response {
+        let (status, code, message, tenant_id) = match self {
+            Self::Unresolved => (
+                StatusCode::BAD_REQUEST,
+                "tenant_required",
+                "tenant could not be resolved".to_string(),
+                None,
+            ),
+            Self::UnknownTenant(tenant_id) => (
+                StatusCode::FORBIDDEN,
+                "unknown_tenant",
+                "tenant is not registered".to_string(),
+                Some(tenant_id),
+            ),
+            Self::Mismatch(tenant_id) => (
+                StatusCode::FORBIDDEN,
+                "tenant_mismatch",
+                "token tenant does not match the requested tenant".to_string(),
+                Some(tenant_id),
+            ),
+            Self::CrossTenant(tenant_id) => (
+                StatusCode::FORBIDDEN,
+                "cross_tenant_access",
+                "path refers to another tenant".to_string(),
+                Some(tenant_id),
+            ),
+            Self::RateLimited {
+                tenant_id,
+                retry_after_seconds,
+            } => {
+                let mut response = (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    Json(ApiErrorResponse {
+                        code: "tenant_rate_limited".to_string(),
+                        message: "tenant rate limit exceeded".to_string(),
+                        detail: None,
+                        details: Some(serde_json::json!({ "tenant_id": tenant_id })),
+                    }),
+                )
+                    .into_response();
+                if let Ok(value) = HeaderValue::from_str(&retry_after_seconds.to_string()) {
+                    response.headers_mut().insert(header::RETRY_AFTER, value);
+                }
+                return response;
+            }
+            Self::Misconfigured(message) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "internal_error",
+                message.to_string(),
+                None,
+            ),
+        };
+        (
+            status,
+            Json(ApiErrorResponse {
+                code: code.to_string(),
+                message,
+                detail: None,
+                details: tenant_id.map(|tenant_id| serde_json::json!({ "tenant_id": tenant_id })),
+            }),
+        )
+            .into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request as HttpRequest, routing::get, Router};
+    use tower::util::ServiceExt;
+
+    fn parts(host: &str, tenant_header: Option<&str>) -> Parts {
+        let mut builder = HttpRequest::builder().uri("/").header(header::HOST, host);
+        if let Some(tenant) = tenant_header {
+            builder = builder.header(DEFAULT_TENANT_HEADER, tenant);
+        }
+        builder.body(()).unwrap().into_parts().0
+    }
+
+    #[test]
+    fn resolvers_read_header_subdomain_and_claim() {
+        assert_eq!(
+            HeaderTenantResolver::default().resolve(&parts("api.example.com", Some("acme"))),
+            Some("acme".to_string())
+        );
+        let subdomain = SubdomainTenantResolver::new("api.example.com");
+        assert_eq!(
+            subdomain.resolve(&parts("Globex.api.example.com:8443", None)),
+            Some("globex".to_string())
+        );
+        assert_eq!(subdomain.resolve(&parts("api.example.com", None)), None);
+
+        let mut with_principal = parts("api.example.com", None);
+        with_principal.extensions.insert(AuthPrincipal {
+            subject: "user-1".to_string(),
+            issuer: None,
+            audience: vec![],
+            scopes: vec![],
+            token_id: None,
+            tenant_id: Some("initech".to_string()),
+        });
+        assert_eq!(
+            ClaimTenantResolver.resolve(&with_principal),
+            Some("initech".to_string())
+        );
+    }
+
+    #[test]
+    fn expired_rate_limit_windows_are_swept() {
+        let tenancy = Tenancy::new();
+        let window = Duration::from_secs(10);
+        let start = Instant::now();
+        for tenant in ["t-1", "t-2", "t-3"] {
+            tenancy.consume(tenant, 5, window, start).unwrap();
+        }
+        assert_eq!(tenancy.windows.lock().unwrap().entries.len(), 3);
+
+        // Within the sweep interval nothing is dropped, even though the windows ran out.
+        tenancy
+            .consume("t-4", 5, window, start + Duration::from_secs(30))
+            .unwrap();
+        assert_eq!(tenancy.windows.lock().unwrap().entries.len(), 4);
+
+        tenancy
+            .consume("t-5", 5, window, start + WINDOW_SWEEP_INTERVAL)
+            .unwrap();
+        let windows = tenancy.windows.lock().unwrap();
+        let mut tracked: Vec<_> = windows.entries.keys().cloned().collect();
+        tracked.sort();
+        assert_eq!(tracked, vec!["t-5".to_string()]);
+    }
+
+    #[test]
+    fn rate_limit_is_tracked_per_tenant() {
+        let tenancy = Tenancy::new()
+            .resolve_with(HeaderTenantResolver::default())
+            .with_tenant(
+                "acme",
+                TenantConfig::new().with_rate_limit(1, Duration::from_secs(60)),
+            )
+            .with_tenant("globex", TenantConfig::new());
+
+        assert!(tenancy.resolve(&parts("h", Some("acme"))).is_ok());
+        assert!(matches!(
+            tenancy.resolve(&parts("h", Some("acme"))),
+            Err(TenantRejection::RateLimited { .. })
+        ));
+        assert!(tenancy.resolve(&parts("h", Some("globex"))).is_ok());
+        assert_eq!(
+            tenancy.resolve(&parts("h", Some("umbrella"))).err(),
+            Some(TenantRejection::UnknownTenant("umbrella".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn extractor_rejects_cross_tenant_path_params() {
+        let tenancy = Tenancy::new()
+            .resolve_with(HeaderTenantResolver::default())
+            .with_tenant(
+                "acme",
+                TenantConfig::new().with_setting("plan", "enterprise"),
+            )
+            .with_tenant("globex", TenantConfig::new());
+        let app = di::with_dependency(
+            Router::new().route(
+                "/tenants/:tenant_id/orders",
+                get(|tenant: TenantContext| async move {
+                    format!(
+                        "{}:{}",
+                        tenant.tenant_id(),
+                        tenant.config().setting("plan").unwrap_or("-")
+                    )
+                }),
+            ),
+            tenancy,
+        );
+        let call = |uri: &'static str| {
+            app.clone().oneshot(
+                HttpRequest::builder()
+                    .uri(uri)
+                    .header(DEFAULT_TENANT_HEADER, "acme")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        let response = call("/tenants/acme/orders").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, "acme:enterprise");
+
+        let response = call("/tenants/globex/orders").await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body: serde_json::Value = serde_json::from_slice(
+            &axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(body["code"], "cross_tenant_access");
+    }
+}
@@ -0,0 +1,268 @@
+use std::{
+    borrow::Cow,
+    convert::Infallible,
+    fmt::Write as _,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{FromRequestParts, Request},
+    http::{request::Parts, HeaderValue},
+    middleware::{from_fn, Next},
+    response::Response,
+    Router,
+};
+use http_body::{Frame, SizeHint};
+
+pub const RESPONSE_TIME_HEADER: &str = "x-response-time-ms";
+pub const SERVER_TIMING_HEADER: &str = "server-timing";
+
+type Phase = (Cow<'static, str>, Duration);
+
+/// Phase durations of the current request, reported in the `Server-Timing` header when debug
+/// timings are on. The layers record `middleware` (time before the route was reached), `body`
+/// (reading the request body, when something read it) and `handler` (extractors, handler and
+/// response conversion); handlers add their own phases:
+///
+/// ```ignore
+/// async fn list(timings: RequestTimings, Depends(repo): Depends<Repo>) -> Response {
+///     let notes = timings.time("db", repo.list()).await;
+///     timings.measure("serialize", || Json(notes).into_response())
+/// }
+/// ```
+///
+/// Outside debug mode the extractor hands out a detached recorder, so the calls cost next to
+/// nothing and report nowhere.
+#[derive(Clone, Default)]
+pub struct RequestTimings {
+    phases: Arc<Mutex<Vec<Phase>>>,
+}
+
+impl RequestTimings {
+    pub fn from_extensions(extensions: &http::Extensions) -> Self {
+        extensions.get::<Self>().cloned().unwrap_or_default()
+    }
+
+    pub fn record(&self, phase: impl Into<Cow<'static, str>>, duration: Duration) {
+        self.lock().push((phase.into(), duration));
+    }
+
+    pub fn measure<T>(&self, phase: impl Into<Cow<'static, str>>, f: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let value = f();
+        self.record(phase, started.elapsed());
+        value
+    }
+
+    pub async fn time<F: Future>(
+        &self,
+        phase: impl Into<Cow<'static, str>>,
+        future: F,
+    ) -> F::Output {
+        let started = Instant::now();
+        let value = future.await;
+        self.record(phase, started.elapsed());
+        value
+    }
+
+    pub fn phases(&self) -> Vec<(Cow<'static, str>, Duration)> {
+        self.lock().clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Phase>> {
+        self.phases.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+#[axum::async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for RequestTimings {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_extensions(&parts.extensions))
+    }
+}
+
+#[derive(Clone, Copy)]
+struct RequestStarted(Instant);
+
+/// Adds `X-Response-Time-Ms` and `Server-Timing` to every response. `OpenportioServer` applies
+/// it for `with_debug_timings()` / `OPENPORTIO_DEBUG_TIMINGS=true` outside production; don't
+/// apply it to routers exposed in production, since phase timings help attackers as much as
+/// they help you.
+pub fn with_debug_timings(router: Router) -> Router {
+    with_response_timings(with_route_timings(router))
+}
+
+/// The inner half of [`with_debug_timings`], applied before any other layer.
+pub(crate) fn with_route_timings(router: Router) -> Router {
+    // `route_layer` panics on a router without routes.
+    if router.has_routes() {
+        router.route_layer(from_fn(route_timing_middleware))
+    } else {
+        router
+    }
+}
+
+/// The outer half of [`with_debug_timings`], applied after every other layer.
+pub(crate) fn with_response_timings(router: Router) -> Router {
+    router.layer(from_fn(debug_timing_middleware))
+}
+
+async fn debug_timing_middleware(mut req: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let timings = RequestTimings::default();
+    req.extensions_mut().insert(timings.clone());
+    req.extensions_mut().insert(RequestStarted(started));
+    let mut response = next.run(req).await;
+    let total = started.elapsed();
+
+    let mut server_timing = String::new();
+    for (phase, duration) in timings
+        .phases()
+        .iter()
+        .map(|(phase, duration)| (phase.as_ref(), *duration))
+        .chain([("total", total)])
+    {
+        let separator = if server_timing.is_empty() { "" } else { ", " };
+        let _ = write!(
+            server_timing,
+            "{separator}{};dur={:.2}",
+            metric_name(phase),
+            millis(duration)
+        );
+    }
+    let headers = response.headers_mut();
+    if let Ok(value) = HeaderValue::from_str(&format!("{:.2}", millis(total))) {
+        headers.insert(RESPONSE_TIME_HEADER, value);
+    }
+    if let Ok(value) = HeaderValue::from_str(&server_timing) {
+        headers.append(SERVER_TIMING_HEADER, value);
+    }
+    response
+}
+
+async fn route_timing_middleware(req: Request, next: Next) -> Response {
+    let reached = Instant::now();
+    let timings = RequestTimings::from_extensions(req.extensions());
+    if let Some(RequestStarted(started)) = req.extensions().get::<RequestStarted>() {
+        timings.record("middleware", reached.duration_since(*started));
+    }
+    let req = req.map(|body| {
+        Body::new(TimedBody {
+            inner: body,
+            timings: timings.clone(),
+            first_poll: None,
+        })
+    });
+    let response = next.run(req).await;
+    timings.record("handler", reached.elapsed());
+    response
+}
+
+/// Records `body` from the first poll to the end of the stream.
+struct TimedBody {
+    inner: Body,
+    timings: RequestTimings,
+    first_poll: Option<Instant>,
+}
+
+impl http_body::Body for TimedBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let first_poll = *self.first_poll.get_or_insert_with(Instant::now);
+        let poll = Pin::new(&mut self.inner).poll_frame(cx);
+        if let Poll::Ready(None) = poll {
+            self.timings.record("body", first_poll.elapsed());
+        }
+        poll
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// `Server-Timing` metric names are tokens; anything else becomes `_`.
+fn metric_name(phase: &str) -> Cow<'_, str> {
+    let is_token = |c: char| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c);
+    if !phase.is_empty() && phase.chars().all(is_token) {
+        Cow::Borrowed(phase)
+    } else {
+        Cow::Owned(
+            phase
+                .chars()
+                .map(|c| if is_token(c) { c } else { '_' })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::to_bytes, routing::post};
+    use tower::util::ServiceExt;
+
+    #[tokio::test]
+    async fn responses_carry_total_and_phase_timings() {
+        let app = with_debug_timings(Router::new().route(
+            "/echo",
+            post(|timings: RequestTimings, body: String| async move {
+                timings.measure("serialize", || body.to_uppercase())
+            }),
+        ));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/echo")
+                    .body(Body::from("hi"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let total: f64 = response.headers()[RESPONSE_TIME_HEADER]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(total >= 0.0);
+        let server_timing = response.headers()[SERVER_TIMING_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let names: Vec<_> = server_timing
+            .split(", ")
+            .map(|entry| entry.split_once(";dur=").unwrap().0)
+            .collect();
+        assert_eq!(
+            names,
+            ["middleware", "body", "serialize", "handler", "total"]
+        );
+        assert_eq!(
+            &to_bytes(response.into_body(), usize::MAX).await.unwrap()[..],
+            b"HI"
+        );
+        assert_eq!(metric_name("db query"), "db_query");
+    }
+}
@@ -0,0 +1,438 @@
+use std::{fmt, fs, io, path::Path, str::FromStr, sync::Arc};
+
+use axum::http::Extensions;
+use openportio_core::auth::AuthPrincipal;
+use rustls::{
+    crypto::ring,
+    pki_types::{CertificateDer, PrivateKeyDer},
+    server::WebPkiClientVerifier,
+    RootCertStore, ServerConfig,
+};
+use x509_parser::prelude::{FromDer, GeneralName, X509Certificate};
+
+const SPIFFE_SCHEME: &str = "spiffe://";
+
+/// Whether the gRPC listener asks for client certificates.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ClientAuth {
+    /// Plain server-side TLS.
+    #[default]
+    None,
+    /// Verify a client certificate when one is presented, but accept anonymous clients.
+    Optional,
+    /// Reject the handshake unless the client presents a certificate signed by the client CA.
+    Required,
+}
+
+impl ClientAuth {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Optional => "optional",
+            Self::Required => "required",
+        }
+    }
+}
+
+impl FromStr for ClientAuth {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "none" | "off" => Ok(Self::None),
+            "optional" => Ok(Self::Optional),
+            "required" | "require" => Ok(Self::Required),
+            other => Err(format!(
+                "unknown client auth mode `{other}`; expected none, optional or required"
+            )),
+        }
+    }
+}
+
+/// Server certificate and optional client-certificate verification for the dedicated gRPC
+/// listener (`with_rest_addr` + `with_grpc_addr`).
+pub struct GrpcTlsConfig {
+    cert_chain: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+    client_ca: Vec<CertificateDer<'static>>,
+    client_auth: ClientAuth,
+}
+
+impl GrpcTlsConfig {
+    pub fn from_pem(cert_chain_pem: &[u8], key_pem: &[u8]) -> io::Result<Self> {
+        let cert_chain = parse_certs(cert_chain_pem)?;
+        if cert_chain.is_empty() {
+            return Err(invalid_input(
+                "gRPC TLS certificate PEM contains no certificates",
+            ));
+        }
+        let key = rustls_pemfile::private_key(&mut &*key_pem)?
+            .ok_or_else(|| invalid_input("gRPC TLS key PEM contains no private key"))?;
+        Ok(Self {
+            cert_chain,
+            key,
+            client_ca: Vec::new(),
+            client_auth: ClientAuth::None,
+        })
+    }
+
+    pub fn from_pem_files(
+        cert_path: impl AsRef<Path>,
+        key_path: impl AsRef<Path>,
+    ) -> io::Result<Self> {
+        Self::from_pem(&fs::read(cert_path)?, &fs::read(key_path)?)
+    }
+
+    /// Trust anchors for client certificates. Switches client auth to `Required` unless
+    /// `with_client_auth` picks otherwise.
+    pub fn with_client_ca_pem(mut self, ca_pem: &[u8]) -> io::Result<Self> {
+        self.client_ca = parse_certs(ca_pem)?;
+        if self.client_ca.is_empty() {
+            return Err(invalid_input(
+                "gRPC TLS client CA PEM contains no certificates",
+            ));
+        }
+        if self.client_auth == ClientAuth::None {
+            self.client_auth = ClientAuth::Required;
+        }
+        Ok(self)
+    }
+
+    pub fn with_client_auth(mut self, client_auth: ClientAuth) -> Self {
+        self.client_auth = client_auth;
+        self
+    }
+
+    pub fn client_auth(&self) -> ClientAuth {
+        self.client_auth
+    }
+
+    /// `tls` or `mtls (optional|required)`, as shown in diagnostics.
+    pub fn mode(&self) -> String {
+        match self.client_auth {
+            ClientAuth::None => "tls".to_string(),
+            mode => format!("mtls ({})", mode.name()),
+        }
+    }
+
+    /// Reads `OPENPORTIO_GRPC_TLS_CERT_FILE` / `OPENPORTIO_GRPC_TLS_KEY_FILE` and the optional
+    /// `OPENPORTIO_GRPC_TLS_CLIENT_CA_FILE` / `OPENPORTIO_GRPC_TLS_CLIENT_AUTH`. `Ok(None)` when no
+    /// certificate is configured.
+    pub fn from_env() -> io::Result<Option<Self>> {
+        let cert = crate::env::var("GRPC_TLS_CERT_FILE").map_err(invalid_input)?;
+        let key = crate::env::var("GRPC_TLS_KEY_FILE").map_err(invalid_input)?;
+        let (cert, key) = match (cert, key) {
+            (None, None) => return Ok(None),
+            (Some(cert), Some(key)) => (cert, key),
+            _ => {
+                return Err(invalid_input(
+                    "gRPC TLS needs both OPENPORTIO_GRPC_TLS_CERT_FILE and OPENPORTIO_GRPC_TLS_KEY_FILE",
+                ))
+            }
+        };
+        let config = Self::from_pem_files(cert, key)?;
+        let config = match crate::env::var("GRPC_TLS_CLIENT_CA_FILE").map_err(invalid_input)? {
+            Some(ca) => config.with_client_ca_pem(&fs::read(ca)?)?,
+            None => config,
+        };
+        match crate::env::var("GRPC_TLS_CLIENT_AUTH").map_err(invalid_input)? {
+            Some(raw) => Ok(Some(
+                config.with_client_auth(raw.parse().map_err(invalid_input)?),
+            )),
+            None => Ok(Some(config)),
+        }
+    }
+
+    pub(crate) fn server_config(&self) -> io::Result<Arc<ServerConfig>> {
+        let provider = Arc::new(ring::default_provider());
+        let builder = ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(invalid_input)?;
+        let builder = match self.client_auth {
+            ClientAuth::None => builder.with_no_client_auth(),
+            client_auth => {
+                if self.client_ca.is_empty() {
+                    return Err(invalid_input(
+                        "gRPC mTLS requires a client CA; call with_client_ca_pem(...)",
+                    ));
+                }
+                let mut roots = RootCertStore::empty();
+                for ca in &self.client_ca {
+                    roots.add(ca.clone()).map_err(invalid_input)?;
+                }
+                let verifier =
+                    WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider);
+                let verifier = match client_auth {
+                    ClientAuth::Optional => verifier.allow_unauthenticated(),
+                    _ => verifier,
+                };
+                builder.with_client_cert_verifier(verifier.build().map_err(invalid_input)?)
+            }
+        };
+        let mut config = builder
+            .with_single_cert(self.cert_chain.clone(), self.key.clone_key())
+            .map_err(invalid_input)?;
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(Arc::new(config))
+    }
+}
+
+impl Clone for GrpcTlsConfig {
+    fn clone(&self) -> Self {
+        Self {
+            cert_chain: self.cert_chain.clone(),
+            key: self.key.clone_key(),
+            client_ca: self.client_ca.clone(),
+            client_auth: self.client_auth,
+        }
+    }
+}
+
+impl fmt::Debug for GrpcTlsConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GrpcTlsConfig")
+            .field("cert_chain_len", &self.cert_chain.len())
+            .field("client_ca_len", &self.client_ca.len())
+            .field("client_auth", &self.client_auth)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Subject alternative names of a verified client certificate, attached to request extensions
+/// on the mTLS gRPC listener.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PeerIdentity {
+    pub uri_sans: Vec<String>,
+    pub dns_sans: Vec<String>,
+}
+
+impl PeerIdentity {
+    pub fn from_der(cert: &[u8]) -> Option<Self> {
+        let (_, cert) = X509Certificate::from_der(cert).ok()?;
+        let mut identity = Self::default();
+        if let Ok(Some(san)) = cert.subject_alternative_name() {
+            for name in &san.value.general_names {
+                match name {
+                    GeneralName::URI(uri) => identity.uri_sans.push(uri.to_string()),
+                    GeneralName::DNSName(dns) => identity.dns_sans.push(dns.to_string()),
+                    _ => {}
+                }
+            }
+        }
+        Some(identity)
+    }
+
+    /// First `spiffe://trust-domain/path` URI SAN.
+    pub fn spiffe_id(&self) -> Option<&str> {
+        self.uri_sans
+            .iter()
+            .map(String::as_str)
+            .find(|uri| uri.starts_with(SPIFFE_SCHEME))
+    }
+
+    pub fn trust_domain(&self) -> Option<&str> {
+        let rest = self.spiffe_id()?.strip_prefix(SPIFFE_SCHEME)?;
+        rest.split('/').next().filter(|domain| !domain.is_empty())
+    }
+
+    /// Workload principal: the SPIFFE ID as subject, `spiffe://<trust domain>` as issuer.
+    pub fn principal(&self) -> Option<AuthPrincipal> {
+        let trust_domain = self.trust_domain()?;
+        Some(AuthPrincipal {
+            subject: self.spiffe_id()?.to_string(),
+            issuer: Some(format!("{SPIFFE_SCHEME}{trust_domain}")),
+            audience: vec![],
+            scopes: vec![],
+            token_id: None,
+            tenant_id: None,
+        })
+    }
+
+    pub(crate) fn principal_from_extensions(extensions: &Extensions) -> Option<AuthPrincipal> {
+        extensions.get::<Self>().and_then(Self::principal)
+    }
+}
+
+fn parse_certs(pem: &[u8]) -> io::Result<Vec<CertificateDer<'static>>> {
+    rustls_pemfile::certs(&mut &*pem).collect()
+}
+
+fn invalid_input(err: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::{self, Http2Config, SocketConfig};
+    use axum::{routing::get, Extension, Router};
+    use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair, SanType};
+    use tokio::{net::TcpListener, sync::oneshot};
+    use tower::util::ServiceExt;
+
+    const WORKLOAD: &str = "spiffe://example.org/ns/payments/sa/billing";
+
+    struct Pki {
+        ca_pem: String,
+        server: (String, String),
+        client: (String, String),
+    }
+
+    fn pki() -> Pki {
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+
+        let server_key = KeyPair::generate().unwrap();
+        let server = CertificateParams::new(vec!["localhost".to_string()])
+            .unwrap()
+            .signed_by(&server_key, &ca, &ca_key)
+            .unwrap();
+
+        let client_key = KeyPair::generate().unwrap();
+        let mut client_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        client_params.subject_alt_names =
+            vec![SanType::URI(WORKLOAD.to_string().try_into().unwrap())];
+        let client = client_params.signed_by(&client_key, &ca, &ca_key).unwrap();
+
+        Pki {
+            ca_pem: ca.pem(),
+            server: (server.pem(), server_key.serialize_pem()),
+            client: (client.pem(), client_key.serialize_pem()),
+        }
+    }
+
+    fn client(pki: &Pki, with_cert: bool) -> reqwest::Client {
+        let builder = reqwest::Client::builder()
+            .use_rustls_tls()
+            .add_root_certificate(reqwest::Certificate::from_pem(pki.ca_pem.as_bytes()).unwrap());
+        let builder = match with_cert {
+            true => builder.identity(
+                reqwest::Identity::from_pem(format!("{}{}", pki.client.0, pki.client.1).as_bytes())
+                    .unwrap(),
+            ),
+            false => builder,
+        };
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn client_auth_modes_parse_and_mtls_needs_a_ca() {
+        assert_eq!("Required".parse::<ClientAuth>(), Ok(ClientAuth::Required));
+        assert!("sometimes".parse::<ClientAuth>().is_err());
+
+        let pki = pki();
+        let config =
+            GrpcTlsConfig::from_pem(pki.server.0.as_bytes(), pki.server.1.as_bytes()).unwrap();
+        assert_eq!(config.mode(), "tls");
+        assert!(config
+            .clone()
+            .with_client_auth(ClientAuth::Required)
+            .server_config()
+            .is_err());
+        let mtls = config.with_client_ca_pem(pki.ca_pem.as_bytes()).unwrap();
+        assert_eq!(mtls.mode(), "mtls (required)");
+        assert!(mtls.server_config().is_ok());
+    }
+
+    #[tokio::test]
+    async fn mtls_listener_exposes_spiffe_identity_and_rejects_anonymous_clients() {
+        let pki = pki();
+        let tls = GrpcTlsConfig::from_pem(pki.server.0.as_bytes(), pki.server.1.as_bytes())
+            .unwrap()
+            .with_client_ca_pem(pki.ca_pem.as_bytes())
+            .unwrap();
+        let app = Router::new().route(
+            "/whoami",
+            get(|Extension(peer): Extension<PeerIdentity>| async move {
+                peer.principal()
+                    .map(|principal| principal.subject)
+                    .unwrap_or_default()
+            }),
+        );
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let server_config = tls.server_config().unwrap();
+        let server = tokio::spawn(async move {
+            transport::serve_tls(
+                listener,
+                app,
+                &Http2Config::default(),
+                &SocketConfig::default(),
+                server_config,
+                async {
+                    let _ = shutdown_rx.await;
+                },
+            )
+            .await
+        });
+        let url = format!("https://localhost:{port}/whoami");
+
+        let response = client(&pki, true).get(&url).send().await.unwrap();
+        assert_eq!(response.text().await.unwrap(), WORKLOAD);
+
+        assert!(client(&pki, false).get(&url).send().await.is_err());
+
+        let _ = shutdown_tx.send(());
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn workload_identity_replaces_bearer_tokens_when_auth_is_required() {
+        let mut cfg = crate::auth::AuthRuntimeConfig::default();
+        cfg.enabled = true;
+        cfg.jwt_secret = Some("secret".to_string());
+        let app =
+            Router::new()
+                .route(
+                    "/whoami",
+                    get(
+                        |Extension(principal): Extension<AuthPrincipal>| async move {
+                            principal.subject
+                        },
+                    ),
+                )
+                .layer(axum::middleware::from_fn_with_state(
+                    cfg,
+                    crate::auth::required_auth_middleware,
+                ));
+        let request = |peer: Option<PeerIdentity>| {
+            let mut request = axum::http::Request::builder()
+                .uri("/whoami")
+                .body(axum::body::Body::empty())
+                .unwrap();
+            if let Some(peer) = peer {
+                request.extensions_mut().insert(peer);
+            }
+            request
+        };
+        let workload = PeerIdentity {
+            uri_sans: vec![WORKLOAD.to_string()],
+            dns_sans: vec![],
+        };
+
+        let response = app.clone().oneshot(request(Some(workload))).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, WORKLOAD.as_bytes());
+
+        let response = app.oneshot(request(None)).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn peer_principal_uses_spiffe_id_and_trust_domain() {
+        let identity = PeerIdentity {
+            uri_sans: vec!["https://example.org".to_string(), WORKLOAD.to_string()],
+            dns_sans: vec![],
+        };
+        let principal = identity.principal().expect("spiffe principal");
+        assert_eq!(principal.subject, WORKLOAD);
+        assert_eq!(principal.issuer.as_deref(), Some("spiffe://example.org"));
+        assert!(PeerIdentity::default().principal().is_none());
+    }
+}
@@ -0,0 +1,264 @@
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use axum::{extract::Request, http::HeaderMap, middleware::Next, response::Response};
+use tonic::metadata::{AsciiMetadataValue, MetadataMap};
+
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+pub const TRACESTATE_HEADER: &str = "tracestate";
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+tokio::task_local! {
+    static CURRENT: TraceContext;
+}
+
+/// W3C trace context (`traceparent` / `tracestate`) plus the `x-request-id` of the request being
+/// served. The shared middleware scopes one to every inbound request so outbound clients using
+/// [`TraceContextInterceptor`] continue the same trace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    trace_id: u128,
+    span_id: u64,
+    flags: u8,
+    tracestate: Option<String>,
+    request_id: Option<String>,
+    received_at: Option<Instant>,
+}
+
+impl TraceContext {
+    /// Starts a new sampled trace.
+    pub fn new_root() -> Self {
+        Self {
+            trace_id: uuid::Uuid::new_v4().as_u128(),
+            span_id: new_span_id(),
+            flags: 0x01,
+            tracestate: None,
+            request_id: None,
+            received_at: None,
+        }
+    }
+
+    /// Parses a version-`00` `traceparent`; all-zero ids and malformed values yield `None`.
+    pub fn parse(traceparent: &str, tracestate: Option<&str>) -> Option<Self> {
+        let mut parts = traceparent.trim().split('-');
+        let (version, trace_id, span_id, flags) =
+            (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+        if version != "00" || parts.next().is_some() {
+            return None;
+        }
+        if trace_id.len() != 32 || span_id.len() != 16 || flags.len() != 2 {
+            return None;
+        }
+        let trace_id = u128::from_str_radix(trace_id, 16).ok()?;
+        let span_id = u64::from_str_radix(span_id, 16).ok()?;
+        if trace_id == 0 || span_id == 0 {
+            return None;
+        }
+        Some(Self {
+            trace_id,
+            span_id,
+            flags: u8::from_str_radix(flags, 16).ok()?,
+            tracestate: tracestate
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string),
+            request_id: None,
+            received_at: None,
+        })
+    }
+
+    /// Reads `traceparent`, `tracestate` and `x-request-id`, starting a new trace when the
+    /// caller did not send a valid `traceparent`. The request counts as received now.
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        let mut context = header(TRACEPARENT_HEADER)
+            .and_then(|traceparent| Self::parse(traceparent, header(TRACESTATE_HEADER)))
+            .unwrap_or_else(Self::new_root);
+        context.received_at = Some(Instant::now());
+        match header(REQUEST_ID_HEADER) {
+            Some(request_id) => context.with_request_id(request_id),
+            None => context,
+        }
+    }
+
+    pub fn with_request_id(mut self, request_id: impl Into<String>) -> Self {
+        self.request_id = Some(request_id.into());
+        self
+    }
+
+    /// Same trace, new span id: the context to send on an outbound call.
+    pub fn child(&self) -> Self {
+        Self {
+            span_id: new_span_id(),
+            ..self.clone()
+        }
+    }
+
+    pub fn trace_id(&self) -> String {
+        format!("{:032x}", self.trace_id)
+    }
+
+    pub fn span_id(&self) -> String {
+        format!("{:016x}", self.span_id)
+    }
+
+    pub fn is_sampled(&self) -> bool {
+        self.flags & 0x01 == 0x01
+    }
+
+    pub fn tracestate(&self) -> Option<&str> {
+        self.tracestate.as_deref()
+    }
+
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+
+    /// Time since the inbound request was received; `None` for contexts not read from a request.
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.received_at.map(|received_at| received_at.elapsed())
+    }
+
+    pub fn traceparent(&self) -> String {
+        format!(
+            "00-{:032x}-{:016x}-{:02x}",
+            self.trace_id, self.span_id, self.flags
+        )
+    }
+
+    /// The context of the request currently being served on this task, if any.
+    pub fn current() -> Option<Self> {
+        CURRENT.try_with(Clone::clone).ok()
+    }
+
+    /// Runs `future` with `self` as [`Self::current`].
+    pub async fn scope<F>(self, future: F) -> F::Output
+    where
+        F: Future,
+    {
+        CURRENT.scope(self, future).await
+    }
+
+    /// Writes `traceparent`, `tracestate` and `x-request-id` into outbound gRPC metadata.
+    pub fn inject(&self, metadata: &mut MetadataMap) {
+        let entries = [
+            (TRACEPARENT_HEADER, Some(self.traceparent())),
+            (TRACESTATE_HEADER, self.tracestate.clone()),
+            (REQUEST_ID_HEADER, self.request_id.clone()),
+        ];
+        for (key, value) in entries {
+            if let Some(value) = value.and_then(|value| value.parse::<AsciiMetadataValue>().ok()) {
+                metadata.insert(key, value);
+            }
+        }
+    }
+}
+
+fn new_span_id() -> u64 {
+    // Non-zero as required by the spec.
+    (uuid::Uuid::new_v4().as_u64_pair().0).max(1)
+}
+
+/// Tonic client interceptor propagating the current [`TraceContext`] (as a child span) or a new
+/// root trace: `GreeterClient::with_interceptor(channel, TraceContextInterceptor)`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TraceContextInterceptor;
+
+impl tonic::service::Interceptor for TraceContextInterceptor {
+    fn call(
+        &mut self,
+        mut request: tonic::Request<()>,
+    ) -> Result<tonic::Request<()>, tonic::Status> {
+        let context = TraceContext::current()
+            .map(|current| current.child())
+            .unwrap_or_else(TraceContext::new_root);
+        context.inject(request.metadata_mut());
+        Ok(request)
+    }
+}
+
+pub(crate) async fn trace_context_middleware(mut req: Request, next: Next) -> Response {
+    let context = TraceContext::from_headers(req.headers());
+    req.extensions_mut().insert(context.clone());
+    context.scope(next.run(req)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::service::Interceptor;
+
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn parses_and_formats_traceparent() {
+        let context = TraceContext::parse(TRACEPARENT, Some("vendor=abc")).expect("valid");
+        assert_eq!(context.traceparent(), TRACEPARENT);
+        assert_eq!(context.tracestate(), Some("vendor=abc"));
+        assert!(context.is_sampled());
+
+        assert!(TraceContext::parse(
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            None
+        )
+        .is_none());
+        assert!(TraceContext::parse("ff-garbage", None).is_none());
+    }
+
+    #[tokio::test]
+    async fn interceptor_continues_the_current_trace() {
+        let inbound = TraceContext::parse(TRACEPARENT, Some("vendor=abc"))
+            .expect("valid")
+            .with_request_id("req-42");
+
+        let request = inbound
+            .clone()
+            .scope(async { TraceContextInterceptor.call(tonic::Request::new(())) })
+            .await
+            .expect("interceptor never fails");
+        let metadata = request.metadata();
+        let outbound = TraceContext::parse(
+            metadata.get(TRACEPARENT_HEADER).unwrap().to_str().unwrap(),
+            None,
+        )
+        .expect("valid outbound traceparent");
+        assert_eq!(outbound.trace_id(), inbound.trace_id());
+        assert_ne!(outbound.span_id(), inbound.span_id());
+        assert_eq!(metadata.get(TRACESTATE_HEADER).unwrap(), "vendor=abc");
+        assert_eq!(metadata.get(REQUEST_ID_HEADER).unwrap(), "req-42");
+
+        let app = crate::middleware::apply_shared_middleware(
+            axum::Router::new().route(
+                "/trace",
+                axum::routing::get(|| async {
+                    TraceContext::current()
+                        .map(|context| context.trace_id())
+                        .unwrap_or_default()
+                }),
+            ),
+            &crate::middleware::MiddlewareConfig::default(),
+        );
+        let response = tower::ServiceExt::oneshot(
+            app,
+            axum::http::Request::builder()
+                .uri("/trace")
+                .header(TRACEPARENT_HEADER, TRACEPARENT)
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, inbound.trace_id());
+
+        let detached = TraceContextInterceptor
+            .call(tonic::Request::new(()))
+            .expect("interceptor never fails");
+        assert!(detached.metadata().get(TRACEPARENT_HEADER).is_some());
+        assert!(detached.metadata().get(REQUEST_ID_HEADER).is_none());
+    }
+}
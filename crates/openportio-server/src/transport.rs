@@ -0,0 +1,954 @@
+use std::{
+    future::Future,
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use axum::{body::Body, extract::ConnectInfo, Router};
+use hyper::body::{Frame, Incoming, SizeHint};
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo, TokioTimer},
+    server::conn::auto,
+    service::TowerToHyperService,
+};
+use socket2::{Domain, Protocol, SockRef, Socket, TcpKeepalive, Type};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, TcpStream},
+    sync::{watch, Notify},
+    time::Instant,
+};
+use tokio_rustls::TlsAcceptor;
+use tower::ServiceExt;
+
+use crate::tls::PeerIdentity;
+
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+const MIN_HTTP2_FRAME_SIZE: u32 = 16_384;
+const MAX_HTTP2_FRAME_SIZE: u32 = 16_777_215;
+const MAX_HTTP2_WINDOW_SIZE: u32 = (1 << 31) - 1;
+const DEFAULT_TCP_BACKLOG: u32 = 1024;
+const DEFAULT_BIND_RETRY_BACKOFF: Duration = Duration::from_millis(250);
+const MAX_BIND_RETRY_BACKOFF: Duration = Duration::from_secs(5);
+
+static OPEN_CONNECTIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Connections currently open on listeners started by `OpenportioServer` in this process.
+pub fn open_connections() -> usize {
+    OPEN_CONNECTIONS.load(Ordering::Relaxed)
+}
+
+/// Counts a connection for as long as its task is alive.
+struct ConnectionGuard;
+
+impl ConnectionGuard {
+    fn open() -> Self {
+        OPEN_CONNECTIONS.fetch_add(1, Ordering::Relaxed);
+        Self
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        OPEN_CONNECTIONS.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Http2Config {
+    pub keep_alive_interval: Option<Duration>,
+    pub keep_alive_timeout: Option<Duration>,
+    pub max_concurrent_streams: Option<u32>,
+    pub initial_stream_window_size: Option<u32>,
+    pub initial_connection_window_size: Option<u32>,
+    pub adaptive_window: bool,
+    pub max_frame_size: Option<u32>,
+    pub h2c_prior_knowledge_only: bool,
+    /// Sends GOAWAY to connections older than this (with up to 10% jitter) so long-lived
+    /// clients reconnect and rebalance across instances after deploys and scale-ups.
+    pub max_connection_age: Option<Duration>,
+    /// How long an aged-out connection may finish in-flight calls before it is closed.
+    /// `None` waits for them.
+    pub max_connection_age_grace: Option<Duration>,
+    /// Closes connections that have had no request or response stream in flight for this long.
+    pub max_connection_idle: Option<Duration>,
+}
+
+impl Http2Config {
+    pub fn long_lived_streaming() -> Self {
+        Self {
+            keep_alive_interval: Some(Duration::from_secs(20)),
+            keep_alive_timeout: Some(Duration::from_secs(10)),
+            max_concurrent_streams: Some(1024),
+            adaptive_window: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn validate(&self) -> io::Result<()> {
+        if let Some(size) = self.max_frame_size {
+            if !(MIN_HTTP2_FRAME_SIZE..=MAX_HTTP2_FRAME_SIZE).contains(&size) {
+                return Err(invalid_input(format!(
+                    "http2 max_frame_size must be between {MIN_HTTP2_FRAME_SIZE} and {MAX_HTTP2_FRAME_SIZE}, got {size}"
+                )));
+            }
+        }
+        for (name, size) in [
+            (
+                "initial_stream_window_size",
+                self.initial_stream_window_size,
+            ),
+            (
+                "initial_connection_window_size",
+                self.initial_connection_window_size,
+            ),
+        ] {
+            if size.is_some_and(|size| size > MAX_HTTP2_WINDOW_SIZE) {
+                return Err(invalid_input(format!(
+                    "http2 {name} must not exceed {MAX_HTTP2_WINDOW_SIZE}"
+                )));
+            }
+        }
+        if self.adaptive_window
+            && (self.initial_stream_window_size.is_some()
+                || self.initial_connection_window_size.is_some())
+        {
+            tracing::warn!("http2 adaptive_window overrides the configured initial window sizes");
+        }
+        if self.keep_alive_timeout.is_some() && self.keep_alive_interval.is_none() {
+            tracing::warn!("http2 keep_alive_timeout has no effect without keep_alive_interval");
+        }
+        if self.max_connection_age_grace.is_some() && self.max_connection_age.is_none() {
+            tracing::warn!("max_connection_age_grace has no effect without max_connection_age");
+        }
+        for (name, duration) in [
+            ("max_connection_age", self.max_connection_age),
+            ("max_connection_idle", self.max_connection_idle),
+        ] {
+            if duration == Some(Duration::ZERO) {
+                return Err(invalid_input(format!("{name} must be greater than zero")));
+            }
+        }
+        Ok(())
+    }
+
+    fn connection_builder(&self) -> auto::Builder<TokioExecutor> {
+        let mut builder = auto::Builder::new(TokioExecutor::new());
+        if self.h2c_prior_knowledge_only {
+            builder = builder.http2_only();
+        }
+
+        let mut http2 = builder.http2();
+        http2
+            .timer(TokioTimer::new())
+            .keep_alive_interval(self.keep_alive_interval)
+            .max_concurrent_streams(self.max_concurrent_streams)
+            .max_frame_size(self.max_frame_size)
+            .initial_stream_window_size(self.initial_stream_window_size)
+            .initial_connection_window_size(self.initial_connection_window_size)
+            .adaptive_window(self.adaptive_window);
+        if let Some(timeout) = self.keep_alive_timeout {
+            http2.keep_alive_timeout(timeout);
+        }
+        builder
+    }
+
+    /// `max_connection_age` minus up to 10% jitter, so connections opened together (after a
+    /// deploy) do not all reconnect at once.
+    fn jittered_connection_age(&self) -> Option<Duration> {
+        let age = self.max_connection_age?;
+        let jitter = (uuid::Uuid::new_v4().as_u128() % 1_000) as u32;
+        Some(age - age / 10 * jitter / 1_000)
+    }
+}
+
+/// Listener-level socket options. `reuse_port` lets several processes bind the same address so
+/// the kernel spreads connections across them (one process per core); the other options tune
+/// accepted connections and the accept loop under high connection churn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SocketConfig {
+    /// `SO_REUSEPORT`; ignored on platforms without it.
+    pub reuse_port: bool,
+    /// Pending-connection queue passed to `listen(2)`; the kernel may cap it (`somaxconn`).
+    pub backlog: u32,
+    /// `TCP_NODELAY` on accepted connections.
+    pub nodelay: bool,
+    /// Idle time before TCP keepalive probes start; `None` leaves keepalive off.
+    pub keepalive: Option<Duration>,
+    pub keepalive_interval: Option<Duration>,
+    /// Tasks accepting on the listener concurrently.
+    pub accept_workers: usize,
+}
+
+impl Default for SocketConfig {
+    fn default() -> Self {
+        Self {
+            reuse_port: false,
+            backlog: DEFAULT_TCP_BACKLOG,
+            nodelay: false,
+            keepalive: None,
+            keepalive_interval: None,
+            accept_workers: 1,
+        }
+    }
+}
+
+impl SocketConfig {
+    pub fn validate(&self) -> io::Result<()> {
+        if self.backlog == 0 || self.backlog > i32::MAX as u32 {
+            return Err(invalid_input(format!(
+                "tcp backlog must be between 1 and {}, got {}",
+                i32::MAX,
+                self.backlog
+            )));
+        }
+        if self.accept_workers == 0 {
+            return Err(invalid_input(
+                "accept_workers must be at least 1".to_string(),
+            ));
+        }
+        if self.keepalive_interval.is_some() && self.keepalive.is_none() {
+            tracing::warn!("tcp keepalive_interval has no effect without keepalive");
+        }
+        Ok(())
+    }
+
+    pub(crate) fn summary(&self) -> String {
+        let mut parts = vec![format!("backlog {}", self.backlog)];
+        if self.reuse_port {
+            parts.push("reuse_port".to_string());
+        }
+        if self.nodelay {
+            parts.push("nodelay".to_string());
+        }
+        if let Some(keepalive) = self.keepalive {
+            parts.push(format!("keepalive {}s", keepalive.as_secs()));
+        }
+        if self.accept_workers > 1 {
+            parts.push(format!("{} accept workers", self.accept_workers));
+        }
+        parts.join(", ")
+    }
+
+    fn keepalive(&self) -> Option<TcpKeepalive> {
+        let time = self.keepalive?;
+        let keepalive = TcpKeepalive::new().with_time(time);
+        Some(match self.keepalive_interval {
+            Some(interval) => keepalive.with_interval(interval),
+            None => keepalive,
+        })
+    }
+
+    fn tune(&self, stream: &TcpStream) {
+        let socket = SockRef::from(stream);
+        let result = socket.set_tcp_nodelay(self.nodelay).and_then(|()| {
+            self.keepalive()
+                .map_or(Ok(()), |keepalive| socket.set_tcp_keepalive(&keepalive))
+        });
+        if let Err(err) = result {
+            tracing::debug!(error = %err, "failed to apply socket options to connection");
+        }
+    }
+}
+
+/// What startup does when a listen address is taken: retry with exponential backoff (e.g. while
+/// a previous instance is still shutting down), then, in the `local` environment only, fall
+/// back to an ephemeral port on the same interface.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BindRetry {
+    /// Extra attempts after the first failed bind; `0` fails straight away.
+    pub attempts: u32,
+    /// Delay before the first retry; doubles per attempt, capped at 5s.
+    pub backoff: Duration,
+    pub ephemeral_fallback_in_local: bool,
+}
+
+impl Default for BindRetry {
+    fn default() -> Self {
+        Self {
+            attempts: 0,
+            backoff: DEFAULT_BIND_RETRY_BACKOFF,
+            ephemeral_fallback_in_local: true,
+        }
+    }
+}
+
+impl BindRetry {
+    pub(crate) fn from_env() -> Result<Self, String> {
+        let defaults = Self::default();
+        Ok(Self {
+            attempts: crate::env::setting("BIND_RETRY_ATTEMPTS")?.unwrap_or(defaults.attempts),
+            backoff: crate::env::setting("BIND_RETRY_BACKOFF_MS")?
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.backoff),
+            ephemeral_fallback_in_local: crate::env::setting("BIND_EPHEMERAL_FALLBACK")?
+                .unwrap_or(defaults.ephemeral_fallback_in_local),
+        })
+    }
+}
+
+/// [`bind`] with [`BindRetry`] applied. Failures name the address and `override_hint`, the
+/// setting that changes it (e.g. the `OPENPORTIO_SERVER_ADDR` env var).
+pub(crate) async fn bind_with_retry(
+    addr: SocketAddr,
+    config: &SocketConfig,
+    retry: &BindRetry,
+    local: bool,
+    override_hint: &str,
+) -> io::Result<TcpListener> {
+    let mut backoff = retry.backoff;
+    let mut attempt = 0;
+    let err = loop {
+        match bind(addr, config) {
+            Ok(listener) => return Ok(listener),
+            Err(err) if err.kind() == io::ErrorKind::AddrInUse && attempt < retry.attempts => {
+                attempt += 1;
+                tracing::warn!(
+                    addr = %addr,
+                    attempt,
+                    max_attempts = retry.attempts,
+                    backoff_ms = backoff.as_millis() as u64,
+                    "listen address in use; retrying"
+                );
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BIND_RETRY_BACKOFF);
+            }
+            Err(err) => break err,
+        }
+    };
+
+    if err.kind() == io::ErrorKind::AddrInUse
+        && local
+        && retry.ephemeral_fallback_in_local
+        && addr.port() != 0
+    {
+        let fallback = SocketAddr::new(addr.ip(), 0);
+        if let Ok(listener) = bind(fallback, config) {
+            let bound = listener.local_addr().unwrap_or(fallback);
+            tracing::warn!(
+                requested = %addr,
+                addr = %bound,
+                "listen address in use; serving on ephemeral port {} instead (local environment only; set {override_hint} to pick a port)",
+                bound.port()
+            );
+            return Ok(listener);
+        }
+    }
+    Err(describe_bind_error(addr, err, override_hint))
+}
+
+fn describe_bind_error(addr: SocketAddr, err: io::Error, override_hint: &str) -> io::Error {
+    let message = match err.kind() {
+        io::ErrorKind::AddrInUse => format!(
+            "cannot listen on {addr}: address already in use (another process is bound to port {}); \
+             stop it or set {override_hint} to a free address, e.g. {}",
+            addr.port(),
+            SocketAddr::new(addr.ip(), 0)
+        ),
+        io::ErrorKind::PermissionDenied if addr.port() < 1024 => format!(
+            "cannot listen on {addr}: permission denied (ports below 1024 need elevated \
+             privileges); set {override_hint} to a port of 1024 or above"
+        ),
+        io::ErrorKind::AddrNotAvailable => format!(
+            "cannot listen on {addr}: {} is not an address of this host; set {override_hint} \
+             to a local interface such as 0.0.0.0:{}",
+            addr.ip(),
+            addr.port()
+        ),
+        _ => format!("cannot listen on {addr}: {err}; check {override_hint}"),
+    };
+    io::Error::new(err.kind(), message)
+}
+
+/// Binds `addr` with `config` applied; like `TcpListener::bind`, `SO_REUSEADDR` is set on Unix.
+pub(crate) fn bind(addr: SocketAddr, config: &SocketConfig) -> io::Result<TcpListener> {
+    config.validate()?;
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    #[cfg(unix)]
+    {
+        socket.set_reuse_address(true)?;
+        if config.reuse_port {
+            socket.set_reuse_port(true)?;
+        }
+    }
+    socket.set_tcp_nodelay(config.nodelay)?;
+    if let Some(keepalive) = config.keepalive() {
+        socket.set_tcp_keepalive(&keepalive)?;
+    }
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(config.backlog as i32)?;
+    TcpListener::from_std(socket.into())
+}
+
+pub(crate) async fn serve<F>(
+    listener: TcpListener,
+    app: Router,
+    http2: &Http2Config,
+    socket: &SocketConfig,
+    shutdown: F,
+) -> io::Result<()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    serve_with(listener, app, http2, socket, None, shutdown).await
+}
+
+/// [`serve`] behind a TLS handshake. Verified client certificates are exposed to handlers as a
+/// [`PeerIdentity`] request extension.
+pub(crate) async fn serve_tls<F>(
+    listener: TcpListener,
+    app: Router,
+    http2: &Http2Config,
+    socket: &SocketConfig,
+    tls: Arc<rustls::ServerConfig>,
+    shutdown: F,
+) -> io::Result<()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    serve_with(
+        listener,
+        app,
+        http2,
+        socket,
+        Some(TlsAcceptor::from(tls)),
+        shutdown,
+    )
+    .await
+}
+
+async fn serve_with<F>(
+    listener: TcpListener,
+    app: Router,
+    http2: &Http2Config,
+    socket: &SocketConfig,
+    tls: Option<TlsAcceptor>,
+    shutdown: F,
+) -> io::Result<()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let (closing_tx, closing_rx) = watch::channel(false);
+    let acceptor = Acceptor {
+        listener: Arc::new(listener),
+        app,
+        builder: http2.connection_builder(),
+        http2: Arc::new(http2.clone()),
+        socket: socket.clone(),
+        tls,
+        closing: closing_rx,
+    };
+    let (stop_tx, stop_rx) = watch::channel(false);
+    let workers: Vec<_> = (0..socket.accept_workers.max(1))
+        .map(|_| tokio::spawn(acceptor.clone().run(stop_rx.clone())))
+        .collect();
+
+    shutdown.await;
+    let _ = stop_tx.send(true);
+    for worker in workers {
+        let _ = worker.await;
+    }
+    let Acceptor {
+        listener, closing, ..
+    } = acceptor;
+    drop(listener);
+    drop(closing);
+    // Every connection task holds a receiver; wait for all of them to finish gracefully.
+    let _ = closing_tx.send(true);
+    closing_tx.closed().await;
+    Ok(())
+}
+
+#[derive(Clone)]
+struct Acceptor {
+    listener: Arc<TcpListener>,
+    app: Router,
+    builder: auto::Builder<TokioExecutor>,
+    http2: Arc<Http2Config>,
+    socket: SocketConfig,
+    tls: Option<TlsAcceptor>,
+    closing: watch::Receiver<bool>,
+}
+
+impl Acceptor {
+    async fn run(self, mut stop: watch::Receiver<bool>) {
+        loop {
+            let (stream, remote_addr) = tokio::select! {
+                accepted = self.listener.accept() => match accepted {
+                    Ok(connection) => connection,
+                    Err(err) => {
+                        handle_accept_error(err).await;
+                        continue;
+                    }
+                },
+                _ = stop.changed() => break,
+            };
+            self.socket.tune(&stream);
+
+            let app = self.app.clone();
+            let builder = self.builder.clone();
+            let http2 = self.http2.clone();
+            let closing = self.closing.clone();
+            let tls = self.tls.clone();
+            let guard = ConnectionGuard::open();
+            tokio::spawn(async move {
+                let _guard = guard;
+                let Some(tls) = tls else {
+                    serve_connection(&builder, &http2, closing, stream, app, remote_addr, None)
+                        .await;
+                    return;
+                };
+                // The handshake runs on the connection task so slow clients never block `accept`.
+                let stream = match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, tls.accept(stream))
+                    .await
+                {
+                    Ok(Ok(stream)) => stream,
+                    Ok(Err(err)) => {
+                        tracing::debug!(peer = %remote_addr, error = %err, "tls handshake failed");
+                        return;
+                    }
+                    Err(_) => {
+                        tracing::debug!(peer = %remote_addr, "tls handshake timed out");
+                        return;
+                    }
+                };
+                let peer = stream
+                    .get_ref()
+                    .1
+                    .peer_certificates()
+                    .and_then(|certs| certs.first())
+                    .and_then(|cert| PeerIdentity::from_der(cert));
+                serve_connection(&builder, &http2, closing, stream, app, remote_addr, peer).await;
+            });
+        }
+    }
+}
+
+async fn serve_connection<I>(
+    builder: &auto::Builder<TokioExecutor>,
+    http2: &Http2Config,
+    mut closing: watch::Receiver<bool>,
+    io: I,
+    app: Router,
+    remote_addr: SocketAddr,
+    peer: Option<PeerIdentity>,
+) where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let activity = http2
+        .max_connection_idle
+        .map(|_| Arc::new(ConnectionActivity::default()));
+    let tracked = activity.clone();
+    let service = app
+        .map_request(move |mut req: http::Request<Incoming>| {
+            req.extensions_mut().insert(ConnectInfo(remote_addr));
+            if let Some(peer) = &peer {
+                req.extensions_mut().insert(peer.clone());
+            }
+            req
+        })
+        .map_future(move |response| {
+            let guard = tracked.as_ref().map(ConnectionActivity::enter);
+            async move {
+                let response = response.await?;
+                Ok::<_, std::convert::Infallible>(match guard {
+                    Some(guard) => response.map(|inner| {
+                        Body::new(TrackedBody {
+                            inner,
+                            _guard: guard,
+                        })
+                    }),
+                    None => response,
+                })
+            }
+        });
+    let connection = builder
+        .serve_connection_with_upgrades(TokioIo::new(io), TowerToHyperService::new(service))
+        .into_owned();
+    tokio::pin!(connection);
+
+    let aged_out = tokio::select! {
+        result = connection.as_mut() => {
+            log_connection_result(result, remote_addr);
+            return;
+        }
+        _ = closing.wait_for(|closing| *closing) => false,
+        _ = sleep_or_pending(http2.jittered_connection_age()) => true,
+        _ = idle_or_pending(activity.as_deref(), http2.max_connection_idle) => {
+            tracing::debug!(peer = %remote_addr, "closing idle connection");
+            false
+        }
+    };
+    connection.as_mut().graceful_shutdown();
+    let result = match (aged_out, http2.max_connection_age_grace) {
+        (true, Some(grace)) => match tokio::time::timeout(grace, connection).await {
+            Ok(result) => result,
+            Err(_) => {
+                tracing::debug!(peer = %remote_addr, "connection age grace period elapsed");
+                return;
+            }
+        },
+        _ => connection.await,
+    };
+    log_connection_result(result, remote_addr);
+}
+
+fn log_connection_result<E: std::fmt::Display>(result: Result<(), E>, remote_addr: SocketAddr) {
+    if let Err(err) = result {
+        tracing::debug!(peer = %remote_addr, error = %err, "connection closed with error");
+    }
+}
+
+async fn sleep_or_pending(duration: Option<Duration>) {
+    match duration {
+        Some(duration) => tokio::time::sleep(duration).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Resolves once the connection has had nothing in flight for `idle`.
+async fn idle_or_pending(activity: Option<&ConnectionActivity>, idle: Option<Duration>) {
+    let (Some(activity), Some(idle)) = (activity, idle) else {
+        return std::future::pending().await;
+    };
+    loop {
+        let changed = activity.changed.notified();
+        match activity.idle_deadline(idle) {
+            Some(deadline) if deadline <= Instant::now() => return,
+            Some(deadline) => tokio::select! {
+                _ = tokio::time::sleep_until(deadline) => {}
+                _ = changed => {}
+            },
+            None => changed.await,
+        }
+    }
+}
+
+/// Requests and response bodies in flight on one connection, for `max_connection_idle`.
+#[derive(Debug)]
+struct ConnectionActivity {
+    in_flight: AtomicUsize,
+    idle_since: Mutex<Instant>,
+    changed: Notify,
+}
+
+impl Default for ConnectionActivity {
+    fn default() -> Self {
+        Self {
+            in_flight: AtomicUsize::new(0),
+            idle_since: Mutex::new(Instant::now()),
+            changed: Notify::new(),
+        }
+    }
+}
+
+impl ConnectionActivity {
+    fn enter(self: &Arc<Self>) -> ActivityGuard {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        self.changed.notify_waiters();
+        ActivityGuard(self.clone())
+    }
+
+    fn idle_deadline(&self, idle: Duration) -> Option<Instant> {
+        if self.in_flight.load(Ordering::SeqCst) > 0 {
+            return None;
+        }
+        let idle_since = *self
+            .idle_since
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        Some(idle_since + idle)
+    }
+}
+
+/// Holds the connection busy until the response body is finished or dropped.
+struct ActivityGuard(Arc<ConnectionActivity>);
+
+impl Drop for ActivityGuard {
+    fn drop(&mut self) {
+        *self
+            .0
+            .idle_since
+            .lock()
+            .unwrap_or_else(|err| err.into_inner()) = Instant::now();
+        self.0.in_flight.fetch_sub(1, Ordering::SeqCst);
+        self.0.changed.notify_waiters();
+    }
+}
+
+struct TrackedBody {
+    inner: Body,
+    _guard: ActivityGuard,
+}
+
+impl http_body::Body for TrackedBody {
+    type Data = axum::body::Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        Pin::new(&mut self.inner).poll_frame(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+async fn handle_accept_error(err: io::Error) {
+    if matches!(
+        err.kind(),
+        io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+    ) {
+        return;
+    }
+    tracing::error!(error = %err, "failed to accept connection");
+    tokio::time::sleep(Duration::from_secs(1)).await;
+}
+
+fn invalid_input(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use tokio::sync::oneshot;
+
+    #[tokio::test]
+    async fn bind_conflicts_retry_fall_back_in_local_and_name_the_address() {
+        let occupant = std::net::TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let addr = occupant.local_addr().unwrap();
+        let retry = BindRetry {
+            attempts: 1,
+            backoff: Duration::from_millis(10),
+            ..BindRetry::default()
+        };
+        let config = SocketConfig::default();
+
+        let err = bind_with_retry(addr, &config, &retry, false, "OPENPORTIO_SERVER_ADDR")
+            .await
+            .expect_err("port is taken");
+        assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+        let message = err.to_string();
+        assert!(message.contains(&addr.to_string()), "{message}");
+        assert!(message.contains("OPENPORTIO_SERVER_ADDR"), "{message}");
+
+        let fallback = bind_with_retry(addr, &config, &retry, true, "OPENPORTIO_SERVER_ADDR")
+            .await
+            .expect("local falls back to an ephemeral port");
+        assert_ne!(fallback.local_addr().unwrap().port(), addr.port());
+
+        let release = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(30));
+            drop(occupant);
+        });
+        let retry = BindRetry {
+            attempts: 10,
+            backoff: Duration::from_millis(20),
+            ephemeral_fallback_in_local: false,
+        };
+        let listener = bind_with_retry(addr, &config, &retry, true, "OPENPORTIO_SERVER_ADDR")
+            .await
+            .expect("bind succeeds once the port is released");
+        assert_eq!(listener.local_addr().unwrap(), addr);
+        release.join().unwrap();
+    }
+
+    #[test]
+    fn http2_config_rejects_out_of_range_frame_size() {
+        let config = Http2Config {
+            max_frame_size: Some(1024),
+            ..Http2Config::default()
+        };
+        let err = config.validate().expect_err("frame size below minimum");
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(err.to_string().contains("max_frame_size"));
+
+        let config = Http2Config {
+            max_frame_size: Some(MAX_HTTP2_FRAME_SIZE),
+            ..Http2Config::default()
+        };
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn http2_config_rejects_oversized_window() {
+        let config = Http2Config {
+            initial_connection_window_size: Some(u32::MAX),
+            ..Http2Config::default()
+        };
+        let err = config.validate().expect_err("window above 2^31-1");
+        assert!(err.to_string().contains("initial_connection_window_size"));
+        assert!(Http2Config::long_lived_streaming().validate().is_ok());
+    }
+
+    #[tokio::test]
+    async fn serve_applies_connect_info_and_graceful_shutdown() {
+        let app = Router::new().route(
+            "/peer",
+            get(
+                |ConnectInfo(addr): ConnectInfo<std::net::SocketAddr>| async move {
+                    addr.ip().to_string()
+                },
+            ),
+        );
+        let listener = TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .expect("bind test listener");
+        let addr = listener.local_addr().expect("listener addr");
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let server = tokio::spawn(async move {
+            serve(
+                listener,
+                app,
+                &Http2Config::long_lived_streaming(),
+                &SocketConfig::default(),
+                async {
+                    let _ = shutdown_rx.await;
+                },
+            )
+            .await
+        });
+
+        let body = reqwest::get(format!("http://{addr}/peer"))
+            .await
+            .expect("request should succeed")
+            .text()
+            .await
+            .expect("body should be readable");
+        assert_eq!(body, "127.0.0.1");
+
+        let _ = shutdown_tx.send(());
+        server
+            .await
+            .expect("server task should join")
+            .expect("server should shut down cleanly");
+    }
+
+    #[tokio::test]
+    async fn aged_and_idle_connections_are_closed_so_clients_reconnect() {
+        for http2 in [
+            Http2Config {
+                max_connection_age: Some(Duration::from_millis(150)),
+                max_connection_age_grace: Some(Duration::from_secs(1)),
+                ..Http2Config::default()
+            },
+            Http2Config {
+                max_connection_idle: Some(Duration::from_millis(150)),
+                ..Http2Config::default()
+            },
+        ] {
+            http2.validate().expect("valid connection limits");
+            let app =
+                Router::new().route(
+                    "/port",
+                    get(|ConnectInfo(addr): ConnectInfo<SocketAddr>| async move {
+                        addr.port().to_string()
+                    }),
+                );
+            let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+            let server = tokio::spawn(async move {
+                serve(listener, app, &http2, &SocketConfig::default(), async {
+                    let _ = shutdown_rx.await;
+                })
+                .await
+            });
+
+            let client = reqwest::Client::new();
+            let port = || async {
+                client
+                    .get(format!("http://{addr}/port"))
+                    .send()
+                    .await
+                    .expect("request should succeed")
+                    .text()
+                    .await
+                    .expect("body should be readable")
+            };
+            let first = port().await;
+            assert_eq!(port().await, first, "connection is reused while fresh");
+            tokio::time::sleep(Duration::from_millis(400)).await;
+            assert_ne!(
+                port().await,
+                first,
+                "client reconnected after the server closed"
+            );
+
+            let _ = shutdown_tx.send(());
+            server.await.unwrap().unwrap();
+        }
+        assert!(Http2Config {
+            max_connection_idle: Some(Duration::ZERO),
+            ..Http2Config::default()
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn reuse_port_listeners_share_an_address_and_accept_workers_serve() {
+        let config = SocketConfig {
+            reuse_port: true,
+            backlog: 64,
+            nodelay: true,
+            keepalive: Some(Duration::from_secs(30)),
+            keepalive_interval: Some(Duration::from_secs(5)),
+            accept_workers: 3,
+        };
+        let listener = bind(SocketAddr::from(([127, 0, 0, 1], 0)), &config).expect("bind");
+        let addr = listener.local_addr().expect("listener addr");
+        #[cfg(target_os = "linux")]
+        drop(bind(addr, &config).expect("SO_REUSEPORT allows a second listener"));
+        assert!(bind(addr, &SocketConfig::default()).is_err());
+        assert!(SocketConfig {
+            accept_workers: 0,
+            ..SocketConfig::default()
+        }
+        .validate()
+        .is_err());
+
+        let app = Router::new().route("/ping", get(|| async { "pong" }));
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let server = tokio::spawn(async move {
+            serve(listener, app, &Http2Config::default(), &config, async {
+                let _ = shutdown_rx.await;
+            })
+            .await
+        });
+        for _ in 0..4 {
+            let body = reqwest::get(format!("http://{addr}/ping"))
+                .await
+                .expect("request should succeed")
+                .text()
+                .await
+                .expect("body should be readable");
+            assert_eq!(body, "pong");
+        }
+
+        let _ = shutdown_tx.send(());
+        server
+            .await
+            .expect("server task should join")
+            .expect("server should shut down cleanly");
+    }
+}
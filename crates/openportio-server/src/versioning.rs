@@ -0,0 +1,665 @@
+use std::{convert::Infallible, fmt, ops::RangeInclusive, sync::Arc};
+
+use axum::{
+    body::Body,
+    extract::{MatchedPath, Request},
+    http::{header::HeaderName, HeaderMap, HeaderValue, Method, StatusCode, Uri},
+    middleware::{from_fn, Next},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use tower::{service_fn, ServiceExt};
+use utoipa::openapi::{
+    path::{Operation, PathItem},
+    tag::Tag,
+    OpenApi,
+};
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::{api::ApiErrorResponse, api_docs, middleware::is_grpc_request};
+
+pub const ACCEPT_VERSION_HEADER: &str = "accept-version";
+pub const API_VERSION_HEADER: &str = "x-api-version";
+
+/// Major API version used by `OpenportioServer::nest_versioned(...)`; mounts under `/v{major}`
+/// and tags OpenAPI operations with `v{major}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ApiVersion(u16);
+
+impl ApiVersion {
+    pub const V1: Self = Self(1);
+    pub const V2: Self = Self(2);
+    pub const V3: Self = Self(3);
+
+    pub const fn new(major: u16) -> Self {
+        Self(major)
+    }
+
+    pub const fn major(self) -> u16 {
+        self.0
+    }
+
+    pub fn tag(self) -> String {
+        format!("v{}", self.0)
+    }
+
+    pub fn prefix(self) -> String {
+        format!("/v{}", self.0)
+    }
+}
+
+impl fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}", self.0)
+    }
+}
+
+impl std::str::FromStr for ApiVersion {
+    type Err = String;
+
+    /// Accepts `2`, `v2`, `V2`, and `2.1` (the minor part is ignored).
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let digits = value
+            .strip_prefix(['v', 'V'])
+            .unwrap_or(value)
+            .split('.')
+            .next()
+            .unwrap_or_default();
+        digits
+            .parse()
+            .map(Self)
+            .map_err(|_| format!("invalid API version `{value}`"))
+    }
+}
+
+/// Inclusive range of major versions served by one router; `max: None` is open-ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionRange {
+    pub min: ApiVersion,
+    pub max: Option<ApiVersion>,
+}
+
+impl VersionRange {
+    pub const fn exactly(version: ApiVersion) -> Self {
+        Self {
+            min: version,
+            max: Some(version),
+        }
+    }
+
+    pub const fn at_least(version: ApiVersion) -> Self {
+        Self {
+            min: version,
+            max: None,
+        }
+    }
+
+    pub fn contains(&self, version: ApiVersion) -> bool {
+        version >= self.min && self.max.is_none_or(|max| version <= max)
+    }
+
+    fn latest(&self) -> ApiVersion {
+        self.max.unwrap_or(self.min)
+    }
+}
+
+impl From<ApiVersion> for VersionRange {
+    fn from(version: ApiVersion) -> Self {
+        Self::exactly(version)
+    }
+}
+
+impl From<RangeInclusive<ApiVersion>> for VersionRange {
+    fn from(range: RangeInclusive<ApiVersion>) -> Self {
+        Self {
+            min: *range.start(),
+            max: Some(*range.end()),
+        }
+    }
+}
+
+impl fmt::Display for VersionRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.max {
+            Some(max) if max == self.min => write!(f, "{max}"),
+            Some(max) => write!(f, "{}-{max}", self.min),
+            None => write!(f, "{}+", self.min),
+        }
+    }
+}
+
+/// Version used when a request carries neither `Accept-Version` nor `X-Api-Version`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DefaultVersion {
+    #[default]
+    Latest,
+    Oldest,
+    Fixed(ApiVersion),
+}
+
+/// `Deprecation` / `Sunset` / `Link: rel="deprecation"` headers added to responses served by a
+/// deprecated version. Dates are passed through verbatim (HTTP-dates, or `@<epoch>` for
+/// `Deprecation` per RFC 9745).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Deprecation {
+    pub deprecated_at: Option<String>,
+    pub sunset: Option<String>,
+    pub link: Option<String>,
+}
+
+impl Deprecation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn since(mut self, date: impl Into<String>) -> Self {
+        self.deprecated_at = Some(date.into());
+        self
+    }
+
+    pub fn with_sunset(mut self, date: impl Into<String>) -> Self {
+        self.sunset = Some(date.into());
+        self
+    }
+
+    pub fn with_link(mut self, url: impl Into<String>) -> Self {
+        self.link = Some(url.into());
+        self
+    }
+
+    fn apply(&self, headers: &mut HeaderMap) {
+        let deprecation = self.deprecated_at.as_deref().unwrap_or("true");
+        let link = self
+            .link
+            .as_ref()
+            .map(|url| format!("<{url}>; rel=\"deprecation\""));
+        for (name, value) in [
+            ("deprecation", Some(deprecation)),
+            ("sunset", self.sunset.as_deref()),
+            ("link", link.as_deref()),
+        ] {
+            if let Some(value) = value.and_then(|value| HeaderValue::from_str(value).ok()) {
+                headers.append(HeaderName::from_static(name), value);
+            }
+        }
+    }
+}
+
+struct VersionedRouter {
+    range: VersionRange,
+    router: Router,
+    deprecation: Option<Deprecation>,
+}
+
+/// Routes REST requests to one of several routers by the `Accept-Version` or `X-Api-Version`
+/// request header, keyed by [`VersionRange`]. Paths a version's router does not define fall
+/// through to the rest of the app, and the resolved version is echoed in `X-Api-Version`. An
+/// invalid or unsupported version header is only rejected on paths some version's router
+/// defines; elsewhere it is ignored.
+#[derive(Default)]
+pub struct HeaderVersioning {
+    versions: Vec<VersionedRouter>,
+    default: DefaultVersion,
+}
+
+impl HeaderVersioning {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serves `range` with `router`; the first registered range containing a version wins.
+    pub fn version(mut self, range: impl Into<VersionRange>, router: Router) -> Self {
+        self.versions.push(VersionedRouter {
+            range: range.into(),
+            router,
+            deprecation: None,
+        });
+        self
+    }
+
+    /// Marks the router serving `version` as deprecated.
+    pub fn deprecate(mut self, version: ApiVersion, deprecation: Deprecation) -> Self {
+        if let Some(entry) = self
+            .versions
+            .iter_mut()
+            .find(|entry| entry.range.contains(version))
+        {
+            entry.deprecation = Some(deprecation);
+        }
+        self
+    }
+
+    pub fn with_default(mut self, default: DefaultVersion) -> Self {
+        self.default = default;
+        self
+    }
+
+    pub(crate) fn summary(&self) -> String {
+        let ranges: Vec<_> = self
+            .versions
+            .iter()
+            .map(|entry| match entry.deprecation {
+                Some(_) => format!("{} (deprecated)", entry.range),
+                None => entry.range.to_string(),
+            })
+            .collect();
+        let default = match self.default {
+            DefaultVersion::Latest => "latest".to_string(),
+            DefaultVersion::Oldest => "oldest".to_string(),
+            DefaultVersion::Fixed(version) => version.to_string(),
+        };
+        format!("header {}, default {default}", ranges.join(", "))
+    }
+
+    fn default_version(&self) -> Option<ApiVersion> {
+        match self.default {
+            DefaultVersion::Latest => self.versions.iter().map(|entry| entry.range.latest()).max(),
+            DefaultVersion::Oldest => self.versions.iter().map(|entry| entry.range.min).min(),
+            DefaultVersion::Fixed(version) => Some(version),
+        }
+    }
+
+    /// Dispatches non-gRPC requests to the matching version router, each falling back to `app`.
+    pub(crate) fn wrap(&self, app: Router) -> Router {
+        if self.versions.is_empty() {
+            return app;
+        }
+        let dispatch = Arc::new(VersionDispatch {
+            versions: self
+                .versions
+                .iter()
+                .map(|entry| VersionedRouter {
+                    range: entry.range,
+                    router: entry.router.clone().fallback_service(app.clone()),
+                    deprecation: entry.deprecation.clone(),
+                })
+                .collect(),
+            probes: self
+                .versions
+                .iter()
+                .map(|entry| entry.router.clone().layer(from_fn(probe_route)))
+                .collect(),
+            default: self.default_version(),
+            app,
+        });
+        Router::new().fallback_service(service_fn(move |req: Request| {
+            let dispatch = Arc::clone(&dispatch);
+            async move { Ok::<_, Infallible>(dispatch.call(req).await) }
+        }))
+    }
+}
+
+struct VersionDispatch {
+    versions: Vec<VersionedRouter>,
+    /// Each version's routes with every handler replaced by [`probe_route`].
+    probes: Vec<Router>,
+    default: Option<ApiVersion>,
+    app: Router,
+}
+
+impl VersionDispatch {
+    async fn call(&self, req: Request) -> Response {
+        if is_grpc_request(req.headers()) {
+            return self.app.clone().oneshot(req).await.into_response();
+        }
+        let version = match requested_version(req.headers()) {
+            Ok(Some(version)) => version,
+            Ok(None) => match self.default {
+                Some(version) => version,
+                None => return self.app.clone().oneshot(req).await.into_response(),
+            },
+            Err(_) if !self.is_versioned(req.method(), req.uri()).await => {
+                return self.app.clone().oneshot(req).await.into_response()
+            }
+            Err(message) => return version_error("invalid_api_version", message, None),
+        };
+        let Some(entry) = self
+            .versions
+            .iter()
+            .find(|entry| entry.range.contains(version))
+        else {
+            if !self.is_versioned(req.method(), req.uri()).await {
+                return self.app.clone().oneshot(req).await.into_response();
+            }
+            let supported: Vec<_> = self
+                .versions
+                .iter()
+                .map(|entry| entry.range.to_string())
+                .collect();
+            return version_error(
+                "unsupported_api_version",
+                format!("API version {version} is not supported"),
+                Some(serde_json::json!({
+                    "requested": version.to_string(),
+                    "supported": supported,
+                })),
+            );
+        };
+
+        let mut response = entry.router.clone().oneshot(req).await.into_response();
+        let headers = response.headers_mut();
+        if let Ok(value) = HeaderValue::from_str(&version.to_string()) {
+            headers.insert(HeaderName::from_static(API_VERSION_HEADER), value);
+        }
+        if let Some(deprecation) = &entry.deprecation {
+            deprecation.apply(headers);
+        }
+        response
+    }
+
+    /// Whether any version's router defines a route for `uri`'s path.
+    async fn is_versioned(&self, method: &Method, uri: &Uri) -> bool {
+        for probe in &self.probes {
+            let Ok(probe_req) = Request::builder()
+                .method(method.clone())
+                .uri(uri.clone())
+                .body(Body::empty())
+            else {
+                return false;
+            };
+            let response = probe.clone().oneshot(probe_req).await.into_response();
+            if response.status() == StatusCode::OK {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// Answers for a probe router without running its handlers or fallback: `200` when the path
+/// matched one of its routes, `404` otherwise.
+pub(crate) async fn probe_route(req: Request, _next: Next) -> StatusCode {
+    match req.extensions().get::<MatchedPath>() {
+        Some(_) => StatusCode::OK,
+        None => StatusCode::NOT_FOUND,
+    }
+}
+
+/// `Accept-Version` wins over `X-Api-Version`; neither present is `Ok(None)`.
+fn requested_version(headers: &HeaderMap) -> Result<Option<ApiVersion>, String> {
+    let Some(value) = [ACCEPT_VERSION_HEADER, API_VERSION_HEADER]
+        .into_iter()
+        .find_map(|name| headers.get(name))
+    else {
+        return Ok(None);
+    };
+    let value = value
+        .to_str()
+        .map_err(|_| "API version header is not valid UTF-8".to_string())?;
+    value.parse().map(Some)
+}
+
+fn version_error(code: &str, message: String, details: Option<serde_json::Value>) -> Response {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ApiErrorResponse {
+            code: code.to_string(),
+            message,
+            detail: None,
+            details,
+        }),
+    )
+        .into_response()
+}
+
+/// A router mounted under a path prefix, optionally with the OpenAPI document describing it
+/// (paths relative to the router, not the prefix).
+pub struct NestedRouter {
+    router: Router,
+    openapi: Option<OpenApi>,
+    bearer_auth: bool,
+    hidden_paths: Vec<String>,
+}
+
+impl NestedRouter {
+    pub fn new(router: Router) -> Self {
+        Self {
+            router,
+            openapi: None,
+            bearer_auth: false,
+            hidden_paths: Vec::new(),
+        }
+    }
+
+    pub fn with_openapi(mut self, doc: OpenApi) -> Self {
+        self.openapi = Some(doc);
+        self
+    }
+
+    /// Documents every operation without its own `security` as requiring `bearerAuth`.
+    pub fn with_bearer_auth(mut self) -> Self {
+        self.bearer_auth = true;
+        self
+    }
+
+    /// Leaves `path` (as written in the document, e.g. `/admin/{id}`) out of the served
+    /// `{prefix}/openapi.json`; it still appears in `describe()`.
+    pub fn with_hidden_path(mut self, path: impl Into<String>) -> Self {
+        self.hidden_paths.push(path.into());
+        self
+    }
+}
+
+impl From<Router> for NestedRouter {
+    fn from(router: Router) -> Self {
+        Self::new(router)
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct MountedRouter {
+    pub(crate) prefix: String,
+    pub(crate) router: Router,
+    pub(crate) openapi: Option<OpenApi>,
+}
+
+impl MountedRouter {
+    pub(crate) fn new(prefix: &str, nested: NestedRouter, version: Option<ApiVersion>) -> Self {
+        let prefix = normalize_prefix(prefix);
+        let openapi = nested.openapi.map(|doc| {
+            let doc = match nested.bearer_auth {
+                true => api_docs::require_bearer_auth(doc),
+                false => doc,
+            };
+            let doc = nested
+                .hidden_paths
+                .iter()
+                .fold(doc, |doc, path| api_docs::hide_path(doc, path));
+            nest_openapi(doc, &prefix, version)
+        });
+        Self {
+            prefix,
+            router: nested.router,
+            openapi,
+        }
+    }
+
+    pub(crate) fn docs_path(&self) -> String {
+        format!("{}/docs", self.prefix)
+    }
+
+    pub(crate) fn openapi_path(&self) -> String {
+        format!("{}/openapi.json", self.prefix)
+    }
+
+    /// The nested routes plus, when an OpenAPI document was supplied, `{prefix}/openapi.json`
+    /// and a Swagger UI at `{prefix}/docs`.
+    pub(crate) fn into_router(self) -> Router {
+        let docs_path = self.docs_path();
+        let openapi_path = self.openapi_path();
+        let router = Router::new().nest(&self.prefix, self.router);
+        match self.openapi {
+            Some(doc) => router.merge(SwaggerUi::new(docs_path).url(
+                openapi_path,
+                api_docs::public_openapi(api_docs::with_dto_schemas(doc)),
+            )),
+            None => router,
+        }
+    }
+}
+
+/// Prefixes every path of `doc` with `prefix` and, for versioned mounts, tags each operation
+/// with the version so docs tooling can group `/v1` and `/v2` separately.
+pub fn nest_openapi(mut doc: OpenApi, prefix: &str, version: Option<ApiVersion>) -> OpenApi {
+    let prefix = normalize_prefix(prefix);
+    doc.paths.paths = std::mem::take(&mut doc.paths.paths)
+        .into_iter()
+        .map(|(path, item)| (format!("{prefix}{path}"), item))
+        .collect();
+
+    if let Some(version) = version {
+        let tag = version.tag();
+        for item in doc.paths.paths.values_mut() {
+            for operation in operations_mut(item) {
+                let tags = operation.tags.get_or_insert_with(Vec::new);
+                if !tags.contains(&tag) {
+                    tags.insert(0, tag.clone());
+                }
+            }
+        }
+        let tags = doc.tags.get_or_insert_with(Vec::new);
+        if !tags.iter().any(|existing| existing.name == tag) {
+            let mut entry = Tag::new(&tag);
+            entry.description = Some(format!("API {version} ({prefix})"));
+            tags.insert(0, entry);
+        }
+    }
+    doc
+}
+
+pub(crate) fn operations_mut(item: &mut PathItem) -> impl Iterator<Item = &mut Operation> {
+    [
+        item.get.as_mut(),
+        item.put.as_mut(),
+        item.post.as_mut(),
+        item.delete.as_mut(),
+        item.options.as_mut(),
+        item.head.as_mut(),
+        item.patch.as_mut(),
+        item.trace.as_mut(),
+    ]
+    .into_iter()
+    .flatten()
+}
+
+fn normalize_prefix(prefix: &str) -> String {
+    format!("/{}", prefix.trim_matches('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use utoipa::openapi::{path::OperationBuilder, HttpMethod, OpenApiBuilder, PathsBuilder};
+
+    #[test]
+    fn nest_openapi_prefixes_paths_and_tags_operations() {
+        let doc = OpenApiBuilder::new()
+            .paths(PathsBuilder::new().path(
+                "/items/{id}",
+                PathItem::new(HttpMethod::Get, OperationBuilder::new().tag("items")),
+            ))
+            .build();
+
+        let nested = nest_openapi(doc, "v2/", Some(ApiVersion::V2));
+
+        let item = nested
+            .paths
+            .paths
+            .get("/v2/items/{id}")
+            .expect("prefixed path");
+        assert_eq!(
+            item.get.as_ref().and_then(|op| op.tags.clone()),
+            Some(vec!["v2".to_string(), "items".to_string()])
+        );
+        assert_eq!(nested.tags.expect("tags")[0].name, "v2");
+    }
+
+    #[tokio::test]
+    async fn header_versioning_routes_by_version_and_marks_deprecated_versions() {
+        let versioning = HeaderVersioning::new()
+            .version(
+                ApiVersion::V1,
+                Router::new().route("/items", get(|| async { "v1 items" })),
+            )
+            .version(
+                VersionRange::at_least(ApiVersion::V2),
+                Router::new().route("/items", get(|| async { "v2 items" })),
+            )
+            .deprecate(
+                ApiVersion::V1,
+                Deprecation::new()
+                    .with_sunset("Sat, 01 Nov 2025 00:00:00 GMT")
+                    .with_link("https://example.com/migrate"),
+            );
+        assert_eq!(
+            versioning.summary(),
+            "header v1 (deprecated), v2+, default latest"
+        );
+        let app = versioning.wrap(Router::new().route("/health", get(|| async { "ok" })));
+        let call = |version: Option<(&str, &str)>, uri: &str| {
+            let mut builder = Request::builder().uri(uri);
+            if let Some((name, value)) = version {
+                builder = builder.header(name, value);
+            }
+            app.clone().oneshot(builder.body(Body::empty()).unwrap())
+        };
+        let body = |response: Response| async move {
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            String::from_utf8(bytes.to_vec()).unwrap()
+        };
+
+        let response = call(Some((ACCEPT_VERSION_HEADER, "v1")), "/items")
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["deprecation"], "true");
+        assert_eq!(
+            response.headers()["sunset"],
+            "Sat, 01 Nov 2025 00:00:00 GMT"
+        );
+        assert_eq!(
+            response.headers()["link"],
+            "<https://example.com/migrate>; rel=\"deprecation\""
+        );
+        assert_eq!(body(response).await, "v1 items");
+
+        let response = call(Some((API_VERSION_HEADER, "3.1")), "/items")
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[API_VERSION_HEADER], "v3");
+        assert!(response.headers().get("deprecation").is_none());
+        assert_eq!(body(response).await, "v2 items");
+
+        let response = call(None, "/items").await.unwrap();
+        assert_eq!(response.headers()[API_VERSION_HEADER], "v2");
+        assert_eq!(body(response).await, "v2 items");
+
+        let response = call(Some((ACCEPT_VERSION_HEADER, "1")), "/health")
+            .await
+            .unwrap();
+        assert_eq!(body(response).await, "ok");
+
+        let response = call(Some((ACCEPT_VERSION_HEADER, "latest")), "/items")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = call(Some((ACCEPT_VERSION_HEADER, "v0")), "/items")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(body(response).await.contains("unsupported_api_version"));
+
+        for version in ["latest", "v0"] {
+            let response = call(Some((ACCEPT_VERSION_HEADER, version)), "/health")
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{version}");
+            assert!(response.headers().get(API_VERSION_HEADER).is_none());
+            assert_eq!(body(response).await, "ok");
+        }
+    }
+}
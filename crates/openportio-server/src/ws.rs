@@ -0,0 +1,644 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    net::IpAddr,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use axum::{
+    extract::ws::{CloseFrame, Message},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures_util::{Sink, SinkExt};
+use openportio_core::MetricsSink;
+use tokio::{
+    sync::{
+        mpsc::{self, error::TrySendError},
+        watch, Notify,
+    },
+    task::JoinHandle,
+};
+
+use crate::api::ApiErrorResponse;
+
+const DEFAULT_OUTBOUND_QUEUE_CAPACITY: usize = 64;
+const DEFAULT_SLOW_CONSUMER_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_SHUTDOWN_DRAIN_SECS: u64 = 5;
+/// `1001 Going Away`: the endpoint is going away, e.g. a server restart.
+pub const CLOSE_GOING_AWAY: u16 = 1001;
+
+/// What a full outbound queue does to the message being sent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WsOverflowPolicy {
+    /// Drop the new message and keep the connection (`ws.messages.dropped`).
+    #[default]
+    DropNewest,
+    /// Treat the client as a slow consumer and close the connection.
+    Close,
+}
+
+impl FromStr for WsOverflowPolicy {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "drop" | "drop_newest" => Ok(Self::DropNewest),
+            "close" => Ok(Self::Close),
+            other => Err(format!(
+                "unknown websocket overflow policy `{other}`; expected drop or close"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WsLimits {
+    pub max_connections: Option<usize>,
+    /// Per resolved client address (see `client_ip`).
+    pub max_connections_per_client: Option<usize>,
+    pub outbound_queue_capacity: usize,
+    pub overflow_policy: WsOverflowPolicy,
+    /// A single frame write taking longer than this marks the client as a slow consumer.
+    pub slow_consumer_timeout: Duration,
+}
+
+impl Default for WsLimits {
+    fn default() -> Self {
+        Self {
+            max_connections: None,
+            max_connections_per_client: None,
+            outbound_queue_capacity: DEFAULT_OUTBOUND_QUEUE_CAPACITY,
+            overflow_policy: WsOverflowPolicy::DropNewest,
+            slow_consumer_timeout: Duration::from_secs(DEFAULT_SLOW_CONSUMER_TIMEOUT_SECS),
+        }
+    }
+}
+
+impl WsLimits {
+    pub fn from_env() -> Result<Self, String> {
+        let defaults = Self::default();
+        Ok(Self {
+            max_connections: crate::env::setting("WS_MAX_CONNECTIONS")?,
+            max_connections_per_client: crate::env::setting("WS_MAX_CONNECTIONS_PER_CLIENT")?,
+            outbound_queue_capacity: crate::env::setting::<usize>("WS_OUTBOUND_QUEUE")?
+                .filter(|capacity| *capacity > 0)
+                .unwrap_or(defaults.outbound_queue_capacity),
+            overflow_policy: crate::env::setting("WS_OVERFLOW_POLICY")?.unwrap_or_default(),
+            slow_consumer_timeout: crate::env::setting::<u64>("WS_SLOW_CONSUMER_TIMEOUT_SECS")?
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.slow_consumer_timeout),
+        })
+    }
+}
+
+/// Close frame sent to every open socket on server shutdown, and how long to wait for clients to
+/// disconnect before the server stops.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WsShutdownConfig {
+    pub close_code: u16,
+    pub reason: String,
+    pub drain_window: Duration,
+}
+
+impl Default for WsShutdownConfig {
+    fn default() -> Self {
+        Self {
+            close_code: CLOSE_GOING_AWAY,
+            reason: "server shutting down".to_string(),
+            drain_window: Duration::from_secs(DEFAULT_SHUTDOWN_DRAIN_SECS),
+        }
+    }
+}
+
+impl WsShutdownConfig {
+    pub fn from_env() -> Result<Self, String> {
+        let defaults = Self::default();
+        Ok(Self {
+            close_code: crate::env::setting("WS_SHUTDOWN_CLOSE_CODE")?
+                .unwrap_or(defaults.close_code),
+            reason: crate::env::setting("WS_SHUTDOWN_REASON")?.unwrap_or(defaults.reason),
+            drain_window: crate::env::setting::<u64>("WS_SHUTDOWN_DRAIN_SECS")?
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.drain_window),
+        })
+    }
+}
+
+/// Server-wide registry of open WebSocket sessions. [`Self::close_all`] sends the configured
+/// close frame through every outbound writer, refuses new upgrades (`503 ws_shutting_down`) and
+/// waits up to the drain window for the sessions to end. `OpenportioServer` calls it while
+/// draining; gates share it via [`WsGate::with_shutdown`]. Clones share state.
+#[derive(Clone)]
+pub struct WsShutdown {
+    config: Arc<WsShutdownConfig>,
+    signal: Arc<watch::Sender<bool>>,
+    open: Arc<AtomicUsize>,
+    idle: Arc<Notify>,
+}
+
+impl WsShutdown {
+    pub fn new(config: WsShutdownConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            signal: Arc::new(watch::channel(false).0),
+            open: Arc::new(AtomicUsize::new(0)),
+            idle: Arc::new(Notify::new()),
+        }
+    }
+
+    pub fn config(&self) -> &WsShutdownConfig {
+        &self.config
+    }
+
+    pub fn open_sockets(&self) -> usize {
+        self.open.load(Ordering::SeqCst)
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        *self.signal.borrow()
+    }
+
+    /// Broadcasts the close frame and resolves once every session has ended or the drain window
+    /// has passed; returns the number of sessions still open.
+    pub async fn close_all(&self) -> usize {
+        self.signal.send_replace(true);
+        let open = self.open_sockets();
+        if open == 0 {
+            return 0;
+        }
+        tracing::info!(
+            open_sockets = open,
+            close_code = self.config.close_code,
+            "closing websocket sessions"
+        );
+        let drained = tokio::time::timeout(self.config.drain_window, async {
+            loop {
+                let idle = self.idle.notified();
+                if self.open_sockets() == 0 {
+                    break;
+                }
+                idle.await;
+            }
+        })
+        .await;
+        let remaining = self.open_sockets();
+        if drained.is_err() {
+            tracing::warn!(
+                open_sockets = remaining,
+                "websocket drain window elapsed; remaining sessions will be dropped"
+            );
+        }
+        remaining
+    }
+
+    fn opened(&self) {
+        self.open.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn closed(&self) {
+        if self.open.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.idle.notify_waiters();
+        }
+    }
+
+    fn close_frame(&self) -> Message {
+        Message::Close(Some(CloseFrame {
+            code: self.config.close_code,
+            reason: Cow::Owned(self.config.reason.clone()),
+        }))
+    }
+}
+
+impl Default for WsShutdown {
+    fn default() -> Self {
+        Self::new(WsShutdownConfig::default())
+    }
+}
+
+#[derive(Default)]
+struct ConnectionCounts {
+    total: usize,
+    per_client: HashMap<Option<IpAddr>, usize>,
+}
+
+/// Admission control for WebSocket upgrades plus the metrics the WS helpers report through:
+/// `ws.connections.opened`, `ws.connections.rejected{reason}`, the `ws.connections.active` gauge,
+/// `ws.messages.dropped` and `ws.slow_consumers{reason}`. Clones share the counts.
+#[derive(Clone)]
+pub struct WsGate {
+    limits: WsLimits,
+    counts: Arc<Mutex<ConnectionCounts>>,
+    metrics: Arc<dyn MetricsSink>,
+    shutdown: WsShutdown,
+}
+
+impl WsGate {
+    pub fn new(limits: WsLimits, metrics: Arc<dyn MetricsSink>) -> Self {
+        Self {
+            limits,
+            counts: Arc::new(Mutex::new(ConnectionCounts::default())),
+            metrics,
+            shutdown: WsShutdown::default(),
+        }
+    }
+
+    /// Registers this gate's sessions with the server's [`WsShutdown`]
+    /// (`OpenportioServer::ws_shutdown`, or `Extension<WsShutdown>` in handlers).
+    pub fn with_shutdown(mut self, shutdown: WsShutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    pub fn limits(&self) -> &WsLimits {
+        &self.limits
+    }
+
+    pub fn active_connections(&self) -> usize {
+        self.counts.lock().expect("ws counts lock").total
+    }
+
+    /// Reserves a connection slot; call before `WebSocketUpgrade::on_upgrade` and keep the permit
+    /// alive for the whole session.
+    pub fn try_acquire(&self, client: Option<IpAddr>) -> Result<WsPermit, WsRejection> {
+        if self.shutdown.is_shutting_down() {
+            return Err(self.reject(WsRejection::ShuttingDown));
+        }
+        let mut counts = self.counts.lock().expect("ws counts lock");
+        if self
+            .limits
+            .max_connections
+            .is_some_and(|max| counts.total >= max)
+        {
+            drop(counts);
+            return Err(self.reject(WsRejection::AtCapacity));
+        }
+        let per_client = counts.per_client.get(&client).copied().unwrap_or(0);
+        if self
+            .limits
+            .max_connections_per_client
+            .is_some_and(|max| per_client >= max)
+        {
+            drop(counts);
+            return Err(self.reject(WsRejection::ClientLimit));
+        }
+        counts.total += 1;
+        *counts.per_client.entry(client).or_insert(0) += 1;
+        let total = counts.total;
+        drop(counts);
+
+        self.shutdown.opened();
+        self.metrics.incr_counter("ws.connections.opened");
+        self.metrics
+            .set_gauge("ws.connections.active", total as f64, &[]);
+        Ok(WsPermit {
+            gate: self.clone(),
+            client,
+        })
+    }
+
+    /// Outbound queue for an upgraded socket's sink, honoring the gate's queue capacity,
+    /// overflow policy and slow-consumer timeout.
+    pub fn outbound<S>(&self, sink: S) -> (WsOutbound, JoinHandle<()>)
+    where
+        S: Sink<Message> + Unpin + Send + 'static,
+    {
+        WsOutbound::spawn(
+            sink,
+            self.limits,
+            self.metrics.clone(),
+            self.shutdown.clone(),
+        )
+    }
+
+    fn reject(&self, rejection: WsRejection) -> WsRejection {
+        self.metrics
+            .incr_counter_with("ws.connections.rejected", &[("reason", rejection.code())]);
+        rejection
+    }
+
+    fn release(&self, client: Option<IpAddr>) {
+        let mut counts = self.counts.lock().expect("ws counts lock");
+        counts.total = counts.total.saturating_sub(1);
+        if let Some(count) = counts.per_client.get_mut(&client) {
+            *count -= 1;
+            if *count == 0 {
+                counts.per_client.remove(&client);
+            }
+        }
+        let total = counts.total;
+        drop(counts);
+        self.shutdown.closed();
+        self.metrics
+            .set_gauge("ws.connections.active", total as f64, &[]);
+    }
+}
+
+/// Held for the lifetime of a WebSocket session; dropping it frees the slot.
+pub struct WsPermit {
+    gate: WsGate,
+    client: Option<IpAddr>,
+}
+
+impl Drop for WsPermit {
+    fn drop(&mut self) {
+        self.gate.release(self.client);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WsRejection {
+    /// Global connection cap reached: `503 ws_capacity`.
+    AtCapacity,
+    /// Per-client cap reached: `429 ws_client_limit`.
+    ClientLimit,
+    /// The server is shutting down: `503 ws_shutting_down`.
+    ShuttingDown,
+}
+
+impl WsRejection {
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::AtCapacity => "ws_capacity",
+            Self::ClientLimit => "ws_client_limit",
+            Self::ShuttingDown => "ws_shutting_down",
+        }
+    }
+}
+
+impl IntoResponse for WsRejection {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            Self::AtCapacity => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "websocket connection limit reached",
+            ),
+            Self::ClientLimit => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "too many websocket connections from this client",
+            ),
+            Self::ShuttingDown => (StatusCode::SERVICE_UNAVAILABLE, "server is shutting down"),
+        };
+        (
+            status,
+            Json(ApiErrorResponse {
+                code: self.code().to_string(),
+                message: message.to_string(),
+                detail: None,
+                details: None,
+            }),
+        )
+            .into_response()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WsSendError {
+    /// The queue was full and the message was dropped (`DropNewest`).
+    Dropped,
+    /// The connection is closed, or was closed because the client could not keep up.
+    Closed,
+}
+
+/// Bounded, non-blocking sender feeding a writer task that owns the socket sink. Producers never
+/// wait on a slow client: a full queue applies the overflow policy instead.
+#[derive(Clone)]
+pub struct WsOutbound {
+    tx: mpsc::Sender<Message>,
+    shutdown: Arc<Notify>,
+    policy: WsOverflowPolicy,
+    metrics: Arc<dyn MetricsSink>,
+}
+
+impl WsOutbound {
+    fn spawn<S>(
+        mut sink: S,
+        limits: WsLimits,
+        metrics: Arc<dyn MetricsSink>,
+        server_shutdown: WsShutdown,
+    ) -> (Self, JoinHandle<()>)
+    where
+        S: Sink<Message> + Unpin + Send + 'static,
+    {
+        let (tx, mut rx) = mpsc::channel::<Message>(limits.outbound_queue_capacity.max(1));
+        let shutdown = Arc::new(Notify::new());
+        let writer_shutdown = shutdown.clone();
+        let writer_metrics = metrics.clone();
+        let mut server_signal = server_shutdown.signal.subscribe();
+        let writer = tokio::spawn(async move {
+            loop {
+                let message = tokio::select! {
+                    message = rx.recv() => match message {
+                        Some(message) => message,
+                        None => break,
+                    },
+                    _ = writer_shutdown.notified() => break,
+                    _ = async { server_signal.wait_for(|closing| *closing).await.map(drop) } => {
+                        let close = server_shutdown.close_frame();
+                        let _ = tokio::time::timeout(limits.slow_consumer_timeout, sink.send(close))
+                            .await;
+                        break;
+                    }
+                };
+                match tokio::time::timeout(limits.slow_consumer_timeout, sink.send(message)).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(_)) => return,
+                    Err(_) => {
+                        tracing::warn!("websocket client is not reading; closing slow consumer");
+                        writer_metrics
+                            .incr_counter_with("ws.slow_consumers", &[("reason", "send_timeout")]);
+                        break;
+                    }
+                }
+            }
+            let _ = tokio::time::timeout(limits.slow_consumer_timeout, sink.close()).await;
+        });
+        (
+            Self {
+                tx,
+                shutdown,
+                policy: limits.overflow_policy,
+                metrics,
+            },
+            writer,
+        )
+    }
+
+    pub fn send(&self, message: Message) -> Result<(), WsSendError> {
+        match self.tx.try_send(message) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Closed(_)) => Err(WsSendError::Closed),
+            Err(TrySendError::Full(_)) => match self.policy {
+                WsOverflowPolicy::DropNewest => {
+                    self.metrics.incr_counter("ws.messages.dropped");
+                    Err(WsSendError::Dropped)
+                }
+                WsOverflowPolicy::Close => {
+                    tracing::warn!("websocket outbound queue full; closing slow consumer");
+                    self.metrics
+                        .incr_counter_with("ws.slow_consumers", &[("reason", "queue_full")]);
+                    self.close();
+                    Err(WsSendError::Closed)
+                }
+            },
+        }
+    }
+
+    /// Stops the writer task, which then closes the sink; queued messages are discarded.
+    pub fn close(&self) {
+        self.shutdown.notify_one();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::sink;
+    use openportio_test::RecordingMetrics;
+
+    #[test]
+    fn gate_enforces_global_and_per_client_caps() {
+        let metrics = Arc::new(RecordingMetrics::default());
+        let gate = WsGate::new(
+            WsLimits {
+                max_connections: Some(3),
+                max_connections_per_client: Some(2),
+                ..WsLimits::default()
+            },
+            metrics.clone(),
+        );
+        let a: Option<IpAddr> = Some("203.0.113.1".parse().unwrap());
+        let b: Option<IpAddr> = Some("203.0.113.2".parse().unwrap());
+
+        let first = gate.try_acquire(a).expect("first slot");
+        let _second = gate.try_acquire(a).expect("second slot");
+        assert_eq!(gate.try_acquire(a).err(), Some(WsRejection::ClientLimit));
+        let _third = gate.try_acquire(b).expect("other client");
+        assert_eq!(gate.try_acquire(b).err(), Some(WsRejection::AtCapacity));
+        assert_eq!(gate.active_connections(), 3);
+
+        drop(first);
+        assert_eq!(gate.active_connections(), 2);
+        assert!(gate.try_acquire(a).is_ok());
+        assert_eq!(metrics.count("ws.connections.opened"), 4);
+        assert_eq!(
+            metrics.count("ws.connections.rejected{reason=ws_client_limit}"),
+            1
+        );
+        assert_eq!(
+            metrics.count("ws.connections.rejected{reason=ws_capacity}"),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn full_queue_drops_or_closes_and_stalled_writes_flag_slow_consumers() {
+        let metrics = Arc::new(RecordingMetrics::default());
+        let limits = WsLimits {
+            outbound_queue_capacity: 1,
+            slow_consumer_timeout: Duration::from_millis(20),
+            ..WsLimits::default()
+        };
+        // A sink that never accepts a frame stands in for a client that stopped reading.
+        let stalled = || {
+            sink::unfold((), |(), _: Message| {
+                std::future::pending::<Result<(), ()>>()
+            })
+        };
+
+        let gate = WsGate::new(limits, metrics.clone());
+        let (outbound, writer) = gate.outbound(Box::pin(stalled()));
+        outbound.send(Message::Text("first".into())).unwrap();
+        tokio::task::yield_now().await;
+        outbound.send(Message::Text("queued".into())).unwrap();
+        assert_eq!(
+            outbound.send(Message::Text("overflow".into())),
+            Err(WsSendError::Dropped)
+        );
+        assert_eq!(metrics.count("ws.messages.dropped"), 1);
+
+        writer.await.unwrap();
+        assert_eq!(metrics.count("ws.slow_consumers{reason=send_timeout}"), 1);
+        assert_eq!(
+            outbound.send(Message::Text("late".into())),
+            Err(WsSendError::Closed)
+        );
+
+        let gate = WsGate::new(
+            WsLimits {
+                overflow_policy: WsOverflowPolicy::Close,
+                ..limits
+            },
+            metrics.clone(),
+        );
+        let (outbound, writer) = gate.outbound(Box::pin(stalled()));
+        outbound.send(Message::Text("first".into())).unwrap();
+        tokio::task::yield_now().await;
+        outbound.send(Message::Text("queued".into())).unwrap();
+        assert_eq!(
+            outbound.send(Message::Text("overflow".into())),
+            Err(WsSendError::Closed)
+        );
+        assert_eq!(metrics.count("ws.slow_consumers{reason=queue_full}"), 1);
+        writer.await.unwrap();
+        assert!(outbound.is_closed());
+    }
+
+    #[tokio::test]
+    async fn shutdown_sends_the_close_frame_and_waits_for_sessions_to_end() {
+        let shutdown = WsShutdown::new(WsShutdownConfig {
+            reason: "deploying".to_string(),
+            drain_window: Duration::from_secs(5),
+            ..WsShutdownConfig::default()
+        });
+        let gate = WsGate::new(WsLimits::default(), Arc::new(RecordingMetrics::default()))
+            .with_shutdown(shutdown.clone());
+        let permit = gate.try_acquire(None).expect("slot");
+        let frames = Arc::new(Mutex::new(Vec::new()));
+        let (outbound, writer) = gate.outbound(Box::pin(sink::unfold(
+            frames.clone(),
+            |frames, message: Message| async move {
+                frames.lock().unwrap().push(message);
+                Ok::<_, ()>(frames)
+            },
+        )));
+        outbound.send(Message::Text("hello".into())).unwrap();
+        while frames.lock().unwrap().is_empty() {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(shutdown.open_sockets(), 1);
+
+        let closing = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { shutdown.close_all().await }
+        });
+        writer.await.unwrap();
+        let frames = frames.lock().unwrap().clone();
+        assert_eq!(frames[0], Message::Text("hello".into()));
+        match &frames[1] {
+            Message::Close(Some(frame)) => {
+                assert_eq!(frame.code, CLOSE_GOING_AWAY);
+                assert_eq!(frame.reason, "deploying");
+            }
+            other => panic!("expected a close frame, got {other:?}"),
+        }
+        assert_eq!(
+            gate.try_acquire(None).err(),
+            Some(WsRejection::ShuttingDown)
+        );
+
+        drop(permit);
+        assert_eq!(closing.await.unwrap(), 0);
+    }
+}
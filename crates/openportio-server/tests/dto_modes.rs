@@ -1,5 +1,6 @@
 use axum::Json;
 use openportio_server::api::RequestValidation;
+use openportio_server::api_docs;
 use openportio_server::utoipa::OpenApi;
 use serde_json::Value;
 
@@ -12,6 +13,7 @@ struct LegacyDto {
 #[derive(
     openportio_server::serde::Deserialize,
     openportio_server::OpenPortIOValidate,
+    openportio_server::RequestValidation,
     openportio_server::OpenPortIOSchema,
 )]
 struct ComposableDto {
@@ -22,6 +24,7 @@ struct ComposableDto {
 #[derive(
     openportio_server::serde::Deserialize,
     openportio_server::MeldValidate,
+    openportio_server::RequestValidation,
     openportio_server::MeldSchema,
 )]
 struct MeldAliasDto {
@@ -29,10 +32,20 @@ struct MeldAliasDto {
     title: String,
 }
 
+#[openportio_server::dto]
+struct UnlistedDto {
+    #[validate(range(min = 1))]
+    quantity: u32,
+}
+
 #[derive(OpenApi)]
 #[openapi(components(schemas(LegacyDto, ComposableDto, MeldAliasDto)))]
 struct DtoModesApiDoc;
 
+#[derive(OpenApi)]
+#[openapi(info(title = "registry"))]
+struct EmptyApiDoc;
+
 #[test]
 fn dto_macro_and_composable_derives_match_validation_contract() {
     let legacy = LegacyDto {
@@ -106,6 +119,33 @@ fn dto_macro_and_composable_derives_match_schema_shape() {
     assert_eq!(legacy_schema, meld_alias_schema);
 }
 
+#[test]
+fn dto_macro_registers_schemas_for_served_documents() {
+    let registered: Vec<String> = api_docs::registered_dto_schemas()
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    assert_eq!(registered, ["LegacyDto", "UnlistedDto"]);
+
+    let doc = api_docs::with_dto_schemas(EmptyApiDoc::openapi());
+    let schemas = doc.components.expect("registered components").schemas;
+    assert!(schemas.contains_key("UnlistedDto"));
+    assert!(schemas.contains_key("LegacyDto"));
+
+    // Schemas the document declares itself are kept as they are.
+    let declared = DtoModesApiDoc::openapi();
+    let merged = api_docs::with_dto_schemas(declared.clone());
+    assert_eq!(
+        serde_json::to_value(&merged.components.as_ref().unwrap().schemas["LegacyDto"]).unwrap(),
+        serde_json::to_value(&declared.components.as_ref().unwrap().schemas["LegacyDto"]).unwrap(),
+    );
+    assert!(merged
+        .components
+        .unwrap()
+        .schemas
+        .contains_key("UnlistedDto"));
+}
+
 fn strip_titles(value: &mut Value) {
     match value {
         Value::Object(map) => {
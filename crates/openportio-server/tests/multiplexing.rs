@@ -4,15 +4,26 @@ use axum::http::header;
 use futures_util::{SinkExt, StreamExt};
 use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
 use openportio_core::AppState;
-use openportio_rpc::{GreeterClient, HelloRequest};
+use openportio_rpc::{
+    Greeter, GreeterClient, GreeterServer, HelloRequest, HelloResponse, HelloStreamRequest,
+};
 use openportio_server::{
-    auth::AuthRuntimeConfig, build_multiplexed_router, build_multiplexed_router_with_auth,
-    middleware, OpenportioServer,
+    auth::AuthRuntimeConfig,
+    build_multiplexed_router, build_multiplexed_router_with_auth,
+    di::GrpcContext,
+    grpc::{GreeterService, GrpcAuthInterceptor, GrpcAuthService},
+    middleware,
+    policy::{require_policy, PolicyRule, RulePolicy},
+    prometheus::{PrometheusMetrics, METRICS_PATH},
+    transport::Http2Config,
+    OpenportioServer,
 };
+use openportio_test::RecordingMetrics;
 use tokio::net::TcpListener;
 use tokio::sync::oneshot;
 use tokio_tungstenite::tungstenite::Message;
 use tonic::metadata::MetadataValue;
+use tower::Layer;
 
 #[tokio::test]
 async fn serves_rest_and_grpc_on_single_port() {
@@ -198,6 +209,20 @@ async fn serves_rest_and_grpc_on_single_port() {
 
     assert_eq!(grpc_response.message, "Hello, Rust!");
 
+    let mut greetings = grpc_client
+        .say_hello_stream(tonic::Request::new(HelloStreamRequest {
+            name: "Rust".to_string(),
+            count: 3,
+        }))
+        .await
+        .expect("grpc stream call should succeed")
+        .into_inner();
+    let mut streamed = Vec::new();
+    while let Some(greeting) = greetings.message().await.expect("stream item") {
+        streamed.push(greeting.message);
+    }
+    assert_eq!(streamed, ["Hello, Rust!"; 3]);
+
     let _ = shutdown_tx.send(());
     let _ = server.await;
 }
@@ -284,6 +309,215 @@ async fn grpc_auth_interceptor_rejects_missing_token_and_accepts_valid_token() {
     let _ = server.await;
 }
 
+#[tokio::test]
+async fn grpc_calls_are_recorded_with_status_codes() {
+    let metrics = Arc::new(RecordingMetrics::default());
+    let state = Arc::new(AppState::new(
+        openportio_core::AppConfig::local("multiplexing-metrics-test"),
+        Arc::new(openportio_core::StaticGreetingEngine::new("Hello")),
+        metrics.clone(),
+    ));
+    let mut auth_cfg = AuthRuntimeConfig::default();
+    auth_cfg.enabled = false;
+    let app = build_multiplexed_router_with_auth(state, auth_cfg);
+    for path in ["/openportio.v1.Greeter/MadeUp", "/made.up.Service/Call"] {
+        use tower::ServiceExt;
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::builder()
+                    .method("POST")
+                    .uri(path)
+                    .header(header::CONTENT_TYPE, "application/grpc")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["grpc-status"], "12");
+    }
+    let listener = TcpListener::bind(("127.0.0.1", 0))
+        .await
+        .expect("bind test listener");
+    let addr = listener.local_addr().expect("listener addr");
+
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server = tokio::spawn(async move {
+        axum::serve(listener, app)
+            .with_graceful_shutdown(async {
+                let _ = shutdown_rx.await;
+            })
+            .await
+            .expect("server should run");
+    });
+
+    let mut grpc_client = GreeterClient::connect(format!("http://{addr}"))
+        .await
+        .expect("grpc client connect");
+    grpc_client
+        .say_hello(tonic::Request::new(HelloRequest {
+            name: "Rust".to_string(),
+        }))
+        .await
+        .expect("grpc hello should succeed");
+    let invalid = grpc_client
+        .say_hello(tonic::Request::new(HelloRequest {
+            name: " ".to_string(),
+        }))
+        .await
+        .expect_err("blank name should fail");
+    assert_eq!(invalid.code(), tonic::Code::InvalidArgument);
+
+    let _ = shutdown_tx.send(());
+    let _ = server.await;
+
+    let recorded = metrics.counters();
+    assert!(recorded.contains(
+        &"grpc.requests{service=openportio.v1.Greeter,method=SayHello,code=Ok}".to_string()
+    ));
+    assert!(recorded.contains(
+        &"grpc.requests{service=openportio.v1.Greeter,method=SayHello,code=InvalidArgument}"
+            .to_string()
+    ));
+    // Paths no registered service or method answers don't become label values.
+    assert!(recorded.contains(
+        &"grpc.requests{service=openportio.v1.Greeter,method=unmatched,code=Unimplemented}"
+            .to_string()
+    ));
+    assert!(recorded.contains(
+        &"grpc.requests{service=unmatched,method=unmatched,code=Unimplemented}".to_string()
+    ));
+    assert!(!recorded
+        .iter()
+        .any(|entry| entry.contains("MadeUp") || entry.contains("made.up")));
+}
+
+#[derive(Clone)]
+struct Salutation(&'static str);
+
+struct ContextGreeter;
+
+#[tonic::async_trait]
+impl Greeter for ContextGreeter {
+    async fn say_hello(
+        &self,
+        request: tonic::Request<HelloRequest>,
+    ) -> Result<tonic::Response<HelloResponse>, tonic::Status> {
+        let ctx = GrpcContext::from_request(&request);
+        let Salutation(salutation) = ctx.depends::<Salutation>()?;
+        Ok(tonic::Response::new(HelloResponse {
+            message: format!("{salutation}, {}!", request.into_inner().name),
+        }))
+    }
+
+    type SayHelloStreamStream = tonic::codegen::BoxStream<HelloResponse>;
+
+    async fn say_hello_stream(
+        &self,
+        _request: tonic::Request<HelloStreamRequest>,
+    ) -> Result<tonic::Response<Self::SayHelloStreamStream>, tonic::Status> {
+        Err(tonic::Status::unimplemented("not used"))
+    }
+}
+
+#[tokio::test]
+async fn grpc_handlers_resolve_builder_dependencies() {
+    let app = OpenportioServer::new()
+        .without_grpc()
+        .with_grpc_service(GreeterServer::new(ContextGreeter))
+        .with_dependency(Salutation("Hola"))
+        .build_app()
+        .expect("app should build");
+    let listener = TcpListener::bind(("127.0.0.1", 0))
+        .await
+        .expect("bind test listener");
+    let addr = listener.local_addr().expect("listener addr");
+
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server = tokio::spawn(async move {
+        axum::serve(listener, app)
+            .with_graceful_shutdown(async {
+                let _ = shutdown_rx.await;
+            })
+            .await
+            .expect("server should run");
+    });
+
+    let mut grpc_client = GreeterClient::connect(format!("http://{addr}"))
+        .await
+        .expect("grpc client connect");
+    let response = grpc_client
+        .say_hello(tonic::Request::new(HelloRequest {
+            name: "Rust".to_string(),
+        }))
+        .await
+        .expect("grpc hello should succeed")
+        .into_inner();
+    assert_eq!(response.message, "Hola, Rust!");
+
+    let _ = shutdown_tx.send(());
+    let _ = server.await;
+}
+
+#[tokio::test]
+async fn grpc_policy_guard_checks_authenticated_principal() {
+    let state = Arc::new(AppState::local("multiplexing-policy-test"));
+    let mut auth_cfg = AuthRuntimeConfig::default();
+    auth_cfg.enabled = true;
+    auth_cfg.jwt_secret = Some("dev-secret".to_string());
+    auth_cfg.expected_issuer = Some("https://issuer.local".to_string());
+    auth_cfg.expected_audience = Some("openportio-api".to_string());
+    let greeter = require_policy("greeter:read")
+        .with_method_policy("SayHello", "greeter:hello")
+        .layer(GreeterServer::new(GreeterService::new(state)));
+    let app = OpenportioServer::new()
+        .without_grpc()
+        .with_grpc_service(GrpcAuthService::new(
+            greeter,
+            GrpcAuthInterceptor::new(auth_cfg),
+        ))
+        .with_policy(
+            RulePolicy::new().rule(PolicyRule::allow("greeter:hello").for_subject("admin")),
+        )
+        .build_app()
+        .expect("app should build");
+    let listener = TcpListener::bind(("127.0.0.1", 0))
+        .await
+        .expect("bind test listener");
+    let addr = listener.local_addr().expect("listener addr");
+
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server = tokio::spawn(async move {
+        axum::serve(listener, app)
+            .with_graceful_shutdown(async {
+                let _ = shutdown_rx.await;
+            })
+            .await
+            .expect("server should run");
+    });
+
+    let mut grpc_client = GreeterClient::connect(format!("http://{addr}"))
+        .await
+        .expect("grpc client connect");
+    let mut request = tonic::Request::new(HelloRequest {
+        name: "Rust".to_string(),
+    });
+    request.metadata_mut().insert(
+        "authorization",
+        MetadataValue::try_from(format!("Bearer {}", issue_test_token("dev-secret")))
+            .expect("metadata value"),
+    );
+    let denied = grpc_client
+        .say_hello(request)
+        .await
+        .expect_err("user-1 is not allowed by the policy");
+    assert_eq!(denied.code(), tonic::Code::PermissionDenied);
+    assert_eq!(denied.message(), "policy denies `greeter:hello`");
+
+    let _ = shutdown_tx.send(());
+    let _ = server.await;
+}
+
 async fn reserve_local_addr() -> std::net::SocketAddr {
     let listener = TcpListener::bind(("127.0.0.1", 0))
         .await
@@ -358,3 +592,187 @@ async fn serves_rest_and_grpc_on_explicit_dual_ports() {
         })
         .await;
 }
+
+#[tokio::test]
+async fn serves_grpc_with_tuned_http2_settings() {
+    let grpc_addr = reserve_local_addr().await;
+    let local = tokio::task::LocalSet::new();
+
+    local
+        .run_until(async move {
+            let server = tokio::task::spawn_local(async move {
+                OpenportioServer::new()
+                    .with_state(Arc::new(AppState::local("http2-tuning-test")))
+                    .with_addr(grpc_addr)
+                    .with_http2_config(Http2Config {
+                        max_concurrent_streams: Some(64),
+                        initial_stream_window_size: Some(1 << 20),
+                        initial_connection_window_size: Some(1 << 22),
+                        max_frame_size: Some(1 << 16),
+                        h2c_prior_knowledge_only: true,
+                        ..Http2Config::long_lived_streaming()
+                    })
+                    .run()
+                    .await
+                    .expect("tuned server should run");
+            });
+
+            let grpc_base_url = format!("http://{grpc_addr}");
+            let mut grpc_client = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+                loop {
+                    match GreeterClient::connect(grpc_base_url.clone()).await {
+                        Ok(client) => break client,
+                        Err(_) => tokio::time::sleep(std::time::Duration::from_millis(50)).await,
+                    }
+                }
+            })
+            .await
+            .expect("grpc listener should become reachable in time");
+
+            let grpc_response = grpc_client
+                .say_hello(tonic::Request::new(HelloRequest {
+                    name: "Tuned".to_string(),
+                }))
+                .await
+                .expect("grpc call should succeed over h2c")
+                .into_inner();
+            assert_eq!(grpc_response.message, "Hello, Tuned!");
+
+            server.abort();
+            let _ = server.await;
+        })
+        .await;
+}
+
+struct MacroGreeter;
+
+#[openportio_server::grpc_service(descriptor = openportio_rpc::FILE_DESCRIPTOR_SET)]
+#[tonic::async_trait]
+impl Greeter for MacroGreeter {
+    async fn say_hello(
+        &self,
+        request: tonic::Request<HelloRequest>,
+    ) -> Result<tonic::Response<HelloResponse>, tonic::Status> {
+        ContextGreeter.say_hello(request).await
+    }
+
+    type SayHelloStreamStream = tonic::codegen::BoxStream<HelloResponse>;
+
+    async fn say_hello_stream(
+        &self,
+        request: tonic::Request<HelloStreamRequest>,
+    ) -> Result<tonic::Response<Self::SayHelloStreamStream>, tonic::Status> {
+        ContextGreeter.say_hello_stream(request).await
+    }
+}
+
+#[tokio::test]
+async fn grpc_service_macro_registers_service_with_auth_and_dependencies() {
+    let mut auth_cfg = AuthRuntimeConfig::default();
+    auth_cfg.jwt_secret = Some("dev-secret".to_string());
+    auth_cfg.expected_issuer = Some("https://issuer.local".to_string());
+    auth_cfg.expected_audience = Some("openportio-api".to_string());
+    let server = OpenportioServer::new()
+        .without_grpc()
+        .require_auth(auth_cfg)
+        .with_grpc(MacroGreeter)
+        .with_dependency(Salutation("Hola"));
+    let description = server.describe();
+    let greeter = description
+        .grpc_services
+        .iter()
+        .find(|service| service.name == "openportio.v1.Greeter")
+        .expect("greeter should be registered");
+    assert!(greeter.methods.iter().any(|method| method == "SayHello"));
+    let app = server.build_app().expect("app should build");
+    let listener = TcpListener::bind(("127.0.0.1", 0))
+        .await
+        .expect("bind test listener");
+    let addr = listener.local_addr().expect("listener addr");
+
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server = tokio::spawn(async move {
+        axum::serve(listener, app)
+            .with_graceful_shutdown(async {
+                let _ = shutdown_rx.await;
+            })
+            .await
+            .expect("server should run");
+    });
+
+    let mut grpc_client = GreeterClient::connect(format!("http://{addr}"))
+        .await
+        .expect("grpc client connect");
+    let missing = grpc_client
+        .say_hello(tonic::Request::new(HelloRequest {
+            name: "Rust".to_string(),
+        }))
+        .await
+        .expect_err("missing token should be rejected");
+    assert_eq!(missing.code(), tonic::Code::Unauthenticated);
+
+    let mut request = tonic::Request::new(HelloRequest {
+        name: "Rust".to_string(),
+    });
+    request.metadata_mut().insert(
+        "authorization",
+        MetadataValue::try_from(format!("Bearer {}", issue_test_token("dev-secret")))
+            .expect("metadata value"),
+    );
+    let response = grpc_client
+        .say_hello(request)
+        .await
+        .expect("grpc hello should succeed")
+        .into_inner();
+    assert_eq!(response.message, "Hola, Rust!");
+
+    let _ = shutdown_tx.send(());
+    let _ = server.await;
+}
+
+#[tokio::test]
+async fn default_grpc_calls_are_scraped_at_prometheus_metrics() {
+    let metrics = Arc::new(PrometheusMetrics::new());
+    let app = OpenportioServer::new()
+        .with_prometheus_metrics(metrics)
+        .build_app()
+        .expect("app should build");
+    let listener = TcpListener::bind(("127.0.0.1", 0))
+        .await
+        .expect("bind test listener");
+    let addr = listener.local_addr().expect("listener addr");
+
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server = tokio::spawn(async move {
+        axum::serve(listener, app)
+            .with_graceful_shutdown(async {
+                let _ = shutdown_rx.await;
+            })
+            .await
+            .expect("server should run");
+    });
+
+    let mut grpc_client = GreeterClient::connect(format!("http://{addr}"))
+        .await
+        .expect("grpc client connect");
+    grpc_client
+        .say_hello(tonic::Request::new(HelloRequest {
+            name: "Rust".to_string(),
+        }))
+        .await
+        .expect("grpc hello should succeed");
+
+    let body = reqwest::get(format!("http://{addr}{METRICS_PATH}"))
+        .await
+        .expect("metrics scrape should succeed")
+        .text()
+        .await
+        .expect("metrics body");
+    assert!(body.contains("# TYPE grpc_requests counter"), "{body}");
+    assert!(body.contains("SayHello"), "{body}");
+    // Recorded by the Greeter itself, through the state the routes were built from.
+    assert!(body.contains("# TYPE greet_requests counter"), "{body}");
+
+    let _ = shutdown_tx.send(());
+    let _ = server.await;
+}
@@ -0,0 +1,11 @@
+use std::sync::Arc;
+
+use openportio_core::AppState;
+use openportio_server::build_router;
+use openportio_test::assert_openapi_snapshot;
+
+#[tokio::test]
+async fn openapi_contract_matches_snapshot() {
+    let app = build_router(Arc::new(AppState::local("openapi-contract-test")));
+    assert_openapi_snapshot(app, "tests/snapshots/openapi.snap.json").await;
+}
@@ -0,0 +1,160 @@
+use axum::{body::Body, http::Request, http::StatusCode, routing::post, Json, Router};
+use openportio_server::api::{
+    ApiError, RequestValidation, ValidatedJson, ValidationRuleError, ValidationRuleResult,
+};
+use serde::Deserialize;
+use tower::util::ServiceExt;
+use validator::Validate;
+
+fn not_blank(value: &str) -> ValidationRuleResult {
+    match value.trim().is_empty() {
+        true => Err(ValidationRuleError::new("blank", "must not be blank")),
+        false => Ok(()),
+    }
+}
+
+fn positive(value: &i64) -> ValidationRuleResult {
+    match *value > 0 {
+        true => Ok(()),
+        false => Err(ValidationRuleError::new("not_positive", "must be positive")),
+    }
+}
+
+fn ends_after_start(booking: &Booking) -> ValidationRuleResult {
+    match booking.end > booking.start {
+        true => Ok(()),
+        false => Err(ValidationRuleError::new("date_order", "must be after `start`").at("end")),
+    }
+}
+
+fn not_reserved(booking: &Booking) -> ValidationRuleResult {
+    match booking.room.starts_with("admin") {
+        true => Err(ValidationRuleError::new("reserved", "room is reserved")),
+        false => Ok(()),
+    }
+}
+
+#[derive(Debug, Deserialize, openportio_server::RequestValidation)]
+#[validate_with(ends_after_start, not_reserved)]
+struct Booking {
+    #[validate_with(not_blank)]
+    #[serde(rename = "roomName")]
+    room: String,
+    #[validate_with(positive)]
+    start: i64,
+    end: i64,
+}
+
+#[test]
+fn derived_rules_report_every_violation_with_custom_issue_types() {
+    let booking = Booking {
+        room: "admin ".to_string(),
+        start: 0,
+        end: -1,
+    };
+    let Err((status, Json(body))) = booking.validate_request("body") else {
+        panic!("invalid booking must fail");
+    };
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body.code, "validation_error");
+    let issues: Vec<_> = body
+        .detail
+        .expect("detail should exist")
+        .into_iter()
+        .map(|issue| (issue.loc.join("."), issue.issue_type))
+        .collect();
+    assert_eq!(
+        issues,
+        [
+            ("body.start".to_string(), "not_positive".to_string()),
+            ("body.end".to_string(), "date_order".to_string()),
+            ("body".to_string(), "reserved".to_string()),
+        ]
+    );
+    assert_eq!(
+        body.details.expect("details")["end"][0],
+        "must be after `start`"
+    );
+
+    let valid = Booking {
+        room: "blue".to_string(),
+        start: 1,
+        end: 2,
+    };
+    assert!(valid.validate_request("body").is_ok());
+}
+
+#[derive(Debug, Deserialize, Validate, openportio_server::RequestValidation)]
+struct Room {
+    #[validate(length(min = 3))]
+    #[validate_with(not_blank)]
+    name: String,
+    #[validate(range(min = 1))]
+    #[validate_with(positive)]
+    seats: i64,
+}
+
+#[test]
+fn validator_and_derived_rules_combine_on_one_type() {
+    let room = Room {
+        name: " ".to_string(),
+        seats: 0,
+    };
+    let Err((_, Json(body))) = room.validate_request("body") else {
+        panic!("invalid room must fail");
+    };
+    let mut issues: Vec<_> = body
+        .detail
+        .expect("detail should exist")
+        .into_iter()
+        .map(|issue| (issue.loc.join("."), issue.issue_type))
+        .collect();
+    issues.sort();
+    assert_eq!(
+        issues,
+        [
+            ("body.name".to_string(), "blank".to_string()),
+            ("body.name".to_string(), "length".to_string()),
+            ("body.seats".to_string(), "not_positive".to_string()),
+            ("body.seats".to_string(), "range".to_string()),
+        ]
+    );
+
+    let room = Room {
+        name: "blue".to_string(),
+        seats: 4,
+    };
+    assert!(room.validate_request("body").is_ok());
+}
+
+async fn create_booking(
+    ValidatedJson(booking): ValidatedJson<Booking>,
+) -> Result<Json<String>, ApiError> {
+    Ok(Json(booking.room))
+}
+
+#[tokio::test]
+async fn validated_json_runs_derived_rules() {
+    let app = Router::new().route("/bookings", post(create_booking));
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/bookings")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"roomName":"  ","start":1,"end":2}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        body["detail"][0]["loc"],
+        serde_json::json!(["body", "roomName"])
+    );
+    assert_eq!(body["detail"][0]["type"], "blank");
+}
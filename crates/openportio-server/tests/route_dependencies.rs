@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::FromRef,
+    http::{Request, StatusCode},
+    routing::get,
+    Router,
+};
+use openportio_core::AppState;
+use openportio_server::{
+    di::{Depends, Lazy, Named},
+    OpenportioServer,
+};
+use tower::util::ServiceExt;
+
+openportio_server::qualifier!(Reporting = "reporting");
+
+#[derive(Debug, Clone)]
+struct ReportStore(&'static str);
+
+#[derive(Debug, Clone)]
+struct AuditLog;
+
+#[derive(Debug, Clone)]
+struct ServiceName(&'static str);
+
+impl FromRef<Arc<AppState>> for ServiceName {
+    fn from_ref(_state: &Arc<AppState>) -> Self {
+        Self("notes")
+    }
+}
+
+#[openportio_server::route(get, "/reports")]
+async fn reports(
+    Depends(service): Depends<ServiceName>,
+    store: Named<ReportStore, Reporting>,
+    audit: Lazy<AuditLog>,
+) -> String {
+    audit.get().await.expect("audit log resolves");
+    format!("{}:{}", service.0, store.into_inner().0)
+}
+
+fn server() -> OpenportioServer {
+    OpenportioServer::new().without_grpc().with_rest_router(
+        Router::new()
+            .route("/reports", get(reports))
+            .with_state(Arc::new(AppState::local("route-dependencies"))),
+    )
+}
+
+#[test]
+fn route_extractors_are_validated_without_declaring_them() {
+    let err = server()
+        .validate_dependencies()
+        .expect_err("the handler's extractors have no providers");
+    let missing: Vec<_> = err
+        .missing
+        .iter()
+        .map(|missing| (missing.consumer.as_str(), missing.dependency))
+        .collect();
+    assert_eq!(
+        missing,
+        [
+            (
+                "GET /reports",
+                std::any::type_name::<Named<ReportStore, Reporting>>()
+            ),
+            (
+                "GET /reports",
+                std::any::type_name::<openportio_server::di::LazyProvider<AuditLog>>()
+            ),
+        ]
+    );
+    assert!(server().build_app().is_err());
+}
+
+#[tokio::test]
+async fn route_extractors_are_satisfied_by_their_providers() {
+    server()
+        .with_named_dependency::<Reporting, _>(ReportStore("weekly"))
+        .with_lazy_dependency(|| async { Ok(AuditLog) })
+        .validate_dependencies()
+        .expect("Depends falls back to state and the others are registered");
+
+    // A plain `T` satisfies `Lazy<T>` as well.
+    let app = server()
+        .with_named_dependency::<Reporting, _>(ReportStore("weekly"))
+        .with_dependency(AuditLog)
+        .build_app()
+        .expect("app builds");
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/reports")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(&body[..], b"notes:weekly");
+}
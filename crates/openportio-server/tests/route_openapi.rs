@@ -0,0 +1,87 @@
+use axum::{extract::Path, Json};
+use openportio_server::utoipa::{self, OpenApi};
+use serde_json::{json, Value};
+
+/// Lists notes.
+#[openportio_server::route(get, "/notes", tag = "notes", operation_id = "listNotes")]
+async fn list_notes() -> Json<Value> {
+    Json(json!([]))
+}
+
+#[openportio_server::route(
+    delete,
+    "/notes/:id",
+    tag = "notes",
+    tag = "admin",
+    operation_id = "deleteNote",
+    deprecated
+)]
+async fn delete_note(Path(id): Path<i64>) -> Json<Value> {
+    Json(json!({ "deleted": id }))
+}
+
+#[openportio_server::route(post, "/notes", protected, scope = "notes:write")]
+async fn create_note() -> Json<Value> {
+    Json(json!({ "created": true }))
+}
+
+#[openportio_server::route(get, "/notes/export", hidden, protected)]
+async fn export_notes() -> Json<Value> {
+    Json(json!([]))
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(list_notes, delete_note))]
+struct NotesApi;
+
+#[derive(OpenApi)]
+#[openapi(paths(list_notes, create_note, export_notes))]
+struct SecuredNotesApi;
+
+#[test]
+fn route_flags_document_operation_ids_tags_and_deprecation() {
+    let doc = serde_json::to_value(NotesApi::openapi()).expect("openapi serializes");
+
+    let list = &doc["paths"]["/notes"]["get"];
+    assert_eq!(list["operationId"], "listNotes");
+    assert_eq!(list["tags"], json!(["notes"]));
+    assert_eq!(list["summary"], "Lists notes.");
+    assert!(list.get("deprecated").is_none());
+
+    let delete = &doc["paths"]["/notes/{id}"]["delete"];
+    assert_eq!(delete["operationId"], "deleteNote");
+    assert_eq!(delete["tags"], json!(["notes", "admin"]));
+    assert_eq!(delete["deprecated"], true);
+    assert_eq!(delete["parameters"][0]["name"], "id");
+    assert_eq!(delete["parameters"][0]["in"], "path");
+}
+
+#[tokio::test]
+async fn handlers_stay_callable_without_deprecation_warnings() {
+    assert_eq!(delete_note(Path(7)).await.0, json!({ "deleted": 7 }));
+    assert_eq!(list_notes().await.0, json!([]));
+    assert_eq!(create_note().await.0, json!({ "created": true }));
+    assert_eq!(export_notes().await.0, json!([]));
+}
+
+#[test]
+fn hidden_routes_leave_the_public_document_and_protected_routes_use_bearer_auth() {
+    let full = serde_json::to_value(SecuredNotesApi::openapi()).expect("openapi serializes");
+    assert_eq!(
+        full["paths"]["/notes/export"]["get"]["x-openportio-hidden"],
+        true
+    );
+
+    let public = openportio_server::api_docs::public_openapi(SecuredNotesApi::openapi());
+    let doc = serde_json::to_value(public).expect("openapi serializes");
+    assert!(doc["paths"].get("/notes/export").is_none());
+    assert!(doc["paths"]["/notes"]["get"].get("security").is_none());
+    assert_eq!(
+        doc["paths"]["/notes"]["post"]["security"],
+        json!([{ "bearerAuth": ["notes:write"] }])
+    );
+    assert_eq!(
+        doc["components"]["securitySchemes"]["bearerAuth"]["scheme"],
+        "bearer"
+    );
+}
@@ -0,0 +1,105 @@
+use axum::{
+    body::{to_bytes, Body},
+    http::{Request, StatusCode},
+    routing::get,
+    Router,
+};
+use openportio_server::api::{ApiErrorResponse, TypedPath};
+use tower::util::ServiceExt;
+
+#[derive(Debug, openportio_server::TypedPath)]
+struct NotePath {
+    owner: String,
+    #[path_param(rename = "noteId")]
+    id: u64,
+    version: Option<u32>,
+}
+
+#[derive(Debug, openportio_server::TypedPath)]
+struct AllOptional {
+    page: Option<u32>,
+}
+
+fn app() -> Router {
+    Router::new()
+        .route(
+            "/users/:owner/notes/:noteId",
+            get(|TypedPath(path): TypedPath<NotePath>| async move {
+                format!("{}/{}/{:?}", path.owner, path.id, path.version)
+            }),
+        )
+        .route(
+            "/users/:owner/notes/:noteId/versions/:version",
+            get(|TypedPath(path): TypedPath<NotePath>| async move {
+                format!("{}/{}/{:?}", path.owner, path.id, path.version)
+            }),
+        )
+        .route(
+            "/items/:id",
+            get(|TypedPath(id): TypedPath<u64>| async move { id.to_string() }),
+        )
+        .route(
+            "/pages",
+            get(
+                |TypedPath(path): TypedPath<AllOptional>| async move { format!("{:?}", path.page) },
+            ),
+        )
+}
+
+async fn call(uri: &str) -> (StatusCode, Vec<u8>) {
+    let response = app()
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, body.to_vec())
+}
+
+#[tokio::test]
+async fn typed_path_parses_fields_with_from_str() {
+    assert_eq!(
+        call("/users/ann/notes/7").await,
+        (StatusCode::OK, b"ann/7/None".to_vec())
+    );
+    assert_eq!(
+        call("/users/ann/notes/7/versions/2").await,
+        (StatusCode::OK, b"ann/7/Some(2)".to_vec())
+    );
+    assert_eq!(call("/items/42").await, (StatusCode::OK, b"42".to_vec()));
+    assert_eq!(call("/pages").await, (StatusCode::OK, b"None".to_vec()));
+}
+
+#[tokio::test]
+async fn parse_failures_become_validation_errors_at_the_field() {
+    let (status, body) = call("/users/ann/notes/abc/versions/-1").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let body: ApiErrorResponse = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body.code, "validation_error");
+    let issues: Vec<_> = body
+        .detail
+        .expect("detail should exist")
+        .into_iter()
+        .map(|issue| (issue.loc, issue.issue_type, issue.msg))
+        .collect();
+    assert_eq!(
+        issues,
+        [
+            (
+                vec!["path".to_string(), "noteId".to_string()],
+                "invalid_path".to_string(),
+                "invalid value `abc`: invalid digit found in string".to_string(),
+            ),
+            (
+                vec!["path".to_string(), "version".to_string()],
+                "invalid_path".to_string(),
+                "invalid value `-1`: invalid digit found in string".to_string(),
+            ),
+        ]
+    );
+
+    let (status, body) = call("/items/x").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let body: ApiErrorResponse = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body.detail.unwrap()[0].loc, ["path", "id"]);
+}
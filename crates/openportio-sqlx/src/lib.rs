@@ -0,0 +1,248 @@
+//! Request-scoped sqlx transactions. Register the pool as a dependency and take [`Tx`] in the
+//! handler; the transaction begins on extraction, commits when the response is a success and
+//! rolls back otherwise:
+//!
+//! ```ignore
+//! OpenportioServer::new().with_dependency(pool);
+//!
+//! async fn create_note(mut tx: PgTx, Json(body): Json<CreateNote>) -> Result<StatusCode, ApiError> {
+//!     sqlx::query("INSERT INTO notes (title) VALUES ($1)")
+//!         .bind(body.title)
+//!         .execute(&mut *tx)
+//!         .await?;
+//!     Ok(StatusCode::CREATED)
+//! }
+//! ```
+
+use std::{
+    fmt,
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
+
+use axum::{extract::FromRequestParts, http::request::Parts};
+use openportio_core::OpenportioError;
+use openportio_server::{
+    api::ApiError,
+    di::{self, internal_di_error, RequestOutcome, RequestTeardown},
+};
+use sqlx::{Database, Pool, Transaction};
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+#[cfg(feature = "postgres")]
+pub type PgTx = Tx<sqlx::Postgres>;
+#[cfg(feature = "sqlite")]
+pub type SqliteTx = Tx<sqlx::Sqlite>;
+
+type Slot<DB> = Arc<Mutex<Option<Transaction<'static, DB>>>>;
+
+/// The request's transaction, shared by every `Tx<DB>` extracted for it.
+struct RequestTx<DB: Database>(Slot<DB>);
+
+impl<DB: Database> Clone for RequestTx<DB> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+/// Extractor for the request's transaction on the registered `Pool<DB>` (resolved like
+/// `Depends<Pool<DB>>`). It dereferences to the connection, so queries run with
+/// `.execute(&mut *tx)`.
+///
+/// The first extraction begins the transaction and registers a [`RequestTeardown`] hook that
+/// commits it after a successful response (2xx, not a gRPC error) and rolls it back otherwise;
+/// a failed commit turns the response into a `503`. Extracting `Tx<DB>` again while another
+/// one is alive in the same request (two handler arguments, a middleware holding one) is a
+/// nesting error, rejected with a `500`, instead of a deadlock or a second transaction.
+/// Once the first is dropped, a later `Tx<DB>` continues the same transaction.
+pub struct Tx<DB: Database> {
+    guard: OwnedMutexGuard<Option<Transaction<'static, DB>>>,
+}
+
+impl<DB: Database> Tx<DB> {
+    /// The underlying transaction, e.g. to open a savepoint with `Transaction::begin`.
+    pub fn transaction(&mut self) -> &mut Transaction<'static, DB> {
+        self.guard
+            .as_mut()
+            .expect("request transaction is open until teardown")
+    }
+}
+
+impl<DB: Database> Deref for Tx<DB> {
+    type Target = DB::Connection;
+
+    fn deref(&self) -> &Self::Target {
+        self.guard
+            .as_ref()
+            .expect("request transaction is open until teardown")
+    }
+}
+
+impl<DB: Database> DerefMut for Tx<DB> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.transaction()
+    }
+}
+
+impl<DB: Database> fmt::Debug for Tx<DB> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tx")
+            .field("database", &DB::NAME)
+            .finish_non_exhaustive()
+    }
+}
+
+#[axum::async_trait]
+impl<DB, S> FromRequestParts<S> for Tx<DB>
+where
+    DB: Database,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(RequestTx(slot)) = parts.extensions.get::<RequestTx<DB>>() {
+            let guard = slot.clone().try_lock_owned().map_err(|_| {
+                tracing::error!(
+                    database = DB::NAME,
+                    "nested request transaction; drop the outer Tx before extracting another"
+                );
+                internal_di_error("a transaction is already in use for this request")
+            })?;
+            if guard.is_none() {
+                return Err(internal_di_error(
+                    "the request transaction has already ended",
+                ));
+            }
+            return Ok(Self { guard });
+        }
+
+        let teardown = RequestTeardown::from_extensions(&parts.extensions).ok_or_else(|| {
+            internal_di_error(
+                "Tx needs the request teardown layer (`OpenportioServer` or `di::with_request_teardown`)",
+            )
+        })?;
+        let pool = di::resolve_from_extensions::<Pool<DB>>(&parts.extensions).ok_or_else(|| {
+            internal_di_error(format!(
+                "dependency `{}` is not registered",
+                std::any::type_name::<Pool<DB>>()
+            ))
+        })?;
+        let transaction = pool.begin().await.map_err(|err| {
+            tracing::error!(database = DB::NAME, error = %err, "failed to begin request transaction");
+            openportio_server::api::map_domain_error_to_rest(OpenportioError::Unavailable(
+                "database is unavailable".to_string(),
+            ))
+        })?;
+
+        let slot: Slot<DB> = Arc::new(Mutex::new(Some(transaction)));
+        let guard = slot
+            .clone()
+            .try_lock_owned()
+            .expect("a new transaction slot is unlocked");
+        parts.extensions.insert(RequestTx(slot.clone()));
+        teardown.on_teardown(move |outcome| finish(slot, outcome));
+        Ok(Self { guard })
+    }
+}
+
+async fn finish<DB: Database>(
+    slot: Slot<DB>,
+    outcome: RequestOutcome,
+) -> Result<(), OpenportioError> {
+    let Some(transaction) = slot.lock().await.take() else {
+        return Ok(());
+    };
+    if outcome.is_success() {
+        return transaction.commit().await.map_err(|err| {
+            tracing::error!(database = DB::NAME, error = %err, "failed to commit request transaction");
+            OpenportioError::Unavailable("the transaction could not be committed".to_string())
+        });
+    }
+    if let Err(err) = transaction.rollback().await {
+        // Dropping the connection rolls back as well; nothing left to report to the client.
+        tracing::warn!(database = DB::NAME, error = %err, "failed to roll back request transaction");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+        routing::post,
+        Router,
+    };
+    use sqlx::{sqlite::SqlitePoolOptions, Sqlite, SqlitePool};
+    use tower::util::ServiceExt;
+
+    async fn pool() -> SqlitePool {
+        // One connection, so the in-memory database survives between transactions.
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE notes (title TEXT NOT NULL)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool
+    }
+
+    async fn insert(mut tx: Tx<Sqlite>, title: String) -> StatusCode {
+        sqlx::query("INSERT INTO notes (title) VALUES (?)")
+            .bind(&title)
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        if title == "rejected" {
+            StatusCode::UNPROCESSABLE_ENTITY
+        } else {
+            StatusCode::CREATED
+        }
+    }
+
+    async fn nested(_outer: Tx<Sqlite>, _inner: Tx<Sqlite>) -> StatusCode {
+        StatusCode::OK
+    }
+
+    #[tokio::test]
+    async fn transactions_commit_on_success_and_roll_back_otherwise() {
+        let pool = pool().await;
+        let app = di::with_request_teardown(di::with_dependency(
+            Router::new()
+                .route("/notes", post(insert))
+                .route("/nested", post(nested)),
+            pool.clone(),
+        ));
+        let call = |uri: &str, title: &str| {
+            app.clone().oneshot(
+                Request::post(uri)
+                    .body(Body::from(title.to_string()))
+                    .unwrap(),
+            )
+        };
+
+        assert_eq!(
+            call("/notes", "kept").await.unwrap().status(),
+            StatusCode::CREATED
+        );
+        assert_eq!(
+            call("/notes", "rejected").await.unwrap().status(),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+        assert_eq!(
+            call("/nested", "").await.unwrap().status(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+
+        let titles: Vec<String> = sqlx::query_scalar("SELECT title FROM notes")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(titles, ["kept"]);
+    }
+}
@@ -0,0 +1,560 @@
+use std::{
+    collections::VecDeque,
+    io::{Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use jsonwebtoken::{
+    encode,
+    jwk::{Jwk, JwkSet},
+    Algorithm, EncodingKey, Header,
+};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+mod metrics;
+mod snapshot;
+
+pub use metrics::RecordingMetrics;
+pub use snapshot::{assert_openapi_snapshot, render_openapi_snapshot};
+
+const RSA_KEYS: [&[u8]; 2] = [
+    include_bytes!("../fixtures/rsa_key_1.der"),
+    include_bytes!("../fixtures/rsa_key_2.der"),
+];
+const EC_P256_KEY: &[u8] = include_bytes!("../fixtures/ec_p256_key.der");
+const EC_P384_KEY: &[u8] = include_bytes!("../fixtures/ec_p384_key.der");
+const HMAC_SECRET: &[u8] = b"openportio-fake-idp-secret";
+
+const DEFAULT_SUBJECT: &str = "user-1";
+const DEFAULT_TOKEN_TTL: Duration = Duration::from_secs(3600);
+
+/// What the JWKS endpoint answers with instead of the published key set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Failure {
+    Status(u16),
+    Malformed,
+    Payload(String),
+}
+
+#[derive(Clone)]
+struct SigningKey {
+    kid: String,
+    alg: Algorithm,
+    encoding: EncodingKey,
+    published: bool,
+}
+
+struct IdpState {
+    keys: Vec<SigningKey>,
+    active_kid: String,
+    minted_keys: usize,
+    queued_failures: VecDeque<Failure>,
+    sticky_failure: Option<Failure>,
+}
+
+/// In-process identity provider serving `/jwks` and `/.well-known/openid-configuration` on a
+/// loopback port. The listener thread stops when the `FakeIdp` is dropped.
+pub struct FakeIdp {
+    addr: SocketAddr,
+    state: Arc<Mutex<IdpState>>,
+    jwks_requests: Arc<AtomicUsize>,
+    shutdown: Arc<AtomicBool>,
+    server: Option<JoinHandle<()>>,
+}
+
+impl FakeIdp {
+    pub fn spawn() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("fake idp listener should bind");
+        listener
+            .set_nonblocking(true)
+            .expect("fake idp listener should be nonblocking");
+        let addr = listener.local_addr().expect("fake idp addr should resolve");
+
+        let mut state = IdpState {
+            keys: Vec::new(),
+            active_kid: String::new(),
+            minted_keys: 0,
+            queued_failures: VecDeque::new(),
+            sticky_failure: None,
+        };
+        state.active_kid = state.add_key(Algorithm::RS256);
+
+        let state = Arc::new(Mutex::new(state));
+        let jwks_requests = Arc::new(AtomicUsize::new(0));
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let server = {
+            let state = Arc::clone(&state);
+            let jwks_requests = Arc::clone(&jwks_requests);
+            let shutdown = Arc::clone(&shutdown);
+            thread::spawn(move || serve(listener, addr, state, jwks_requests, shutdown))
+        };
+
+        Self {
+            addr,
+            state,
+            jwks_requests,
+            shutdown,
+            server: Some(server),
+        }
+    }
+
+    pub fn issuer(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn jwks_url(&self) -> String {
+        format!("{}/jwks", self.issuer())
+    }
+
+    pub fn discovery_url(&self) -> String {
+        format!("{}/.well-known/openid-configuration", self.issuer())
+    }
+
+    /// Number of requests the JWKS endpoint has received, including failed ones.
+    pub fn jwks_requests(&self) -> usize {
+        self.jwks_requests.load(Ordering::SeqCst)
+    }
+
+    pub fn active_kid(&self) -> String {
+        self.lock().active_kid.clone()
+    }
+
+    /// Publishes an additional key without making it the default signing key.
+    pub fn add_key(&self, alg: Algorithm) -> String {
+        self.lock().add_key(alg)
+    }
+
+    /// Publishes fresh key material under a new `kid` and signs new tokens with it. The previous
+    /// key stays in the JWKS until [`FakeIdp::retire_key`] is called.
+    pub fn rotate_key(&self) -> String {
+        let mut state = self.lock();
+        let alg = state.active_key().alg;
+        let kid = state.add_key(alg);
+        state.active_kid = kid.clone();
+        kid
+    }
+
+    /// Removes `kid` from the JWKS. Tokens can still be minted with it to simulate stale keys.
+    pub fn retire_key(&self, kid: &str) -> bool {
+        let mut state = self.lock();
+        match state.keys.iter_mut().find(|key| key.kid == kid) {
+            Some(key) => {
+                key.published = false;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn jwks_json(&self) -> String {
+        self.lock().jwks_json()
+    }
+
+    pub fn token(&self) -> TokenBuilder<'_> {
+        let mut claims = Map::new();
+        claims.insert("sub".to_string(), Value::from(DEFAULT_SUBJECT));
+        claims.insert("iss".to_string(), Value::from(self.issuer()));
+        claims.insert(
+            "exp".to_string(),
+            Value::from(unix_now().saturating_add(DEFAULT_TOKEN_TTL.as_secs())),
+        );
+        TokenBuilder {
+            idp: self,
+            kid: None,
+            alg: None,
+            claims,
+        }
+    }
+
+    /// Answers every JWKS request with `failure` until [`FakeIdp::recover`] is called.
+    pub fn fail_with(&self, failure: Failure) {
+        self.lock().sticky_failure = Some(failure);
+    }
+
+    /// Answers the next `times` JWKS requests with `failure`, then serves keys again.
+    pub fn fail_next(&self, failure: Failure, times: usize) {
+        let mut state = self.lock();
+        state
+            .queued_failures
+            .extend(std::iter::repeat_n(failure, times));
+    }
+
+    pub fn recover(&self) {
+        let mut state = self.lock();
+        state.queued_failures.clear();
+        state.sticky_failure = None;
+    }
+
+    fn lock(&self) -> MutexGuard<'_, IdpState> {
+        self.state.lock().expect("fake idp state lock")
+    }
+}
+
+impl Drop for FakeIdp {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        if let Some(server) = self.server.take() {
+            let _ = server.join();
+        }
+    }
+}
+
+impl IdpState {
+    fn add_key(&mut self, alg: Algorithm) -> String {
+        self.minted_keys += 1;
+        let kid = format!("fake-idp-key-{}", self.minted_keys);
+        self.keys.push(SigningKey {
+            kid: kid.clone(),
+            alg,
+            encoding: key_material(alg, self.minted_keys - 1),
+            published: true,
+        });
+        kid
+    }
+
+    fn active_key(&self) -> &SigningKey {
+        self.keys
+            .iter()
+            .find(|key| key.kid == self.active_kid)
+            .expect("active key should exist")
+    }
+
+    fn jwks_json(&self) -> String {
+        let keys = self
+            .keys
+            .iter()
+            .filter(|key| key.published)
+            .map(|key| {
+                let mut jwk = Jwk::from_encoding_key(&key.encoding, key.alg)
+                    .expect("jwk should be generated");
+                jwk.common.key_id = Some(key.kid.clone());
+                jwk
+            })
+            .collect();
+        serde_json::to_string(&JwkSet { keys }).expect("jwks should serialize")
+    }
+
+    fn next_failure(&mut self) -> Option<Failure> {
+        self.queued_failures
+            .pop_front()
+            .or_else(|| self.sticky_failure.clone())
+    }
+}
+
+/// Builds a signed token. Defaults: the active key, `sub = "user-1"`, `iss` = the fake issuer,
+/// and a one hour expiry.
+pub struct TokenBuilder<'a> {
+    idp: &'a FakeIdp,
+    kid: Option<String>,
+    alg: Option<Algorithm>,
+    claims: Map<String, Value>,
+}
+
+impl TokenBuilder<'_> {
+    /// Signs with the key published under `kid`, or with the active key while still stamping
+    /// `kid` into the header when no such key exists.
+    pub fn kid(mut self, kid: impl Into<String>) -> Self {
+        self.kid = Some(kid.into());
+        self
+    }
+
+    /// Overrides the header algorithm. Built-in key material is used when the selected key
+    /// belongs to a different algorithm family.
+    pub fn alg(mut self, alg: Algorithm) -> Self {
+        self.alg = Some(alg);
+        self
+    }
+
+    pub fn subject(self, subject: impl Into<String>) -> Self {
+        self.claim("sub", subject.into())
+    }
+
+    pub fn issuer(self, issuer: impl Into<String>) -> Self {
+        self.claim("iss", issuer.into())
+    }
+
+    pub fn audience(self, audience: impl Into<String>) -> Self {
+        self.claim("aud", audience.into())
+    }
+
+    pub fn scope(self, scope: impl Into<String>) -> Self {
+        self.claim("scope", scope.into())
+    }
+
+    pub fn expires_in(self, ttl: Duration) -> Self {
+        self.claim("exp", unix_now().saturating_add(ttl.as_secs()))
+    }
+
+    /// Sets `nbf` `delay` from now, as an issuer whose clock runs ahead would.
+    pub fn not_before_in(self, delay: Duration) -> Self {
+        self.claim("nbf", unix_now().saturating_add(delay.as_secs()))
+    }
+
+    pub fn expired(self) -> Self {
+        self.claim("exp", unix_now().saturating_sub(3600))
+    }
+
+    pub fn claim(mut self, name: &str, value: impl Serialize) -> Self {
+        let value = serde_json::to_value(value).expect("claim should serialize");
+        self.claims.insert(name.to_string(), value);
+        self
+    }
+
+    pub fn without_claim(mut self, name: &str) -> Self {
+        self.claims.remove(name);
+        self
+    }
+
+    pub fn mint(self) -> String {
+        let state = self.idp.lock();
+        let selected = match &self.kid {
+            Some(kid) => state.keys.iter().find(|key| &key.kid == kid),
+            None => Some(state.active_key()),
+        };
+        let alg = self
+            .alg
+            .or(selected.map(|key| key.alg))
+            .unwrap_or(state.active_key().alg);
+        let encoding = match selected {
+            Some(key) if same_family(key.alg, alg) => key.encoding.clone(),
+            _ if same_family(state.active_key().alg, alg) => state.active_key().encoding.clone(),
+            _ => key_material(alg, 0),
+        };
+
+        let mut header = Header::new(alg);
+        header.kid = Some(self.kid.unwrap_or_else(|| state.active_kid.clone()));
+        encode(&header, &self.claims, &encoding).expect("token should encode")
+    }
+}
+
+fn key_material(alg: Algorithm, index: usize) -> EncodingKey {
+    match alg {
+        Algorithm::RS256
+        | Algorithm::RS384
+        | Algorithm::RS512
+        | Algorithm::PS256
+        | Algorithm::PS384
+        | Algorithm::PS512 => EncodingKey::from_rsa_der(RSA_KEYS[index % RSA_KEYS.len()]),
+        Algorithm::ES256 => EncodingKey::from_ec_der(EC_P256_KEY),
+        Algorithm::ES384 => EncodingKey::from_ec_der(EC_P384_KEY),
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+            EncodingKey::from_secret(HMAC_SECRET)
+        }
+        Algorithm::EdDSA => panic!("FakeIdp does not support EdDSA keys"),
+    }
+}
+
+fn same_family(left: Algorithm, right: Algorithm) -> bool {
+    fn family(alg: Algorithm) -> &'static str {
+        match alg {
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => "hmac",
+            Algorithm::ES256 => "p256",
+            Algorithm::ES384 => "p384",
+            Algorithm::EdDSA => "ed",
+            _ => "rsa",
+        }
+    }
+    family(left) == family(right)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+fn serve(
+    listener: TcpListener,
+    addr: SocketAddr,
+    state: Arc<Mutex<IdpState>>,
+    jwks_requests: Arc<AtomicUsize>,
+    shutdown: Arc<AtomicBool>,
+) {
+    while !shutdown.load(Ordering::SeqCst) {
+        match listener.accept() {
+            Ok((stream, _)) => handle_connection(stream, addr, &state, &jwks_requests),
+            Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {
+                thread::sleep(Duration::from_millis(10));
+            }
+            Err(_) => break,
+        }
+    }
+}
+
+fn handle_connection(
+    mut stream: TcpStream,
+    addr: SocketAddr,
+    state: &Mutex<IdpState>,
+    jwks_requests: &AtomicUsize,
+) {
+    let _ = stream.set_nonblocking(false);
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(2)));
+    // Read the whole request head; closing with unread bytes would reset the connection.
+    let mut request = Vec::new();
+    let mut chunk = [0_u8; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        match stream.read(&mut chunk) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => request.extend_from_slice(&chunk[..read]),
+        }
+    }
+    let path = String::from_utf8_lossy(&request)
+        .split_whitespace()
+        .nth(1)
+        .map(|path| path.split('?').next().unwrap_or(path).to_string())
+        .unwrap_or_default();
+
+    let (status, body) = match path.as_str() {
+        "/jwks" => {
+            jwks_requests.fetch_add(1, Ordering::SeqCst);
+            let mut state = state.lock().expect("fake idp state lock");
+            match state.next_failure() {
+                Some(Failure::Status(status)) => {
+                    (status, json!({ "error": "injected failure" }).to_string())
+                }
+                Some(Failure::Malformed) => (200, "{ invalid-json".to_string()),
+                Some(Failure::Payload(payload)) => (200, payload),
+                None => (200, state.jwks_json()),
+            }
+        }
+        "/.well-known/openid-configuration" => {
+            let issuer = format!("http://{addr}");
+            (
+                200,
+                json!({
+                    "issuer": issuer,
+                    "jwks_uri": format!("{issuer}/jwks"),
+                    "id_token_signing_alg_values_supported": ["RS256", "ES256", "ES384"],
+                })
+                .to_string(),
+            )
+        }
+        _ => (404, json!({ "error": "not found" }).to_string()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {status} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        reason_phrase(status),
+        body.len(),
+    );
+    let _ = stream.write_all(response.as_bytes());
+    let _ = stream.flush();
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        404 => "Not Found",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{decode, decode_header, DecodingKey, Validation};
+
+    fn http_get(url: &str) -> (u16, String) {
+        let rest = url.strip_prefix("http://").expect("http url");
+        let (host, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+        let mut stream = TcpStream::connect(host).expect("fake idp should accept");
+        write!(stream, "GET {path} HTTP/1.1\r\nHost: {host}\r\n\r\n").expect("request write");
+        let mut response = String::new();
+        stream.read_to_string(&mut response).expect("response read");
+        let status = response[9..12].parse().expect("status code");
+        let body = response
+            .split_once("\r\n\r\n")
+            .map(|(_, body)| body.to_string())
+            .unwrap_or_default();
+        (status, body)
+    }
+
+    fn verify(idp: &FakeIdp, token: &str) -> Result<Map<String, Value>, String> {
+        let (_, body) = http_get(&idp.jwks_url());
+        let jwks: JwkSet = serde_json::from_str(&body).map_err(|err| err.to_string())?;
+        let header = decode_header(token).map_err(|err| err.to_string())?;
+        let jwk = jwks
+            .find(header.kid.as_deref().unwrap_or_default())
+            .ok_or_else(|| "unknown kid".to_string())?;
+        let key = DecodingKey::from_jwk(jwk).map_err(|err| err.to_string())?;
+        let mut validation = Validation::new(header.alg);
+        validation.validate_aud = false;
+        decode::<Map<String, Value>>(token, &key, &validation)
+            .map(|data| data.claims)
+            .map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn minted_tokens_verify_against_published_jwks() {
+        let idp = FakeIdp::spawn();
+        let token = idp.token().audience("openportio-api").mint();
+        let claims = verify(&idp, &token).expect("rs256 token should verify");
+        assert_eq!(claims["sub"], "user-1");
+        assert_eq!(claims["iss"], Value::from(idp.issuer()));
+
+        let ec_kid = idp.add_key(Algorithm::ES256);
+        let token = idp.token().kid(&ec_kid).subject("user-2").mint();
+        assert_eq!(decode_header(&token).unwrap().alg, Algorithm::ES256);
+        let claims = verify(&idp, &token).expect("es256 token should verify");
+        assert_eq!(claims["sub"], "user-2");
+
+        let (status, body) = http_get(&idp.discovery_url());
+        assert_eq!(status, 200);
+        assert!(body.contains(&idp.jwks_url()));
+    }
+
+    #[test]
+    fn rotation_publishes_new_key_until_old_key_is_retired() {
+        let idp = FakeIdp::spawn();
+        let old_kid = idp.active_kid();
+        let old_token = idp.token().mint();
+
+        let new_kid = idp.rotate_key();
+        assert_ne!(old_kid, new_kid);
+        let new_token = idp.token().mint();
+        assert_eq!(decode_header(&new_token).unwrap().kid, Some(new_kid));
+        assert!(verify(&idp, &old_token).is_ok());
+        assert!(verify(&idp, &new_token).is_ok());
+
+        assert!(idp.retire_key(&old_kid));
+        assert_eq!(verify(&idp, &old_token), Err("unknown kid".to_string()));
+        assert!(verify(&idp, &new_token).is_ok());
+
+        let forged = idp.token().kid(&old_kid).mint();
+        let jwks: JwkSet = serde_json::from_str(&idp.jwks_json()).unwrap();
+        let key = DecodingKey::from_jwk(&jwks.keys[0]).unwrap();
+        assert!(decode::<Value>(&forged, &key, &Validation::new(Algorithm::RS256)).is_err());
+    }
+
+    #[test]
+    fn injected_failures_replace_jwks_responses() {
+        let idp = FakeIdp::spawn();
+        idp.fail_next(Failure::Status(503), 2);
+        assert_eq!(http_get(&idp.jwks_url()).0, 503);
+        assert_eq!(http_get(&idp.jwks_url()).0, 503);
+        assert_eq!(http_get(&idp.jwks_url()).0, 200);
+
+        idp.fail_with(Failure::Malformed);
+        let (status, body) = http_get(&idp.jwks_url());
+        assert_eq!(status, 200);
+        assert!(serde_json::from_str::<Value>(&body).is_err());
+
+        idp.recover();
+        let (_, body) = http_get(&idp.jwks_url());
+        assert!(serde_json::from_str::<JwkSet>(&body).is_ok());
+        assert_eq!(idp.jwks_requests(), 5);
+    }
+}
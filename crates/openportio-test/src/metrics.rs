@@ -0,0 +1,133 @@
+use std::sync::{Mutex, PoisonError};
+
+use openportio_core::{MetricLabels, MetricsSink};
+
+/// `MetricsSink` that keeps every report as text, in order, so tests can assert on exactly what
+/// a component emitted. Counters are recorded as `name` or `name{key=value,...}`; gauges and
+/// histogram observations as `name{key=value,...}=value` (`name=value` without labels).
+#[derive(Debug, Default)]
+pub struct RecordingMetrics {
+    counters: Mutex<Vec<String>>,
+    gauges: Mutex<Vec<String>>,
+    histograms: Mutex<Vec<String>>,
+}
+
+impl RecordingMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every counter increment so far.
+    pub fn counters(&self) -> Vec<String> {
+        snapshot(&self.counters)
+    }
+
+    /// Every gauge update so far.
+    pub fn gauges(&self) -> Vec<String> {
+        snapshot(&self.gauges)
+    }
+
+    /// Every histogram observation so far.
+    pub fn histograms(&self) -> Vec<String> {
+        snapshot(&self.histograms)
+    }
+
+    /// How often `counter` (`name` or `name{key=value,...}`) was incremented.
+    pub fn count(&self, counter: &str) -> usize {
+        lock(&self.counters)
+            .iter()
+            .filter(|recorded| *recorded == counter)
+            .count()
+    }
+
+    /// Values set on gauge `name`, whatever the labels, in order.
+    pub fn gauge_values(&self, name: &str) -> Vec<f64> {
+        values(&self.gauges, name)
+    }
+
+    /// Values observed by histogram `name`, whatever the labels, in order.
+    pub fn histogram_values(&self, name: &str) -> Vec<f64> {
+        values(&self.histograms, name)
+    }
+}
+
+impl MetricsSink for RecordingMetrics {
+    fn incr_counter(&self, name: &str) {
+        self.incr_counter_with(name, &[]);
+    }
+
+    fn incr_counter_with(&self, name: &str, labels: MetricLabels<'_>) {
+        lock(&self.counters).push(series(name, labels));
+    }
+
+    fn observe_histogram(&self, name: &str, value: f64, labels: MetricLabels<'_>) {
+        lock(&self.histograms).push(format!("{}={value}", series(name, labels)));
+    }
+
+    fn set_gauge(&self, name: &str, value: f64, labels: MetricLabels<'_>) {
+        lock(&self.gauges).push(format!("{}={value}", series(name, labels)));
+    }
+}
+
+fn series(name: &str, labels: MetricLabels<'_>) -> String {
+    if labels.is_empty() {
+        return name.to_string();
+    }
+    let labels: Vec<_> = labels
+        .iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect();
+    format!("{name}{{{}}}", labels.join(","))
+}
+
+fn values(entries: &Mutex<Vec<String>>, name: &str) -> Vec<f64> {
+    lock(entries)
+        .iter()
+        .filter_map(|entry| {
+            let (series, value) = entry.rsplit_once('=')?;
+            let recorded = series.split_once('{').map_or(series, |(name, _)| name);
+            (recorded == name).then(|| value.parse().ok()).flatten()
+        })
+        .collect()
+}
+
+fn snapshot(entries: &Mutex<Vec<String>>) -> Vec<String> {
+    lock(entries).clone()
+}
+
+fn lock(entries: &Mutex<Vec<String>>) -> std::sync::MutexGuard<'_, Vec<String>> {
+    entries.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_series_as_text() {
+        let metrics = RecordingMetrics::new();
+        metrics.incr_counter("plain");
+        metrics.incr_counter_with("http.requests", &[("route", "/a"), ("status", "200")]);
+        metrics.incr_counter_with("http.requests", &[("route", "/a"), ("status", "200")]);
+        metrics.set_gauge("sse.clients", 1.0, &[("topic", "notes")]);
+        metrics.set_gauge("sse.clients", 0.0, &[("topic", "notes")]);
+        metrics.observe_histogram("latency", 0.25, &[]);
+
+        assert_eq!(
+            metrics.counters(),
+            [
+                "plain",
+                "http.requests{route=/a,status=200}",
+                "http.requests{route=/a,status=200}"
+            ]
+        );
+        assert_eq!(metrics.count("http.requests{route=/a,status=200}"), 2);
+        assert_eq!(
+            metrics.gauges(),
+            ["sse.clients{topic=notes}=1", "sse.clients{topic=notes}=0"]
+        );
+        assert_eq!(metrics.gauge_values("sse.clients"), [1.0, 0.0]);
+        assert_eq!(metrics.histogram_values("latency"), [0.25]);
+        assert!(metrics.gauge_values("latency").is_empty());
+    }
+}
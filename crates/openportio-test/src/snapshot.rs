@@ -0,0 +1,228 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{Request, StatusCode},
+    Router,
+};
+use serde_json::{json, Map, Value};
+use tower::util::ServiceExt;
+
+const REST_OPENAPI_PATH: &str = "/openapi.json";
+const GRPC_BRIDGE_PATH: &str = "/grpc/contracts/openapi.json";
+const UPDATE_ENV: &str = "OPENPORTIO_UPDATE_SNAPSHOTS";
+const REDACTED: &str = "[redacted]";
+const MAX_REPORTED_CHANGES: usize = 50;
+const MAX_RENDERED_VALUE: usize = 120;
+
+/// Renders `/openapi.json` and, when the app serves it, the gRPC bridge document from
+/// `/grpc/contracts/openapi.json`, with volatile fields (`info.version`) redacted.
+pub async fn render_openapi_snapshot(app: Router) -> Value {
+    let mut snapshot = Map::new();
+    let rest = fetch_json(&app, REST_OPENAPI_PATH)
+        .await
+        .unwrap_or_else(|| panic!("app does not serve {REST_OPENAPI_PATH}"));
+    snapshot.insert("rest".to_string(), normalize(rest));
+    if let Some(bridge) = fetch_json(&app, GRPC_BRIDGE_PATH).await {
+        snapshot.insert("grpc_bridge".to_string(), normalize(bridge));
+    }
+    Value::Object(snapshot)
+}
+
+/// Contract-regression gate: diffs the app's rendered OpenAPI documents against the committed
+/// snapshot at `path` (relative paths resolve against `CARGO_MANIFEST_DIR`) and panics with a
+/// per-field report on mismatch. Run with `OPENPORTIO_UPDATE_SNAPSHOTS=1` to (re)write it.
+pub async fn assert_openapi_snapshot(app: Router, path: impl AsRef<Path>) {
+    let path = snapshot_path(path.as_ref());
+    let actual = render_openapi_snapshot(app).await;
+
+    if update_requested() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).expect("snapshot directory should be writable");
+        }
+        let mut rendered = serde_json::to_string_pretty(&actual).expect("snapshot serializes");
+        rendered.push('\n');
+        fs::write(&path, rendered).expect("snapshot should be writable");
+        return;
+    }
+
+    let expected = match fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str::<Value>(&contents)
+            .unwrap_or_else(|err| panic!("snapshot {} is not JSON: {err}", path.display())),
+        Err(err) => panic!(
+            "snapshot {} could not be read ({err}); rerun with {}=1 to create it",
+            path.display(),
+            UPDATE_ENV
+        ),
+    };
+
+    let changes = diff(&expected, &actual);
+    if changes.is_empty() {
+        return;
+    }
+    let mut report = format!(
+        "OpenAPI contract differs from snapshot {} ({} change(s)):\n",
+        path.display(),
+        changes.len()
+    );
+    for change in changes.iter().take(MAX_REPORTED_CHANGES) {
+        report.push_str("  ");
+        report.push_str(change);
+        report.push('\n');
+    }
+    if changes.len() > MAX_REPORTED_CHANGES {
+        report.push_str(&format!(
+            "  ... and {} more\n",
+            changes.len() - MAX_REPORTED_CHANGES
+        ));
+    }
+    report.push_str(&format!(
+        "If the change is intended, rerun with {}=1 and commit the snapshot.",
+        UPDATE_ENV
+    ));
+    panic!("{report}");
+}
+
+async fn fetch_json(app: &Router, path: &str) -> Option<Value> {
+    let request = Request::builder()
+        .uri(path)
+        .body(Body::empty())
+        .expect("request should build");
+    let response = app
+        .clone()
+        .oneshot(request)
+        .await
+        .unwrap_or_else(|err| match err {});
+    if response.status() == StatusCode::NOT_FOUND {
+        return None;
+    }
+    assert!(
+        response.status().is_success(),
+        "{path} answered {}",
+        response.status()
+    );
+    let body = to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("response body should be readable");
+    Some(
+        serde_json::from_slice(&body)
+            .unwrap_or_else(|err| panic!("{path} did not return JSON: {err}")),
+    )
+}
+
+fn normalize(mut document: Value) -> Value {
+    if let Some(version) = document.pointer_mut("/info/version") {
+        *version = json!(REDACTED);
+    }
+    document
+}
+
+fn snapshot_path(path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+    match env::var_os("CARGO_MANIFEST_DIR") {
+        Some(dir) => PathBuf::from(dir).join(path),
+        None => path.to_path_buf(),
+    }
+}
+
+fn update_requested() -> bool {
+    env::var(UPDATE_ENV).is_ok_and(|value| matches!(value.trim(), "1" | "true" | "yes"))
+}
+
+/// One line per changed leaf, keyed by JSON pointer: `+` added, `-` removed, `~` changed.
+fn diff(expected: &Value, actual: &Value) -> Vec<String> {
+    let mut changes = Vec::new();
+    diff_at("", expected, actual, &mut changes);
+    changes
+}
+
+fn diff_at(pointer: &str, expected: &Value, actual: &Value, changes: &mut Vec<String>) {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            let mut keys: Vec<&String> = expected.keys().chain(actual.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let child = format!("{pointer}/{}", escape_pointer(key));
+                match (expected.get(key), actual.get(key)) {
+                    (Some(expected), Some(actual)) => diff_at(&child, expected, actual, changes),
+                    (Some(expected), None) => {
+                        changes.push(format!("- {child}: {}", render(expected)))
+                    }
+                    (None, Some(actual)) => changes.push(format!("+ {child}: {}", render(actual))),
+                    (None, None) => {}
+                }
+            }
+        }
+        (Value::Array(expected_items), Value::Array(actual_items))
+            if expected_items.len() == actual_items.len() =>
+        {
+            for (index, (expected, actual)) in expected_items.iter().zip(actual_items).enumerate() {
+                diff_at(&format!("{pointer}/{index}"), expected, actual, changes);
+            }
+        }
+        _ if expected != actual => changes.push(format!(
+            "~ {}: {} -> {}",
+            if pointer.is_empty() { "/" } else { pointer },
+            render(expected),
+            render(actual)
+        )),
+        _ => {}
+    }
+}
+
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+fn render(value: &Value) -> String {
+    let rendered = value.to_string();
+    if rendered.chars().count() <= MAX_RENDERED_VALUE {
+        return rendered;
+    }
+    let truncated: String = rendered.chars().take(MAX_RENDERED_VALUE).collect();
+    format!("{truncated}...")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Json};
+
+    fn app(summary: &'static str, version: &'static str) -> Router {
+        Router::new().route(
+            REST_OPENAPI_PATH,
+            get(move || async move {
+                Json(json!({
+                    "openapi": "3.1.0",
+                    "info": {"title": "orders", "version": version},
+                    "paths": {"/orders/{id}": {"get": {"summary": summary}}}
+                }))
+            }),
+        )
+    }
+
+    #[tokio::test]
+    async fn snapshot_ignores_versions_and_reports_changed_fields() {
+        let dir = env::temp_dir().join(format!("openportio-snapshot-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("openapi.snap.json");
+        let snapshot = render_openapi_snapshot(app("Fetch an order", "0.1.0")).await;
+        assert!(snapshot.get("grpc_bridge").is_none());
+        fs::write(&path, serde_json::to_string_pretty(&snapshot).unwrap()).unwrap();
+
+        assert_openapi_snapshot(app("Fetch an order", "0.2.0"), &path).await;
+
+        let changed = render_openapi_snapshot(app("Get an order", "0.1.0")).await;
+        assert_eq!(
+            diff(&snapshot, &changed),
+            vec![r#"~ /rest/paths/~1orders~1{id}/get/summary: "Fetch an order" -> "Get an order""#]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
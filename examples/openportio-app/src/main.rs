@@ -25,11 +25,17 @@ async fn get_item(
     Ok(Json(path.id))
 }
 
+#[openportio::route(get, "/beta/items", feature = "beta_items")]
+async fn list_beta_items() -> Json<Vec<String>> {
+    Json(vec!["beta".to_string()])
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let router = Router::new()
         .route("/payload", axum::routing::post(create_payload))
-        .route("/items/:id", axum::routing::get(get_item));
+        .route("/items/:id", axum::routing::get(get_item))
+        .route("/beta/items", axum::routing::get(list_beta_items));
 
     OpenportioServer::new()
         .with_rest_router(router)
@@ -49,6 +55,7 @@ mod tests {
         Router::new()
             .route("/payload", axum::routing::post(create_payload))
             .route("/items/:id", axum::routing::get(get_item))
+            .route("/beta/items", axum::routing::get(list_beta_items))
     }
 
     #[tokio::test]
@@ -75,4 +82,29 @@ mod tests {
             .iter()
             .any(|issue| issue.loc.first() == Some(&"path".to_string())));
     }
+
+    #[tokio::test]
+    async fn feature_route_is_hidden_until_flag_is_enabled() {
+        let request = || {
+            Request::builder()
+                .uri("/beta/items")
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+        let hidden = app()
+            .oneshot(request())
+            .await
+            .expect("request should complete");
+        assert_eq!(hidden.status(), axum::http::StatusCode::NOT_FOUND);
+
+        let flags = openportio::flags::FeatureFlags::new(
+            openportio::flags::StaticFlagProvider::new()
+                .with("beta_items", openportio::flags::FlagRule::On),
+        );
+        let enabled = with_dependency(app(), FlagClient::new(flags))
+            .oneshot(request())
+            .await
+            .expect("request should complete");
+        assert_eq!(enabled.status(), axum::http::StatusCode::OK);
+    }
 }
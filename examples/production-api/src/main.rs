@@ -14,6 +14,7 @@ use openportio_server::{
     auth::{self, AuthRuntimeConfig},
     OpenportioServer,
 };
+use openportio_sqlx::PgTx;
 use serde::Serialize;
 use sqlx::{postgres::PgPoolOptions, FromRow, PgPool};
 use tracing_subscriber::EnvFilter;
@@ -233,7 +234,7 @@ async fn readyz(
 #[openportio_server::route(post, "/v1/notes", auto_validate)]
 async fn create_note(
     Extension(principal): Extension<AuthPrincipal>,
-    State(state): State<Arc<ProductionApiState>>,
+    mut tx: PgTx,
     Json(body): Json<CreateNoteBody>,
 ) -> Result<(StatusCode, Json<NoteResponse>), ApiError> {
     let note = sqlx::query_as::<_, NoteRow>(
@@ -250,7 +251,7 @@ async fn create_note(
     .bind(principal.subject)
     .bind(body.title)
     .bind(body.body)
-    .fetch_one(&state.pool)
+    .fetch_one(&mut *tx)
     .await
     .map_err(database_error)?;
 
@@ -260,7 +261,7 @@ async fn create_note(
 #[openportio_server::route(get, "/v1/notes", auto_validate)]
 async fn list_notes(
     Extension(principal): Extension<AuthPrincipal>,
-    State(state): State<Arc<ProductionApiState>>,
+    mut tx: PgTx,
     axum::extract::Query(query): axum::extract::Query<ListNotesQuery>,
 ) -> Result<Json<Vec<NoteResponse>>, ApiError> {
     let limit = query.limit.unwrap_or(20);
@@ -280,7 +281,7 @@ async fn list_notes(
     )
     .bind(principal.subject)
     .bind(limit)
-    .fetch_all(&state.pool)
+    .fetch_all(&mut *tx)
     .await
     .map_err(database_error)?;
 
@@ -295,7 +296,7 @@ async fn list_notes(
 #[openportio_server::route(get, "/protected/notes/:id", auto_validate)]
 async fn get_protected_note(
     Extension(principal): Extension<AuthPrincipal>,
-    State(state): State<Arc<ProductionApiState>>,
+    mut tx: PgTx,
     axum::extract::Path(path): axum::extract::Path<NotePath>,
 ) -> Result<Json<ProtectedNoteResponse>, ApiError> {
     let subject = principal.subject;
@@ -312,7 +313,7 @@ async fn get_protected_note(
     )
     .bind(path.id)
     .bind(&subject)
-    .fetch_optional(&state.pool)
+    .fetch_optional(&mut *tx)
     .await
     .map_err(database_error)?;
 
@@ -428,13 +429,18 @@ async fn main() -> Result<(), Box<dyn Error>> {
         spawn_migration_worker(pool.clone(), config.migration_retry_seconds);
     }
 
-    let rest_state = Arc::new(ProductionApiState::new(config.service_name.clone(), pool));
-    let rest_router = build_rest_router(rest_state, AuthRuntimeConfig::from_env());
+    let rest_state = Arc::new(ProductionApiState::new(
+        config.service_name.clone(),
+        pool.clone(),
+    ));
+    let rest_router = build_rest_router(rest_state, AuthRuntimeConfig::from_env()?);
 
     let grpc_state = Arc::new(AppState::local(config.service_name.clone()));
     OpenportioServer::new()
         .with_addr(config.addr)
         .with_state(grpc_state)
+        // Resolved by `PgTx`: notes handlers get a per-request transaction.
+        .with_dependency(pool)
         .with_rest_router(rest_router)
         .on_startup(|addr| {
             tracing::info!(addr = %addr, "production-api started");
@@ -0,0 +1,327 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use futures_util::{stream, SinkExt, Stream, StreamExt};
+use openportio_server::OpenportioServer;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tonic::{Request, Response, Status, Streaming};
+
+pub mod proto {
+    tonic::include_proto!("openportio.examples.chat.v1");
+}
+
+use proto::{
+    chat_room_server::{ChatRoom, ChatRoomServer},
+    ChatEvent, ChatMessage,
+};
+
+const ROOM_METADATA_KEY: &str = "x-chat-room";
+const DEFAULT_ROOM: &str = "lobby";
+const ROOM_CAPACITY: usize = 64;
+
+/// In-process event bus shared by the gRPC service and the WebSocket bridge: one broadcast
+/// channel per room, created on first use.
+#[derive(Clone, Default)]
+struct ChatHub {
+    rooms: Arc<Mutex<HashMap<String, broadcast::Sender<ChatEvent>>>>,
+    sequence: Arc<AtomicU64>,
+}
+
+impl ChatHub {
+    fn subscribe(&self, room: &str) -> broadcast::Receiver<ChatEvent> {
+        self.sender(room).subscribe()
+    }
+
+    fn publish(&self, room: &str, user: String, text: String, transport: &str) {
+        let event = ChatEvent {
+            sequence: self.sequence.fetch_add(1, Ordering::Relaxed) + 1,
+            room: room.to_string(),
+            user,
+            text,
+            transport: transport.to_string(),
+        };
+        // No subscribers is fine: nobody is listening to the room yet.
+        let _ = self.sender(room).send(event);
+    }
+
+    fn sender(&self, room: &str) -> broadcast::Sender<ChatEvent> {
+        let mut rooms = self.rooms.lock().expect("chat rooms lock");
+        rooms
+            .entry(room.to_string())
+            .or_insert_with(|| broadcast::channel(ROOM_CAPACITY).0)
+            .clone()
+    }
+}
+
+/// Room events as a stream, skipping events a slow subscriber missed.
+fn room_events(receiver: broadcast::Receiver<ChatEvent>) -> impl Stream<Item = ChatEvent> {
+    stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => return Some((event, receiver)),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+struct ChatService {
+    hub: ChatHub,
+}
+
+#[tonic::async_trait]
+impl ChatRoom for ChatService {
+    type ChatStream = Pin<Box<dyn Stream<Item = Result<ChatEvent, Status>> + Send + 'static>>;
+
+    async fn chat(
+        &self,
+        request: Request<Streaming<ChatMessage>>,
+    ) -> Result<Response<Self::ChatStream>, Status> {
+        let room = request
+            .metadata()
+            .get(ROOM_METADATA_KEY)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or(DEFAULT_ROOM)
+            .to_string();
+        // Subscribe before reading the inbound stream so the caller sees its own messages.
+        let events = room_events(self.hub.subscribe(&room));
+
+        let hub = self.hub.clone();
+        let mut inbound = request.into_inner();
+        tokio::spawn(async move {
+            while let Some(Ok(message)) = inbound.next().await {
+                hub.publish(&room, message.user, message.text, "grpc");
+            }
+        });
+
+        Ok(Response::new(Box::pin(events.map(Ok))))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct JoinQuery {
+    user: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct WsChatEvent {
+    sequence: u64,
+    room: String,
+    user: String,
+    text: String,
+    transport: String,
+}
+
+impl From<ChatEvent> for WsChatEvent {
+    fn from(event: ChatEvent) -> Self {
+        Self {
+            sequence: event.sequence,
+            room: event.room,
+            user: event.user,
+            text: event.text,
+            transport: event.transport,
+        }
+    }
+}
+
+async fn join_room(
+    ws: WebSocketUpgrade,
+    State(hub): State<ChatHub>,
+    Path(room): Path<String>,
+    Query(query): Query<JoinQuery>,
+) -> impl IntoResponse {
+    let user = query.user.unwrap_or_else(|| "anonymous".to_string());
+    // Subscribe before the upgrade completes so no message sent right after joining is missed.
+    let events = hub.subscribe(&room);
+    ws.on_upgrade(move |socket| bridge_session(socket, hub, events, room, user))
+}
+
+async fn bridge_session(
+    socket: WebSocket,
+    hub: ChatHub,
+    events: broadcast::Receiver<ChatEvent>,
+    room: String,
+    user: String,
+) {
+    let (mut sender, mut receiver) = socket.split();
+    let mut events = Box::pin(room_events(events));
+
+    let forward = tokio::spawn(async move {
+        while let Some(event) = events.next().await {
+            let Ok(payload) = serde_json::to_string(&WsChatEvent::from(event)) else {
+                continue;
+            };
+            if sender.send(Message::Text(payload)).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    while let Some(Ok(message)) = receiver.next().await {
+        match message {
+            Message::Text(text) => hub.publish(&room, user.clone(), text, "websocket"),
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
+    forward.abort();
+}
+
+fn ws_router(hub: ChatHub) -> Router {
+    Router::new()
+        .route("/ws/rooms/:room", get(join_room))
+        .with_state(hub)
+}
+
+fn server(hub: ChatHub) -> OpenportioServer {
+    OpenportioServer::new()
+        .without_grpc()
+        .with_grpc_service(ChatRoomServer::new(ChatService { hub: hub.clone() }))
+        .merge_raw_router(ws_router(hub))
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    server(ChatHub::default())
+        .with_addr(SocketAddr::from(([127, 0, 0, 1], 4100)))
+        .on_startup(|addr| {
+            println!("streaming-chat started on {addr}");
+        })
+        .run()
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proto::chat_room_client::ChatRoomClient;
+    use tokio::{net::TcpListener, sync::oneshot, time::Duration};
+    use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+    const STEP_TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[tokio::test]
+    async fn rooms_are_isolated_and_sequenced() {
+        let hub = ChatHub::default();
+        let mut lobby = hub.subscribe("lobby");
+        let mut other = hub.subscribe("other");
+
+        hub.publish("lobby", "ana".to_string(), "hi".to_string(), "grpc");
+        hub.publish("other", "bo".to_string(), "yo".to_string(), "websocket");
+
+        let first = lobby.recv().await.expect("lobby event");
+        assert_eq!((first.sequence, first.text.as_str()), (1, "hi"));
+        let second = other.recv().await.expect("other event");
+        assert_eq!((second.sequence, second.user.as_str()), (2, "bo"));
+        assert!(lobby.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn grpc_and_websocket_clients_share_rooms() {
+        let app = server(ChatHub::default())
+            .build_app()
+            .expect("app should build");
+        let listener = TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .expect("bind test listener");
+        let addr = listener.local_addr().expect("listener addr");
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let server = tokio::spawn(async move {
+            axum::serve(listener, app)
+                .with_graceful_shutdown(async {
+                    let _ = shutdown_rx.await;
+                })
+                .await
+                .expect("server should run");
+        });
+
+        let (mut ws, _) =
+            tokio_tungstenite::connect_async(format!("ws://{addr}/ws/rooms/rust?user=web"))
+                .await
+                .expect("websocket connect");
+
+        let mut client = ChatRoomClient::connect(format!("http://{addr}"))
+            .await
+            .expect("grpc client connect");
+        let (outbound_tx, outbound_rx) = tokio::sync::mpsc::channel::<ChatMessage>(4);
+        let mut request = Request::new(tokio_stream::wrappers::ReceiverStream::new(outbound_rx));
+        request
+            .metadata_mut()
+            .insert(ROOM_METADATA_KEY, "rust".parse().unwrap());
+        let mut inbound = client
+            .chat(request)
+            .await
+            .expect("chat stream should open")
+            .into_inner();
+
+        outbound_tx
+            .send(ChatMessage {
+                user: "cli".to_string(),
+                text: "hello from grpc".to_string(),
+            })
+            .await
+            .expect("send grpc message");
+
+        let echoed = tokio::time::timeout(STEP_TIMEOUT, inbound.message())
+            .await
+            .expect("grpc echo in time")
+            .expect("grpc stream ok")
+            .expect("grpc event");
+        assert_eq!(echoed.text, "hello from grpc");
+
+        let bridged = loop {
+            let frame = tokio::time::timeout(STEP_TIMEOUT, ws.next())
+                .await
+                .expect("ws event in time")
+                .expect("ws stream open")
+                .expect("ws frame");
+            if let WsMessage::Text(text) = frame {
+                break serde_json::from_str::<WsChatEvent>(&text).expect("chat event json");
+            }
+        };
+        assert_eq!(
+            (bridged.user.as_str(), bridged.transport.as_str()),
+            ("cli", "grpc")
+        );
+
+        ws.send(WsMessage::Text("hello from the browser".to_string()))
+            .await
+            .expect("send ws message");
+        let from_ws = tokio::time::timeout(STEP_TIMEOUT, inbound.message())
+            .await
+            .expect("grpc event in time")
+            .expect("grpc stream ok")
+            .expect("grpc event");
+        assert_eq!(
+            (from_ws.user.as_str(), from_ws.transport.as_str()),
+            ("web", "websocket")
+        );
+        assert_eq!(from_ws.room, "rust");
+
+        drop(outbound_tx);
+        drop(inbound);
+        drop(client);
+        let _ = ws.close(None).await;
+        let _ = shutdown_tx.send(());
+        let _ = tokio::time::timeout(STEP_TIMEOUT, server).await;
+    }
+}
@@ -0,0 +1,37 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+ROOT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")/.." && pwd)"
+cd "$ROOT_DIR"
+
+MODULE="${OPENPORTIO_BUF_MODULE:-buf.build/openportio/openportio}"
+OUT_DIR="${OPENPORTIO_BUF_OUT:-target/buf/openportio}"
+LABEL="${OPENPORTIO_BUF_LABEL:-$(git rev-parse --abbrev-ref HEAD)}"
+# e.g. `buf.build/openportio/openportio:main`; empty skips the breaking-change check.
+AGAINST="${OPENPORTIO_BUF_AGAINST:-}"
+DRY_RUN="${OPENPORTIO_BUF_DRY_RUN:-false}"
+
+cargo run -q -p openportio-rpc --bin buf-export -- \
+  --proto-root crates/openportio-rpc/proto \
+  --out "$OUT_DIR" \
+  --module "$MODULE"
+
+if ! command -v buf >/dev/null 2>&1; then
+  echo "buf CLI not found; install it from https://buf.build/docs/installation" >&2
+  exit 1
+fi
+
+cd "$OUT_DIR"
+buf build
+buf lint
+if [[ -n "$AGAINST" ]]; then
+  buf breaking --against "$AGAINST"
+fi
+
+if [[ "$DRY_RUN" == "true" ]]; then
+  echo "Dry run: $MODULE exported to $OUT_DIR and checked; not pushed."
+  exit 0
+fi
+
+buf push --label "$LABEL"
+echo "Pushed $MODULE with label $LABEL."